@@ -5,37 +5,43 @@
     task::{ready, Context, Poll},
 };
 
-use futures_util::{io::Cursor, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutureExt};
+use futures_util::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, FutureExt};
 use tracing::{debug, instrument};
 
-use crate::{ReadBuffer, WriteBuffer};
+use crate::BufDuplex;
 
 pub struct BufStream<S> {
     stream: S,
-    read_buffer: ReadBuffer,
-    write_buffer: WriteBuffer,
+    duplex: BufDuplex,
 }
 
 impl<S> BufStream<S> {
     pub fn new(stream: S) -> Self {
-        Self {
-            stream,
-            read_buffer: Default::default(),
-            write_buffer: Default::default(),
-        }
+        Self::from_parts(stream, BufDuplex::new())
+    }
+
+    /// Build a buffered stream from a stream and a buffered duplex
+    /// core, for example the ones returned by
+    /// [`BufStream::into_parts`].
+    pub fn from_parts(stream: S, duplex: BufDuplex) -> Self {
+        Self { stream, duplex }
     }
 
     pub fn set_read_capacity(&mut self, capacity: usize) {
-        self.read_buffer.set_capacity(capacity)
+        self.duplex.set_read_capacity(capacity)
     }
 
     pub fn with_read_capacity(mut self, capacity: usize) -> Self {
-        self.read_buffer.set_capacity(capacity);
+        self.duplex.set_read_capacity(capacity);
         self
     }
 
     pub fn wants_read(&self) -> bool {
-        self.read_buffer.wants_read()
+        self.duplex.wants_read()
+    }
+
+    pub fn wants_write(&self) -> bool {
+        self.duplex.wants_write()
     }
 
     pub fn get_ref(&self) -> &S {
@@ -49,25 +55,36 @@ pub fn get_mut(&mut self) -> &mut S {
     pub fn into_inner(self) -> S {
         self.stream
     }
+
+    /// Split the buffered stream into the inner stream and the
+    /// buffered duplex core, so that buffered bytes are not lost
+    /// when the stream is taken over (TLS upgrade etc).
+    pub fn into_parts(self) -> (S, BufDuplex) {
+        (self.stream, self.duplex)
+    }
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> BufStream<S> {
     #[instrument(skip_all)]
     pub async fn progress_read(&mut self) -> Result<usize> {
-        let slice = &mut self.read_buffer.to_io_slice_mut();
-        let count = self.stream.read_vectored(slice).await?;
-        self.read_buffer.progress(count)
+        if !self.duplex.needs_read() {
+            return Ok(0);
+        }
+
+        let slices = &mut self.duplex.read_slices();
+        let count = self.stream.read_vectored(slices).await?;
+        Ok(self.duplex.progress_read(count)?.len())
     }
 
     #[instrument(skip_all)]
     pub async fn progress_write(&mut self) -> Result<usize> {
-        if !self.write_buffer.wants_write() {
+        if !self.duplex.wants_write() {
             return Ok(0);
         }
 
-        let slices = &mut self.write_buffer.to_io_slices();
+        let slices = &self.duplex.write_slices();
         let count = self.stream.write_vectored(slices).await?;
-        self.write_buffer.progress(count)
+        self.duplex.progress_write(count)
     }
 
     pub async fn progress(&mut self) -> Result<&[u8]> {
@@ -99,7 +116,8 @@ pub async fn progress(&mut self) -> Result<&[u8]> {
 
         self.stream.flush().await?;
 
-        Ok(&self.read_buffer.as_slice()[..count])
+        let buffered = self.duplex.buffered();
+        Ok(&buffered[buffered.len() - count..])
     }
 }
 
@@ -107,24 +125,16 @@ impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for BufStream<S> {
     #[instrument(skip_all)]
     fn poll_read(
         self: Pin<&mut Self>,
-        cx: &mut Context<'_>,
+        _cx: &mut Context<'_>,
         buf: &mut [u8],
     ) -> Poll<Result<usize>> {
-        if !self.read_buffer.wants_read() {
-            return Poll::Ready(Ok(0));
-        }
-
-        let this = self.get_mut();
-        let mut buf = Cursor::new(buf);
-        let write = pin!(buf.write(&this.read_buffer.as_slice()));
-        let count = ready!(write.poll(cx))?;
-        Poll::Ready(this.read_buffer.sync(count))
+        Poll::Ready(Ok(self.get_mut().duplex.read(buf)))
     }
 }
 
 impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for BufStream<S> {
     fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
-        self.get_mut().write_buffer.extend(buf);
+        self.get_mut().duplex.write(buf);
         Poll::Ready(Ok(buf.len()))
     }
 
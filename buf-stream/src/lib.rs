@@ -1,3 +1,20 @@
+//! # Buf stream
+//!
+//! Buffered duplex streams, made of a sans I/O core [`BufDuplex`]
+//! and thin I/O adapters:
+//!
+//! - [`std::BufStream`] for blocking streams (cargo feature
+//!   `blocking`)
+//! - [`futures::BufStream`] for async streams (cargo feature
+//!   `async`)
+//!
+//! The core never performs I/O by itself: it only exposes the slices
+//! to fill from or to drain into the underlying stream, and needs to
+//! be told how many bytes were actually transferred. This makes it
+//! reusable by protocol crates that need to take over the stream at
+//! some point, for example to upgrade it to TLS, without losing the
+//! bytes already buffered (see [`std::BufStream::into_parts`]).
+
 #[cfg(feature = "async")]
 pub mod futures;
 #[cfg(feature = "blocking")]
@@ -10,6 +27,111 @@
 
 use tracing::{debug, trace};
 
+/// The default capacity of the read buffer.
+pub const DEFAULT_READ_CAPACITY: usize = 1024;
+
+/// The sans I/O buffered duplex core.
+///
+/// Bytes read from the stream are accumulated in a read buffer until
+/// they are consumed with [`BufDuplex::read`], and bytes written with
+/// [`BufDuplex::write`] are queued in a write buffer until they are
+/// sent to the stream.
+#[derive(Clone, Debug, Default)]
+pub struct BufDuplex {
+    read_buffer: ReadBuffer,
+    write_buffer: WriteBuffer,
+}
+
+impl BufDuplex {
+    /// Create a new buffered duplex core using defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change the read buffer capacity.
+    ///
+    /// Bytes already buffered are kept, as long as they fit in the
+    /// new capacity.
+    pub fn set_read_capacity(&mut self, capacity: usize) {
+        self.read_buffer.set_capacity(capacity)
+    }
+
+    /// Change the read buffer capacity, using the builder pattern.
+    pub fn with_read_capacity(mut self, capacity: usize) -> Self {
+        self.set_read_capacity(capacity);
+        self
+    }
+
+    /// Return `true` if buffered bytes are waiting to be consumed.
+    pub fn wants_read(&self) -> bool {
+        self.read_buffer.wants_read()
+    }
+
+    /// Return `true` if queued bytes are waiting to be sent to the
+    /// stream.
+    pub fn wants_write(&self) -> bool {
+        self.write_buffer.wants_write()
+    }
+
+    /// Return `true` if the read buffer has room for more bytes from
+    /// the stream.
+    pub fn needs_read(&self) -> bool {
+        self.read_buffer.has_room()
+    }
+
+    /// Return the slice the stream should read into.
+    pub fn read_slices(&mut self) -> [IoSliceMut<'_>; 1] {
+        self.read_buffer.to_io_slice_mut()
+    }
+
+    /// Notify the core that the stream read `count` bytes into
+    /// [`BufDuplex::read_slices`], and return the newly read bytes.
+    ///
+    /// Reading 0 byte means that the stream reached its end, which
+    /// is reported as an [`ErrorKind::UnexpectedEof`] error.
+    pub fn progress_read(&mut self, count: usize) -> Result<&[u8]> {
+        self.read_buffer.progress(count)
+    }
+
+    /// Return the slices the stream should write from.
+    pub fn write_slices(&self) -> [IoSlice<'_>; 2] {
+        self.write_buffer.to_io_slices()
+    }
+
+    /// Notify the core that the stream wrote `count` bytes from
+    /// [`BufDuplex::write_slices`].
+    pub fn progress_write(&mut self, count: usize) -> Result<usize> {
+        self.write_buffer.progress(count)
+    }
+
+    /// Return the buffered bytes, without consuming them.
+    pub fn buffered(&self) -> &[u8] {
+        self.read_buffer.as_slice()
+    }
+
+    /// Move buffered bytes into the given buffer, and return how
+    /// many bytes were moved.
+    pub fn read(&mut self, buf: &mut [u8]) -> usize {
+        let count = buf.len().min(self.read_buffer.cursor);
+        buf[..count].copy_from_slice(&self.read_buffer.as_slice()[..count]);
+        self.read_buffer.consume(count);
+        count
+    }
+
+    /// Queue the given bytes for the stream.
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.write_buffer.extend(bytes)
+    }
+
+    /// Take the bytes buffered but not consumed yet, leaving the read
+    /// buffer empty.
+    pub fn take_buffered(&mut self) -> Vec<u8> {
+        let bytes = self.read_buffer.as_slice().to_vec();
+        self.read_buffer.consume(bytes.len());
+        bytes
+    }
+}
+
 #[derive(Clone, Debug)]
 pub(crate) struct ReadBuffer {
     buffer: Box<[u8]>,
@@ -19,42 +141,47 @@ pub(crate) struct ReadBuffer {
 impl ReadBuffer {
     fn new() -> Self {
         Self {
-            buffer: vec![0; 1024].into(),
+            buffer: vec![0; DEFAULT_READ_CAPACITY].into(),
             cursor: 0,
         }
     }
 
     fn set_capacity(&mut self, capacity: usize) {
-        self.buffer = vec![0; capacity].into();
+        let mut buffer = vec![0; capacity];
+        self.cursor = self.cursor.min(capacity);
+        buffer[..self.cursor].copy_from_slice(&self.buffer[..self.cursor]);
+        self.buffer = buffer.into();
     }
 
     fn wants_read(&self) -> bool {
-        self.cursor > 0 && !self.buffer.is_empty()
+        self.cursor > 0
+    }
+
+    fn has_room(&self) -> bool {
+        self.cursor < self.buffer.len()
     }
 
     fn to_io_slice_mut(&mut self) -> [IoSliceMut; 1] {
-        [IoSliceMut::new(self.buffer.as_mut())]
+        [IoSliceMut::new(&mut self.buffer[self.cursor..])]
     }
 
     fn as_slice(&self) -> &[u8] {
-        &self.buffer.as_ref()[..self.cursor]
+        &self.buffer[..self.cursor]
     }
 
-    fn sync(&mut self, count: usize) -> Result<usize> {
-        validate_byte_count(count)?;
+    fn consume(&mut self, count: usize) {
         debug!("read {count}/{} bytes", self.cursor);
-        let remaining = self.buffer.len() - count;
-        self.buffer.copy_within(count.., 0);
-        self.buffer[remaining..].fill(0);
+        self.buffer.copy_within(count..self.cursor, 0);
         self.cursor -= count;
-        Ok(count)
     }
 
-    fn progress(&mut self, count: usize) -> Result<usize> {
-        self.cursor = validate_byte_count(count)?;
-        let bytes = &self.buffer[..self.cursor];
-        trace!(?bytes, len = self.cursor, "read bytes");
-        Ok(self.cursor)
+    fn progress(&mut self, count: usize) -> Result<&[u8]> {
+        validate_byte_count(count)?;
+        let start = self.cursor;
+        self.cursor += count;
+        let bytes = &self.buffer[start..self.cursor];
+        trace!(?bytes, len = count, "read bytes");
+        Ok(bytes)
     }
 }
 
@@ -100,3 +227,42 @@ fn validate_byte_count(count: usize) -> Result<usize> {
 
     Ok(count)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use super::BufDuplex;
+
+    #[test]
+    fn read_and_write() {
+        let mut duplex = BufDuplex::new().with_read_capacity(8);
+
+        assert!(!duplex.wants_read());
+        assert!(duplex.needs_read());
+
+        let [mut slice] = duplex.read_slices();
+        slice[..5].copy_from_slice(b"hello");
+        assert_eq!(duplex.progress_read(5).unwrap(), b"hello");
+
+        let [mut slice] = duplex.read_slices();
+        slice[..3].copy_from_slice(b" wo");
+        assert_eq!(duplex.progress_read(3).unwrap(), b" wo");
+        assert!(!duplex.needs_read());
+
+        let mut buf = [0; 6];
+        assert_eq!(duplex.read(&mut buf), 6);
+        assert_eq!(&buf, b"hello ");
+        assert_eq!(duplex.take_buffered(), b"wo");
+        assert!(!duplex.wants_read());
+
+        let err = duplex.progress_read(0).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+
+        duplex.write(b"ping");
+        assert!(duplex.wants_write());
+        assert_eq!(&*duplex.write_slices()[0], b"ping");
+        duplex.progress_write(4).unwrap();
+        assert!(!duplex.wants_write());
+    }
+}
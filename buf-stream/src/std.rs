@@ -1,35 +1,41 @@
-use std::io::{Cursor, Read, Result, Write};
+use std::io::{Read, Result, Write};
 
 use tracing::debug;
 
-use crate::{ReadBuffer, WriteBuffer};
+use crate::BufDuplex;
 
 pub struct BufStream<S> {
     stream: S,
-    read_buffer: ReadBuffer,
-    write_buffer: WriteBuffer,
+    duplex: BufDuplex,
 }
 
 impl<S> BufStream<S> {
     pub fn new(stream: S) -> Self {
-        Self {
-            stream,
-            read_buffer: Default::default(),
-            write_buffer: Default::default(),
-        }
+        Self::from_parts(stream, BufDuplex::new())
+    }
+
+    /// Build a buffered stream from a stream and a buffered duplex
+    /// core, for example the ones returned by
+    /// [`BufStream::into_parts`].
+    pub fn from_parts(stream: S, duplex: BufDuplex) -> Self {
+        Self { stream, duplex }
     }
 
     pub fn set_read_capacity(&mut self, capacity: usize) {
-        self.read_buffer.set_capacity(capacity)
+        self.duplex.set_read_capacity(capacity)
     }
 
     pub fn with_read_capacity(mut self, capacity: usize) -> Self {
-        self.read_buffer.set_capacity(capacity);
+        self.duplex.set_read_capacity(capacity);
         self
     }
 
     pub fn wants_read(&self) -> bool {
-        self.read_buffer.wants_read()
+        self.duplex.wants_read()
+    }
+
+    pub fn wants_write(&self) -> bool {
+        self.duplex.wants_write()
     }
 
     pub fn get_ref(&self) -> &S {
@@ -43,23 +49,34 @@ pub fn get_mut(&mut self) -> &mut S {
     pub fn into_inner(self) -> S {
         self.stream
     }
+
+    /// Split the buffered stream into the inner stream and the
+    /// buffered duplex core, so that buffered bytes are not lost
+    /// when the stream is taken over (TLS upgrade etc).
+    pub fn into_parts(self) -> (S, BufDuplex) {
+        (self.stream, self.duplex)
+    }
 }
 
 impl<S: Read + Write> BufStream<S> {
     pub fn progress_read(&mut self) -> Result<usize> {
-        let slice = &mut self.read_buffer.to_io_slice_mut();
-        let count = self.stream.read_vectored(slice)?;
-        self.read_buffer.progress(count)
+        if !self.duplex.needs_read() {
+            return Ok(0);
+        }
+
+        let slices = &mut self.duplex.read_slices();
+        let count = self.stream.read_vectored(slices)?;
+        Ok(self.duplex.progress_read(count)?.len())
     }
 
     pub fn progress_write(&mut self) -> Result<usize> {
-        if !self.write_buffer.wants_write() {
+        if !self.duplex.wants_write() {
             return Ok(0);
         }
 
-        let slices = &mut self.write_buffer.to_io_slices();
+        let slices = &self.duplex.write_slices();
         let count = self.stream.write_vectored(slices)?;
-        self.write_buffer.progress(count)
+        self.duplex.progress_write(count)
     }
 
     pub fn progress(&mut self) -> Result<&[u8]> {
@@ -71,25 +88,20 @@ pub fn progress(&mut self) -> Result<&[u8]> {
 
         self.stream.flush()?;
 
-        Ok(&self.read_buffer.as_slice()[..count])
+        let buffered = self.duplex.buffered();
+        Ok(&buffered[buffered.len() - count..])
     }
 }
 
 impl<S: Read + Write> Read for BufStream<S> {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
-        if !self.read_buffer.wants_read() {
-            return Ok(0);
-        }
-
-        let mut buf = Cursor::new(buf);
-        let count = buf.write(self.read_buffer.as_slice())?;
-        self.read_buffer.sync(count)
+        Ok(self.duplex.read(buf))
     }
 }
 
 impl<S: Read + Write> Write for BufStream<S> {
     fn write(&mut self, buf: &[u8]) -> Result<usize> {
-        self.write_buffer.extend(buf);
+        self.duplex.write(buf);
         Ok(buf.len())
     }
 
@@ -0,0 +1,59 @@
+//! # Calendar
+//!
+//! Module dedicated to calendar collections.
+
+use http::webdav::{MultistatusResponse, DAV_NS};
+
+use crate::{APPLE_NS, CALDAV_NS, CS_NS};
+
+/// The calendar collection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Calendar {
+    /// The URL path of the calendar collection.
+    pub href: String,
+
+    /// The name of the calendar.
+    pub display_name: Option<String>,
+
+    /// The description of the calendar.
+    pub description: Option<String>,
+
+    /// The color of the calendar, usually `#RRGGBB` or `#RRGGBBAA`.
+    pub color: Option<String>,
+
+    /// The collection tag of the calendar.
+    ///
+    /// The tag changes whenever an event of the calendar changes, it
+    /// is a cheap way to know if a calendar needs to be synchronized.
+    pub ctag: Option<String>,
+
+    /// The current synchronization token of the calendar, if the
+    /// server supports incremental synchronization (RFC 6578).
+    pub sync_token: Option<String>,
+}
+
+impl Calendar {
+    /// Builds a calendar from a `PROPFIND` response, if the resource
+    /// is a calendar collection.
+    pub(crate) fn from_response(response: &MultistatusResponse) -> Option<Self> {
+        let is_calendar = response
+            .prop(DAV_NS, "resourcetype")
+            .and_then(|rt| rt.child(CALDAV_NS, "calendar"))
+            .is_some();
+
+        if !is_calendar {
+            return None;
+        }
+
+        let text = |ns, name| response.prop_text(ns, name).map(ToOwned::to_owned);
+
+        Some(Self {
+            href: response.href.clone(),
+            display_name: text(DAV_NS, "displayname"),
+            description: text(CALDAV_NS, "calendar-description"),
+            color: text(APPLE_NS, "calendar-color"),
+            ctag: text(CS_NS, "getctag"),
+            sync_token: text(DAV_NS, "sync-token"),
+        })
+    }
+}
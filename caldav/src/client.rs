@@ -0,0 +1,418 @@
+//! # Client
+//!
+//! Module dedicated to the CalDAV client (RFC 4791). The
+//! [`CaldavClient`] discovers calendars, manages calendars and events,
+//! and synchronizes events incrementally using the WebDAV
+//! `sync-collection` report (RFC 6578).
+
+use std::fmt::Write;
+
+use http::webdav::{self, Multistatus, WebdavClient, DAV_NS};
+use tracing::debug;
+
+use crate::{Calendar, Error, Event, Result, APPLE_NS, CALDAV_NS, CS_NS};
+
+/// The CalDAV authentication.
+///
+/// See [`webdav::WebdavAuth`].
+pub use http::webdav::WebdavAuth as CaldavAuth;
+
+/// The content type of iCalendar request bodies.
+const ICAL_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+
+/// The result of an incremental synchronization.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventsSync {
+    /// The token to give to the next synchronization.
+    pub sync_token: Option<String>,
+
+    /// The events created or updated since the previous
+    /// synchronization.
+    pub changed: Vec<Event>,
+
+    /// The URL paths of the events deleted since the previous
+    /// synchronization.
+    pub deleted: Vec<String>,
+}
+
+/// The CalDAV client.
+#[derive(Clone, Debug)]
+pub struct CaldavClient {
+    dav: WebdavClient,
+}
+
+impl CaldavClient {
+    /// Creates a new client for the given server URL.
+    ///
+    /// The URL is the CalDAV context path of the server, for example
+    /// `https://cal.example.com/dav/`.
+    pub fn new(url: impl ToString) -> Result<Self> {
+        let dav = WebdavClient::new(url)?;
+        Ok(Self { dav })
+    }
+
+    pub fn set_auth(&mut self, auth: CaldavAuth) {
+        self.dav.set_auth(auth);
+    }
+
+    pub fn with_auth(mut self, auth: CaldavAuth) -> Self {
+        self.set_auth(auth);
+        self
+    }
+
+    /// Finds the URL path of the calendar home set of the current
+    /// user.
+    pub async fn find_calendar_home(&self) -> Result<String> {
+        let home = self
+            .dav
+            .find_home_set(CALDAV_NS, "calendar-home-set")
+            .await?;
+        Ok(home)
+    }
+
+    /// Lists the calendars of the given calendar home set.
+    pub async fn list_calendars(&self, home: &str) -> Result<Vec<Calendar>> {
+        let body = webdav::propfind_body(&[
+            ("d", DAV_NS, "resourcetype"),
+            ("d", DAV_NS, "displayname"),
+            ("d", DAV_NS, "sync-token"),
+            ("c", CALDAV_NS, "calendar-description"),
+            ("cs", CS_NS, "getctag"),
+            ("a", APPLE_NS, "calendar-color"),
+        ]);
+
+        let multistatus = self
+            .dav
+            .send_multistatus("PROPFIND", home, "1", body)
+            .await?;
+
+        let calendars = multistatus
+            .responses
+            .iter()
+            .filter_map(Calendar::from_response)
+            .collect();
+
+        Ok(calendars)
+    }
+
+    /// Creates a new calendar at the given URL path.
+    pub async fn create_calendar(&self, href: &str, display_name: &str) -> Result<()> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<c:mkcalendar xmlns:d="{}" xmlns:c="{}">"#,
+                "<d:set><d:prop><d:displayname>{}</d:displayname></d:prop></d:set>",
+                "</c:mkcalendar>",
+            ),
+            DAV_NS,
+            CALDAV_NS,
+            webdav::escape(display_name),
+        );
+
+        self.dav.send_xml("MKCALENDAR", href, body).await?;
+        Ok(())
+    }
+
+    /// Deletes the calendar at the given URL path, including all its
+    /// events.
+    pub async fn delete_calendar(&self, href: &str) -> Result<()> {
+        let response = self.dav.send("DELETE", href, vec![], None).await?;
+        self.dav.expect_success("DELETE", href, response.status)?;
+        Ok(())
+    }
+
+    /// Lists all the events of the given calendar.
+    pub async fn list_events(&self, calendar: &str) -> Result<Vec<Event>> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<c:calendar-query xmlns:d="{}" xmlns:c="{}">"#,
+                "<d:prop><d:getetag/><c:calendar-data/></d:prop>",
+                r#"<c:filter><c:comp-filter name="VCALENDAR">"#,
+                r#"<c:comp-filter name="VEVENT"/>"#,
+                "</c:comp-filter></c:filter>",
+                "</c:calendar-query>",
+            ),
+            DAV_NS, CALDAV_NS,
+        );
+
+        let multistatus = self
+            .dav
+            .send_multistatus("REPORT", calendar, "1", body)
+            .await?;
+        Ok(events_from_multistatus(&multistatus))
+    }
+
+    /// Gets the events matching the given URL paths, using a single
+    /// request.
+    pub async fn get_events(&self, calendar: &str, hrefs: &[String]) -> Result<Vec<Event>> {
+        if hrefs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hrefs = hrefs.iter().fold(String::new(), |mut xml, href| {
+            let _ = write!(xml, "<d:href>{}</d:href>", webdav::escape(href));
+            xml
+        });
+
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<c:calendar-multiget xmlns:d="{}" xmlns:c="{}">"#,
+                "<d:prop><d:getetag/><c:calendar-data/></d:prop>",
+                "{}",
+                "</c:calendar-multiget>",
+            ),
+            DAV_NS, CALDAV_NS, hrefs,
+        );
+
+        let multistatus = self
+            .dav
+            .send_multistatus("REPORT", calendar, "1", body)
+            .await?;
+        Ok(events_from_multistatus(&multistatus))
+    }
+
+    /// Gets the event at the given URL path.
+    pub async fn get_event(&self, href: &str) -> Result<Event> {
+        let response = self.dav.send("GET", href, vec![], None).await?;
+
+        if response.status == 404 {
+            return Err(Error::GetEventNotFoundError(href.to_owned()));
+        }
+
+        self.dav.expect_success("GET", href, response.status)?;
+
+        let mut event = Event::parse(String::from_utf8_lossy(&response.body))?;
+        event.href = Some(href.to_owned());
+        event.etag = response.etag;
+        Ok(event)
+    }
+
+    /// Creates the given event in the given calendar.
+    ///
+    /// The URL path of the event is derived from its unique
+    /// identifier. On success, the URL path and the entity tag of the
+    /// event are updated.
+    pub async fn create_event(&self, calendar: &str, event: &mut Event) -> Result<()> {
+        let href = format!(
+            "{}/{}.ics",
+            calendar.trim_end_matches('/'),
+            webdav::sanitize_resource_name(&event.uid)
+        );
+
+        // prevents overriding an existing event
+        let headers = vec![
+            ("If-None-Match", String::from("*")),
+            ("Content-Type", ICAL_CONTENT_TYPE.to_owned()),
+        ];
+        let response = self
+            .dav
+            .send("PUT", &href, headers, Some(event.to_ical()))
+            .await?;
+
+        if response.status == 412 {
+            return Err(Error::EventConflictError(href));
+        }
+
+        self.dav.expect_success("PUT", &href, response.status)?;
+
+        event.href = Some(href);
+        event.etag = response.etag;
+        Ok(())
+    }
+
+    /// Updates the given event.
+    ///
+    /// When the entity tag of the event is known, the update fails
+    /// with [`Error::EventConflictError`] if the event has been
+    /// modified on the server in the meantime.
+    pub async fn update_event(&self, event: &mut Event) -> Result<()> {
+        let href = event
+            .href
+            .clone()
+            .ok_or_else(|| Error::GetEventHrefMissingError(event.uid.clone()))?;
+
+        let mut headers = vec![("Content-Type", ICAL_CONTENT_TYPE.to_owned())];
+
+        if let Some(etag) = &event.etag {
+            headers.push(("If-Match", etag.clone()));
+        }
+
+        let response = self
+            .dav
+            .send("PUT", &href, headers, Some(event.to_ical()))
+            .await?;
+
+        if response.status == 412 {
+            return Err(Error::EventConflictError(href));
+        }
+
+        self.dav.expect_success("PUT", &href, response.status)?;
+
+        event.etag = response.etag;
+        Ok(())
+    }
+
+    /// Deletes the given event.
+    ///
+    /// Like [`CaldavClient::update_event`], the deletion fails if the
+    /// event has been modified on the server in the meantime.
+    pub async fn delete_event(&self, event: &Event) -> Result<()> {
+        let href = event
+            .href
+            .as_deref()
+            .ok_or_else(|| Error::GetEventHrefMissingError(event.uid.clone()))?;
+
+        let headers = match &event.etag {
+            Some(etag) => vec![("If-Match", etag.clone())],
+            None => vec![],
+        };
+
+        let response = self.dav.send("DELETE", href, headers, None).await?;
+
+        if response.status == 412 {
+            return Err(Error::EventConflictError(href.to_owned()));
+        }
+
+        self.dav.expect_success("DELETE", href, response.status)?;
+        Ok(())
+    }
+
+    /// Synchronizes the events of the given calendar.
+    ///
+    /// Without token, all the events are returned as changed. With
+    /// the token returned by the previous synchronization, only the
+    /// events changed or deleted since then are returned. When the
+    /// server rejects the token, [`Error::InvalidSyncTokenError`] is
+    /// returned and a full synchronization is needed.
+    pub async fn sync_events(
+        &self,
+        calendar: &str,
+        sync_token: Option<&str>,
+    ) -> Result<EventsSync> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<d:sync-collection xmlns:d="{}" xmlns:c="{}">"#,
+                "<d:sync-token>{}</d:sync-token>",
+                "<d:sync-level>1</d:sync-level>",
+                "<d:prop><d:getetag/><c:calendar-data/></d:prop>",
+                "</d:sync-collection>",
+            ),
+            DAV_NS,
+            CALDAV_NS,
+            webdav::escape(sync_token.unwrap_or_default()),
+        );
+
+        let multistatus = match self
+            .dav
+            .send_multistatus("REPORT", calendar, "0", body)
+            .await
+        {
+            Err(http::Error::UnexpectedWebdavStatusError(403 | 409, ..))
+                if sync_token.is_some() =>
+            {
+                return Err(Error::InvalidSyncTokenError(calendar.to_owned()));
+            }
+            res => res?,
+        };
+
+        let mut sync = EventsSync {
+            sync_token: multistatus.sync_token.clone(),
+            changed: events_from_multistatus(&multistatus),
+            deleted: Vec::new(),
+        };
+
+        // some servers do not send calendar data in sync reports,
+        // missing events are fetched in a second pass
+        let mut missing = Vec::new();
+
+        for response in multistatus.responses {
+            if response.is_not_found() {
+                sync.deleted.push(response.href);
+            } else if response.prop(CALDAV_NS, "calendar-data").is_none()
+                && response.href.ends_with(".ics")
+            {
+                missing.push(response.href);
+            }
+        }
+
+        let fetched = self.get_events(calendar, &missing).await?;
+        sync.changed.extend(fetched);
+
+        Ok(sync)
+    }
+}
+
+/// Parses the events contained in the given `multistatus`.
+///
+/// Responses without calendar data, or with invalid one, are
+/// skipped.
+fn events_from_multistatus(multistatus: &Multistatus) -> Vec<Event> {
+    multistatus
+        .responses
+        .iter()
+        .filter_map(|response| {
+            let ical = response.prop(CALDAV_NS, "calendar-data")?;
+
+            match Event::parse(&ical.text) {
+                Ok(mut event) => {
+                    event.href = Some(response.href.clone());
+                    event.etag = response.prop_text(DAV_NS, "getetag").map(ToOwned::to_owned);
+                    Some(event)
+                }
+                Err(err) => {
+                    let href = &response.href;
+                    debug!(href, ?err, "skipping invalid event");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use http::webdav::Multistatus;
+
+    use super::events_from_multistatus;
+
+    #[test]
+    fn parse_events_from_multistatus() {
+        let xml = br#"<?xml version="1.0" encoding="utf-8"?>
+            <d:multistatus xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">
+              <d:response>
+                <d:href>/cal/work/abc.ics</d:href>
+                <d:propstat>
+                  <d:prop>
+                    <d:getetag>"etag-1"</d:getetag>
+                    <c:calendar-data>BEGIN:VCALENDAR
+VERSION:2.0
+BEGIN:VEVENT
+UID:abc
+SUMMARY:Standup
+DTSTART:20240102T090000Z
+END:VEVENT
+END:VCALENDAR
+</c:calendar-data>
+                  </d:prop>
+                  <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+              </d:response>
+              <d:response>
+                <d:href>/cal/work/gone.ics</d:href>
+                <d:status>HTTP/1.1 404 Not Found</d:status>
+              </d:response>
+            </d:multistatus>"#;
+
+        let multistatus = Multistatus::parse(xml).unwrap();
+        let events = events_from_multistatus(&multistatus);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].uid, "abc");
+        assert_eq!(events[0].summary.as_deref(), Some("Standup"));
+        assert_eq!(events[0].href.as_deref(), Some("/cal/work/abc.ics"));
+        assert_eq!(events[0].etag.as_deref(), Some("\"etag-1\""));
+    }
+}
@@ -0,0 +1,30 @@
+//! # Error
+//!
+//! Module dedicated to CalDAV errors. It contains an [`Error`] enum
+//! based on [`thiserror::Error`] and a type alias [`Result`].
+
+use thiserror::Error;
+
+/// The global `Result` alias of the library.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The global `Error` enum of the library.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    WebdavError(#[from] http::Error),
+
+    #[error("sync token of calendar {0} is not valid anymore, a full sync is needed")]
+    InvalidSyncTokenError(String),
+
+    #[error("cannot find event in iCalendar data")]
+    ParseEventNotFoundError,
+    #[error("cannot find unique identifier of event")]
+    ParseEventUidNotFoundError,
+    #[error("cannot find event at {0}")]
+    GetEventNotFoundError(String),
+    #[error("cannot update event {0}: event has not been stored yet")]
+    GetEventHrefMissingError(String),
+    #[error("cannot save event {0}: event has been modified or created in the meantime")]
+    EventConflictError(String),
+}
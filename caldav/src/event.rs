@@ -0,0 +1,390 @@
+//! # Event
+//!
+//! Module dedicated to calendar events. An [`Event`] exposes the
+//! most common properties of an iCalendar `VEVENT` (RFC 5545), and
+//! keeps the original iCalendar data so that properties it does not
+//! know about (recurrence rules, alarms, attendees etc) survive
+//! updates.
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use http::webdav::content_line::{escape_text, fold, unescape_text, unfold, ContentLine};
+
+use crate::{Error, Result};
+
+/// The product identifier written in generated iCalendar data.
+pub const PRODID: &str = "-//pimalaya//caldav-lib//EN";
+
+/// Properties managed by [`Event`], rewritten on update.
+const MANAGED_PROPS: [&str; 6] = [
+    "UID",
+    "SUMMARY",
+    "DESCRIPTION",
+    "LOCATION",
+    "DTSTART",
+    "DTEND",
+];
+
+/// The date or date-time of an event.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EventTime {
+    /// An all-day date.
+    Date(NaiveDate),
+
+    /// A date-time without timezone, relative to the attendee.
+    Floating(NaiveDateTime),
+
+    /// A date-time in UTC.
+    Utc(DateTime<Utc>),
+
+    /// A date-time relative to the given timezone identifier.
+    Zoned {
+        datetime: NaiveDateTime,
+        tzid: String,
+    },
+}
+
+impl EventTime {
+    /// Parses a `DTSTART` or `DTEND` content line.
+    fn parse(line: &ContentLine) -> Option<Self> {
+        let value = line.value;
+
+        if line.param("VALUE") == Some("DATE") || value.len() == 8 {
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+            return Some(Self::Date(date));
+        }
+
+        if let Some(value) = value.strip_suffix('Z') {
+            let datetime = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+            return Some(Self::Utc(datetime.and_utc()));
+        }
+
+        let datetime = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+
+        match line.param("TZID") {
+            Some(tzid) => Some(Self::Zoned {
+                datetime,
+                tzid: tzid.to_owned(),
+            }),
+            None => Some(Self::Floating(datetime)),
+        }
+    }
+
+    /// Formats the time as an iCalendar property.
+    fn to_property(&self, name: &str) -> String {
+        match self {
+            Self::Date(date) => format!("{name};VALUE=DATE:{}", date.format("%Y%m%d")),
+            Self::Floating(datetime) => format!("{name}:{}", datetime.format("%Y%m%dT%H%M%S")),
+            Self::Utc(datetime) => format!("{name}:{}", datetime.format("%Y%m%dT%H%M%SZ")),
+            Self::Zoned { datetime, tzid } => {
+                let datetime = datetime.format("%Y%m%dT%H%M%S");
+                format!("{name};TZID={tzid}:{datetime}")
+            }
+        }
+    }
+}
+
+/// The calendar event.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Event {
+    /// The URL path of the event resource.
+    ///
+    /// Defined once the event has been stored on the server.
+    pub href: Option<String>,
+
+    /// The entity tag of the event resource.
+    ///
+    /// Used to detect concurrent modifications when updating or
+    /// deleting the event.
+    pub etag: Option<String>,
+
+    /// The unique identifier of the event.
+    pub uid: String,
+
+    /// The title of the event.
+    pub summary: Option<String>,
+
+    /// The description of the event.
+    pub description: Option<String>,
+
+    /// The location of the event.
+    pub location: Option<String>,
+
+    /// When the event starts.
+    pub start: Option<EventTime>,
+
+    /// When the event ends (exclusive).
+    pub end: Option<EventTime>,
+
+    /// The original iCalendar data of the event, if any.
+    ical: Option<String>,
+}
+
+impl Event {
+    /// Creates a new event with the given unique identifier.
+    pub fn new(uid: impl ToString) -> Self {
+        Self {
+            uid: uid.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_summary(&mut self, summary: impl ToString) {
+        self.summary = Some(summary.to_string());
+    }
+
+    pub fn with_summary(mut self, summary: impl ToString) -> Self {
+        self.set_summary(summary);
+        self
+    }
+
+    pub fn set_description(&mut self, description: impl ToString) {
+        self.description = Some(description.to_string());
+    }
+
+    pub fn with_description(mut self, description: impl ToString) -> Self {
+        self.set_description(description);
+        self
+    }
+
+    pub fn set_location(&mut self, location: impl ToString) {
+        self.location = Some(location.to_string());
+    }
+
+    pub fn with_location(mut self, location: impl ToString) -> Self {
+        self.set_location(location);
+        self
+    }
+
+    pub fn set_start(&mut self, start: EventTime) {
+        self.start = Some(start);
+    }
+
+    pub fn with_start(mut self, start: EventTime) -> Self {
+        self.set_start(start);
+        self
+    }
+
+    pub fn set_end(&mut self, end: EventTime) {
+        self.end = Some(end);
+    }
+
+    pub fn with_end(mut self, end: EventTime) -> Self {
+        self.set_end(end);
+        self
+    }
+
+    /// Parses the first `VEVENT` of the given iCalendar data.
+    pub fn parse(ical: impl ToString) -> Result<Self> {
+        let ical = ical.to_string();
+        let mut event = Self::default();
+        let mut depth: Option<usize> = None;
+
+        for line in unfold(&ical) {
+            let Some(line) = ContentLine::parse(&line) else {
+                continue;
+            };
+
+            match (depth, line.name.as_str(), line.value) {
+                (None, "BEGIN", "VEVENT") => depth = Some(0),
+                (Some(0), "END", "VEVENT") => break,
+                (Some(n), "BEGIN", _) => depth = Some(n + 1),
+                (Some(n), "END", _) => depth = Some(n.saturating_sub(1)),
+                (Some(0), "UID", value) => event.uid = unescape_text(value),
+                (Some(0), "SUMMARY", value) => event.summary = Some(unescape_text(value)),
+                (Some(0), "DESCRIPTION", value) => event.description = Some(unescape_text(value)),
+                (Some(0), "LOCATION", value) => event.location = Some(unescape_text(value)),
+                (Some(0), "DTSTART", _) => event.start = EventTime::parse(&line),
+                (Some(0), "DTEND", _) => event.end = EventTime::parse(&line),
+                _ => (),
+            }
+        }
+
+        if depth.is_none() {
+            return Err(Error::ParseEventNotFoundError);
+        }
+
+        if event.uid.is_empty() {
+            return Err(Error::ParseEventUidNotFoundError);
+        }
+
+        event.ical = Some(ical);
+        Ok(event)
+    }
+
+    /// Returns the original iCalendar data of the event, if any.
+    pub fn ical(&self) -> Option<&str> {
+        self.ical.as_deref()
+    }
+
+    /// Formats the event as iCalendar data.
+    ///
+    /// When the event comes from existing iCalendar data, only the
+    /// properties managed by [`Event`] are rewritten, everything else
+    /// is kept as is.
+    pub fn to_ical(&self) -> String {
+        let mut lines = Vec::new();
+
+        match &self.ical {
+            Some(ical) => {
+                let mut depth: Option<usize> = None;
+                let mut done = false;
+
+                for line in unfold(ical) {
+                    let parsed = ContentLine::parse(&line);
+                    let (name, value) = match &parsed {
+                        Some(parsed) => (parsed.name.as_str(), parsed.value),
+                        None => ("", ""),
+                    };
+
+                    match (done, depth, name, value) {
+                        (false, None, "BEGIN", "VEVENT") => depth = Some(0),
+                        (false, Some(0), "END", "VEVENT") => {
+                            self.push_managed_props(&mut lines);
+                            depth = None;
+                            done = true;
+                        }
+                        (false, Some(n), "BEGIN", _) => depth = Some(n + 1),
+                        (false, Some(n), "END", _) => depth = Some(n.saturating_sub(1)),
+                        (false, Some(0), name, _) if MANAGED_PROPS.contains(&name) => continue,
+                        _ => (),
+                    }
+
+                    lines.push(line);
+                }
+            }
+            None => {
+                let dtstamp = Utc::now().format("%Y%m%dT%H%M%SZ");
+
+                lines.push(String::from("BEGIN:VCALENDAR"));
+                lines.push(String::from("VERSION:2.0"));
+                lines.push(format!("PRODID:{PRODID}"));
+                lines.push(String::from("BEGIN:VEVENT"));
+                lines.push(format!("DTSTAMP:{dtstamp}"));
+                self.push_managed_props(&mut lines);
+                lines.push(String::from("END:VEVENT"));
+                lines.push(String::from("END:VCALENDAR"));
+            }
+        }
+
+        let mut ical = String::new();
+
+        for line in lines {
+            fold(&mut ical, &line);
+        }
+
+        ical
+    }
+
+    fn push_managed_props(&self, lines: &mut Vec<String>) {
+        lines.push(format!("UID:{}", escape_text(&self.uid)));
+
+        if let Some(summary) = &self.summary {
+            lines.push(format!("SUMMARY:{}", escape_text(summary)));
+        }
+
+        if let Some(description) = &self.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+
+        if let Some(location) = &self.location {
+            lines.push(format!("LOCATION:{}", escape_text(location)));
+        }
+
+        if let Some(start) = &self.start {
+            lines.push(start.to_property("DTSTART"));
+        }
+
+        if let Some(end) = &self.end {
+            lines.push(end.to_property("DTEND"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::{Event, EventTime};
+
+    const ICAL: &str = concat!(
+        "BEGIN:VCALENDAR\r\n",
+        "VERSION:2.0\r\n",
+        "PRODID:-//Example//EN\r\n",
+        "BEGIN:VEVENT\r\n",
+        "UID:abc-123\r\n",
+        "DTSTAMP:20240101T080000Z\r\n",
+        "SUMMARY:Team meeting\\, weekly\r\n",
+        "DESCRIPTION:First line\\nsecond line that is long enough to be folded by\r\n",
+        "  the server\r\n",
+        "DTSTART;TZID=Europe/Paris:20240102T100000\r\n",
+        "DTEND;TZID=Europe/Paris:20240102T110000\r\n",
+        "RRULE:FREQ=WEEKLY\r\n",
+        "BEGIN:VALARM\r\n",
+        "ACTION:DISPLAY\r\n",
+        "DESCRIPTION:Reminder\r\n",
+        "TRIGGER:-PT15M\r\n",
+        "END:VALARM\r\n",
+        "END:VEVENT\r\n",
+        "END:VCALENDAR\r\n",
+    );
+
+    #[test]
+    fn parse_event() {
+        let event = Event::parse(ICAL).unwrap();
+        let datetime = NaiveDate::from_ymd_opt(2024, 1, 2)
+            .and_then(|date| date.and_hms_opt(10, 0, 0))
+            .unwrap();
+
+        assert_eq!(event.uid, "abc-123");
+        assert_eq!(event.summary.as_deref(), Some("Team meeting, weekly"));
+        assert_eq!(
+            event.description.as_deref(),
+            Some("First line\nsecond line that is long enough to be folded by the server")
+        );
+        assert_eq!(
+            event.start,
+            Some(EventTime::Zoned {
+                datetime,
+                tzid: String::from("Europe/Paris"),
+            })
+        );
+        assert!(event.location.is_none());
+    }
+
+    #[test]
+    fn update_event_keeps_unknown_props() {
+        let mut event = Event::parse(ICAL).unwrap();
+        event.set_summary("Renamed");
+        event.set_location("Room 1");
+
+        let ical = event.to_ical();
+        assert!(ical.contains("SUMMARY:Renamed\r\n"));
+        assert!(ical.contains("LOCATION:Room 1\r\n"));
+        assert!(ical.contains("RRULE:FREQ=WEEKLY\r\n"));
+        assert!(ical.contains("DESCRIPTION:Reminder\r\n"));
+        assert!(!ical.contains("Team meeting"));
+
+        let reparsed = Event::parse(ical).unwrap();
+        assert_eq!(reparsed.summary.as_deref(), Some("Renamed"));
+        assert_eq!(reparsed.description, event.description);
+        assert_eq!(reparsed.start, event.start);
+    }
+
+    #[test]
+    fn new_event_to_ical() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let event = Event::new("new-1")
+            .with_summary("Holiday")
+            .with_start(EventTime::Date(date))
+            .with_end(EventTime::Date(date.succ_opt().unwrap()));
+
+        let ical = event.to_ical();
+        assert!(ical.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(ical.contains("DTSTART;VALUE=DATE:20240301\r\n"));
+        assert!(ical.lines().all(|line| line.len() <= 75));
+
+        let parsed = Event::parse(ical).unwrap();
+        assert_eq!(parsed.uid, "new-1");
+        assert_eq!(parsed.end, Some(EventTime::Date(date.succ_opt().unwrap())));
+    }
+}
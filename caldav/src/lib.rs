@@ -0,0 +1,24 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+
+mod calendar;
+mod client;
+mod error;
+mod event;
+
+#[doc(inline)]
+pub use crate::{
+    calendar::Calendar,
+    client::{CaldavAuth, CaldavClient, EventsSync},
+    error::{Error, Result},
+    event::{Event, EventTime, PRODID},
+};
+
+/// The CalDAV XML namespace.
+pub const CALDAV_NS: &str = "urn:ietf:params:xml:ns:caldav";
+
+/// The CalendarServer XML namespace, used for collection tags.
+pub const CS_NS: &str = "http://calendarserver.org/ns/";
+
+/// The Apple iCal XML namespace, used for calendar colors.
+pub const APPLE_NS: &str = "http://apple.com/ns/ical/";
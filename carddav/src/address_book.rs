@@ -0,0 +1,51 @@
+//! # Address book
+//!
+//! Module dedicated to address book collections.
+
+use http::webdav::{MultistatusResponse, DAV_NS};
+
+use crate::{CARDDAV_NS, CS_NS};
+
+/// The address book collection.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AddressBook {
+    /// The URL path of the address book collection.
+    pub href: String,
+
+    /// The name of the address book.
+    pub display_name: Option<String>,
+
+    /// The description of the address book.
+    pub description: Option<String>,
+
+    /// The collection tag of the address book.
+    ///
+    /// The tag changes whenever a contact of the address book
+    /// changes, it is a cheap way to know if a local cache of the
+    /// address book is outdated.
+    pub ctag: Option<String>,
+}
+
+impl AddressBook {
+    /// Builds an address book from a `PROPFIND` response, if the
+    /// resource is an address book collection.
+    pub(crate) fn from_response(response: &MultistatusResponse) -> Option<Self> {
+        let is_address_book = response
+            .prop(DAV_NS, "resourcetype")
+            .and_then(|rt| rt.child(CARDDAV_NS, "addressbook"))
+            .is_some();
+
+        if !is_address_book {
+            return None;
+        }
+
+        let text = |ns, name| response.prop_text(ns, name).map(ToOwned::to_owned);
+
+        Some(Self {
+            href: response.href.clone(),
+            display_name: text(DAV_NS, "displayname"),
+            description: text(CARDDAV_NS, "addressbook-description"),
+            ctag: text(CS_NS, "getctag"),
+        })
+    }
+}
@@ -0,0 +1,354 @@
+//! # Client
+//!
+//! Module dedicated to the CardDAV client (RFC 6352). The
+//! [`CarddavClient`] discovers address books and manages their
+//! contacts, with conflict detection based on entity tags.
+
+use std::fmt::Write;
+
+use http::webdav::{self, Multistatus, WebdavClient, DAV_NS};
+use tracing::debug;
+
+use crate::{AddressBook, Error, Result, Vcard, CARDDAV_NS, CS_NS};
+
+/// The CardDAV authentication.
+///
+/// See [`webdav::WebdavAuth`].
+pub use http::webdav::WebdavAuth as CarddavAuth;
+
+/// The content type of vCard request bodies.
+const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+
+/// The CardDAV client.
+#[derive(Clone, Debug)]
+pub struct CarddavClient {
+    dav: WebdavClient,
+}
+
+impl CarddavClient {
+    /// Creates a new client for the given server URL.
+    ///
+    /// The URL is the CardDAV context path of the server, for example
+    /// `https://dav.example.com/carddav/`.
+    pub fn new(url: impl ToString) -> Result<Self> {
+        let dav = WebdavClient::new(url)?;
+        Ok(Self { dav })
+    }
+
+    pub fn set_auth(&mut self, auth: CarddavAuth) {
+        self.dav.set_auth(auth);
+    }
+
+    pub fn with_auth(mut self, auth: CarddavAuth) -> Self {
+        self.set_auth(auth);
+        self
+    }
+
+    /// Finds the URL path of the address book home set of the current
+    /// user.
+    pub async fn find_address_book_home(&self) -> Result<String> {
+        let home = self
+            .dav
+            .find_home_set(CARDDAV_NS, "addressbook-home-set")
+            .await?;
+        Ok(home)
+    }
+
+    /// Lists the address books of the given address book home set.
+    pub async fn list_address_books(&self, home: &str) -> Result<Vec<AddressBook>> {
+        let body = webdav::propfind_body(&[
+            ("d", DAV_NS, "resourcetype"),
+            ("d", DAV_NS, "displayname"),
+            ("card", CARDDAV_NS, "addressbook-description"),
+            ("cs", CS_NS, "getctag"),
+        ]);
+
+        let multistatus = self
+            .dav
+            .send_multistatus("PROPFIND", home, "1", body)
+            .await?;
+
+        let address_books = multistatus
+            .responses
+            .iter()
+            .filter_map(AddressBook::from_response)
+            .collect();
+
+        Ok(address_books)
+    }
+
+    /// Lists all the vCards of the given address book.
+    pub async fn list_vcards(&self, address_book: &str) -> Result<Vec<Vcard>> {
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<card:addressbook-query xmlns:d="{}" xmlns:card="{}">"#,
+                "<d:prop><d:getetag/><card:address-data/></d:prop>",
+                "</card:addressbook-query>",
+            ),
+            DAV_NS, CARDDAV_NS,
+        );
+
+        let multistatus = self
+            .dav
+            .send_multistatus("REPORT", address_book, "1", body)
+            .await?;
+
+        Ok(vcards_from_multistatus(&multistatus))
+    }
+
+    /// Searches the vCards of the given address book whose formatted
+    /// name or email address contains the given query, ignoring case.
+    ///
+    /// The search is performed by the server, which makes it suitable
+    /// for address completion on large address books.
+    pub async fn search_vcards(&self, address_book: &str, query: &str) -> Result<Vec<Vcard>> {
+        let query = webdav::escape(query);
+        let filters = ["FN", "EMAIL"].iter().fold(String::new(), |mut xml, prop| {
+            let _ = write!(
+                xml,
+                concat!(
+                    r#"<card:prop-filter name="{}">"#,
+                    r#"<card:text-match collation="i;unicode-casemap" match-type="contains">"#,
+                    "{}",
+                    "</card:text-match>",
+                    "</card:prop-filter>",
+                ),
+                prop, query,
+            );
+            xml
+        });
+
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<card:addressbook-query xmlns:d="{}" xmlns:card="{}">"#,
+                "<d:prop><d:getetag/><card:address-data/></d:prop>",
+                r#"<card:filter test="anyof">{}</card:filter>"#,
+                "</card:addressbook-query>",
+            ),
+            DAV_NS, CARDDAV_NS, filters,
+        );
+
+        let multistatus = self
+            .dav
+            .send_multistatus("REPORT", address_book, "1", body)
+            .await?;
+
+        Ok(vcards_from_multistatus(&multistatus))
+    }
+
+    /// Gets the vCards matching the given URL paths, using a single
+    /// request.
+    pub async fn get_vcards(&self, address_book: &str, hrefs: &[String]) -> Result<Vec<Vcard>> {
+        if hrefs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let hrefs = hrefs.iter().fold(String::new(), |mut xml, href| {
+            let _ = write!(xml, "<d:href>{}</d:href>", webdav::escape(href));
+            xml
+        });
+
+        let body = format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<card:addressbook-multiget xmlns:d="{}" xmlns:card="{}">"#,
+                "<d:prop><d:getetag/><card:address-data/></d:prop>",
+                "{}",
+                "</card:addressbook-multiget>",
+            ),
+            DAV_NS, CARDDAV_NS, hrefs,
+        );
+
+        let multistatus = self
+            .dav
+            .send_multistatus("REPORT", address_book, "1", body)
+            .await?;
+
+        Ok(vcards_from_multistatus(&multistatus))
+    }
+
+    /// Gets the vCard at the given URL path.
+    pub async fn get_vcard(&self, href: &str) -> Result<Vcard> {
+        let response = self.dav.send("GET", href, vec![], None).await?;
+
+        if response.status == 404 {
+            return Err(Error::GetVcardNotFoundError(href.to_owned()));
+        }
+
+        self.dav.expect_success("GET", href, response.status)?;
+
+        let mut vcard = Vcard::parse(String::from_utf8_lossy(&response.body))?;
+        vcard.href = Some(href.to_owned());
+        vcard.etag = response.etag;
+        Ok(vcard)
+    }
+
+    /// Creates the given vCard in the given address book.
+    ///
+    /// The URL path of the vCard is derived from its unique
+    /// identifier. On success, the URL path and the entity tag of the
+    /// vCard are updated.
+    pub async fn create_vcard(&self, address_book: &str, vcard: &mut Vcard) -> Result<()> {
+        let href = format!(
+            "{}/{}.vcf",
+            address_book.trim_end_matches('/'),
+            webdav::sanitize_resource_name(&vcard.uid)
+        );
+
+        // prevents overriding an existing vCard
+        let headers = vec![
+            ("If-None-Match", String::from("*")),
+            ("Content-Type", VCARD_CONTENT_TYPE.to_owned()),
+        ];
+        let response = self
+            .dav
+            .send("PUT", &href, headers, Some(vcard.to_vcard()))
+            .await?;
+
+        if response.status == 412 {
+            return Err(Error::VcardConflictError(href));
+        }
+
+        self.dav.expect_success("PUT", &href, response.status)?;
+
+        vcard.href = Some(href);
+        vcard.etag = response.etag;
+        Ok(())
+    }
+
+    /// Updates the given vCard.
+    ///
+    /// When the entity tag of the vCard is known, the update fails
+    /// with [`Error::VcardConflictError`] if the vCard has been
+    /// modified on the server in the meantime.
+    pub async fn update_vcard(&self, vcard: &mut Vcard) -> Result<()> {
+        let href = vcard
+            .href
+            .clone()
+            .ok_or_else(|| Error::GetVcardHrefMissingError(vcard.uid.clone()))?;
+
+        let mut headers = vec![("Content-Type", VCARD_CONTENT_TYPE.to_owned())];
+
+        if let Some(etag) = &vcard.etag {
+            headers.push(("If-Match", etag.clone()));
+        }
+
+        let response = self
+            .dav
+            .send("PUT", &href, headers, Some(vcard.to_vcard()))
+            .await?;
+
+        if response.status == 412 {
+            return Err(Error::VcardConflictError(href));
+        }
+
+        self.dav.expect_success("PUT", &href, response.status)?;
+
+        vcard.etag = response.etag;
+        Ok(())
+    }
+
+    /// Deletes the given vCard.
+    ///
+    /// Like [`CarddavClient::update_vcard`], the deletion fails if
+    /// the vCard has been modified on the server in the meantime.
+    pub async fn delete_vcard(&self, vcard: &Vcard) -> Result<()> {
+        let href = vcard
+            .href
+            .as_deref()
+            .ok_or_else(|| Error::GetVcardHrefMissingError(vcard.uid.clone()))?;
+
+        let headers = match &vcard.etag {
+            Some(etag) => vec![("If-Match", etag.clone())],
+            None => vec![],
+        };
+
+        let response = self.dav.send("DELETE", href, headers, None).await?;
+
+        if response.status == 412 {
+            return Err(Error::VcardConflictError(href.to_owned()));
+        }
+
+        self.dav.expect_success("DELETE", href, response.status)?;
+        Ok(())
+    }
+}
+
+/// Parses the vCards contained in the given `multistatus`.
+///
+/// Responses without address data, or with invalid one, are
+/// skipped.
+fn vcards_from_multistatus(multistatus: &Multistatus) -> Vec<Vcard> {
+    multistatus
+        .responses
+        .iter()
+        .filter_map(|response| {
+            let data = response.prop(CARDDAV_NS, "address-data")?;
+
+            match Vcard::parse(&data.text) {
+                Ok(mut vcard) => {
+                    vcard.href = Some(response.href.clone());
+                    vcard.etag = response.prop_text(DAV_NS, "getetag").map(ToOwned::to_owned);
+                    Some(vcard)
+                }
+                Err(err) => {
+                    let href = &response.href;
+                    debug!(href, ?err, "skipping invalid vCard");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use http::webdav::Multistatus;
+
+    use super::vcards_from_multistatus;
+
+    #[test]
+    fn parse_vcards_from_multistatus() {
+        let xml = br#"<?xml version="1.0" encoding="utf-8"?>
+            <d:multistatus xmlns:d="DAV:" xmlns:card="urn:ietf:params:xml:ns:carddav">
+              <d:response>
+                <d:href>/contacts/user/default/abc.vcf</d:href>
+                <d:propstat>
+                  <d:prop>
+                    <d:getetag>"etag-1"</d:getetag>
+                    <card:address-data>BEGIN:VCARD
+VERSION:3.0
+UID:abc
+FN:Jane Doe
+EMAIL:jane@example.com
+END:VCARD
+</card:address-data>
+                  </d:prop>
+                  <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+              </d:response>
+              <d:response>
+                <d:href>/contacts/user/default/</d:href>
+                <d:propstat>
+                  <d:prop><d:getetag>"etag-0"</d:getetag></d:prop>
+                  <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+              </d:response>
+            </d:multistatus>"#;
+
+        let multistatus = Multistatus::parse(xml).unwrap();
+        let vcards = vcards_from_multistatus(&multistatus);
+
+        assert_eq!(vcards.len(), 1);
+        assert_eq!(vcards[0].uid, "abc");
+        assert_eq!(vcards[0].full_name.as_deref(), Some("Jane Doe"));
+        assert_eq!(vcards[0].emails, vec!["jane@example.com"]);
+        assert_eq!(
+            vcards[0].href.as_deref(),
+            Some("/contacts/user/default/abc.vcf")
+        );
+        assert_eq!(vcards[0].etag.as_deref(), Some("\"etag-1\""));
+    }
+}
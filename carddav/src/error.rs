@@ -0,0 +1,27 @@
+//! # Error
+//!
+//! Module dedicated to CardDAV errors. It contains an [`Error`] enum
+//! based on [`thiserror::Error`] and a type alias [`Result`].
+
+use thiserror::Error;
+
+/// The global `Result` alias of the library.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The global `Error` enum of the library.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error(transparent)]
+    WebdavError(#[from] http::Error),
+
+    #[error("cannot find vCard in contact data")]
+    ParseVcardNotFoundError,
+    #[error("cannot find unique identifier of vCard")]
+    ParseVcardUidNotFoundError,
+    #[error("cannot find vCard at {0}")]
+    GetVcardNotFoundError(String),
+    #[error("cannot update vCard {0}: vCard has not been stored yet")]
+    GetVcardHrefMissingError(String),
+    #[error("cannot save vCard {0}: vCard has been modified or created in the meantime")]
+    VcardConflictError(String),
+}
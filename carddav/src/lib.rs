@@ -0,0 +1,21 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+
+mod address_book;
+mod client;
+mod error;
+mod vcard;
+
+#[doc(inline)]
+pub use crate::{
+    address_book::AddressBook,
+    client::{CarddavAuth, CarddavClient},
+    error::{Error, Result},
+    vcard::{Vcard, PRODID},
+};
+
+/// The CardDAV XML namespace.
+pub const CARDDAV_NS: &str = "urn:ietf:params:xml:ns:carddav";
+
+/// The CalendarServer XML namespace, used for collection tags.
+pub const CS_NS: &str = "http://calendarserver.org/ns/";
@@ -0,0 +1,316 @@
+//! # vCard
+//!
+//! Module dedicated to contacts. A [`Vcard`] exposes the properties
+//! of a vCard (RFC 6350) needed to identify and reach a contact, and
+//! keeps the original vCard data so that properties it does not know
+//! about (addresses, photos, birthdays etc) survive updates.
+
+use http::webdav::content_line::{escape_text, fold, unescape_text, unfold, ContentLine};
+
+use crate::{Error, Result};
+
+/// The product identifier written in generated vCard data.
+pub const PRODID: &str = "-//pimalaya//carddav-lib//EN";
+
+/// The contact.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Vcard {
+    /// The URL path of the vCard resource.
+    ///
+    /// Defined once the vCard has been stored on the server.
+    pub href: Option<String>,
+
+    /// The entity tag of the vCard resource.
+    ///
+    /// Used to detect concurrent modifications when updating or
+    /// deleting the vCard.
+    pub etag: Option<String>,
+
+    /// The unique identifier of the vCard.
+    pub uid: String,
+
+    /// The formatted name of the contact.
+    pub full_name: Option<String>,
+
+    /// The email addresses of the contact.
+    pub emails: Vec<String>,
+
+    /// The phone numbers of the contact.
+    pub phones: Vec<String>,
+
+    /// The original vCard data, if any.
+    vcard: Option<String>,
+}
+
+impl Vcard {
+    /// Creates a new vCard with the given unique identifier.
+    pub fn new(uid: impl ToString) -> Self {
+        Self {
+            uid: uid.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_full_name(&mut self, full_name: impl ToString) {
+        self.full_name = Some(full_name.to_string());
+    }
+
+    pub fn with_full_name(mut self, full_name: impl ToString) -> Self {
+        self.set_full_name(full_name);
+        self
+    }
+
+    pub fn add_email(&mut self, email: impl ToString) {
+        self.emails.push(email.to_string());
+    }
+
+    pub fn with_email(mut self, email: impl ToString) -> Self {
+        self.add_email(email);
+        self
+    }
+
+    pub fn add_phone(&mut self, phone: impl ToString) {
+        self.phones.push(phone.to_string());
+    }
+
+    pub fn with_phone(mut self, phone: impl ToString) -> Self {
+        self.add_phone(phone);
+        self
+    }
+
+    /// Returns `true` if the formatted name or one of the email
+    /// addresses contains the given query, ignoring case.
+    ///
+    /// This is the matching used for address completion.
+    pub fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+
+        self.full_name
+            .iter()
+            .chain(&self.emails)
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+
+    /// Parses the first vCard of the given vCard data.
+    pub fn parse(vcard: impl ToString) -> Result<Self> {
+        let vcard = vcard.to_string();
+        let mut card = Self::default();
+        let mut found = false;
+
+        for line in unfold(&vcard) {
+            let Some(line) = ContentLine::parse(&line) else {
+                continue;
+            };
+
+            match (found, line.name.as_str(), line.value) {
+                (false, "BEGIN", value) if value.eq_ignore_ascii_case("VCARD") => found = true,
+                (true, "END", value) if value.eq_ignore_ascii_case("VCARD") => break,
+                (true, "UID", value) => card.uid = unescape_text(value),
+                (true, "FN", value) => card.full_name = Some(unescape_text(value)),
+                (true, "EMAIL", value) => card.emails.push(unescape_text(value)),
+                (true, "TEL", value) => card.phones.push(unescape_text(value)),
+                _ => (),
+            }
+        }
+
+        if !found {
+            return Err(Error::ParseVcardNotFoundError);
+        }
+
+        if card.uid.is_empty() {
+            return Err(Error::ParseVcardUidNotFoundError);
+        }
+
+        card.vcard = Some(vcard);
+        Ok(card)
+    }
+
+    /// Returns the original vCard data, if any.
+    pub fn vcard(&self) -> Option<&str> {
+        self.vcard.as_deref()
+    }
+
+    /// Formats the contact as vCard data.
+    ///
+    /// When the contact comes from existing vCard data, only the
+    /// properties managed by [`Vcard`] are rewritten, everything else
+    /// is kept as is. Email addresses and phone numbers that did not
+    /// change keep their original parameters (types, preferences
+    /// etc).
+    pub fn to_vcard(&self) -> String {
+        let mut lines = Vec::new();
+
+        match &self.vcard {
+            Some(vcard) => {
+                let mut kept_emails = Vec::new();
+                let mut kept_phones = Vec::new();
+                let mut inside = false;
+                let mut done = false;
+
+                for line in unfold(vcard) {
+                    let parsed = ContentLine::parse(&line);
+                    let (name, value) = match &parsed {
+                        Some(parsed) => (parsed.name.as_str(), parsed.value),
+                        None => ("", ""),
+                    };
+
+                    if done {
+                        lines.push(line);
+                        continue;
+                    }
+
+                    match (inside, name) {
+                        (false, "BEGIN") if value.eq_ignore_ascii_case("VCARD") => inside = true,
+                        (true, "END") if value.eq_ignore_ascii_case("VCARD") => {
+                            self.push_managed_props(&mut lines, &kept_emails, &kept_phones);
+                            done = true;
+                        }
+                        (true, "UID" | "FN") => continue,
+                        (true, "EMAIL") => {
+                            if !keep_value(&self.emails, &mut kept_emails, value) {
+                                continue;
+                            }
+                        }
+                        (true, "TEL") => {
+                            if !keep_value(&self.phones, &mut kept_phones, value) {
+                                continue;
+                            }
+                        }
+                        _ => (),
+                    }
+
+                    lines.push(line);
+                }
+            }
+            None => {
+                lines.push(String::from("BEGIN:VCARD"));
+                lines.push(String::from("VERSION:3.0"));
+                lines.push(format!("PRODID:{PRODID}"));
+                lines.push(String::from("N:;;;;"));
+                self.push_managed_props(&mut lines, &[], &[]);
+                lines.push(String::from("END:VCARD"));
+            }
+        }
+
+        let mut vcard = String::new();
+
+        for line in lines {
+            fold(&mut vcard, &line);
+        }
+
+        vcard
+    }
+
+    fn push_managed_props(&self, lines: &mut Vec<String>, emails: &[String], phones: &[String]) {
+        lines.push(format!("UID:{}", escape_text(&self.uid)));
+
+        let full_name = self.full_name.as_deref().unwrap_or_default();
+        lines.push(format!("FN:{}", escape_text(full_name)));
+
+        for email in self.emails.iter().filter(|email| !emails.contains(email)) {
+            lines.push(format!("EMAIL:{}", escape_text(email)));
+        }
+
+        for phone in self.phones.iter().filter(|phone| !phones.contains(phone)) {
+            lines.push(format!("TEL:{}", escape_text(phone)));
+        }
+    }
+}
+
+/// Returns `true` if the given raw value is still part of the given
+/// values and has not been kept yet, in which case it is marked as
+/// kept.
+fn keep_value(values: &[String], kept: &mut Vec<String>, value: &str) -> bool {
+    let value = unescape_text(value);
+
+    if values.contains(&value) && !kept.contains(&value) {
+        kept.push(value);
+        true
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Vcard;
+
+    const VCARD: &str = concat!(
+        "BEGIN:VCARD\r\n",
+        "VERSION:3.0\r\n",
+        "PRODID:-//Example//EN\r\n",
+        "UID:abc-123\r\n",
+        "N:Doe;John;;;\r\n",
+        "FN:John Doe\\, Jr.\r\n",
+        "item1.EMAIL;TYPE=INTERNET,WORK:john@work.example.com\r\n",
+        "item1.X-ABLabel:Work\r\n",
+        "EMAIL;TYPE=INTERNET,HOME:john@home.example.com\r\n",
+        "TEL;TYPE=CELL:+33 6 00 00 00 00\r\n",
+        "ADR;TYPE=HOME:;;1 Main Street;Springfield;;12345;Somewhere far far a\r\n",
+        " way\r\n",
+        "END:VCARD\r\n",
+    );
+
+    #[test]
+    fn parse_vcard() {
+        let vcard = Vcard::parse(VCARD).unwrap();
+
+        assert_eq!(vcard.uid, "abc-123");
+        assert_eq!(vcard.full_name.as_deref(), Some("John Doe, Jr."));
+        assert_eq!(
+            vcard.emails,
+            vec!["john@work.example.com", "john@home.example.com"]
+        );
+        assert_eq!(vcard.phones, vec!["+33 6 00 00 00 00"]);
+
+        assert!(vcard.matches("doe"));
+        assert!(vcard.matches("HOME.example"));
+        assert!(!vcard.matches("jane"));
+    }
+
+    #[test]
+    fn update_vcard_keeps_unknown_props() {
+        let mut vcard = Vcard::parse(VCARD).unwrap();
+        vcard.set_full_name("Johnny Doe");
+        vcard
+            .emails
+            .retain(|email| email != "john@home.example.com");
+        vcard.add_email("johnny@example.com");
+
+        let raw = vcard.to_vcard();
+        assert!(raw.contains("FN:Johnny Doe\r\n"));
+        assert!(raw.contains("item1.EMAIL;TYPE=INTERNET,WORK:john@work.example.com\r\n"));
+        assert!(raw.contains("EMAIL:johnny@example.com\r\n"));
+        assert!(!raw.contains("john@home.example.com"));
+        assert!(raw.contains("TEL;TYPE=CELL:+33 6 00 00 00 00\r\n"));
+        assert!(raw.contains("N:Doe;John;;;\r\n"));
+        assert!(raw.replace("\r\n ", "").contains(
+            "ADR;TYPE=HOME:;;1 Main Street;Springfield;;12345;Somewhere far far away\r\n"
+        ));
+        assert!(raw.ends_with("END:VCARD\r\n"));
+
+        let updated = Vcard::parse(raw).unwrap();
+        assert_eq!(updated.full_name.as_deref(), Some("Johnny Doe"));
+        assert_eq!(
+            updated.emails,
+            vec!["john@work.example.com", "johnny@example.com"]
+        );
+    }
+
+    #[test]
+    fn build_new_vcard() {
+        let vcard = Vcard::new("new-1")
+            .with_full_name("Jane")
+            .with_email("jane@example.com");
+
+        let raw = vcard.to_vcard();
+        assert!(raw.starts_with("BEGIN:VCARD\r\nVERSION:3.0\r\n"));
+        assert!(raw.contains("UID:new-1\r\n"));
+        assert!(raw.contains("FN:Jane\r\n"));
+        assert!(raw.contains("EMAIL:jane@example.com\r\n"));
+
+        let parsed = Vcard::parse(raw).unwrap();
+        assert_eq!(parsed.uid, "new-1");
+        assert_eq!(parsed.emails, vec!["jane@example.com"]);
+    }
+}
@@ -4,7 +4,7 @@
 use email::{
     account::config::AccountConfig,
     backend::BackendBuilder,
-    envelope::{list::ListEnvelopes, Id},
+    envelope::{list::ListEnvelopes, Id, StableId},
     flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flag},
     folder::{
         add::AddFolder, config::FolderConfig, delete::DeleteFolder, expunge::ExpungeFolder,
@@ -39,6 +39,8 @@ async fn test_maildir_features() {
     let mdir_config = Arc::new(MaildirConfig {
         root_dir: tmp_dir.clone(),
         maildirpp: false,
+        clean_tmp: false,
+        expunge_flags: None,
     });
 
     let mdir_ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config.clone());
@@ -198,6 +200,14 @@ async fn test_maildir_features() {
     assert!(envelope.flags.contains(&Flag::Seen));
     assert!(envelope.flags.contains(&Flag::Flagged));
 
+    // check that stable ids survive flag changes
+    let stable_id = StableId::maildir("INBOX", &envelope.id);
+    let (folder, id) = mdir.resolve_stable_ids(&[stable_id]).await.unwrap();
+    assert_eq!("INBOX", folder);
+    assert_eq!(Id::single(&envelope.id), id);
+    let unknown_id = StableId::maildir("INBOX", "unknown");
+    assert!(mdir.resolve_stable_ids(&[unknown_id]).await.is_err());
+
     // check that the message flags can be changed
     mdir.set_flag("INBOX", &Id::single(&envelope.id), Flag::Answered)
         .await
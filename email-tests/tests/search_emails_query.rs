@@ -108,6 +108,8 @@ async fn test_search_emails_query() {
     let mdir_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("maildir"),
         maildirpp: false,
+        clean_tmp: false,
+        expunge_flags: None,
     });
 
     let mdir_ctx = MaildirContextBuilder::new(account_config.clone(), mdir_config.clone());
@@ -267,6 +269,11 @@ async fn test_query(
                 page_size: 0,
                 page: 0,
                 query: Some(query),
+                notmuch_query: None,
+                with_previews: false,
+                with_saved_dates: false,
+                extra_headers: Vec::new(),
+                filters: Default::default(),
             },
         )
         .await
@@ -39,6 +39,7 @@ async fn test_smtp_features() {
             encryption: Some(Encryption::None),
             login: "alice".into(),
             auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         let imap_ctx = ImapContextBuilder::new(account_config.clone(), imap_config);
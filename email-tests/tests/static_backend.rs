@@ -43,6 +43,7 @@ async fn test_static_backend() {
             encryption: Some(Encryption::None),
             login: "alice".into(),
             auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw("password"))),
+            ..Default::default()
         });
 
         // 1. define custom context made of subcontexts
@@ -39,6 +39,8 @@ async fn test_sync() {
     let left_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("left"),
         maildirpp: true,
+        clean_tmp: false,
+        expunge_flags: None,
     });
 
     let left_account_config = Arc::new(AccountConfig {
@@ -64,6 +66,8 @@ async fn test_sync() {
     let right_config = Arc::new(MaildirConfig {
         root_dir: tmp.join("right"),
         maildirpp: false,
+        clean_tmp: false,
+        expunge_flags: None,
     });
 
     let right_account_config = Arc::new(AccountConfig {
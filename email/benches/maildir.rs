@@ -0,0 +1,55 @@
+//! Benchmark of the Maildir envelopes listing.
+//!
+//! Compares the listing of envelopes with and without headers on a
+//! large folder. The number of messages defaults to 10k, and can be
+//! changed using the `MAILDIR_BENCH_SIZE` environment variable:
+//!
+//! ```sh
+//! MAILDIR_BENCH_SIZE=50000 cargo bench --features maildir --bench maildir
+//! ```
+
+use std::env;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use email::{date::SystemClock, envelope::Envelopes};
+use maildirs::{Flag, Maildir};
+
+fn list_envelopes(c: &mut Criterion) {
+    let size: usize = env::var("MAILDIR_BENCH_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(10_000);
+
+    let root = tempfile::tempdir().unwrap();
+    let mdir = Maildir::from(root.path().to_owned());
+    mdir.create_all().unwrap();
+
+    for i in 0..size {
+        let msg = format!(
+            "From: alice@localhost\r\nTo: bob@localhost\r\nSubject: Message {i}\r\nDate: Thu, 1 Jan 2024 00:00:00 +0000\r\n\r\nHello, world!\r\n"
+        );
+        mdir.write_cur(msg, [Flag::Seen]).unwrap();
+    }
+
+    let mut group = c.benchmark_group(format!("list {size} maildir envelopes"));
+    group.sample_size(10);
+
+    group.bench_function("without headers", |b| {
+        b.iter(|| {
+            let entries = mdir.read().unwrap();
+            Envelopes::from_mdir_entries_without_headers(entries).len()
+        })
+    });
+
+    group.bench_function("with headers", |b| {
+        b.iter(|| {
+            let entries = mdir.read().unwrap();
+            Envelopes::from_mdir_entries(entries, None, &SystemClock).len()
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, list_envelopes);
+criterion_main!(benches);
@@ -64,6 +64,11 @@ pub async fn main() {
                     page: 1,
                     page_size: 10,
                     query: Some(query),
+                    notmuch_query: None,
+                    with_previews: false,
+                    with_saved_dates: false,
+                    extra_headers: Vec::new(),
+                    filters: Default::default(),
                 },
             )
             .await
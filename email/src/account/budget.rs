@@ -0,0 +1,133 @@
+//! # Concurrency budget
+//!
+//! Module dedicated to the account concurrency budget, see
+//! [`ConcurrencyBudget`].
+//!
+//! Providers like Gmail or Outlook temporarily lock accounts opening
+//! too many simultaneous connections. The budget is shared by every
+//! component of the same account (IMAP clients pool, watchers and
+//! synchronization workers), so that together they never exceed the
+//! configured limit.
+//!
+//! Some servers also limit the number of connections per client
+//! address, whatever the account. Connections to the same host can
+//! then be capped as well, using a budget shared by all accounts.
+//!
+//! Budgets are shared through the
+//! [`AccountRuntime`](super::runtime::AccountRuntime).
+
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// The default concurrency budget of an account.
+///
+/// Most providers accept around 15 simultaneous connections per
+/// account, the default keeps a margin for other clients.
+pub const DEFAULT_CONCURRENCY_BUDGET: u32 = 10;
+
+/// The weight of a network connection.
+pub const CONNECTION_WEIGHT: u32 = 1;
+
+/// The concurrency budget of an account.
+///
+/// The budget is a weighted semaphore: operations acquire a weight
+/// before starting, and give it back when the returned permit is
+/// dropped. Cloning the budget shares the same semaphore.
+#[derive(Clone, Debug)]
+pub struct ConcurrencyBudget {
+    limit: u32,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyBudget {
+    /// Create a new budget with the given limit.
+    pub fn new(limit: u32) -> Self {
+        let limit = limit.max(1);
+
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit as usize)),
+        }
+    }
+
+    /// Return the maximum weight of concurrent operations.
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+
+    /// Return the weight still available.
+    pub fn available(&self) -> u32 {
+        self.semaphore.available_permits() as u32
+    }
+
+    /// Return the weight currently in use.
+    pub fn in_use(&self) -> u32 {
+        self.limit.saturating_sub(self.available())
+    }
+
+    /// Wait until the given weight fits in the budget.
+    ///
+    /// Weights bigger than the whole budget are reduced to the limit,
+    /// so that they can still be acquired, alone.
+    pub async fn acquire(&self, weight: u32) -> ConcurrencyPermit {
+        let weight = weight.clamp(1, self.limit);
+        let permit = self
+            .semaphore
+            .clone()
+            .acquire_many_owned(weight)
+            .await
+            .expect("concurrency budget semaphore should never be closed");
+        ConcurrencyPermit(permit)
+    }
+
+    /// Acquire the given weight if it immediately fits in the
+    /// budget.
+    pub fn try_acquire(&self, weight: u32) -> Option<ConcurrencyPermit> {
+        let weight = weight.clamp(1, self.limit);
+        let permit = self.semaphore.clone().try_acquire_many_owned(weight).ok()?;
+        Some(ConcurrencyPermit(permit))
+    }
+}
+
+/// A weight acquired from a [`ConcurrencyBudget`].
+///
+/// The weight is given back to the budget when the permit is
+/// dropped.
+#[derive(Debug)]
+pub struct ConcurrencyPermit(OwnedSemaphorePermit);
+
+impl ConcurrencyPermit {
+    /// Return the weight held by the permit.
+    pub fn weight(&self) -> u32 {
+        self.0.num_permits() as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConcurrencyBudget;
+
+    #[tokio::test]
+    async fn weighted_budget() {
+        let budget = ConcurrencyBudget::new(3);
+        assert_eq!(budget.limit(), 3);
+
+        let permit = budget.acquire(2).await;
+        assert_eq!(permit.weight(), 2);
+
+        let shared = budget.clone();
+        assert_eq!(shared.in_use(), 2);
+        assert!(shared.try_acquire(2).is_none());
+
+        let other = shared.try_acquire(1).unwrap();
+        assert_eq!(budget.available(), 0);
+
+        drop(permit);
+        drop(other);
+        assert_eq!(budget.available(), 3);
+
+        // weights bigger than the budget are acquired alone
+        assert_eq!(budget.acquire(5).await.weight(), 3);
+    }
+}
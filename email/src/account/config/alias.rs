@@ -0,0 +1,109 @@
+//! Module dedicated to account email aliases.
+//!
+//! This module contains the [`EmailAlias`] patterns used to tell
+//! whether an email address belongs to the user, see
+//! [`AccountConfig::is_own_address`](super::AccountConfig::is_own_address).
+
+use regex::Regex;
+use tracing::debug;
+
+use crate::envelope::address::normalize_addr;
+
+/// The email alias pattern.
+///
+/// Addresses matching an alias are considered as the user's own
+/// addresses, so that they are not treated as foreign recipients
+/// (for example when replying to all).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EmailAlias {
+    /// Match exactly the given address.
+    ///
+    /// The domain is compared case-insensitively.
+    Exact(String),
+
+    /// Match the given address and all its plus-addressing variants.
+    ///
+    /// For example, `me@localhost` matches `me@localhost`,
+    /// `me+tag@localhost` and `me+other@localhost`.
+    PlusAddressing(String),
+
+    /// Match addresses against the given regular expression.
+    ///
+    /// The expression is matched against the normalized address,
+    /// whose domain is lowercased.
+    Regex(String),
+}
+
+impl EmailAlias {
+    /// Return `true` if the given address matches the alias.
+    pub fn matches(&self, addr: &str) -> bool {
+        let addr = normalize_addr(addr);
+
+        match self {
+            Self::Exact(alias) => normalize_addr(alias) == addr,
+            Self::PlusAddressing(alias) => {
+                let alias = normalize_addr(alias);
+                alias == addr || strip_plus_addressing(&addr).is_some_and(|addr| alias == addr)
+            }
+            Self::Regex(regex) => match Regex::new(regex) {
+                Ok(regex) => regex.is_match(&addr),
+                Err(err) => {
+                    debug!(?regex, ?err, "skipping invalid email alias regex");
+                    false
+                }
+            },
+        }
+    }
+}
+
+/// Strip the plus-addressing tag from the given address.
+///
+/// Return `None` if the given address does not contain any tag.
+fn strip_plus_addressing(addr: &str) -> Option<String> {
+    let (local, domain) = addr.rsplit_once('@')?;
+    let (local, _tag) = local.split_once('+')?;
+    Some(format!("{local}@{domain}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmailAlias;
+
+    #[test]
+    fn exact() {
+        let alias = EmailAlias::Exact("me@localhost".into());
+
+        assert!(alias.matches("me@localhost"));
+        assert!(alias.matches("me@LOCALHOST"));
+        assert!(!alias.matches("me+tag@localhost"));
+        assert!(!alias.matches("you@localhost"));
+    }
+
+    #[test]
+    fn plus_addressing() {
+        let alias = EmailAlias::PlusAddressing("me@localhost".into());
+
+        assert!(alias.matches("me@localhost"));
+        assert!(alias.matches("me+tag@localhost"));
+        assert!(alias.matches("me+tag@Localhost"));
+        assert!(!alias.matches("me+tag@remotehost"));
+        assert!(!alias.matches("meme+tag@localhost"));
+    }
+
+    #[test]
+    fn regex() {
+        let alias = EmailAlias::Regex("^(me|myself)@(.*\\.)?localhost$".into());
+
+        assert!(alias.matches("me@localhost"));
+        assert!(alias.matches("myself@sub.localhost"));
+        assert!(!alias.matches("you@localhost"));
+
+        let alias = EmailAlias::Regex("(".into());
+        assert!(!alias.matches("me@localhost"));
+    }
+}
@@ -3,11 +3,14 @@
 //! This module contains the representation of the user's current
 //! account configuration named [`AccountConfig`].
 
+pub mod alias;
 #[cfg(feature = "oauth2")]
 pub mod oauth2;
 pub mod passwd;
 #[cfg(feature = "pgp")]
 pub mod pgp;
+#[cfg(feature = "derive")]
+pub mod portable;
 
 use std::{
     collections::HashMap,
@@ -15,6 +18,7 @@
     ffi::OsStr,
     fs, io,
     path::{Path, PathBuf},
+    time::Duration,
     vec,
 };
 
@@ -23,13 +27,14 @@
 use dirs::download_dir;
 use mail_builder::headers::address::{Address, EmailAddress};
 use mail_parser::Address::*;
-use mml::MimeInterpreterBuilder;
+use mml::{MimeInterpreterBuilder, MmlCompilerBuilder};
 #[cfg(feature = "notify")]
 use notify_rust::Notification;
 use process::Command;
 use shellexpand_utils::{shellexpand_path, shellexpand_str, try_shellexpand_path};
 use tracing::debug;
 
+use self::alias::EmailAlias;
 #[cfg(feature = "pgp")]
 use self::pgp::PgpConfig;
 #[cfg(feature = "sync")]
@@ -37,12 +42,22 @@
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    date::from_mail_parser_to_chrono_datetime,
+    account::{
+        budget::{ConcurrencyBudget, DEFAULT_CONCURRENCY_BUDGET},
+        health::{HealthConfig, HealthLog},
+        runtime::AccountRuntime,
+    },
+    backend::journal::JournalConfig,
+    date::{from_mail_parser_to_chrono_datetime, ClockSource},
     email::config::EmailTextPlainFormat,
-    envelope::{config::EnvelopeConfig, Envelope},
+    envelope::{
+        address::{normalize_addr, AddressDisplayFormat},
+        config::EnvelopeConfig,
+        Envelope,
+    },
     flag::config::FlagConfig,
     folder::{config::FolderConfig, FolderKind, DRAFTS, INBOX, SENT, TRASH},
-    message::config::MessageConfig,
+    message::{config::MessageConfig, send::sanitize::DEFAULT_HEADER_LINE_LEN},
     template::{
         config::TemplateConfig,
         forward::config::{ForwardTemplatePostingStyle, ForwardTemplateSignatureStyle},
@@ -81,6 +96,12 @@ pub struct AccountConfig {
     /// The email address of the user account.
     pub email: String,
 
+    /// The email address aliases of the user account.
+    ///
+    /// Addresses matching one of these aliases are considered as the
+    /// user's own addresses, see [`AccountConfig::is_own_address`].
+    pub email_aliases: Option<Vec<EmailAlias>>,
+
     /// The display name of the user.
     ///
     /// It usually corresponds to the full name of the user.
@@ -92,6 +113,13 @@ pub struct AccountConfig {
     /// a raw string.
     pub signature: Option<String>,
 
+    /// The HTML email signature of the user.
+    ///
+    /// It can be either a path to a file or a raw string. It is used
+    /// instead of [`AccountConfig::signature`] in HTML parts. No
+    /// delimiter is added.
+    pub signature_html: Option<String>,
+
     /// The email signature delimiter of the user signature.
     ///
     /// Defaults to `-- \n`.
@@ -126,9 +154,66 @@ pub struct AccountConfig {
     /// The PGP configuration.
     #[cfg(feature = "pgp")]
     pub pgp: Option<PgpConfig>,
+
+    /// The journal configuration.
+    ///
+    /// When defined, every mutating backend operation is appended
+    /// to the journal file. See [`crate::backend::journal::Journal`].
+    pub journal: Option<JournalConfig>,
+
+    /// The maximum weight of concurrent operations of the account.
+    ///
+    /// Every network connection weighs
+    /// [`CONNECTION_WEIGHT`](crate::account::budget::CONNECTION_WEIGHT),
+    /// whatever component opens it (IMAP clients pool, watchers or
+    /// synchronization workers). Defaults to
+    /// [`DEFAULT_CONCURRENCY_BUDGET`].
+    pub concurrency_budget: Option<u32>,
+
+    /// The health log configuration.
+    ///
+    /// When defined, the outcome of synchronizations and sendings is
+    /// recorded locally, see [`HealthLog`].
+    pub health: Option<HealthConfig>,
+
+    /// The clock used to get the current time and the local
+    /// timezone.
+    ///
+    /// Defaults to the system clock. It is not part of the
+    /// configuration file, it is meant to be injected by clients or
+    /// tests.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub clock: ClockSource,
+
+    /// The runtime state of the account: concurrency budgets, OAuth
+    /// 2.0 token managers, offline mode and health log.
+    ///
+    /// Defaults to a runtime dedicated to this configuration and its
+    /// clones. Clients managing several accounts should share the
+    /// same runtime between them, so that host concurrency budgets
+    /// apply to all accounts. It is not part of the configuration
+    /// file.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub runtime: AccountRuntime,
 }
 
 impl AccountConfig {
+    /// Return `true` if the given address belongs to the user.
+    ///
+    /// The address belongs to the user if it matches either the
+    /// account email address or one of the
+    /// [`AccountConfig::email_aliases`].
+    pub fn is_own_address(&self, addr: &str) -> bool {
+        if normalize_addr(addr) == normalize_addr(&self.email) {
+            return true;
+        }
+
+        self.email_aliases
+            .iter()
+            .flatten()
+            .any(|alias| alias.matches(addr))
+    }
+
     /// Get the signature, including the delimiter.
     ///
     /// Uses the default delimiter `-- \n` in case no delimiter has
@@ -142,18 +227,20 @@ pub fn find_full_signature(&self) -> Option<String> {
         let signature = self.signature.as_ref();
 
         signature.map(|path_or_raw| {
-            let signature = try_shellexpand_path(path_or_raw)
-                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
-                .and_then(fs::read_to_string)
-                .unwrap_or_else(|_err| {
-                    debug!("cannot read signature from path: {_err}");
-                    debug!("{_err:?}");
-                    shellexpand_str(path_or_raw)
-                });
+            let signature = read_signature(path_or_raw);
             format!("{}{}", delim, signature.trim())
         })
     }
 
+    /// Get the HTML signature.
+    ///
+    /// Return `None` if no HTML signature has been defined.
+    pub fn find_html_signature(&self) -> Option<String> {
+        self.signature_html
+            .as_ref()
+            .map(|path_or_raw| read_signature(path_or_raw).trim().to_owned())
+    }
+
     /// Get then expand the downloads directory path.
     ///
     /// Falls back to [`dirs::download_dir`].
@@ -188,6 +275,59 @@ pub fn get_download_file_path(&self, path: impl AsRef<Path>) -> Result<PathBuf>
         rename_file_if_duplicate(&final_path, |path, _count| path.is_file())
     }
 
+    /// Return `true` if the account is in offline mode.
+    pub fn is_offline(&self) -> bool {
+        self.runtime.is_offline(&self.name)
+    }
+
+    /// Enable or disable the offline mode of the account.
+    ///
+    /// When the offline mode is enabled, network-backed features
+    /// (IMAP, SMTP) fail fast with
+    /// [`crate::backend::Error::OfflineError`] instead of waiting for
+    /// connections to time out.
+    pub fn set_offline(&self, offline: bool) {
+        self.runtime.set_offline(&self.name, offline)
+    }
+
+    /// Return an error if the account is in offline mode.
+    pub fn ensure_online(&self) -> crate::backend::Result<()> {
+        if self.is_offline() {
+            Err(crate::backend::Error::OfflineError(self.name.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Find the maximum weight of concurrent operations of the
+    /// account.
+    pub fn find_concurrency_budget(&self) -> u32 {
+        self.concurrency_budget
+            .unwrap_or(DEFAULT_CONCURRENCY_BUDGET)
+    }
+
+    /// Get the concurrency budget of the account.
+    ///
+    /// The budget is a runtime state shared by all contexts of the
+    /// same account, like the offline mode.
+    pub fn concurrency_budget(&self) -> ConcurrencyBudget {
+        let limit = self.find_concurrency_budget();
+        self.runtime.concurrency_budget(&self.name, limit)
+    }
+
+    /// Get the health log of the account.
+    ///
+    /// Return `None` if no health log has been configured. Like the
+    /// concurrency budget, the log is shared by all contexts of the
+    /// same account.
+    pub fn health_log(&self) -> Option<HealthLog> {
+        let config = self.health.as_ref()?;
+        let log = self
+            .runtime
+            .health_log(&self.name, config, self.clock.clone());
+        Some(log)
+    }
+
     /// Return `true` if the synchronization is enabled.
     #[cfg(feature = "sync")]
     pub fn is_sync_enabled(&self) -> bool {
@@ -228,6 +368,34 @@ pub async fn exec_received_envelope_hook(&self, envelope: &Envelope) {
         }
     }
 
+    /// Execute the envelope flags changed hook.
+    #[cfg(feature = "watch")]
+    pub async fn exec_flags_changed_envelope_hook(&self, envelope: &Envelope) {
+        let hook = self
+            .envelope
+            .as_ref()
+            .and_then(|c| c.watch.as_ref())
+            .and_then(|c| c.flags_changed.as_ref());
+
+        if let Some(hook) = hook.as_ref() {
+            self.exec_envelope_hook(hook, envelope).await
+        }
+    }
+
+    /// Execute the envelope removed hook.
+    #[cfg(feature = "watch")]
+    pub async fn exec_removed_envelope_hook(&self, envelope: &Envelope) {
+        let hook = self
+            .envelope
+            .as_ref()
+            .and_then(|c| c.watch.as_ref())
+            .and_then(|c| c.removed.as_ref());
+
+        if let Some(hook) = hook.as_ref() {
+            self.exec_envelope_hook(hook, envelope).await
+        }
+    }
+
     /// Execute the envelope any hook.
     #[cfg(feature = "watch")]
     pub async fn exec_any_envelope_hook(&self, envelope: &Envelope) {
@@ -244,9 +412,9 @@ pub async fn exec_any_envelope_hook(&self, envelope: &Envelope) {
 
     /// Execute the given envelope hook.
     pub async fn exec_envelope_hook(&self, hook: &WatchHook, envelope: &Envelope) {
-        let sender = envelope.from.name.as_deref().unwrap_or(&envelope.from.addr);
+        let sender = envelope.from.display_name();
         let sender_name = envelope.from.name.as_deref().unwrap_or("unknown");
-        let recipient = envelope.to.name.as_deref().unwrap_or(&envelope.to.addr);
+        let recipient = envelope.to.display_name();
         let recipient_name = envelope.to.name.as_deref().unwrap_or("unknown");
 
         if let Some(cmd) = hook.cmd.as_ref() {
@@ -386,6 +554,28 @@ pub fn is_delete_message_style_flag(&self) -> bool {
             .is_some()
     }
 
+    /// Find the message retention policies, as a list of folder
+    /// aliases associated to the maximum age of their messages.
+    pub fn find_message_retention_policies(&self) -> Vec<(String, Duration)> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.delete.as_ref())
+            .and_then(|c| c.retention.as_ref())
+            .map(|c| c.policies())
+            .unwrap_or_default()
+    }
+
+    /// Return `true` if the message retention policy should be
+    /// applied automatically.
+    pub fn should_auto_apply_message_retention(&self) -> bool {
+        self.message
+            .as_ref()
+            .and_then(|c| c.delete.as_ref())
+            .and_then(|c| c.retention.as_ref())
+            .map(|c| c.is_auto())
+            .unwrap_or_default()
+    }
+
     /// Get all folder aliases.
     pub fn get_folder_aliases(&self) -> Option<&HashMap<String, String>> {
         self.folder.as_ref().and_then(|c| c.aliases.as_ref())
@@ -460,6 +650,17 @@ pub fn get_message_read_headers(&self) -> Vec<String> {
             ])
     }
 
+    /// Get the message reading fallback charsets if defined,
+    /// otherwise return an empty list.
+    pub fn get_message_read_charset_fallbacks(&self) -> Vec<String> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.read.as_ref())
+            .and_then(|c| c.charset_fallbacks.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Get the message writing headers if defined, otherwise return
     /// the default ones.
     pub fn get_message_write_headers(&self) -> Vec<String> {
@@ -477,6 +678,17 @@ pub fn get_message_write_headers(&self) -> Vec<String> {
             ])
     }
 
+    /// Find the stylesheet inlined into HTML parts rendered from
+    /// markdown message bodies.
+    #[cfg(feature = "markdown")]
+    pub fn find_message_write_markdown_css(&self) -> Option<&str> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.write.as_ref())
+            .and_then(|c| c.markdown.as_ref())
+            .and_then(|c| c.css.as_deref())
+    }
+
     /// Find the message pre-send hook.
     pub fn find_message_pre_send_hook(&self) -> Option<&Command> {
         self.message
@@ -485,6 +697,14 @@ pub fn find_message_pre_send_hook(&self) -> Option<&Command> {
             .and_then(|c| c.pre_hook.as_ref())
     }
 
+    /// Find the hook called when a message is too large to be sent.
+    pub fn find_message_send_too_large_hook(&self) -> Option<&Command> {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.too_large_hook.as_ref())
+    }
+
     /// Return `true` if a copy of sent messages should be saved in
     /// the sent folder.
     pub fn should_save_copy_sent_message(&self) -> bool {
@@ -495,11 +715,32 @@ pub fn should_save_copy_sent_message(&self) -> bool {
             .unwrap_or(true)
     }
 
+    /// Return `true` if the Bcc headers of messages being sent should
+    /// be stripped.
+    pub fn should_strip_bcc_sent_message(&self) -> bool {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.strip_bcc)
+            .unwrap_or(true)
+    }
+
+    /// Find the soft limit of header lines length of messages being
+    /// sent.
+    pub fn find_sent_message_header_line_length(&self) -> usize {
+        self.message
+            .as_ref()
+            .and_then(|c| c.send.as_ref())
+            .and_then(|c| c.header_line_length)
+            .unwrap_or(DEFAULT_HEADER_LINE_LEN)
+    }
+
     /// Generate a template interpreter with prefilled options from
     /// the current user account configuration.
     pub fn generate_tpl_interpreter(&self) -> MimeInterpreterBuilder {
-        let builder =
-            MimeInterpreterBuilder::new().with_save_attachments_dir(self.get_downloads_dir());
+        let builder = MimeInterpreterBuilder::new()
+            .with_save_attachments_dir(self.get_downloads_dir())
+            .with_charset_fallbacks(self.get_message_read_charset_fallbacks());
 
         #[cfg(feature = "pgp")]
         if let Some(ref pgp) = self.pgp {
@@ -509,6 +750,34 @@ pub fn generate_tpl_interpreter(&self) -> MimeInterpreterBuilder {
         builder
     }
 
+    /// Generate a template compiler with prefilled options from the
+    /// current user account configuration.
+    pub fn generate_tpl_compiler(&self) -> MmlCompilerBuilder {
+        #[allow(unused_mut)]
+        let mut builder = MmlCompilerBuilder::new();
+
+        #[cfg(feature = "markdown")]
+        if let Some(css) = self.find_message_write_markdown_css() {
+            builder.set_markdown_css(css);
+        }
+
+        #[cfg(feature = "pgp")]
+        if let Some(ref pgp) = self.pgp {
+            builder.set_pgp(pgp.clone());
+        }
+
+        builder
+    }
+
+    /// Get the envelope address display format, otherwise return the
+    /// default one.
+    pub fn get_envelope_address_format(&self) -> AddressDisplayFormat {
+        self.envelope
+            .as_ref()
+            .and_then(|c| c.address_format)
+            .unwrap_or_default()
+    }
+
     /// Get the envelope listing datetime format, otherwise return the
     /// default one.
     pub fn get_envelope_list_datetime_fmt(&self) -> String {
@@ -691,6 +960,19 @@ pub(crate) fn rename_file_if_duplicate(
     Ok(file_path)
 }
 
+/// Read the signature from the given path, or use it as a raw
+/// signature if the path cannot be read.
+fn read_signature(path_or_raw: &str) -> String {
+    try_shellexpand_path(path_or_raw)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+        .and_then(fs::read_to_string)
+        .unwrap_or_else(|_err| {
+            debug!("cannot read signature from path: {_err}");
+            debug!("{_err:?}");
+            shellexpand_str(path_or_raw)
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
@@ -731,4 +1013,79 @@ fn rename_file_if_duplicate() {
             Ok(path) if path == PathBuf::from("downloads/file.ext_5.ext2")
         ));
     }
+
+    #[test]
+    fn offline_mode() {
+        use super::AccountConfig;
+        use crate::backend::Error;
+
+        let config = AccountConfig {
+            name: "account".into(),
+            ..Default::default()
+        };
+
+        // clones share the same runtime, hence the same offline mode
+        let clone = config.clone();
+        clone.set_offline(true);
+        assert!(config.is_offline());
+        assert!(matches!(
+            config.ensure_online(),
+            Err(Error::OfflineError(name)) if name == "account"
+        ));
+
+        // other runtimes are not affected
+        let other = AccountConfig {
+            name: "account".into(),
+            ..Default::default()
+        };
+        assert!(other.ensure_online().is_ok());
+
+        clone.set_offline(false);
+        assert!(config.ensure_online().is_ok());
+    }
+
+    #[cfg(feature = "markdown")]
+    #[tokio::test]
+    async fn generate_tpl_compiler_markdown() {
+        use concat_with::concat_line;
+        use mail_parser::MessageParser;
+
+        use super::AccountConfig;
+        use crate::message::{
+            add::config::MessageWriteConfig, config::MessageConfig, markdown::MarkdownConfig,
+        };
+
+        let config = AccountConfig {
+            message: Some(MessageConfig {
+                write: Some(MessageWriteConfig {
+                    markdown: Some(MarkdownConfig {
+                        css: Some("strong { color: red }".into()),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let tpl = concat_line!(
+            "From: me@localhost",
+            "To: you@localhost",
+            "Subject: markdown",
+            "",
+            "<#part type=text/markdown>",
+            "Hello, **world**!",
+            "<#/part>",
+        );
+
+        let compiler = config.generate_tpl_compiler().build(tpl).unwrap();
+        let msg = compiler.compile().await.unwrap().into_vec().unwrap();
+        let msg = MessageParser::new().parse(&msg).unwrap();
+
+        assert_eq!(msg.body_text(0).unwrap().trim(), "Hello, **world**!");
+        assert_eq!(
+            msg.body_html(0).unwrap().trim(),
+            "<p>Hello, <strong style=\"color: red\">world</strong>!</p>"
+        );
+    }
 }
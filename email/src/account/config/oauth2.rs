@@ -3,14 +3,17 @@
 //! This module contains everything related to OAuth 2.0
 //! configuration.
 
-use std::{fmt, io, net::TcpListener, vec};
+use std::{fmt, io, net::TcpListener, sync::Arc, vec};
 
-use oauth::v2_0::{AuthorizationCodeGrant, Client, RefreshAccessToken};
+use oauth::v2_0::{
+    AuthorizationCodeGrant, Client, Provider, ProviderMetadata, SecretTokenStorage, TokenManager,
+};
 use secret::Secret;
 use tracing::debug;
 
 #[doc(inline)]
 pub use super::{Error, Result};
+use crate::account::runtime::AccountRuntime;
 
 /// The OAuth 2.0 configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -32,10 +35,31 @@ pub struct OAuth2Config {
     /// [Section 2.2](https://datatracker.ietf.org/doc/html/rfc6749#section-2.2).
     pub client_secret: Option<Secret>,
 
+    /// Well-known provider preset.
+    ///
+    /// When defined, the authorization endpoint, the token endpoint
+    /// and the scopes default to the ones of the provider.
+    pub provider: Option<Provider>,
+
+    /// URL of the OpenID Connect issuer.
+    ///
+    /// When defined, the authorization and the token endpoints
+    /// default to the ones exposed by the issuer's discovery document
+    /// (`/.well-known/openid-configuration`).
+    pub issuer: Option<String>,
+
     /// URL of the authorization server's authorization endpoint.
+    ///
+    /// Can be omitted when either [`OAuth2Config::provider`] or
+    /// [`OAuth2Config::issuer`] is defined.
+    #[cfg_attr(feature = "derive", serde(default))]
     pub auth_url: String,
 
     /// URL of the authorization server's token endpoint.
+    ///
+    /// Can be omitted when either [`OAuth2Config::provider`] or
+    /// [`OAuth2Config::issuer`] is defined.
+    #[cfg_attr(feature = "derive", serde(default))]
     pub token_url: String,
 
     /// Access token returned by the token endpoint and used to access
@@ -54,6 +78,17 @@ pub struct OAuth2Config {
     )]
     pub refresh_token: Secret,
 
+    /// Expiration time of the access token, as a UNIX timestamp in
+    /// seconds.
+    ///
+    /// Saved along with the access token when the authorization
+    /// server exposes it.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Secret::is_empty")
+    )]
+    pub expires_at: Secret,
+
     /// Enable the [PKCE](https://datatracker.ietf.org/doc/html/rfc7636) protection.
     /// The value must have a minimum length of 43 characters and a maximum length of 128 characters.
     /// Each character must be ASCII alphanumeric or one of the characters “-” / “.” / “_” / “~”.
@@ -78,7 +113,7 @@ pub fn get_first_available_port() -> Result<u16> {
             .ok_or(Error::GetAvailablePortError)
     }
 
-    /// Resets the three secrets of the OAuth 2.0 configuration.
+    /// Resets the secrets of the OAuth 2.0 configuration.
     pub async fn reset(&self) -> Result<()> {
         if let Some(secret) = self.client_secret.as_ref() {
             secret
@@ -96,6 +131,13 @@ pub async fn reset(&self) -> Result<()> {
             .await
             .map_err(Error::DeleteRefreshTokenOauthError)?;
 
+        if let Ok(Some(_)) = self.expires_at.find().await {
+            self.expires_at
+                .delete_if_keyring()
+                .await
+                .map_err(Error::DeleteExpiresAtOauthError)?;
+        }
+
         Ok(())
     }
 
@@ -110,21 +152,6 @@ pub async fn configure(
             return Ok(());
         }
 
-        let redirect_scheme = match self.redirect_scheme.as_ref() {
-            Some(scheme) => scheme.clone(),
-            None => "http".into(),
-        };
-
-        let redirect_host = match self.redirect_host.as_ref() {
-            Some(host) => host.clone(),
-            None => OAuth2Config::LOCALHOST.to_owned(),
-        };
-
-        let redirect_port = match self.redirect_port {
-            Some(port) => port,
-            None => OAuth2Config::get_first_available_port()?,
-        };
-
         let client_secret = match self.client_secret.as_ref() {
             None => None,
             Some(secret) => Some(match secret.find().await {
@@ -143,16 +170,7 @@ pub async fn configure(
             }?),
         };
 
-        let client = Client::new(
-            self.client_id.clone(),
-            client_secret,
-            self.auth_url.clone(),
-            self.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-        .map_err(Error::BuildOauthClientError)?;
+        let client = self.build_client(client_secret).await?;
 
         let mut auth_code_grant = AuthorizationCodeGrant::new();
 
@@ -160,7 +178,7 @@ pub async fn configure(
             auth_code_grant = auth_code_grant.with_pkce();
         }
 
-        for scope in self.scopes.clone() {
+        for scope in self.scopes() {
             auth_code_grant = auth_code_grant.with_scope(scope);
         }
 
@@ -180,6 +198,13 @@ pub async fn configure(
             .await
             .map_err(Error::SetAccessTokenOauthError)?;
 
+        // the expiration time of the previous access token, if any,
+        // does not apply anymore
+        self.expires_at
+            .set_if_keyring("")
+            .await
+            .map_err(Error::SetAccessTokenOauthError)?;
+
         if let Some(refresh_token) = &refresh_token {
             self.refresh_token
                 .set_if_keyring(refresh_token)
@@ -190,9 +215,59 @@ pub async fn configure(
         Ok(())
     }
 
-    /// Runs the refresh access token OAuth 2.0 flow by exchanging a
-    /// refresh token with a new pair of access/refresh token.
-    pub async fn refresh_access_token(&self) -> Result<String> {
+    /// Returns the URLs of the authorization and the token endpoints.
+    ///
+    /// Explicit URLs take precedence over the ones of the provider
+    /// preset, which take precedence over the discovered ones.
+    pub async fn endpoints(&self) -> Result<(String, String)> {
+        let mut auth_url = self.auth_url.clone();
+        let mut token_url = self.token_url.clone();
+
+        if !auth_url.is_empty() && !token_url.is_empty() {
+            return Ok((auth_url, token_url));
+        }
+
+        let (default_auth_url, default_token_url) = match (&self.provider, &self.issuer) {
+            (Some(provider), _) => (
+                provider.auth_url().to_owned(),
+                provider.token_url().to_owned(),
+            ),
+            (None, Some(issuer)) => {
+                let metadata = ProviderMetadata::discover(issuer)
+                    .await
+                    .map_err(Error::DiscoverOauthProviderError)?;
+                (metadata.authorization_endpoint, metadata.token_endpoint)
+            }
+            (None, None) => return Err(Error::MissingOauthEndpointsError),
+        };
+
+        if auth_url.is_empty() {
+            auth_url = default_auth_url;
+        }
+
+        if token_url.is_empty() {
+            token_url = default_token_url;
+        }
+
+        Ok((auth_url, token_url))
+    }
+
+    /// Returns the access token scopes.
+    ///
+    /// Defaults to the scopes of the provider preset, if any.
+    pub fn scopes(&self) -> Vec<String> {
+        let scopes: Vec<String> = self.scopes.clone().into_iter().collect();
+
+        match &self.provider {
+            Some(provider) if scopes.is_empty() => {
+                provider.scopes().iter().map(ToString::to_string).collect()
+            }
+            _ => scopes,
+        }
+    }
+
+    /// Builds the OAuth 2.0 client using the given client secret.
+    async fn build_client(&self, client_secret: Option<String>) -> Result<Client> {
         let redirect_scheme = match self.redirect_scheme.as_ref() {
             Some(scheme) => scheme.clone(),
             None => "http".into(),
@@ -208,6 +283,33 @@ pub async fn refresh_access_token(&self) -> Result<String> {
             None => OAuth2Config::get_first_available_port()?,
         };
 
+        let (auth_url, token_url) = self.endpoints().await?;
+
+        Client::new(
+            self.client_id.clone(),
+            client_secret,
+            auth_url,
+            token_url,
+            redirect_scheme,
+            redirect_host,
+            redirect_port,
+        )
+        .map_err(Error::BuildOauthClientError)
+    }
+
+    /// Returns the token storage backed by the secrets of the
+    /// configuration.
+    pub fn token_storage(&self) -> SecretTokenStorage {
+        SecretTokenStorage::new(self.access_token.clone(), self.refresh_token.clone())
+            .with_expires_at(self.expires_at.clone())
+    }
+
+    /// Builds a token manager backed by the secrets of the
+    /// configuration.
+    ///
+    /// Prefer [`OAuth2Config::token_manager`], which shares the
+    /// manager between configurations.
+    pub async fn build_token_manager(&self) -> Result<TokenManager> {
         let client_secret = match self.client_secret.as_ref() {
             None => None,
             Some(secret) => {
@@ -219,41 +321,18 @@ pub async fn refresh_access_token(&self) -> Result<String> {
             }
         };
 
-        let client = Client::new(
-            self.client_id.clone(),
-            client_secret,
-            self.auth_url.clone(),
-            self.token_url.clone(),
-            redirect_scheme,
-            redirect_host,
-            redirect_port,
-        )
-        .map_err(Error::BuildOauthClientError)?;
-
-        let refresh_token = self
-            .refresh_token
-            .get()
-            .await
-            .map_err(Error::GetRefreshTokenOauthError)?;
-
-        let (access_token, refresh_token) = RefreshAccessToken::new()
-            .refresh_access_token(&client, refresh_token)
-            .await
-            .map_err(Error::RefreshAccessTokenOauthError)?;
+        let client = self.build_client(client_secret).await?;
+        Ok(TokenManager::new(client, self.token_storage()))
+    }
 
-        self.access_token
-            .set_if_keyring(&access_token)
+    /// Runs the refresh access token OAuth 2.0 flow by exchanging a
+    /// refresh token with a new pair of access/refresh token.
+    pub async fn refresh_access_token(&self) -> Result<String> {
+        self.build_token_manager()
+            .await?
+            .refresh_access_token(None)
             .await
-            .map_err(Error::SetAccessTokenOauthError)?;
-
-        if let Some(refresh_token) = &refresh_token {
-            self.refresh_token
-                .set_if_keyring(refresh_token)
-                .await
-                .map_err(Error::SetRefreshTokenOauthError)?;
-        }
-
-        Ok(access_token)
+            .map_err(Error::RefreshAccessTokenOauthError)
     }
 
     /// Returns the access token if existing, otherwise returns an
@@ -264,6 +343,28 @@ pub async fn access_token(&self) -> Result<String> {
             .await
             .map_err(Error::GetAccessTokenOauthError)
     }
+
+    /// Returns the token manager shared by all configurations of
+    /// the given runtime using the same OAuth 2.0 client for the
+    /// given login.
+    ///
+    /// This is typically the case of the IMAP and the SMTP
+    /// configurations of the same account. Sharing the manager makes
+    /// sure that only one refresh happens at a time.
+    pub async fn token_manager(
+        &self,
+        runtime: &AccountRuntime,
+        login: &str,
+    ) -> Result<Arc<TokenManager>> {
+        let key = format!("{login}|{}|{}", self.client_id, self.token_url);
+
+        if let Some(manager) = runtime.oauth2_token_manager(&key) {
+            return Ok(manager);
+        }
+
+        let manager = self.build_token_manager().await?;
+        Ok(runtime.insert_oauth2_token_manager(key, manager))
+    }
 }
 
 /// Method for presenting an OAuth 2.0 bearer token to a service for
@@ -59,6 +59,9 @@ pub async fn reset(&self) -> Result<()> {
                 .delete_secret()
                 .await
                 .map_err(Error::DeletePgpKeyFromKeyringError)?,
+            // keys held by gpg-agent are managed by GnuPG
+            #[cfg(unix)]
+            NativePgpSecretKey::Agent(..) => (),
         };
 
         Ok(())
@@ -110,6 +113,8 @@ pub async fn configure(
                     .await
                     .map_err(Error::SetPublicKeyToKeyringError)?;
             }
+            #[cfg(unix)]
+            NativePgpSecretKey::Agent(..) => (),
         }
 
         Ok(())
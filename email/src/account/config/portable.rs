@@ -0,0 +1,227 @@
+//! # Portable account configuration
+//!
+//! Module dedicated to the export and the import of account
+//! configurations between machines, see [`PortableAccountConfig`].
+
+#[cfg(any(feature = "imap", feature = "smtp", feature = "pgp-native"))]
+use secret::Secret;
+
+#[cfg(feature = "oauth2")]
+use super::oauth2::OAuth2Config;
+#[cfg(all(
+    feature = "keyring",
+    any(feature = "imap", feature = "smtp", feature = "pgp-native")
+))]
+use super::Error;
+use super::{AccountConfig, Result};
+#[cfg(feature = "imap")]
+use crate::imap::config::{ImapAuthConfig, ImapConfig};
+#[cfg(feature = "maildir")]
+use crate::maildir::config::MaildirConfig;
+#[cfg(feature = "notmuch")]
+use crate::notmuch::config::NotmuchConfig;
+#[cfg(feature = "sendmail")]
+use crate::sendmail::config::SendmailConfig;
+#[cfg(feature = "smtp")]
+use crate::smtp::config::{SmtpAuthConfig, SmtpConfig};
+
+/// The portable account configuration.
+///
+/// It holds the account configuration together with its backend
+/// configurations. Raw secrets are replaced by keyring references
+/// (or removed when the `keyring` cargo feature is disabled), so the
+/// configuration can be serialized to TOML or JSON and moved to
+/// another machine without leaking credentials. Secrets are then
+/// asked again when configuring the account on the new machine.
+///
+/// Command-based secrets are kept as they are, since they do not
+/// contain any secret by themselves.
+#[derive(Clone, Debug, Default, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PortableAccountConfig {
+    /// The account configuration.
+    pub account: AccountConfig,
+
+    /// The backend configurations of the account.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backends: Vec<PortableBackendConfig>,
+}
+
+/// The portable backend configuration.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum PortableBackendConfig {
+    #[cfg(feature = "imap")]
+    Imap(ImapConfig),
+    #[cfg(feature = "maildir")]
+    Maildir(MaildirConfig),
+    #[cfg(feature = "notmuch")]
+    Notmuch(NotmuchConfig),
+    #[cfg(feature = "smtp")]
+    Smtp(SmtpConfig),
+    #[cfg(feature = "sendmail")]
+    Sendmail(SendmailConfig),
+}
+
+impl AccountConfig {
+    /// Export the account configuration, including the given backend
+    /// configurations, to a portable configuration.
+    ///
+    /// Raw secrets are replaced by keyring entries named after the
+    /// account, using the same names as `replace_empty_secrets`.
+    /// Secret values are not moved into the keyring.
+    pub fn to_portable(
+        &self,
+        backends: impl IntoIterator<Item = PortableBackendConfig>,
+    ) -> Result<PortableAccountConfig> {
+        #[cfg_attr(
+            not(any(feature = "imap", feature = "smtp", feature = "pgp-native")),
+            allow(unused_variables)
+        )]
+        let name = self.name.as_str();
+        #[cfg_attr(not(feature = "pgp-native"), allow(unused_mut))]
+        let mut account = self.clone();
+
+        #[cfg(feature = "pgp-native")]
+        if let Some(super::pgp::PgpConfig::Native(config)) = account.pgp.as_mut() {
+            use mml::pgp::NativePgpSecretKey;
+
+            if let NativePgpSecretKey::Raw(_) = config.secret_key {
+                #[cfg(feature = "keyring")]
+                let key = secret::keyring::KeyringEntry::try_new(format!("{name}-pgp-secret-key"))
+                    .map(NativePgpSecretKey::Keyring)
+                    .map_err(|err| Error::ReplaceSecretWithKeyringError(err.into()))?;
+                #[cfg(not(feature = "keyring"))]
+                let key = NativePgpSecretKey::None;
+
+                config.secret_key = key;
+            }
+
+            let entry = format!("{name}-pgp-secret-key-passphrase");
+            make_portable(&mut config.secret_key_passphrase, entry)?;
+        }
+
+        let mut backends: Vec<_> = backends.into_iter().collect();
+
+        for backend in &mut backends {
+            match backend {
+                #[cfg(feature = "imap")]
+                PortableBackendConfig::Imap(config) => match &mut config.auth {
+                    ImapAuthConfig::Password(passwd) => {
+                        make_portable(passwd, format!("{name}-imap-passwd"))?;
+                    }
+                    #[cfg(feature = "oauth2")]
+                    ImapAuthConfig::OAuth2(config) => {
+                        make_oauth2_portable(config, &format!("{name}-imap"))?;
+                    }
+                },
+                #[cfg(feature = "smtp")]
+                PortableBackendConfig::Smtp(config) => match &mut config.auth {
+                    SmtpAuthConfig::Password(passwd) => {
+                        make_portable(passwd, format!("{name}-smtp-passwd"))?;
+                    }
+                    #[cfg(feature = "oauth2")]
+                    SmtpAuthConfig::OAuth2(config) => {
+                        make_oauth2_portable(config, &format!("{name}-smtp"))?;
+                    }
+                },
+                #[allow(unreachable_patterns)]
+                _ => (),
+            }
+        }
+
+        Ok(PortableAccountConfig { account, backends })
+    }
+
+    /// Import the account configuration and its backend
+    /// configurations from a portable configuration.
+    ///
+    /// Secrets stored in the keyring still need to be configured on
+    /// the current machine.
+    pub fn from_portable(portable: PortableAccountConfig) -> (Self, Vec<PortableBackendConfig>) {
+        (portable.account, portable.backends)
+    }
+}
+
+/// Replace the given secret by a keyring entry if it is raw.
+#[cfg(any(feature = "imap", feature = "smtp", feature = "pgp-native"))]
+fn make_portable(secret: &mut Secret, entry: String) -> Result<()> {
+    if let Secret::Raw(_) = secret {
+        #[cfg(feature = "keyring")]
+        {
+            *secret = Secret::try_new_keyring_entry(entry)
+                .map_err(Error::ReplaceSecretWithKeyringError)?;
+        }
+
+        #[cfg(not(feature = "keyring"))]
+        {
+            let _ = entry;
+            *secret = Secret::Empty;
+        }
+    }
+
+    Ok(())
+}
+
+/// Replace the raw secrets of the given OAuth 2.0 configuration by
+/// keyring entries.
+#[cfg(feature = "oauth2")]
+fn make_oauth2_portable(config: &mut OAuth2Config, prefix: &str) -> Result<()> {
+    if let Some(secret) = config.client_secret.as_mut() {
+        make_portable(secret, format!("{prefix}-oauth2-client-secret"))?;
+    }
+
+    let entry = format!("{prefix}-oauth2-access-token");
+    make_portable(&mut config.access_token, entry)?;
+    let entry = format!("{prefix}-oauth2-refresh-token");
+    make_portable(&mut config.refresh_token, entry)?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "imap", feature = "keyring"))]
+mod tests {
+    use secret::Secret;
+
+    use super::PortableBackendConfig;
+    use crate::{
+        account::config::{passwd::PasswordConfig, AccountConfig},
+        imap::config::{ImapAuthConfig, ImapConfig},
+    };
+
+    #[test]
+    fn raw_secrets_replaced_by_keyring() {
+        let account = AccountConfig {
+            name: "example".into(),
+            email: "me@localhost".into(),
+            ..Default::default()
+        };
+
+        let imap = ImapConfig {
+            host: "localhost".into(),
+            auth: ImapAuthConfig::Password(PasswordConfig(Secret::new_raw("passwd"))),
+            ..Default::default()
+        };
+
+        let portable = account
+            .to_portable([PortableBackendConfig::Imap(imap)])
+            .unwrap();
+
+        let (imported, backends) = AccountConfig::from_portable(portable);
+        assert_eq!(imported, account);
+
+        let [PortableBackendConfig::Imap(imap)] = backends.as_slice() else {
+            panic!("expected one IMAP backend");
+        };
+
+        let ImapAuthConfig::Password(passwd) = &imap.auth else {
+            panic!("expected password authentication");
+        };
+
+        let Secret::Keyring(entry) = &passwd.0 else {
+            panic!("expected keyring secret");
+        };
+
+        assert_eq!(entry.key, "example-imap-passwd");
+    }
+}
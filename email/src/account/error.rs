@@ -31,9 +31,17 @@ pub enum Error {
     #[error("cannot create oauth2 client")]
     BuildOauthClientError(#[source] oauth::v2_0::Error),
     #[cfg(feature = "oauth2")]
+    #[error("cannot discover oauth2 provider endpoints")]
+    DiscoverOauthProviderError(#[source] oauth::v2_0::Error),
+    #[error("cannot find oauth2 endpoints: missing auth/token urls, provider or issuer")]
+    MissingOauthEndpointsError,
+    #[cfg(feature = "oauth2")]
     #[error("cannot wait for oauth2 redirection error")]
     WaitForOauthRedirectionError(#[source] oauth::v2_0::Error),
 
+    #[error("cannot replace secret with keyring entry")]
+    ReplaceSecretWithKeyringError(#[source] secret::Error),
+
     #[error("cannot get oauth2 access token from global keyring")]
     GetAccessTokenOauthError(#[source] secret::Error),
     #[error("cannot set oauth2 access token")]
@@ -50,6 +58,11 @@ pub enum Error {
     SetRefreshTokenOauthError(#[source] secret::Error),
     #[error("cannot delete oauth2 refresh token from global keyring")]
     DeleteRefreshTokenOauthError(#[source] secret::Error),
+    #[error("cannot delete oauth2 access token expiration time from global keyring")]
+    DeleteExpiresAtOauthError(#[source] secret::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot get oauth2 access token")]
+    GetAccessTokenFromManagerOauthError(#[source] oauth::v2_0::Error),
 
     #[error("cannot get oauth2 client secret from user")]
     GetClientSecretFromUserOauthError(#[source] io::Error),
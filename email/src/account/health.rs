@@ -0,0 +1,376 @@
+//! # Account health
+//!
+//! Module dedicated to the account health log, see [`HealthLog`].
+//!
+//! The health log records the outcome of the last synchronizations
+//! and sendings of an account, so that clients can display
+//! statuses like "last synced 5 min ago, 3 failures since". The log
+//! is opt-in and strictly local: it is a ring buffer persisted to a
+//! file of the user's choice, nothing is sent anywhere.
+//!
+//! Each line of the file is a JSON object containing the RFC 3339
+//! timestamp, the operation (`sync` or `send`) and the error message
+//! of an event, if any:
+//!
+//! ```json
+//! {"timestamp":"2024-01-01T12:00:00Z","operation":"sync","error":"connection refused"}
+//! ```
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    io,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use shellexpand_utils::shellexpand_path;
+use tracing::warn;
+
+use crate::{
+    backend::middleware::{BackendMiddleware, BackendNext, BackendOperation},
+    date::ClockSource,
+    jsonl::{self, JsonLinesWriter},
+    AnyResult,
+};
+
+/// The default number of events kept in the health log.
+pub const DEFAULT_HEALTH_CAPACITY: usize = 100;
+
+/// The health log configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case", deny_unknown_fields)
+)]
+pub struct HealthConfig {
+    /// The path of the health log file.
+    ///
+    /// Path is shell-expanded, which means environment variables and
+    /// tilde `~` are replaced by their values.
+    pub path: PathBuf,
+
+    /// The number of events kept in the health log.
+    ///
+    /// Defaults to 100.
+    pub capacity: Option<usize>,
+}
+
+impl HealthConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity.unwrap_or(DEFAULT_HEALTH_CAPACITY).max(1)
+    }
+}
+
+/// The operation recorded in the health log.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthOperation {
+    /// The synchronization of the account.
+    Sync,
+
+    /// The sending of a message.
+    Send,
+}
+
+impl HealthOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Sync => "sync",
+            Self::Send => "send",
+        }
+    }
+}
+
+impl fmt::Display for HealthOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for HealthOperation {
+    type Err = String;
+
+    fn from_str(op: &str) -> Result<Self, Self::Err> {
+        match op {
+            "sync" => Ok(Self::Sync),
+            "send" => Ok(Self::Send),
+            op => Err(format!("invalid health operation {op}")),
+        }
+    }
+}
+
+/// The outcome of an operation, as recorded in the health log.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct HealthEvent {
+    /// When the operation ended.
+    pub timestamp: DateTime<Utc>,
+
+    /// The operation.
+    pub operation: HealthOperation,
+
+    /// The error message, if the operation failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl HealthEvent {
+    /// Return `true` if the operation succeeded.
+    pub fn is_success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// The health status of an account, computed from its health log.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct HealthStatus {
+    /// When the last successful synchronization ended.
+    pub last_sync: Option<DateTime<Utc>>,
+
+    /// When the last message has been successfully sent.
+    pub last_send: Option<DateTime<Utc>>,
+
+    /// The number of failures since the last success, whatever the
+    /// operation.
+    pub consecutive_failures: usize,
+
+    /// The last failure, if any.
+    pub last_failure: Option<HealthEvent>,
+}
+
+#[derive(Debug)]
+struct HealthLogState {
+    config: HealthConfig,
+    events: VecDeque<HealthEvent>,
+}
+
+/// The health log of an account.
+///
+/// The log is a runtime state shared by all contexts of the same
+/// account, see [`AccountRuntime`](super::runtime::AccountRuntime). Cloning the log shares the
+/// same events.
+#[derive(Clone, Debug)]
+pub struct HealthLog {
+    clock: ClockSource,
+    writer: JsonLinesWriter,
+    state: Arc<Mutex<HealthLogState>>,
+}
+
+impl HealthLog {
+    /// Create a new health log, loading events from the configured
+    /// file if it exists.
+    pub fn new(config: &HealthConfig, clock: ClockSource) -> Self {
+        let path = shellexpand_path(&config.path);
+        let capacity = config.capacity();
+
+        let mut events = match jsonl::read(&path) {
+            Ok(events) => VecDeque::from(events),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => VecDeque::new(),
+            Err(err) => {
+                warn!(?path, ?err, "cannot load health log, starting from scratch");
+                VecDeque::new()
+            }
+        };
+
+        while events.len() > capacity {
+            events.pop_front();
+        }
+
+        let state = HealthLogState {
+            config: config.clone(),
+            events,
+        };
+
+        Self {
+            clock,
+            writer: JsonLinesWriter::new(path),
+            state: Arc::new(Mutex::new(state)),
+        }
+    }
+
+    /// Return the configuration of the log.
+    pub fn config(&self) -> HealthConfig {
+        self.lock().config.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HealthLogState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Record the outcome of the given operation.
+    ///
+    /// The event is added straight away, the returned future
+    /// persists the log. Persistence errors are logged, not returned:
+    /// health must never make an operation fail.
+    pub fn record<T, E: fmt::Display>(
+        &self,
+        operation: HealthOperation,
+        result: &Result<T, E>,
+    ) -> impl Future<Output = ()> + Send + '_ {
+        let event = HealthEvent {
+            timestamp: self.clock.now(),
+            operation,
+            error: result.as_ref().err().map(ToString::to_string),
+        };
+
+        let mut state = self.lock();
+
+        if state.events.len() >= state.config.capacity() {
+            state.events.pop_front();
+        }
+
+        state.events.push_back(event);
+        drop(state);
+
+        self.save()
+    }
+
+    /// Return the recorded events, from the oldest to the newest.
+    pub fn events(&self) -> Vec<HealthEvent> {
+        self.lock().events.iter().cloned().collect()
+    }
+
+    /// Return the last event of the given operation, if any.
+    pub fn last_event(&self, operation: HealthOperation) -> Option<HealthEvent> {
+        self.lock()
+            .events
+            .iter()
+            .rev()
+            .find(|event| event.operation == operation)
+            .cloned()
+    }
+
+    /// Compute the health status of the account.
+    pub fn status(&self) -> HealthStatus {
+        let state = self.lock();
+        let mut status = HealthStatus::default();
+        let mut failing = true;
+
+        for event in state.events.iter().rev() {
+            match (event.is_success(), event.operation) {
+                (true, HealthOperation::Sync) => {
+                    status.last_sync.get_or_insert(event.timestamp);
+                }
+                (true, HealthOperation::Send) => {
+                    status.last_send.get_or_insert(event.timestamp);
+                }
+                (false, _) => {
+                    if status.last_failure.is_none() {
+                        status.last_failure = Some(event.clone());
+                    }
+                }
+            }
+
+            if failing && event.is_success() {
+                failing = false;
+            } else if failing {
+                status.consecutive_failures += 1;
+            }
+        }
+
+        status
+    }
+
+    /// Remove all the recorded events.
+    pub async fn clear(&self) {
+        self.lock().events.clear();
+        self.save().await
+    }
+
+    /// Persist the events.
+    ///
+    /// Events are taken once the writer is ready, so that concurrent
+    /// saves always end up with the latest events.
+    async fn save(&self) {
+        if let Err(err) = self.writer.replace(|| self.events()).await {
+            let path = self.writer.path();
+            warn!(?path, ?err, "cannot save health log");
+        }
+    }
+}
+
+#[async_trait]
+impl BackendMiddleware for HealthLog {
+    async fn handle<'a>(&self, op: &'a BackendOperation, next: BackendNext<'a>) -> AnyResult<()> {
+        if op.feature != "send_message" {
+            return next.run().await;
+        }
+
+        let result = next.run().await;
+        self.record(HealthOperation::Send, &result).await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::{HealthConfig, HealthLog, HealthOperation};
+    use crate::date::FixedClock;
+
+    #[tokio::test]
+    async fn status_and_persistence() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = HealthConfig {
+            path: dir.path().join("health.jsonl"),
+            capacity: Some(4),
+        };
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let clock = FixedClock::new(now, FixedOffset::east_opt(0).unwrap());
+        let log = HealthLog::new(&config, clock.into());
+
+        log.record(HealthOperation::Sync, &Ok::<_, String>(()))
+            .await;
+        log.record(HealthOperation::Send, &Ok::<_, String>(()))
+            .await;
+        log.record(HealthOperation::Sync, &Err::<(), _>("timed\tout"))
+            .await;
+        log.record(HealthOperation::Sync, &Err::<(), _>("refused"))
+            .await;
+        log.record(HealthOperation::Send, &Err::<(), _>("refused"))
+            .await;
+
+        let status = log.status();
+        assert_eq!(log.events().len(), 4);
+        assert_eq!(status.last_sync, None);
+        assert_eq!(status.last_send, Some(now));
+        assert_eq!(status.consecutive_failures, 3);
+        assert_eq!(
+            status.last_failure.map(|event| event.operation),
+            Some(HealthOperation::Send)
+        );
+
+        let reloaded = HealthLog::new(&config, Default::default());
+        assert_eq!(reloaded.events(), log.events());
+        assert_eq!(
+            reloaded
+                .last_event(HealthOperation::Sync)
+                .and_then(|event| event.error),
+            Some(String::from("refused"))
+        );
+        assert_eq!(reloaded.events()[1].error.as_deref(), Some("timed\tout"));
+
+        let contents = fs::read_to_string(&config.path).unwrap();
+        assert_eq!(
+            contents.lines().next(),
+            Some(r#"{"timestamp":"2024-01-01T12:00:00Z","operation":"send"}"#)
+        );
+    }
+}
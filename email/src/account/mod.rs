@@ -5,8 +5,11 @@
 //! local Maildir backend. It also contains common code related to
 //! PGP.
 
+pub mod budget;
 pub mod config;
 mod error;
+pub mod health;
+pub mod runtime;
 #[cfg(feature = "sync")]
 pub mod sync;
 
@@ -0,0 +1,267 @@
+//! # Account runtime
+//!
+//! Module dedicated to the account runtime, see [`AccountRuntime`].
+//!
+//! Some state outlives backend contexts: concurrency budgets, OAuth
+//! 2.0 token managers, detected SMTP encryptions, offline mode and
+//! health logs. This state is
+//! owned by the client and passed to accounts through
+//! [`AccountConfig::runtime`](super::config::AccountConfig::runtime).
+//! Accounts sharing the same runtime share the same state, while
+//! separate runtimes (for example of two clients, or of two tests
+//! running in the same process) never interfere.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, RwLock},
+};
+
+#[cfg(feature = "oauth2")]
+use oauth::v2_0::TokenManager;
+use tracing::debug;
+
+use super::{
+    budget::ConcurrencyBudget,
+    health::{HealthConfig, HealthLog},
+};
+use crate::date::ClockSource;
+#[cfg(feature = "smtp")]
+use crate::tls::SecurityLevel;
+
+/// The runtime state shared by accounts.
+///
+/// Cloning the runtime shares the same state.
+#[derive(Clone, Debug, Default)]
+pub struct AccountRuntime(Arc<AccountRuntimeState>);
+
+#[derive(Debug, Default)]
+struct AccountRuntimeState {
+    /// The concurrency budgets, indexed by account name.
+    budgets: Mutex<HashMap<String, ConcurrencyBudget>>,
+
+    /// The concurrency budgets, indexed by host name.
+    host_budgets: Mutex<HashMap<String, ConcurrencyBudget>>,
+
+    /// The OAuth 2.0 token managers, indexed by login and OAuth 2.0
+    /// client.
+    #[cfg(feature = "oauth2")]
+    token_managers: Mutex<HashMap<String, Arc<TokenManager>>>,
+
+    /// The security levels detected by probing SMTP servers, indexed
+    /// by host name and port.
+    #[cfg(feature = "smtp")]
+    smtp_security_levels: Mutex<HashMap<(String, u16), SecurityLevel>>,
+
+    /// The names of the accounts currently in offline mode.
+    offline_accounts: RwLock<HashSet<String>>,
+
+    /// The health logs, indexed by account name.
+    health_logs: Mutex<HashMap<String, HealthLog>>,
+}
+
+impl AccountRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the concurrency budget of the given account, creating it
+    /// if needed.
+    ///
+    /// The budget is replaced when its limit changed, permits
+    /// acquired from the previous budget stay valid.
+    pub fn concurrency_budget(&self, account: &str, limit: u32) -> ConcurrencyBudget {
+        debug!(account, limit, "getting account concurrency budget");
+        get_or_create_budget(&self.0.budgets, account, limit)
+    }
+
+    /// Get the concurrency budget of the given host, creating it if
+    /// needed.
+    ///
+    /// The host budget is shared by all accounts of the runtime
+    /// connecting to the same host, and behaves like
+    /// [`AccountRuntime::concurrency_budget`] otherwise. Host names
+    /// are case-insensitive.
+    pub fn host_concurrency_budget(&self, host: &str, limit: u32) -> ConcurrencyBudget {
+        debug!(host, limit, "getting host concurrency budget");
+        get_or_create_budget(&self.0.host_budgets, &host.to_lowercase(), limit)
+    }
+
+    /// Get the OAuth 2.0 token manager of the given key, if any.
+    #[cfg(feature = "oauth2")]
+    pub(crate) fn oauth2_token_manager(&self, key: &str) -> Option<Arc<TokenManager>> {
+        let managers = self
+            .0
+            .token_managers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        managers.get(key).cloned()
+    }
+
+    /// Insert the given OAuth 2.0 token manager, unless another one
+    /// has been inserted for the same key in the meantime.
+    ///
+    /// Returns the manager of the key.
+    #[cfg(feature = "oauth2")]
+    pub(crate) fn insert_oauth2_token_manager(
+        &self,
+        key: String,
+        manager: TokenManager,
+    ) -> Arc<TokenManager> {
+        let mut managers = self
+            .0
+            .token_managers
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        managers.entry(key).or_insert(Arc::new(manager)).clone()
+    }
+
+    /// Get the security level detected for the given SMTP server, if
+    /// any. Host names are case-insensitive.
+    #[cfg(feature = "smtp")]
+    pub(crate) fn smtp_security_level(&self, host: &str, port: u16) -> Option<SecurityLevel> {
+        let levels = self
+            .0
+            .smtp_security_levels
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        levels.get(&(host.to_lowercase(), port)).copied()
+    }
+
+    /// Set the security level detected for the given SMTP server.
+    #[cfg(feature = "smtp")]
+    pub(crate) fn set_smtp_security_level(&self, host: &str, port: u16, level: SecurityLevel) {
+        let mut levels = self
+            .0
+            .smtp_security_levels
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        levels.insert((host.to_lowercase(), port), level);
+    }
+
+    /// Return `true` if the given account is in offline mode.
+    pub fn is_offline(&self, account: &str) -> bool {
+        self.0
+            .offline_accounts
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .contains(account)
+    }
+
+    /// Enable or disable the offline mode of the given account.
+    pub fn set_offline(&self, account: &str, offline: bool) {
+        let mut accounts = self
+            .0
+            .offline_accounts
+            .write()
+            .unwrap_or_else(|err| err.into_inner());
+
+        if offline {
+            debug!(account, "enabling offline mode");
+            accounts.insert(account.to_owned());
+        } else {
+            debug!(account, "disabling offline mode");
+            accounts.remove(account);
+        }
+    }
+
+    /// Get the health log of the given account, creating it if
+    /// needed.
+    ///
+    /// The log is replaced when its configuration changed.
+    pub fn health_log(
+        &self,
+        account: &str,
+        config: &HealthConfig,
+        clock: ClockSource,
+    ) -> HealthLog {
+        let mut logs = self
+            .0
+            .health_logs
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        match logs.get(account) {
+            Some(log) if log.config() == *config => log.clone(),
+            _ => {
+                debug!(account, "creating health log");
+                let log = HealthLog::new(config, clock);
+                logs.insert(account.to_owned(), log.clone());
+                log
+            }
+        }
+    }
+}
+
+/// Two runtimes are equal when they share the same state.
+impl PartialEq for AccountRuntime {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for AccountRuntime {}
+
+fn get_or_create_budget(
+    budgets: &Mutex<HashMap<String, ConcurrencyBudget>>,
+    key: &str,
+    limit: u32,
+) -> ConcurrencyBudget {
+    let mut budgets = budgets.lock().unwrap_or_else(|err| err.into_inner());
+
+    match budgets.get(key) {
+        Some(budget) if budget.limit() == limit.max(1) => budget.clone(),
+        _ => {
+            let budget = ConcurrencyBudget::new(limit);
+            budgets.insert(key.to_owned(), budget.clone());
+            budget
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AccountRuntime;
+
+    #[tokio::test]
+    async fn shared_budgets() {
+        let runtime = AccountRuntime::new();
+
+        let budget = runtime.concurrency_budget("account", 3);
+        let _permit = budget.acquire(2).await;
+
+        let shared = runtime.clone().concurrency_budget("account", 3);
+        assert_eq!(shared.in_use(), 2);
+
+        // the host budget is shared whatever the case of the name,
+        // but not with the account budget of the same name
+        let host = runtime.host_concurrency_budget("localhost", 2);
+        let _permit = host.acquire(1).await;
+        assert_eq!(runtime.host_concurrency_budget("LOCALHOST", 2).in_use(), 1);
+        assert_eq!(runtime.concurrency_budget("localhost", 2).in_use(), 0);
+
+        // a changed limit replaces the budget
+        assert_eq!(runtime.concurrency_budget("account", 4).in_use(), 0);
+
+        // other runtimes do not share anything
+        let other = AccountRuntime::new();
+        assert_eq!(other.concurrency_budget("account", 4).in_use(), 0);
+        assert_ne!(runtime, other);
+    }
+
+    #[test]
+    fn offline_mode() {
+        let runtime = AccountRuntime::new();
+        runtime.set_offline("account", true);
+
+        assert!(runtime.is_offline("account"));
+        assert!(!runtime.is_offline("other"));
+        assert!(!AccountRuntime::new().is_offline("account"));
+
+        runtime.set_offline("account", false);
+        assert!(!runtime.is_offline("account"));
+    }
+}
@@ -7,9 +7,25 @@
 
 use std::time::Duration;
 
+#[cfg(feature = "oauth2")]
+use oauth::v2_0::Provider;
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use super::{Error, Result};
+#[cfg(feature = "oauth2")]
+use crate::account::config::oauth2::{OAuth2Config as AccountOAuth2Config, OAuth2Scopes};
+#[cfg(feature = "imap")]
+use crate::imap::config::{ImapAuthConfig, ImapConfig};
+#[cfg(feature = "smtp")]
+use crate::smtp::config::{SmtpAuthConfig, SmtpConfig};
+#[cfg(any(feature = "imap", feature = "smtp"))]
+use crate::{
+    account::config::passwd::PasswordConfig,
+    tls::{Encryption, Tls},
+};
+
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 /// The root level of the Mozilla Autoconfiguration.
 pub struct AutoConfig {
@@ -40,9 +56,165 @@ pub fn email_provider(&self) -> &EmailProvider {
     pub fn oauth2(&self) -> Option<&OAuth2Config> {
         self.oauth2.as_ref()
     }
+
+    /// The well-known OAuth 2.0 provider matching the email
+    /// provider, if any.
+    #[cfg(feature = "oauth2")]
+    pub fn oauth2_provider(&self) -> Option<Provider> {
+        let id = self.email_provider.id();
+        let domains = self.email_provider.domain();
+
+        [id].into_iter()
+            .chain(domains)
+            .find_map(|domain| match domain.to_lowercase().as_str() {
+                "googlemail.com" | "gmail.com" => Some(Provider::Google),
+                "outlook.com" | "hotmail.com" | "live.com" | "office365.com" => {
+                    Some(Provider::Microsoft)
+                }
+                "yahoo.com" => Some(Provider::Yahoo),
+                "fastmail.com" | "fastmail.fm" => Some(Provider::Fastmail),
+                _ => None,
+            })
+    }
+
+    /// Convert the discovered incoming server into an IMAP
+    /// configuration, authenticating with the given login.
+    ///
+    /// Encrypted servers are preferred over plain ones. OAuth 2.0 is
+    /// used when the server advertises it and the provider can be
+    /// configured, otherwise password authentication is used. The
+    /// password or the OAuth 2.0 client still need to be defined by
+    /// the caller.
+    #[cfg(feature = "imap")]
+    pub fn into_imap_config(self, login: impl ToString) -> Result<ImapConfig> {
+        let server = self
+            .email_provider
+            .incoming_servers()
+            .into_iter()
+            .filter(|server| matches!(server.server_type(), ServerType::Imap))
+            .min_by_key(|server| server.security_rank())
+            .ok_or_else(|| Error::GetImapServerNotFoundError(self.email_provider.id.clone()))?;
+
+        let host = server
+            .hostname()
+            .ok_or_else(|| Error::GetServerHostnameNotFoundError(self.email_provider.id.clone()))?
+            .to_owned();
+
+        let encryption = server.encryption();
+
+        let port = match server.port() {
+            Some(port) => *port,
+            None => match encryption {
+                Encryption::Tls(_) => 993,
+                Encryption::StartTls(_) | Encryption::None => 143,
+            },
+        };
+
+        #[allow(unused_mut)]
+        let mut auth = ImapAuthConfig::Password(PasswordConfig::default());
+
+        #[cfg(feature = "oauth2")]
+        if let Some(config) = self.oauth2_config_for(server) {
+            auth = ImapAuthConfig::OAuth2(config);
+        }
+
+        Ok(ImapConfig {
+            host,
+            port,
+            encryption: Some(encryption),
+            login: login.to_string(),
+            auth,
+            ..Default::default()
+        })
+    }
+
+    /// Convert the discovered outgoing server into an SMTP
+    /// configuration, authenticating with the given login.
+    ///
+    /// See [`AutoConfig::into_imap_config`] for the server and the
+    /// authentication selection.
+    #[cfg(feature = "smtp")]
+    pub fn into_smtp_config(self, login: impl ToString) -> Result<SmtpConfig> {
+        let server = self
+            .email_provider
+            .outgoing_servers()
+            .into_iter()
+            .filter(|server| matches!(server.server_type(), ServerType::Smtp))
+            .min_by_key(|server| server.security_rank())
+            .ok_or_else(|| Error::GetSmtpServerNotFoundError(self.email_provider.id.clone()))?;
+
+        let host = server
+            .hostname()
+            .ok_or_else(|| Error::GetServerHostnameNotFoundError(self.email_provider.id.clone()))?
+            .to_owned();
+
+        let encryption = server.encryption();
+
+        let port = match server.port() {
+            Some(port) => *port,
+            None => match encryption {
+                Encryption::Tls(_) => 465,
+                Encryption::StartTls(_) => 587,
+                Encryption::None => 25,
+            },
+        };
+
+        #[allow(unused_mut)]
+        let mut auth = SmtpAuthConfig::Password(PasswordConfig::default());
+
+        #[cfg(feature = "oauth2")]
+        if let Some(config) = self.oauth2_config_for(server) {
+            auth = SmtpAuthConfig::OAuth2(config);
+        }
+
+        Ok(SmtpConfig {
+            host,
+            port,
+            encryption: Some(encryption),
+            auto_encryption: None,
+            login: login.to_string(),
+            auth,
+            timeout: None,
+            fallbacks: Vec::new(),
+        })
+    }
+
+    /// Build the OAuth 2.0 configuration of the given server, if
+    /// the server advertises OAuth 2.0 and if either the autoconfig
+    /// exposes OAuth 2.0 endpoints or the provider is well-known.
+    #[cfg(all(feature = "oauth2", any(feature = "imap", feature = "smtp")))]
+    fn oauth2_config_for(&self, server: &Server) -> Option<AccountOAuth2Config> {
+        let advertised = server
+            .authentication_type()
+            .into_iter()
+            .any(|auth| matches!(auth, AuthenticationType::OAuth2));
+
+        if !advertised {
+            return None;
+        }
+
+        if let Some(oauth2) = self.oauth2() {
+            return Some(AccountOAuth2Config {
+                issuer: Some(oauth2.issuer().to_owned()),
+                auth_url: oauth2.auth_url().to_owned(),
+                token_url: oauth2.token_url().to_owned(),
+                scopes: OAuth2Scopes::Scopes(
+                    oauth2.scope().into_iter().map(ToOwned::to_owned).collect(),
+                ),
+                ..Default::default()
+            });
+        }
+
+        let provider = self.oauth2_provider()?;
+
+        Some(AccountOAuth2Config {
+            provider: Some(provider),
+            ..Default::default()
+        })
+    }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct OAuth2Config {
     issuer: String,
@@ -77,7 +249,7 @@ pub fn token_url(&self) -> &str {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct EmailProvider {
     pub id: String,
     #[serde(rename = "$value")]
@@ -190,7 +362,7 @@ pub fn documentation(&self) -> Option<&Documentation> {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum EmailProviderProperty {
     Domain(String),
@@ -201,7 +373,7 @@ pub enum EmailProviderProperty {
     Documentation(Documentation),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Server {
     pub r#type: ServerType,
     #[serde(rename = "$value")]
@@ -253,6 +425,29 @@ pub fn security_type(&self) -> Option<&SecurityType> {
         None
     }
 
+    /// The encryption matching the security type of the server.
+    ///
+    /// Defaults to SSL/TLS when the security type is not defined.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    pub fn encryption(&self) -> Encryption {
+        match self.security_type() {
+            Some(SecurityType::Tls) | None => Encryption::Tls(Tls::default()),
+            Some(SecurityType::Starttls) => Encryption::StartTls(Tls::default()),
+            Some(SecurityType::Plain) => Encryption::None,
+        }
+    }
+
+    /// The rank of the server security type, the lowest being the
+    /// most secure.
+    #[cfg(any(feature = "imap", feature = "smtp"))]
+    fn security_rank(&self) -> u8 {
+        match self.security_type() {
+            Some(SecurityType::Tls) | None => 0,
+            Some(SecurityType::Starttls) => 1,
+            Some(SecurityType::Plain) => 2,
+        }
+    }
+
     /// The kind of authentication is needed to login to this mail
     /// server
     pub fn authentication_type(&self) -> Vec<&AuthenticationType> {
@@ -290,7 +485,7 @@ pub fn password(&self) -> Option<&str> {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ServerProperty {
     Hostname(String),
@@ -305,7 +500,7 @@ pub enum ServerProperty {
     Password(String),
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub enum SecurityType {
     #[serde(rename = "plain")]
     Plain,
@@ -315,7 +510,7 @@ pub enum SecurityType {
     Tls,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum ServerType {
     Exchange,
@@ -327,7 +522,7 @@ pub enum ServerType {
     Unknown,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub enum AuthenticationType {
     #[serde(rename = "password-cleartext")]
     PasswordCleartext,
@@ -346,7 +541,7 @@ pub enum AuthenticationType {
     None,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Pop3Config {
     leave_messages_on_server: bool,
@@ -389,12 +584,12 @@ pub fn check_interval(&self) -> Option<Duration> {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 struct CheckInterval {
     minutes: Option<u64>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct Documentation {
     url: String,
     #[serde(rename = "$value")]
@@ -413,7 +608,7 @@ pub fn properties(&self) -> &Vec<DocumentationDescription> {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize)]
 pub struct DocumentationDescription {
     lang: Option<String>,
     #[serde(rename = "$value")]
@@ -431,3 +626,65 @@ pub fn description(&self) -> &str {
         &self.description
     }
 }
+
+#[cfg(all(test, feature = "imap", feature = "smtp"))]
+mod tests {
+    use super::{
+        AuthenticationType, AutoConfig, EmailProvider, EmailProviderProperty, SecurityType, Server,
+        ServerProperty, ServerType,
+    };
+    use crate::{imap::config::ImapAuthConfig, tls::Encryption};
+
+    fn server(r#type: ServerType, security: SecurityType, port: Option<u16>) -> Server {
+        let mut properties = vec![
+            ServerProperty::Hostname(String::from("mail.example.com")),
+            ServerProperty::SocketType(security),
+            ServerProperty::Authentication(AuthenticationType::PasswordCleartext),
+        ];
+
+        if let Some(port) = port {
+            properties.push(ServerProperty::Port(port));
+        }
+
+        Server { r#type, properties }
+    }
+
+    #[test]
+    fn into_backend_configs() {
+        let config = AutoConfig {
+            version: String::from("1.1"),
+            email_provider: EmailProvider {
+                id: String::from("example.com"),
+                properties: vec![
+                    EmailProviderProperty::IncomingServer(server(
+                        ServerType::Imap,
+                        SecurityType::Starttls,
+                        Some(143),
+                    )),
+                    EmailProviderProperty::IncomingServer(server(
+                        ServerType::Imap,
+                        SecurityType::Tls,
+                        None,
+                    )),
+                    EmailProviderProperty::OutgoingServer(server(
+                        ServerType::Smtp,
+                        SecurityType::Starttls,
+                        None,
+                    )),
+                ],
+            },
+            oauth2: None,
+        };
+
+        let imap = config.clone().into_imap_config("test@example.com").unwrap();
+        assert_eq!(imap.host, "mail.example.com");
+        assert_eq!(imap.port, 993);
+        assert!(matches!(imap.encryption, Some(Encryption::Tls(_))));
+        assert_eq!(imap.login, "test@example.com");
+        assert!(matches!(imap.auth, ImapAuthConfig::Password(_)));
+
+        let smtp = config.into_smtp_config("test@example.com").unwrap();
+        assert_eq!(smtp.port, 587);
+        assert!(matches!(smtp.encryption, Some(Encryption::StartTls(_))));
+    }
+}
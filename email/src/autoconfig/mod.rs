@@ -69,6 +69,12 @@ pub enum Error {
     SerdeXmlFailedForAutoConfig(#[source] serde_xml_rs::Error, Uri),
     #[error("cannot parse email {0}: {1}")]
     ParsingEmailAddress(String, #[source] email_address::Error),
+    #[error("cannot find any IMAP server in autoconfig of {0}")]
+    GetImapServerNotFoundError(String),
+    #[error("cannot find any SMTP server in autoconfig of {0}")]
+    GetSmtpServerNotFoundError(String),
+    #[error("cannot find hostname of server in autoconfig of {0}")]
+    GetServerHostnameNotFoundError(String),
 }
 
 /// Discover configuration associated to a given email address using
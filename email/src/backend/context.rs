@@ -8,21 +8,25 @@
 use async_trait::async_trait;
 use paste::paste;
 
-use super::feature::{BackendFeature, CheckUp};
+use super::{
+    feature::{BackendFeature, BackendFeatureKind, BackendFeatureSupport, CheckUp},
+    Error,
+};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::ThreadEnvelopes;
 #[cfg(feature = "watch")]
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
-    envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    envelope::{get::GetEnvelope, list::ListEnvelopes, SingleId, StableId},
+    flag::{add::AddFlags, mark_read::MarkReadBefore, remove::RemoveFlags, set::SetFlags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
-        purge::PurgeFolder,
+        acl::ManageFolderAcl, add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder,
+        list::ListFolders, purge::PurgeFolder, quota::GetQuota,
     },
     message::{
-        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
-        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+        add::AddMessage, attachment::GetAttachment, copy::CopyMessages, delete::DeleteMessages,
+        get::GetMessages, peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages,
+        send::SendMessage,
     },
     AnyResult,
 };
@@ -32,7 +36,38 @@
 /// This is just a marker for other backend traits. Every backend
 /// context needs to implement this trait manually or to derive
 /// [`crate::backend_v2::macros::BackendContextV2`].
-pub trait BackendContext: Send + Sync {}
+#[async_trait]
+pub trait BackendContext: Send + Sync {
+    /// Return the level of support of the given feature.
+    ///
+    /// Contexts relying on server capabilities should override this
+    /// function, so that unsupported features can be hidden from
+    /// users. Features are fully supported by default.
+    fn feature_support(&self, _feature: BackendFeatureKind) -> BackendFeatureSupport {
+        BackendFeatureSupport::Full
+    }
+
+    /// Take the folders whose envelope identifiers changed since the
+    /// last call, as returned by [`ListFolders`].
+    ///
+    /// Some backends can renumber all the envelopes of a folder, like
+    /// IMAP when the UIDVALIDITY of a mailbox changes: identifiers
+    /// cached for these folders are not valid anymore. Contexts able
+    /// to detect it should override this function.
+    fn take_invalidated_folders(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Resolve the given stable identifier into an envelope
+    /// identifier usable by features.
+    ///
+    /// Contexts must check that the stable identifier still targets
+    /// the message it was built for, and fail if they cannot. Stable
+    /// identifiers are not supported by default.
+    async fn resolve_stable_id(&self, id: &StableId) -> AnyResult<SingleId> {
+        Err(Error::ResolveStableIdNotSupportedError(id.encode(), id.kind()).into())
+    }
+}
 
 /// Macro for defining [`BackendContextBuilder`] features.
 macro_rules! feature {
@@ -82,6 +117,8 @@ async fn configure(&mut self) -> AnyResult<()> {
     feature!(ExpungeFolder);
     feature!(PurgeFolder);
     feature!(DeleteFolder);
+    feature!(GetQuota);
+    feature!(ManageFolderAcl);
     feature!(GetEnvelope);
     feature!(ListEnvelopes);
     #[cfg(feature = "thread")]
@@ -91,15 +128,28 @@ async fn configure(&mut self) -> AnyResult<()> {
     feature!(AddFlags);
     feature!(SetFlags);
     feature!(RemoveFlags);
+    feature!(MarkReadBefore);
     feature!(AddMessage);
     feature!(SendMessage);
     feature!(PeekMessages);
     feature!(GetMessages);
+    feature!(GetAttachment);
     feature!(CopyMessages);
     feature!(MoveMessages);
     feature!(DeleteMessages);
     feature!(RemoveMessages);
 
+    /// Define the path where the context can persist its own state,
+    /// next to the synchronization caches.
+    ///
+    /// The path has no extension, contexts are free to add their
+    /// own. For example the IMAP context persists the UIDVALIDITY
+    /// of folders there.
+    #[cfg(feature = "sync")]
+    fn set_sync_state_path(&mut self, _path: std::path::PathBuf) {
+        //
+    }
+
     /// Build the final context used by the backend.
     async fn build(self) -> AnyResult<Self::Context>;
 
@@ -111,23 +161,20 @@ fn try_to_sync_cache_builder(
     where
         Self: crate::sync::hash::SyncHash,
     {
-        use std::{
-            hash::{DefaultHasher, Hasher},
-            sync::Arc,
-        };
+        use std::sync::Arc;
 
         use dirs::data_dir;
         use shellexpand_utils::try_shellexpand_path;
-        use tracing::debug;
+        use tracing::{debug, warn};
 
         use crate::{
             account::{config::AccountConfig, Error},
             maildir::{config::MaildirConfig, MaildirContextBuilder},
+            sync::hash::{migrate_legacy_dir, SyncHasher},
         };
 
-        let mut hasher = DefaultHasher::new();
+        let mut hasher = SyncHasher::new();
         self.sync_hash(&mut hasher);
-        let hash = format!("{:x}", hasher.finish());
 
         let sync_dir = account_config.sync.as_ref().and_then(|c| c.dir.as_ref());
         let root_dir = match sync_dir {
@@ -138,12 +185,18 @@ fn try_to_sync_cache_builder(
                 dir
             }
             None => {
-                let dir = data_dir()
+                let parent = data_dir()
                     .ok_or(Error::GetXdgDataDirSyncError)?
                     .join("pimalaya")
                     .join("email")
-                    .join("sync")
-                    .join(&hash);
+                    .join("sync");
+                let dir = match migrate_legacy_dir(&parent, &hasher) {
+                    Ok(dir) => dir,
+                    Err(err) => {
+                        warn!(?err, "cannot migrate legacy sync directory, skipping it");
+                        parent.join(hasher.finish())
+                    }
+                };
                 debug!(?dir, "using default sync directory");
                 dir
             }
@@ -152,8 +205,10 @@ fn try_to_sync_cache_builder(
         let account_config = Arc::new(AccountConfig {
             name: account_config.name.clone(),
             email: account_config.email.clone(),
+            email_aliases: account_config.email_aliases.clone(),
             display_name: account_config.display_name.clone(),
             signature: account_config.signature.clone(),
+            signature_html: account_config.signature_html.clone(),
             signature_delim: account_config.signature_delim.clone(),
             downloads_dir: account_config.downloads_dir.clone(),
             folder: account_config.folder.clone(),
@@ -164,11 +219,18 @@ fn try_to_sync_cache_builder(
             sync: None,
             #[cfg(feature = "pgp")]
             pgp: account_config.pgp.clone(),
+            journal: None,
+            concurrency_budget: account_config.concurrency_budget,
+            health: None,
+            clock: account_config.clock.clone(),
+            runtime: account_config.runtime.clone(),
         });
 
         let config = Arc::new(MaildirConfig {
             root_dir,
             maildirpp: false,
+            clean_tmp: false,
+            expunge_flags: None,
         });
 
         let ctx = MaildirContextBuilder::new(account_config.clone(), config);
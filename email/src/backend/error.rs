@@ -2,7 +2,8 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use super::feature::BackendFeatureKind;
+use crate::{envelope::StableIdKind, AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -20,6 +21,10 @@ pub enum Error {
     PurgeFolderNotAvailableError,
     #[error("cannot delete folder: feature not available, or backend configuration for this functionality is not set")]
     DeleteFolderNotAvailableError,
+    #[error("cannot get folder quota: feature not available, or backend configuration for this functionality is not set")]
+    GetQuotaNotAvailableError,
+    #[error("cannot manage folder ACL: feature not available, or backend configuration for this functionality is not set")]
+    ManageFolderAclNotAvailableError,
     #[error("cannot list envelopes: feature not available, or backend configuration for this functionality is not set")]
     ListEnvelopesNotAvailableError,
     #[error("cannot thread envelopes: feature not available, or backend configuration for this functionality is not set")]
@@ -34,6 +39,8 @@ pub enum Error {
     SetFlagsNotAvailableError,
     #[error("cannot remove flag(s): feature not available, or backend configuration for this functionality is not set")]
     RemoveFlagsNotAvailableError,
+    #[error("cannot mark envelopes as read: feature not available, or backend configuration for this functionality is not set")]
+    MarkReadBeforeNotAvailableError,
     #[error("cannot add message: feature not available, or backend configuration for this functionality is not set")]
     AddMessageNotAvailableError,
     #[error("cannot add message with flags: feature not available, or backend configuration for this functionality is not set")]
@@ -44,6 +51,8 @@ pub enum Error {
     GetMessagesNotAvailableError,
     #[error("cannot peek messages: feature not available, or backend configuration for this functionality is not set")]
     PeekMessagesNotAvailableError,
+    #[error("cannot get attachment: feature not available, or backend configuration for this functionality is not set")]
+    GetAttachmentNotAvailableError,
     #[error("cannot copy messages: feature not available, or backend configuration for this functionality is not set")]
     CopyMessagesNotAvailableError,
     #[error("cannot move messages: feature not available, or backend configuration for this functionality is not set")]
@@ -52,6 +61,18 @@ pub enum Error {
     DeleteMessagesNotAvailableError,
     #[error("cannot remove messages: feature not available, or backend configuration for this functionality is not set")]
     RemoveMessagesNotAvailableError,
+    #[error("cannot reach server: account {0} is offline")]
+    OfflineError(String),
+    #[error("cannot get output of backend feature {0}: skipped by middleware")]
+    FeatureSkippedByMiddlewareError(&'static str),
+    #[error("cannot resolve stable envelope id {0}: {1} stable ids not supported by this backend")]
+    ResolveStableIdNotSupportedError(String, StableIdKind),
+    #[error("cannot resolve stable envelope ids: at least one id is required")]
+    ResolveEmptyStableIdsError,
+    #[error("cannot resolve stable envelope ids: folders {0} and {1} differ")]
+    ResolveStableIdsFolderMismatchError(String, String),
+    #[error("cannot build backend: required feature(s) not available: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    MissingRequiredFeaturesError(Vec<BackendFeatureKind>),
 }
 
 impl AnyError for Error {
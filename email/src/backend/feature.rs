@@ -4,7 +4,7 @@
 //! envelopes or sending message. A feature needs a backend context to
 //! be executed.
 
-use std::sync::Arc;
+use std::{collections::BTreeMap, fmt, sync::Arc};
 
 use async_trait::async_trait;
 
@@ -71,3 +71,138 @@ fn from(value: T) -> Self {
         Self::Backend(Arc::new(value))
     }
 }
+
+/// The backend feature kind.
+///
+/// This enum is used to designate a backend feature without its
+/// implementation, for example when requiring features at build
+/// time, see [`super::BackendBuilder::with_required_features`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum BackendFeatureKind {
+    AddFolder,
+    ListFolders,
+    ExpungeFolder,
+    PurgeFolder,
+    DeleteFolder,
+    GetQuota,
+    ManageFolderAcl,
+    GetEnvelope,
+    ListEnvelopes,
+    #[cfg(feature = "thread")]
+    ThreadEnvelopes,
+    #[cfg(feature = "watch")]
+    WatchEnvelopes,
+    AddFlags,
+    SetFlags,
+    RemoveFlags,
+    MarkReadBefore,
+    AddMessage,
+    SendMessage,
+    PeekMessages,
+    GetMessages,
+    GetAttachment,
+    CopyMessages,
+    MoveMessages,
+    DeleteMessages,
+    RemoveMessages,
+}
+
+impl BackendFeatureKind {
+    /// Return all the feature kinds.
+    pub fn all() -> impl Iterator<Item = Self> {
+        [
+            Self::AddFolder,
+            Self::ListFolders,
+            Self::ExpungeFolder,
+            Self::PurgeFolder,
+            Self::DeleteFolder,
+            Self::GetQuota,
+            Self::ManageFolderAcl,
+            Self::GetEnvelope,
+            Self::ListEnvelopes,
+            #[cfg(feature = "thread")]
+            Self::ThreadEnvelopes,
+            #[cfg(feature = "watch")]
+            Self::WatchEnvelopes,
+            Self::AddFlags,
+            Self::SetFlags,
+            Self::RemoveFlags,
+            Self::MarkReadBefore,
+            Self::AddMessage,
+            Self::SendMessage,
+            Self::PeekMessages,
+            Self::GetMessages,
+            Self::GetAttachment,
+            Self::CopyMessages,
+            Self::MoveMessages,
+            Self::DeleteMessages,
+            Self::RemoveMessages,
+        ]
+        .into_iter()
+    }
+
+    /// Return the snake case name of the feature, as used by
+    /// [`super::middleware::BackendOperation`].
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AddFolder => "add_folder",
+            Self::ListFolders => "list_folders",
+            Self::ExpungeFolder => "expunge_folder",
+            Self::PurgeFolder => "purge_folder",
+            Self::DeleteFolder => "delete_folder",
+            Self::GetQuota => "get_quota",
+            Self::ManageFolderAcl => "manage_folder_acl",
+            Self::GetEnvelope => "get_envelope",
+            Self::ListEnvelopes => "list_envelopes",
+            #[cfg(feature = "thread")]
+            Self::ThreadEnvelopes => "thread_envelopes",
+            #[cfg(feature = "watch")]
+            Self::WatchEnvelopes => "watch_envelopes",
+            Self::AddFlags => "add_flags",
+            Self::SetFlags => "set_flags",
+            Self::RemoveFlags => "remove_flags",
+            Self::MarkReadBefore => "mark_read_before",
+            Self::AddMessage => "add_message",
+            Self::SendMessage => "send_message",
+            Self::PeekMessages => "peek_messages",
+            Self::GetMessages => "get_messages",
+            Self::GetAttachment => "get_attachment",
+            Self::CopyMessages => "copy_messages",
+            Self::MoveMessages => "move_messages",
+            Self::DeleteMessages => "delete_messages",
+            Self::RemoveMessages => "remove_messages",
+        }
+    }
+}
+
+impl fmt::Display for BackendFeatureKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The level of support of a backend feature.
+///
+/// Some backends rely on server capabilities to implement features:
+/// when a capability is missing, the feature may be emulated with
+/// reduced performances, or not work at all.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub enum BackendFeatureSupport {
+    /// The feature is fully supported.
+    #[default]
+    Full,
+
+    /// The feature works, but is emulated client side (slower, or
+    /// with less precise results).
+    Partial,
+
+    /// The feature is wired but cannot work, calling it leads to a
+    /// runtime error.
+    Unsupported,
+}
+
+/// The features supported by a backend, associated to their level of
+/// support.
+///
+/// See [`super::Backend::supported_features`].
+pub type BackendFeatures = BTreeMap<BackendFeatureKind, BackendFeatureSupport>;
@@ -0,0 +1,306 @@
+//! # Backend handle
+//!
+//! Module dedicated to the [`BackendHandle`], a thread-safe facade
+//! around [`Backend`] suitable for sharing across threads, for
+//! example between the widgets of a GUI.
+//!
+//! Backend features borrow the backend and their arguments, which
+//! makes their futures hard to move to another thread or to store in
+//! a widget. The handle is cheap to clone and its methods take owned
+//! arguments, so they return `'static` futures that can be spawned
+//! directly:
+//!
+//! ```rust,ignore
+//! let handle = BackendHandle::new(backend);
+//!
+//! // from an async context
+//! let envelopes = handle.list_envelopes("INBOX", Default::default()).await?;
+//!
+//! // from a non-async UI thread, using the runtime handle captured
+//! // when the application started
+//! let task = handle.spawn_on(&runtime, |handle| async move {
+//!     handle.list_folders().await
+//! });
+//! ```
+
+use std::{future::Future, ops::Deref, sync::Arc};
+
+use tokio::{runtime, task::JoinHandle};
+
+use super::{context::BackendContext, Backend};
+use crate::{
+    envelope::{
+        get::GetEnvelope,
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope, Envelopes, Id, SingleId,
+    },
+    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
+    folder::{
+        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders, Folders,
+    },
+    message::{
+        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
+        peek::PeekMessages, r#move::MoveMessages, send::SendMessage, Messages,
+    },
+    AnyResult,
+};
+
+/// The thread-safe backend handle.
+///
+/// The handle wraps the backend into an [`Arc`]: cloning it only
+/// clones the reference, and all clones share the same context,
+/// middlewares and prefetch cache. The underlying backend can still
+/// be reached by dereferencing the handle.
+pub struct BackendHandle<C: BackendContext> {
+    backend: Arc<Backend<C>>,
+}
+
+impl<C: BackendContext + 'static> BackendHandle<C> {
+    /// Create a new handle from the given backend.
+    pub fn new(backend: Backend<C>) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Spawn the future built by the given function on the current
+    /// Tokio runtime.
+    ///
+    /// The function receives a clone of the handle. Must be called
+    /// from within a Tokio runtime, see [`BackendHandle::spawn_on`]
+    /// otherwise.
+    pub fn spawn<T, F, Fut>(&self, f: F) -> JoinHandle<AnyResult<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(Self) -> Fut,
+        Fut: Future<Output = AnyResult<T>> + Send + 'static,
+    {
+        tokio::spawn(f(self.clone()))
+    }
+
+    /// Spawn the future built by the given function on the given
+    /// Tokio runtime.
+    ///
+    /// This is the way to call backend features from non-async
+    /// threads (like GUI event loops): the returned [`JoinHandle`]
+    /// can be polled later on, or awaited from another async
+    /// context.
+    pub fn spawn_on<T, F, Fut>(&self, runtime: &runtime::Handle, f: F) -> JoinHandle<AnyResult<T>>
+    where
+        T: Send + 'static,
+        F: FnOnce(Self) -> Fut,
+        Fut: Future<Output = AnyResult<T>> + Send + 'static,
+    {
+        runtime.spawn(f(self.clone()))
+    }
+
+    /// See [`AddFolder::add_folder`].
+    pub fn add_folder(
+        &self,
+        folder: impl ToString,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        async move { backend.add_folder(&folder).await }
+    }
+
+    /// See [`ListFolders::list_folders`].
+    pub fn list_folders(&self) -> impl Future<Output = AnyResult<Folders>> + Send + 'static {
+        let backend = self.backend.clone();
+        async move { backend.list_folders().await }
+    }
+
+    /// See [`ExpungeFolder::expunge_folder`].
+    pub fn expunge_folder(
+        &self,
+        folder: impl ToString,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        async move { backend.expunge_folder(&folder).await }
+    }
+
+    /// See [`DeleteFolder::delete_folder`].
+    pub fn delete_folder(
+        &self,
+        folder: impl ToString,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        async move { backend.delete_folder(&folder).await }
+    }
+
+    /// See [`GetEnvelope::get_envelope`].
+    pub fn get_envelope(
+        &self,
+        folder: impl ToString,
+        id: impl Into<SingleId>,
+    ) -> impl Future<Output = AnyResult<Envelope>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.get_envelope(&folder, &id).await }
+    }
+
+    /// See [`ListEnvelopes::list_envelopes`].
+    pub fn list_envelopes(
+        &self,
+        folder: impl ToString,
+        opts: ListEnvelopesOptions,
+    ) -> impl Future<Output = AnyResult<Envelopes>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        async move { backend.list_envelopes(&folder, opts).await }
+    }
+
+    /// See [`AddFlags::add_flags`].
+    pub fn add_flags(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+        flags: Flags,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.add_flags(&folder, &id, &flags).await }
+    }
+
+    /// See [`SetFlags::set_flags`].
+    pub fn set_flags(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+        flags: Flags,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.set_flags(&folder, &id, &flags).await }
+    }
+
+    /// See [`RemoveFlags::remove_flags`].
+    pub fn remove_flags(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+        flags: Flags,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.remove_flags(&folder, &id, &flags).await }
+    }
+
+    /// See [`AddMessage::add_message_with_flags`].
+    pub fn add_message_with_flags(
+        &self,
+        folder: impl ToString,
+        msg: Vec<u8>,
+        flags: Flags,
+    ) -> impl Future<Output = AnyResult<SingleId>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        async move { backend.add_message_with_flags(&folder, &msg, &flags).await }
+    }
+
+    /// See [`SendMessage::send_message`].
+    pub fn send_message(
+        &self,
+        msg: Vec<u8>,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        async move { backend.send_message(&msg).await }
+    }
+
+    /// See [`PeekMessages::peek_messages`].
+    pub fn peek_messages(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+    ) -> impl Future<Output = AnyResult<Messages>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.peek_messages(&folder, &id).await }
+    }
+
+    /// See [`GetMessages::get_messages`].
+    pub fn get_messages(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+    ) -> impl Future<Output = AnyResult<Messages>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.get_messages(&folder, &id).await }
+    }
+
+    /// See [`CopyMessages::copy_messages`].
+    pub fn copy_messages(
+        &self,
+        from_folder: impl ToString,
+        to_folder: impl ToString,
+        id: impl Into<Id>,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let from_folder = from_folder.to_string();
+        let to_folder = to_folder.to_string();
+        let id = id.into();
+        async move { backend.copy_messages(&from_folder, &to_folder, &id).await }
+    }
+
+    /// See [`MoveMessages::move_messages`].
+    pub fn move_messages(
+        &self,
+        from_folder: impl ToString,
+        to_folder: impl ToString,
+        id: impl Into<Id>,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let from_folder = from_folder.to_string();
+        let to_folder = to_folder.to_string();
+        let id = id.into();
+        async move { backend.move_messages(&from_folder, &to_folder, &id).await }
+    }
+
+    /// See [`DeleteMessages::delete_messages`].
+    pub fn delete_messages(
+        &self,
+        folder: impl ToString,
+        id: impl Into<Id>,
+    ) -> impl Future<Output = AnyResult<()>> + Send + 'static {
+        let backend = self.backend.clone();
+        let folder = folder.to_string();
+        let id = id.into();
+        async move { backend.delete_messages(&folder, &id).await }
+    }
+
+    /// See [`Backend::prefetch`].
+    pub fn prefetch(&self, folder: impl AsRef<str>, id: impl Into<Id>) -> JoinHandle<()> {
+        self.backend.prefetch(folder.as_ref(), &id.into())
+    }
+}
+
+impl<C: BackendContext> Clone for BackendHandle<C> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+        }
+    }
+}
+
+impl<C: BackendContext> Deref for BackendHandle<C> {
+    type Target = Backend<C>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.backend
+    }
+}
+
+impl<C: BackendContext + 'static> From<Backend<C>> for BackendHandle<C> {
+    fn from(backend: Backend<C>) -> Self {
+        Self::new(backend)
+    }
+}
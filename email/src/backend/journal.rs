@@ -0,0 +1,254 @@
+//! # Backend journal
+//!
+//! The [`Journal`] is a [`BackendMiddleware`] recording every
+//! mutating feature call in an append-only file, one JSON object per
+//! line. It allows users to audit what a client did to their
+//! mailbox.
+//!
+//! Each call is recorded twice: a `pending` line is written before
+//! the call, so that the intent survives a crash, then a line with
+//! the result of the call. Both lines share the same operation id,
+//! and contain the timestamp, the feature name, the folders and the
+//! ids involved:
+//!
+//! ```json
+//! {"timestamp":"2024-01-01T12:00:00+00:00","op_id":"4e0c…","op":"move_messages","folder":"INBOX","target_folder":"Trash","ids":["42"],"result":"pending"}
+//! {"timestamp":"2024-01-01T12:00:01+00:00","op_id":"4e0c…","op":"move_messages","folder":"INBOX","target_folder":"Trash","ids":["42"],"result":"ok"}
+//! ```
+//!
+//! The journal file is rotated once it reaches a given size: the
+//! current file becomes `<path>.1`, the previous `<path>.1` becomes
+//! `<path>.2` and so on.
+
+use std::{io, path::PathBuf};
+
+use async_trait::async_trait;
+use serde::Serialize;
+use shellexpand_utils::shellexpand_path;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::middleware::{BackendMiddleware, BackendNext, BackendOperation};
+use crate::{
+    date::ClockSource,
+    jsonl::{JsonLinesWriter, Rotation},
+    AnyResult,
+};
+
+/// The default maximum size of the journal file, in bytes.
+pub const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// The default number of rotated journal files to keep.
+pub const DEFAULT_MAX_FILES: usize = 5;
+
+/// The journal configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case", deny_unknown_fields)
+)]
+pub struct JournalConfig {
+    /// The path of the journal file.
+    ///
+    /// Path is shell-expanded, which means environment variables and
+    /// tilde `~` are replaced by their values.
+    pub path: PathBuf,
+
+    /// The size from which the journal file is rotated, in bytes.
+    ///
+    /// Defaults to 10 MiB.
+    pub max_size: Option<u64>,
+
+    /// The number of rotated journal files to keep.
+    ///
+    /// Defaults to 5.
+    pub max_files: Option<usize>,
+}
+
+impl JournalConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn max_size(&self) -> u64 {
+        self.max_size.unwrap_or(DEFAULT_MAX_SIZE)
+    }
+
+    pub fn max_files(&self) -> usize {
+        self.max_files.unwrap_or(DEFAULT_MAX_FILES)
+    }
+}
+
+/// The journal of mutating backend operations.
+#[derive(Debug)]
+pub struct Journal {
+    writer: JsonLinesWriter,
+    rotation: Rotation,
+    clock: ClockSource,
+}
+
+impl Journal {
+    pub fn new(config: &JournalConfig, clock: ClockSource) -> Self {
+        Self {
+            writer: JsonLinesWriter::new(shellexpand_path(&config.path)),
+            rotation: Rotation {
+                max_size: config.max_size(),
+                max_files: config.max_files(),
+            },
+            clock,
+        }
+    }
+
+    /// Append the given operation to the journal.
+    ///
+    /// The operation is recorded as pending when no result is given.
+    pub async fn append(
+        &self,
+        op_id: &str,
+        op: &BackendOperation,
+        result: Option<&AnyResult<()>>,
+    ) -> io::Result<()> {
+        let (result, error) = match result {
+            None => (JournalResult::Pending, None),
+            Some(Ok(())) => (JournalResult::Ok, None),
+            Some(Err(err)) => (JournalResult::Error, Some(err.to_string())),
+        };
+
+        let entry = JournalEntry {
+            timestamp: self.clock.now().to_rfc3339(),
+            op_id,
+            op: op.feature,
+            folder: op.folder.as_deref(),
+            target_folder: op.target_folder.as_deref(),
+            ids: &op.ids,
+            result,
+            error,
+        };
+
+        self.writer.append(&entry, Some(self.rotation)).await
+    }
+}
+
+#[async_trait]
+impl BackendMiddleware for Journal {
+    async fn handle<'a>(&self, op: &'a BackendOperation, next: BackendNext<'a>) -> AnyResult<()> {
+        if !op.is_mutating() {
+            return next.run().await;
+        }
+
+        let op_id = Uuid::new_v4().to_string();
+
+        if let Err(err) = self.append(&op_id, op, None).await {
+            let path = self.writer.path();
+            warn!(?path, ?err, "cannot append pending operation to journal");
+        }
+
+        let result = next.run().await;
+
+        if let Err(err) = self.append(&op_id, op, Some(&result)).await {
+            let path = self.writer.path();
+            warn!(?path, ?err, "cannot append operation result to journal");
+        }
+
+        result
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JournalResult {
+    Pending,
+    Ok,
+    Error,
+}
+
+#[derive(Serialize)]
+struct JournalEntry<'a> {
+    timestamp: String,
+    op_id: &'a str,
+    op: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    folder: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    target_folder: Option<&'a str>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    ids: &'a Vec<String>,
+    result: JournalResult,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::{Journal, JournalConfig};
+    use crate::{
+        backend::middleware::BackendOperation,
+        date::{ClockSource, FixedClock},
+        email::Error,
+        envelope::Id,
+    };
+
+    #[tokio::test]
+    async fn append_and_rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let clock = ClockSource::from(FixedClock::new(now, FixedOffset::east_opt(0).unwrap()));
+
+        let config = JournalConfig {
+            path: path.clone(),
+            max_size: Some(512),
+            max_files: Some(1),
+        };
+        let journal = Journal::new(&config, clock);
+
+        let op = BackendOperation::new("move_messages")
+            .with_folder("INBOX")
+            .with_target_folder("Trash")
+            .with_ids(&Id::multiple(["1", "2"]));
+        journal.append("a", &op, None).await.unwrap();
+        journal.append("a", &op, Some(&Ok(()))).await.unwrap();
+
+        let expected = concat!(
+            r#"{"timestamp":"2024-01-01T12:00:00+00:00","op_id":"a","op":"move_messages","#,
+            r#""folder":"INBOX","target_folder":"Trash","ids":["1","2"],"result":"pending"}"#,
+            "\n",
+            r#"{"timestamp":"2024-01-01T12:00:00+00:00","op_id":"a","op":"move_messages","#,
+            r#""folder":"INBOX","target_folder":"Trash","ids":["1","2"],"result":"ok"}"#,
+            "\n",
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+
+        // push the journal over its maximum size
+        journal.append("a", &op, Some(&Ok(()))).await.unwrap();
+        journal.append("a", &op, Some(&Ok(()))).await.unwrap();
+
+        let op = BackendOperation::new("delete_folder").with_folder("Archives");
+        let err = Error::FindMessageError("42\"".into());
+        journal
+            .append("b", &op, Some(&Err(Box::new(err))))
+            .await
+            .unwrap();
+
+        // the previous lines have been rotated
+        let rotated = fs::read_to_string(dir.path().join("journal.jsonl.1")).unwrap();
+        assert!(rotated.starts_with(expected));
+        assert_eq!(rotated.lines().count(), 4);
+
+        let expected = concat!(
+            r#"{"timestamp":"2024-01-01T12:00:00+00:00","op_id":"b","op":"delete_folder","#,
+            r#""folder":"Archives","result":"error","error":"cannot find message associated to envelope 42\""}"#,
+            "\n",
+        );
+        assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    }
+}
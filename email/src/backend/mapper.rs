@@ -17,14 +17,15 @@
 use crate::envelope::watch::WatchEnvelopes;
 use crate::{
     envelope::{get::GetEnvelope, list::ListEnvelopes},
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags},
+    flag::{add::AddFlags, mark_read::MarkReadBefore, remove::RemoveFlags, set::SetFlags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
-        purge::PurgeFolder,
+        acl::ManageFolderAcl, add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder,
+        list::ListFolders, purge::PurgeFolder, quota::GetQuota,
     },
     message::{
-        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
-        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
+        add::AddMessage, attachment::GetAttachment, copy::CopyMessages, delete::DeleteMessages,
+        get::GetMessages, peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages,
+        send::SendMessage,
     },
 };
 
@@ -71,6 +72,8 @@ fn map_feature<T: ?Sized + 'static>(
     some_feature_mapper!(ExpungeFolder);
     some_feature_mapper!(PurgeFolder);
     some_feature_mapper!(DeleteFolder);
+    some_feature_mapper!(GetQuota);
+    some_feature_mapper!(ManageFolderAcl);
     some_feature_mapper!(GetEnvelope);
     some_feature_mapper!(ListEnvelopes);
     #[cfg(feature = "thread")]
@@ -80,10 +83,12 @@ fn map_feature<T: ?Sized + 'static>(
     some_feature_mapper!(AddFlags);
     some_feature_mapper!(SetFlags);
     some_feature_mapper!(RemoveFlags);
+    some_feature_mapper!(MarkReadBefore);
     some_feature_mapper!(AddMessage);
     some_feature_mapper!(SendMessage);
     some_feature_mapper!(PeekMessages);
     some_feature_mapper!(GetMessages);
+    some_feature_mapper!(GetAttachment);
     some_feature_mapper!(CopyMessages);
     some_feature_mapper!(MoveMessages);
     some_feature_mapper!(DeleteMessages);
@@ -135,6 +140,8 @@ fn map_feature<T: ?Sized + 'static>(
     feature_mapper!(ExpungeFolder);
     feature_mapper!(PurgeFolder);
     feature_mapper!(DeleteFolder);
+    feature_mapper!(GetQuota);
+    feature_mapper!(ManageFolderAcl);
     feature_mapper!(GetEnvelope);
     feature_mapper!(ListEnvelopes);
     #[cfg(feature = "thread")]
@@ -144,10 +151,12 @@ fn map_feature<T: ?Sized + 'static>(
     feature_mapper!(AddFlags);
     feature_mapper!(SetFlags);
     feature_mapper!(RemoveFlags);
+    feature_mapper!(MarkReadBefore);
     feature_mapper!(AddMessage);
     feature_mapper!(SendMessage);
     feature_mapper!(PeekMessages);
     feature_mapper!(GetMessages);
+    feature_mapper!(GetAttachment);
     feature_mapper!(CopyMessages);
     feature_mapper!(MoveMessages);
     feature_mapper!(DeleteMessages);
@@ -0,0 +1,167 @@
+//! # Backend middleware
+//!
+//! A [`BackendMiddleware`] wraps every feature call of a
+//! [`super::Backend`]. It is useful to add cross-cutting behaviour
+//! like auditing, metrics or access control, without having to fork
+//! feature implementations.
+//!
+//! Middlewares are registered at [`super::BackendBuilder`] level,
+//! see [`super::BackendBuilder::with_feature_middleware`]. They are
+//! executed in registration order: the first registered middleware
+//! is the outermost one.
+//!
+//! A middleware can run code before and after the feature, observe
+//! its result or abort the call by returning an error without
+//! running the rest of the chain. Feature arguments and outputs are
+//! not exposed, since they differ for each feature.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+
+use crate::{envelope::Id, AnyResult};
+
+/// The names of the features mutating the mailbox.
+pub const MUTATING_FEATURES: &[&str] = &[
+    "add_folder",
+    "expunge_folder",
+    "purge_folder",
+    "delete_folder",
+    "set_folder_acl",
+    "delete_folder_acl",
+    "add_flags",
+    "set_flags",
+    "remove_flags",
+    "mark_read_before",
+    "add_message_with_flags",
+    "add_messages_with_flags",
+    "send_message",
+    "get_messages",
+    "copy_messages",
+    "move_messages",
+    "delete_messages",
+    "remove_messages",
+];
+
+/// The backend operation being executed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BackendOperation {
+    /// The name of the feature method, for example `list_envelopes`.
+    pub feature: &'static str,
+
+    /// The folder the operation applies to, if any.
+    ///
+    /// For operations involving two folders (copy and move), this is
+    /// the source folder.
+    pub folder: Option<String>,
+
+    /// The target folder of operations involving two folders (copy
+    /// and move).
+    pub target_folder: Option<String>,
+
+    /// The ids of the envelopes the operation applies to, if any.
+    pub ids: Vec<String>,
+}
+
+impl BackendOperation {
+    pub fn new(feature: &'static str) -> Self {
+        Self {
+            feature,
+            folder: None,
+            target_folder: None,
+            ids: Vec::new(),
+        }
+    }
+
+    /// Return `true` if the operation mutates the mailbox.
+    ///
+    /// Getting messages is considered mutating, since it may mark
+    /// them as seen.
+    pub fn is_mutating(&self) -> bool {
+        MUTATING_FEATURES.contains(&self.feature)
+    }
+
+    pub fn set_folder(&mut self, folder: impl ToString) {
+        self.folder = Some(folder.to_string());
+    }
+
+    pub fn with_folder(mut self, folder: impl ToString) -> Self {
+        self.set_folder(folder);
+        self
+    }
+
+    pub fn set_target_folder(&mut self, folder: impl ToString) {
+        self.target_folder = Some(folder.to_string());
+    }
+
+    pub fn with_target_folder(mut self, folder: impl ToString) -> Self {
+        self.set_target_folder(folder);
+        self
+    }
+
+    pub fn set_ids(&mut self, id: &Id) {
+        self.ids = id.iter().map(ToOwned::to_owned).collect();
+    }
+
+    pub fn with_ids(mut self, id: &Id) -> Self {
+        self.set_ids(id);
+        self
+    }
+}
+
+/// The rest of the middleware chain.
+///
+/// Running it executes the next middlewares, then the feature
+/// itself.
+pub struct BackendNext<'a>(BoxFuture<'a, AnyResult<()>>);
+
+impl<'a> BackendNext<'a> {
+    pub fn new(next: BoxFuture<'a, AnyResult<()>>) -> Self {
+        Self(next)
+    }
+
+    /// Run the rest of the middleware chain.
+    pub async fn run(self) -> AnyResult<()> {
+        self.0.await
+    }
+}
+
+/// The backend middleware.
+///
+/// This trait is implemented for functions taking the operation and
+/// the rest of the chain, and returning a boxed future:
+///
+/// ```rust,ignore
+/// fn log<'a>(op: &'a BackendOperation, next: BackendNext<'a>) -> BoxFuture<'a, AnyResult<()>> {
+///     Box::pin(async move {
+///         println!("running {}", op.feature);
+///         next.run().await
+///     })
+/// }
+///
+/// builder.with_feature_middleware(log)
+/// ```
+#[async_trait]
+pub trait BackendMiddleware: Send + Sync {
+    /// Handle the given operation.
+    ///
+    /// Implementations are expected to call [`BackendNext::run`] in
+    /// order to execute the feature.
+    async fn handle<'a>(&self, op: &'a BackendOperation, next: BackendNext<'a>) -> AnyResult<()>;
+}
+
+#[async_trait]
+impl<F> BackendMiddleware for F
+where
+    F: for<'a> Fn(&'a BackendOperation, BackendNext<'a>) -> BoxFuture<'a, AnyResult<()>>
+        + Send
+        + Sync,
+{
+    async fn handle<'a>(&self, op: &'a BackendOperation, next: BackendNext<'a>) -> AnyResult<()> {
+        self(op, next).await
+    }
+}
+
+/// The shareable backend middleware.
+pub type BackendMiddlewares = Vec<Arc<dyn BackendMiddleware>>;
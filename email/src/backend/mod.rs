@@ -42,51 +42,88 @@
 pub mod context;
 mod error;
 pub mod feature;
+pub mod handle;
+pub mod journal;
 pub mod mapper;
+pub mod middleware;
+pub mod prefetch;
 pub mod macros {
     pub use email_macros::BackendContext;
 }
 
-#[cfg(feature = "sync")]
-use std::hash::DefaultHasher;
-use std::sync::Arc;
+#[cfg(feature = "watch")]
+use std::time::Duration;
+use std::{
+    collections::HashSet,
+    future::Future,
+    sync::{Arc, Mutex},
+};
 
 use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use futures::{io::Cursor, AsyncRead};
 use paste::paste;
+use tokio::task::JoinHandle;
 #[cfg(feature = "watch")]
-use tokio::sync::oneshot::{Receiver, Sender};
+use tokio::{
+    sync::oneshot::{Receiver, Sender},
+    time::sleep,
+};
+use tracing::{debug, warn};
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
 use self::{
     context::{BackendContext, BackendContextBuilder},
-    feature::{BackendFeature, BackendFeatureSource, CheckUp},
+    feature::{
+        BackendFeature, BackendFeatureKind, BackendFeatureSource, BackendFeatureSupport,
+        BackendFeatures, CheckUp,
+    },
+    handle::BackendHandle,
+    journal::Journal,
+    middleware::{BackendMiddleware, BackendMiddlewares, BackendNext, BackendOperation},
+    prefetch::PrefetchCache,
 };
-#[cfg(feature = "watch")]
-use crate::envelope::watch::WatchEnvelopes;
 #[cfg(feature = "thread")]
 use crate::envelope::{thread::ThreadEnvelopes, ThreadedEnvelopes};
 #[cfg(feature = "sync")]
-use crate::sync::hash::SyncHash;
+use crate::sync::hash::{SyncHash, SyncHasher};
 use crate::{
     account::config::{AccountConfig, HasAccountConfig},
     envelope::{
         get::GetEnvelope,
         list::{ListEnvelopes, ListEnvelopesOptions},
-        Envelope, Envelopes, Id, SingleId,
+        Envelope, Envelopes, Id, IdMapping, SingleId, StableId,
+    },
+    flag::{
+        add::AddFlags, mark_read::MarkReadBefore, remove::RemoveFlags, set::SetFlags, Flag, Flags,
     },
-    flag::{add::AddFlags, remove::RemoveFlags, set::SetFlags, Flags},
     folder::{
-        add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
-        purge::PurgeFolder, Folders,
+        acl::{AclEntry, AclRights, AclRightsModification, ManageFolderAcl},
+        add::AddFolder,
+        delete::DeleteFolder,
+        expunge::ExpungeFolder,
+        list::ListFolders,
+        purge::PurgeFolder,
+        quota::{GetQuota, Quota},
+        Folders,
     },
     message::{
-        add::AddMessage, copy::CopyMessages, delete::DeleteMessages, get::GetMessages,
-        peek::PeekMessages, r#move::MoveMessages, remove::RemoveMessages, send::SendMessage,
-        Messages,
+        add::AddMessage,
+        attachment::{AttachmentStore, GetAttachment, PartPath, SharedAttachment},
+        copy::CopyMessages,
+        delete::DeleteMessages,
+        get::GetMessages,
+        peek::PeekMessages,
+        r#move::MoveMessages,
+        remove::RemoveMessages,
+        send::SendMessage,
+        Message, Messages,
     },
     AnyResult,
 };
+#[cfg(feature = "watch")]
+use crate::{envelope::watch::WatchEnvelopes, message::delete::retention::PurgeExpiredMessages};
 
 /// The basic backend implementation.
 ///
@@ -105,6 +142,20 @@ pub struct Backend<C>
     /// The backend context.
     pub context: Arc<C>,
 
+    /// The middlewares wrapping every feature call.
+    pub middlewares: BackendMiddlewares,
+
+    /// The cache filled by [`Backend::prefetch`].
+    pub prefetch_cache: PrefetchCache,
+
+    /// The store deduplicating attachments, see
+    /// [`Backend::get_messages_attachments`].
+    pub attachment_store: AttachmentStore,
+
+    /// The folders whose identifiers changed, see
+    /// [`Backend::take_invalidated_folder`].
+    invalidated_folders: Arc<Mutex<HashSet<String>>>,
+
     /// The add folder backend feature.
     pub add_folder: Option<BackendFeature<C, dyn AddFolder>>,
     /// The list folders backend feature.
@@ -115,6 +166,10 @@ pub struct Backend<C>
     pub purge_folder: Option<BackendFeature<C, dyn PurgeFolder>>,
     /// The delete folder backend feature.
     pub delete_folder: Option<BackendFeature<C, dyn DeleteFolder>>,
+    /// The get quota backend feature.
+    pub get_quota: Option<BackendFeature<C, dyn GetQuota>>,
+    /// The manage folder ACL backend feature.
+    pub manage_folder_acl: Option<BackendFeature<C, dyn ManageFolderAcl>>,
 
     /// The get envelope backend feature.
     pub get_envelope: Option<BackendFeature<C, dyn GetEnvelope>>,
@@ -133,6 +188,8 @@ pub struct Backend<C>
     pub set_flags: Option<BackendFeature<C, dyn SetFlags>>,
     /// The remove flags backend feature.
     pub remove_flags: Option<BackendFeature<C, dyn RemoveFlags>>,
+    /// The mark read before backend feature.
+    pub mark_read_before: Option<BackendFeature<C, dyn MarkReadBefore>>,
 
     /// The add message backend feature.
     pub add_message: Option<BackendFeature<C, dyn AddMessage>>,
@@ -142,6 +199,8 @@ pub struct Backend<C>
     pub peek_messages: Option<BackendFeature<C, dyn PeekMessages>>,
     /// The get messages backend feature.
     pub get_messages: Option<BackendFeature<C, dyn GetMessages>>,
+    /// The get attachment backend feature.
+    pub get_attachment: Option<BackendFeature<C, dyn GetAttachment>>,
     /// The copy messages backend feature.
     pub copy_messages: Option<BackendFeature<C, dyn CopyMessages>>,
     /// The move messages backend feature.
@@ -152,6 +211,308 @@ pub struct Backend<C>
     pub remove_messages: Option<BackendFeature<C, dyn RemoveMessages>>,
 }
 
+impl<C: BackendContext> Backend<C> {
+    /// Call the given feature future through the middleware chain.
+    async fn call<T: Send>(
+        &self,
+        op: BackendOperation,
+        feature: impl Future<Output = AnyResult<T>> + Send,
+    ) -> AnyResult<T> {
+        // invalidated before and after, so that entries prefetched
+        // while the operation runs are discarded as well
+        self.prefetch_cache.invalidate(&op);
+        let output = self.call_middlewares(op.clone(), feature).await;
+        self.prefetch_cache.invalidate(&op);
+
+        // any operation selecting a folder can reveal that its
+        // identifiers changed
+        self.collect_invalidated_folders();
+
+        output
+    }
+
+    async fn call_middlewares<T: Send>(
+        &self,
+        op: BackendOperation,
+        feature: impl Future<Output = AnyResult<T>> + Send,
+    ) -> AnyResult<T> {
+        if self.middlewares.is_empty() {
+            return feature.await;
+        }
+
+        let mut output = None;
+
+        let mut next = BackendNext::new(Box::pin(async {
+            output = Some(feature.await?);
+            Ok(())
+        }));
+
+        for middleware in self.middlewares.iter().rev() {
+            next = BackendNext::new(middleware.handle(&op, next));
+        }
+
+        next.run().await?;
+
+        Ok(output.ok_or(Error::FeatureSkippedByMiddlewareError(op.feature))?)
+    }
+
+    /// Take the folders invalidated by the context, clear their
+    /// prefetched entries, then keep them until
+    /// [`Backend::take_invalidated_folder`] is called.
+    fn collect_invalidated_folders(&self) {
+        let folders = self.context.take_invalidated_folders();
+
+        if folders.is_empty() {
+            return;
+        }
+
+        let mut invalidated = self
+            .invalidated_folders
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        for folder in folders {
+            warn!("identifiers of folder {folder} changed, invalidating caches");
+            let folder = self.account_config.get_folder_alias(&folder);
+            self.prefetch_cache
+                .clear_folders(|f| self.account_config.get_folder_alias(f) == folder);
+            invalidated.insert(folder);
+        }
+    }
+
+    /// Return `true` if the identifiers of the given folder changed
+    /// since the last call, for example because the UIDVALIDITY of an
+    /// IMAP mailbox changed.
+    ///
+    /// Identifiers cached outside of the backend for this folder are
+    /// not valid anymore, they need to be dropped then rebuilt.
+    pub fn take_invalidated_folder(&self, folder: &str) -> bool {
+        self.collect_invalidated_folders();
+
+        let folder = self.account_config.get_folder_alias(folder);
+        self.invalidated_folders
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(&folder)
+    }
+
+    /// Resolve the given stable identifiers into the folder and the
+    /// identifier expected by backend features.
+    ///
+    /// Each identifier is checked by the context, see
+    /// [`BackendContext::resolve_stable_id`]. All identifiers must
+    /// belong to the same folder.
+    pub async fn resolve_stable_ids(&self, ids: &[StableId]) -> AnyResult<(String, Id)> {
+        let Some(first) = ids.first() else {
+            return Err(Error::ResolveEmptyStableIdsError.into());
+        };
+
+        let folder = first.folder().to_owned();
+        let mut resolved = Vec::with_capacity(ids.len());
+
+        for id in ids {
+            if id.folder() != folder {
+                let err =
+                    Error::ResolveStableIdsFolderMismatchError(folder, id.folder().to_owned());
+                return Err(err.into());
+            }
+
+            resolved.push(self.context.resolve_stable_id(id).await?);
+        }
+
+        let id = match resolved.len() {
+            1 => Id::Single(resolved.remove(0)),
+            _ => Id::multiple(resolved.iter().map(SingleId::as_str)),
+        };
+
+        Ok((folder, id))
+    }
+
+    /// Warm the prefetch cache up with the envelopes and messages
+    /// matching the given id, in the background.
+    ///
+    /// Prefetching has a low priority: it only runs while the account
+    /// concurrency budget has room left, and stops as soon as it does
+    /// not. Prefetching reuses the connections of the backend
+    /// context, so it does not hold any weight of the budget. Entries
+    /// already cached are skipped, and errors are only logged.
+    ///
+    /// Features are called through the middlewares, like any other
+    /// call. Entries invalidated while being fetched are discarded,
+    /// see [`PrefetchCache::generation`]. Prefetched entries are then
+    /// served by [`GetEnvelope`], [`PeekMessages`] and
+    /// [`GetMessages`].
+    pub fn prefetch(self: &Arc<Self>, folder: &str, id: &Id) -> JoinHandle<()>
+    where
+        C: 'static,
+    {
+        let backend = self.clone();
+        let budget = self.account_config.concurrency_budget();
+        let folder = folder.to_owned();
+        let ids: Vec<String> = id
+            .iter()
+            .filter(|id| !self.prefetch_cache.contains(&folder, id))
+            .map(ToOwned::to_owned)
+            .collect();
+
+        tokio::spawn(async move {
+            if backend.is_offline() {
+                debug!("backend offline, skipping prefetch");
+                return;
+            }
+
+            let get_envelope = backend
+                .get_envelope
+                .as_ref()
+                .and_then(|feature| feature(&backend.context));
+            let peek_messages = backend
+                .peek_messages
+                .as_ref()
+                .and_then(|feature| feature(&backend.context));
+            let cache = &backend.prefetch_cache;
+
+            for id in ids {
+                if budget.available() == 0 {
+                    debug!("concurrency budget exhausted, stopping prefetch");
+                    break;
+                }
+
+                if let Some(feature) = &get_envelope {
+                    let generation = cache.generation();
+                    let op = BackendOperation::new("get_envelope").with_folder(&folder);
+                    let single_id = SingleId::from(&id);
+                    let envelope = feature.get_envelope(&folder, &single_id);
+
+                    match backend.call(op, envelope).await {
+                        Ok(envelope) => {
+                            cache.insert_envelope_since(generation, &folder, envelope);
+                        }
+                        Err(err) => debug!(?err, id, "cannot prefetch envelope"),
+                    }
+                }
+
+                if let Some(feature) = &peek_messages {
+                    let generation = cache.generation();
+                    let multi_id = Id::single(&id);
+                    let op = BackendOperation::new("peek_messages")
+                        .with_folder(&folder)
+                        .with_ids(&multi_id);
+                    let msgs = feature.peek_messages(&folder, &multi_id);
+
+                    match backend.call(op, msgs).await {
+                        Ok(msgs) => match msgs.first().map(Message::raw) {
+                            Some(Ok(raw)) => {
+                                cache.insert_message_since(generation, &folder, &id, raw.to_vec());
+                            }
+                            _ => debug!(id, "cannot prefetch empty message"),
+                        },
+                        Err(err) => debug!(?err, id, "cannot prefetch message"),
+                    }
+                }
+            }
+        })
+    }
+
+    /// Get the attachments of the messages matching the given id,
+    /// without changing their flags.
+    ///
+    /// Identical attachments share the same content through the
+    /// [`Backend::attachment_store`], which keeps memory low when
+    /// fetching a whole thread where the same images (logos, footers
+    /// etc) are attached to most messages. Messages are served by
+    /// the prefetch cache when possible.
+    pub async fn get_messages_attachments(
+        &self,
+        folder: &str,
+        id: &Id,
+    ) -> AnyResult<Vec<Vec<SharedAttachment>>> {
+        let msgs = self.peek_messages(folder, id).await?;
+        Ok(msgs.shared_attachments(&self.attachment_store)?)
+    }
+
+    /// Wrap the backend into a thread-safe, clone-cheap handle.
+    ///
+    /// See [`BackendHandle`].
+    pub fn into_handle(self) -> BackendHandle<C>
+    where
+        C: 'static,
+    {
+        BackendHandle::new(self)
+    }
+
+    /// Return `true` if the given feature is wired to the backend.
+    ///
+    /// This does not tell if the feature actually works, see
+    /// [`Backend::supported_features`].
+    pub fn has_feature(&self, feature: BackendFeatureKind) -> bool {
+        fn is_available<C, F: ?Sized>(ctx: &C, f: &Option<BackendFeature<C, F>>) -> bool {
+            f.as_ref().and_then(|f| f(ctx)).is_some()
+        }
+
+        let ctx = self.context.as_ref();
+
+        match feature {
+            BackendFeatureKind::AddFolder => is_available(ctx, &self.add_folder),
+            BackendFeatureKind::ListFolders => is_available(ctx, &self.list_folders),
+            BackendFeatureKind::ExpungeFolder => is_available(ctx, &self.expunge_folder),
+            BackendFeatureKind::PurgeFolder => is_available(ctx, &self.purge_folder),
+            BackendFeatureKind::DeleteFolder => is_available(ctx, &self.delete_folder),
+            BackendFeatureKind::GetQuota => is_available(ctx, &self.get_quota),
+            BackendFeatureKind::ManageFolderAcl => is_available(ctx, &self.manage_folder_acl),
+            BackendFeatureKind::GetEnvelope => is_available(ctx, &self.get_envelope),
+            BackendFeatureKind::ListEnvelopes => is_available(ctx, &self.list_envelopes),
+            #[cfg(feature = "thread")]
+            BackendFeatureKind::ThreadEnvelopes => is_available(ctx, &self.thread_envelopes),
+            #[cfg(feature = "watch")]
+            BackendFeatureKind::WatchEnvelopes => is_available(ctx, &self.watch_envelopes),
+            BackendFeatureKind::AddFlags => is_available(ctx, &self.add_flags),
+            BackendFeatureKind::SetFlags => is_available(ctx, &self.set_flags),
+            BackendFeatureKind::RemoveFlags => is_available(ctx, &self.remove_flags),
+            BackendFeatureKind::MarkReadBefore => is_available(ctx, &self.mark_read_before),
+            BackendFeatureKind::AddMessage => is_available(ctx, &self.add_message),
+            BackendFeatureKind::SendMessage => is_available(ctx, &self.send_message),
+            BackendFeatureKind::PeekMessages => is_available(ctx, &self.peek_messages),
+            BackendFeatureKind::GetMessages => is_available(ctx, &self.get_messages),
+            BackendFeatureKind::GetAttachment => is_available(ctx, &self.get_attachment),
+            BackendFeatureKind::CopyMessages => is_available(ctx, &self.copy_messages),
+            BackendFeatureKind::MoveMessages => is_available(ctx, &self.move_messages),
+            BackendFeatureKind::DeleteMessages => is_available(ctx, &self.delete_messages),
+            BackendFeatureKind::RemoveMessages => is_available(ctx, &self.remove_messages),
+        }
+    }
+
+    /// Return the features supported by the backend, with their
+    /// level of support.
+    ///
+    /// A feature is supported when it is wired to the backend, and
+    /// when the context does not consider it as unsupported (for
+    /// example because of a missing server capability, see
+    /// [`BackendContext::feature_support`]). User interfaces can use
+    /// it to hide actions that would fail at runtime.
+    pub fn supported_features(&self) -> BackendFeatures {
+        BackendFeatureKind::all()
+            .filter(|feature| self.has_feature(*feature))
+            .map(|feature| (feature, self.context.feature_support(feature)))
+            .filter(|(_, support)| *support != BackendFeatureSupport::Unsupported)
+            .collect()
+    }
+
+    /// Return `true` if the backend is in offline mode.
+    pub fn is_offline(&self) -> bool {
+        self.account_config.is_offline()
+    }
+
+    /// Enable or disable the offline mode.
+    ///
+    /// When enabled, network-backed features fail fast with
+    /// [`Error::OfflineError`] instead of hanging on timeouts. The
+    /// switch is shared by all backends of the same account, see
+    /// [`AccountConfig::set_offline`].
+    pub fn set_offline(&self, offline: bool) {
+        self.account_config.set_offline(offline)
+    }
+}
+
 impl<C: BackendContext> HasAccountConfig for Backend<C> {
     fn account_config(&self) -> &AccountConfig {
         &self.account_config
@@ -161,72 +522,157 @@ fn account_config(&self) -> &AccountConfig {
 #[async_trait]
 impl<C: BackendContext> AddFolder for Backend<C> {
     async fn add_folder(&self, folder: &str) -> AnyResult<()> {
-        self.add_folder
+        let feature = self
+            .add_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFolderNotAvailableError)?
-            .add_folder(folder)
-            .await
+            .ok_or(Error::AddFolderNotAvailableError)?;
+
+        let op = BackendOperation::new("add_folder").with_folder(folder);
+        self.call(op, feature.add_folder(folder)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ListFolders for Backend<C> {
     async fn list_folders(&self) -> AnyResult<Folders> {
-        self.list_folders
+        let feature = self
+            .list_folders
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListFoldersNotAvailableError)?
-            .list_folders()
-            .await
+            .ok_or(Error::ListFoldersNotAvailableError)?;
+
+        let op = BackendOperation::new("list_folders");
+        self.call(op, feature.list_folders()).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> ExpungeFolder for Backend<C> {
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.expunge_folder
+        let feature = self
+            .expunge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ExpungeFolderNotAvailableError)?
-            .expunge_folder(folder)
-            .await
+            .ok_or(Error::ExpungeFolderNotAvailableError)?;
+
+        let op = BackendOperation::new("expunge_folder").with_folder(folder);
+        self.call(op, feature.expunge_folder(folder)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PurgeFolder for Backend<C> {
     async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
-        self.purge_folder
+        let feature = self
+            .purge_folder
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PurgeFolderNotAvailableError)?
-            .purge_folder(folder)
-            .await
+            .ok_or(Error::PurgeFolderNotAvailableError)?;
+
+        let op = BackendOperation::new("purge_folder").with_folder(folder);
+        self.call(op, feature.purge_folder(folder)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteFolder for Backend<C> {
     async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
-        self.delete_folder
+        let feature = self
+            .delete_folder
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::DeleteFolderNotAvailableError)?;
+
+        let op = BackendOperation::new("delete_folder").with_folder(folder);
+        self.call(op, feature.delete_folder(folder)).await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetQuota for Backend<C> {
+    async fn get_quota(&self, folder: &str) -> AnyResult<Quota> {
+        let feature = self
+            .get_quota
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::GetQuotaNotAvailableError)?;
+
+        let op = BackendOperation::new("get_quota").with_folder(folder);
+        self.call(op, feature.get_quota(folder)).await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> ManageFolderAcl for Backend<C> {
+    async fn get_folder_acl(&self, folder: &str) -> AnyResult<Vec<AclEntry>> {
+        let feature = self
+            .manage_folder_acl
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ManageFolderAclNotAvailableError)?;
+
+        let op = BackendOperation::new("get_folder_acl").with_folder(folder);
+        self.call(op, feature.get_folder_acl(folder)).await
+    }
+
+    async fn set_folder_acl(
+        &self,
+        folder: &str,
+        identifier: &str,
+        modification: &AclRightsModification,
+    ) -> AnyResult<()> {
+        let feature = self
+            .manage_folder_acl
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ManageFolderAclNotAvailableError)?;
+
+        let op = BackendOperation::new("set_folder_acl").with_folder(folder);
+        self.call(op, feature.set_folder_acl(folder, identifier, modification))
+            .await
+    }
+
+    async fn delete_folder_acl(&self, folder: &str, identifier: &str) -> AnyResult<()> {
+        let feature = self
+            .manage_folder_acl
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteFolderNotAvailableError)?
-            .delete_folder(folder)
+            .ok_or(Error::ManageFolderAclNotAvailableError)?;
+
+        let op = BackendOperation::new("delete_folder_acl").with_folder(folder);
+        self.call(op, feature.delete_folder_acl(folder, identifier))
             .await
     }
+
+    async fn get_folder_rights(&self, folder: &str) -> AnyResult<AclRights> {
+        let feature = self
+            .manage_folder_acl
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::ManageFolderAclNotAvailableError)?;
+
+        let op = BackendOperation::new("get_folder_rights").with_folder(folder);
+        self.call(op, feature.get_folder_rights(folder)).await
+    }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetEnvelope for Backend<C> {
     async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
-        self.get_envelope
+        if let Some(envelope) = self.prefetch_cache.get_envelope(folder, id) {
+            debug!(folder, id = id.as_str(), "envelope found in prefetch cache");
+            return Ok(envelope);
+        }
+
+        let feature = self
+            .get_envelope
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetEnvelopeNotAvailableError)?
-            .get_envelope(folder, id)
-            .await
+            .ok_or(Error::GetEnvelopeNotAvailableError)?;
+
+        let op = BackendOperation::new("get_envelope").with_folder(folder);
+        self.call(op, feature.get_envelope(folder, id)).await
     }
 }
 
@@ -237,12 +683,14 @@ async fn list_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<Envelopes> {
-        self.list_envelopes
+        let feature = self
+            .list_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ListEnvelopesNotAvailableError)?
-            .list_envelopes(folder, opts)
-            .await
+            .ok_or(Error::ListEnvelopesNotAvailableError)?;
+
+        let op = BackendOperation::new("list_envelopes").with_folder(folder);
+        self.call(op, feature.list_envelopes(folder, opts)).await
     }
 }
 
@@ -254,12 +702,14 @@ async fn thread_envelopes(
         folder: &str,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
+        let feature = self
+            .thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelopes(folder, opts)
-            .await
+            .ok_or(Error::ThreadEnvelopesNotAvailableError)?;
+
+        let op = BackendOperation::new("thread_envelopes").with_folder(folder);
+        self.call(op, feature.thread_envelopes(folder, opts)).await
     }
 
     async fn thread_envelope(
@@ -268,11 +718,14 @@ async fn thread_envelope(
         id: SingleId,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        self.thread_envelopes
+        let feature = self
+            .thread_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::ThreadEnvelopesNotAvailableError)?
-            .thread_envelope(folder, id, opts)
+            .ok_or(Error::ThreadEnvelopesNotAvailableError)?;
+
+        let op = BackendOperation::new("thread_envelope").with_folder(folder);
+        self.call(op, feature.thread_envelope(folder, id, opts))
             .await
     }
 }
@@ -286,11 +739,61 @@ async fn watch_envelopes(
         wait_for_shutdown_request: Receiver<()>,
         shutdown: Sender<()>,
     ) -> AnyResult<()> {
-        self.watch_envelopes
+        let feature = self
+            .watch_envelopes
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::WatchEnvelopesNotAvailableError)?;
+
+        if self.account_config.should_auto_apply_message_retention() {
+            self.apply_message_retention().await;
+        }
+
+        let op = BackendOperation::new("watch_envelopes").with_folder(folder);
+        self.call(
+            op,
+            feature.watch_envelopes(folder, wait_for_shutdown_request, shutdown),
+        )
+        .await
+    }
+
+    async fn wait_for_envelopes_change(&self, folder: &str, timeout: Duration) -> AnyResult<()> {
+        let Some(feature) = self
+            .watch_envelopes
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+        else {
+            // without watcher, callers fall back to polling
+            sleep(timeout).await;
+            return Ok(());
+        };
+
+        let op = BackendOperation::new("wait_for_envelopes_change").with_folder(folder);
+        self.call(op, feature.wait_for_envelopes_change(folder, timeout))
+            .await
+    }
+
+    async fn wait_for_folders_change(
+        &self,
+        folders: &[String],
+        timeout: Duration,
+    ) -> AnyResult<()> {
+        let Some(feature) = self
+            .watch_envelopes
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::WatchEnvelopesNotAvailableError)?
-            .watch_envelopes(folder, wait_for_shutdown_request, shutdown)
+        else {
+            // without watcher, callers fall back to polling
+            sleep(timeout).await;
+            return Ok(());
+        };
+
+        let mut op = BackendOperation::new("wait_for_folders_change");
+        if let Some(folder) = folders.first() {
+            op.set_folder(folder);
+        }
+
+        self.call(op, feature.wait_for_folders_change(folders, timeout))
             .await
     }
 }
@@ -298,36 +801,62 @@ async fn watch_envelopes(
 #[async_trait]
 impl<C: BackendContext> AddFlags for Backend<C> {
     async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.add_flags
+        let feature = self
+            .add_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddFlagsNotAvailableError)?
-            .add_flags(folder, id, flags)
-            .await
+            .ok_or(Error::AddFlagsNotAvailableError)?;
+
+        let op = BackendOperation::new("add_flags")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.add_flags(folder, id, flags)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> SetFlags for Backend<C> {
     async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.set_flags
+        let feature = self
+            .set_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SetFlagsNotAvailableError)?
-            .set_flags(folder, id, flags)
-            .await
+            .ok_or(Error::SetFlagsNotAvailableError)?;
+
+        let op = BackendOperation::new("set_flags")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.set_flags(folder, id, flags)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveFlags for Backend<C> {
     async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
-        self.remove_flags
+        let feature = self
+            .remove_flags
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveFlagsNotAvailableError)?
-            .remove_flags(folder, id, flags)
-            .await
+            .ok_or(Error::RemoveFlagsNotAvailableError)?;
+
+        let op = BackendOperation::new("remove_flags")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.remove_flags(folder, id, flags)).await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> MarkReadBefore for Backend<C> {
+    async fn mark_read_before(&self, folder: &str, date: DateTime<FixedOffset>) -> AnyResult<()> {
+        let feature = self
+            .mark_read_before
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::MarkReadBeforeNotAvailableError)?;
+
+        let op = BackendOperation::new("mark_read_before").with_folder(folder);
+        self.call(op, feature.mark_read_before(folder, date)).await
     }
 }
 
@@ -339,11 +868,30 @@ async fn add_message_with_flags(
         msg: &[u8],
         flags: &Flags,
     ) -> AnyResult<SingleId> {
-        self.add_message
+        let feature = self
+            .add_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::AddMessageNotAvailableError)?
-            .add_message_with_flags(folder, msg, flags)
+            .ok_or(Error::AddMessageNotAvailableError)?;
+
+        let op = BackendOperation::new("add_message_with_flags").with_folder(folder);
+        self.call(op, feature.add_message_with_flags(folder, msg, flags))
+            .await
+    }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: Vec<(Flags, Vec<u8>)>,
+    ) -> AnyResult<Vec<SingleId>> {
+        let feature = self
+            .add_message
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::AddMessageNotAvailableError)?;
+
+        let op = BackendOperation::new("add_messages_with_flags").with_folder(folder);
+        self.call(op, feature.add_messages_with_flags(folder, msgs))
             .await
     }
 }
@@ -351,84 +899,219 @@ async fn add_message_with_flags(
 #[async_trait]
 impl<C: BackendContext> SendMessage for Backend<C> {
     async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
-        self.send_message
+        let feature = self
+            .send_message
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::SendMessageNotAvailableError)?
-            .send_message(msg)
-            .await
+            .ok_or(Error::SendMessageNotAvailableError)?;
+
+        let op = BackendOperation::new("send_message");
+        self.call(op, feature.send_message(msg)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> PeekMessages for Backend<C> {
     async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.peek_messages
+        if let Some(msgs) = self.prefetch_cache.get_messages(folder, id) {
+            debug!(folder, %id, "messages found in prefetch cache");
+            return Ok(msgs);
+        }
+
+        let feature = self
+            .peek_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::PeekMessagesNotAvailableError)?
-            .peek_messages(folder, id)
-            .await
+            .ok_or(Error::PeekMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("peek_messages")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.peek_messages(folder, id)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> GetMessages for Backend<C> {
     async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
-        self.get_messages
+        if let Some(msgs) = self.prefetch_cache.get_messages(folder, id) {
+            if self.add_flags.is_some() {
+                debug!(folder, %id, "messages found in prefetch cache");
+                self.add_flag(folder, id, Flag::Seen).await?;
+                return Ok(msgs);
+            }
+        }
+
+        let feature = self
+            .get_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::GetMessagesNotAvailableError)?
-            .get_messages(folder, id)
-            .await
+            .ok_or(Error::GetMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("get_messages")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.get_messages(folder, id)).await
+    }
+}
+
+#[async_trait]
+impl<C: BackendContext> GetAttachment for Backend<C> {
+    /// Get the decoded content of the MIME part at the given path.
+    ///
+    /// Falls back to peeking the whole message when the backend does
+    /// not implement the feature.
+    async fn get_attachment(
+        &self,
+        folder: &str,
+        id: &SingleId,
+        path: &PartPath,
+    ) -> AnyResult<Box<dyn AsyncRead + Send + Unpin>> {
+        let op = BackendOperation::new("get_attachment")
+            .with_folder(folder)
+            .with_ids(&Id::from(id));
+
+        if let Some(feature) = self
+            .get_attachment
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+        {
+            return self
+                .call(op, feature.get_attachment(folder, id, path))
+                .await;
+        }
+
+        let feature = self
+            .peek_messages
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::GetAttachmentNotAvailableError)?;
+
+        let peek = async {
+            let msgs = feature.peek_messages(folder, &Id::from(id)).await?;
+            let msg = msgs.first().ok_or_else(|| {
+                crate::email::Error::GetMessagePartNotFoundError(path.to_string())
+            })?;
+            let part: Box<dyn AsyncRead + Send + Unpin> =
+                Box::new(Cursor::new(msg.get_part(path)?));
+            AnyResult::Ok(part)
+        };
+
+        self.call(op, peek).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> CopyMessages for Backend<C> {
     async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.copy_messages
+        let feature = self
+            .copy_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::CopyMessagesNotAvailableError)?
-            .copy_messages(from_folder, to_folder, id)
+            .ok_or(Error::CopyMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("copy_messages")
+            .with_folder(from_folder)
+            .with_target_folder(to_folder)
+            .with_ids(id);
+        self.call(op, feature.copy_messages(from_folder, to_folder, id))
             .await
     }
+
+    async fn copy_messages_with_mapping(
+        &self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+    ) -> AnyResult<IdMapping> {
+        let feature = self
+            .copy_messages
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::CopyMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("copy_messages_with_mapping")
+            .with_folder(from_folder)
+            .with_target_folder(to_folder)
+            .with_ids(id);
+        self.call(
+            op,
+            feature.copy_messages_with_mapping(from_folder, to_folder, id),
+        )
+        .await
+    }
 }
 
 #[async_trait]
 impl<C: BackendContext> MoveMessages for Backend<C> {
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
-        self.move_messages
+        let feature = self
+            .move_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::MoveMessagesNotAvailableError)?
-            .move_messages(from_folder, to_folder, id)
+            .ok_or(Error::MoveMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("move_messages")
+            .with_folder(from_folder)
+            .with_target_folder(to_folder)
+            .with_ids(id);
+        self.call(op, feature.move_messages(from_folder, to_folder, id))
             .await
     }
+
+    async fn move_messages_with_mapping(
+        &self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+    ) -> AnyResult<IdMapping> {
+        let feature = self
+            .move_messages
+            .as_ref()
+            .and_then(|feature| feature(&self.context))
+            .ok_or(Error::MoveMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("move_messages_with_mapping")
+            .with_folder(from_folder)
+            .with_target_folder(to_folder)
+            .with_ids(id);
+        self.call(
+            op,
+            feature.move_messages_with_mapping(from_folder, to_folder, id),
+        )
+        .await
+    }
 }
 
 #[async_trait]
 impl<C: BackendContext> DeleteMessages for Backend<C> {
     async fn delete_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.delete_messages
+        let feature = self
+            .delete_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::DeleteMessagesNotAvailableError)?
-            .delete_messages(folder, id)
-            .await
+            .ok_or(Error::DeleteMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("delete_messages")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.delete_messages(folder, id)).await
     }
 }
 
 #[async_trait]
 impl<C: BackendContext> RemoveMessages for Backend<C> {
     async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
-        self.remove_messages
+        let feature = self
+            .remove_messages
             .as_ref()
             .and_then(|feature| feature(&self.context))
-            .ok_or(Error::RemoveMessagesNotAvailableError)?
-            .remove_messages(folder, id)
-            .await
+            .ok_or(Error::RemoveMessagesNotAvailableError)?;
+
+        let op = BackendOperation::new("remove_messages")
+            .with_folder(folder)
+            .with_ids(id);
+        self.call(op, feature.remove_messages(folder, id)).await
     }
 }
 
@@ -505,6 +1188,15 @@ pub struct BackendBuilder<CB>
     /// The backend context builder.
     pub ctx_builder: CB,
 
+    /// The middlewares wrapping every feature call.
+    pub middlewares: BackendMiddlewares,
+
+    /// The features that must be available once the backend is
+    /// built.
+    ///
+    /// See [`BackendBuilder::with_required_features`].
+    pub required_features: Vec<BackendFeatureKind>,
+
     /// The noop backend builder feature.
     pub check_up: BackendFeatureSource<CB::Context, dyn CheckUp>,
 
@@ -518,6 +1210,10 @@ pub struct BackendBuilder<CB>
     pub purge_folder: BackendFeatureSource<CB::Context, dyn PurgeFolder>,
     /// The delete folder backend builder feature.
     pub delete_folder: BackendFeatureSource<CB::Context, dyn DeleteFolder>,
+    /// The get quota backend builder feature.
+    pub get_quota: BackendFeatureSource<CB::Context, dyn GetQuota>,
+    /// The manage folder ACL backend builder feature.
+    pub manage_folder_acl: BackendFeatureSource<CB::Context, dyn ManageFolderAcl>,
 
     /// The get envelope backend builder feature.
     pub get_envelope: BackendFeatureSource<CB::Context, dyn GetEnvelope>,
@@ -536,6 +1232,8 @@ pub struct BackendBuilder<CB>
     pub set_flags: BackendFeatureSource<CB::Context, dyn SetFlags>,
     /// The remove flags backend builder feature.
     pub remove_flags: BackendFeatureSource<CB::Context, dyn RemoveFlags>,
+    /// The mark read before backend builder feature.
+    pub mark_read_before: BackendFeatureSource<CB::Context, dyn MarkReadBefore>,
 
     /// The add message backend builder feature.
     pub add_message: BackendFeatureSource<CB::Context, dyn AddMessage>,
@@ -545,6 +1243,8 @@ pub struct BackendBuilder<CB>
     pub peek_messages: BackendFeatureSource<CB::Context, dyn PeekMessages>,
     /// The get messages backend builder feature.
     pub get_messages: BackendFeatureSource<CB::Context, dyn GetMessages>,
+    /// The get attachment backend builder feature.
+    pub get_attachment: BackendFeatureSource<CB::Context, dyn GetAttachment>,
     /// The copy messages backend builder feature.
     pub copy_messages: BackendFeatureSource<CB::Context, dyn CopyMessages>,
     /// The move messages backend builder feature.
@@ -565,6 +1265,8 @@ impl<CB> BackendBuilder<CB>
     feature_accessors!(ExpungeFolder);
     feature_accessors!(PurgeFolder);
     feature_accessors!(DeleteFolder);
+    feature_accessors!(GetQuota);
+    feature_accessors!(ManageFolderAcl);
     feature_accessors!(GetEnvelope);
     feature_accessors!(ListEnvelopes);
     #[cfg(feature = "thread")]
@@ -574,10 +1276,12 @@ impl<CB> BackendBuilder<CB>
     feature_accessors!(AddFlags);
     feature_accessors!(SetFlags);
     feature_accessors!(RemoveFlags);
+    feature_accessors!(MarkReadBefore);
     feature_accessors!(AddMessage);
     feature_accessors!(SendMessage);
     feature_accessors!(PeekMessages);
     feature_accessors!(GetMessages);
+    feature_accessors!(GetAttachment);
     feature_accessors!(CopyMessages);
     feature_accessors!(MoveMessages);
     feature_accessors!(DeleteMessages);
@@ -588,9 +1292,22 @@ impl<CB> BackendBuilder<CB>
     ///
     /// All features are taken from the context by default.
     pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
+        let mut middlewares: BackendMiddlewares = Vec::new();
+
+        if let Some(config) = &account_config.journal {
+            let clock = account_config.clock.clone();
+            middlewares.push(Arc::new(Journal::new(config, clock)));
+        }
+
+        if let Some(log) = account_config.health_log() {
+            middlewares.push(Arc::new(log));
+        }
+
         Self {
             account_config,
             ctx_builder,
+            middlewares,
+            required_features: Vec::new(),
 
             check_up: BackendFeatureSource::Context,
 
@@ -599,6 +1316,8 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             expunge_folder: BackendFeatureSource::Context,
             purge_folder: BackendFeatureSource::Context,
             delete_folder: BackendFeatureSource::Context,
+            get_quota: BackendFeatureSource::Context,
+            manage_folder_acl: BackendFeatureSource::Context,
 
             get_envelope: BackendFeatureSource::Context,
             list_envelopes: BackendFeatureSource::Context,
@@ -610,11 +1329,13 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
             add_flags: BackendFeatureSource::Context,
             set_flags: BackendFeatureSource::Context,
             remove_flags: BackendFeatureSource::Context,
+            mark_read_before: BackendFeatureSource::Context,
 
             add_message: BackendFeatureSource::Context,
             send_message: BackendFeatureSource::Context,
             peek_messages: BackendFeatureSource::Context,
             get_messages: BackendFeatureSource::Context,
+            get_attachment: BackendFeatureSource::Context,
             copy_messages: BackendFeatureSource::Context,
             move_messages: BackendFeatureSource::Context,
             delete_messages: BackendFeatureSource::Context,
@@ -622,6 +1343,43 @@ pub fn new(account_config: Arc<AccountConfig>, ctx_builder: CB) -> Self {
         }
     }
 
+    /// Add the given middleware, wrapping every feature call of the
+    /// built backend.
+    ///
+    /// See [`middleware`] for more details.
+    pub fn add_feature_middleware(&mut self, middleware: impl BackendMiddleware + 'static) {
+        self.middlewares.push(Arc::new(middleware));
+    }
+
+    /// Add the given middleware, using the builder pattern.
+    pub fn with_feature_middleware(mut self, middleware: impl BackendMiddleware + 'static) -> Self {
+        self.add_feature_middleware(middleware);
+        self
+    }
+
+    /// Require the given features to be available once the backend
+    /// is built.
+    ///
+    /// Features are resolved lazily, which means that a missing
+    /// feature is usually detected only when it is called. Required
+    /// features are instead validated by [`BackendBuilder::build`],
+    /// which fails with an error listing all the missing ones.
+    pub fn set_required_features(
+        &mut self,
+        features: impl IntoIterator<Item = BackendFeatureKind>,
+    ) {
+        self.required_features = features.into_iter().collect();
+    }
+
+    /// Require the given features, using the builder pattern.
+    pub fn with_required_features(
+        mut self,
+        features: impl IntoIterator<Item = BackendFeatureKind>,
+    ) -> Self {
+        self.set_required_features(features);
+        self
+    }
+
     /// Disable all features for this backend builder.
     pub fn without_features(mut self) -> Self {
         self.set_list_folders(BackendFeatureSource::None);
@@ -642,6 +1400,8 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         let expunge_folder = self.get_expunge_folder();
         let purge_folder = self.get_purge_folder();
         let delete_folder = self.get_delete_folder();
+        let get_quota = self.get_get_quota();
+        let manage_folder_acl = self.get_manage_folder_acl();
 
         let get_envelope = self.get_get_envelope();
         let list_envelopes = self.get_list_envelopes();
@@ -653,25 +1413,35 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
         let add_flags = self.get_add_flags();
         let set_flags = self.get_set_flags();
         let remove_flags = self.get_remove_flags();
+        let mark_read_before = self.get_mark_read_before();
 
         let add_message = self.get_add_message();
         let send_message = self.get_send_message();
         let peek_messages = self.get_peek_messages();
         let get_messages = self.get_get_messages();
+        let get_attachment = self.get_get_attachment();
         let copy_messages = self.get_copy_messages();
         let move_messages = self.get_move_messages();
         let delete_messages = self.get_delete_messages();
         let remove_messages = self.get_remove_messages();
 
-        Ok(Backend {
+        let context = self.ctx_builder.build().await?;
+
+        let backend = Backend {
             account_config: self.account_config,
-            context: Arc::new(self.ctx_builder.build().await?),
+            context: Arc::new(context),
+            middlewares: self.middlewares,
+            prefetch_cache: PrefetchCache::default(),
+            attachment_store: AttachmentStore::default(),
+            invalidated_folders: Default::default(),
 
             add_folder,
             list_folders,
             expunge_folder,
             purge_folder,
             delete_folder,
+            get_quota,
+            manage_folder_acl,
 
             get_envelope,
             list_envelopes,
@@ -683,16 +1453,33 @@ pub async fn build(self) -> AnyResult<Backend<CB::Context>> {
             add_flags,
             set_flags,
             remove_flags,
+            mark_read_before,
 
             add_message,
             send_message,
             peek_messages,
             get_messages,
+            get_attachment,
             copy_messages,
             move_messages,
             delete_messages,
             remove_messages,
-        })
+        };
+
+        let mut missing_features: Vec<_> = self
+            .required_features
+            .iter()
+            .copied()
+            .filter(|feature| !backend.has_feature(*feature))
+            .collect();
+
+        if !missing_features.is_empty() {
+            missing_features.sort();
+            missing_features.dedup();
+            return Err(Error::MissingRequiredFeaturesError(missing_features).into());
+        }
+
+        Ok(backend)
     }
 }
 
@@ -705,6 +1492,8 @@ fn clone(&self) -> Self {
         Self {
             account_config: self.account_config.clone(),
             ctx_builder: self.ctx_builder.clone(),
+            middlewares: self.middlewares.clone(),
+            required_features: self.required_features.clone(),
 
             check_up: self.check_up.clone(),
 
@@ -713,6 +1502,8 @@ fn clone(&self) -> Self {
             expunge_folder: self.expunge_folder.clone(),
             purge_folder: self.purge_folder.clone(),
             delete_folder: self.delete_folder.clone(),
+            get_quota: self.get_quota.clone(),
+            manage_folder_acl: self.manage_folder_acl.clone(),
 
             get_envelope: self.get_envelope.clone(),
             list_envelopes: self.list_envelopes.clone(),
@@ -724,11 +1515,13 @@ fn clone(&self) -> Self {
             add_flags: self.add_flags.clone(),
             set_flags: self.set_flags.clone(),
             remove_flags: self.remove_flags.clone(),
+            mark_read_before: self.mark_read_before.clone(),
 
             add_message: self.add_message.clone(),
             send_message: self.send_message.clone(),
             peek_messages: self.peek_messages.clone(),
             get_messages: self.get_messages.clone(),
+            get_attachment: self.get_attachment.clone(),
             copy_messages: self.copy_messages.clone(),
             move_messages: self.move_messages.clone(),
             delete_messages: self.delete_messages.clone(),
@@ -742,7 +1535,285 @@ impl<CB> SyncHash for BackendBuilder<CB>
 where
     CB: BackendContextBuilder + SyncHash,
 {
-    fn sync_hash(&self, state: &mut DefaultHasher) {
+    fn sync_hash(&self, state: &mut SyncHasher) {
         self.ctx_builder.sync_hash(state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+    use futures::future::BoxFuture;
+
+    use super::{
+        context::{BackendContext, BackendContextBuilder},
+        middleware::{BackendMiddleware, BackendNext, BackendOperation},
+        BackendBuilder, Error,
+    };
+    use crate::{
+        account::config::AccountConfig,
+        envelope::{get::GetEnvelope, Envelope, Id, SingleId},
+        AnyResult,
+    };
+
+    #[derive(Default)]
+    struct TestContext {
+        invalidated_folders: Mutex<Vec<String>>,
+    }
+
+    impl BackendContext for TestContext {
+        fn take_invalidated_folders(&self) -> Vec<String> {
+            std::mem::take(&mut self.invalidated_folders.lock().unwrap())
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct TestContextBuilder;
+
+    #[async_trait]
+    impl BackendContextBuilder for TestContextBuilder {
+        type Context = TestContext;
+
+        async fn build(self) -> AnyResult<Self::Context> {
+            Ok(TestContext::default())
+        }
+    }
+
+    fn envelope(id: &str) -> Envelope {
+        Envelope {
+            id: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn uid_validity_change_invalidates_folder() {
+        let config = Arc::new(AccountConfig::default());
+        let backend = BackendBuilder::new(config, TestContextBuilder)
+            .build()
+            .await
+            .unwrap();
+
+        backend
+            .prefetch_cache
+            .insert_envelope("INBOX", envelope("1"));
+        backend
+            .prefetch_cache
+            .insert_envelope("Archives", envelope("1"));
+
+        assert!(!backend.take_invalidated_folder("INBOX"));
+
+        // simulates a UIDVALIDITY bump noticed while selecting INBOX
+        backend
+            .context
+            .invalidated_folders
+            .lock()
+            .unwrap()
+            .push("INBOX".into());
+
+        assert!(backend.take_invalidated_folder("INBOX"));
+        assert!(backend.prefetch_cache.get_envelope("INBOX", "1").is_none());
+        assert!(backend
+            .prefetch_cache
+            .get_envelope("Archives", "1")
+            .is_some());
+
+        // the invalidation is only reported once
+        assert!(!backend.take_invalidated_folder("INBOX"));
+    }
+
+    struct TestGetEnvelope;
+
+    #[async_trait]
+    impl GetEnvelope for TestGetEnvelope {
+        async fn get_envelope(&self, _folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+            Ok(envelope(id.as_str()))
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct TestMiddleware(Arc<Mutex<Vec<&'static str>>>);
+
+    #[async_trait]
+    impl BackendMiddleware for TestMiddleware {
+        async fn handle<'a>(
+            &self,
+            op: &'a BackendOperation,
+            next: BackendNext<'a>,
+        ) -> AnyResult<()> {
+            self.0.lock().unwrap().push(op.feature);
+            next.run().await
+        }
+    }
+
+    #[tokio::test]
+    async fn prefetch_through_middlewares() {
+        let config = Arc::new(AccountConfig::default());
+        let middleware = TestMiddleware::default();
+
+        let mut builder = BackendBuilder::new(config, TestContextBuilder)
+            .with_feature_middleware(middleware.clone());
+        builder.get_envelope =
+            (|_: &TestContext| -> Option<Box<dyn GetEnvelope>> { Some(Box::new(TestGetEnvelope)) })
+                .into();
+
+        let backend = Arc::new(builder.build().await.unwrap());
+        backend
+            .prefetch("INBOX", &Id::multiple(["1", "2"]))
+            .await
+            .unwrap();
+
+        assert_eq!(*middleware.0.lock().unwrap(), ["get_envelope"; 2]);
+        assert!(backend.prefetch_cache.get_envelope("INBOX", "1").is_some());
+        assert!(backend.prefetch_cache.get_envelope("INBOX", "2").is_some());
+    }
+
+    /// Build a backend getting envelopes through the given
+    /// middlewares.
+    async fn backend_with_middlewares(
+        middlewares: impl IntoIterator<Item = Arc<dyn BackendMiddleware>>,
+    ) -> super::Backend<TestContext> {
+        let config = Arc::new(AccountConfig::default());
+
+        let mut builder = BackendBuilder::new(config, TestContextBuilder);
+        builder.middlewares.extend(middlewares);
+        builder.get_envelope =
+            (|_: &TestContext| -> Option<Box<dyn GetEnvelope>> { Some(Box::new(TestGetEnvelope)) })
+                .into();
+
+        builder.build().await.unwrap()
+    }
+
+    struct RecordingMiddleware {
+        name: &'static str,
+        calls: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl BackendMiddleware for RecordingMiddleware {
+        async fn handle<'a>(
+            &self,
+            op: &'a BackendOperation,
+            next: BackendNext<'a>,
+        ) -> AnyResult<()> {
+            let (name, folder) = (self.name, &op.folder);
+            self.calls
+                .lock()
+                .unwrap()
+                .push(format!("{name} before {folder:?}"));
+            let res = next.run().await;
+            self.calls.lock().unwrap().push(format!("{name} after"));
+            res
+        }
+    }
+
+    fn abort<'a>(_: &'a BackendOperation, _: BackendNext<'a>) -> BoxFuture<'a, AnyResult<()>> {
+        Box::pin(async { Err(Error::OfflineError("account".into()).into()) })
+    }
+
+    fn skip<'a>(_: &'a BackendOperation, _: BackendNext<'a>) -> BoxFuture<'a, AnyResult<()>> {
+        Box::pin(async { Ok(()) })
+    }
+
+    #[tokio::test]
+    async fn middlewares_run_in_registration_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        let middleware = |name| -> Arc<dyn BackendMiddleware> {
+            let calls = calls.clone();
+            Arc::new(RecordingMiddleware { name, calls })
+        };
+
+        let backend = backend_with_middlewares([middleware("outer"), middleware("inner")]).await;
+        let envelope = backend
+            .get_envelope("INBOX", &SingleId::from("1"))
+            .await
+            .unwrap();
+
+        assert_eq!(envelope.id, "1");
+        assert_eq!(
+            *calls.lock().unwrap(),
+            [
+                "outer before Some(\"INBOX\")",
+                "inner before Some(\"INBOX\")",
+                "inner after",
+                "outer after",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn middleware_aborts_feature() {
+        let backend =
+            backend_with_middlewares([Arc::new(abort) as Arc<dyn BackendMiddleware>]).await;
+        let err = backend
+            .get_envelope("INBOX", &SingleId::from("1"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.as_any().downcast_ref::<Error>(),
+            Some(Error::OfflineError(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn middleware_skips_feature() {
+        let backend =
+            backend_with_middlewares([Arc::new(skip) as Arc<dyn BackendMiddleware>]).await;
+        let err = backend
+            .get_envelope("INBOX", &SingleId::from("1"))
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err.as_any().downcast_ref::<Error>(),
+            Some(Error::FeatureSkippedByMiddlewareError("get_envelope"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn handle_spawns_static_futures() {
+        let handle = backend_with_middlewares([]).await.into_handle();
+
+        // futures own their arguments, so they can be spawned
+        let folder = String::from("INBOX");
+        let envelope = tokio::spawn(handle.get_envelope(folder, "1"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(envelope.id, "1");
+
+        let envelope = handle
+            .spawn(|handle| handle.get_envelope("INBOX", "2"))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(envelope.id, "2");
+
+        // clones share the same backend, hence the same cache
+        handle
+            .clone()
+            .prefetch("INBOX", Id::single("3"))
+            .await
+            .unwrap();
+        assert!(handle.prefetch_cache.get_envelope("INBOX", "3").is_some());
+    }
+
+    #[test]
+    fn handle_spawns_on_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .build()
+            .unwrap();
+
+        let handle = runtime.block_on(backend_with_middlewares([])).into_handle();
+
+        // spawned from a non-async thread, then awaited later on
+        let task = handle.spawn_on(runtime.handle(), |handle| handle.get_envelope("INBOX", "1"));
+        let envelope = runtime.block_on(task).unwrap().unwrap();
+        assert_eq!(envelope.id, "1");
+    }
+}
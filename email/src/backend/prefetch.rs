@@ -0,0 +1,336 @@
+//! # Prefetch
+//!
+//! Module dedicated to envelopes and messages prefetching, see
+//! [`Backend::prefetch`](super::Backend::prefetch).
+//!
+//! Prefetched envelopes and messages are kept in a bounded
+//! [`PrefetchCache`], which is consulted before reaching the backend
+//! and invalidated by mutating operations. Every invalidation bumps
+//! the cache generation, so that entries fetched before an
+//! invalidation but inserted after it are discarded, see
+//! [`PrefetchCache::generation`].
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tracing::debug;
+
+use super::middleware::BackendOperation;
+use crate::{
+    envelope::{Envelope, Id},
+    message::Messages,
+};
+
+/// The default maximum number of entries kept in the prefetch cache.
+pub const DEFAULT_PREFETCH_CACHE_CAPACITY: usize = 128;
+
+/// The key of a prefetch cache entry: folder and envelope id.
+type PrefetchKey = (String, String);
+
+/// The prefetched data of a single envelope.
+#[derive(Clone, Debug, Default)]
+struct PrefetchEntry {
+    envelope: Option<Envelope>,
+    message: Option<Vec<u8>>,
+}
+
+#[derive(Debug)]
+struct PrefetchCacheInner {
+    capacity: usize,
+    generation: u64,
+    entries: HashMap<PrefetchKey, PrefetchEntry>,
+    order: VecDeque<PrefetchKey>,
+}
+
+/// The prefetch cache.
+///
+/// The cache is bounded: when full, the oldest entries are evicted
+/// first. Cloning the cache shares the same entries.
+#[derive(Clone, Debug)]
+pub struct PrefetchCache(Arc<Mutex<PrefetchCacheInner>>);
+
+impl PrefetchCache {
+    /// Create a new cache holding at most the given number of
+    /// entries.
+    pub fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(PrefetchCacheInner {
+            capacity: capacity.max(1),
+            generation: 0,
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+        })))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, PrefetchCacheInner> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn upsert(
+        &self,
+        generation: Option<u64>,
+        folder: &str,
+        id: &str,
+        f: impl FnOnce(&mut PrefetchEntry),
+    ) -> bool {
+        let mut inner = self.lock();
+
+        if generation.is_some_and(|generation| generation != inner.generation) {
+            debug!(
+                folder,
+                id, "prefetch cache invalidated meanwhile, skipping entry"
+            );
+            return false;
+        }
+
+        let key = (folder.to_owned(), id.to_owned());
+
+        if !inner.entries.contains_key(&key) {
+            while inner.entries.len() >= inner.capacity {
+                let Some(oldest) = inner.order.pop_front() else {
+                    break;
+                };
+                inner.entries.remove(&oldest);
+            }
+
+            inner.order.push_back(key.clone());
+        }
+
+        f(inner.entries.entry(key).or_default());
+        true
+    }
+
+    /// Return the current generation of the cache.
+    ///
+    /// The generation changes every time entries are invalidated or
+    /// cleared. Data fetched from the backend should be inserted with
+    /// the generation taken before fetching, using
+    /// [`PrefetchCache::insert_envelope_since`] and
+    /// [`PrefetchCache::insert_message_since`].
+    pub fn generation(&self) -> u64 {
+        self.lock().generation
+    }
+
+    /// Return `true` if both the envelope and the message matching
+    /// the given id are cached.
+    pub fn contains(&self, folder: &str, id: &str) -> bool {
+        let key = (folder.to_owned(), id.to_owned());
+
+        self.lock()
+            .entries
+            .get(&key)
+            .is_some_and(|entry| entry.envelope.is_some() && entry.message.is_some())
+    }
+
+    /// Get the cached envelope matching the given id.
+    pub fn get_envelope(&self, folder: &str, id: &str) -> Option<Envelope> {
+        let key = (folder.to_owned(), id.to_owned());
+        self.lock().entries.get(&key)?.envelope.clone()
+    }
+
+    /// Get the cached messages matching the given id.
+    ///
+    /// Return `None` unless all the messages are cached.
+    pub fn get_messages(&self, folder: &str, id: &Id) -> Option<Messages> {
+        let inner = self.lock();
+
+        let msgs = id
+            .iter()
+            .map(|id| {
+                let key = (folder.to_owned(), id.to_owned());
+                inner.entries.get(&key)?.message.clone()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Messages::from(msgs))
+    }
+
+    /// Cache the given envelope.
+    pub fn insert_envelope(&self, folder: &str, envelope: Envelope) {
+        let id = envelope.id.clone();
+        self.upsert(None, folder, &id, |entry| entry.envelope = Some(envelope));
+    }
+
+    /// Cache the given envelope, unless the cache was invalidated
+    /// since the given generation.
+    ///
+    /// Return `true` if the envelope has been cached.
+    pub fn insert_envelope_since(&self, generation: u64, folder: &str, envelope: Envelope) -> bool {
+        let id = envelope.id.clone();
+        self.upsert(Some(generation), folder, &id, |entry| {
+            entry.envelope = Some(envelope)
+        })
+    }
+
+    /// Cache the given raw message.
+    pub fn insert_message(&self, folder: &str, id: &str, message: Vec<u8>) {
+        self.upsert(None, folder, id, |entry| entry.message = Some(message));
+    }
+
+    /// Cache the given raw message, unless the cache was invalidated
+    /// since the given generation.
+    ///
+    /// Return `true` if the message has been cached.
+    pub fn insert_message_since(
+        &self,
+        generation: u64,
+        folder: &str,
+        id: &str,
+        message: Vec<u8>,
+    ) -> bool {
+        self.upsert(Some(generation), folder, id, |entry| {
+            entry.message = Some(message)
+        })
+    }
+
+    /// Remove the entries affected by the given operation.
+    ///
+    /// Non-mutating operations are ignored. Operations without
+    /// folder clear the whole cache, operations without ids clear
+    /// the whole folder.
+    pub fn invalidate(&self, op: &BackendOperation) {
+        if !op.is_mutating() {
+            return;
+        }
+
+        let mut inner = self.lock();
+
+        // bumped even when empty, so that entries being prefetched
+        // cannot be inserted back
+        inner.generation += 1;
+
+        if inner.entries.is_empty() {
+            return;
+        }
+
+        debug!(?op, "invalidating prefetch cache");
+
+        let matches = |(folder, id): &PrefetchKey| match &op.folder {
+            None => true,
+            Some(op_folder) if op_folder != folder => false,
+            Some(_) => op.ids.is_empty() || op.ids.contains(id),
+        };
+
+        inner.entries.retain(|key, _| !matches(key));
+        inner.order.retain(|key| !matches(key));
+    }
+
+    /// Remove the entries of the folders matching the given
+    /// predicate.
+    pub fn clear_folders(&self, f: impl Fn(&str) -> bool) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.entries.retain(|(folder, _), _| !f(folder));
+        inner.order.retain(|(folder, _)| !f(folder));
+    }
+
+    /// Remove all the entries.
+    pub fn clear(&self) {
+        let mut inner = self.lock();
+        inner.generation += 1;
+        inner.entries.clear();
+        inner.order.clear();
+    }
+}
+
+impl Default for PrefetchCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREFETCH_CACHE_CAPACITY)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefetchCache;
+    use crate::{
+        backend::middleware::BackendOperation,
+        envelope::{Envelope, Id},
+    };
+
+    fn envelope(id: &str) -> Envelope {
+        Envelope {
+            id: id.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evict_oldest_entries() {
+        let cache = PrefetchCache::new(2);
+
+        cache.insert_envelope("INBOX", envelope("1"));
+        cache.insert_message("INBOX", "1", b"1".to_vec());
+        cache.insert_envelope("INBOX", envelope("2"));
+        cache.insert_envelope("INBOX", envelope("3"));
+
+        assert!(cache.get_envelope("INBOX", "1").is_none());
+        assert!(cache.get_envelope("INBOX", "2").is_some());
+        assert!(cache.get_envelope("INBOX", "3").is_some());
+    }
+
+    #[test]
+    fn get_messages_only_when_all_cached() {
+        let cache = PrefetchCache::default();
+
+        cache.insert_message("INBOX", "1", b"Subject: 1\r\n\r\n".to_vec());
+        assert!(cache.get_messages("INBOX", &Id::single("1")).is_some());
+        assert!(cache
+            .get_messages("INBOX", &Id::multiple(["1", "2"]))
+            .is_none());
+        assert!(!cache.contains("INBOX", "1"));
+
+        cache.insert_envelope("INBOX", envelope("1"));
+        assert!(cache.contains("INBOX", "1"));
+    }
+
+    #[test]
+    fn invalidate_mutated_entries() {
+        let cache = PrefetchCache::default();
+
+        cache.insert_envelope("INBOX", envelope("1"));
+        cache.insert_envelope("INBOX", envelope("2"));
+        cache.insert_envelope("Archives", envelope("1"));
+
+        let op = BackendOperation::new("peek_messages").with_folder("INBOX");
+        cache.invalidate(&op);
+        assert!(cache.get_envelope("INBOX", "1").is_some());
+
+        let op = BackendOperation::new("add_flags")
+            .with_folder("INBOX")
+            .with_ids(&Id::single("1"));
+        cache.invalidate(&op);
+        assert!(cache.get_envelope("INBOX", "1").is_none());
+        assert!(cache.get_envelope("INBOX", "2").is_some());
+
+        let op = BackendOperation::new("expunge_folder").with_folder("INBOX");
+        cache.invalidate(&op);
+        assert!(cache.get_envelope("INBOX", "2").is_none());
+        assert!(cache.get_envelope("Archives", "1").is_some());
+    }
+
+    #[test]
+    fn discard_entries_fetched_before_invalidation() {
+        let cache = PrefetchCache::default();
+
+        let generation = cache.generation();
+        assert!(cache.insert_envelope_since(generation, "INBOX", envelope("1")));
+
+        // the message is being fetched while a mutating operation
+        // invalidates the folder
+        let generation = cache.generation();
+        let op = BackendOperation::new("delete_messages")
+            .with_folder("INBOX")
+            .with_ids(&Id::single("2"));
+        cache.invalidate(&op);
+
+        assert!(!cache.insert_envelope_since(generation, "INBOX", envelope("2")));
+        assert!(!cache.insert_message_since(generation, "INBOX", "2", b"2".to_vec()));
+        assert!(cache.get_envelope("INBOX", "2").is_none());
+        assert!(cache.get_envelope("INBOX", "1").is_some());
+
+        let generation = cache.generation();
+        assert!(cache.insert_message_since(generation, "INBOX", "1", b"1".to_vec()));
+        assert!(cache.contains("INBOX", "1"));
+    }
+}
@@ -1,7 +1,7 @@
 use std::{collections::HashMap, path::PathBuf};
 
 use crate::{
-    account::{config::AccountConfig, Error},
+    account::{config::AccountConfig, runtime::AccountRuntime, Error},
     Result,
 };
 
@@ -25,6 +25,12 @@ pub struct Config {
     /// accounts.
     pub signature: Option<String>,
 
+    /// The default HTML email signature of the user.
+    ///
+    /// It can be either a path to a file or a raw string. This HTML
+    /// signature is used by default for all accounts.
+    pub signature_html: Option<String>,
+
     /// The default email signature delimiter of the user signature.
     ///
     /// Defaults to `-- \n`. This signature delimiter is used by
@@ -41,6 +47,13 @@ pub struct Config {
 
     /// The map of account-specific configurations.
     pub accounts: HashMap<String, AccountConfig>,
+
+    /// The runtime state shared by all accounts, see
+    /// [`AccountRuntime`].
+    ///
+    /// It is not part of the configuration file.
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub runtime: AccountRuntime,
 }
 
 impl Config {
@@ -55,6 +68,7 @@ pub fn account(&self, name: impl AsRef<str>) -> Result<AccountConfig> {
         Ok(AccountConfig {
             name: name.to_owned(),
             email: account_config.email.clone(),
+            email_aliases: account_config.email_aliases.clone(),
             display_name: account_config
                 .display_name
                 .as_ref()
@@ -70,6 +84,11 @@ pub fn account(&self, name: impl AsRef<str>) -> Result<AccountConfig> {
                 .as_ref()
                 .map(ToOwned::to_owned)
                 .or_else(|| self.signature.as_ref().map(ToOwned::to_owned)),
+            signature_html: account_config
+                .signature_html
+                .as_ref()
+                .map(ToOwned::to_owned)
+                .or_else(|| self.signature_html.as_ref().map(ToOwned::to_owned)),
             downloads_dir: account_config
                 .downloads_dir
                 .as_ref()
@@ -84,6 +103,11 @@ pub fn account(&self, name: impl AsRef<str>) -> Result<AccountConfig> {
             sync: account_config.sync.clone(),
             #[cfg(feature = "pgp")]
             pgp: account_config.pgp.clone(),
+            journal: account_config.journal.clone(),
+            concurrency_budget: account_config.concurrency_budget,
+            health: account_config.health.clone(),
+            clock: account_config.clock.clone(),
+            runtime: self.runtime.clone(),
         })
     }
 }
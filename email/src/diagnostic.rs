@@ -0,0 +1,428 @@
+//! # Connection diagnostics
+//!
+//! Module dedicated to connection diagnostics. When a connection to
+//! an IMAP or an SMTP server fails, the underlying error rarely tells
+//! which step actually failed: DNS resolution, TCP connection, TLS
+//! handshake or protocol greeting.
+//!
+//! The [`ConnectionDiagnostics`] replays those steps one by one and
+//! records a typed chain of [`ConnectionStep`], up to the first
+//! failing [`ConnectionStage`]. Diagnostics are attached to IMAP and
+//! SMTP connection errors.
+
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, Instant},
+};
+
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    net::{lookup_host, TcpStream},
+    time::timeout,
+};
+use tracing::debug;
+
+use crate::tls::{SecurityLevel, Tls};
+
+/// The default timeout of each diagnostic step.
+pub const DEFAULT_STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The maximum length of the greeting banner, in bytes.
+const MAX_BANNER_LEN: usize = 1024;
+
+/// A connection step that succeeded.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConnectionStep {
+    /// The host name resolved to the given IP addresses.
+    DnsResolved { ips: Vec<IpAddr> },
+
+    /// The TCP connection succeeded to the given address, after the
+    /// given round-trip time.
+    TcpConnected { addr: SocketAddr, rtt: Duration },
+
+    /// The TLS handshake succeeded, using the given protocol version
+    /// (when known).
+    TlsHandshake { version: Option<String> },
+
+    /// The server greeted with the given banner.
+    Greeting { banner: String },
+}
+
+impl fmt::Display for ConnectionStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DnsResolved { ips } => {
+                let ips: Vec<_> = ips.iter().map(ToString::to_string).collect();
+                write!(f, "DNS resolved to {}", ips.join(", "))
+            }
+            Self::TcpConnected { addr, rtt } => {
+                write!(f, "TCP connected to {addr} in {}ms", rtt.as_millis())
+            }
+            Self::TlsHandshake {
+                version: Some(version),
+            } => {
+                write!(f, "TLS handshake succeeded using {version}")
+            }
+            Self::TlsHandshake { version: None } => write!(f, "TLS handshake succeeded"),
+            Self::Greeting { banner } => write!(f, "server greeted with {banner:?}"),
+        }
+    }
+}
+
+/// A connection stage.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConnectionStage {
+    Dns,
+    Tcp,
+    Tls,
+    Greeting,
+}
+
+impl fmt::Display for ConnectionStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns => write!(f, "DNS resolution"),
+            Self::Tcp => write!(f, "TCP connection"),
+            Self::Tls => write!(f, "TLS handshake"),
+            Self::Greeting => write!(f, "server greeting"),
+        }
+    }
+}
+
+/// The connection stage that failed, with the reason of the failure.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConnectionFailure {
+    pub stage: ConnectionStage,
+    pub reason: String,
+}
+
+/// The diagnostics of a connection to a server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConnectionDiagnostics {
+    /// The host name of the server.
+    pub host: String,
+
+    /// The port of the server.
+    pub port: u16,
+
+    /// The steps that succeeded, in order.
+    pub steps: Vec<ConnectionStep>,
+
+    /// The stage that failed, if any.
+    pub failure: Option<ConnectionFailure>,
+}
+
+impl ConnectionDiagnostics {
+    /// Diagnose the connection to the given server, expecting the
+    /// given security level.
+    ///
+    /// The TLS handshake uses the default TLS options, see
+    /// [`ConnectionDiagnostics::probe_with_tls`].
+    pub async fn probe(host: impl ToString, port: u16, security_level: SecurityLevel) -> Self {
+        Self::probe_with_tls(host, port, security_level, &Tls::default()).await
+    }
+
+    /// Diagnose the connection to the given server, expecting the
+    /// given security level and using the given TLS options.
+    ///
+    /// The TLS handshake is only replayed for implicit SSL/TLS
+    /// connections: STARTTLS connections stop after the plaintext
+    /// greeting. Using the TLS options of the account makes the
+    /// handshake accept the same certificates as the actual
+    /// connection (additional root certificates, pinned fingerprint
+    /// or invalid certificates).
+    pub async fn probe_with_tls(
+        host: impl ToString,
+        port: u16,
+        security_level: SecurityLevel,
+        tls: &Tls,
+    ) -> Self {
+        let mut diagnostics = Self {
+            host: host.to_string(),
+            port,
+            steps: Vec::new(),
+            failure: None,
+        };
+
+        if let Err(failure) = diagnostics.run(security_level, tls).await {
+            debug!(?failure, "connection diagnostics failed");
+            diagnostics.failure = Some(failure);
+        }
+
+        diagnostics
+    }
+
+    /// Return the stage that failed, if any.
+    pub fn failed_stage(&self) -> Option<ConnectionStage> {
+        self.failure.as_ref().map(|failure| failure.stage)
+    }
+
+    async fn run(
+        &mut self,
+        security_level: SecurityLevel,
+        tls: &Tls,
+    ) -> Result<(), ConnectionFailure> {
+        let fail = |stage, reason: String| ConnectionFailure { stage, reason };
+
+        let addrs: Vec<SocketAddr> = match timeout(
+            DEFAULT_STEP_TIMEOUT,
+            lookup_host((self.host.as_str(), self.port)),
+        )
+        .await
+        {
+            Ok(Ok(addrs)) => addrs.collect(),
+            Ok(Err(err)) => return Err(fail(ConnectionStage::Dns, err.to_string())),
+            Err(_) => return Err(fail(ConnectionStage::Dns, "timed out".into())),
+        };
+
+        if addrs.is_empty() {
+            return Err(fail(ConnectionStage::Dns, "no address found".into()));
+        }
+
+        let ips = addrs.iter().map(SocketAddr::ip).collect();
+        self.steps.push(ConnectionStep::DnsResolved { ips });
+
+        let mut last_reason = String::new();
+        let mut tcp = None;
+
+        for addr in addrs {
+            let start = Instant::now();
+            match timeout(DEFAULT_STEP_TIMEOUT, TcpStream::connect(addr)).await {
+                Ok(Ok(stream)) => {
+                    let rtt = start.elapsed();
+                    self.steps.push(ConnectionStep::TcpConnected { addr, rtt });
+                    tcp = Some(stream);
+                    break;
+                }
+                Ok(Err(err)) => last_reason = format!("{addr}: {err}"),
+                Err(_) => last_reason = format!("{addr}: timed out"),
+            }
+        }
+
+        let Some(tcp) = tcp else {
+            return Err(fail(ConnectionStage::Tcp, last_reason));
+        };
+
+        let banner = match security_level {
+            SecurityLevel::Tls => self.tls_handshake(tcp, tls).await?,
+            SecurityLevel::StartTls | SecurityLevel::Plaintext => read_banner(tcp).await?,
+        };
+
+        self.steps.push(ConnectionStep::Greeting { banner });
+
+        Ok(())
+    }
+
+    /// Replay the TLS handshake, then read the greeting banner.
+    #[cfg(feature = "tokio-rustls")]
+    async fn tls_handshake(
+        &mut self,
+        tcp: TcpStream,
+        tls: &Tls,
+    ) -> Result<String, ConnectionFailure> {
+        use tokio_rustls::rustls::pki_types::ServerName;
+
+        let fail = |reason: String| ConnectionFailure {
+            stage: ConnectionStage::Tls,
+            reason,
+        };
+
+        let name = ServerName::try_from(self.host.clone()).map_err(|err| fail(err.to_string()))?;
+        let connector = tls
+            .build_rustls_connector()
+            .map_err(|err| fail(err.to_string()))?;
+
+        let stream = match timeout(DEFAULT_STEP_TIMEOUT, connector.connect(name, tcp)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(err)) => return Err(fail(err.to_string())),
+            Err(_) => return Err(fail("timed out".into())),
+        };
+
+        let version = stream.get_ref().1.protocol_version();
+        let version = version.map(|version| format!("{version:?}"));
+        self.steps.push(ConnectionStep::TlsHandshake { version });
+
+        read_banner(stream).await
+    }
+
+    /// Replay the TLS handshake, then read the greeting banner.
+    #[cfg(all(feature = "tokio-native-tls", not(feature = "tokio-rustls")))]
+    async fn tls_handshake(
+        &mut self,
+        tcp: TcpStream,
+        tls: &Tls,
+    ) -> Result<String, ConnectionFailure> {
+        use crate::tls::info::TlsConnectionInfo;
+
+        let fail = |reason: String| ConnectionFailure {
+            stage: ConnectionStage::Tls,
+            reason,
+        };
+
+        let connector = tls
+            .build_native_tls_connector()
+            .map_err(|err| fail(err.to_string()))?;
+
+        let stream = match timeout(DEFAULT_STEP_TIMEOUT, connector.connect(&self.host, tcp)).await {
+            Ok(Ok(stream)) => stream,
+            Ok(Err(err)) => return Err(fail(err.to_string())),
+            Err(_) => return Err(fail("timed out".into())),
+        };
+
+        // native-tls cannot check the pinned fingerprint during the
+        // handshake
+        let info = TlsConnectionInfo::from_native_tls(stream.get_ref());
+        tls.verify_cert_fingerprint(&info)
+            .map_err(|err| fail(err.to_string()))?;
+
+        // native-tls does not expose the negotiated protocol version
+        self.steps
+            .push(ConnectionStep::TlsHandshake { version: None });
+
+        read_banner(stream).await
+    }
+
+    /// Replay the TLS handshake, then read the greeting banner.
+    #[cfg(not(any(feature = "tokio-rustls", feature = "tokio-native-tls")))]
+    async fn tls_handshake(
+        &mut self,
+        _tcp: TcpStream,
+        _tls: &Tls,
+    ) -> Result<String, ConnectionFailure> {
+        Err(ConnectionFailure {
+            stage: ConnectionStage::Tls,
+            reason: "missing TLS provider".into(),
+        })
+    }
+}
+
+impl fmt::Display for ConnectionDiagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.host, self.port)?;
+
+        for step in &self.steps {
+            write!(f, ", {step}")?;
+        }
+
+        if let Some(failure) = &self.failure {
+            write!(f, ", {} failed: {}", failure.stage, failure.reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the first line sent by the server.
+async fn read_banner(mut stream: impl AsyncRead + Unpin) -> Result<String, ConnectionFailure> {
+    let fail = |reason: String| ConnectionFailure {
+        stage: ConnectionStage::Greeting,
+        reason,
+    };
+
+    let mut banner = Vec::new();
+    let mut buf = [0; 256];
+
+    let read = async {
+        while !banner.ends_with(b"\n") && banner.len() < MAX_BANNER_LEN {
+            match stream.read(&mut buf).await? {
+                0 => break,
+                n => banner.extend_from_slice(&buf[..n]),
+            }
+        }
+
+        std::io::Result::Ok(())
+    };
+
+    match timeout(DEFAULT_STEP_TIMEOUT, read).await {
+        Ok(Ok(())) => (),
+        Ok(Err(err)) => return Err(fail(err.to_string())),
+        Err(_) => return Err(fail("timed out".into())),
+    }
+
+    let banner = String::from_utf8_lossy(&banner);
+    let banner = banner.lines().next().unwrap_or_default().trim();
+
+    if banner.is_empty() {
+        return Err(fail("connection closed without greeting".into()));
+    }
+
+    Ok(banner.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::{ConnectionDiagnostics, ConnectionStage, ConnectionStep};
+    use crate::tls::{SecurityLevel, Tls};
+
+    #[tokio::test]
+    async fn probe_plaintext_greeting() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            stream.write_all(b"* OK IMAP4rev1 ready\r\n").await.unwrap();
+        });
+
+        let diagnostics =
+            ConnectionDiagnostics::probe("127.0.0.1", port, SecurityLevel::Plaintext).await;
+
+        assert_eq!(diagnostics.failure, None);
+        assert_eq!(diagnostics.steps.len(), 3);
+        assert!(matches!(
+            diagnostics.steps[1],
+            ConnectionStep::TcpConnected { addr, .. } if addr.port() == port
+        ));
+        assert_eq!(
+            diagnostics.steps[2],
+            ConnectionStep::Greeting {
+                banner: "* OK IMAP4rev1 ready".into()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_tcp_failure() {
+        // bind then drop the listener to get a closed port
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let diagnostics =
+            ConnectionDiagnostics::probe("127.0.0.1", port, SecurityLevel::Plaintext).await;
+
+        assert_eq!(diagnostics.steps.len(), 1);
+        assert_eq!(diagnostics.failed_stage(), Some(ConnectionStage::Tcp));
+    }
+
+    #[tokio::test]
+    async fn probe_tls_with_account_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let _ = listener.accept().await;
+        });
+
+        // the handshake is built from the given TLS options, here
+        // failing to load the additional root certificates
+        let tls = Tls {
+            root_certs: Some(vec!["/nonexistent/root-certs.pem".into()]),
+            ..Default::default()
+        };
+        let diagnostics =
+            ConnectionDiagnostics::probe_with_tls("127.0.0.1", port, SecurityLevel::Tls, &tls)
+                .await;
+
+        assert_eq!(diagnostics.steps.len(), 2);
+        assert_eq!(diagnostics.failed_stage(), Some(ConnectionStage::Tls));
+        assert!(diagnostics
+            .failure
+            .unwrap()
+            .reason
+            .contains("/nonexistent/root-certs.pem"));
+    }
+}
@@ -1,4 +1,124 @@
-use chrono::{FixedOffset, TimeZone};
+//! Module dedicated to dates.
+//!
+//! This module contains date conversion helpers, as well as the
+//! [`Clock`] abstraction used to get the current time and the local
+//! timezone.
+
+use std::{fmt, ops::Deref, sync::Arc};
+
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use once_cell::sync::Lazy;
+
+/// The system clock shared by all default [`ClockSource`]s.
+static SYSTEM_CLOCK: Lazy<Arc<dyn Clock>> = Lazy::new(|| Arc::new(SystemClock));
+
+/// The timezone of the [`SystemClock`].
+///
+/// Pinned to UTC in tests, so that they do not depend on the timezone
+/// of the machine running them.
+#[cfg(test)]
+static SYSTEM_TZ: &Utc = &Utc;
+#[cfg(not(test))]
+static SYSTEM_TZ: &chrono::Local = &chrono::Local;
+
+/// The clock abstraction.
+///
+/// The clock gives the current time and the local timezone. It makes
+/// date handling deterministic in tests, and lets clients display
+/// dates in a timezone different from the system one.
+pub trait Clock: fmt::Debug + Send + Sync {
+    /// Return the current time.
+    fn now(&self) -> DateTime<Utc>;
+
+    /// Convert the given date to the local timezone.
+    ///
+    /// The offset of the local timezone depends on the date itself,
+    /// since the timezone may observe daylight saving time.
+    fn to_local(&self, date: &DateTime<FixedOffset>) -> DateTime<FixedOffset>;
+
+    /// Return the current time in the local timezone.
+    fn local_now(&self) -> DateTime<FixedOffset> {
+        self.to_local(&self.now().fixed_offset())
+    }
+}
+
+/// The clock based on the system time and timezone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn to_local(&self, date: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        date.with_timezone(SYSTEM_TZ).fixed_offset()
+    }
+}
+
+/// The clock always returning the same time and timezone.
+///
+/// Mostly useful for tests.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedClock {
+    pub now: DateTime<Utc>,
+    pub offset: FixedOffset,
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>, offset: FixedOffset) -> Self {
+        Self { now, offset }
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now
+    }
+
+    fn to_local(&self, date: &DateTime<FixedOffset>) -> DateTime<FixedOffset> {
+        date.with_timezone(&self.offset)
+    }
+}
+
+/// The shareable clock source, defaulting to the [`SystemClock`].
+#[derive(Clone, Debug)]
+pub struct ClockSource(Arc<dyn Clock>);
+
+impl ClockSource {
+    pub fn new(clock: impl Clock + 'static) -> Self {
+        Self(Arc::new(clock))
+    }
+}
+
+impl Default for ClockSource {
+    fn default() -> Self {
+        Self(SYSTEM_CLOCK.clone())
+    }
+}
+
+/// Two clock sources are equal when they share the same clock.
+impl PartialEq for ClockSource {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for ClockSource {}
+
+impl Deref for ClockSource {
+    type Target = dyn Clock;
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_ref()
+    }
+}
+
+impl<T: Clock + 'static> From<T> for ClockSource {
+    fn from(clock: T) -> Self {
+        Self::new(clock)
+    }
+}
 
 pub fn from_mail_parser_to_chrono_datetime(
     dt: &mail_parser::DateTime,
@@ -17,3 +137,29 @@ pub fn from_mail_parser_to_chrono_datetime(
         )
         .earliest()
 }
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+    use super::{Clock, FixedClock, SystemClock};
+
+    #[test]
+    fn to_local() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 23, 0, 0).unwrap();
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let clock = FixedClock::new(now, offset);
+
+        let date = DateTime::parse_from_rfc2822("Mon, 1 Jul 2024 23:30:00 +0000").unwrap();
+        let local = clock.to_local(&date);
+        assert_eq!(local.to_rfc2822(), "Tue, 2 Jul 2024 01:30:00 +0200");
+        assert_eq!(
+            clock.local_now().to_rfc2822(),
+            "Tue, 2 Jan 2024 01:00:00 +0200"
+        );
+
+        // the system clock is pinned to UTC in tests
+        let local = SystemClock.to_local(&local);
+        assert_eq!(local.to_rfc2822(), "Mon, 1 Jul 2024 23:30:00 +0000");
+    }
+}
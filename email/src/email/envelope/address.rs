@@ -2,8 +2,52 @@
 //!
 //! This core concept of this module is the [Address] structure, which
 //! represents an email envelope address.
+//!
+//! This module also contains helpers to handle internationalized
+//! email addresses (RFC 6531): domains are encoded using UTS-46 IDNA
+//! processing, while Unicode local parts require the SMTPUTF8
+//! extension.
+//!
+//! Display names are normalized leniently, since badly encoded
+//! headers are common: see [`normalize_display_name`] and
+//! [`decode_raw_header`].
+
+use std::{
+    borrow::Cow,
+    hash::{Hash, Hasher},
+};
+
+use mail_parser::decoders::{
+    base64::base64_decode, charsets::map::charset_decoder,
+    quoted_printable::quoted_printable_decode,
+};
+
+use crate::email::error::{Error, Result};
+
+/// The maximum length of an address local part, in octets (RFC 5321
+/// section 4.5.3.1.1).
+pub const LOCAL_PART_MAX_LEN: usize = 64;
+
+/// The format used to display an address.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum AddressDisplayFormat {
+    /// Display the name, or the email address if the address has no
+    /// name.
+    #[default]
+    Name,
+
+    /// Display both the name and the email address, like
+    /// `Name <addr>`.
+    NameAndAddress,
 
-use std::hash::{Hash, Hasher};
+    /// Display the email address only.
+    Address,
+}
 
 /// The email envelope address.
 ///
@@ -52,4 +96,391 @@ pub fn new(name: Option<impl ToString>, address: impl ToString) -> Self {
     pub fn new_nameless(address: impl ToString) -> Self {
         Self::new(Option::<String>::None, address)
     }
+
+    /// Return the name, or the email address if the address has no
+    /// name.
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.addr)
+    }
+
+    /// Display the address using the given format.
+    pub fn display(&self, format: AddressDisplayFormat) -> String {
+        match format {
+            AddressDisplayFormat::Name => self.display_name().to_owned(),
+            AddressDisplayFormat::NameAndAddress => self.to_string(),
+            AddressDisplayFormat::Address => self.addr.clone(),
+        }
+    }
+
+    /// Return the local part of the email address, if any.
+    pub fn local_part(&self) -> Option<&str> {
+        split_addr(&self.addr).map(|(local, _)| local)
+    }
+
+    /// Return the domain of the email address, if any.
+    pub fn domain(&self) -> Option<&str> {
+        split_addr(&self.addr).map(|(_, domain)| domain)
+    }
+
+    /// Return `true` if the address cannot be represented without
+    /// the SMTPUTF8 extension.
+    pub fn requires_smtputf8(&self) -> bool {
+        requires_smtputf8(&self.addr)
+    }
+
+    /// Validate the email address.
+    ///
+    /// See [`validate_addr`].
+    pub fn validate(&self, smtputf8: bool) -> Result<()> {
+        validate_addr(&self.addr, smtputf8)
+    }
+
+    /// Return the email address with its domain encoded in ASCII.
+    ///
+    /// See [`addr_to_ascii`].
+    pub fn to_ascii(&self) -> Result<String> {
+        addr_to_ascii(&self.addr)
+    }
+
+    /// Return the address with its domain decoded from punycode,
+    /// suitable for display.
+    pub fn to_unicode_string(&self) -> String {
+        let addr = addr_to_unicode(&self.addr);
+        match &self.name {
+            Some(name) => format!("{name} <{addr}>"),
+            None => addr,
+        }
+    }
+}
+
+/// Split the given email address into its local part and its
+/// domain.
+///
+/// The split happens at the last `@`, since quoted local parts may
+/// contain `@` as well.
+pub fn split_addr(addr: &str) -> Option<(&str, &str)> {
+    addr.trim().rsplit_once('@')
+}
+
+/// Return `true` if the given email address has a non-ASCII local
+/// part.
+///
+/// Non-ASCII domains do not count, since they can be encoded using
+/// IDNA.
+pub fn requires_smtputf8(addr: &str) -> bool {
+    match split_addr(addr) {
+        Some((local, _)) => !local.is_ascii(),
+        None => !addr.is_ascii(),
+    }
+}
+
+/// Validate the given email address.
+///
+/// The address must have a non-empty local part and a domain valid
+/// according to UTS-46. When `smtputf8` is `false`, the local part
+/// must be ASCII.
+pub fn validate_addr(addr: &str, smtputf8: bool) -> Result<()> {
+    let invalid = |reason: &str| Error::ValidateAddressError(addr.to_owned(), reason.to_owned());
+
+    let (local, domain) = split_addr(addr).ok_or_else(|| invalid("missing @"))?;
+
+    if local.is_empty() {
+        return Err(invalid("empty local part"));
+    }
+
+    if local.len() > LOCAL_PART_MAX_LEN {
+        return Err(invalid("local part too long"));
+    }
+
+    if local.chars().any(|c| c.is_control() || c.is_whitespace()) && !local.starts_with('"') {
+        return Err(invalid("invalid character in local part"));
+    }
+
+    if !smtputf8 && !local.is_ascii() {
+        return Err(invalid("non-ASCII local part requires SMTPUTF8"));
+    }
+
+    if domain.is_empty() {
+        return Err(invalid("empty domain"));
+    }
+
+    domain_to_ascii(addr, domain)?;
+
+    Ok(())
+}
+
+/// Return the given email address with its domain encoded in ASCII
+/// using UTS-46 IDNA processing, the local part being kept as it is.
+pub fn addr_to_ascii(addr: &str) -> Result<String> {
+    let addr = addr.trim();
+
+    match split_addr(addr) {
+        Some((local, domain)) => Ok(format!("{local}@{}", domain_to_ascii(addr, domain)?)),
+        None => Ok(addr.to_owned()),
+    }
+}
+
+/// Return the given email address with its domain decoded from
+/// punycode.
+///
+/// Domains which cannot be decoded are kept as they are, which makes
+/// this function suitable for display.
+pub fn addr_to_unicode(addr: &str) -> String {
+    let addr = addr.trim();
+
+    match split_addr(addr) {
+        Some((local, domain)) => match idna::domain_to_unicode(domain) {
+            (domain, Ok(())) => format!("{local}@{domain}"),
+            (_, Err(_)) => addr.to_owned(),
+        },
+        None => addr.to_owned(),
+    }
+}
+
+/// Return the canonical form of the given email address, used to
+/// compare addresses.
+///
+/// The domain is encoded in ASCII and lowercased, so that Unicode and
+/// punycode forms of the same domain compare equal.
+pub fn normalize_addr(addr: &str) -> String {
+    addr_to_ascii(addr)
+        .map(|addr| match split_addr(&addr) {
+            Some((local, domain)) => format!("{local}@{}", domain.to_lowercase()),
+            None => addr,
+        })
+        .unwrap_or_else(|_| addr.trim().to_owned())
+}
+
+/// Normalize the given display name, as decoded by the message
+/// parser.
+///
+/// Encoded words left undecoded by the parser (unknown charset,
+/// broken encoding) are decoded leniently, see [`decode_raw_header`].
+/// Dangerous characters (control, bidirectional and zero-width
+/// characters) are stripped, whitespaces are collapsed and stray
+/// quotes are removed. Empty names become `None`.
+pub fn normalize_display_name(name: &str) -> Option<String> {
+    let name = decode_encoded_words(name);
+    let mut normalized = String::with_capacity(name.len());
+
+    for c in name.chars() {
+        if c.is_whitespace() {
+            if !normalized.ends_with(' ') {
+                normalized.push(' ');
+            }
+        } else if !is_dangerous_char(c) {
+            normalized.push(c);
+        }
+    }
+
+    let normalized = normalized.trim().trim_matches(['"', '\'']).trim();
+
+    if normalized.is_empty() {
+        None
+    } else {
+        Some(normalized.to_owned())
+    }
+}
+
+/// Decode the given raw header bytes.
+///
+/// Valid UTF-8 is kept as it is. Otherwise bytes are decoded as
+/// Windows-1252, a superset of Latin-1, which is by far the most
+/// common charset of raw 8-bit headers.
+pub fn decode_raw_header(bytes: &[u8]) -> Cow<'_, str> {
+    match std::str::from_utf8(bytes) {
+        Ok(header) => Cow::Borrowed(header),
+        Err(_) => match charset_decoder(b"windows-1252") {
+            Some(decode) => Cow::Owned(decode(bytes)),
+            None => Cow::Owned(bytes.iter().map(|b| *b as char).collect()),
+        },
+    }
+}
+
+/// Decode the RFC 2047 encoded words of the given text.
+///
+/// Unknown charsets are sniffed using [`decode_raw_header`], and
+/// words that cannot be decoded are kept as they are.
+fn decode_encoded_words(text: &str) -> Cow<'_, str> {
+    if !text.contains("=?") {
+        return Cow::Borrowed(text);
+    }
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut prev_was_word = false;
+
+    while let Some(start) = rest.find("=?") {
+        let (before, word) = rest.split_at(start);
+
+        let Some((bytes, len)) = decode_encoded_word(word) else {
+            decoded.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            prev_was_word = false;
+            continue;
+        };
+
+        // whitespaces between adjacent encoded words are ignored
+        if !(prev_was_word && before.trim().is_empty()) {
+            decoded.push_str(before);
+        }
+
+        decoded.push_str(&bytes);
+        rest = &word[len..];
+        prev_was_word = true;
+    }
+
+    decoded.push_str(rest);
+    Cow::Owned(decoded)
+}
+
+/// Decode the encoded word `=?charset?encoding?text?=` at the start
+/// of the given text, then return the decoded text and the length of
+/// the encoded word.
+fn decode_encoded_word(text: &str) -> Option<(String, usize)> {
+    let mut parts = text.get(2..)?.splitn(3, '?');
+    let charset = parts.next()?;
+    let encoding = parts.next()?;
+    let rest = parts.next()?;
+    let (word, _) = rest.split_once("?=")?;
+    let len = 2 + charset.len() + 1 + encoding.len() + 1 + word.len() + 2;
+
+    let bytes = match encoding {
+        "B" | "b" => base64_decode(word.as_bytes())?,
+        "Q" | "q" => quoted_printable_decode(word.replace('_', " ").as_bytes())?,
+        _ => return None,
+    };
+
+    // RFC 2231 language suffix, like utf-8*en
+    let charset = charset.split('*').next().unwrap_or_default();
+
+    let is_utf8 = charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8");
+
+    let decoded = match std::str::from_utf8(&bytes) {
+        Ok(text) => text.to_owned(),
+        Err(_) => match charset_decoder(charset.as_bytes()) {
+            Some(decode) if !is_utf8 => decode(&bytes),
+            _ => decode_raw_header(&bytes).into_owned(),
+        },
+    };
+
+    Some((decoded, len))
+}
+
+fn is_dangerous_char(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            // zero-width and directional marks
+            '\u{200B}'..='\u{200F}'
+            // bidirectional embeddings and overrides
+            | '\u{202A}'..='\u{202E}'
+            // bidirectional isolates
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+        )
+}
+
+fn domain_to_ascii(addr: &str, domain: &str) -> Result<String> {
+    // domain literals like [127.0.0.1] are not subject to IDNA
+    if domain.starts_with('[') && domain.ends_with(']') {
+        return Ok(domain.to_owned());
+    }
+
+    // strict mode enforces STD3 rules, which match the RFC 5321
+    // domain syntax
+    idna::domain_to_ascii_strict(domain)
+        .map_err(|err| Error::EncodeAddressDomainError(err, addr.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_raw_header, normalize_display_name, Address, AddressDisplayFormat};
+
+    #[test]
+    fn idn_domain_to_ascii() {
+        let addr = Address::new(Some("Bücher"), "info@Bücher.example");
+        assert_eq!(addr.to_ascii().unwrap(), "info@xn--bcher-kva.example");
+        assert!(!addr.requires_smtputf8());
+        assert!(addr.validate(false).is_ok());
+    }
+
+    #[test]
+    fn punycode_domain_to_unicode() {
+        let addr = Address::new(Some("Info"), "info@xn--bcher-kva.example");
+        assert_eq!(addr.to_unicode_string(), "Info <info@bücher.example>");
+    }
+
+    #[test]
+    fn mixed_script_addresses() {
+        let addr = Address::new_nameless("用户@例子.广告");
+        assert!(addr.requires_smtputf8());
+        assert!(addr.validate(false).is_err());
+        assert!(addr.validate(true).is_ok());
+        assert_eq!(addr.to_ascii().unwrap(), "用户@xn--fsqu00a.xn--4rr70v");
+
+        let addr = Address::new_nameless("αβγ.user@παράδειγμα.δοκιμή");
+        assert_eq!(addr.local_part(), Some("αβγ.user"));
+        assert!(addr.validate(true).is_ok());
+        assert_eq!(
+            super::normalize_addr(&addr.addr),
+            super::normalize_addr(&addr.to_ascii().unwrap())
+        );
+
+        let addr = Address::new_nameless("user@exa mple.com");
+        assert!(addr.validate(true).is_err());
+
+        let addr = Address::new_nameless("user.example.com");
+        assert!(addr.validate(true).is_err());
+    }
+
+    #[test]
+    fn display_address() {
+        let addr = Address::new(Some("Alice"), "alice@localhost");
+        assert_eq!(addr.display_name(), "Alice");
+        assert_eq!(addr.display(AddressDisplayFormat::Name), "Alice");
+        assert_eq!(
+            addr.display(AddressDisplayFormat::NameAndAddress),
+            "Alice <alice@localhost>"
+        );
+        assert_eq!(
+            addr.display(AddressDisplayFormat::Address),
+            "alice@localhost"
+        );
+
+        let addr = Address::new_nameless("alice@localhost");
+        assert_eq!(addr.display_name(), "alice@localhost");
+    }
+
+    #[test]
+    fn normalize_names() {
+        // undecoded encoded words, with an unknown charset
+        assert_eq!(
+            normalize_display_name("=?x-unknown?Q?Ren=C3=A9?= =?utf-8?B?RHVwb250?=").as_deref(),
+            Some("RenéDupont")
+        );
+
+        // latin-1 bytes declared as utf-8
+        assert_eq!(
+            normalize_display_name("=?utf-8?Q?Ren=E9?=").as_deref(),
+            Some("René")
+        );
+
+        // bidi override and control characters
+        assert_eq!(
+            normalize_display_name("\"Bob\u{202E}gpj.exe\u{7}\t Smith\"").as_deref(),
+            Some("Bobgpj.exe Smith")
+        );
+
+        assert_eq!(normalize_display_name(" \"\" "), None);
+    }
+
+    #[test]
+    fn decode_latin1_header() {
+        assert_eq!(
+            decode_raw_header(b"Ren\xe9 <rene@localhost>"),
+            "René <rene@localhost>"
+        );
+        assert_eq!(decode_raw_header("René".as_bytes()), "René");
+    }
 }
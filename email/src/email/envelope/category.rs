@@ -0,0 +1,237 @@
+//! # Envelope category
+//!
+//! Module dedicated to envelope categories, which allow clients to
+//! implement tabbed inboxes. Envelopes are classified locally by the
+//! rule-based [`EnvelopeClassifier`], using headers set by mailing
+//! list managers and automated senders, and using the frequency of
+//! senders among the recipients of sent messages.
+
+use std::{collections::HashMap, fmt};
+
+use super::{Envelope, Envelopes};
+
+/// The minimum number of sent messages to a sender from which this
+/// sender is considered as a known correspondent.
+pub const DEFAULT_MIN_SENDER_FREQUENCY: usize = 1;
+
+/// The envelope category.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum EnvelopeCategory {
+    /// The message has been written by a person.
+    #[default]
+    Personal,
+
+    /// The message has been sent to a mailing list, or is a bulk
+    /// message (newsletter, promotion etc).
+    Newsletter,
+
+    /// The message has been generated automatically (notification,
+    /// receipt, auto-reply etc).
+    Notification,
+}
+
+impl fmt::Display for EnvelopeCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Personal => write!(f, "personal"),
+            Self::Newsletter => write!(f, "newsletter"),
+            Self::Notification => write!(f, "notification"),
+        }
+    }
+}
+
+/// The rule-based envelope classifier.
+///
+/// Rules are applied in this order:
+///
+/// 1. A message with an `Auto-Submitted` header different from `no`
+///    (RFC 3834) is a notification.
+///
+/// 2. A message sent by a known correspondent is personal.
+///
+/// 3. A message with a `List-Id` or `List-Unsubscribe` header, or
+///    with a `Precedence` header set to `bulk`, `list` or `junk` is a
+///    newsletter.
+///
+/// 4. A message sent from a no-reply address is a notification.
+///
+/// 5. Other messages are personal.
+///
+/// Headers are taken from [`Envelope::headers`], so they need to be
+/// requested when listing envelopes, see [`Self::HEADERS`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EnvelopeClassifier {
+    /// The number of sent messages per recipient address, in lower
+    /// case.
+    sender_frequencies: HashMap<String, usize>,
+
+    /// The minimum frequency from which a sender is known.
+    min_sender_frequency: Option<usize>,
+}
+
+impl EnvelopeClassifier {
+    /// The headers used by the classifier, to be added to
+    /// [`ListEnvelopesOptions::extra_headers`](super::list::ListEnvelopesOptions::extra_headers).
+    pub const HEADERS: [&'static str; 4] = [
+        "auto-submitted",
+        "list-id",
+        "list-unsubscribe",
+        "precedence",
+    ];
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count the recipients of the given sent envelopes as known
+    /// correspondents.
+    pub fn add_sent_envelopes<'a>(&mut self, envelopes: impl IntoIterator<Item = &'a Envelope>) {
+        for envelope in envelopes {
+            let addr = envelope.to.addr.to_lowercase();
+            if !addr.is_empty() {
+                *self.sender_frequencies.entry(addr).or_default() += 1;
+            }
+        }
+    }
+
+    /// Count the recipients of the given sent envelopes, using the
+    /// builder pattern.
+    pub fn with_sent_envelopes<'a>(
+        mut self,
+        envelopes: impl IntoIterator<Item = &'a Envelope>,
+    ) -> Self {
+        self.add_sent_envelopes(envelopes);
+        self
+    }
+
+    pub fn set_min_sender_frequency(&mut self, frequency: usize) {
+        self.min_sender_frequency = Some(frequency);
+    }
+
+    pub fn with_min_sender_frequency(mut self, frequency: usize) -> Self {
+        self.set_min_sender_frequency(frequency);
+        self
+    }
+
+    /// Return `true` if the given address belongs to a known
+    /// correspondent.
+    pub fn is_known_sender(&self, addr: &str) -> bool {
+        let min = self
+            .min_sender_frequency
+            .unwrap_or(DEFAULT_MIN_SENDER_FREQUENCY)
+            .max(1);
+
+        self.sender_frequencies
+            .get(&addr.to_lowercase())
+            .is_some_and(|frequency| *frequency >= min)
+    }
+
+    /// Classify the given envelope.
+    pub fn classify(&self, envelope: &Envelope) -> EnvelopeCategory {
+        let header = |name: &str| envelope.get_header(name).map(|v| v.trim().to_lowercase());
+
+        if header("auto-submitted").is_some_and(|value| !value.starts_with("no")) {
+            return EnvelopeCategory::Notification;
+        }
+
+        if self.is_known_sender(&envelope.from.addr) {
+            return EnvelopeCategory::Personal;
+        }
+
+        let is_list = header("list-id").is_some() || header("list-unsubscribe").is_some();
+        let is_bulk = header("precedence")
+            .is_some_and(|value| matches!(value.as_str(), "bulk" | "list" | "junk"));
+
+        if is_list || is_bulk {
+            return EnvelopeCategory::Newsletter;
+        }
+
+        if is_no_reply(&envelope.from.addr) {
+            return EnvelopeCategory::Notification;
+        }
+
+        EnvelopeCategory::Personal
+    }
+
+    /// Classify the given envelopes, and set their category.
+    pub fn classify_envelopes(&self, envelopes: &mut Envelopes) {
+        for envelope in envelopes.iter_mut() {
+            envelope.category = Some(self.classify(envelope));
+        }
+    }
+}
+
+/// Return `true` if the local part of the given address looks like a
+/// no-reply address.
+fn is_no_reply(addr: &str) -> bool {
+    let local = addr.split('@').next().unwrap_or_default().to_lowercase();
+    let local: String = local.chars().filter(char::is_ascii_alphanumeric).collect();
+
+    ["noreply", "donotreply", "notification", "mailerdaemon"]
+        .iter()
+        .any(|prefix| local.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EnvelopeCategory, EnvelopeClassifier};
+    use crate::envelope::{Address, Envelope};
+
+    fn envelope(from: &str, headers: &[(&str, &str)]) -> Envelope {
+        Envelope {
+            from: Address::new_nameless(from),
+            to: Address::new_nameless(from),
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..Envelope::default()
+        }
+    }
+
+    #[test]
+    fn classify() {
+        let classifier =
+            EnvelopeClassifier::new().with_sent_envelopes([&envelope("Friend@localhost", &[])]);
+
+        let list = [("list-id", "<rust.lists.localhost>")];
+        let auto = [("auto-submitted", "auto-generated")];
+        let bulk = [("precedence", "Bulk")];
+
+        let category = |from, headers| classifier.classify(&envelope(from, headers));
+
+        assert_eq!(
+            category("friend@localhost", &[]),
+            EnvelopeCategory::Personal
+        );
+        assert_eq!(
+            category("friend@localhost", &list),
+            EnvelopeCategory::Personal
+        );
+        assert_eq!(
+            category("friend@localhost", &auto),
+            EnvelopeCategory::Notification
+        );
+        assert_eq!(
+            category("news@localhost", &list),
+            EnvelopeCategory::Newsletter
+        );
+        assert_eq!(
+            category("news@localhost", &bulk),
+            EnvelopeCategory::Newsletter
+        );
+        assert_eq!(
+            category("no-reply@localhost", &[]),
+            EnvelopeCategory::Notification
+        );
+        assert_eq!(
+            category("stranger@localhost", &[("auto-submitted", "no")]),
+            EnvelopeCategory::Personal
+        );
+    }
+}
@@ -1,10 +1,10 @@
-use super::list::config::EnvelopeListConfig;
 #[cfg(feature = "sync")]
 use super::sync::config::EnvelopeSyncConfig;
 #[cfg(feature = "thread")]
 use super::thread::config::EnvelopeThreadConfig;
 #[cfg(feature = "watch")]
 use super::watch::config::WatchEnvelopeConfig;
+use super::{address::AddressDisplayFormat, list::config::EnvelopeListConfig};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -13,6 +13,13 @@
     serde(rename_all = "kebab-case")
 )]
 pub struct EnvelopeConfig {
+    /// The format used to display envelope addresses, in both
+    /// listings and threads.
+    ///
+    /// Defaults to the name, or the email address if the address has
+    /// no name.
+    pub address_format: Option<AddressDisplayFormat>,
+
     /// The envelope config related to listing.
     pub list: Option<EnvelopeListConfig>,
 
@@ -30,7 +30,7 @@ impl AddFlags for AddImapFlags {
     async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("adding imap flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
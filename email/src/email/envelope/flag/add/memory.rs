@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::AddFlags;
+use crate::{envelope::Id, flag::Flags, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct AddMemoryFlags {
+    ctx: MemoryContextSync,
+}
+
+impl AddMemoryFlags {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn AddFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn AddFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl AddFlags for AddMemoryFlags {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("adding memory flag(s) {flags} to envelope {id} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let mut store = self.ctx.lock();
+        let mfolder = store.folder_mut(&folder)?;
+
+        for id in id.iter() {
+            if let Some(msg) = mfolder.get_mut(id) {
+                msg.flags.extend(flags.iter().cloned());
+            }
+        }
+
+        Ok(())
+    }
+}
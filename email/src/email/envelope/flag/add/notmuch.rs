@@ -86,8 +86,16 @@ async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
                             .insert_flag(maildirs::Flag::Draft)
                             .map_err(Error::MaildirppFailure)?;
                     }
-                    Flag::Custom(tag) => {
-                        msg.add_tag(tag).map_err(Error::NotMuchFailure)?;
+                    Flag::Forwarded => {
+                        msg.add_tag("passed").map_err(Error::NotMuchFailure)?;
+                        entry
+                            .insert_flag(maildirs::Flag::Passed)
+                            .map_err(Error::MaildirppFailure)?;
+                    }
+                    flag => {
+                        if let Some(tag) = flag.to_notmuch_tag() {
+                            msg.add_tag(tag).map_err(Error::NotMuchFailure)?;
+                        }
                     }
                 }
 
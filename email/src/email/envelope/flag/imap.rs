@@ -3,7 +3,7 @@
 //! This module contains flag-related mapping functions from the
 //! [imap] crate types.
 
-use std::fmt;
+use std::{fmt, str::FromStr};
 
 use imap_client::imap_next::imap_types::{
     error::ValidationError,
@@ -51,10 +51,39 @@ pub fn to_imap_string(&self) -> String {
             Flag::Flagged => String::from("\\Flagged"),
             Flag::Deleted => String::from("\\Deleted"),
             Flag::Draft => String::from("\\Draft"),
+            Flag::Forwarded => String::from("$Forwarded"),
+            Flag::Important => String::from("$Important"),
+            Flag::Junk => String::from("$Junk"),
+            Flag::NotJunk => String::from("$NotJunk"),
+            Flag::Phishing => String::from("$Phishing"),
+            Flag::MdnSent => String::from("$MDNSent"),
             Flag::Custom(flag) => flag.clone(),
         }
     }
 
+    /// Parse an IMAP keyword.
+    ///
+    /// Well-known keywords (`$Forwarded`, `$Important`, `$Junk`,
+    /// `$NotJunk`, `$Phishing`, `$MDNSent`) are parsed into their
+    /// typed variant, other keywords are kept as custom flags.
+    pub fn from_imap_keyword(keyword: &str) -> Self {
+        let flag = keyword
+            .strip_prefix('$')
+            .and_then(|name| Flag::from_str(name).ok());
+
+        match flag {
+            Some(
+                flag @ (Flag::Forwarded
+                | Flag::Important
+                | Flag::Junk
+                | Flag::NotJunk
+                | Flag::Phishing
+                | Flag::MdnSent),
+            ) => flag,
+            _ => Flag::custom(keyword),
+        }
+    }
+
     pub fn try_from_imap_fetch(fetch: &FlagFetch<'_>) -> Result<Self, Error> {
         match fetch {
             FlagFetch::Flag(ImapFlag::Seen) => Ok(Flag::Seen),
@@ -62,6 +91,9 @@ pub fn try_from_imap_fetch(fetch: &FlagFetch<'_>) -> Result<Self, Error> {
             FlagFetch::Flag(ImapFlag::Flagged) => Ok(Flag::Flagged),
             FlagFetch::Flag(ImapFlag::Deleted) => Ok(Flag::Deleted),
             FlagFetch::Flag(ImapFlag::Draft) => Ok(Flag::Draft),
+            FlagFetch::Flag(ImapFlag::Keyword(keyword)) => {
+                Ok(Flag::from_imap_keyword(keyword.as_ref()))
+            }
             FlagFetch::Flag(flag) => Err(Error::ParseFlagImapError(flag.to_string())),
             FlagFetch::Recent => Err(Error::ParseFlagImapError("\\Recent".into())),
         }
@@ -79,6 +111,7 @@ fn try_from(flag: Flag) -> Result<ImapFlag<'static>, Self::Error> {
             Flag::Deleted => ImapFlag::Deleted,
             Flag::Draft => ImapFlag::Draft,
             Flag::Custom(flag) => ImapFlag::Keyword(flag.try_into()?),
+            flag => ImapFlag::Keyword(flag.to_imap_string().try_into()?),
         })
     }
 }
@@ -94,6 +127,7 @@ fn try_from(flag: Flag) -> Result<SearchKey<'a>, Self::Error> {
             Flag::Deleted => SearchKey::Deleted,
             Flag::Draft => SearchKey::Draft,
             Flag::Custom(flag) => SearchKey::Keyword(flag.try_into()?),
+            flag => SearchKey::Keyword(flag.to_imap_string().try_into()?),
         })
     }
 }
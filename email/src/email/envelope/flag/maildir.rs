@@ -62,7 +62,7 @@ impl TryFrom<maildirs::Flag> for Flag {
 
     fn try_from(flag: maildirs::Flag) -> Result<Self> {
         match flag {
-            maildirs::Flag::Passed => Err(Error::ParseFlagError(format!("{flag:?}"))),
+            maildirs::Flag::Passed => Ok(Flag::Forwarded),
             maildirs::Flag::Replied => Ok(Flag::Answered),
             maildirs::Flag::Seen => Ok(Flag::Seen),
             maildirs::Flag::Trashed => Ok(Flag::Deleted),
@@ -82,7 +82,9 @@ fn try_from(flag: &Flag) -> Result<Self> {
             Flag::Deleted => Ok(maildirs::Flag::Trashed),
             Flag::Draft => Ok(maildirs::Flag::Draft),
             Flag::Flagged => Ok(maildirs::Flag::Flagged),
+            Flag::Forwarded => Ok(maildirs::Flag::Passed),
             Flag::Custom(flag) => Err(Error::ParseFlagError(flag.clone())),
+            flag => Err(Error::ParseFlagError(flag.to_string())),
         }
     }
 }
@@ -97,7 +99,9 @@ fn try_from(flag: Flag) -> Result<Self> {
             Flag::Deleted => Ok(maildirs::Flag::Trashed),
             Flag::Draft => Ok(maildirs::Flag::Draft),
             Flag::Flagged => Ok(maildirs::Flag::Flagged),
+            Flag::Forwarded => Ok(maildirs::Flag::Passed),
             Flag::Custom(flag) => Err(Error::ParseFlagError(flag)),
+            flag => Err(Error::ParseFlagError(flag.to_string())),
         }
     }
 }
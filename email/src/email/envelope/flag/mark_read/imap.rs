@@ -0,0 +1,85 @@
+use std::num::NonZeroU32;
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use imap_client::imap_next::imap_types::{
+    datetime::NaiveDate, flag::Flag, search::SearchKey, sequence::SequenceSet,
+};
+use tracing::{debug, info};
+use utf7_imap::encode_utf7_imap as encode_utf7;
+
+use super::MarkReadBefore;
+use crate::{email::error::Error, imap::ImapContext, AnyResult};
+
+#[derive(Clone, Debug)]
+pub struct MarkReadBeforeImap {
+    ctx: ImapContext,
+}
+
+impl MarkReadBeforeImap {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn MarkReadBefore> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn MarkReadBefore>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl MarkReadBefore for MarkReadBeforeImap {
+    async fn mark_read_before(&self, folder: &str, date: DateTime<FixedOffset>) -> AnyResult<()> {
+        info!("marking imap envelopes from folder {folder} as read before {date}");
+
+        let mut client = self.ctx.client().await?;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = encode_utf7(folder.clone());
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let day = date.date_naive();
+        let imap_day: NaiveDate = day
+            .try_into()
+            .map_err(|err| Error::ParseImapSearchDateError(err, day))?;
+
+        client.select_mailbox(&folder_encoded).await?;
+
+        // IMAP dates have a day granularity: unseen messages received
+        // before the given day are marked as read straight away…
+        let mut uids = client
+            .search_uids([SearchKey::Unseen, SearchKey::Before(imap_day.clone())])
+            .await?;
+
+        // …while messages received the given day need their envelope
+        // date to be compared with the given datetime.
+        let same_day_uids = client
+            .search_uids([SearchKey::Unseen, SearchKey::On(imap_day)])
+            .await?;
+
+        if let Ok(same_day_uids) = SequenceSet::try_from(same_day_uids) {
+            let envelopes = client.fetch_envelopes(same_day_uids).await?;
+
+            uids.extend(
+                envelopes
+                    .iter()
+                    .filter(|envelope| envelope.date < date)
+                    .filter_map(|envelope| envelope.id.parse::<NonZeroU32>().ok()),
+            );
+        }
+
+        debug!("marking {} imap envelope(s) as read", uids.len());
+
+        let Ok(uids) = SequenceSet::try_from(uids) else {
+            return Ok(());
+        };
+
+        client.add_flags_silently(uids, [Flag::Seen]).await?;
+
+        Ok(())
+    }
+}
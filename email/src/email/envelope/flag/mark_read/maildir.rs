@@ -0,0 +1,129 @@
+use std::{fs, time::SystemTime};
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use maildirs::{Flag, MaildirEntry};
+use tracing::{debug, info};
+
+use super::MarkReadBefore;
+use crate::{
+    email::error::Error,
+    envelope::Envelope,
+    maildir::{find_cur_entries, AsyncMaildir, MaildirContextSync},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct MarkReadBeforeMaildir {
+    ctx: MaildirContextSync,
+}
+
+impl MarkReadBeforeMaildir {
+    pub fn new(ctx: &MaildirContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MaildirContextSync) -> Box<dyn MarkReadBefore> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MaildirContextSync) -> Option<Box<dyn MarkReadBefore>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl MarkReadBefore for MarkReadBeforeMaildir {
+    async fn mark_read_before(&self, folder: &str, date: DateTime<FixedOffset>) -> AnyResult<()> {
+        info!("marking maildir envelopes from folder {folder} as read before {date}");
+
+        let ctx = self.ctx.lock().await;
+        let mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(folder)?);
+        let folder = folder.to_owned();
+
+        mdir.run(move |mdir| {
+            let ids: Vec<String> = mdir
+                .read()
+                .map_err(Error::ListMaildirEntriesError)?
+                .filter(|entry| !entry.flags().is_ok_and(|flags| flags.contains(&Flag::Seen)))
+                .filter(|entry| received_before(entry, &date))
+                .filter_map(|entry| Some(entry.id().ok()?.to_owned()))
+                .collect();
+
+            debug!("marking {} maildir envelope(s) as read", ids.len());
+
+            let entries = find_cur_entries(&mdir, &ids).map_err(Error::ListMaildirEntriesError)?;
+
+            for mut entry in entries {
+                entry.insert_flag(Flag::Seen).map_err(|err| {
+                    Error::MarkReadBeforeMaildirError(err, folder.clone(), entry.path().to_owned())
+                })?;
+            }
+
+            Ok::<_, Error>(())
+        })
+        .await
+        .map_err(Error::ListMaildirEntriesError)??;
+
+        Ok(())
+    }
+}
+
+/// Return `true` if the given entry was received before the given
+/// date.
+///
+/// The modification time of the entry file is used as reception
+/// date, since it does not require to read the message. The `Date`
+/// header is used as a fallback when the modification time is not
+/// available.
+fn received_before(entry: &MaildirEntry, date: &DateTime<FixedOffset>) -> bool {
+    let mtime = fs::metadata(entry.path()).and_then(|meta| meta.modified());
+
+    match mtime {
+        Ok(mtime) => mtime < SystemTime::from(*date),
+        Err(err) => {
+            debug!(?err, path = ?entry.path(), "cannot get mtime, reading date header");
+            let entry = MaildirEntry::new(entry.path());
+            Envelope::try_from(entry).is_ok_and(|envelope| envelope.date < *date)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::File,
+        time::{Duration, SystemTime},
+    };
+
+    use chrono::{DateTime, FixedOffset};
+    use maildirs::{Flag, Maildir};
+
+    use super::received_before;
+
+    #[test]
+    fn received_before_uses_mtime() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = Maildir::from(path.clone());
+        mdir.create_all().unwrap();
+
+        let entry = mdir
+            .write_cur("From: alice@localhost\r\n\r\nHello", [Flag::Draft])
+            .unwrap();
+
+        let mtime = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::options()
+            .write(true)
+            .open(entry.path())
+            .unwrap()
+            .set_modified(mtime)
+            .unwrap();
+
+        let before: DateTime<FixedOffset> = "2023-11-14T22:13:19+00:00".parse().unwrap();
+        let after: DateTime<FixedOffset> = "2023-11-14T22:13:21+00:00".parse().unwrap();
+
+        assert!(!received_before(&entry, &before));
+        assert!(received_before(&entry, &after));
+    }
+}
@@ -0,0 +1,26 @@
+//! # Mark read before
+//!
+//! Module dedicated to catch-up markers: marking as read every
+//! envelope of a folder received before a given date. Backends
+//! implement it without listing envelopes client-side, which keeps
+//! the operation cheap on folders holding tens of thousands of
+//! messages.
+
+#[cfg(feature = "imap")]
+pub mod imap;
+#[cfg(feature = "maildir")]
+pub mod maildir;
+#[cfg(feature = "notmuch")]
+pub mod notmuch;
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+
+use crate::AnyResult;
+
+#[async_trait]
+pub trait MarkReadBefore: Send + Sync {
+    /// Mark as read all envelopes from the given folder received
+    /// strictly before the given date.
+    async fn mark_read_before(&self, folder: &str, date: DateTime<FixedOffset>) -> AnyResult<()>;
+}
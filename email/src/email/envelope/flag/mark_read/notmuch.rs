@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use maildirs::MaildirEntry;
+use tracing::{debug, info};
+
+use super::MarkReadBefore;
+use crate::{email::error::Error, folder::FolderKind, notmuch::NotmuchContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct MarkReadBeforeNotmuch {
+    ctx: NotmuchContextSync,
+}
+
+impl MarkReadBeforeNotmuch {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn MarkReadBefore> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn MarkReadBefore>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl MarkReadBefore for MarkReadBeforeNotmuch {
+    async fn mark_read_before(&self, folder: &str, date: DateTime<FixedOffset>) -> AnyResult<()> {
+        info!("marking notmuch envelopes from folder {folder} as read before {date}");
+
+        let config = &self.ctx.account_config;
+        let ctx = self.ctx.lock().await;
+        let db = ctx.open_db()?;
+
+        let folder = &config.get_folder_alias(folder);
+        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
+            String::from("folder:\"\"")
+        } else {
+            format!("folder:{folder:?}")
+        };
+        // notmuch date ranges are inclusive, hence the second
+        // subtracted from the timestamp.
+        let date_query = format!("date:..@{}", date.timestamp() - 1);
+        let query = [folder_query, String::from("tag:unread"), date_query].join(" and ");
+        debug!("notmuch query: {query:?}");
+
+        let query_builder = db.create_query(&query).map_err(Error::NotMuchFailure)?;
+        let msgs = query_builder
+            .search_messages()
+            .map_err(Error::NotMuchFailure)?;
+
+        for msg in msgs {
+            let mut entry = MaildirEntry::new(msg.filename());
+
+            msg.remove_tag("unread").map_err(Error::NotMuchFailure)?;
+            entry
+                .insert_flag(maildirs::Flag::Seen)
+                .map_err(Error::MaildirppFailure)?;
+
+            if msg.filename() != entry.path() {
+                db.index_file(entry.path(), None)
+                    .map_err(Error::NotMuchFailure)?;
+            }
+        }
+
+        db.close().map_err(Error::NotMuchFailure)?;
+
+        Ok(())
+    }
+}
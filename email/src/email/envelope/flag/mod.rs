@@ -9,6 +9,7 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+pub mod mark_read;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod remove;
@@ -59,6 +60,30 @@ pub enum Flag {
     /// complete.
     Draft,
 
+    /// Flag used when the email has been forwarded. Maps to the IMAP
+    /// keyword `$Forwarded` and to the Maildir flag `P` (passed).
+    Forwarded,
+
+    /// Flag used when the email is considered important. Maps to the
+    /// IMAP keyword `$Important`.
+    Important,
+
+    /// Flag used when the email is considered as junk (spam). Maps to
+    /// the IMAP keyword `$Junk`.
+    Junk,
+
+    /// Flag used when the email is explicitly considered as not
+    /// junk. Maps to the IMAP keyword `$NotJunk`.
+    NotJunk,
+
+    /// Flag used when the email is considered as a phishing
+    /// attempt. Maps to the IMAP keyword `$Phishing`.
+    Phishing,
+
+    /// Flag used when a message disposition notification has been
+    /// sent for the email. Maps to the IMAP keyword `$MDNSent`.
+    MdnSent,
+
     /// Flag used for all other use cases.
     Custom(String),
 }
@@ -74,17 +99,8 @@ pub fn custom(flag: impl ToString) -> Self {
 /// the existing variant, it is considered as custom.
 impl From<&str> for Flag {
     fn from(s: &str) -> Self {
-        match s.trim() {
-            seen if seen.eq_ignore_ascii_case("seen") => Flag::Seen,
-            answered if answered.eq_ignore_ascii_case("answered") => Flag::Answered,
-            replied if replied.eq_ignore_ascii_case("replied") => Flag::Answered,
-            flagged if flagged.eq_ignore_ascii_case("flagged") => Flag::Flagged,
-            deleted if deleted.eq_ignore_ascii_case("deleted") => Flag::Deleted,
-            trashed if trashed.eq_ignore_ascii_case("trashed") => Flag::Deleted,
-            draft if draft.eq_ignore_ascii_case("draft") => Flag::Draft,
-            draft if draft.eq_ignore_ascii_case("draft") => Flag::Draft,
-            flag => Flag::Custom(flag.into()),
-        }
+        s.parse()
+            .unwrap_or_else(|_| Flag::Custom(s.trim().to_owned()))
     }
 }
 
@@ -103,7 +119,18 @@ fn from_str(s: &str) -> Result<Self, Error> {
             trashed if trashed.eq_ignore_ascii_case("trashed") => Ok(Flag::Deleted),
             draft if draft.eq_ignore_ascii_case("draft") => Ok(Flag::Draft),
             drafts if drafts.eq_ignore_ascii_case("drafts") => Ok(Flag::Draft),
-            unknown => Err(Error::ParseFlagError(unknown.to_string())),
+            keyword => match keyword.strip_prefix('$').unwrap_or(keyword) {
+                fwd if fwd.eq_ignore_ascii_case("forwarded") => Ok(Flag::Forwarded),
+                passed if passed.eq_ignore_ascii_case("passed") => Ok(Flag::Forwarded),
+                important if important.eq_ignore_ascii_case("important") => Ok(Flag::Important),
+                junk if junk.eq_ignore_ascii_case("junk") => Ok(Flag::Junk),
+                spam if spam.eq_ignore_ascii_case("spam") => Ok(Flag::Junk),
+                not_junk if not_junk.eq_ignore_ascii_case("notjunk") => Ok(Flag::NotJunk),
+                not_spam if not_spam.eq_ignore_ascii_case("notspam") => Ok(Flag::NotJunk),
+                phishing if phishing.eq_ignore_ascii_case("phishing") => Ok(Flag::Phishing),
+                mdn_sent if mdn_sent.eq_ignore_ascii_case("mdnsent") => Ok(Flag::MdnSent),
+                _ => Err(Error::ParseFlagError(keyword.to_string())),
+            },
         }
     }
 }
@@ -125,6 +152,12 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             Flag::Flagged => "flagged".into(),
             Flag::Deleted => "deleted".into(),
             Flag::Draft => "draft".into(),
+            Flag::Forwarded => "forwarded".into(),
+            Flag::Important => "important".into(),
+            Flag::Junk => "junk".into(),
+            Flag::NotJunk => "notjunk".into(),
+            Flag::Phishing => "phishing".into(),
+            Flag::MdnSent => "mdnsent".into(),
             Flag::Custom(flag) => flag.clone(),
         };
         write!(f, "{flag}")
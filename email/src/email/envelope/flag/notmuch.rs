@@ -9,6 +9,38 @@
 
 use super::Flag;
 
+impl Flag {
+    /// Parse a notmuch tag into a flag.
+    ///
+    /// Only covers keyword-like tags, system tags (`unread`, `draft`,
+    /// `flagged`, `replied`, `passed`) are handled while reading
+    /// message tags.
+    pub fn from_notmuch_tag(tag: &str) -> Self {
+        match tag {
+            "important" => Flag::Important,
+            "spam" => Flag::Junk,
+            "notspam" => Flag::NotJunk,
+            "phishing" => Flag::Phishing,
+            "mdnsent" => Flag::MdnSent,
+            tag => Flag::custom(tag),
+        }
+    }
+
+    /// Return the notmuch tag of keyword-like flags, see
+    /// [`Flag::from_notmuch_tag`].
+    pub fn to_notmuch_tag(&self) -> Option<&str> {
+        match self {
+            Flag::Important => Some("important"),
+            Flag::Junk => Some("spam"),
+            Flag::NotJunk => Some("notspam"),
+            Flag::Phishing => Some("phishing"),
+            Flag::MdnSent => Some("mdnsent"),
+            Flag::Custom(tag) => Some(tag),
+            _ => None,
+        }
+    }
+}
+
 impl From<&Message> for Flags {
     fn from(msg: &Message) -> Self {
         let mut flags = Flags::default();
@@ -25,11 +57,14 @@ fn from(msg: &Message) -> Self {
                 "replied" => {
                     flags.insert(Flag::Answered);
                 }
+                "passed" => {
+                    flags.insert(Flag::Forwarded);
+                }
                 "unread" => {
                     unread = true;
                 }
-                flag => {
-                    flags.insert(Flag::custom(flag));
+                tag => {
+                    flags.insert(Flag::from_notmuch_tag(tag));
                 }
             }
         }
@@ -31,7 +31,7 @@ impl RemoveFlags for RemoveImapFlags {
     async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("removing imap flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::RemoveFlags;
+use crate::{envelope::Id, flag::Flags, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct RemoveMemoryFlags {
+    ctx: MemoryContextSync,
+}
+
+impl RemoveMemoryFlags {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn RemoveFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn RemoveFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl RemoveFlags for RemoveMemoryFlags {
+    async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("removing memory flag(s) {flags} to envelope {id} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let mut store = self.ctx.lock();
+        let mfolder = store.folder_mut(&folder)?;
+
+        for id in id.iter() {
+            if let Some(msg) = mfolder.get_mut(id) {
+                msg.flags.retain(|flag| !flags.contains(flag));
+            }
+        }
+
+        Ok(())
+    }
+}
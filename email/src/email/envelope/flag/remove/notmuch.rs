@@ -86,8 +86,16 @@ async fn remove_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<
                             .remove_flag(maildirs::Flag::Draft)
                             .map_err(Error::MaildirppFailure)?;
                     }
-                    Flag::Custom(tag) => {
-                        msg.remove_tag(tag).map_err(Error::NotMuchFailure)?;
+                    Flag::Forwarded => {
+                        msg.remove_tag("passed").map_err(Error::NotMuchFailure)?;
+                        entry
+                            .remove_flag(maildirs::Flag::Passed)
+                            .map_err(Error::MaildirppFailure)?;
+                    }
+                    flag => {
+                        if let Some(tag) = flag.to_notmuch_tag() {
+                            msg.remove_tag(tag).map_err(Error::NotMuchFailure)?;
+                        }
                     }
                 }
 
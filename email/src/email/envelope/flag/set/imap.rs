@@ -30,7 +30,7 @@ impl SetFlags for SetImapFlags {
     async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
         info!("setting imap flag(s) {flags} to envelope {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
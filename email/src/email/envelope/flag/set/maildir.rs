@@ -4,7 +4,12 @@
 use tracing::info;
 
 use super::{Flags, SetFlags};
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::Id,
+    maildir::{find_cur_entries, AsyncMaildir, MaildirContextSync},
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct SetMaildirFlags {
@@ -31,20 +36,22 @@ async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()>
         info!("setting maildir flag(s) {flags} to envelope {id} from folder {folder}");
 
         let ctx = self.ctx.lock().await;
-        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
-
-        id.iter()
-            .filter_map(|id| mdir.find(id).ok().flatten())
-            .try_for_each(|mut entry| {
-                entry.update_flags(HashSet::from(flags)).map_err(|err| {
-                    Error::SetFlagsMaildirError(
-                        err,
-                        folder.to_owned(),
-                        id.to_string(),
-                        flags.clone(),
-                    )
-                })
-            })?;
+        let mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(folder)?);
+
+        let ids: Vec<String> = id.iter().map(ToOwned::to_owned).collect();
+        let mdir_flags = HashSet::from(flags);
+
+        mdir.run(move |mdir| {
+            for mut entry in find_cur_entries(&mdir, &ids)? {
+                entry.update_flags(mdir_flags.clone())?;
+            }
+            Ok(())
+        })
+        .await
+        .and_then(|res| res)
+        .map_err(|err| {
+            Error::SetFlagsMaildirError(err, folder.to_owned(), id.to_string(), flags.clone())
+        })?;
 
         Ok(())
     }
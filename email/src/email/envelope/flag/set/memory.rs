@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::SetFlags;
+use crate::{envelope::Id, flag::Flags, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct SetMemoryFlags {
+    ctx: MemoryContextSync,
+}
+
+impl SetMemoryFlags {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn SetFlags> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn SetFlags>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl SetFlags for SetMemoryFlags {
+    async fn set_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        info!("setting memory flag(s) {flags} to envelope {id} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let mut store = self.ctx.lock();
+        let mfolder = store.folder_mut(&folder)?;
+
+        for id in id.iter() {
+            if let Some(msg) = mfolder.get_mut(id) {
+                msg.flags = flags.clone();
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -2,6 +2,8 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 
@@ -29,7 +29,7 @@ impl GetEnvelope for GetImapEnvelope {
     async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
         info!("getting imap envelope {id:?} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -2,7 +2,11 @@
 use tracing::{info, trace};
 
 use super::{Envelope, GetEnvelope};
-use crate::{envelope::SingleId, maildir::MaildirContextSync, AnyResult, Error};
+use crate::{
+    envelope::SingleId,
+    maildir::{AsyncMaildir, MaildirContextSync},
+    AnyResult, Error,
+};
 
 #[derive(Clone)]
 pub struct GetMaildirEnvelope {
@@ -29,10 +33,16 @@ async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope>
         info!("getting maildir envelope {id:?} from folder {folder}");
 
         let session = self.ctx.lock().await;
-        let mdir = session.get_maildir_from_folder_alias(folder)?;
-
-        let entry = mdir.get(id.to_string()).map_err(Error::from)?;
-        let envelope = Envelope::try_from(entry)?;
+        let mdir = AsyncMaildir::from(session.get_maildir_from_folder_alias(folder)?);
+        let id = id.to_string();
+
+        let envelope = mdir
+            .run(move |mdir| -> AnyResult<Envelope> {
+                let entry = mdir.get(id).map_err(Error::from)?;
+                Ok(Envelope::try_from(entry)?)
+            })
+            .await
+            .map_err(Error::from)??;
         trace!("maildir envelope: {envelope:#?}");
 
         Ok(envelope)
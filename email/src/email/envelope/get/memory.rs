@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use tracing::{info, trace};
+
+use super::{Envelope, GetEnvelope};
+use crate::{
+    envelope::SingleId,
+    memory::{Error, MemoryContextSync},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct GetMemoryEnvelope {
+    ctx: MemoryContextSync,
+}
+
+impl GetMemoryEnvelope {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn GetEnvelope> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn GetEnvelope>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetEnvelope for GetMemoryEnvelope {
+    async fn get_envelope(&self, folder: &str, id: &SingleId) -> AnyResult<Envelope> {
+        info!("getting memory envelope {id:?} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let store = self.ctx.lock();
+
+        let msg = store
+            .folder(&folder)?
+            .get(id)
+            .ok_or_else(|| Error::MessageNotFoundError(folder.clone(), id.to_string()))?;
+
+        let envelope = msg.to_envelope(id.as_str());
+        trace!("memory envelope: {envelope:#?}");
+
+        Ok(envelope)
+    }
+}
@@ -1,8 +1,13 @@
 use std::{
+    collections::HashMap,
     fmt,
+    num::NonZeroU32,
     ops::{Deref, DerefMut},
+    str::FromStr,
 };
 
+use crate::email::error::{Error, Result};
+
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Id {
     Single(SingleId),
@@ -66,6 +71,11 @@ fn from(ids: &MultipleIds) -> Self {
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SingleId(String);
 
+/// The mapping between the identifiers of messages in a source
+/// folder and the identifiers of their copies in a destination
+/// folder.
+pub type IdMapping = HashMap<String, String>;
+
 impl SingleId {
     pub fn as_str(&self) -> &str {
         self.deref().as_str()
@@ -127,6 +137,259 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// The version of the stable identifier encoding.
+const STABLE_ID_VERSION: &str = "v1";
+
+/// The kind of backend a [`StableId`] belongs to.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum StableIdKind {
+    Imap,
+    Maildir,
+    Notmuch,
+    Memory,
+}
+
+impl StableIdKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Imap => "imap",
+            Self::Maildir => "maildir",
+            Self::Notmuch => "notmuch",
+            Self::Memory => "memory",
+        }
+    }
+}
+
+impl fmt::Display for StableIdKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for StableIdKind {
+    type Err = Error;
+
+    fn from_str(kind: &str) -> Result<Self> {
+        match kind {
+            "imap" => Ok(Self::Imap),
+            "maildir" => Ok(Self::Maildir),
+            "notmuch" => Ok(Self::Notmuch),
+            "memory" => Ok(Self::Memory),
+            kind => Err(Error::ParseStableIdKindError(kind.to_owned())),
+        }
+    }
+}
+
+/// The stable identifier of an envelope.
+///
+/// Raw envelope identifiers only make sense within a folder, and for
+/// IMAP within a given UIDVALIDITY. A stable identifier bundles all
+/// of them, so it can be stored and reused later on without risking
+/// to target the wrong message.
+///
+/// The stable identifier should be considered opaque: use
+/// [`StableId::encode`] and [`StableId::decode`] to persist it, and
+/// [`Backend::resolve_stable_ids`] to turn it back into a folder and
+/// an [`Id`] usable by backend features. The backend checks that the
+/// identifier still targets the same message, which a plain
+/// conversion could not do.
+///
+/// [`Backend::resolve_stable_ids`]: crate::backend::Backend::resolve_stable_ids
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct StableId {
+    kind: StableIdKind,
+    folder: String,
+    uid_validity: Option<NonZeroU32>,
+    id: String,
+}
+
+impl StableId {
+    /// Build a stable identifier from an IMAP UID and the
+    /// UIDVALIDITY of its folder.
+    pub fn imap(folder: impl ToString, uid_validity: NonZeroU32, uid: NonZeroU32) -> Self {
+        Self {
+            kind: StableIdKind::Imap,
+            folder: folder.to_string(),
+            uid_validity: Some(uid_validity),
+            id: uid.to_string(),
+        }
+    }
+
+    /// Build a stable identifier from a Maildir entry identifier.
+    ///
+    /// The entry identifier is the unique part of the entry file
+    /// name. Only its hash is kept, see [`StableId::maildir_hash`].
+    #[cfg(feature = "maildir")]
+    pub fn maildir(folder: impl ToString, id: impl AsRef<str>) -> Self {
+        Self {
+            kind: StableIdKind::Maildir,
+            folder: folder.to_string(),
+            uid_validity: None,
+            id: Self::maildir_hash(id),
+        }
+    }
+
+    /// Hash the given Maildir entry identifier.
+    ///
+    /// The unique part of a Maildir file name does not change when
+    /// flags change, but it is long and exposes host details. The
+    /// hash keeps the stable identifier short and opaque, the entry
+    /// is found back by hashing the entries of the folder.
+    #[cfg(feature = "maildir")]
+    pub fn maildir_hash(id: impl AsRef<str>) -> String {
+        use std::fmt::Write;
+
+        use sha2::{Digest, Sha256};
+
+        let digest = Sha256::digest(id.as_ref().as_bytes());
+        digest[..16]
+            .iter()
+            .fold(String::with_capacity(32), |mut hash, b| {
+                let _ = write!(hash, "{b:02x}");
+                hash
+            })
+    }
+
+    /// Build a stable identifier from a Notmuch message identifier.
+    pub fn notmuch(folder: impl ToString, id: impl ToString) -> Self {
+        Self {
+            kind: StableIdKind::Notmuch,
+            folder: folder.to_string(),
+            uid_validity: None,
+            id: id.to_string(),
+        }
+    }
+
+    /// Build a stable identifier from an in-memory envelope
+    /// identifier.
+    pub fn memory(folder: impl ToString, id: impl ToString) -> Self {
+        Self {
+            kind: StableIdKind::Memory,
+            folder: folder.to_string(),
+            uid_validity: None,
+            id: id.to_string(),
+        }
+    }
+
+    pub fn kind(&self) -> StableIdKind {
+        self.kind
+    }
+
+    pub fn folder(&self) -> &str {
+        &self.folder
+    }
+
+    pub fn uid_validity(&self) -> Option<NonZeroU32> {
+        self.uid_validity
+    }
+
+    /// Return the raw identifier part.
+    ///
+    /// It matches the envelope identifier for IMAP, Notmuch and
+    /// memory backends, and the entry identifier hash for Maildir.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Ensure the stable identifier belongs to the given backend
+    /// kind.
+    pub fn ensure_kind(&self, kind: StableIdKind) -> Result<()> {
+        if self.kind == kind {
+            Ok(())
+        } else {
+            Err(Error::StableIdKindMismatchError(self.encode(), kind))
+        }
+    }
+
+    /// Ensure the stable identifier was built with the given
+    /// UIDVALIDITY.
+    ///
+    /// Fails when UIDVALIDITY changed since the identifier was
+    /// built, which means that the UID may now target another
+    /// message.
+    pub fn ensure_uid_validity(&self, uid_validity: NonZeroU32) -> Result<()> {
+        match self.uid_validity {
+            Some(prev) if prev != uid_validity => {
+                Err(Error::StaleStableIdError(self.encode(), prev, uid_validity))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Encode the stable identifier into an opaque string.
+    pub fn encode(&self) -> String {
+        let kind = self.kind.as_str();
+        let uid_validity = match self.uid_validity {
+            Some(uid_validity) => uid_validity.to_string(),
+            None => String::new(),
+        };
+        let folder = urlencoding::encode(&self.folder);
+        let id = urlencoding::encode(&self.id);
+        format!("{STABLE_ID_VERSION}:{kind}:{uid_validity}:{folder}:{id}")
+    }
+
+    /// Decode a stable identifier previously encoded with
+    /// [`StableId::encode`].
+    pub fn decode(id: &str) -> Result<Self> {
+        let invalid = || Error::ParseStableIdError(id.to_owned());
+
+        let mut parts = id.split(':');
+
+        if parts.next() != Some(STABLE_ID_VERSION) {
+            return Err(invalid());
+        }
+
+        let (Some(kind), Some(uid_validity), Some(folder), Some(raw_id), None) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return Err(invalid());
+        };
+
+        let kind = kind.parse::<StableIdKind>()?;
+
+        let uid_validity = match uid_validity {
+            "" => None,
+            uid_validity => Some(uid_validity.parse::<NonZeroU32>().map_err(|_| invalid())?),
+        };
+
+        // only IMAP identifiers depend on UIDVALIDITY, and IMAP UIDs
+        // are always non-zero integers
+        match kind {
+            StableIdKind::Imap if uid_validity.is_none() => return Err(invalid()),
+            StableIdKind::Imap if raw_id.parse::<NonZeroU32>().is_err() => return Err(invalid()),
+            StableIdKind::Imap => (),
+            _ if uid_validity.is_some() => return Err(invalid()),
+            _ => (),
+        }
+
+        let folder = urlencoding::decode(folder).map_err(|_| invalid())?;
+        let raw_id = urlencoding::decode(raw_id).map_err(|_| invalid())?;
+
+        if raw_id.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            kind,
+            folder: folder.into_owned(),
+            uid_validity,
+            id: raw_id.into_owned(),
+        })
+    }
+}
+
+impl FromStr for StableId {
+    type Err = Error;
+
+    fn from_str(id: &str) -> Result<Self> {
+        Self::decode(id)
+    }
+}
+
 pub struct IdIterator<'a> {
     id: &'a Id,
     index: usize,
@@ -160,3 +423,61 @@ fn next(&mut self) -> Option<Self::Item> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::{StableId, StableIdKind};
+
+    #[test]
+    fn encode_decode_stable_id() {
+        let uid_validity = NonZeroU32::new(1234).unwrap();
+        let uid = NonZeroU32::new(42).unwrap();
+
+        let id = StableId::imap("Sent: 2024/Ω", uid_validity, uid);
+        let encoded = id.encode();
+        assert_eq!(StableId::decode(&encoded).unwrap(), id);
+        assert_eq!(id.id(), "42");
+
+        let id = StableId::notmuch("INBOX", "abc@localhost");
+        let encoded = id.encode();
+        assert_eq!(encoded.parse::<StableId>().unwrap(), id);
+        assert_eq!(id.kind(), StableIdKind::Notmuch);
+    }
+
+    #[cfg(feature = "maildir")]
+    #[test]
+    fn maildir_stable_id_hashes_entry_id() {
+        let id = StableId::maildir("INBOX", "1700000000.M1P2.host");
+        let encoded = id.encode();
+        assert_eq!(encoded.parse::<StableId>().unwrap(), id);
+        assert_eq!(id.kind(), StableIdKind::Maildir);
+        assert_eq!(id.id(), StableId::maildir_hash("1700000000.M1P2.host"));
+        assert_eq!(id.id().len(), 32);
+        assert!(!encoded.contains("host"));
+    }
+
+    #[test]
+    fn decode_invalid_stable_id() {
+        assert!(StableId::decode("42").is_err());
+        assert!(StableId::decode("v2:imap:1:INBOX:42").is_err());
+        assert!(StableId::decode("v1:pop:1:INBOX:42").is_err());
+        assert!(StableId::decode("v1:imap::INBOX:42").is_err());
+        assert!(StableId::decode("v1:imap:1:INBOX:abc").is_err());
+        assert!(StableId::decode("v1:maildir:1:INBOX:abc").is_err());
+        assert!(StableId::decode("v1:maildir::INBOX:").is_err());
+        assert!(StableId::decode("v1:maildir::INBOX:abc:def").is_err());
+    }
+
+    #[test]
+    fn ensure_stable_id_uid_validity() {
+        let uid_validity = NonZeroU32::new(1).unwrap();
+        let id = StableId::imap("INBOX", uid_validity, NonZeroU32::new(1).unwrap());
+
+        assert!(id.ensure_uid_validity(uid_validity).is_ok());
+        assert!(id.ensure_uid_validity(NonZeroU32::new(2).unwrap()).is_err());
+        assert!(id.ensure_kind(StableIdKind::Imap).is_ok());
+        assert!(id.ensure_kind(StableIdKind::Maildir).is_err());
+    }
+}
@@ -7,11 +7,13 @@
 
 use imap_client::imap_next::imap_types::{
     body::{BodyStructure, Disposition},
-    core::Vec1,
-    fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName},
+    core::{AString, Vec1},
+    fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName, Section},
 };
 use once_cell::sync::Lazy;
 
+use tracing::debug;
+
 use crate::{
     envelope::{Envelope, Envelopes},
     flag::Flags,
@@ -19,17 +21,125 @@
 };
 
 /// The IMAP fetch items needed to retrieve everything we need to
-/// build an envelope: UID, flags and envelope (Message-ID, From, To,
-/// Subject, Date).
-pub static FETCH_ENVELOPES: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
-    MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+/// build an envelope: UID, flags, envelope (Message-ID, From, To,
+/// Subject, Date), body structure, size and internal date.
+pub static FETCH_ENVELOPES: Lazy<MacroOrMessageDataItemNames<'static>> =
+    Lazy::new(|| MacroOrMessageDataItemNames::MessageDataItemNames(envelope_item_names()));
+
+fn envelope_item_names() -> Vec<MessageDataItemName<'static>> {
+    vec![
         MessageDataItemName::Uid,
         MessageDataItemName::Flags,
         MessageDataItemName::Envelope,
         MessageDataItemName::BodyStructure,
+        MessageDataItemName::Rfc822Size,
+        MessageDataItemName::InternalDate,
+    ]
+}
+
+/// Build the IMAP fetch items needed to retrieve envelopes, extended
+/// with the given extra headers.
+///
+/// Extra headers are fetched using a peeked `BODY[HEADER.FIELDS
+/// (…)]` item, see [`Envelope::headers`].
+pub fn fetch_envelopes_items(extra_headers: &[String]) -> MacroOrMessageDataItemNames<'static> {
+    let fields: Vec<AString<'static>> = extra_headers
+        .iter()
+        .filter_map(|name| match AString::try_from(name.clone()) {
+            Ok(name) => Some(name),
+            Err(err) => {
+                debug!(?name, ?err, "skipping invalid extra header name");
+                None
+            }
+        })
+        .collect();
+
+    let Ok(fields) = Vec1::try_from(fields) else {
+        return FETCH_ENVELOPES.clone();
+    };
+
+    let mut items = envelope_item_names();
+    items.push(MessageDataItemName::BodyExt {
+        section: Some(Section::HeaderFields(None, fields)),
+        partial: None,
+        peek: true,
+    });
+
+    MacroOrMessageDataItemNames::MessageDataItemNames(items)
+}
+
+/// The MIME headers needed to decode envelope previews.
+const PREVIEW_HEADERS: [&str; 3] = ["MIME-Version", "Content-Type", "Content-Transfer-Encoding"];
+
+/// The maximum number of octets of the message text fetched to build
+/// envelope previews.
+///
+/// Greater than [`PREVIEW_MAX_LEN`](crate::envelope::PREVIEW_MAX_LEN)
+/// since the text may contain MIME boundaries and part headers, and
+/// may be transfer-encoded.
+const PREVIEW_TEXT_LEN: u32 = 2048;
+
+/// The IMAP fetch items needed to build envelope previews: UID, the
+/// MIME headers and the beginning of the message text, without
+/// setting the `\Seen` flag.
+pub static FETCH_PREVIEWS: Lazy<MacroOrMessageDataItemNames<'static>> = Lazy::new(|| {
+    let headers = PREVIEW_HEADERS.map(|name| AString::try_from(name).unwrap());
+
+    MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+        MessageDataItemName::Uid,
+        MessageDataItemName::BodyExt {
+            section: Some(Section::HeaderFields(
+                None,
+                Vec1::try_from(headers.to_vec()).unwrap(),
+            )),
+            partial: None,
+            peek: true,
+        },
+        MessageDataItemName::BodyExt {
+            section: Some(Section::Text(None)),
+            partial: Some((0, NonZeroU32::new(PREVIEW_TEXT_LEN).unwrap())),
+            peek: true,
+        },
     ])
 });
 
+/// Build a preview from IMAP fetch items containing the MIME headers
+/// and the beginning of the message text, see [`FETCH_PREVIEWS`].
+///
+/// The truncated message is parsed, so that the preview is taken
+/// from the first text part, decoded from its transfer encoding and
+/// its charset.
+pub fn preview_from_imap_data_items(items: &[MessageDataItem]) -> Option<String> {
+    let mut headers = None;
+    let mut text = None;
+
+    for item in items {
+        match item {
+            MessageDataItem::BodyExt {
+                section: Some(Section::HeaderFields(..)),
+                data,
+                ..
+            } => headers = data.0.as_ref(),
+            MessageDataItem::BodyExt {
+                section: Some(Section::Text(..)),
+                data,
+                ..
+            } => text = data.0.as_ref(),
+            _ => (),
+        }
+    }
+
+    let mut msg = headers
+        .map(|headers| headers.as_ref().trim_ascii_end().to_vec())
+        .unwrap_or_default();
+    msg.extend(b"\r\n\r\n");
+    msg.extend(text?.as_ref());
+
+    let mut envelope = Envelope::default();
+    envelope.set_preview_from_msg(&Message::from(msg));
+    envelope.preview
+}
+
 impl Envelopes {
     pub fn from_imap_data_items(fetches: HashMap<NonZeroU32, Vec1<MessageDataItem>>) -> Self {
         fetches
@@ -54,6 +164,9 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
         let mut flags = Flags::default();
         let mut msg = Vec::default();
         let mut has_attachment = false;
+        let mut headers = None;
+        let mut size = None;
+        let mut internal_date = None;
 
         for item in items {
             match item {
@@ -149,13 +262,39 @@ pub fn from_imap_data_items(items: &[MessageDataItem]) -> Self {
                 MessageDataItem::BodyStructure(body) => {
                     has_attachment = has_at_least_one_attachment([body]);
                 }
+                MessageDataItem::Rfc822Size(n) => {
+                    size = Some(*n as usize);
+                }
+                MessageDataItem::InternalDate(date) => {
+                    internal_date = Some(*date.as_ref());
+                }
+                MessageDataItem::BodyExt {
+                    section: Some(Section::HeaderFields(..)),
+                    data,
+                    ..
+                } => {
+                    headers = data.0.as_ref().map(|data| data.as_ref().to_vec());
+                }
                 _ => (),
             }
         }
 
-        let msg = Message::from(msg);
-        let mut env = Envelope::from_msg(id, flags, msg);
+        let mut env = Envelope::from_raw_headers(id, flags, &msg);
         env.has_attachment = has_attachment;
+        env.size = size;
+        // the internal date is the best approximation of the save
+        // date, see [`Envelope::saved_at`]
+        env.saved_at = internal_date;
+
+        if let Some(headers) = headers {
+            let msg = Message::from(headers);
+            if let Ok(msg) = msg.parsed() {
+                for (name, value) in msg.headers_raw() {
+                    env.insert_header(name, value);
+                }
+            }
+        }
+
         env
     }
 }
@@ -205,3 +344,42 @@ fn is_attachment(disp: Option<&Disposition>) -> bool {
 
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::imap_types::{
+        core::{AString, NString, Vec1},
+        fetch::{MessageDataItem, Section},
+    };
+
+    use super::preview_from_imap_data_items;
+
+    #[test]
+    fn decode_preview() {
+        let headers = concat!(
+            "Content-Type: text/plain; charset=iso-8859-1\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+        );
+        // "Café crème" encoded in ISO-8859-1, then in base64
+        let text = "Q2Fm6SBjcuhtZQ==\r\n";
+        let items = [
+            MessageDataItem::BodyExt {
+                section: Some(Section::HeaderFields(
+                    None,
+                    Vec1::from(AString::try_from("Content-Type").unwrap()),
+                )),
+                origin: None,
+                data: NString::try_from(headers).unwrap(),
+            },
+            MessageDataItem::BodyExt {
+                section: Some(Section::Text(None)),
+                origin: Some(0),
+                data: NString::try_from(text).unwrap(),
+            },
+        ];
+
+        let preview = preview_from_imap_data_items(&items);
+        assert_eq!(preview.as_deref(), Some("Café crème"));
+    }
+}
@@ -12,7 +12,7 @@
 use tracing::{debug, info, instrument, trace};
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
-use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use super::{Envelopes, ListEnvelopes, ListEnvelopesFilters, ListEnvelopesOptions};
 use crate::{
     email::error::Error,
     envelope::Envelope,
@@ -58,7 +58,7 @@ async fn list_envelopes(
         info!("listing IMAP envelopes from mailbox {folder}");
 
         let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
 
         let folder = config.get_folder_alias(folder);
         let folder_encoded = encode_utf7(folder.clone());
@@ -72,10 +72,10 @@ async fn list_envelopes(
             return Ok(Envelopes::default());
         }
 
-        let envelopes = if let Some(query) = opts.query.as_ref() {
+        let envelopes = if opts.query.is_some() || !opts.filters.is_empty() {
             let sort_supported = client.ext_sort_supported();
-            let sort_criteria = query.to_imap_sort_criteria();
-            let search_criteria = query.to_imap_search_criteria();
+            let sort_criteria = opts.to_imap_sort_criteria();
+            let search_criteria = opts.to_imap_search_criteria();
 
             let uids = if sort_supported {
                 client
@@ -102,20 +102,27 @@ async fn list_envelopes(
                 &uids
             };
 
-            let uids_chunks = uids.chunks(MAX_SEQUENCE_SIZE as usize);
+            // empty chunks cannot be turned into sequence sets, they
+            // are skipped since there is nothing to fetch
+            let uids_chunks: Vec<SequenceSet> = uids
+                .chunks(MAX_SEQUENCE_SIZE as usize)
+                .filter_map(|uids| SequenceSet::try_from(uids.to_vec()).ok())
+                .collect();
             let uids_chunks_len = uids_chunks.len();
 
             debug!(?uids, "fetching envelopes using {uids_chunks_len} chunks");
 
-            let mut fetches = FuturesUnordered::from_iter(uids_chunks.map(|uids| {
+            let mut fetches = FuturesUnordered::from_iter(uids_chunks.into_iter().map(|uids| {
                 let ctx = self.ctx.clone();
                 let mbox = folder_encoded.clone();
-                let uids = SequenceSet::try_from(uids.to_vec()).unwrap();
+                let extra_headers = opts.extra_headers.clone();
 
                 tokio::spawn(async move {
-                    let mut client = ctx.client().await;
+                    let mut client = ctx.lock_client().await;
                     client.select_mailbox(mbox).await?;
-                    client.fetch_envelopes(uids).await
+                    client
+                        .fetch_envelopes_with_headers(uids, &extra_headers)
+                        .await
                 })
             }))
             .enumerate()
@@ -162,11 +169,78 @@ async fn list_envelopes(
             envelopes
         } else {
             let seq = build_sequence(opts.page, opts.page_size, folder_size)?;
-            let mut envelopes = client.fetch_envelopes_by_sequence(seq.into()).await?;
+            let mut envelopes = client
+                .fetch_envelopes_by_sequence_with_headers(seq.into(), &opts.extra_headers)
+                .await?;
             envelopes.sort_by(|a, b| b.date.cmp(&a.date));
             envelopes
         };
 
+        // without SAVEDATE, the internal date fetched along with
+        // envelopes is kept, see [`Envelope::saved_at`]
+        let saved_dates_uids = opts.with_saved_dates.then(|| {
+            let uids: Vec<_> = envelopes
+                .iter()
+                .filter_map(|envelope| envelope.id.parse::<NonZeroU32>().ok())
+                .collect();
+            SequenceSet::try_from(uids).ok()
+        });
+
+        let envelopes = if let Some(Some(uids)) = saved_dates_uids {
+            let mut client = self.ctx.client().await?;
+
+            if client.ext_savedate_supported() {
+                client.select_mailbox(folder_encoded.clone()).await?;
+                let save_dates = client.fetch_save_dates(uids).await?;
+
+                envelopes
+                    .into_iter()
+                    .map(|mut envelope| {
+                        let uid = envelope.id.parse().ok();
+                        if let Some(date) = uid.and_then(|uid| save_dates.get(&uid)) {
+                            envelope.saved_at = Some(*date);
+                        }
+                        envelope
+                    })
+                    .collect()
+            } else {
+                debug!("SAVEDATE not supported, keeping internal dates");
+                envelopes
+            }
+        } else {
+            envelopes
+        };
+
+        // the sequence set cannot be built from an empty list of
+        // UIDs, in which case there is no preview to fetch
+        let previews_uids = opts.with_previews.then(|| {
+            let uids: Vec<_> = envelopes
+                .iter()
+                .filter_map(|envelope| envelope.id.parse::<NonZeroU32>().ok())
+                .collect();
+            SequenceSet::try_from(uids).ok()
+        });
+
+        let envelopes = if let Some(Some(uids)) = previews_uids {
+            let mut client = self.ctx.client().await?;
+            client.select_mailbox(folder_encoded).await?;
+            let mut previews = client.fetch_previews(uids).await?;
+
+            envelopes
+                .into_iter()
+                .map(|mut envelope| {
+                    envelope.preview = envelope
+                        .id
+                        .parse()
+                        .ok()
+                        .and_then(|uid| previews.remove(&uid));
+                    envelope
+                })
+                .collect()
+        } else {
+            envelopes
+        };
+
         debug!("found {} imap envelopes", envelopes.len());
         trace!("{envelopes:#?}");
 
@@ -174,6 +248,61 @@ async fn list_envelopes(
     }
 }
 
+impl ListEnvelopesOptions {
+    /// Build the IMAP search criteria from both the query filter and
+    /// the common filters.
+    pub fn to_imap_search_criteria(&self) -> Vec1<SearchKey<'static>> {
+        let criteria: Vec<_> = self
+            .query
+            .as_ref()
+            .and_then(|query| query.filter.as_ref())
+            .map(|filter| filter.to_imap_search_criterion())
+            .into_iter()
+            .chain(self.filters.to_imap_search_keys())
+            .collect();
+
+        Vec1::try_from(criteria).unwrap_or_else(|_| Vec1::from(SearchKey::All))
+    }
+
+    /// Build the IMAP sort criteria from the query sorters.
+    pub fn to_imap_sort_criteria(&self) -> Vec1<SortCriterion> {
+        match self.query.as_ref() {
+            Some(query) => query.to_imap_sort_criteria(),
+            None => Vec1::from(SortCriterion {
+                reverse: true,
+                key: SortKey::Date,
+            }),
+        }
+    }
+}
+
+impl ListEnvelopesFilters {
+    /// Map the filters to IMAP search keys.
+    ///
+    /// IMAP has no search key dedicated to attachments, so messages
+    /// with a `multipart/mixed` content type are searched instead.
+    pub fn to_imap_search_keys(&self) -> Vec<SearchKey<'static>> {
+        let mut keys = Vec::new();
+
+        if self.unseen_only {
+            keys.push(SearchKey::Unseen);
+        }
+
+        if self.flagged_only {
+            keys.push(SearchKey::Flagged);
+        }
+
+        if self.has_attachment_only {
+            keys.push(SearchKey::Header(
+                "Content-Type".try_into().unwrap(),
+                "multipart/mixed".try_into().unwrap(),
+            ));
+        }
+
+        keys
+    }
+}
+
 impl SearchEmailsQuery {
     pub fn to_imap_search_criteria(&self) -> Vec1<SearchKey<'static>> {
         self.filter
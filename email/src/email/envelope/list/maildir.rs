@@ -1,23 +1,20 @@
 use std::{fs, path::Path};
 
 use async_trait::async_trait;
-use mail_parser::MessageParser;
+use maildirs::{Flag, MaildirEntry};
 use tracing::{debug, info, trace, warn};
 
-use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use super::{Envelopes, ListEnvelopes, ListEnvelopesFilters, ListEnvelopesOptions};
 use crate::{
+    date::Clock,
     email::error::Error,
     envelope::Envelope,
-    maildir::MaildirContextSync,
+    maildir::{AsyncMaildir, MaildirContextSync},
+    message::Message,
     search_query::{filter::SearchEmailsFilterQuery, SearchEmailsQuery},
     AnyResult,
 };
 
-#[cfg(test)]
-static USER_TZ: &chrono::Utc = &chrono::Utc;
-#[cfg(not(test))]
-static USER_TZ: &chrono::Local = &chrono::Local;
-
 #[derive(Clone)]
 pub struct ListMaildirEnvelopes {
     ctx: MaildirContextSync,
@@ -46,22 +43,57 @@ async fn list_envelopes(
     ) -> AnyResult<Envelopes> {
         info!("listing maildir envelopes from folder {folder}");
 
+        // save dates are taken from the status change time of files,
+        // which is only tracked by Unix-like systems
+        if opts.with_saved_dates && cfg!(not(unix)) {
+            let err = Error::RetentionUnsupportedError(folder.to_owned(), "maildir");
+            return Err(err.into());
+        }
+
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
-        let entries = mdir.read().map_err(Error::ListMaildirEntriesError)?;
-        let mut envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref());
-        debug!("found {} maildir envelopes", envelopes.len());
-        trace!("{envelopes:#?}");
+        let entries: Vec<MaildirEntry> = AsyncMaildir::from(mdir.clone())
+            .read()
+            .await
+            .map_err(Error::ListMaildirEntriesError)?
+            .into_iter()
+            .filter(|entry| opts.filters.matches_mdir_entry(entry))
+            .collect();
 
         let page_begin = opts.page * opts.page_size;
         debug!("page begin: {}", page_begin);
+        let out_of_bounds =
+            || Error::GetEnvelopesOutOfBoundsMaildirError(folder.to_owned(), page_begin + 1);
+
+        // without filter requiring headers, envelopes can be counted
+        // from the entry file names only: out of bounds pages are
+        // rejected before reading any message
+        let needs_headers = opts.filters.has_attachment_only
+            || opts.query.as_ref().is_some_and(|q| q.filter.is_some());
+
+        if !needs_headers {
+            let count = Envelopes::from_mdir_entries_without_headers(entries.iter().cloned()).len();
+            debug!("counted {count} maildir envelopes");
+
+            if page_begin > count {
+                return Err(out_of_bounds().into());
+            }
+        }
+
+        let mut envelopes = Envelopes::from_mdir_entries(
+            entries.into_iter(),
+            opts.query.as_ref(),
+            &*ctx.account_config.clock,
+        );
+        if opts.filters.has_attachment_only {
+            envelopes.retain(|envelope| envelope.has_attachment);
+        }
+        debug!("found {} maildir envelopes", envelopes.len());
+        trace!("{envelopes:#?}");
+
         if page_begin > envelopes.len() {
-            return Err(Error::GetEnvelopesOutOfBoundsMaildirError(
-                folder.to_owned(),
-                page_begin + 1,
-            )
-            .into());
+            return Err(out_of_bounds().into());
         }
 
         let page_end = envelopes.len().min(if opts.page_size == 0 {
@@ -74,98 +106,132 @@ async fn list_envelopes(
         opts.sort_envelopes(&mut envelopes);
         *envelopes = envelopes[page_begin..page_end].into();
 
+        if opts.with_previews {
+            for envelope in envelopes.iter_mut() {
+                match mdir.get(&envelope.id).and_then(|entry| entry.read()) {
+                    Ok(msg) => envelope.set_preview_from_msg(&Message::from(msg)),
+                    Err(err) => debug!(id = envelope.id, ?err, "cannot read maildir message"),
+                }
+            }
+        }
+
+        if !opts.extra_headers.is_empty() {
+            for envelope in envelopes.iter_mut() {
+                match mdir
+                    .get(&envelope.id)
+                    .and_then(|entry| entry.read_headers())
+                {
+                    Ok(headers) => {
+                        let msg = Message::from(headers);
+                        envelope.set_headers_from_msg(&msg, &opts.extra_headers);
+                    }
+                    Err(err) => debug!(id = envelope.id, ?err, "cannot read maildir headers"),
+                }
+            }
+        }
+
         Ok(envelopes)
     }
 }
 
+impl ListEnvelopesFilters {
+    /// Return `true` if the given Maildir entry matches the
+    /// flag-based filters.
+    ///
+    /// Only the entry file name is checked, the message itself is
+    /// never read. Entries from the `new` directory have no flag at
+    /// all, so their flags are not even parsed.
+    pub fn matches_mdir_entry(&self, entry: &MaildirEntry) -> bool {
+        if !self.unseen_only && !self.flagged_only {
+            return true;
+        }
+
+        let is_new = entry
+            .path()
+            .parent()
+            .map(|dir| dir.ends_with("new"))
+            .unwrap_or_default();
+
+        if is_new {
+            return !self.flagged_only;
+        }
+
+        match entry.flags() {
+            Ok(flags) => {
+                (!self.unseen_only || !flags.contains(&Flag::Seen))
+                    && (!self.flagged_only || flags.contains(&Flag::Flagged))
+            }
+            Err(err) => {
+                debug!(path = ?entry.path(), ?err, "cannot get maildir entry flags");
+                false
+            }
+        }
+    }
+}
+
 impl SearchEmailsQuery {
-    pub fn matches_maildir_search_query(&self, envelope: &Envelope, msg_path: &Path) -> bool {
+    pub fn matches_maildir_search_query(
+        &self,
+        envelope: &Envelope,
+        msg_path: &Path,
+        clock: &dyn Clock,
+    ) -> bool {
         self.filter
             .as_ref()
-            .map(|f| f.matches_maildir_search_query(envelope, msg_path))
+            .map(|f| f.matches_maildir_search_query(envelope, msg_path, clock))
             .unwrap_or(true)
     }
 }
 
-fn contains_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> bool {
-    for window in haystack.windows(needle.len()) {
-        if window.eq_ignore_ascii_case(needle) {
-            return true;
-        }
+impl SearchEmailsFilterQuery {
+    pub fn matches_maildir_search_query(
+        &self,
+        envelope: &Envelope,
+        msg_path: &Path,
+        clock: &dyn Clock,
+    ) -> bool {
+        self.matches_envelope(envelope, clock, &|| match fs::read(msg_path) {
+            Ok(contents) => Some(contents),
+            Err(_err) => {
+                warn!("cannot find message at {msg_path:?}, skipping body filter");
+                trace!("{_err:?}");
+                None
+            }
+        })
     }
-
-    false
 }
 
-impl SearchEmailsFilterQuery {
-    pub fn matches_maildir_search_query(&self, envelope: &Envelope, msg_path: &Path) -> bool {
-        match self {
-            SearchEmailsFilterQuery::And(left, right) => {
-                let left = left.matches_maildir_search_query(envelope, msg_path);
-                let right = right.matches_maildir_search_query(envelope, msg_path);
-                left && right
-            }
-            SearchEmailsFilterQuery::Or(left, right) => {
-                let left = left.matches_maildir_search_query(envelope, msg_path);
-                let right = right.matches_maildir_search_query(envelope, msg_path);
-                left || right
-            }
-            SearchEmailsFilterQuery::Not(filter) => {
-                !filter.matches_maildir_search_query(envelope, msg_path)
-            }
-            SearchEmailsFilterQuery::Date(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() == date
-            }
-            SearchEmailsFilterQuery::BeforeDate(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() < date
-            }
-            SearchEmailsFilterQuery::AfterDate(date) => {
-                &envelope.date.with_timezone(USER_TZ).date_naive() > date
-            }
-            SearchEmailsFilterQuery::From(pattern) => {
-                let pattern = pattern.as_bytes();
-                if let Some(name) = &envelope.from.name {
-                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
-                        return true;
-                    }
-                }
-                contains_ignore_ascii_case(envelope.from.addr.as_bytes(), pattern)
-            }
-            SearchEmailsFilterQuery::To(pattern) => {
-                let pattern = pattern.as_bytes();
-                if let Some(name) = &envelope.to.name {
-                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
-                        return true;
-                    }
-                }
-                contains_ignore_ascii_case(envelope.to.addr.as_bytes(), pattern)
-            }
-            SearchEmailsFilterQuery::Subject(pattern) => {
-                contains_ignore_ascii_case(envelope.subject.as_bytes(), pattern.as_bytes())
-            }
-            SearchEmailsFilterQuery::Body(pattern) => match fs::read(msg_path) {
-                Ok(contents) => {
-                    if let Some(msg) = MessageParser::new().parse(&contents) {
-                        for plain in msg.text_bodies() {
-                            if contains_ignore_ascii_case(plain.contents(), pattern.as_bytes()) {
-                                return true;
-                            }
-                        }
-                        for html in msg.html_bodies() {
-                            if contains_ignore_ascii_case(html.contents(), pattern.as_bytes()) {
-                                return true;
-                            }
-                        }
-                    }
-                    false
-                }
-                Err(_err) => {
-                    warn!("cannot find message at {msg_path:?}, skipping body filter");
-                    trace!("{_err:?}");
-                    true
-                }
-            },
-            SearchEmailsFilterQuery::Flag(flag) => envelope.flags.contains(flag),
-        }
+#[cfg(test)]
+mod tests {
+    use maildirs::MaildirEntry;
+
+    use super::ListEnvelopesFilters;
+
+    #[test]
+    fn matches_mdir_entry() {
+        let new = MaildirEntry::new("/tmp/mdir/new/1");
+        let seen = MaildirEntry::new("/tmp/mdir/cur/2:2,S");
+        let flagged = MaildirEntry::new("/tmp/mdir/cur/3:2,F");
+
+        let filters = ListEnvelopesFilters::default();
+        assert!(filters.matches_mdir_entry(&new));
+        assert!(filters.matches_mdir_entry(&seen));
+        assert!(filters.matches_mdir_entry(&flagged));
+
+        let filters = ListEnvelopesFilters {
+            unseen_only: true,
+            ..Default::default()
+        };
+        assert!(filters.matches_mdir_entry(&new));
+        assert!(!filters.matches_mdir_entry(&seen));
+        assert!(filters.matches_mdir_entry(&flagged));
+
+        let filters = ListEnvelopesFilters {
+            flagged_only: true,
+            ..Default::default()
+        };
+        assert!(!filters.matches_mdir_entry(&new));
+        assert!(!filters.matches_mdir_entry(&seen));
+        assert!(filters.matches_mdir_entry(&flagged));
     }
 }
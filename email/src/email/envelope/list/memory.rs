@@ -0,0 +1,105 @@
+use async_trait::async_trait;
+use tracing::{debug, info, trace};
+
+use super::{Envelopes, ListEnvelopes, ListEnvelopesOptions};
+use crate::{
+    memory::{Error, MemoryContextSync},
+    message::Message,
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct ListMemoryEnvelopes {
+    ctx: MemoryContextSync,
+}
+
+impl ListMemoryEnvelopes {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn ListEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn ListEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListEnvelopes for ListMemoryEnvelopes {
+    async fn list_envelopes(
+        &self,
+        folder: &str,
+        opts: ListEnvelopesOptions,
+    ) -> AnyResult<Envelopes> {
+        info!("listing memory envelopes from folder {folder}");
+
+        let config = &self.ctx.account_config;
+        let clock = &*config.clock;
+        let folder = config.get_folder_alias(folder);
+        let store = self.ctx.lock();
+        let mfolder = store.folder(&folder)?;
+
+        let filter = opts.query.as_ref().and_then(|q| q.filter.as_ref());
+
+        let mut envelopes: Envelopes = mfolder
+            .iter()
+            .filter_map(|(id, msg)| {
+                let envelope = msg.to_envelope(id);
+
+                if !opts.filters.matches(&envelope) {
+                    return None;
+                }
+
+                match filter {
+                    Some(filter) => {
+                        let read_msg = || Some(msg.raw.clone());
+                        filter
+                            .matches_envelope(&envelope, clock, &read_msg)
+                            .then_some(envelope)
+                    }
+                    None => Some(envelope),
+                }
+            })
+            .collect();
+        debug!("found {} memory envelopes", envelopes.len());
+        trace!("{envelopes:#?}");
+
+        let page_begin = opts.page * opts.page_size;
+        debug!("page begin: {}", page_begin);
+        if page_begin > envelopes.len() {
+            return Err(Error::GetEnvelopesOutOfBoundsError(folder, page_begin + 1).into());
+        }
+
+        let page_end = envelopes.len().min(if opts.page_size == 0 {
+            envelopes.len()
+        } else {
+            page_begin + opts.page_size
+        });
+        debug!("page end: {}", page_end);
+
+        opts.sort_envelopes(&mut envelopes);
+        *envelopes = envelopes[page_begin..page_end].into();
+
+        if opts.with_previews {
+            for envelope in envelopes.iter_mut() {
+                if let Some(msg) = mfolder.get(&envelope.id) {
+                    envelope.set_preview_from_msg(&Message::from(msg.raw.as_slice()));
+                }
+            }
+        }
+
+        if !opts.extra_headers.is_empty() {
+            for envelope in envelopes.iter_mut() {
+                if let Some(msg) = mfolder.get(&envelope.id) {
+                    let msg = Message::from(msg.raw.as_slice());
+                    envelope.set_headers_from_msg(&msg, &opts.extra_headers);
+                }
+            }
+        }
+
+        Ok(envelopes)
+    }
+}
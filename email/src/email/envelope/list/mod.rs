@@ -3,6 +3,8 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 
@@ -10,7 +12,7 @@
 
 use async_trait::async_trait;
 
-use super::{Envelope, Envelopes};
+use super::{Envelope, Envelopes, Flag, Flags};
 use crate::{
     email::search_query::SearchEmailsQuery,
     search_query::sort::{SearchEmailsSorter, SearchEmailsSorterKind, SearchEmailsSorterOrder},
@@ -33,6 +35,82 @@ pub struct ListEnvelopesOptions {
     pub page_size: usize,
     pub page: usize,
     pub query: Option<SearchEmailsQuery>,
+
+    /// Raw notmuch query used instead of the translated
+    /// [`SearchEmailsQuery`] filter.
+    ///
+    /// This lets power users take advantage of the full notmuch query
+    /// language (`thread:{...}`, `path:`, date ranges etc). The query
+    /// is still scoped to the given folder, and sorters from
+    /// [`ListEnvelopesOptions::query`] still apply. It is ignored by
+    /// backends other than notmuch.
+    pub notmuch_query: Option<String>,
+
+    /// Populate [`Envelope::preview`] with a short plain text preview
+    /// of the message body.
+    ///
+    /// This requires extra reads (partial body fetch for IMAP, full
+    /// message read for Maildir), so it is disabled by default.
+    pub with_previews: bool,
+
+    /// Populate [`Envelope::saved_at`] with the exact date messages
+    /// entered the folder.
+    ///
+    /// For IMAP, this requires an extra fetch of the SAVEDATE
+    /// (RFC 8514), so it is disabled by default. Backends unable to
+    /// tell when messages entered the folder fail with
+    /// [`Error::RetentionUnsupportedError`](crate::Error::RetentionUnsupportedError).
+    pub with_saved_dates: bool,
+
+    /// Extra headers to fetch along with envelopes (`List-Id`,
+    /// `X-Priority` etc).
+    ///
+    /// Values are exposed in [`Envelope::headers`]. For IMAP, the
+    /// headers are fetched within the same FETCH command as the
+    /// envelopes, so they do not cost any extra round trip.
+    pub extra_headers: Vec<String>,
+
+    /// Common filters, applied on top of the query.
+    ///
+    /// Unlike [`ListEnvelopesOptions::query`], these filters are
+    /// mapped to the cheapest check available in each backend (IMAP
+    /// SEARCH keys, Maildir file names etc).
+    pub filters: ListEnvelopesFilters,
+}
+
+/// Common envelope filters.
+///
+/// See [`ListEnvelopesOptions::filters`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ListEnvelopesFilters {
+    /// Only list envelopes without the [`Flag::Seen`] flag.
+    pub unseen_only: bool,
+
+    /// Only list envelopes with the [`Flag::Flagged`] flag.
+    pub flagged_only: bool,
+
+    /// Only list envelopes having at least one attachment.
+    pub has_attachment_only: bool,
+}
+
+impl ListEnvelopesFilters {
+    /// Return `true` if no filter is enabled.
+    pub fn is_empty(&self) -> bool {
+        !self.unseen_only && !self.flagged_only && !self.has_attachment_only
+    }
+
+    /// Return `true` if the given flags match the flag-based
+    /// filters.
+    pub fn matches_flags(&self, flags: &Flags) -> bool {
+        (!self.unseen_only || !flags.contains(&Flag::Seen))
+            && (!self.flagged_only || flags.contains(&Flag::Flagged))
+    }
+
+    /// Return `true` if the given envelope matches all the filters.
+    pub fn matches(&self, envelope: &Envelope) -> bool {
+        self.matches_flags(&envelope.flags)
+            && (!self.has_attachment_only || envelope.has_attachment)
+    }
 }
 
 impl SearchEmailsSorter {
@@ -39,7 +39,15 @@ async fn list_envelopes(
     ) -> AnyResult<Envelopes> {
         info!("listing notmuch envelopes from folder {folder}");
 
-        let ctx = self.ctx.lock().await;
+        // notmuch does not track when messages entered their folder
+        if opts.with_saved_dates {
+            let err = Error::RetentionUnsupportedError(folder.to_owned(), "notmuch");
+            return Err(err.into());
+        }
+
+        let mut ctx = self.ctx.lock().await;
+        ctx.refresh_index_if_stale()?;
+
         let config = &ctx.account_config;
         let db = ctx.open_db()?;
 
@@ -50,12 +58,34 @@ async fn list_envelopes(
             format!("folder:{folder:?}")
         };
 
-        if let Some(query) = opts.query.as_ref() {
-            let query = query.to_notmuch_search_query();
-            if !query.is_empty() {
-                final_query.push_str(" and ");
-                final_query.push_str(&query);
+        let query = match opts.notmuch_query.as_ref() {
+            Some(query) => {
+                debug!("using raw notmuch query {query:?}");
+                query.trim().to_owned()
             }
+            None => opts
+                .query
+                .as_ref()
+                .map(SearchEmailsQuery::to_notmuch_search_query)
+                .unwrap_or_default(),
+        };
+
+        if !query.is_empty() {
+            final_query.push_str(" and (");
+            final_query.push_str(&query);
+            final_query.push(')');
+        }
+
+        if opts.filters.unseen_only {
+            final_query.push_str(" and tag:unread");
+        }
+
+        if opts.filters.flagged_only {
+            final_query.push_str(" and tag:flagged");
+        }
+
+        if opts.filters.has_attachment_only {
+            final_query.push_str(" and tag:attachment");
         }
 
         let query_builder = db
@@ -66,7 +96,7 @@ async fn list_envelopes(
             Error::SearchMessagesInvalidQueryNotmuch(err, folder.to_owned(), final_query.clone())
         })?;
 
-        let mut envelopes = Envelopes::from_notmuch_msgs(msgs);
+        let mut envelopes = Envelopes::from_notmuch_msgs_with_headers(msgs, &opts.extra_headers);
 
         debug!(
             "found {} notmuch envelopes matching query {final_query}",
@@ -3,10 +3,14 @@
 //! This module contains envelope-related mapping functions from the
 //! [maildirpp] crate types.
 
+use std::path::Path;
+
+use chrono::{DateTime, FixedOffset};
 use maildirs::MaildirEntry;
 use rayon::prelude::*;
 
 use crate::{
+    date::Clock,
     envelope::{Envelope, Envelopes, Flags},
     message::Message,
     search_query::SearchEmailsQuery,
@@ -14,9 +18,13 @@
 };
 
 impl Envelopes {
+    /// Build envelopes from the given Maildir entries, keeping only
+    /// the ones matching the given query. Dates are compared in the
+    /// local timezone of the given clock.
     pub fn from_mdir_entries(
         entries: impl Iterator<Item = MaildirEntry>,
         query: Option<&SearchEmailsQuery>,
+        clock: &dyn Clock,
     ) -> Self {
         Envelopes::from_iter(
             entries
@@ -27,7 +35,7 @@ pub fn from_mdir_entries(
                     let envelope = Envelope::try_from(entry).ok()?;
                     if let Some(query) = query {
                         query
-                            .matches_maildir_search_query(&envelope, msg_path.as_ref())
+                            .matches_maildir_search_query(&envelope, msg_path.as_ref(), clock)
                             .then_some(envelope)
                     } else {
                         Some(envelope)
@@ -36,6 +44,27 @@ pub fn from_mdir_entries(
                 .collect::<Vec<_>>(),
         )
     }
+
+    /// Build envelopes from the given Maildir entries without reading
+    /// any message file.
+    ///
+    /// Only identifiers and flags are set, since they are both taken
+    /// from the entry file name. This is way faster than
+    /// [`Envelopes::from_mdir_entries`] on large folders, and should
+    /// be preferred when headers are not needed (counting, diffing
+    /// etc).
+    pub fn from_mdir_entries_without_headers(entries: impl Iterator<Item = MaildirEntry>) -> Self {
+        Envelopes::from_iter(entries.filter_map(|entry| {
+            let id = entry.id().ok()?.to_owned();
+            let flags = Flags::try_from(entry).ok()?;
+
+            Some(Envelope {
+                id,
+                flags,
+                ..Default::default()
+            })
+        }))
+    }
 }
 
 impl TryFrom<MaildirEntry> for Envelope {
@@ -43,7 +72,10 @@ impl TryFrom<MaildirEntry> for Envelope {
 
     fn try_from(entry: MaildirEntry) -> Result<Self> {
         let id = entry.id()?.to_owned();
-        let msg = Message::from(entry.read()?);
+        let saved_at = saved_at(entry.path());
+        let raw = entry.read()?;
+        let size = raw.len();
+        let msg = Message::from(raw);
 
         let has_attachment = {
             let attachments = msg.attachments();
@@ -57,6 +89,36 @@ fn try_from(entry: MaildirEntry) -> Result<Self> {
         let flags = Flags::try_from(entry)?;
         let mut env = Envelope::from_msg(id, flags, msg);
         env.has_attachment = has_attachment;
+        env.saved_at = saved_at;
+        env.size = Some(size);
         Ok(env)
     }
 }
+
+/// Return the date the message file at the given path entered its
+/// folder.
+///
+/// Moving a message to another folder renames its file, which
+/// updates the status change time (ctime) but not the modification
+/// time. Renaming a file to change its flags also updates the ctime,
+/// so the returned date can be more recent than the actual move, but
+/// never older.
+#[cfg(unix)]
+fn saved_at(path: &Path) -> Option<DateTime<FixedOffset>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = std::fs::metadata(path).ok()?;
+    let nsecs = u32::try_from(metadata.ctime_nsec()).unwrap_or_default();
+    let date = DateTime::from_timestamp(metadata.ctime(), nsecs)?;
+    Some(date.fixed_offset())
+}
+
+/// Return the date the message file at the given path entered its
+/// folder.
+///
+/// Only Unix-like systems expose a status change time updated on
+/// rename, other systems do not track it.
+#[cfg(not(unix))]
+fn saved_at(_path: &Path) -> Option<DateTime<FixedOffset>> {
+    None
+}
@@ -5,6 +5,7 @@
 //! [message](crate::Message).
 
 pub mod address;
+pub mod category;
 pub mod config;
 pub mod flag;
 pub mod get;
@@ -26,21 +27,23 @@
 #[cfg(feature = "thread")]
 use std::collections::HashMap;
 use std::{
+    collections::BTreeMap,
     hash::{DefaultHasher, Hash, Hasher},
     ops::{Deref, DerefMut},
     vec,
 };
 
-use chrono::{DateTime, FixedOffset, Local};
+use chrono::{DateTime, FixedOffset};
+use mail_parser::{parsers::MessageStream, HeaderName, HeaderValue};
 #[cfg(feature = "thread")]
 use petgraph::graphmap::DiGraphMap;
 use tracing::{debug, trace};
 
 #[doc(inline)]
 pub use self::{
-    address::Address,
+    address::{Address, AddressDisplayFormat},
     flag::{Flag, Flags},
-    id::{Id, MultipleIds, SingleId},
+    id::{Id, IdMapping, MultipleIds, SingleId, StableId, StableIdKind},
 };
 use crate::{
     account::config::AccountConfig, date::from_mail_parser_to_chrono_datetime, message::Message,
@@ -73,11 +76,48 @@ pub struct Envelope {
     /// The Date header from the email message.
     pub date: DateTime<FixedOffset>,
 
+    /// The date the message entered its current folder.
+    ///
+    /// Only populated by backends able to track it. For Maildir, it
+    /// is taken from the status change time of the message file. For
+    /// IMAP, it is taken from the INTERNALDATE, which is kept when a
+    /// message is copied or moved, unless envelopes are listed with
+    /// [`ListEnvelopesOptions::with_saved_dates`](list::ListEnvelopesOptions::with_saved_dates)
+    /// and the server supports the SAVEDATE extension (RFC 8514).
+    pub saved_at: Option<DateTime<FixedOffset>>,
+
+    /// The size of the raw message, in bytes.
+    ///
+    /// Only populated by backends able to tell it without
+    /// downloading the message: IMAP (`RFC822.SIZE`) and Maildir.
+    pub size: Option<usize>,
+
     /// True if the current envelope contains at least one attachment.
     ///
     /// An attachment is defined here as a MIME part that is not a
     /// `text/*`.
     pub has_attachment: bool,
+
+    /// A short plain text preview of the message body.
+    ///
+    /// Only populated when listing envelopes with
+    /// [`ListEnvelopesOptions::with_previews`](list::ListEnvelopesOptions::with_previews).
+    pub preview: Option<String>,
+
+    /// Extra headers from the email message.
+    ///
+    /// Only populated with headers requested when listing envelopes
+    /// with
+    /// [`ListEnvelopesOptions::extra_headers`](list::ListEnvelopesOptions::extra_headers).
+    /// Keys are lowercased header names, values are unfolded raw
+    /// header values.
+    pub headers: BTreeMap<String, String>,
+
+    /// The category of the message.
+    ///
+    /// Only populated by the
+    /// [`EnvelopeClassifier`](category::EnvelopeClassifier).
+    pub category: Option<category::EnvelopeCategory>,
 }
 
 impl Envelope {
@@ -91,62 +131,16 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
         };
 
         if let Ok(msg) = msg.parsed() {
-            match msg.from() {
-                Some(mail_parser::Address::List(addrs))
-                    if !addrs.is_empty() && addrs[0].address.is_some() =>
-                {
-                    let name = addrs[0].name.as_ref().map(|name| name.to_string());
-                    let email = addrs[0]
-                        .address
-                        .as_ref()
-                        .map(|name| name.to_string())
-                        .unwrap();
-                    envelope.from = Address::new(name, email);
-                }
-                Some(mail_parser::Address::Group(groups))
-                    if !groups.is_empty()
-                        && !groups[0].addresses.is_empty()
-                        && groups[0].addresses[0].address.is_some() =>
-                {
-                    let name = groups[0].name.as_ref().map(|name| name.to_string());
-                    let email = groups[0].addresses[0]
-                        .address
-                        .as_ref()
-                        .map(|name| name.to_string())
-                        .unwrap();
-                    envelope.from = Address::new(name, email)
-                }
-                _ => {
+            match first_address(msg, HeaderName::From) {
+                Some(addr) => envelope.from = addr,
+                None => {
                     trace!("cannot extract envelope sender from message header, skipping it");
                 }
             };
 
-            match msg.to() {
-                Some(mail_parser::Address::List(addrs))
-                    if !addrs.is_empty() && addrs[0].address.is_some() =>
-                {
-                    let name = addrs[0].name.as_ref().map(|name| name.to_string());
-                    let email = addrs[0]
-                        .address
-                        .as_ref()
-                        .map(|name| name.to_string())
-                        .unwrap();
-                    envelope.to = Address::new(name, email);
-                }
-                Some(mail_parser::Address::Group(groups))
-                    if !groups.is_empty()
-                        && !groups[0].addresses.is_empty()
-                        && groups[0].addresses[0].address.is_some() =>
-                {
-                    let name = groups[0].name.as_ref().map(|name| name.to_string());
-                    let email = groups[0].addresses[0]
-                        .address
-                        .as_ref()
-                        .map(|name| name.to_string())
-                        .unwrap();
-                    envelope.to = Address::new(name, email)
-                }
-                _ => {
+            match first_address(msg, HeaderName::To) {
+                Some(addr) => envelope.to = addr,
+                None => {
                     trace!("cannot extract envelope recipient from message header, skipping it");
                 }
             };
@@ -169,7 +163,7 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
                 .unwrap_or_else(|| {
                     let mut hasher = DefaultHasher::new();
                     envelope.date.to_string().hash(&mut hasher);
-                    format!("<{:x}@generated>", hasher.finish())
+                    format!("<{:x}{GENERATED_MESSAGE_ID_SUFFIX}", hasher.finish())
                 });
 
             envelope.in_reply_to = msg.in_reply_to().as_text().map(|mid| format!("<{mid}>"));
@@ -180,6 +174,35 @@ pub fn from_msg(id: impl ToString, flags: Flags, msg: Message) -> Envelope {
         envelope
     }
 
+    /// Build an envelope from an identifier, some
+    /// [flags](self::Flags) and raw RFC 822 headers.
+    ///
+    /// Headers are parsed and normalized the same way backends do,
+    /// which makes this constructor useful for external integrations
+    /// (indexers, tests) that need envelopes consistent with the ones
+    /// returned by backends.
+    pub fn from_raw_headers(id: impl ToString, flags: Flags, headers: &[u8]) -> Envelope {
+        let mut msg = headers.to_vec();
+
+        if !msg.ends_with(b"\n\n") && !msg.ends_with(b"\r\n\r\n") {
+            if !msg.ends_with(b"\n") {
+                msg.push(b'\n');
+            }
+            msg.push(b'\n');
+        }
+
+        Envelope::from_msg(id, flags, Message::from(msg))
+    }
+
+    /// Return `true` if the Message-ID was generated from the date,
+    /// because the message has no Message-ID header.
+    ///
+    /// Such Message-IDs are not unique: messages without Message-ID
+    /// sharing the same date share the same generated Message-ID.
+    pub fn has_generated_message_id(&self) -> bool {
+        self.message_id.ends_with(GENERATED_MESSAGE_ID_SUFFIX)
+    }
+
     pub fn set_some_from(&mut self, addr: Option<Address>) {
         if let Some(addr) = addr {
             self.from = addr;
@@ -207,13 +230,53 @@ pub fn set_date(&mut self, date: &mail_parser::DateTime) {
         });
     }
 
+    /// Get the value of the given extra header, see
+    /// [`Envelope::headers`].
+    pub fn get_header(&self, name: impl AsRef<str>) -> Option<&str> {
+        self.headers
+            .get(&name.as_ref().to_lowercase())
+            .map(String::as_str)
+    }
+
+    /// Set the envelope extra headers from the given message, keeping
+    /// only the ones matching the given names.
+    pub fn set_headers_from_msg(&mut self, msg: &Message, names: &[String]) {
+        let Ok(msg) = msg.parsed() else {
+            trace!("cannot parse message header, skipping extra headers");
+            return;
+        };
+
+        for (name, value) in msg.headers_raw() {
+            if names.iter().any(|n| n.eq_ignore_ascii_case(name)) {
+                self.insert_header(name, value);
+            }
+        }
+    }
+
+    /// Insert the given raw header into the envelope extra headers.
+    pub(crate) fn insert_header(&mut self, name: &str, value: &str) {
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.headers.insert(name.to_lowercase(), value);
+    }
+
+    /// Set the envelope preview from the first text part of the given
+    /// message.
+    pub fn set_preview_from_msg(&mut self, msg: &Message) {
+        self.preview = msg
+            .parsed()
+            .ok()
+            .and_then(|msg| msg.body_text(0))
+            .map(|text| build_preview(&text))
+            .filter(|preview| !preview.is_empty());
+    }
+
     /// Format the envelope date according to the datetime format and
     /// timezone from the [account configuration](crate::AccountConfig).
     pub fn format_date(&self, config: &AccountConfig) -> String {
         let fmt = config.get_envelope_list_datetime_fmt();
 
         let date = if config.has_envelope_list_datetime_local_tz() {
-            self.date.with_timezone(&Local).format(&fmt)
+            config.clock.to_local(&self.date).format(&fmt)
         } else {
             self.date.format(&fmt)
         };
@@ -221,6 +284,19 @@ pub fn format_date(&self, config: &AccountConfig) -> String {
         date.to_string()
     }
 
+    /// Format the envelope sender according to the address display
+    /// format from the [account configuration](crate::AccountConfig).
+    pub fn format_from(&self, config: &AccountConfig) -> String {
+        self.from.display(config.get_envelope_address_format())
+    }
+
+    /// Format the envelope recipient according to the address
+    /// display format from the [account
+    /// configuration](crate::AccountConfig).
+    pub fn format_to(&self, config: &AccountConfig) -> String {
+        self.to.display(config.get_envelope_address_format())
+    }
+
     /// Build a message from the current envelope.
     ///
     /// The message is just composed of two headers and contains no
@@ -239,15 +315,106 @@ pub fn as_threaded(&self) -> ThreadedEnvelope {
             id: self.id.as_str(),
             message_id: self.message_id.as_str(),
             subject: self.subject.as_str(),
-            from: match self.from.name.as_ref() {
-                Some(name) => name.as_str(),
-                None => self.from.addr.as_str(),
-            },
+            from: self.from.display_name(),
+            from_name: self.from.name.as_deref(),
+            from_addr: self.from.addr.as_str(),
             date: self.date,
         }
     }
 }
 
+/// Extract the first address of the given address header.
+///
+/// Headers which are not valid UTF-8 are decoded again using
+/// [`address::decode_raw_header`], then the display name is
+/// normalized using [`address::normalize_display_name`]. Display
+/// names containing invalid encoded words are decoded again from the
+/// raw header.
+fn first_address(msg: &mail_parser::Message, name: HeaderName) -> Option<Address> {
+    let header = msg.headers().iter().find(|header| header.name == name)?;
+    let raw = msg
+        .raw_message()
+        .get(header.offset_start()..header.offset_end())
+        .unwrap_or_default();
+
+    let decoded;
+    let parsed;
+    let value = if std::str::from_utf8(raw).is_ok() {
+        header.value()
+    } else {
+        decoded = address::decode_raw_header(raw);
+        parsed = MessageStream::new(decoded.as_bytes()).parse_address();
+        &parsed
+    };
+
+    let (name, email) = match value {
+        HeaderValue::Address(mail_parser::Address::List(addrs)) => {
+            let addr = addrs.first()?;
+            (addr.name.as_deref(), addr.address.as_deref()?)
+        }
+        HeaderValue::Address(mail_parser::Address::Group(groups)) => {
+            let group = groups.first()?;
+            let addr = group.addresses.first()?;
+            (group.name.as_deref(), addr.address.as_deref()?)
+        }
+        _ => return None,
+    };
+
+    let name = match name {
+        // the parser replaces invalid sequences of decoded words by
+        // U+FFFD, so the name is decoded again from the raw header
+        Some(name) if name.contains(char::REPLACEMENT_CHARACTER) => {
+            let raw = address::decode_raw_header(raw);
+            match raw.split_once('<') {
+                Some((name, _)) => address::normalize_display_name(name),
+                None => address::normalize_display_name(name),
+            }
+        }
+        name => name.and_then(address::normalize_display_name),
+    };
+
+    Some(Address::new(name, email))
+}
+
+/// The suffix of Message-IDs generated for messages without
+/// Message-ID header, see [`Envelope::has_generated_message_id`].
+const GENERATED_MESSAGE_ID_SUFFIX: &str = "@generated>";
+
+/// The maximum length of an envelope preview, in characters.
+pub const PREVIEW_MAX_LEN: usize = 200;
+
+/// Build an envelope preview from the given text.
+///
+/// HTML tags are stripped, quoted-printable soft line breaks are
+/// removed and whitespaces are collapsed. The result is truncated to
+/// [`PREVIEW_MAX_LEN`] characters.
+pub fn build_preview(text: &str) -> String {
+    let text = text.replace("=\r\n", "").replace("=\n", "");
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                stripped.push(' ');
+            }
+            _ if in_tag => (),
+            c => stripped.push(c),
+        }
+    }
+
+    stripped
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(PREVIEW_MAX_LEN)
+        .collect()
+}
+
 // NOTE: this is useful for the sync, not sure how relevant it is for
 // the rest.
 impl PartialEq for Envelope {
@@ -311,7 +478,12 @@ fn from_iter<T: IntoIterator<Item = Envelope>>(iter: T) -> Self {
 pub struct ThreadedEnvelope<'a> {
     pub id: &'a str,
     pub message_id: &'a str,
+    /// The display name of the sender, see [`Address::display_name`].
     pub from: &'a str,
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub from_name: Option<&'a str>,
+    #[cfg_attr(feature = "derive", serde(skip))]
+    pub from_addr: &'a str,
     pub subject: &'a str,
     pub date: DateTime<FixedOffset>,
 }
@@ -324,13 +496,19 @@ pub fn format_date(&self, config: &AccountConfig) -> String {
         let fmt = config.get_envelope_list_datetime_fmt();
 
         let date = if config.has_envelope_list_datetime_local_tz() {
-            self.date.with_timezone(&Local).format(&fmt)
+            config.clock.to_local(&self.date).format(&fmt)
         } else {
             self.date.format(&fmt)
         };
 
         date.to_string()
     }
+
+    /// Format the envelope sender according to the address display
+    /// format from the [account configuration](crate::AccountConfig).
+    pub fn format_from(&self, config: &AccountConfig) -> String {
+        Address::new(self.from_name, self.from_addr).display(config.get_envelope_address_format())
+    }
 }
 
 #[cfg(feature = "thread")]
@@ -404,3 +582,74 @@ fn deserialize<D>(_deserializer: D) -> Result<Self, D::Error>
         todo!()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::{Address, Envelope, Flag, Flags, Message};
+
+    #[test]
+    fn from_raw_headers() {
+        let headers = concat_line!(
+            "Message-ID: <id@localhost>",
+            "In-Reply-To: <parent@localhost>",
+            "From: \"Alice\" <alice@localhost>",
+            "To: bob@localhost",
+            "Subject: subject",
+            "Date: Thu, 01 Jan 2015 00:00:00 +0100",
+        );
+
+        let flags = Flags::from_iter([Flag::Seen]);
+        let envelope = Envelope::from_raw_headers(1, flags.clone(), headers.as_bytes());
+
+        assert_eq!(envelope.id, "1");
+        assert_eq!(envelope.flags, flags);
+        assert_eq!(envelope.message_id, "<id@localhost>");
+        assert_eq!(envelope.in_reply_to.as_deref(), Some("<parent@localhost>"));
+        assert_eq!(
+            envelope.from,
+            Address::new(Some("Alice"), "alice@localhost")
+        );
+        assert_eq!(envelope.from.name.as_deref(), Some("Alice"));
+        assert_eq!(envelope.to, Address::new_nameless("bob@localhost"));
+        assert_eq!(envelope.subject, "subject");
+        assert_eq!(envelope.date.to_rfc3339(), "2015-01-01T00:00:00+01:00");
+    }
+
+    #[test]
+    fn from_latin1_raw_headers() {
+        let headers =
+            b"From: Ren\xe9 Dupont <rene@localhost>\nTo: =?utf-8?Q?Bob_=E9?= <bob@localhost>\n";
+        let envelope = Envelope::from_raw_headers(1, Flags::default(), headers);
+
+        assert_eq!(envelope.from.name.as_deref(), Some("René Dupont"));
+        assert_eq!(envelope.from.addr, "rene@localhost");
+        assert_eq!(envelope.to.name.as_deref(), Some("Bob é"));
+    }
+
+    #[test]
+    fn set_headers_from_msg() {
+        let msg = concat_line!(
+            "Message-ID: <id@localhost>",
+            "List-ID: Pimalaya",
+            "  <pimalaya.lists.sr.ht>",
+            "X-Priority: 1 (Highest)",
+            "Subject: subject",
+            "",
+            "body",
+        );
+
+        let names = vec![String::from("List-Id"), String::from("x-priority")];
+        let mut envelope = Envelope::default();
+        envelope.set_headers_from_msg(&Message::from(msg.as_bytes()), &names);
+
+        assert_eq!(envelope.headers.len(), 2);
+        assert_eq!(
+            envelope.get_header("list-id"),
+            Some("Pimalaya <pimalaya.lists.sr.ht>")
+        );
+        assert_eq!(envelope.get_header("X-Priority"), Some("1 (Highest)"));
+        assert_eq!(envelope.get_header("Subject"), None);
+    }
+}
@@ -15,6 +15,26 @@ impl Envelopes {
     pub fn from_notmuch_msgs(msgs: notmuch::Messages) -> Self {
         msgs.map(Envelope::from_notmuch_msg).collect()
     }
+
+    /// Same as [`Envelopes::from_notmuch_msgs`], but also set the
+    /// given extra headers, see [`Envelope::headers`].
+    pub fn from_notmuch_msgs_with_headers(msgs: notmuch::Messages, names: &[String]) -> Self {
+        msgs.map(|msg| {
+            let headers: Vec<_> = names
+                .iter()
+                .filter_map(|name| Some((name.clone(), msg.header(name).ok()??.into_owned())))
+                .collect();
+
+            let mut envelope = Envelope::from_notmuch_msg(msg);
+
+            for (name, value) in headers {
+                envelope.insert_header(&name, &value);
+            }
+
+            envelope
+        })
+        .collect()
+    }
 }
 
 impl Envelope {
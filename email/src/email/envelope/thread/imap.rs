@@ -46,7 +46,7 @@ async fn thread_envelopes(
     ) -> AnyResult<ThreadedEnvelopes> {
         debug!(?opts, "thread options");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -99,6 +99,8 @@ async fn thread_envelopes(
                             message_id: "0",
                             subject: "",
                             from: "",
+                            from_name: None,
+                            from_addr: "",
                             date: Default::default(),
                         };
                         final_graph.add_edge(ea, eb.as_threaded(), *w);
@@ -119,7 +121,7 @@ async fn thread_envelope(
         id: SingleId,
         opts: ListEnvelopesOptions,
     ) -> AnyResult<ThreadedEnvelopes> {
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -173,6 +175,8 @@ async fn thread_envelope(
                             message_id: "0",
                             subject: "",
                             from: "",
+                            from_name: None,
+                            from_addr: "",
                             date: Default::default(),
                         };
                         final_graph.add_edge(ea, eb.as_threaded(), *w);
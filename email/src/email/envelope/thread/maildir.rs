@@ -44,10 +44,11 @@ async fn thread_envelopes(
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
         let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref())
-            .into_iter()
-            .map(|e| (e.id.clone(), e))
-            .collect();
+        let envelopes =
+            Envelopes::from_mdir_entries(entries, opts.query.as_ref(), &*ctx.account_config.clock)
+                .into_iter()
+                .map(|e| (e.id.clone(), e))
+                .collect();
 
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let msg_id_mapping: HashMap<_, _> = envelopes
@@ -98,6 +99,8 @@ async fn thread_envelopes(
                             message_id: "0",
                             subject: "",
                             from: "",
+                            from_name: None,
+                            from_addr: "",
                             date: Default::default(),
                         };
                         final_graph.add_edge(ea, eb.as_threaded(), *w);
@@ -122,10 +125,11 @@ async fn thread_envelope(
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
 
         let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, opts.query.as_ref())
-            .into_iter()
-            .map(|e| (e.id.clone(), e))
-            .collect();
+        let envelopes =
+            Envelopes::from_mdir_entries(entries, opts.query.as_ref(), &*ctx.account_config.clock)
+                .into_iter()
+                .map(|e| (e.id.clone(), e))
+                .collect();
 
         let envelopes = ThreadedEnvelopes::new(envelopes, move |envelopes| {
             let msg_id_mapping: HashMap<_, _> = envelopes
@@ -180,6 +184,8 @@ async fn thread_envelope(
                             message_id: "0",
                             subject: "",
                             from: "",
+                            from_name: None,
+                            from_addr: "",
                             date: Default::default(),
                         };
                         final_graph.add_edge(ea, eb.as_threaded(), *w);
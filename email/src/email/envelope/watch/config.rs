@@ -12,6 +12,14 @@ pub struct WatchEnvelopeConfig {
     /// received.
     pub received: Option<WatchHook>,
 
+    /// Watch hook configuration for when the flags of an existing
+    /// envelope have changed.
+    pub flags_changed: Option<WatchHook>,
+
+    /// Watch hook configuration for when an envelope has been
+    /// removed (expunged).
+    pub removed: Option<WatchHook>,
+
     /// Watch hook configuration hook for any other case.
     pub any: Option<WatchHook>,
 }
@@ -1,12 +1,23 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use async_trait::async_trait;
-use tokio::sync::oneshot::{Receiver, Sender};
+use imap_client::imap_next::imap_types::sequence::SequenceSet;
+use tokio::{
+    sync::oneshot::{self, Receiver, Sender},
+    time::sleep,
+};
 use tracing::{debug, info};
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::WatchEnvelopes;
-use crate::{envelope::Envelope, imap::ImapContext, AnyResult};
+use crate::{
+    envelope::Envelope,
+    imap::{
+        notify::{ImapNotifyEvent, ImapNotifyMailboxes, ImapNotifySet},
+        Error, ImapClient, ImapContext,
+    },
+    AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct WatchImapEnvelopes {
@@ -34,35 +45,22 @@ pub async fn watch_envelopes_loop(
         info!("watching imap folder {folder} for envelope changes");
 
         let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
 
         let folder = config.get_folder_alias(folder);
         let folder_encoded = encode_utf7(folder.clone());
         debug!("utf7 encoded folder: {folder_encoded}");
 
-        let envelopes_count = client
-            .examine_mailbox(folder_encoded)
-            .await?
-            .exists
-            .unwrap() as usize;
-
-        let envelopes = if envelopes_count == 0 {
-            Default::default()
-        } else {
-            client.fetch_all_envelopes().await?
-        };
-
-        let mut envelopes: HashMap<String, Envelope> =
-            HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        let mut envelopes = fetch_envelopes(&mut client, &folder_encoded).await?;
+        let set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES);
 
         loop {
-            info!("starting new IMAP IDLE loop…");
-            client.idle(wait_for_shutdown_request).await?;
-            info!("received IDLE change notification or timeout");
+            info!("waiting for IMAP change notification…");
+            client.notify(&set, wait_for_shutdown_request).await?;
+            info!("received change notification or timeout");
 
-            let next_envelopes = client.fetch_all_envelopes().await?;
-            let next_envelopes: HashMap<String, Envelope> =
-                HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
+            let next_envelopes = update_envelopes(&mut client, &folder_encoded, &envelopes).await?;
 
             self.exec_hooks(config, &envelopes, &next_envelopes).await;
 
@@ -71,6 +69,77 @@ pub async fn watch_envelopes_loop(
     }
 }
 
+/// Examine the given mailbox then fetch all its envelopes, including
+/// their flags, indexed by identifier.
+async fn fetch_envelopes(
+    client: &mut ImapClient,
+    folder: &str,
+) -> AnyResult<HashMap<String, Envelope>> {
+    let envelopes_count = client
+        .examine_mailbox(folder.to_owned())
+        .await?
+        .exists
+        .unwrap_or_default();
+
+    let envelopes = if envelopes_count == 0 {
+        Default::default()
+    } else {
+        client.fetch_all_envelopes().await?
+    };
+
+    Ok(HashMap::from_iter(
+        envelopes.into_iter().map(|e| (e.id.clone(), e)),
+    ))
+}
+
+/// Update the given envelopes after an IDLE change notification.
+///
+/// The IMAP client consumes the untagged responses received while
+/// idling, so changes cannot be taken from them. Instead, the mailbox
+/// is examined again and only UIDs and flags are fetched: flags of
+/// known envelopes are updated, envelopes missing from the mailbox
+/// are dropped, and full envelopes are only fetched for new
+/// messages.
+async fn update_envelopes(
+    client: &mut ImapClient,
+    folder: &str,
+    envelopes: &HashMap<String, Envelope>,
+) -> AnyResult<HashMap<String, Envelope>> {
+    let envelopes_count = client
+        .examine_mailbox(folder.to_owned())
+        .await?
+        .exists
+        .unwrap_or_default();
+
+    if envelopes_count == 0 {
+        return Ok(Default::default());
+    }
+
+    let mut next_envelopes = HashMap::with_capacity(envelopes_count as usize);
+    let mut new_uids = Vec::new();
+
+    for (uid, flags) in client.fetch_all_flags().await? {
+        let id = uid.to_string();
+        match envelopes.get(&id) {
+            Some(envelope) => {
+                let mut envelope = envelope.clone();
+                envelope.flags = flags;
+                next_envelopes.insert(id, envelope);
+            }
+            None => new_uids.push(uid),
+        }
+    }
+
+    if let Ok(uids) = SequenceSet::try_from(new_uids) {
+        debug!("fetching new envelopes {uids:?}");
+        for envelope in client.fetch_envelopes(uids).await? {
+            next_envelopes.insert(envelope.id.clone(), envelope);
+        }
+    }
+
+    Ok(next_envelopes)
+}
+
 #[async_trait]
 impl WatchEnvelopes for WatchImapEnvelopes {
     async fn watch_envelopes(
@@ -87,4 +156,67 @@ async fn watch_envelopes(
 
         res
     }
+
+    /// Wait for changes using IMAP NOTIFY, or IDLE when the server
+    /// does not support it, interrupted once the given timeout
+    /// elapses.
+    async fn wait_for_envelopes_change(&self, folder: &str, timeout: Duration) -> AnyResult<()> {
+        self.wait_for_folders_change(&[folder.to_owned()], timeout)
+            .await
+    }
+
+    /// Wait for changes in all the given folders at once using IMAP
+    /// NOTIFY, interrupted once the given timeout elapses.
+    ///
+    /// The first folder is examined and notified as the selected
+    /// mailbox. When the server does not support NOTIFY, only this
+    /// folder is watched, using IDLE.
+    async fn wait_for_folders_change(
+        &self,
+        folders: &[String],
+        timeout: Duration,
+    ) -> AnyResult<()> {
+        let config = &self.ctx.account_config;
+        let mut client = self.ctx.client().await?;
+
+        let Some((folder, others)) = folders.split_first() else {
+            sleep(timeout).await;
+            return Ok(());
+        };
+
+        let folder = encode_utf7(config.get_folder_alias(folder));
+        client.examine_mailbox(folder).await?;
+
+        let mut set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES);
+
+        if !others.is_empty() {
+            let others = others
+                .iter()
+                .map(|folder| config.get_folder_alias(folder))
+                .collect();
+            set.add_group(
+                ImapNotifyMailboxes::Mailboxes(others),
+                ImapNotifyEvent::ENVELOPES,
+            );
+        }
+
+        let (interrupt, mut wait_for_interrupt) = oneshot::channel();
+        tokio::spawn(async move {
+            sleep(timeout).await;
+            let _ = interrupt.send(());
+        });
+
+        match client.notify(&set, &mut wait_for_interrupt).await {
+            Err(Error::NotifyInterruptedError) => {
+                debug!("timed out without change notification");
+                Ok(())
+            }
+            Err(err) => Err(err.into()),
+            Ok(notifications) => {
+                debug!(?notifications, "received change notification");
+                Ok(())
+            }
+        }
+    }
 }
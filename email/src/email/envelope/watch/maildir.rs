@@ -1,15 +1,18 @@
-use std::{collections::HashMap, sync::mpsc};
+use std::collections::HashMap;
 
 use async_trait::async_trait;
-use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use futures::StreamExt;
 use tokio::sync::oneshot::{Receiver, Sender};
 use tracing::{debug, info, trace};
 
 use super::WatchEnvelopes;
 use crate::{
     email::error::Error,
-    envelope::{Envelope, Envelopes},
-    maildir::MaildirContextSync,
+    envelope::{Envelope, Envelopes, Flags},
+    maildir::{
+        watch::{MaildirEvent, MaildirWatcher},
+        AsyncMaildir, MaildirContextSync,
+    },
     AnyResult,
 };
 
@@ -42,41 +45,62 @@ async fn watch_envelopes(
         info!("maildir: watching folder {folder} for email changes");
 
         let session = self.ctx.lock().await;
-        let config = &session.account_config;
+        let config = session.account_config.clone();
+        let mdir = AsyncMaildir::from(session.get_maildir_from_folder_alias(folder)?);
+        drop(session);
 
-        let mdir = session.get_maildir_from_folder_alias(folder)?;
-        let entries = mdir.read().map_err(Error::MaildirsError)?;
-        let envelopes = Envelopes::from_mdir_entries(entries, None);
-        let mut envelopes: HashMap<String, Envelope> =
-            HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
-
-        let (tx, rx) = mpsc::channel();
-        let mut watcher =
-            RecommendedWatcher::new(tx, Default::default()).map_err(Error::NotifyFailure)?;
-        watcher
-            .watch(mdir.path(), RecursiveMode::Recursive)
-            .map_err(Error::NotifyFailure)?;
+        // both the watcher and the initial envelopes read the whole
+        // folder, which blocks
+        let (mut watch, envelopes) = mdir
+            .run({
+                let config = config.clone();
+                move |mdir| -> AnyResult<_> {
+                    let watch = MaildirWatcher::new(mdir.clone()).watch()?;
+                    let entries = mdir.read().map_err(Error::MaildirsError)?;
+                    let envelopes = Envelopes::from_mdir_entries(entries, None, &*config.clock);
+                    Ok((watch, envelopes))
+                }
+            })
+            .await
+            .map_err(Error::MaildirsError)??;
         debug!("watching maildir folder {folder:?}…");
 
-        for res in rx {
-            match res {
-                Ok(_evt) => {
-                    trace!("received filesystem change event: {_evt:?}");
+        let mut envelopes: HashMap<String, Envelope> =
+            HashMap::from_iter(envelopes.into_iter().map(|e| (e.id.clone(), e)));
 
-                    let entries = mdir.read().map_err(Error::MaildirsError)?;
-                    let next_envelopes = Envelopes::from_mdir_entries(entries, None);
-                    let next_envelopes: HashMap<String, Envelope> =
-                        HashMap::from_iter(next_envelopes.into_iter().map(|e| (e.id.clone(), e)));
+        while let Some(evt) = watch.next().await {
+            trace!("received maildir event: {evt:?}");
 
-                    self.exec_hooks(config, &envelopes, &next_envelopes).await;
+            // only new messages are read, known ones are updated
+            // from their file name
+            let mut next_envelopes = envelopes.clone();
 
-                    envelopes = next_envelopes;
+            match evt {
+                MaildirEvent::MessageAdded(entry) => {
+                    let envelope = mdir.run(move |_| Envelope::try_from(entry)).await;
+                    if let Ok(Ok(envelope)) = envelope {
+                        next_envelopes.insert(envelope.id.clone(), envelope);
+                    }
+                }
+                MaildirEvent::FlagsChanged(entry) => {
+                    let Ok(id) = entry.id().map(ToOwned::to_owned) else {
+                        continue;
+                    };
+                    let Ok(flags) = Flags::try_from(entry) else {
+                        continue;
+                    };
+                    if let Some(envelope) = next_envelopes.get_mut(&id) {
+                        envelope.flags = flags;
+                    }
                 }
-                Err(_err) => {
-                    debug!("error while receiving message added event: {_err}");
-                    debug!("{_err:?}");
+                MaildirEvent::MessageRemoved(id) => {
+                    next_envelopes.remove(&id);
                 }
             }
+
+            self.exec_hooks(&config, &envelopes, &next_envelopes).await;
+
+            envelopes = next_envelopes;
         }
 
         Ok(())
@@ -3,15 +3,80 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "notmuch")]
+pub mod notmuch;
+pub mod supervisor;
 
-use std::collections::HashMap;
+use std::{cmp::Ordering, collections::HashMap, time::Duration};
 
 use async_trait::async_trait;
-use tokio::sync::oneshot::{Receiver, Sender};
+use tokio::{
+    sync::oneshot::{Receiver, Sender},
+    time::sleep,
+};
 use tracing::{debug, info};
 
 use crate::{account::config::AccountConfig, envelope::Envelope, AnyResult};
 
+/// The envelope change detected by a watcher.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EnvelopeChange<'a> {
+    /// A new envelope has been received.
+    Received(&'a Envelope),
+
+    /// The flags of an existing envelope have changed.
+    FlagsChanged(&'a Envelope),
+
+    /// An envelope has been removed (expunged).
+    Removed(&'a Envelope),
+}
+
+/// Compute the changes between two snapshots of the same folder,
+/// indexed by envelope identifier.
+///
+/// Changes are sorted by envelope identifier, see [`compare_ids`]:
+/// removed envelopes come first, then received and updated ones.
+pub fn diff_envelopes<'a>(
+    prev_envelopes: &'a HashMap<String, Envelope>,
+    next_envelopes: &'a HashMap<String, Envelope>,
+) -> Vec<EnvelopeChange<'a>> {
+    let mut removed: Vec<_> = prev_envelopes
+        .iter()
+        .filter(|(id, _)| !next_envelopes.contains_key(*id))
+        .collect();
+    removed.sort_by(|(a, _), (b, _)| compare_ids(a, b));
+
+    let mut changed: Vec<_> = next_envelopes
+        .iter()
+        .filter_map(|(id, envelope)| match prev_envelopes.get(id) {
+            None => Some((id, EnvelopeChange::Received(envelope))),
+            Some(prev) if prev.flags != envelope.flags => {
+                Some((id, EnvelopeChange::FlagsChanged(envelope)))
+            }
+            Some(_) => None,
+        })
+        .collect();
+    changed.sort_by(|(a, _), (b, _)| compare_ids(a, b));
+
+    removed
+        .into_iter()
+        .map(|(_, envelope)| EnvelopeChange::Removed(envelope))
+        .chain(changed.into_iter().map(|(_, change)| change))
+        .collect()
+}
+
+/// Compare envelope identifiers.
+///
+/// Identifiers are compared numerically when they are both numbers,
+/// like IMAP UIDs, so that `9` comes before `10`. They are compared
+/// lexicographically otherwise.
+pub fn compare_ids(a: &str, b: &str) -> Ordering {
+    match (a.parse::<u64>(), b.parse::<u64>()) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
 #[async_trait]
 pub trait WatchEnvelopes: Send + Sync {
     /// Watch the given folder for envelopes changes.
@@ -22,6 +87,39 @@ async fn watch_envelopes(
         shutdown: Sender<()>,
     ) -> AnyResult<()>;
 
+    /// Wait until the given folder changes, or until the given
+    /// timeout elapses.
+    ///
+    /// Returning does not guarantee that the folder changed: callers
+    /// are expected to compare snapshots of the folder. The default
+    /// implementation just waits for the timeout, which makes callers
+    /// poll the folder.
+    async fn wait_for_envelopes_change(&self, _folder: &str, timeout: Duration) -> AnyResult<()> {
+        sleep(timeout).await;
+        Ok(())
+    }
+
+    /// Wait until one of the given folders changes, or until the
+    /// given timeout elapses.
+    ///
+    /// Backends able to watch multiple folders at once, like IMAP
+    /// servers supporting NOTIFY, wait for changes in all of them.
+    /// The default implementation only waits for changes in the
+    /// first folder, see [`WatchEnvelopes::wait_for_envelopes_change`].
+    async fn wait_for_folders_change(
+        &self,
+        folders: &[String],
+        timeout: Duration,
+    ) -> AnyResult<()> {
+        match folders.first() {
+            Some(folder) => self.wait_for_envelopes_change(folder, timeout).await,
+            None => {
+                sleep(timeout).await;
+                Ok(())
+            }
+        }
+    }
+
     async fn exec_hooks(
         &self,
         config: &AccountConfig,
@@ -29,17 +127,78 @@ async fn exec_hooks(
         next_envelopes: &HashMap<String, Envelope>,
     ) {
         debug!("executing watch hooks…");
-        for (id, envelope) in next_envelopes {
-            // a new envelope has been added
-            if !prev_envelopes.contains_key(id) {
-                info!(id, "new message detected");
-                debug!("processing received envelope event…");
-                config.exec_received_envelope_hook(envelope).await;
-            } else {
-                // TODO
-                // debug!("processing any envelope event…");
-                // config.exec_any_envelope_hook(envelope).await;
+        for change in diff_envelopes(prev_envelopes, next_envelopes) {
+            match change {
+                EnvelopeChange::Received(envelope) => {
+                    info!(id = envelope.id, "new message detected");
+                    debug!("processing received envelope event…");
+                    config.exec_received_envelope_hook(envelope).await;
+                }
+                EnvelopeChange::FlagsChanged(envelope) => {
+                    info!(id = envelope.id, "message flags change detected");
+                    debug!("processing flags changed envelope event…");
+                    config.exec_flags_changed_envelope_hook(envelope).await;
+                    config.exec_any_envelope_hook(envelope).await;
+                }
+                EnvelopeChange::Removed(envelope) => {
+                    info!(id = envelope.id, "message removal detected");
+                    debug!("processing removed envelope event…");
+                    config.exec_removed_envelope_hook(envelope).await;
+                    config.exec_any_envelope_hook(envelope).await;
+                }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{diff_envelopes, EnvelopeChange};
+    use crate::{
+        envelope::Envelope,
+        flag::{Flag, Flags},
+    };
+
+    fn envelopes(envelopes: Vec<(&str, Flags)>) -> HashMap<String, Envelope> {
+        HashMap::from_iter(envelopes.into_iter().map(|(id, flags)| {
+            let envelope = Envelope {
+                id: id.into(),
+                flags,
+                ..Default::default()
+            };
+            (id.to_owned(), envelope)
+        }))
+    }
+
+    #[test]
+    fn diff() {
+        let prev = envelopes(vec![
+            ("1", Flags::default()),
+            ("2", Flags::default()),
+            ("3", Flags::default()),
+            ("9", Flags::default()),
+            ("10", Flags::default()),
+        ]);
+        let next = envelopes(vec![
+            ("1", Flags::default()),
+            ("3", Flags::from_iter([Flag::Seen])),
+            ("4", Flags::default()),
+            ("11", Flags::default()),
+        ]);
+
+        // identifiers are compared numerically
+        assert_eq!(
+            diff_envelopes(&prev, &next),
+            vec![
+                EnvelopeChange::Removed(&prev["2"]),
+                EnvelopeChange::Removed(&prev["9"]),
+                EnvelopeChange::Removed(&prev["10"]),
+                EnvelopeChange::FlagsChanged(&next["3"]),
+                EnvelopeChange::Received(&next["4"]),
+                EnvelopeChange::Received(&next["11"]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::mpsc::{self, RecvTimeoutError},
+};
+
+use async_trait::async_trait;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notmuch::Revision;
+use tokio::sync::oneshot::{Receiver, Sender};
+use tracing::{debug, info, trace};
+
+use super::WatchEnvelopes;
+use crate::{
+    email::error::Error,
+    envelope::{Envelope, Envelopes},
+    folder::FolderKind,
+    notmuch::{NotmuchContext, NotmuchContextSync},
+    AnyResult,
+};
+
+pub struct WatchNotmuchEnvelopes {
+    ctx: NotmuchContextSync,
+}
+
+impl WatchNotmuchEnvelopes {
+    pub fn new(ctx: &NotmuchContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &NotmuchContextSync) -> Box<dyn WatchEnvelopes> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &NotmuchContextSync) -> Option<Box<dyn WatchEnvelopes>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl WatchEnvelopes for WatchNotmuchEnvelopes {
+    async fn watch_envelopes(
+        &self,
+        folder: &str,
+        _wait_for_shutdown_request: Receiver<()>,
+        _shutdown: Sender<()>,
+    ) -> AnyResult<()> {
+        info!("notmuch: watching folder {folder} for email changes");
+
+        let mut ctx = self.ctx.lock().await;
+        let config = ctx.account_config.clone();
+        let poll_interval = ctx.notmuch_config.find_watch_poll_interval();
+
+        let folder = config.get_folder_alias(folder);
+        let mdir = ctx.mdir_ctx.get_maildir_from_folder_alias(&folder)?;
+
+        ctx.refresh_index()?;
+        let (mut envelopes, mut revision) = list_envelopes(&ctx, &folder)?;
+
+        // only the cur and new directories are watched: in Maildir++
+        // the inbox is the root directory, which also contains other
+        // folders as well as the Notmuch database
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            RecommendedWatcher::new(tx, Default::default()).map_err(Error::NotifyFailure)?;
+        for dir in ["cur", "new"] {
+            watcher
+                .watch(&mdir.path().join(dir), RecursiveMode::NonRecursive)
+                .map_err(Error::NotifyFailure)?;
+        }
+        debug!("watching notmuch folder {folder:?}…");
+
+        loop {
+            match rx.recv_timeout(poll_interval) {
+                Ok(Ok(_evt)) => {
+                    trace!("received filesystem change event: {_evt:?}");
+                    ctx.refresh_index()?;
+                }
+                Ok(Err(_err)) => {
+                    debug!("error while receiving message added event: {_err}");
+                    debug!("{_err:?}");
+                    continue;
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    // tags can be changed without touching the
+                    // maildir, which is detected by the database
+                    // revision (lastmod)
+                    let db = ctx.open_db()?;
+                    let next_revision = db.revision();
+                    db.close().map_err(Error::NotMuchFailure)?;
+
+                    if next_revision == revision {
+                        trace!("notmuch database revision unchanged");
+                        continue;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    break;
+                }
+            }
+
+            let (next_envelopes, next_revision) = list_envelopes(&ctx, &folder)?;
+            self.exec_hooks(&config, &envelopes, &next_envelopes).await;
+
+            envelopes = next_envelopes;
+            revision = next_revision;
+        }
+
+        Ok(())
+    }
+}
+
+/// List all envelopes of the given folder, indexed by identifier,
+/// alongside the database revision they were listed at.
+fn list_envelopes(
+    ctx: &NotmuchContext,
+    folder: &str,
+) -> AnyResult<(HashMap<String, Envelope>, Revision)> {
+    let db = ctx.open_db()?;
+
+    let query = if ctx.maildirpp() && FolderKind::matches_inbox(folder) {
+        String::from("folder:\"\"")
+    } else {
+        format!("folder:{folder:?}")
+    };
+
+    let query_builder = db.create_query(&query).map_err(Error::NotMuchFailure)?;
+    let msgs = query_builder.search_messages().map_err(|err| {
+        Error::SearchMessagesInvalidQueryNotmuch(err, folder.to_owned(), query.clone())
+    })?;
+
+    let envelopes = HashMap::from_iter(
+        Envelopes::from_notmuch_msgs(msgs)
+            .into_iter()
+            .map(|e| (e.id.clone(), e)),
+    );
+    let revision = db.revision();
+
+    db.close().map_err(Error::NotMuchFailure)?;
+
+    Ok((envelopes, revision))
+}
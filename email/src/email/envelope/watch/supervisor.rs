@@ -0,0 +1,268 @@
+//! # Watch supervisor
+//!
+//! Module dedicated to multi-folder watching, see
+//! [`WatchEnvelopesSupervisor`].
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use tokio::{
+    sync::{mpsc, oneshot::Receiver},
+    time::sleep,
+};
+use tracing::{debug, info, warn};
+
+use super::{diff_envelopes, EnvelopeChange, WatchEnvelopes};
+use crate::{
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope,
+    },
+    AnyResult,
+};
+
+/// The default duration the supervisor waits for changes on a
+/// single folder before moving to the next one.
+pub const DEFAULT_WATCH_SLICE: Duration = Duration::from_secs(60);
+
+/// The maximum duration the supervisor waits before retrying after
+/// an error.
+pub const MAX_WATCH_BACKOFF: Duration = Duration::from_secs(300);
+
+/// The kind of change of a [`WatchEvent`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WatchEventKind {
+    /// A new envelope has been received.
+    Received,
+
+    /// The flags of an existing envelope have changed.
+    FlagsChanged,
+
+    /// An envelope has been removed (expunged).
+    Removed,
+}
+
+/// The event emitted by the supervisor when an envelope changed in
+/// one of the watched folders.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct WatchEvent {
+    /// The folder the envelope belongs to.
+    pub folder: String,
+
+    /// The kind of change.
+    pub kind: WatchEventKind,
+
+    /// The changed envelope.
+    pub envelope: Envelope,
+}
+
+impl WatchEvent {
+    fn new(folder: &str, change: EnvelopeChange<'_>) -> Self {
+        let (kind, envelope) = match change {
+            EnvelopeChange::Received(envelope) => (WatchEventKind::Received, envelope),
+            EnvelopeChange::FlagsChanged(envelope) => (WatchEventKind::FlagsChanged, envelope),
+            EnvelopeChange::Removed(envelope) => (WatchEventKind::Removed, envelope),
+        };
+
+        Self {
+            folder: folder.to_owned(),
+            kind,
+            envelope: envelope.clone(),
+        }
+    }
+}
+
+/// The multi-folder watch supervisor.
+///
+/// Watches a set of folders using a single backend, and merges
+/// their changes into one stream of [`WatchEvent`]s. The supervisor
+/// waits for changes during a slice of time, then compares snapshots
+/// of all folders. Backends able to watch all folders at once (using
+/// IMAP NOTIFY, see [`WatchEnvelopes::wait_for_folders_change`])
+/// wake the supervisor up as soon as any folder changes. Other
+/// backends watch folders in turn (using IMAP IDLE when supported),
+/// changes occurring in other folders being caught when the slice
+/// ends (polling).
+///
+/// Errors do not stop the supervisor: it waits with an exponential
+/// backoff then tries again, letting the backend reconnect.
+pub struct WatchEnvelopesSupervisor<B: ?Sized> {
+    backend: Arc<B>,
+    folders: Vec<String>,
+    slice: Duration,
+}
+
+impl<B: ListEnvelopes + WatchEnvelopes + ?Sized> WatchEnvelopesSupervisor<B> {
+    pub fn new(backend: Arc<B>, folders: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self {
+            backend,
+            folders: folders.into_iter().map(|f| f.to_string()).collect(),
+            slice: DEFAULT_WATCH_SLICE,
+        }
+    }
+
+    pub fn set_slice(&mut self, slice: Duration) {
+        self.slice = slice;
+    }
+
+    /// Set the duration the supervisor waits for changes on a single
+    /// folder before moving to the next one, which is also the
+    /// polling interval of the other folders.
+    pub fn with_slice(mut self, slice: Duration) -> Self {
+        self.set_slice(slice);
+        self
+    }
+
+    /// Watch the folders, sending their changes to the given
+    /// channel.
+    ///
+    /// The supervisor stops when a shutdown is requested or when the
+    /// receiver of the channel is dropped. A shutdown request is
+    /// taken into account at the end of the current slice.
+    pub async fn watch(
+        &self,
+        events: mpsc::Sender<WatchEvent>,
+        mut wait_for_shutdown_request: Receiver<()>,
+    ) -> AnyResult<()> {
+        info!(folders = ?self.folders, "watching folders for envelope changes");
+
+        let mut snapshots: HashMap<&str, HashMap<String, Envelope>> = HashMap::new();
+        let mut backoff = Duration::ZERO;
+
+        for folder in self.folders.iter().cycle() {
+            if wait_for_shutdown_request.try_recv().is_ok() || events.is_closed() {
+                break;
+            }
+
+            if !backoff.is_zero() {
+                sleep(backoff).await;
+            }
+
+            match self.watch_slice(folder, &mut snapshots, &events).await {
+                Ok(()) => {
+                    backoff = Duration::ZERO;
+                }
+                Err(err) => {
+                    backoff = (backoff * 2).clamp(Duration::from_secs(1), MAX_WATCH_BACKOFF);
+                    warn!(
+                        folder,
+                        ?err,
+                        ?backoff,
+                        "cannot watch folder, retrying later"
+                    );
+                }
+            }
+        }
+
+        debug!("watch supervisor stopped");
+        Ok(())
+    }
+
+    /// Wait for changes on the given folder, then emit the changes of
+    /// all folders.
+    ///
+    /// Folders are snapshotted the first time they are seen, so that
+    /// existing envelopes are not reported as received.
+    async fn watch_slice<'a>(
+        &'a self,
+        folder: &str,
+        snapshots: &mut HashMap<&'a str, HashMap<String, Envelope>>,
+        events: &mpsc::Sender<WatchEvent>,
+    ) -> AnyResult<()> {
+        for folder in &self.folders {
+            if !snapshots.contains_key(folder.as_str()) {
+                snapshots.insert(folder, self.list_envelopes(folder).await?);
+            }
+        }
+
+        // the folder of the slice comes first, so that backends only
+        // able to watch one folder at once watch this one
+        let mut folders = vec![folder.to_owned()];
+        folders.extend(self.folders.iter().filter(|f| *f != folder).cloned());
+
+        self.backend
+            .wait_for_folders_change(&folders, self.slice)
+            .await?;
+
+        for folder in &self.folders {
+            let next = self.list_envelopes(folder).await?;
+            let prev = snapshots.insert(folder, next).unwrap_or_default();
+
+            for change in diff_envelopes(&prev, &snapshots[folder.as_str()]) {
+                let event = WatchEvent::new(folder, change);
+                debug!(?event, "envelope change detected");
+
+                if events.send(event).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn list_envelopes(&self, folder: &str) -> AnyResult<HashMap<String, Envelope>> {
+        let envelopes = self
+            .backend
+            .list_envelopes(folder, ListEnvelopesOptions::default())
+            .await?;
+
+        Ok(HashMap::from_iter(
+            envelopes.into_iter().map(|e| (e.id.clone(), e)),
+        ))
+    }
+}
+
+#[cfg(all(test, feature = "memory"))]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use mail_builder::MessageBuilder;
+    use tokio::sync::{mpsc, oneshot};
+
+    use super::{WatchEnvelopesSupervisor, WatchEventKind};
+    use crate::{
+        account::config::AccountConfig, backend::BackendBuilder, folder::add::AddFolder,
+        memory::MemoryContextBuilder, message::add::AddMessage,
+    };
+
+    #[tokio::test]
+    async fn merged_events() {
+        let account_config = Arc::new(AccountConfig::default());
+        let ctx = MemoryContextBuilder::new(account_config.clone());
+        let backend = BackendBuilder::new(account_config, ctx)
+            .build()
+            .await
+            .unwrap();
+        let backend = Arc::new(backend);
+
+        backend.add_folder("Archives").await.unwrap();
+
+        let supervisor = WatchEnvelopesSupervisor::new(backend.clone(), ["INBOX", "Archives"])
+            .with_slice(Duration::from_millis(10));
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let (shutdown, wait_for_shutdown_request) = oneshot::channel();
+        let watcher =
+            tokio::spawn(async move { supervisor.watch(tx, wait_for_shutdown_request).await });
+
+        // let the supervisor snapshot folders first
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let msg = MessageBuilder::new()
+            .from("alice@localhost")
+            .to("bob@localhost")
+            .subject("Hello")
+            .text_body("Hello")
+            .write_to_vec()
+            .unwrap();
+        backend.add_message("Archives", &msg).await.unwrap();
+
+        let event = rx.recv().await.unwrap();
+        assert_eq!(event.folder, "Archives");
+        assert_eq!(event.kind, WatchEventKind::Received);
+        assert_eq!(event.envelope.subject, "Hello");
+
+        shutdown.send(()).unwrap();
+        watcher.await.unwrap().unwrap();
+    }
+}
@@ -1,4 +1,4 @@
-use std::{any::Any, io, path::PathBuf, result};
+use std::{any::Any, io, num::NonZeroU32, path::PathBuf, result};
 
 use chumsky::error::Rich;
 #[cfg(feature = "imap")]
@@ -9,7 +9,7 @@
 #[cfg(feature = "maildir")]
 use crate::flag::Flags;
 use crate::{
-    envelope::{Id, SingleId},
+    envelope::{Id, SingleId, StableIdKind},
     AnyBoxedError, AnyError,
 };
 
@@ -42,6 +42,12 @@ pub enum Error {
     #[cfg(feature = "imap")]
     #[error("cannot parse IMAP sequence")]
     ParseSequenceError(#[source] ValidationError),
+    #[cfg(feature = "imap")]
+    #[error("cannot use {1} as IMAP search date")]
+    ParseImapSearchDateError(
+        #[source] imap_client::imap_next::imap_types::datetime::error::NaiveDateError,
+        chrono::NaiveDate,
+    ),
     #[cfg(feature = "maildir")]
     #[error("cannot list maildir entries")]
     ListMaildirEntriesError(#[source] maildirs::Error),
@@ -54,8 +60,20 @@ pub enum Error {
     ParseError(Vec<Rich<'static, char>>, String),
     #[error("cannot interpret message as template")]
     InterpretMessageAsTemplateError(#[source] mml::Error),
+    #[error("cannot compose message")]
+    ComposeMessageError(#[source] io::Error),
     #[error("cannot interpret message as thread template")]
     InterpretMessageAsThreadTemplateError(#[source] mml::Error),
+    #[error("cannot encode forwarded message")]
+    EncodeForwardedMessageError(#[source] io::Error),
+    #[error("cannot send message: forbidden header {0}")]
+    SendMessageForbiddenHeaderError(&'static str),
+    #[error("cannot send message: illegal character {0:#04x} in headers")]
+    SendMessageIllegalHeaderCharError(u8),
+    #[error("cannot send message: illegal character {0:#04x} in body")]
+    SendMessageIllegalBodyCharError(u8),
+    #[error("cannot send message: header {0} cannot be folded under 998 characters")]
+    SendMessageHeaderLineTooLongError(String),
     #[error("cannot run sendmail command")]
     RunSendmailCommandError(#[source] process::Error),
     #[cfg(feature = "notmuch")]
@@ -64,16 +82,36 @@ pub enum Error {
     #[cfg(feature = "maildir")]
     #[error("cannot remove maildir message(s) {2} from folder {1}")]
     RemoveMaildirMessageError(#[source] maildirs::Error, String, String),
+    #[cfg(feature = "maildir")]
+    #[error("cannot remove message {1} from folder {0}: message not found in this folder")]
+    RemoveMessageNotInFolderError(String, String),
+    #[cfg(feature = "notmuch")]
+    #[error("cannot remove notmuch message file at {1}")]
+    RemoveNotmuchMessageFileError(#[source] io::Error, PathBuf),
     #[cfg(feature = "notmuch")]
     #[error("cannot move notmuch message {3} from {1} to {2}")]
     MoveMessageNotmuchError(#[source] notmuch::Error, String, String, String),
     #[cfg(feature = "maildir")]
     #[error("cannot move message {3} from maildir folder {1} to folder {2}")]
-    MoveMessagesMaildirError(#[source] maildirs::Error, String, String, PathBuf),
+    MoveMessagesMaildirError(#[source] crate::maildir::Error, String, String, PathBuf),
     #[error("cannot parse email")]
     ParseEmailError,
     #[error("cannot parse email: raw email is empty")]
     ParseEmailEmptyRawError,
+    #[error("cannot get file name of attachment {0}")]
+    GetAttachmentFileNameError(PathBuf),
+    #[error("cannot create private directory at {1}")]
+    CreatePrivateDirError(#[source] io::Error, PathBuf),
+    #[error("cannot write private file at {1}")]
+    WritePrivateFileError(#[source] io::Error, PathBuf),
+    #[error("cannot open file at {1}")]
+    OpenFileError(#[source] process::Error, PathBuf),
+    #[error("cannot preview message: message has no text part")]
+    GetMessagePreviewEmptyError,
+    #[error("cannot parse message part path {0}")]
+    ParsePartPathError(String),
+    #[error("cannot find message part {0}")]
+    GetMessagePartNotFoundError(String),
     #[error("cannot delete local draft at {1}")]
     DeleteLocalDraftError(#[source] io::Error, PathBuf),
     #[error("cannot parse email: empty entries")]
@@ -155,6 +193,19 @@ pub enum Error {
     #[cfg(feature = "maildir")]
     #[error("cannot add maildir flags {3} to envelope(s) {2} from folder {1}")]
     AddFlagsMaildirError(#[source] maildirs::Error, String, String, Flags),
+    #[cfg(feature = "maildir")]
+    #[error("cannot mark maildir entry {2:?} from folder {1} as read")]
+    MarkReadBeforeMaildirError(#[source] maildirs::Error, String, PathBuf),
+    #[error("cannot parse stable envelope id {0}")]
+    ParseStableIdError(String),
+    #[error("cannot parse stable envelope id kind {0}")]
+    ParseStableIdKindError(String),
+    #[error("cannot use stable envelope id {0} with {1} backend")]
+    StableIdKindMismatchError(String, StableIdKind),
+    #[error("stable envelope id {0} is stale: UIDVALIDITY changed from {1} to {2}")]
+    StaleStableIdError(String, NonZeroU32, NonZeroU32),
+    #[error("cannot apply retention to {1} folder {0}: the backend cannot tell when messages entered it")]
+    RetentionUnsupportedError(String, &'static str),
     #[error("invalid input: {0}")]
     InvalidInput(String),
     #[error("failed to get envelopes: {0}")]
@@ -181,6 +232,16 @@ pub enum Error {
     ListRightEnvelopesCachedError(#[source] AnyBoxedError),
     #[error("cannot list envelopes from right sync backend")]
     ListRightEnvelopesError(#[source] AnyBoxedError),
+    #[cfg(feature = "sync")]
+    #[error("cannot invalidate {1} sync cache of folder {2}")]
+    InvalidateCacheError(
+        #[source] AnyBoxedError,
+        crate::sync::SyncDestination,
+        String,
+    ),
+    #[cfg(feature = "sync")]
+    #[error("cannot add messages to {0} folder {1}: the batch was rejected")]
+    AddMessagesBatchError(crate::sync::SyncDestination, String),
 
     #[cfg(feature = "maildir")]
     #[error(transparent)]
@@ -188,6 +249,11 @@ pub enum Error {
 
     #[error(transparent)]
     IoError(#[from] io::Error),
+
+    #[error("invalid email address {0}: {1}")]
+    ValidateAddressError(String, String),
+    #[error("cannot encode domain of email address {1} using IDNA")]
+    EncodeAddressDomainError(#[source] idna::Errors, String),
 }
 
 impl AnyError for Error {
@@ -1,3 +1,6 @@
+#[cfg(feature = "markdown")]
+use crate::message::markdown::MarkdownConfig;
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
@@ -8,4 +11,8 @@ pub struct MessageWriteConfig {
     /// Define visible headers at the top of messages when writing
     /// them (new/reply/forward).
     pub headers: Option<Vec<String>>,
+
+    /// Configuration dedicated to markdown message bodies.
+    #[cfg(feature = "markdown")]
+    pub markdown: Option<MarkdownConfig>,
 }
@@ -36,7 +36,7 @@ async fn add_message_with_flags(
     ) -> AnyResult<SingleId> {
         info!("adding imap message to folder {folder} with flags {flags}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -53,4 +53,36 @@ async fn add_message_with_flags(
 
         Ok(SingleId::from(uid.to_string()))
     }
+
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: Vec<(Flags, Vec<u8>)>,
+    ) -> AnyResult<Vec<SingleId>> {
+        info!("adding {} imap messages to folder {folder}", msgs.len());
+
+        let mut client = self.ctx.client().await?;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = encode_utf7(folder.clone());
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let msgs = msgs
+            .into_iter()
+            .map(|(flags, msg)| {
+                (
+                    flags.to_imap_flags_iter().into_iter().collect::<Vec<_>>(),
+                    msg,
+                )
+            })
+            .collect();
+
+        let uids = client.add_messages(&folder_encoded, msgs).await?;
+
+        Ok(uids
+            .into_iter()
+            .map(|uid| SingleId::from(uid.to_string()))
+            .collect())
+    }
 }
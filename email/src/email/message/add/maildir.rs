@@ -1,8 +1,16 @@
+use std::time::{Duration, UNIX_EPOCH};
+
 use async_trait::async_trait;
-use tracing::info;
+use mail_parser::MessageParser;
+use tracing::{info, warn};
 
 use super::{AddMessage, Flags};
-use crate::{email::error::Error, envelope::SingleId, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::SingleId,
+    maildir::{AsyncMaildir, MaildirContextSync},
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct AddMaildirMessage {
@@ -34,18 +42,35 @@ async fn add_message_with_flags(
         info!("adding maildir message to folder {folder} with flags {flags}");
 
         let ctx = self.ctx.lock().await;
-        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
-
-        let entry = mdir
-            .write_cur(
-                raw_msg,
-                flags
-                    .iter()
-                    .filter_map(|flag| maildirs::Flag::try_from(flag).ok()),
-            )
-            .map_err(|err| {
-                Error::StoreWithFlagsMaildirError(err, folder.to_owned(), flags.clone())
-            })?;
+        let mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(folder)?);
+        ctx.check_quota(raw_msg.len() as u64)?;
+
+        let mdir_flags = flags
+            .iter()
+            .filter_map(|flag| maildirs::Flag::try_from(flag).ok());
+
+        // keep the chronology of messages carrying a Date header
+        let time = MessageParser::new()
+            .parse_headers(raw_msg)
+            .and_then(|msg| msg.date().map(|date| date.to_timestamp()))
+            .and_then(|secs| u64::try_from(secs).ok())
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+
+        let entry = match time {
+            Some(time) => mdir.write_cur_with_time(raw_msg, mdir_flags, time).await,
+            None => mdir.write_cur(raw_msg, mdir_flags).await,
+        };
+
+        let entry = entry.map_err(|err| {
+            Error::StoreWithFlagsMaildirError(err, folder.to_owned(), flags.clone())
+        })?;
+
+        // the message is already stored, failing would make callers
+        // add it again: the quota file is fixed by its next
+        // recalculation instead
+        if let Err(err) = ctx.update_quota(raw_msg.len() as i64, 1) {
+            warn!(?err, "cannot update maildir quota, skipping it");
+        }
 
         Ok(SingleId::from(entry.id().unwrap()))
     }
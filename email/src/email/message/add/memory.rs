@@ -0,0 +1,49 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::AddMessage;
+use crate::{
+    envelope::SingleId,
+    flag::Flags,
+    memory::{MemoryContextSync, MemoryMessage},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct AddMemoryMessage {
+    ctx: MemoryContextSync,
+}
+
+impl AddMemoryMessage {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn AddMessage> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn AddMessage>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl AddMessage for AddMemoryMessage {
+    async fn add_message_with_flags(
+        &self,
+        folder: &str,
+        raw_msg: &[u8],
+        flags: &Flags,
+    ) -> AnyResult<SingleId> {
+        info!("adding memory message to folder {folder} with flags {flags}");
+
+        let config = &self.ctx.account_config;
+        let folder = config.get_folder_alias(folder);
+        let now = config.clock.now().fixed_offset();
+        let msg = MemoryMessage::new(raw_msg, flags.clone()).with_saved_at(now);
+        let id = self.ctx.lock().add_message(&folder, msg)?;
+
+        Ok(id)
+    }
+}
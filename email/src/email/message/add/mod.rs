@@ -3,6 +3,8 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 
@@ -25,6 +27,26 @@ async fn add_message_with_flags(
         flags: &Flags,
     ) -> AnyResult<SingleId>;
 
+    /// Add the given raw email messages with their flags to the given
+    /// folder.
+    ///
+    /// The default implementation adds messages one by one using
+    /// [`AddMessage::add_message_with_flags`]. Backends able to batch
+    /// additions should override it.
+    async fn add_messages_with_flags(
+        &self,
+        folder: &str,
+        msgs: Vec<(Flags, Vec<u8>)>,
+    ) -> AnyResult<Vec<SingleId>> {
+        let mut ids = Vec::with_capacity(msgs.len());
+
+        for (flags, msg) in msgs {
+            ids.push(self.add_message_with_flags(folder, &msg, &flags).await?);
+        }
+
+        Ok(ids)
+    }
+
     /// Add the given raw email message with the given flag to the
     /// given folder.
     async fn add_message_with_flag(
@@ -0,0 +1,118 @@
+use std::{io, num::NonZeroU32};
+
+use async_trait::async_trait;
+use futures::{io::Cursor, stream, AsyncRead, TryStreamExt};
+use mail_parser::MessageParser;
+use tracing::{debug, info};
+use utf7_imap::encode_utf7_imap as encode_utf7;
+
+use super::{GetAttachment, PartPath};
+use crate::{
+    envelope::SingleId,
+    imap::{Error, ImapContext},
+    AnyResult,
+};
+
+/// The size of the chunks attachments are fetched with, when the
+/// server supports the BINARY extension.
+const CHUNK_SIZE: NonZeroU32 = match NonZeroU32::new(1024 * 1024) {
+    Some(size) => size,
+    None => unreachable!(),
+};
+
+#[derive(Clone, Debug)]
+pub struct GetImapAttachment {
+    ctx: ImapContext,
+}
+
+impl GetImapAttachment {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetAttachment> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetAttachment>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetAttachment for GetImapAttachment {
+    async fn get_attachment(
+        &self,
+        folder: &str,
+        id: &SingleId,
+        path: &PartPath,
+    ) -> AnyResult<Box<dyn AsyncRead + Send + Unpin>> {
+        let id = id.as_str();
+        info!("getting imap attachment {path} of message {id} from folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = encode_utf7(folder.clone());
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let uid: NonZeroU32 = id
+            .parse()
+            .map_err(|_| Error::ParseUidError(id.to_owned()))?;
+
+        client.select_mailbox(&folder_encoded).await?;
+
+        if !client.ext_binary_supported() {
+            debug!("BINARY extension not supported, fetching the whole part");
+
+            let part = client.fetch_part(uid, path.as_slice()).await?;
+            let contents = MessageParser::new()
+                .parse(&part)
+                .map(|part| part.root_part().contents().to_vec())
+                .unwrap_or_default();
+
+            return Ok(Box::new(Cursor::new(contents)));
+        }
+
+        drop(client);
+
+        // every chunk is fetched with its own request, so that a
+        // failure only requires to fetch the current chunk again
+        let ctx = self.ctx.clone();
+        let section = path.as_slice().to_vec();
+
+        let chunks = stream::try_unfold(Some(0), move |offset| {
+            let ctx = ctx.clone();
+            let folder = folder_encoded.clone();
+            let section = section.clone();
+
+            async move {
+                let Some(offset) = offset else {
+                    return Ok(None);
+                };
+
+                let mut client = ctx.client().await.map_err(io::Error::other)?;
+                client
+                    .select_mailbox(&folder)
+                    .await
+                    .map_err(io::Error::other)?;
+
+                let chunk = client
+                    .fetch_binary_part(uid, &section, offset, CHUNK_SIZE)
+                    .await
+                    .map_err(io::Error::other)?;
+
+                let next = if chunk.len() < CHUNK_SIZE.get() as usize {
+                    None
+                } else {
+                    Some(offset + CHUNK_SIZE.get())
+                };
+
+                Ok(Some((chunk, next)))
+            }
+        });
+
+        Ok(Box::new(Box::pin(chunks).into_async_read()))
+    }
+}
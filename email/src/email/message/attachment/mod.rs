@@ -0,0 +1,198 @@
+//! Module dedicated to email message attachment.
+//!
+//! This module contains everything related to email message
+//! attachments, including the [`GetAttachment`] backend feature which
+//! retrieves a single attachment without downloading the whole
+//! message, and the [`AttachmentStore`] which deduplicates identical
+//! attachments across messages.
+
+#[cfg(feature = "imap")]
+pub mod imap;
+mod store;
+
+use std::{fmt, num::NonZeroU32, str::FromStr};
+
+use async_trait::async_trait;
+use futures::AsyncRead;
+use mail_parser::{MessagePart, PartType};
+
+#[doc(inline)]
+pub use self::store::{AttachmentStore, SharedAttachment};
+use crate::{email::error::Error, envelope::SingleId, AnyResult};
+
+/// The email message attachment.
+///
+/// Represents a simplified version of an email message attachment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Attachment {
+    /// The optional attachment filename.
+    pub filename: Option<String>,
+
+    /// The attachment MIME type.
+    pub mime: String,
+
+    /// The raw content of the attachment.
+    pub body: Vec<u8>,
+}
+
+/// The path of a MIME part inside a message.
+///
+/// Parts are addressed the IMAP way (RFC 3501 section 6.4.5): each
+/// number is the 1-based index of a part inside its multipart
+/// parent, for example `2.3` for the third part of the second part.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PartPath(Vec<NonZeroU32>);
+
+impl PartPath {
+    /// Return the part numbers of the path.
+    pub fn as_slice(&self) -> &[NonZeroU32] {
+        &self.0
+    }
+}
+
+impl From<Vec<NonZeroU32>> for PartPath {
+    fn from(parts: Vec<NonZeroU32>) -> Self {
+        Self(parts)
+    }
+}
+
+impl FromStr for PartPath {
+    type Err = Error;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let parts = path
+            .split('.')
+            .map(NonZeroU32::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::ParsePartPathError(path.to_owned()))?;
+
+        if parts.is_empty() {
+            return Err(Error::ParsePartPathError(path.to_owned()));
+        }
+
+        Ok(Self(parts))
+    }
+}
+
+impl fmt::Display for PartPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = self.0.iter();
+
+        if let Some(part) = parts.next() {
+            write!(f, "{part}")?;
+        }
+
+        for part in parts {
+            write!(f, ".{part}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait GetAttachment: Send + Sync {
+    /// Get the decoded content of the MIME part at the given path,
+    /// from the message matching the given id in the given folder.
+    ///
+    /// Only the requested part is retrieved when the backend allows
+    /// it, so large attachments can be streamed to disk without
+    /// holding the whole message in memory. Flags do not change.
+    async fn get_attachment(
+        &self,
+        folder: &str,
+        id: &SingleId,
+        path: &PartPath,
+    ) -> AnyResult<Box<dyn AsyncRead + Send + Unpin>>;
+}
+
+/// Find the MIME part at the given path inside the given parsed
+/// message.
+///
+/// A message which is not multipart has a single part, numbered 1.
+/// Parts containing a message (`message/rfc822`) are addressed like
+/// the message they contain.
+pub(crate) fn find_part<'a>(
+    msg: &'a mail_parser::Message<'_>,
+    path: &[NonZeroU32],
+) -> Option<&'a MessagePart<'a>> {
+    let (n, path) = path.split_first()?;
+    let root = msg.parts.first()?;
+
+    let part = match &root.body {
+        PartType::Multipart(ids) => msg.parts.get(*ids.get(n.get() as usize - 1)?)?,
+        _ if n.get() == 1 => root,
+        _ => return None,
+    };
+
+    find_sub_part(msg, part, path)
+}
+
+/// Find the MIME part at the given path, relative to the given part.
+fn find_sub_part<'a>(
+    msg: &'a mail_parser::Message<'_>,
+    part: &'a MessagePart<'_>,
+    path: &[NonZeroU32],
+) -> Option<&'a MessagePart<'a>> {
+    let Some((n, sub_path)) = path.split_first() else {
+        return Some(part);
+    };
+
+    match &part.body {
+        PartType::Multipart(ids) => {
+            let part = msg.parts.get(*ids.get(n.get() as usize - 1)?)?;
+            find_sub_part(msg, part, sub_path)
+        }
+        PartType::Message(msg) => find_part(msg, path),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::{find_part, PartPath};
+
+    #[test]
+    fn find_part_by_path() {
+        let raw = concat!(
+            "Content-Type: multipart/mixed; boundary=\"outer\"\r\n",
+            "\r\n",
+            "--outer\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "body\r\n",
+            "--outer\r\n",
+            "Content-Type: multipart/mixed; boundary=\"inner\"\r\n",
+            "\r\n",
+            "--inner\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "first\r\n",
+            "--inner\r\n",
+            "Content-Type: application/octet-stream\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "c2Vjb25k\r\n",
+            "--inner--\r\n",
+            "--outer--\r\n",
+        );
+
+        let msg = MessageParser::new().parse(raw.as_bytes()).unwrap();
+        let find = |path: &str| {
+            let path: PartPath = path.parse().unwrap();
+            find_part(&msg, path.as_slice()).map(|part| part.contents().to_vec())
+        };
+
+        assert_eq!(find("1").unwrap(), b"body");
+        assert_eq!(find("2.1").unwrap(), b"first");
+        assert_eq!(find("2.2").unwrap(), b"second");
+        assert_eq!(find("3"), None);
+        assert_eq!(find("1.1"), None);
+
+        assert_eq!("2.2".parse::<PartPath>().unwrap().to_string(), "2.2");
+        assert!("2..1".parse::<PartPath>().is_err());
+        assert!("0".parse::<PartPath>().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+//! # Attachment store
+//!
+//! Module dedicated to attachments deduplication. The
+//! [`AttachmentStore`] indexes attachment contents by hash, so that
+//! identical attachments found in different messages (signature
+//! logos, footers etc) share the same buffer, see
+//! [`SharedAttachment`].
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hasher},
+    sync::{Arc, Mutex, MutexGuard, Weak},
+};
+
+/// The index of the store: weak references to contents, by hash.
+type AttachmentIndex = HashMap<u64, Vec<Weak<[u8]>>>;
+
+/// The attachment sharing its content with identical attachments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SharedAttachment {
+    /// The optional attachment filename.
+    pub filename: Option<String>,
+
+    /// The attachment MIME type.
+    pub mime: String,
+
+    /// The hash of the attachment content.
+    ///
+    /// The hash is only meant to be compared with hashes coming from
+    /// the same process, it is not stable across releases.
+    pub hash: u64,
+
+    /// The shared content of the attachment.
+    pub body: Arc<[u8]>,
+}
+
+impl SharedAttachment {
+    /// Return `true` if both attachments share the same content
+    /// buffer.
+    pub fn shares_body_with(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.body, &other.body)
+    }
+}
+
+/// The content-addressed attachment store.
+///
+/// The store only keeps weak references: a content is dropped as
+/// soon as the last [`SharedAttachment`] referencing it is dropped.
+/// Cloning the store shares the same index.
+#[derive(Clone, Debug, Default)]
+pub struct AttachmentStore(Arc<Mutex<AttachmentIndex>>);
+
+impl AttachmentStore {
+    /// Create a new empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, AttachmentIndex> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Return the shared buffer matching the given content, with its
+    /// hash.
+    ///
+    /// The buffer is allocated only if no identical content is
+    /// already stored. Contents sharing the same hash are compared
+    /// byte by byte, so hash collisions cannot mix up attachments.
+    pub fn share(&self, content: &[u8]) -> (u64, Arc<[u8]>) {
+        let hash = hash(content);
+        let mut index = self.lock();
+        let bodies = index.entry(hash).or_default();
+
+        bodies.retain(|body| body.strong_count() > 0);
+
+        let shared = bodies
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|body| body.as_ref() == content);
+
+        let body = match shared {
+            Some(body) => body,
+            None => {
+                let body: Arc<[u8]> = Arc::from(content);
+                bodies.push(Arc::downgrade(&body));
+                body
+            }
+        };
+
+        (hash, body)
+    }
+
+    /// Return the number of distinct contents still referenced.
+    pub fn len(&self) -> usize {
+        self.lock()
+            .values()
+            .flatten()
+            .filter(|body| body.strong_count() > 0)
+            .count()
+    }
+
+    /// Return `true` if no content is referenced anymore.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return the total size in bytes of the distinct contents still
+    /// referenced.
+    pub fn size(&self) -> usize {
+        self.lock()
+            .values()
+            .flatten()
+            .filter_map(Weak::upgrade)
+            .map(|body| body.len())
+            .sum()
+    }
+
+    /// Remove the index entries of dropped contents.
+    pub fn prune(&self) {
+        self.lock().retain(|_, bodies| {
+            bodies.retain(|body| body.strong_count() > 0);
+            !bodies.is_empty()
+        });
+    }
+}
+
+fn hash(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(content);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AttachmentStore;
+
+    #[test]
+    fn share_identical_contents() {
+        let store = AttachmentStore::new();
+
+        let (hash1, logo1) = store.share(b"logo");
+        let (hash2, logo2) = store.share(b"logo");
+        let (_, other) = store.share(b"other");
+
+        assert_eq!(hash1, hash2);
+        assert!(std::sync::Arc::ptr_eq(&logo1, &logo2));
+        assert_eq!(store.len(), 2);
+        assert_eq!(store.size(), 9);
+
+        drop(other);
+        assert_eq!(store.len(), 1);
+
+        drop((logo1, logo2));
+        store.prune();
+        assert!(store.is_empty());
+    }
+}
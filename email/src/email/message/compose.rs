@@ -0,0 +1,525 @@
+//! # Message composition
+//!
+//! The main structure of this module is the [`MessageBuilder`], which
+//! helps you to compose a message without writing any MML markup. It
+//! wraps the [`mail_builder::MessageBuilder`] and produces raw bytes
+//! ready to be sent with
+//! [`SendMessage`](crate::message::send::SendMessage).
+//!
+//! The sender identity and the signature are taken from the account
+//! configuration.
+
+use std::sync::Arc;
+
+use mail_builder::{
+    headers::{address::Address, raw::Raw},
+    MessageBuilder as MailBuilder,
+};
+
+#[cfg(feature = "markdown")]
+use super::markdown::MarkdownRenderer;
+use super::{
+    size::{estimate_part_size, HEADERS_OVERHEAD},
+    template::new::config::NewTemplateSignatureStyle,
+};
+use crate::{account::config::AccountConfig, email::error::Error};
+
+/// A composed message part, attached or inlined.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ComposePart {
+    /// The MIME type of the part.
+    pub mime: String,
+
+    /// The file name of an attachment, or the content id of an
+    /// inline part.
+    pub name: String,
+
+    /// The raw content of the part.
+    pub body: Vec<u8>,
+}
+
+/// The typed message builder.
+///
+/// This builder helps you to compose a message from typed parts
+/// (recipients, subject, text and HTML bodies, attachments, inline
+/// images and custom headers) instead of an MML template.
+pub struct MessageBuilder {
+    /// Account configuration reference.
+    config: Arc<AccountConfig>,
+
+    from: Option<Address<'static>>,
+    to: Vec<Address<'static>>,
+    cc: Vec<Address<'static>>,
+    bcc: Vec<Address<'static>>,
+    reply_to: Vec<Address<'static>>,
+    subject: String,
+    text: Option<String>,
+    html: Option<String>,
+    #[cfg(feature = "markdown")]
+    markdown: Option<String>,
+    attachments: Vec<ComposePart>,
+    inline: Vec<ComposePart>,
+    headers: Vec<(String, String)>,
+
+    /// Override the style of the signature.
+    ///
+    /// Uses the signature style of the new template configuration if
+    /// this one is `None`.
+    signature_style: Option<NewTemplateSignatureStyle>,
+}
+
+impl MessageBuilder {
+    /// Create a new message builder from an account configuration.
+    pub fn new(config: Arc<AccountConfig>) -> Self {
+        Self {
+            config,
+            from: None,
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            reply_to: Vec::new(),
+            subject: String::new(),
+            text: None,
+            html: None,
+            #[cfg(feature = "markdown")]
+            markdown: None,
+            attachments: Vec::new(),
+            inline: Vec::new(),
+            headers: Vec::new(),
+            signature_style: None,
+        }
+    }
+
+    /// Override the sender, which defaults to the account identity.
+    pub fn with_from(mut self, addr: impl Into<Address<'static>>) -> Self {
+        self.from = Some(addr.into());
+        self
+    }
+
+    /// Add a recipient, using the builder pattern.
+    pub fn with_to(mut self, addr: impl Into<Address<'static>>) -> Self {
+        self.to.push(addr.into());
+        self
+    }
+
+    /// Add a carbon copy recipient, using the builder pattern.
+    pub fn with_cc(mut self, addr: impl Into<Address<'static>>) -> Self {
+        self.cc.push(addr.into());
+        self
+    }
+
+    /// Add a blind carbon copy recipient, using the builder pattern.
+    pub fn with_bcc(mut self, addr: impl Into<Address<'static>>) -> Self {
+        self.bcc.push(addr.into());
+        self
+    }
+
+    /// Add a reply-to address, using the builder pattern.
+    pub fn with_reply_to(mut self, addr: impl Into<Address<'static>>) -> Self {
+        self.reply_to.push(addr.into());
+        self
+    }
+
+    /// Set the subject, using the builder pattern.
+    pub fn with_subject(mut self, subject: impl ToString) -> Self {
+        self.subject = subject.to_string();
+        self
+    }
+
+    /// Set the plain text body, using the builder pattern.
+    pub fn with_text(mut self, text: impl ToString) -> Self {
+        self.text = Some(text.to_string());
+        self
+    }
+
+    /// Set the HTML body, using the builder pattern.
+    pub fn with_html(mut self, html: impl ToString) -> Self {
+        self.html = Some(html.to_string());
+        self
+    }
+
+    /// Set the markdown body, using the builder pattern.
+    ///
+    /// The markdown source is used as plain text body, and its HTML
+    /// rendering as HTML body, which produces a
+    /// `multipart/alternative` message. The HTML body is styled using
+    /// the stylesheet from the account configuration, see
+    /// [`MarkdownConfig`](super::markdown::MarkdownConfig).
+    #[cfg(feature = "markdown")]
+    pub fn with_markdown(mut self, markdown: impl ToString) -> Self {
+        self.markdown = Some(markdown.to_string());
+        self
+    }
+
+    /// Add an attachment, using the builder pattern.
+    pub fn with_attachment(
+        mut self,
+        mime: impl ToString,
+        filename: impl ToString,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.attachments.push(ComposePart {
+            mime: mime.to_string(),
+            name: filename.to_string(),
+            body: body.into(),
+        });
+        self
+    }
+
+    /// Add an inline part (usually an image referenced by the HTML
+    /// body using `cid:<cid>`), using the builder pattern.
+    pub fn with_inline(
+        mut self,
+        mime: impl ToString,
+        cid: impl ToString,
+        body: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.inline.push(ComposePart {
+            mime: mime.to_string(),
+            name: cid.to_string(),
+            body: body.into(),
+        });
+        self
+    }
+
+    /// Add a custom header, using the builder pattern.
+    pub fn with_header(mut self, key: impl ToString, val: impl ToString) -> Self {
+        self.headers.push((key.to_string(), val.to_string()));
+        self
+    }
+
+    /// Set some signature style.
+    pub fn set_some_signature_style(
+        &mut self,
+        style: Option<impl Into<NewTemplateSignatureStyle>>,
+    ) {
+        self.signature_style = style.map(Into::into);
+    }
+
+    /// Set the signature style.
+    pub fn set_signature_style(&mut self, style: impl Into<NewTemplateSignatureStyle>) {
+        self.set_some_signature_style(Some(style));
+    }
+
+    /// Set some signature style, using the builder pattern.
+    pub fn with_some_signature_style(
+        mut self,
+        style: Option<impl Into<NewTemplateSignatureStyle>>,
+    ) -> Self {
+        self.set_some_signature_style(style);
+        self
+    }
+
+    /// Set the signature style, using the builder pattern.
+    pub fn with_signature_style(mut self, style: impl Into<NewTemplateSignatureStyle>) -> Self {
+        self.set_signature_style(style);
+        self
+    }
+
+    /// Estimate the size of the raw message, in bytes.
+    ///
+    /// Bodies and parts are considered base64 encoded, which is the
+    /// worst case, so that the estimation can be compared to server
+    /// limits before composing and transmitting the message.
+    pub fn estimate_size(&self) -> usize {
+        let sig = self.config.find_full_signature();
+        let html_sig = self.config.find_html_signature();
+
+        let addrs = [&self.to, &self.cc, &self.bcc, &self.reply_to];
+        let addrs_len: usize = addrs.iter().map(|addrs| addrs.len() * 64).sum();
+
+        let headers_len: usize = self
+            .headers
+            .iter()
+            .map(|(key, val)| key.len() + val.len() + 4)
+            .sum();
+
+        #[cfg(not(feature = "markdown"))]
+        let bodies = [&self.text, &self.html];
+        // markdown is sent as is, alongside its HTML rendering
+        #[cfg(feature = "markdown")]
+        let bodies = [&self.text, &self.html, &self.markdown, &self.markdown];
+
+        let bodies = bodies
+            .into_iter()
+            .chain([&sig, &html_sig])
+            .flatten()
+            .map(String::len);
+
+        let parts = self
+            .attachments
+            .iter()
+            .chain(&self.inline)
+            .map(|part| part.body.len());
+
+        HEADERS_OVERHEAD
+            + self.subject.len()
+            + addrs_len
+            + headers_len
+            + bodies.chain(parts).map(estimate_part_size).sum::<usize>()
+    }
+
+    /// Build the raw message.
+    pub fn build(self) -> Result<Vec<u8>, Error> {
+        let sig = self.config.find_full_signature();
+        let html_sig = self.config.find_html_signature();
+        let sig_style = self
+            .signature_style
+            .unwrap_or_else(|| self.config.get_new_template_signature_style());
+
+        let from = match self.from {
+            Some(from) => from,
+            None => {
+                Address::new_address(self.config.display_name.clone(), self.config.email.clone())
+            }
+        };
+
+        let mut msg = MailBuilder::new()
+            .from(from)
+            .subject(self.subject)
+            .date(self.config.clock.now().timestamp());
+
+        if !self.to.is_empty() {
+            msg = msg.to(self.to);
+        }
+
+        if !self.cc.is_empty() {
+            msg = msg.cc(self.cc);
+        }
+
+        if !self.bcc.is_empty() {
+            msg = msg.bcc(self.bcc);
+        }
+
+        if !self.reply_to.is_empty() {
+            msg = msg.reply_to(self.reply_to);
+        }
+
+        for (key, val) in self.headers {
+            msg = msg.header(key, Raw::new(val));
+        }
+
+        #[cfg(not(feature = "markdown"))]
+        let (mut text, html) = (self.text, self.html);
+
+        #[cfg(feature = "markdown")]
+        let (mut text, html) = match self.markdown {
+            None => (self.text, self.html),
+            Some(markdown) => {
+                let css = self.config.find_message_write_markdown_css();
+                let renderer = MarkdownRenderer::new().with_some_css(css);
+                let mut html = renderer.render(&markdown);
+
+                // the HTML signature is pushed below
+                if sig_style.is_inlined() && html_sig.is_none() {
+                    if let Some(sig) = &sig {
+                        html.push_str(&renderer.render_signature(sig));
+                    }
+                }
+
+                (Some(markdown), Some(html))
+            }
+        };
+
+        if sig_style.is_inlined() {
+            if let Some(sig) = &sig {
+                // the signature goes to the plain text body, which is
+                // created if the message has no body at all
+                if text.is_some() || html.is_none() {
+                    let text = text.get_or_insert_with(String::new);
+                    if !text.is_empty() && !text.ends_with('\n') {
+                        text.push('\n');
+                    }
+                    text.push_str(sig);
+                }
+            }
+        }
+
+        if let Some(text) = text {
+            msg = msg.text_body(text);
+        }
+
+        if let Some(mut html) = html {
+            if sig_style.is_inlined() {
+                if let Some(sig) = &html_sig {
+                    push_html_signature(&mut html, sig);
+                }
+            }
+
+            msg = msg.html_body(html);
+        }
+
+        for part in self.inline {
+            msg = msg.inline(part.mime, part.name, part.body);
+        }
+
+        for part in self.attachments {
+            msg = msg.attachment(part.mime, part.name, part.body);
+        }
+
+        if sig_style.is_attached() {
+            if let Some(sig) = sig {
+                msg = msg.attachment("text/plain", "signature.txt", sig);
+            }
+
+            if let Some(sig) = html_sig {
+                msg = msg.attachment("text/html", "signature.html", sig);
+            }
+        }
+
+        msg.write_to_vec().map_err(Error::ComposeMessageError)
+    }
+}
+
+/// Push the given HTML signature at the end of the given HTML body,
+/// before the closing body tag if any.
+fn push_html_signature(html: &mut String, sig: &str) {
+    match html.rfind("</body>") {
+        Some(i) => html.insert_str(i, sig),
+        None => html.push_str(sig),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::MessageBuilder;
+    #[cfg(feature = "markdown")]
+    use crate::message::{
+        add::config::MessageWriteConfig, config::MessageConfig, markdown::MarkdownConfig,
+    };
+    use crate::{
+        account::config::AccountConfig,
+        date::{ClockSource, FixedClock},
+        message::Message,
+    };
+
+    #[test]
+    fn compose() {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+
+        let config = Arc::new(AccountConfig {
+            display_name: Some("Me".into()),
+            email: "me@localhost".into(),
+            signature: Some("Regards".into()),
+            clock: ClockSource::from(FixedClock::new(now, FixedOffset::east_opt(0).unwrap())),
+            ..Default::default()
+        });
+
+        let bytes = MessageBuilder::new(config)
+            .with_to(("You".to_owned(), "you@localhost".to_owned()))
+            .with_bcc("hidden@localhost")
+            .with_subject("Hello")
+            .with_text("Hello, world!")
+            .with_attachment("text/plain", "hello.txt", "attachment")
+            .with_header("X-Custom", "custom")
+            .build()
+            .unwrap();
+
+        let msg = Message::from(bytes.as_slice());
+        let msg = msg.parsed().unwrap();
+
+        assert_eq!(
+            msg.from().unwrap().first().unwrap().address(),
+            Some("me@localhost")
+        );
+        assert_eq!(msg.to().unwrap().first().unwrap().name(), Some("You"));
+        assert_eq!(
+            msg.bcc().unwrap().first().unwrap().address(),
+            Some("hidden@localhost")
+        );
+        assert_eq!(msg.subject(), Some("Hello"));
+        assert_eq!(msg.date().unwrap().to_timestamp(), now.timestamp());
+        assert_eq!(msg.header_raw("X-Custom").map(str::trim), Some("custom"));
+        assert_eq!(
+            msg.body_text(0).unwrap().replace('\r', ""),
+            "Hello, world!\n-- \nRegards"
+        );
+        assert_eq!(msg.attachment_count(), 1);
+    }
+
+    #[test]
+    fn estimate_size() {
+        let config = Arc::new(AccountConfig {
+            email: "me@localhost".into(),
+            ..Default::default()
+        });
+
+        let builder = MessageBuilder::new(config)
+            .with_to("you@localhost")
+            .with_subject("Hello")
+            .with_text("Hello, world!")
+            .with_attachment("application/octet-stream", "data.bin", vec![0xff; 100_000]);
+
+        let estimated = builder.estimate_size();
+        let actual = builder.build().unwrap().len();
+
+        assert!(estimated >= actual);
+        assert!(estimated < actual + 2048);
+    }
+
+    #[test]
+    fn compose_html_signature() {
+        let config = Arc::new(AccountConfig {
+            email: "me@localhost".into(),
+            signature: Some("Regards".into()),
+            signature_html: Some("<p><em>Regards</em></p>".into()),
+            ..Default::default()
+        });
+
+        let bytes = MessageBuilder::new(config)
+            .with_to("you@localhost")
+            .with_text("Hello, world!")
+            .with_html("<html><body><p>Hello, world!</p></body></html>")
+            .build()
+            .unwrap();
+
+        let msg = Message::from(bytes.as_slice());
+        let msg = msg.parsed().unwrap();
+
+        assert_eq!(
+            msg.body_text(0).unwrap().replace('\r', ""),
+            "Hello, world!\n-- \nRegards"
+        );
+        assert_eq!(
+            msg.body_html(0).unwrap().trim(),
+            "<html><body><p>Hello, world!</p><p><em>Regards</em></p></body></html>"
+        );
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn compose_markdown() {
+        let config = Arc::new(AccountConfig {
+            email: "me@localhost".into(),
+            message: Some(MessageConfig {
+                write: Some(MessageWriteConfig {
+                    markdown: Some(MarkdownConfig {
+                        css: Some("strong { color: red }".into()),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+
+        let bytes = MessageBuilder::new(config)
+            .with_to("you@localhost")
+            .with_markdown("Hello, **world**!")
+            .build()
+            .unwrap();
+
+        let msg = Message::from(bytes.as_slice());
+        let msg = msg.parsed().unwrap();
+
+        assert_eq!(msg.body_text(0).unwrap().trim(), "Hello, **world**!");
+        assert_eq!(
+            msg.body_html(0).unwrap().trim(),
+            "<p>Hello, <strong style=\"color: red\">world</strong>!</p>"
+        );
+    }
+}
@@ -4,7 +4,11 @@
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::CopyMessages;
-use crate::{envelope::Id, imap::ImapContext, AnyResult};
+use crate::{
+    envelope::{Id, IdMapping},
+    imap::ImapContext,
+    AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct CopyImapMessages {
@@ -28,9 +32,20 @@ pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn CopyMessages>> {
 #[async_trait]
 impl CopyMessages for CopyImapMessages {
     async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        self.copy_messages_with_mapping(from_folder, to_folder, id)
+            .await?;
+        Ok(())
+    }
+
+    async fn copy_messages_with_mapping(
+        &self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+    ) -> AnyResult<IdMapping> {
         info!("copying imap messages {id} from folder {from_folder} to folder {to_folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let from_folder = config.get_folder_alias(from_folder);
@@ -52,8 +67,12 @@ async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         };
 
         client.select_mailbox(&from_folder_encoded).await?;
-        client.copy_messages(uids, &to_folder_encoded).await?;
+        let uids = client.copy_messages(uids, &to_folder_encoded).await?;
+        debug!(?uids, "copied messages uids");
 
-        Ok(())
+        Ok(uids
+            .into_iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect())
     }
 }
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::CopyMessages;
+use crate::{envelope::Id, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct CopyMemoryMessages {
+    ctx: MemoryContextSync,
+}
+
+impl CopyMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn CopyMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn CopyMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl CopyMessages for CopyMemoryMessages {
+    async fn copy_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        info!("copying memory messages {id} from folder {from_folder} to folder {to_folder}");
+
+        let config = &self.ctx.account_config;
+        let from_folder = config.get_folder_alias(from_folder);
+        let to_folder = config.get_folder_alias(to_folder);
+        let now = config.clock.now().fixed_offset();
+
+        self.ctx
+            .lock()
+            .copy_messages(&from_folder, &to_folder, id, now)?;
+
+        Ok(())
+    }
+}
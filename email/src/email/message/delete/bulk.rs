@@ -0,0 +1,203 @@
+//! # Bulk delete
+//!
+//! Module dedicated to the deletion of a large amount of messages.
+//! Messages are deleted by chunks using the [`DeleteMessages`]
+//! feature, so it works with any backend. When a chunk fails, its
+//! messages are deleted one by one in order to find out which ones
+//! actually failed.
+
+use async_trait::async_trait;
+use tracing::{debug, warn};
+
+use super::DeleteMessages;
+use crate::{envelope::Id, AnyBoxedError};
+
+/// The default amount of messages deleted per chunk.
+pub const DEFAULT_CHUNK_SIZE: usize = 100;
+
+/// The progress of a bulk delete, reported after each chunk.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeleteMessagesBulkProgress {
+    /// The number of the chunk just processed, starting from 1.
+    pub chunk: usize,
+
+    /// The total number of chunks.
+    pub chunks: usize,
+
+    /// The number of messages processed so far.
+    pub processed: usize,
+
+    /// The number of messages that failed to be deleted so far.
+    pub failed: usize,
+
+    /// The total number of messages to delete.
+    pub total: usize,
+}
+
+/// The message that failed to be deleted.
+#[derive(Debug)]
+pub struct DeleteMessagesBulkFailure {
+    /// The id of the message.
+    pub id: String,
+
+    /// The reason of the failure.
+    pub err: AnyBoxedError,
+}
+
+/// The report of a bulk delete.
+#[derive(Debug, Default)]
+pub struct DeleteMessagesBulkReport {
+    /// The ids of the successfully deleted messages.
+    pub deleted: Vec<String>,
+
+    /// The messages that failed to be deleted.
+    pub failed: Vec<DeleteMessagesBulkFailure>,
+}
+
+impl DeleteMessagesBulkReport {
+    /// Return `true` if all the messages have been deleted.
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+#[async_trait]
+pub trait DeleteMessagesBulk: DeleteMessages {
+    /// Delete messages from the given folder matching the given ids,
+    /// using chunks of [`DEFAULT_CHUNK_SIZE`] messages.
+    ///
+    /// See [`DeleteMessagesBulk::delete_messages_by_chunks`].
+    async fn delete_messages_bulk(
+        &self,
+        folder: &str,
+        ids: &[String],
+        on_progress: &(dyn for<'p> Fn(&'p DeleteMessagesBulkProgress) + Send + Sync),
+    ) -> DeleteMessagesBulkReport {
+        self.delete_messages_by_chunks(folder, ids, DEFAULT_CHUNK_SIZE, on_progress)
+            .await
+    }
+
+    /// Delete messages from the given folder matching the given ids,
+    /// using chunks of the given size.
+    ///
+    /// The given callback is called after each chunk. Errors do not
+    /// stop the deletion: they are collected in the returned report,
+    /// with the id of the message that failed.
+    async fn delete_messages_by_chunks(
+        &self,
+        folder: &str,
+        ids: &[String],
+        chunk_size: usize,
+        on_progress: &(dyn for<'p> Fn(&'p DeleteMessagesBulkProgress) + Send + Sync),
+    ) -> DeleteMessagesBulkReport {
+        let chunk_size = chunk_size.max(1);
+        let chunks = ids.len().div_ceil(chunk_size);
+        let mut report = DeleteMessagesBulkReport::default();
+        let mut processed = 0;
+
+        for (i, chunk) in ids.chunks(chunk_size).enumerate() {
+            let id = Id::multiple(chunk);
+
+            match self.delete_messages(folder, &id).await {
+                Ok(()) => {
+                    report.deleted.extend_from_slice(chunk);
+                }
+                Err(err) => {
+                    debug!(
+                        ?err,
+                        "cannot delete chunk {}/{chunks}, retrying one by one",
+                        i + 1
+                    );
+
+                    for id in chunk {
+                        match self.delete_messages(folder, &Id::single(id)).await {
+                            Ok(()) => {
+                                report.deleted.push(id.clone());
+                            }
+                            Err(err) => {
+                                warn!(?err, "cannot delete message {id} from folder {folder}");
+                                let id = id.clone();
+                                report.failed.push(DeleteMessagesBulkFailure { id, err });
+                            }
+                        }
+                    }
+                }
+            }
+
+            processed += chunk.len();
+
+            on_progress(&DeleteMessagesBulkProgress {
+                chunk: i + 1,
+                chunks,
+                processed,
+                failed: report.failed.len(),
+                total: ids.len(),
+            });
+        }
+
+        report
+    }
+}
+
+impl<T: DeleteMessages + ?Sized> DeleteMessagesBulk for T {}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+
+    use super::{DeleteMessagesBulk, DeleteMessagesBulkProgress};
+    use crate::{envelope::Id, message::delete::DeleteMessages, AnyResult};
+
+    /// Fake backend failing to delete ids starting with `x`.
+    #[derive(Default)]
+    struct FakeDeleteMessages {
+        deleted: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl DeleteMessages for FakeDeleteMessages {
+        async fn delete_messages(&self, _folder: &str, id: &Id) -> AnyResult<()> {
+            let ids: Vec<_> = id.iter().map(ToOwned::to_owned).collect();
+
+            if ids.iter().any(|id| id.starts_with('x')) {
+                let err = crate::email::error::Error::FindMessageError(id.to_string());
+                return Err(Box::new(err));
+            }
+
+            self.deleted.lock().unwrap().extend(ids);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn delete_by_chunks() {
+        let backend = FakeDeleteMessages::default();
+        let ids: Vec<String> = ["1", "2", "3", "x4", "5"]
+            .into_iter()
+            .map(ToOwned::to_owned)
+            .collect();
+
+        let progress = Mutex::new(Vec::new());
+        let on_progress = |p: &DeleteMessagesBulkProgress| progress.lock().unwrap().push(p.clone());
+
+        let report = backend
+            .delete_messages_by_chunks("INBOX", &ids, 2, &on_progress)
+            .await;
+
+        assert!(!report.is_success());
+        assert_eq!(report.deleted, vec!["1", "2", "3", "5"]);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].id, "x4");
+        assert_eq!(*backend.deleted.lock().unwrap(), vec!["1", "2", "3", "5"]);
+
+        let progress = progress.into_inner().unwrap();
+        assert_eq!(progress.len(), 3);
+        assert_eq!(progress[1].processed, 4);
+        assert_eq!(progress[1].failed, 1);
+        assert_eq!(progress[2].chunk, 3);
+        assert_eq!(progress[2].chunks, 3);
+        assert_eq!(progress[2].processed, 5);
+    }
+}
@@ -1,3 +1,7 @@
+use std::{collections::HashMap, time::Duration};
+
+use crate::folder::TRASH;
+
 /// Configuration dedicated to message deletion.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -12,6 +16,64 @@ pub struct DeleteMessageConfig {
     /// the Trash folder or by adding the Deleted flag to their
     /// respective envelopes.
     pub style: Option<DeleteMessageStyle>,
+
+    /// The message retention policy.
+    ///
+    /// Defines after how long messages sitting in the Trash folder
+    /// (or any other folder like Junk) are definitely removed.
+    pub retention: Option<MessageRetentionConfig>,
+}
+
+/// Configuration dedicated to message retention.
+///
+/// See [`super::retention::PurgeExpiredMessages`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct MessageRetentionConfig {
+    /// The maximum time messages can sit in the Trash folder, in
+    /// days.
+    pub trash: Option<u64>,
+
+    /// The maximum time messages can sit in other folders, in days,
+    /// indexed by folder alias.
+    pub folders: Option<HashMap<String, u64>>,
+
+    /// Apply the retention policy automatically, at the end of
+    /// every synchronization and before watching envelopes.
+    ///
+    /// Disabled by default.
+    pub auto: Option<bool>,
+}
+
+impl MessageRetentionConfig {
+    /// Return the maximum age of messages for every configured
+    /// folder, starting with the Trash folder.
+    pub fn policies(&self) -> Vec<(String, Duration)> {
+        let mut folders: Vec<_> = self
+            .folders
+            .iter()
+            .flatten()
+            .map(|(folder, days)| (folder.clone(), *days))
+            .collect();
+        folders.sort();
+
+        self.trash
+            .map(|days| (TRASH.to_owned(), days))
+            .into_iter()
+            .chain(folders)
+            .map(|(folder, days)| (folder, Duration::from_secs(days * 24 * 60 * 60)))
+            .collect()
+    }
+
+    /// Return `true` if the retention policy should be applied
+    /// automatically.
+    pub fn is_auto(&self) -> bool {
+        self.auto.unwrap_or_default()
+    }
 }
 
 /// The message deletion style.
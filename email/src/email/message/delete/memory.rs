@@ -0,0 +1,64 @@
+use async_trait::async_trait;
+
+use super::{DefaultDeleteMessages, DeleteMessages};
+use crate::{
+    account::config::{AccountConfig, HasAccountConfig},
+    envelope::Id,
+    flag::{
+        add::{memory::AddMemoryFlags, AddFlags},
+        Flags,
+    },
+    memory::MemoryContextSync,
+    message::r#move::{memory::MoveMemoryMessages, MoveMessages},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct DeleteMemoryMessages {
+    ctx: MemoryContextSync,
+    move_messages: MoveMemoryMessages,
+    add_flags: AddMemoryFlags,
+}
+
+impl DeleteMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self {
+            ctx: ctx.clone(),
+            move_messages: MoveMemoryMessages::new(ctx),
+            add_flags: AddMemoryFlags::new(ctx),
+        }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn DeleteMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn DeleteMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+impl HasAccountConfig for DeleteMemoryMessages {
+    fn account_config(&self) -> &AccountConfig {
+        &self.ctx.account_config
+    }
+}
+
+#[async_trait]
+impl MoveMessages for DeleteMemoryMessages {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        self.move_messages
+            .move_messages(from_folder, to_folder, id)
+            .await
+    }
+}
+
+#[async_trait]
+impl AddFlags for DeleteMemoryMessages {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        self.add_flags.add_flags(folder, id, flags).await
+    }
+}
+
+#[async_trait]
+impl DefaultDeleteMessages for DeleteMemoryMessages {}
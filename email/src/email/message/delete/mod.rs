@@ -1,10 +1,14 @@
+pub mod bulk;
 pub mod config;
 #[cfg(feature = "imap")]
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
+pub mod retention;
 
 use async_trait::async_trait;
 
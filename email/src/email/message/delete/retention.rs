@@ -0,0 +1,255 @@
+//! # Message retention
+//!
+//! Module dedicated to message retention. Messages sitting in a
+//! folder for longer than a given age are definitely removed using
+//! the [`ListEnvelopes`] and [`RemoveMessages`] features, so it works
+//! with any backend able to tell when messages entered their folder
+//! (see [`Envelope::saved_at`](crate::envelope::Envelope::saved_at)).
+//! Other backends fail with
+//! [`Error::RetentionUnsupportedError`](crate::Error::RetentionUnsupportedError).
+//!
+//! See [`MessageRetentionConfig`](super::config::MessageRetentionConfig).
+
+use std::{collections::BTreeMap, time::Duration};
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset, TimeDelta};
+use tracing::{debug, info, trace, warn};
+
+use crate::{
+    account::config::HasAccountConfig,
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelopes, Id,
+    },
+    folder::TRASH,
+    message::remove::RemoveMessages,
+    AnyResult,
+};
+
+#[async_trait]
+pub trait PurgeExpiredMessages: HasAccountConfig + ListEnvelopes + RemoveMessages {
+    /// Definitely remove messages sitting in the given folder for
+    /// longer than the given age, and return their ids.
+    ///
+    /// The age of a message is computed from the date it entered the
+    /// folder, not from its Date header: an old message moved to the
+    /// Trash folder recently is kept. Messages for which the backend
+    /// cannot tell when they entered the folder are kept, and
+    /// backends that cannot tell it at all fail with
+    /// [`Error::RetentionUnsupportedError`](crate::Error::RetentionUnsupportedError).
+    async fn purge_folder_older_than(
+        &self,
+        folder: &str,
+        older_than: Duration,
+    ) -> AnyResult<Vec<String>> {
+        let now = self.account_config().clock.now();
+        let cutoff = now - TimeDelta::from_std(older_than).unwrap_or(TimeDelta::MAX);
+
+        let opts = ListEnvelopesOptions {
+            with_saved_dates: true,
+            ..Default::default()
+        };
+        let envelopes = self.list_envelopes(folder, opts).await?;
+        let ids = find_expired_ids(&envelopes, &cutoff.fixed_offset());
+
+        if ids.is_empty() {
+            debug!("no expired message found in folder {folder}");
+            return Ok(ids);
+        }
+
+        info!(
+            "removing {} expired messages from folder {folder}",
+            ids.len()
+        );
+        self.remove_messages(folder, &Id::multiple(&ids)).await?;
+
+        Ok(ids)
+    }
+
+    /// Definitely remove messages sitting in the Trash folder for
+    /// longer than the given age, and return their ids.
+    async fn purge_trash(&self, older_than: Duration) -> AnyResult<Vec<String>> {
+        self.purge_folder_older_than(TRASH, older_than).await
+    }
+
+    /// Apply the retention policy from the account configuration.
+    ///
+    /// Errors do not stop the process: they are logged, and the
+    /// failing folder is skipped. Returns the ids of the removed
+    /// messages, indexed by folder alias.
+    async fn apply_message_retention(&self) -> BTreeMap<String, Vec<String>> {
+        let mut report = BTreeMap::new();
+
+        for (folder, older_than) in self.account_config().find_message_retention_policies() {
+            match self.purge_folder_older_than(&folder, older_than).await {
+                Ok(ids) => {
+                    report.insert(folder, ids);
+                }
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        "cannot apply retention policy to folder {folder}, skipping it"
+                    );
+                }
+            }
+        }
+
+        report
+    }
+}
+
+impl<T: HasAccountConfig + ListEnvelopes + RemoveMessages> PurgeExpiredMessages for T {}
+
+/// Return the ids of envelopes saved in their folder before the
+/// given cutoff.
+///
+/// Envelopes without saved date are skipped.
+fn find_expired_ids(envelopes: &Envelopes, cutoff: &DateTime<FixedOffset>) -> Vec<String> {
+    envelopes
+        .iter()
+        .filter(|envelope| match envelope.saved_at {
+            Some(saved_at) => saved_at < *cutoff,
+            None => {
+                trace!(id = envelope.id, "unknown saved date, keeping message");
+                false
+            }
+        })
+        .map(|envelope| envelope.id.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, TimeZone};
+
+    use super::find_expired_ids;
+    use crate::envelope::{Envelope, Envelopes};
+
+    fn envelope(
+        id: &str,
+        date: DateTime<FixedOffset>,
+        saved_at: Option<DateTime<FixedOffset>>,
+    ) -> Envelope {
+        Envelope {
+            id: id.to_owned(),
+            date,
+            saved_at,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn expired_ids() {
+        let offset = FixedOffset::east_opt(0).unwrap();
+        let date = |m| offset.with_ymd_and_hms(2024, m, 1, 0, 0, 0).unwrap();
+
+        let envelopes = Envelopes::from_iter([
+            envelope("1", date(1), Some(date(1))),
+            envelope("2", date(3), Some(date(3))),
+            envelope("3", DateTime::default(), Some(date(1))),
+            envelope("4", date(1), None),
+        ]);
+
+        assert_eq!(find_expired_ids(&envelopes, &date(2)), vec!["1", "3"]);
+    }
+
+    #[test]
+    fn old_message_trashed_recently_is_kept() {
+        let offset = FixedOffset::east_opt(0).unwrap();
+        let sent = offset.with_ymd_and_hms(2019, 6, 1, 0, 0, 0).unwrap();
+        let trashed = offset.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap();
+        let cutoff = offset.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap();
+
+        let envelopes = Envelopes::from_iter([envelope("1", sent, Some(trashed))]);
+
+        assert!(find_expired_ids(&envelopes, &cutoff).is_empty());
+    }
+
+    #[cfg(feature = "memory")]
+    #[tokio::test]
+    async fn purge_memory_trash() {
+        use std::{sync::Arc, time::Duration};
+
+        use chrono::Utc;
+        use mail_builder::MessageBuilder;
+
+        use super::PurgeExpiredMessages;
+        use crate::{
+            account::config::AccountConfig,
+            backend::BackendBuilder,
+            date::{ClockSource, FixedClock},
+            envelope::{list::ListEnvelopes, Id},
+            flag::Flags,
+            folder::{add::AddFolder, TRASH},
+            memory::MemoryContextBuilder,
+            message::{add::AddMessage, r#move::MoveMessages},
+        };
+
+        let msg = |subject: &str| {
+            MessageBuilder::new()
+                .from("alice@localhost")
+                .to("bob@localhost")
+                .subject(subject)
+                .text_body(subject)
+                .write_to_vec()
+                .unwrap()
+        };
+
+        let config_at = |month| {
+            let now = Utc.with_ymd_and_hms(2024, month, 1, 0, 0, 0).unwrap();
+            let offset = FixedOffset::east_opt(0).unwrap();
+            Arc::new(AccountConfig {
+                clock: ClockSource::new(FixedClock::new(now, offset)),
+                ..Default::default()
+            })
+        };
+
+        // clones of the builder share the same store
+        let ctx = MemoryContextBuilder::new(config_at(1));
+        let january = BackendBuilder::new(config_at(1), ctx.clone())
+            .build()
+            .await
+            .unwrap();
+
+        january.add_folder(TRASH).await.unwrap();
+        january
+            .add_message_with_flags(TRASH, &msg("old"), &Flags::default())
+            .await
+            .unwrap();
+        let id = january
+            .add_message_with_flags("INBOX", &msg("moved"), &Flags::default())
+            .await
+            .unwrap();
+
+        let mut ctx = ctx.clone();
+        ctx.account_config = config_at(3);
+        let march = BackendBuilder::new(config_at(3), ctx)
+            .build()
+            .await
+            .unwrap();
+
+        march
+            .move_messages("INBOX", TRASH, &Id::from(id))
+            .await
+            .unwrap();
+        march
+            .add_message_with_flags(TRASH, &msg("recent"), &Flags::default())
+            .await
+            .unwrap();
+
+        let ids = march
+            .purge_trash(Duration::from_secs(30 * 24 * 3600))
+            .await
+            .unwrap();
+        assert_eq!(ids.len(), 1);
+
+        let envelopes = march
+            .list_envelopes(TRASH, Default::default())
+            .await
+            .unwrap();
+        let mut subjects: Vec<_> = envelopes.iter().map(|e| e.subject.as_str()).collect();
+        subjects.sort();
+        assert_eq!(subjects, ["moved", "recent"]);
+    }
+}
@@ -0,0 +1,333 @@
+//! # Delivery status notification
+//!
+//! Module dedicated to delivery status notifications (DSN), also
+//! known as bounces. A DSN is a `multipart/report` message whose
+//! report type is `delivery-status`, as defined in RFC 3464. See
+//! [`Message::delivery_report`] and [`FindBouncedMessage`].
+
+use async_trait::async_trait;
+use mail_parser::{MessageParser, MimeHeaders, PartType};
+use tracing::debug;
+
+use super::Message;
+use crate::{
+    email::error::Error,
+    envelope::{
+        list::{ListEnvelopes, ListEnvelopesOptions},
+        Envelope,
+    },
+    folder::SENT,
+    AnyResult,
+};
+
+/// The action performed by the reporting MTA for a recipient.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum DeliveryAction {
+    /// The message could not be delivered to the recipient.
+    Failed,
+
+    /// The delivery is delayed, but the MTA will keep trying.
+    Delayed,
+
+    /// The message was successfully delivered.
+    Delivered,
+
+    /// The message was relayed to a system which does not send DSNs.
+    Relayed,
+
+    /// The message was delivered and forwarded to other recipients.
+    Expanded,
+}
+
+impl DeliveryAction {
+    fn parse(action: &str) -> Option<Self> {
+        match action.trim().to_ascii_lowercase().as_str() {
+            "failed" => Some(Self::Failed),
+            "delayed" => Some(Self::Delayed),
+            "delivered" => Some(Self::Delivered),
+            "relayed" => Some(Self::Relayed),
+            "expanded" => Some(Self::Expanded),
+            _ => None,
+        }
+    }
+}
+
+/// The delivery status of a recipient.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DeliveryStatus {
+    /// The address of the recipient, from the `Final-Recipient`
+    /// field.
+    pub recipient: String,
+
+    /// The action performed for the recipient.
+    pub action: DeliveryAction,
+
+    /// The enhanced status code, for example `5.1.1`.
+    pub status: String,
+
+    /// The diagnostic of the remote server, usually its SMTP reply.
+    pub diagnostic: Option<String>,
+}
+
+impl DeliveryStatus {
+    /// Return `true` if the message could not be delivered to the
+    /// recipient.
+    pub fn is_failed(&self) -> bool {
+        self.action == DeliveryAction::Failed
+    }
+}
+
+/// The content of a delivery status notification.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DeliveryReport {
+    /// The MTA which generated the report.
+    pub reporting_mta: Option<String>,
+
+    /// The Message-ID of the message the report is about, including
+    /// angle brackets like [`Envelope::message_id`].
+    pub original_message_id: Option<String>,
+
+    /// The delivery status of each recipient.
+    pub recipients: Vec<DeliveryStatus>,
+}
+
+impl DeliveryReport {
+    /// Return the recipients the message could not be delivered to.
+    pub fn failed_recipients(&self) -> impl Iterator<Item = &DeliveryStatus> {
+        self.recipients.iter().filter(|r| r.is_failed())
+    }
+
+    /// Find the envelope of the message the report is about among
+    /// the given envelopes.
+    pub fn find_original<'a>(&self, envelopes: &'a [Envelope]) -> Option<&'a Envelope> {
+        let id = self.original_message_id.as_ref()?;
+        envelopes.iter().find(|e| &e.message_id == id)
+    }
+}
+
+impl Message<'_> {
+    /// Parse the delivery status notification of the message.
+    ///
+    /// Returns `None` if the message is not a delivery status
+    /// notification.
+    pub fn delivery_report(&self) -> Result<Option<DeliveryReport>, Error> {
+        let msg = self.parsed()?;
+
+        let is_report = msg.content_type().is_some_and(|ctype| {
+            ctype.ctype().eq_ignore_ascii_case("multipart")
+                && ctype
+                    .subtype()
+                    .is_some_and(|stype| stype.eq_ignore_ascii_case("report"))
+                && ctype
+                    .attribute("report-type")
+                    .is_some_and(|rtype| rtype.eq_ignore_ascii_case("delivery-status"))
+        });
+
+        if !is_report {
+            return Ok(None);
+        }
+
+        let mut report = DeliveryReport::default();
+
+        for part in msg.parts.iter().skip(1) {
+            let Some(ctype) = part.content_type() else {
+                continue;
+            };
+
+            let mtype = format!("{}/{}", ctype.ctype(), ctype.subtype().unwrap_or_default());
+
+            match mtype.to_ascii_lowercase().as_str() {
+                "message/delivery-status" | "message/global-delivery-status" => {
+                    let fields = String::from_utf8_lossy(part.contents());
+                    parse_delivery_status(&fields, &mut report);
+                }
+                "message/rfc822" | "message/global" => {
+                    if let PartType::Message(msg) = &part.body {
+                        report.original_message_id = msg.message_id().map(|id| format!("<{id}>"));
+                    }
+                }
+                "text/rfc822-headers" => {
+                    let headers = MessageParser::new().parse_headers(part.contents());
+                    report.original_message_id =
+                        headers.and_then(|msg| msg.message_id().map(|id| format!("<{id}>")));
+                }
+                _ => (),
+            }
+        }
+
+        debug!(?report, "parsed delivery status notification");
+
+        Ok(Some(report))
+    }
+}
+
+/// Parse the fields of a `message/delivery-status` part.
+///
+/// The first block holds the per-message fields, the following ones
+/// the per-recipient fields. Blocks are separated by empty lines.
+fn parse_delivery_status(fields: &str, report: &mut DeliveryReport) {
+    let mut blocks: Vec<Vec<(String, String)>> = vec![Vec::new()];
+
+    for line in fields.lines() {
+        let block = blocks.last_mut().unwrap();
+
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                blocks.push(Vec::new());
+            }
+        } else if line.starts_with([' ', '\t']) {
+            // folded line
+            if let Some((_, value)) = block.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            block.push((name.trim().to_ascii_lowercase(), value.trim().to_owned()));
+        }
+    }
+
+    let mut blocks = blocks.into_iter().filter(|block| !block.is_empty());
+
+    if let Some(block) = blocks.next() {
+        report.reporting_mta = find_field(&block, "reporting-mta").map(strip_type);
+    }
+
+    for block in blocks {
+        let recipient = find_field(&block, "final-recipient").map(strip_type);
+        let action = find_field(&block, "action").and_then(DeliveryAction::parse);
+
+        let (Some(recipient), Some(action)) = (recipient, action) else {
+            debug!(
+                ?block,
+                "invalid delivery status recipient fields, skipping them"
+            );
+            continue;
+        };
+
+        report.recipients.push(DeliveryStatus {
+            recipient,
+            action,
+            status: find_field(&block, "status").unwrap_or_default().to_owned(),
+            diagnostic: find_field(&block, "diagnostic-code").map(strip_type),
+        });
+    }
+}
+
+/// Find the value of the given field in the given block.
+fn find_field<'a>(block: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    block
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Strip the type prefix of a field value, like `rfc822;` or
+/// `smtp;`.
+fn strip_type(value: &str) -> String {
+    match value.split_once(';') {
+        Some((_, value)) => value.trim().to_owned(),
+        None => value.trim().to_owned(),
+    }
+}
+
+#[async_trait]
+pub trait FindBouncedMessage: ListEnvelopes {
+    /// Find the envelope of the sent message the given report is
+    /// about, so that clients can show which message could not be
+    /// delivered.
+    async fn find_bounced_envelope(&self, report: &DeliveryReport) -> AnyResult<Option<Envelope>> {
+        if report.original_message_id.is_none() {
+            return Ok(None);
+        }
+
+        let envelopes = self
+            .list_envelopes(SENT, ListEnvelopesOptions::default())
+            .await?;
+
+        Ok(report.find_original(&envelopes).cloned())
+    }
+}
+
+impl<T: ListEnvelopes> FindBouncedMessage for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::{DeliveryAction, DeliveryStatus};
+    use crate::{envelope::Envelope, message::Message};
+
+    #[test]
+    fn delivery_report() {
+        let raw = concat!(
+            "From: MAILER-DAEMON@localhost\r\n",
+            "Subject: Undelivered Mail Returned to Sender\r\n",
+            "Content-Type: multipart/report; report-type=delivery-status;\r\n",
+            " boundary=\"bounce\"\r\n",
+            "\r\n",
+            "--bounce\r\n",
+            "Content-Type: text/plain\r\n",
+            "\r\n",
+            "Your message could not be delivered.\r\n",
+            "--bounce\r\n",
+            "Content-Type: message/delivery-status\r\n",
+            "\r\n",
+            "Reporting-MTA: dns; mx.localhost\r\n",
+            "\r\n",
+            "Final-Recipient: rfc822; unknown@localhost\r\n",
+            "Action: failed\r\n",
+            "Status: 5.1.1\r\n",
+            "Diagnostic-Code: smtp; 550 5.1.1 <unknown@localhost>:\r\n",
+            " Recipient address rejected\r\n",
+            "\r\n",
+            "Final-Recipient: rfc822; slow@localhost\r\n",
+            "Action: delayed\r\n",
+            "Status: 4.4.1\r\n",
+            "\r\n",
+            "--bounce\r\n",
+            "Content-Type: text/rfc822-headers\r\n",
+            "\r\n",
+            "Message-ID: <sent@localhost>\r\n",
+            "Subject: Hello\r\n",
+            "--bounce--\r\n",
+        );
+
+        let report = Message::from(raw).delivery_report().unwrap().unwrap();
+
+        assert_eq!(report.reporting_mta.as_deref(), Some("mx.localhost"));
+        assert_eq!(
+            report.original_message_id.as_deref(),
+            Some("<sent@localhost>")
+        );
+
+        let failed: Vec<_> = report.failed_recipients().collect();
+        assert_eq!(
+            failed,
+            vec![&DeliveryStatus {
+                recipient: "unknown@localhost".into(),
+                action: DeliveryAction::Failed,
+                status: "5.1.1".into(),
+                diagnostic: Some(
+                    "550 5.1.1 <unknown@localhost>: Recipient address rejected".into()
+                ),
+            }]
+        );
+        assert_eq!(report.recipients[1].action, DeliveryAction::Delayed);
+
+        let envelopes = [
+            Envelope {
+                id: "1".into(),
+                message_id: "<other@localhost>".into(),
+                ..Default::default()
+            },
+            Envelope {
+                id: "2".into(),
+                message_id: "<sent@localhost>".into(),
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(report.find_original(&envelopes).unwrap().id, "2");
+
+        let raw = "Subject: Hello\r\n\r\nWorld\r\n";
+        assert_eq!(Message::from(raw).delivery_report().unwrap(), None);
+    }
+}
@@ -14,4 +14,13 @@ pub struct MessageReadConfig {
     /// Define the text/plain format as defined in the [RFC
     /// 2646](https://www.ietf.org/rfc/rfc2646.txt).
     pub format: Option<EmailTextPlainFormat>,
+
+    /// Define the fallback charsets used to decode legacy messages.
+    ///
+    /// Text parts and text headers whose bytes do not decode in
+    /// their declared charset (or in UTF-8 when none is declared)
+    /// are decoded using the first fallback charset matching their
+    /// content. When none matches, the charset is guessed from the
+    /// content.
+    pub charset_fallbacks: Option<Vec<String>>,
 }
@@ -30,7 +30,7 @@ impl GetMessages for GetImapMessages {
     async fn get_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         info!("getting messages {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
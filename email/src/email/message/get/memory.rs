@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+
+use super::{DefaultGetMessages, GetMessages, Messages};
+use crate::{
+    envelope::Id,
+    flag::{
+        add::{memory::AddMemoryFlags, AddFlags},
+        Flags,
+    },
+    memory::MemoryContextSync,
+    message::peek::{memory::PeekMemoryMessages, PeekMessages},
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct GetMemoryMessages {
+    peek_messages: PeekMemoryMessages,
+    add_flags: AddMemoryFlags,
+}
+
+impl GetMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self {
+            peek_messages: PeekMemoryMessages::new(ctx),
+            add_flags: AddMemoryFlags::new(ctx),
+        }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn GetMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn GetMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl PeekMessages for GetMemoryMessages {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        self.peek_messages.peek_messages(folder, id).await
+    }
+}
+
+#[async_trait]
+impl AddFlags for GetMemoryMessages {
+    async fn add_flags(&self, folder: &str, id: &Id, flags: &Flags) -> AnyResult<()> {
+        self.add_flags.add_flags(folder, id, flags).await
+    }
+}
+
+#[async_trait]
+impl DefaultGetMessages for GetMemoryMessages {}
@@ -0,0 +1,32 @@
+//! # Markdown
+//!
+//! Module dedicated to markdown message bodies. A markdown body is
+//! sent as a `multipart/alternative` message: the markdown source,
+//! which is already readable as is, becomes the plain text part, and
+//! the [`MarkdownRenderer`] produces the HTML part.
+//!
+//! Rendering is shared with the MML compiler, which renders
+//! `<#part type=text/markdown>` parts the same way, see
+//! [`AccountConfig::generate_tpl_compiler`].
+//!
+//! [`AccountConfig::generate_tpl_compiler`]: crate::account::config::AccountConfig::generate_tpl_compiler
+
+#[doc(inline)]
+pub use mml::markdown::{MarkdownRenderer, ALLOWED_URL_SCHEMES};
+
+/// The markdown configuration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct MarkdownConfig {
+    /// The stylesheet applied to the rendered HTML part.
+    ///
+    /// Since most email clients ignore `<style>` elements, rules are
+    /// inlined into the `style` attribute of the matching
+    /// elements. Only type selectors are supported, for example
+    /// `p`, `h1, h2` or `code`.
+    pub css: Option<String>,
+}
@@ -0,0 +1,259 @@
+//! # MIME tree
+//!
+//! Module dedicated to the inspection of the MIME structure of a
+//! message, see [`Message::mime_tree`](super::Message::mime_tree).
+//! Bodies are never decoded: only headers and offsets of parts are
+//! read, so that clients can display the structure of a message and
+//! download the parts they need using the [`PartPath`] of each part.
+
+use std::num::NonZeroU32;
+
+use mail_parser::{MessagePart, MimeHeaders, PartType};
+
+use super::attachment::PartPath;
+
+/// The MIME part of a message, with its sub-parts.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MimePart {
+    /// The path of the part, usable to retrieve it.
+    ///
+    /// The root part of a multipart message has no path, since it
+    /// represents the whole message. Parts containing a message
+    /// (`message/rfc822`) share their path with the root part of the
+    /// message they contain, as defined in RFC 3501 section 6.4.5.
+    pub path: Option<PartPath>,
+
+    /// The MIME type of the part, in lower case, for example
+    /// `text/plain` or `multipart/mixed`.
+    ///
+    /// Defaults to `text/plain` when the part has no Content-Type.
+    pub content_type: String,
+
+    /// The parameters of the Content-Type header, for example the
+    /// `charset` or the multipart `boundary`.
+    pub content_type_params: Vec<(String, String)>,
+
+    /// The disposition of the part, in lower case, for example
+    /// `inline` or `attachment`.
+    pub disposition: Option<String>,
+
+    /// The file name of the part, taken from the Content-Disposition
+    /// or the Content-Type header.
+    pub filename: Option<String>,
+
+    /// The Content-ID of the part.
+    pub content_id: Option<String>,
+
+    /// The Content-Transfer-Encoding of the part.
+    pub encoding: Option<String>,
+
+    /// The size of the encoded body of the part, in bytes.
+    pub size: usize,
+
+    /// The raw headers of the part, in order.
+    pub headers: Vec<(String, String)>,
+
+    /// The sub-parts of the part.
+    pub children: Vec<MimePart>,
+}
+
+impl MimePart {
+    /// Build the MIME tree of the given parsed message.
+    pub(crate) fn from_parsed(msg: &mail_parser::Message<'_>) -> Self {
+        from_message(msg, &[])
+    }
+
+    /// Return `true` if the part is a multipart container.
+    pub fn is_multipart(&self) -> bool {
+        self.content_type.starts_with("multipart/")
+    }
+
+    /// Return `true` if the part is an attachment.
+    pub fn is_attachment(&self) -> bool {
+        self.disposition.as_deref() == Some("attachment")
+    }
+
+    /// Iterate over the part and all its sub-parts, depth first.
+    pub fn iter(&self) -> impl Iterator<Item = &MimePart> {
+        let mut stack = vec![self];
+
+        std::iter::from_fn(move || {
+            let part = stack.pop()?;
+            stack.extend(part.children.iter().rev());
+            Some(part)
+        })
+    }
+
+    /// Find the part matching the given path.
+    pub fn find(&self, path: &PartPath) -> Option<&MimePart> {
+        self.iter().find(|part| part.path.as_ref() == Some(path))
+    }
+}
+
+/// Build the MIME tree of the given message, contained by the part
+/// at the given path (empty for the top-level message).
+fn from_message(msg: &mail_parser::Message<'_>, path: &[NonZeroU32]) -> MimePart {
+    let root = msg.root_part();
+
+    match root.body {
+        PartType::Multipart(_) if path.is_empty() => from_part(msg, root, None, path),
+        PartType::Multipart(_) => from_part(msg, root, Some(path.to_vec().into()), path),
+        _ => {
+            let path = child_path(path, 0);
+            from_part(msg, root, Some(path.clone().into()), &path)
+        }
+    }
+}
+
+fn from_part(
+    msg: &mail_parser::Message<'_>,
+    part: &MessagePart<'_>,
+    part_path: Option<PartPath>,
+    path: &[NonZeroU32],
+) -> MimePart {
+    let raw = msg.raw_message();
+
+    let (content_type, content_type_params) = match part.content_type() {
+        Some(ctype) => {
+            let mime = match ctype.subtype() {
+                Some(subtype) => format!("{}/{subtype}", ctype.ctype()),
+                None => ctype.ctype().to_owned(),
+            };
+            let params = ctype
+                .attributes()
+                .unwrap_or_default()
+                .iter()
+                .map(|(key, val)| (key.to_string(), val.to_string()))
+                .collect();
+            (mime.to_lowercase(), params)
+        }
+        None => (String::from("text/plain"), Vec::new()),
+    };
+
+    let headers = part
+        .headers
+        .iter()
+        .map(|header| {
+            let val = raw
+                .get(header.offset_start..header.offset_end)
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+            (header.name.as_str().to_owned(), val.trim().to_owned())
+        })
+        .collect();
+
+    let children = match &part.body {
+        PartType::Multipart(ids) => ids
+            .iter()
+            .enumerate()
+            .filter_map(|(i, id)| {
+                let child = msg.parts.get(*id)?;
+                let path = child_path(path, i);
+                Some(from_part(msg, child, Some(path.clone().into()), &path))
+            })
+            .collect(),
+        PartType::Message(inner) => vec![from_message(inner, path)],
+        _ => Vec::new(),
+    };
+
+    MimePart {
+        path: part_path,
+        content_type,
+        content_type_params,
+        disposition: part
+            .content_disposition()
+            .map(|disposition| disposition.ctype().to_lowercase()),
+        filename: part.attachment_name().map(ToOwned::to_owned),
+        content_id: part.content_id().map(ToOwned::to_owned),
+        encoding: part.content_transfer_encoding().map(ToOwned::to_owned),
+        size: part.offset_end.saturating_sub(part.offset_body),
+        headers,
+        children,
+    }
+}
+
+/// Return the path of the sub-part at the given 0-based index.
+fn child_path(path: &[NonZeroU32], index: usize) -> Vec<NonZeroU32> {
+    let n = NonZeroU32::new(index as u32 + 1).unwrap_or(NonZeroU32::MIN);
+    path.iter().copied().chain([n]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::message::Message;
+
+    #[test]
+    fn mime_tree() {
+        let raw = concat!(
+            "Subject: tree\r\n",
+            "Content-Type: multipart/mixed; boundary=\"outer\"\r\n",
+            "\r\n",
+            "--outer\r\n",
+            "Content-Type: multipart/alternative; boundary=\"inner\"\r\n",
+            "\r\n",
+            "--inner\r\n",
+            "Content-Type: text/plain; charset=utf-8\r\n",
+            "\r\n",
+            "Hello\r\n",
+            "--inner\r\n",
+            "Content-Type: text/html\r\n",
+            "\r\n",
+            "<p>Hello</p>\r\n",
+            "--inner--\r\n",
+            "--outer\r\n",
+            "Content-Type: message/rfc822\r\n",
+            "\r\n",
+            "Subject: forwarded\r\n",
+            "\r\n",
+            "Forwarded\r\n",
+            "--outer\r\n",
+            "Content-Type: application/pdf\r\n",
+            "Content-Disposition: attachment; filename=\"doc.pdf\"\r\n",
+            "Content-Transfer-Encoding: base64\r\n",
+            "\r\n",
+            "JVBERi0=\r\n",
+            "--outer--\r\n",
+        );
+
+        let msg = Message::from(raw);
+        let tree = msg.mime_tree().unwrap();
+
+        assert!(tree.is_multipart());
+        assert_eq!(tree.path, None);
+        assert_eq!(tree.headers[0], ("Subject".into(), "tree".into()));
+
+        let parts: Vec<_> = tree
+            .iter()
+            .map(|part| {
+                let path = part.path.as_ref().map(ToString::to_string);
+                (path.unwrap_or_default(), part.content_type.as_str())
+            })
+            .collect();
+
+        assert_eq!(
+            parts,
+            vec![
+                (String::new(), "multipart/mixed"),
+                ("1".into(), "multipart/alternative"),
+                ("1.1".into(), "text/plain"),
+                ("1.2".into(), "text/html"),
+                ("2".into(), "message/rfc822"),
+                ("2.1".into(), "text/plain"),
+                ("3".into(), "application/pdf"),
+            ]
+        );
+
+        let pdf = tree.find(&"3".parse().unwrap()).unwrap();
+        assert!(pdf.is_attachment());
+        assert_eq!(pdf.filename.as_deref(), Some("doc.pdf"));
+        assert_eq!(pdf.encoding.as_deref(), Some("base64"));
+        assert_eq!(pdf.size, "JVBERi0=".len());
+
+        let plain = tree.find(&"1.1".parse().unwrap()).unwrap();
+        assert_eq!(
+            plain.content_type_params,
+            vec![("charset".into(), "utf-8".into())]
+        );
+        assert_eq!(msg.get_part(&"2.1".parse().unwrap()).unwrap(), b"Forwarded");
+    }
+}
@@ -8,16 +8,23 @@
 
 pub mod add;
 pub mod attachment;
+pub mod compose;
 pub mod config;
 pub mod copy;
 pub mod delete;
+pub mod dsn;
 pub mod get;
 #[cfg(feature = "imap")]
 pub mod imap;
+#[cfg(feature = "markdown")]
+pub mod markdown;
+pub mod mime_tree;
 pub mod r#move;
+pub mod open;
 pub mod peek;
 pub mod remove;
 pub mod send;
+pub mod size;
 #[cfg(feature = "sync")]
 pub mod sync;
 pub mod template;
@@ -42,9 +49,12 @@
 use uuid::Uuid;
 
 use self::{
-    attachment::Attachment,
+    attachment::{Attachment, AttachmentStore, PartPath, SharedAttachment},
+    mime_tree::MimePart,
     template::{
-        forward::ForwardTemplateBuilder, new::NewTemplateBuilder, reply::ReplyTemplateBuilder,
+        forward::{ForwardAsAttachmentTemplateBuilder, ForwardTemplateBuilder},
+        new::NewTemplateBuilder,
+        reply::ReplyTemplateBuilder,
     },
 };
 use crate::{account::config::AccountConfig, email::error::Error};
@@ -248,6 +258,44 @@ pub fn attachments(&self) -> Result<Vec<Attachment>, Error> {
             .collect())
     }
 
+    /// Returns the list of message attachments, sharing their
+    /// content with identical attachments of the given store.
+    pub fn shared_attachments(
+        &self,
+        store: &AttachmentStore,
+    ) -> Result<Vec<SharedAttachment>, Error> {
+        Ok(self
+            .parsed()?
+            .attachments()
+            .map(|part| {
+                let (hash, body) = store.share(part.contents());
+                SharedAttachment {
+                    filename: part.attachment_name().map(ToOwned::to_owned),
+                    mime: tree_magic_mini::from_u8(&body).to_owned(),
+                    hash,
+                    body,
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the decoded content of the MIME part at the given
+    /// path.
+    pub fn get_part(&self, path: &PartPath) -> Result<Vec<u8>, Error> {
+        let part = attachment::find_part(self.parsed()?, path.as_slice())
+            .ok_or_else(|| Error::GetMessagePartNotFoundError(path.to_string()))?;
+        Ok(part.contents().to_owned())
+    }
+
+    /// Returns the MIME structure of the message.
+    ///
+    /// Bodies are not decoded. The path of each part can be used to
+    /// retrieve it, see [`Message::get_part`] and
+    /// [`GetAttachment`](attachment::GetAttachment).
+    pub fn mime_tree(&self) -> Result<MimePart, Error> {
+        Ok(MimePart::from_parsed(self.parsed()?))
+    }
+
     /// Creates a new template builder from an account configuration.
     pub fn new_tpl_builder(config: Arc<AccountConfig>) -> NewTemplateBuilder {
         NewTemplateBuilder::new(config)
@@ -285,6 +333,15 @@ pub fn to_reply_tpl_builder(&self, config: Arc<AccountConfig>) -> ReplyTemplateB
     pub fn to_forward_tpl_builder(&self, config: Arc<AccountConfig>) -> ForwardTemplateBuilder {
         ForwardTemplateBuilder::new(self, config)
     }
+
+    /// Turns the current message into a forward as attachment
+    /// template builder.
+    pub fn to_forward_as_attachment_tpl_builder(
+        &self,
+        config: Arc<AccountConfig>,
+    ) -> ForwardAsAttachmentTemplateBuilder {
+        ForwardAsAttachmentTemplateBuilder::new(self, config)
+    }
 }
 
 impl<'a> From<Vec<u8>> for Message<'a> {
@@ -330,8 +387,7 @@ enum RawMessages {
     Imap(Vec<Vec1<MessageDataItem<'static>>>),
     #[cfg(feature = "maildir")]
     MailEntries(Vec<MaildirEntry>),
-    #[cfg(feature = "notmuch")]
-    Notmuch(Vec<Vec<u8>>),
+    Raw(Vec<Vec<u8>>),
     #[allow(dead_code)]
     None,
 }
@@ -361,8 +417,7 @@ fn emails_builder<'a>(raw: &'a mut RawMessages) -> Vec<Message<'a>> {
                 .collect(),
             #[cfg(feature = "maildir")]
             RawMessages::MailEntries(entries) => entries.iter_mut().map(Message::from).collect(),
-            #[cfg(feature = "notmuch")]
-            RawMessages::Notmuch(raw) => raw
+            RawMessages::Raw(raw) => raw
                 .iter()
                 .map(|raw| Message::from(raw.as_slice()))
                 .collect(),
@@ -377,6 +432,21 @@ pub fn first(&self) -> Option<&Message> {
     pub fn to_vec(&self) -> Vec<&Message> {
         self.borrow_emails().iter().collect()
     }
+
+    /// Returns the attachments of every message, identical
+    /// attachments sharing the same content.
+    ///
+    /// This is useful when fetching a whole thread, where the same
+    /// images (logos, footers etc) are attached to most messages.
+    pub fn shared_attachments(
+        &self,
+        store: &AttachmentStore,
+    ) -> Result<Vec<Vec<SharedAttachment>>, Error> {
+        self.borrow_emails()
+            .iter()
+            .map(|msg| msg.shared_attachments(store))
+            .collect()
+    }
 }
 
 #[cfg(feature = "imap")]
@@ -407,11 +477,10 @@ fn try_from(entries: Vec<MaildirEntry>) -> Result<Self, Error> {
     }
 }
 
-#[cfg(feature = "notmuch")]
 impl From<Vec<Vec<u8>>> for Messages {
     fn from(raw: Vec<Vec<u8>>) -> Self {
         MessagesBuilder {
-            raw: RawMessages::Notmuch(raw),
+            raw: RawMessages::Raw(raw),
             emails_builder: Messages::emails_builder,
         }
         .build()
@@ -426,10 +495,53 @@ mod tests {
 
     use crate::{
         account::config::AccountConfig,
-        message::{config::MessageConfig, get::config::MessageReadConfig, Message},
+        message::{
+            attachment::AttachmentStore, config::MessageConfig, get::config::MessageReadConfig,
+            Message, Messages,
+        },
         template::Template,
     };
 
+    #[test]
+    fn shared_attachments() {
+        let msg = |body: &str| {
+            format!(
+                concat_line!(
+                    "Content-Type: multipart/mixed; boundary=\"b\"\r",
+                    "\r",
+                    "--b\r",
+                    "Content-Type: text/plain\r",
+                    "\r",
+                    "{}\r",
+                    "--b\r",
+                    "Content-Type: image/png\r",
+                    "Content-Disposition: attachment; filename=\"logo.png\"\r",
+                    "Content-Transfer-Encoding: base64\r",
+                    "\r",
+                    "bG9nbw==\r",
+                    "--b--\r",
+                    "",
+                ),
+                body
+            )
+            .into_bytes()
+        };
+
+        let msgs = Messages::from(vec![msg("first"), msg("second")]);
+        let store = AttachmentStore::new();
+        let attachments = msgs.shared_attachments(&store).unwrap();
+
+        let [first, second] = attachments.as_slice() else {
+            panic!("expected 2 messages");
+        };
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].filename.as_deref(), Some("logo.png"));
+        assert_eq!(first[0].body.as_ref(), b"logo");
+        assert!(first[0].shares_body_with(&second[0]));
+        assert_eq!(store.len(), 1);
+    }
+
     #[tokio::test]
     async fn to_read_tpl() {
         let config = AccountConfig::default();
@@ -4,7 +4,11 @@
 use utf7_imap::encode_utf7_imap as encode_utf7;
 
 use super::MoveMessages;
-use crate::{envelope::Id, imap::ImapContext, AnyResult};
+use crate::{
+    envelope::{Id, IdMapping},
+    imap::ImapContext,
+    AnyResult,
+};
 
 #[derive(Clone, Debug)]
 pub struct MoveImapMessages {
@@ -28,9 +32,20 @@ pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn MoveMessages>> {
 #[async_trait]
 impl MoveMessages for MoveImapMessages {
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        self.move_messages_with_mapping(from_folder, to_folder, id)
+            .await?;
+        Ok(())
+    }
+
+    async fn move_messages_with_mapping(
+        &self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+    ) -> AnyResult<IdMapping> {
         info!("moving imap messages {id} from folder {from_folder} to folder {to_folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let from_folder = config.get_folder_alias(from_folder);
@@ -52,8 +67,12 @@ async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         };
 
         client.select_mailbox(&from_folder_encoded).await?;
-        client.move_messages(uids, &to_folder_encoded).await?;
+        let uids = client.move_messages(uids, &to_folder_encoded).await?;
+        debug!(?uids, "moved messages uids");
 
-        Ok(())
+        Ok(uids
+            .into_iter()
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect())
     }
 }
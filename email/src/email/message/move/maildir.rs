@@ -2,7 +2,12 @@
 use tracing::info;
 
 use super::MoveMessages;
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::Id,
+    maildir::{self, entry::MaildirEntryExt, find_entries, AsyncMaildir, MaildirContextSync},
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct MoveMaildirMessages {
@@ -29,22 +34,31 @@ async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> An
         info!("moving maildir messages {id} from folder {from_folder} to folder {to_folder}");
 
         let ctx = self.ctx.lock().await;
-        let from_mdir = ctx.get_maildir_from_folder_alias(from_folder)?;
+        let from_mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(from_folder)?);
         let to_mdir = ctx.get_maildir_from_folder_alias(to_folder)?;
 
-        id.iter()
-            .filter_map(|id| from_mdir.find(id).ok().flatten())
-            .try_for_each(|entry| {
-                entry.r#move(&to_mdir).map_err(|err| {
-                    Error::MoveMessagesMaildirError(
-                        err,
-                        from_folder.to_owned(),
-                        to_folder.to_owned(),
-                        entry.path().to_owned(),
-                    )
-                })?;
-                AnyResult::Ok(())
-            })?;
+        let ids: Vec<String> = id.iter().map(ToOwned::to_owned).collect();
+        let (from_folder, to_folder) = (from_folder.to_owned(), to_folder.to_owned());
+
+        from_mdir
+            .run(move |from_mdir| {
+                let mut entries =
+                    find_entries(&from_mdir, &ids).map_err(Error::ListMaildirEntriesError)?;
+
+                ids.iter()
+                    .filter_map(|id| entries.remove(id))
+                    .try_for_each(|entry| match entry.move_to(&to_mdir) {
+                        Ok(_) | Err(maildir::Error::MoveEntryToSameFolderError(_)) => Ok(()),
+                        Err(err) => Err(Error::MoveMessagesMaildirError(
+                            err,
+                            from_folder.clone(),
+                            to_folder.clone(),
+                            entry.path().to_owned(),
+                        )),
+                    })
+            })
+            .await
+            .map_err(Error::ListMaildirEntriesError)??;
 
         Ok(())
     }
@@ -0,0 +1,42 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::MoveMessages;
+use crate::{envelope::Id, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct MoveMemoryMessages {
+    ctx: MemoryContextSync,
+}
+
+impl MoveMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn MoveMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn MoveMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl MoveMessages for MoveMemoryMessages {
+    async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()> {
+        info!("moving memory messages {id} from folder {from_folder} to folder {to_folder}");
+
+        let config = &self.ctx.account_config;
+        let from_folder = config.get_folder_alias(from_folder);
+        let to_folder = config.get_folder_alias(to_folder);
+        let now = config.clock.now().fixed_offset();
+
+        self.ctx
+            .lock()
+            .move_messages(&from_folder, &to_folder, id, now)?;
+
+        Ok(())
+    }
+}
@@ -2,16 +2,38 @@
 pub mod imap;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 
 use async_trait::async_trait;
 
-use crate::{envelope::Id, AnyResult};
+use crate::{
+    envelope::{Id, IdMapping},
+    AnyResult,
+};
 
 #[async_trait]
 pub trait MoveMessages: Send + Sync {
     /// Move emails from the given folder to the given folder matching
     /// the given id.
     async fn move_messages(&self, from_folder: &str, to_folder: &str, id: &Id) -> AnyResult<()>;
+
+    /// Move emails from the given folder to the given folder matching
+    /// the given id, and return the identifiers of the moved emails
+    /// indexed by the given identifiers.
+    ///
+    /// The default implementation returns an empty mapping. Backends
+    /// able to tell the identifiers of the moved emails should
+    /// override it.
+    async fn move_messages_with_mapping(
+        &self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+    ) -> AnyResult<IdMapping> {
+        self.move_messages(from_folder, to_folder, id).await?;
+        Ok(IdMapping::new())
+    }
 }
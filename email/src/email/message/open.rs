@@ -0,0 +1,192 @@
+//! # Open
+//!
+//! Module dedicated to opening attachments and message previews with
+//! the platform opener (`xdg-open`, `open` or `start`).
+//!
+//! Files are written in a private directory of the system temporary
+//! directory, readable by the current user only, so that other users
+//! cannot read or replace them before the opener reads them.
+
+use std::{
+    env::temp_dir,
+    fs::{self, DirBuilder, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use process::Command;
+use tracing::debug;
+use uuid::Uuid;
+
+use super::{attachment::Attachment, Message};
+use crate::{account::config::rename_file_if_duplicate, email::error::Error};
+
+/// The name of the private directory, inside the system temporary
+/// directory.
+const TMP_DIR_NAME: &str = "email-lib";
+
+impl Attachment {
+    /// Write the attachment in a private temporary directory, then
+    /// open it with the platform opener.
+    ///
+    /// Returns the path of the written file.
+    pub async fn open(&self) -> Result<PathBuf, Error> {
+        let dir = create_private_dir(private_tmp_dir())?;
+
+        let name = match &self.filename {
+            Some(name) => PathBuf::from(name),
+            None => {
+                let exts = mime_guess::get_mime_extensions_str(&self.mime);
+                let ext = *exts.and_then(|exts| exts.first()).unwrap_or(&"bin");
+                PathBuf::from(Uuid::new_v4().to_string()).with_extension(ext)
+            }
+        };
+
+        // only keep the file name, so that attachment names cannot
+        // escape the private directory
+        let name = name
+            .file_name()
+            .ok_or_else(|| Error::GetAttachmentFileNameError(name.clone()))?;
+        let path = rename_file_if_duplicate(&dir.join(name), |path, _| path.exists())?;
+
+        write_private_file(&path, &self.body)?;
+        open(&path).await?;
+
+        Ok(path)
+    }
+}
+
+impl Message<'_> {
+    /// Download the parts of the message in a private temporary
+    /// directory, then open the HTML preview with the platform
+    /// opener.
+    ///
+    /// Inline parts are downloaded next to the preview, so that they
+    /// are displayed. Falls back to the plain text preview when the
+    /// message has no HTML part.
+    ///
+    /// Returns the path of the opened preview.
+    pub async fn open_html_preview(&self) -> Result<PathBuf, Error> {
+        let dir = private_tmp_dir().join(Uuid::new_v4().to_string());
+        let dir = create_private_dir(dir)?;
+
+        self.download_parts(&dir)?;
+
+        let path = [dir.join("index.html"), dir.join("plain.txt")]
+            .into_iter()
+            .find(|path| path.is_file())
+            .ok_or(Error::GetMessagePreviewEmptyError)?;
+
+        open(&path).await?;
+
+        Ok(path)
+    }
+}
+
+/// Open the given path with the platform opener.
+///
+/// The output of the opener is piped, so that it does not mess up
+/// the terminal of interactive clients.
+pub async fn open(path: impl AsRef<Path>) -> Result<(), Error> {
+    let path = path.as_ref();
+    debug!(?path, "open file with platform opener");
+
+    opener(path)
+        .run()
+        .await
+        .map_err(|err| Error::OpenFileError(err, path.to_owned()))?;
+
+    Ok(())
+}
+
+/// Build the platform opener command for the given path.
+fn opener(path: &Path) -> Command {
+    let path = path.to_string_lossy();
+
+    if cfg!(windows) {
+        // the first quoted argument of start is the window title
+        Command::new(format!("start \"\" \"{path}\""))
+    } else {
+        let path = format!("'{}'", path.replace('\'', r"'\''"));
+
+        if cfg!(target_os = "macos") {
+            Command::new(format!("open {path}"))
+        } else {
+            Command::new(format!("xdg-open {path}"))
+        }
+    }
+}
+
+/// Return the private directory of the system temporary directory.
+fn private_tmp_dir() -> PathBuf {
+    temp_dir().join(TMP_DIR_NAME)
+}
+
+/// Create the given directory, readable by the current user only.
+fn create_private_dir(dir: PathBuf) -> Result<PathBuf, Error> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+    builder
+        .create(&dir)
+        .map_err(|err| Error::CreatePrivateDirError(err, dir.clone()))?;
+
+    // the directory may already exist with looser permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))
+            .map_err(|err| Error::CreatePrivateDirError(err, dir.clone()))?;
+    }
+
+    Ok(dir)
+}
+
+/// Write the given contents to a new file, readable by the current
+/// user only.
+///
+/// Fails if the file already exists.
+fn write_private_file(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    let mut opts = OpenOptions::new();
+    opts.write(true).create_new(true);
+
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut opts, 0o600);
+
+    opts.open(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|err| Error::WritePrivateFileError(err, path.to_owned()))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+    use super::{create_private_dir, opener, write_private_file};
+
+    #[test]
+    fn private_file() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("open");
+        let dir = create_private_dir(dir).unwrap();
+        let mode = fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        let path = dir.join("file.txt");
+        write_private_file(&path, b"content").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        // files are never overridden
+        assert!(write_private_file(&path, b"other").is_err());
+    }
+
+    #[test]
+    fn opener_quotes_path() {
+        let cmd = opener(Path::new("/tmp/it's.pdf"));
+        assert!(cmd.to_string().ends_with(r"'/tmp/it'\''s.pdf'"));
+    }
+}
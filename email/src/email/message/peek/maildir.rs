@@ -2,7 +2,11 @@
 use tracing::info;
 
 use super::{Messages, PeekMessages};
-use crate::{envelope::Id, maildir::MaildirContextSync, AnyResult, Error};
+use crate::{
+    envelope::Id,
+    maildir::{find_entries, AsyncMaildir, MaildirContextSync},
+    AnyResult, Error,
+};
 
 #[derive(Clone)]
 pub struct PeekMaildirMessages {
@@ -29,34 +33,24 @@ async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
         info!("peeking maildir messages {id} from folder {folder}");
 
         let ctx = self.ctx.lock().await;
-        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
-
-        let mut msgs: Vec<(usize, maildirs::MaildirEntry)> = mdir
-            .read()
-            .map_err(Error::ListMaildirEntriesError)?
-            .filter_map(|entry| {
-                let mut entry = (entry, String::new());
-                match entry.0.id() {
-                    Err(_) => None,
-                    Ok(id) => {
-                        entry.1 = id.to_owned();
-                        Some(entry)
-                    }
-                }
-            })
-            .filter_map(|(entry, entry_id)| {
-                id.iter()
-                    .position(|id| id == entry_id)
-                    .map(|pos| (pos, entry))
+        let mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(folder)?);
+        let ids: Vec<String> = id.iter().map(ToOwned::to_owned).collect();
+
+        let msgs = mdir
+            .run(move |mdir| -> AnyResult<Messages> {
+                let mut entries =
+                    find_entries(&mdir, &ids).map_err(Error::ListMaildirEntriesError)?;
+
+                let msgs: Messages = ids
+                    .iter()
+                    .filter_map(|id| entries.remove(id))
+                    .collect::<Vec<_>>()
+                    .try_into()?;
+
+                Ok(msgs)
             })
-            .collect();
-        msgs.sort_by_key(|(pos, _)| *pos);
-
-        let msgs: Messages = msgs
-            .into_iter()
-            .map(|(_, entry)| entry)
-            .collect::<Vec<_>>()
-            .try_into()?;
+            .await
+            .map_err(Error::ListMaildirEntriesError)??;
 
         Ok(msgs)
     }
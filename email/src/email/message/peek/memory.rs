@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::{Messages, PeekMessages};
+use crate::{envelope::Id, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct PeekMemoryMessages {
+    ctx: MemoryContextSync,
+}
+
+impl PeekMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn PeekMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn PeekMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl PeekMessages for PeekMemoryMessages {
+    async fn peek_messages(&self, folder: &str, id: &Id) -> AnyResult<Messages> {
+        info!("peeking memory messages {id} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let store = self.ctx.lock();
+        let mfolder = store.folder(&folder)?;
+
+        let msgs: Vec<Vec<u8>> = id
+            .iter()
+            .filter_map(|id| mfolder.get(id))
+            .map(|msg| msg.raw.clone())
+            .collect();
+
+        Ok(Messages::from(msgs))
+    }
+}
@@ -30,7 +30,7 @@ impl RemoveMessages for RemoveImapMessages {
     async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         info!("removing imap messages {id} from folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
@@ -1,8 +1,13 @@
 use async_trait::async_trait;
-use tracing::info;
+use tracing::{info, warn};
 
 use super::RemoveMessages;
-use crate::{email::error::Error, envelope::Id, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    email::error::Error,
+    envelope::Id,
+    maildir::{find_entries, quota::message_size, AsyncMaildir, MaildirContextSync},
+    AnyResult,
+};
 
 #[derive(Clone)]
 pub struct RemoveMaildirMessages {
@@ -29,15 +34,47 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
         info!("removing maildir message(s) {id} from folder {folder}");
 
         let ctx = self.ctx.lock().await;
-        let mdir = ctx.get_maildir_from_folder_alias(folder)?;
-
-        id.iter()
-            .filter_map(|id| mdir.find(id).ok().flatten())
-            .try_for_each(|entry| {
-                entry.remove().map_err(|err| {
-                    Error::RemoveMaildirMessageError(err, folder.to_owned(), id.to_string())
-                })
-            })?;
+        let mdir = AsyncMaildir::from(ctx.get_maildir_from_folder_alias(folder)?);
+
+        let ids: Vec<String> = id.iter().map(ToOwned::to_owned).collect();
+        let (folder, id) = (folder.to_owned(), id.to_string());
+
+        let (size, count) = mdir
+            .run(move |mdir| {
+                let mut entries = find_entries(&mdir, &ids).map_err(|err| {
+                    Error::RemoveMaildirMessageError(err, folder.clone(), id.clone())
+                })?;
+
+                // find all entries first, so that nothing gets removed
+                // if one of the messages does not belong to the given
+                // folder
+                let entries = ids
+                    .into_iter()
+                    .map(|id| match entries.remove(&id) {
+                        Some(entry) => Ok(entry),
+                        None => Err(Error::RemoveMessageNotInFolderError(folder.clone(), id)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let mut size = 0;
+
+                for entry in &entries {
+                    size += message_size(entry.path()).unwrap_or_default() as i64;
+                    entry.remove().map_err(|err| {
+                        Error::RemoveMaildirMessageError(err, folder.clone(), id.clone())
+                    })?;
+                }
+
+                Ok::<_, Error>((size, entries.len() as i64))
+            })
+            .await
+            .map_err(Error::ListMaildirEntriesError)??;
+
+        // messages are already removed, so the operation must not
+        // fail: the quota file is fixed by its next recalculation
+        if let Err(err) = ctx.update_quota(-size, -count) {
+            warn!(?err, "cannot update maildir quota, skipping it");
+        }
 
         Ok(())
     }
@@ -0,0 +1,41 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::RemoveMessages;
+use crate::{envelope::Id, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct RemoveMemoryMessages {
+    ctx: MemoryContextSync,
+}
+
+impl RemoveMemoryMessages {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn RemoveMessages> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn RemoveMessages>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl RemoveMessages for RemoveMemoryMessages {
+    async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
+        info!("removing memory message(s) {id} from folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let mut store = self.ctx.lock();
+        let mfolder = store.folder_mut(&folder)?;
+
+        for id in id.iter() {
+            mfolder.remove(id);
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use std::{collections::HashMap, fs, path::PathBuf};
+
 use async_trait::async_trait;
 use tracing::{debug, info};
 
@@ -32,12 +34,13 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
 
         let config = &self.ctx.account_config;
         let ctx = self.ctx.lock().await;
+        let mdir = ctx.mdir_ctx.get_maildir_from_folder_alias(folder)?;
         let db = ctx.open_db()?;
 
-        let folder_query = if FolderKind::matches_inbox(folder) {
+        let folder = config.get_folder_alias(folder);
+        let folder_query = if ctx.maildirpp() && FolderKind::matches_inbox(&folder) {
             "folder:\"\"".to_owned()
         } else {
-            let folder = config.get_folder_alias(folder);
             format!("folder:{folder:?}")
         };
         let mid_query = format!("mid:\"/^({})$/\"", id.join("|"));
@@ -49,17 +52,38 @@ async fn remove_messages(&self, folder: &str, id: &Id) -> AnyResult<()> {
             .search_messages()
             .map_err(Error::NotMuchFailure)?;
 
+        // a message can be stored in multiple folders: only the files
+        // belonging to the given folder are removed
+        let mut filenames = HashMap::<String, Vec<PathBuf>>::new();
+
         for msg in msgs {
-            let Some(filename) = msg.filenames().find(|f| f.is_file()) else {
-                let id = msg.id();
-                debug!(?id, "skipping notmuch message with invalid filename");
+            let paths = msg.filenames().filter(|path| {
+                path.is_file()
+                    && path
+                        .parent()
+                        .is_some_and(|dir| dir == mdir.cur() || dir == mdir.new())
+            });
 
-                continue;
-            };
+            filenames
+                .entry(msg.id().to_string())
+                .or_default()
+                .extend(paths);
+        }
+
+        // refuse to remove anything if one of the messages does not
+        // belong to the given folder
+        if let Some(id) = id
+            .iter()
+            .find(|id| filenames.get(*id).map_or(true, Vec::is_empty))
+        {
+            return Err(Error::RemoveMessageNotInFolderError(folder, id.to_owned()).into());
+        }
 
-            db.remove_message(filename).map_err(|err| {
-                Error::RemoveNotmuchMessageError(err, folder.to_owned(), id.clone())
-            })?
+        for path in filenames.into_values().flatten() {
+            fs::remove_file(&path)
+                .map_err(|err| Error::RemoveNotmuchMessageFileError(err, path.clone()))?;
+            db.remove_message(&path)
+                .map_err(|err| Error::RemoveNotmuchMessageError(err, folder.clone(), id.clone()))?;
         }
 
         db.close().map_err(Error::NotMuchFailure)?;
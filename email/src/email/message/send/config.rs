@@ -11,10 +11,37 @@ pub struct MessageSendConfig {
     /// sent.
     pub save_copy: Option<bool>,
 
+    /// Should strip the Bcc headers of the message being sent.
+    ///
+    /// Blind carbon copy recipients still receive the message, but
+    /// are not disclosed to other recipients. Defaults to `true`.
+    ///
+    /// Sendmail commands are expected to strip those headers by
+    /// themselves, since they need them to find the recipients.
+    pub strip_bcc: Option<bool>,
+
+    /// The soft limit of header lines length of the message being
+    /// sent, excluding the line ending.
+    ///
+    /// Longer header fields are folded at whitespaces. Lines can
+    /// never exceed 998 characters, as required by RFC 5322.
+    /// Defaults to 78.
+    pub header_line_length: Option<usize>,
+
     /// The hook called just before sending a message.
     ///
     /// The command should take a raw message as standard input
     /// (stdin) and returns the modified raw message to the standard
     /// output (stdout).
     pub pre_hook: Option<Command>,
+
+    /// The hook called when a message exceeds the maximum size
+    /// accepted by the server.
+    ///
+    /// The command should take the raw message as standard input
+    /// (stdin) and returns a lighter raw message to the standard
+    /// output (stdout), for example by uploading attachments
+    /// somewhere and replacing them by links. If the message is
+    /// still too large, sending fails.
+    pub too_large_hook: Option<Command>,
 }
@@ -1,4 +1,6 @@
 pub mod config;
+pub mod resend;
+pub mod sanitize;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "smtp")]
@@ -6,8 +8,16 @@
 
 use async_trait::async_trait;
 
-use super::add::AddMessage;
-use crate::{account::config::HasAccountConfig, flag::Flag, folder::SENT, AnyResult};
+use self::resend::prepare_resent_message;
+use super::{add::AddMessage, peek::PeekMessages};
+use crate::{
+    account::config::HasAccountConfig,
+    email::error::Error,
+    envelope::{Address, Id, SingleId},
+    flag::Flag,
+    folder::SENT,
+    AnyResult,
+};
 
 #[async_trait]
 pub trait SendMessage: Send + Sync {
@@ -31,3 +41,34 @@ async fn send_message_then_save_copy(&self, msg: &[u8]) -> AnyResult<()> {
 }
 
 impl<T: HasAccountConfig + AddMessage + SendMessage> SendMessageThenSaveCopy for T {}
+
+#[async_trait]
+pub trait ResendMessage: HasAccountConfig + PeekMessages + SendMessage {
+    /// Send again the message matching the given id from the given
+    /// folder.
+    ///
+    /// Transport headers are stripped before sending. If recipients
+    /// are given, `Resent-*` headers are added and the message is
+    /// delivered to them, otherwise it is delivered to its original
+    /// recipients. This is useful to resume failed sends from the
+    /// Sent folder or to forward bounced messages as they are.
+    async fn resend_message(
+        &self,
+        folder: &str,
+        id: &SingleId,
+        recipients: &[Address],
+    ) -> AnyResult<()> {
+        let msgs = self.peek_messages(folder, &Id::from(id)).await?;
+        let msg = msgs
+            .first()
+            .ok_or_else(|| Error::FindMessageError(id.to_string()))?;
+
+        let config = self.account_config();
+        let from = Address::new(config.display_name.as_ref(), &config.email);
+        let msg = prepare_resent_message(msg.raw()?, &from, recipients, config.clock.local_now());
+
+        self.send_message(&msg).await
+    }
+}
+
+impl<T: HasAccountConfig + PeekMessages + SendMessage> ResendMessage for T {}
@@ -0,0 +1,161 @@
+//! Module dedicated to message re-sending.
+//!
+//! This module contains helpers to prepare an already sent (or
+//! failed) message to be sent again, see
+//! [`ResendMessage`](super::ResendMessage).
+
+use chrono::{DateTime, FixedOffset};
+use uuid::Uuid;
+
+use crate::envelope::Address;
+
+/// Headers added by transport agents along the way, which must not be
+/// kept when sending a message again.
+pub const TRANSPORT_HEADERS: [&str; 6] = [
+    "Received",
+    "Return-Path",
+    "Delivered-To",
+    "DKIM-Signature",
+    "ARC-Seal",
+    "ARC-Message-Signature",
+];
+
+/// Prepare the given raw message to be sent again.
+///
+/// Transport headers (see [`TRANSPORT_HEADERS`]) are stripped. When
+/// recipients are given, `Resent-*` headers are prepended as defined
+/// in RFC 5322 section 3.6.6, so that the message is delivered to
+/// those recipients instead of the original ones. The given date is
+/// used for the `Resent-Date` header.
+pub fn prepare_resent_message(
+    msg: &[u8],
+    from: &Address,
+    recipients: &[Address],
+    date: DateTime<FixedOffset>,
+) -> Vec<u8> {
+    let (headers, body) = split_headers(msg);
+
+    let mut resent = Vec::with_capacity(msg.len());
+
+    if !recipients.is_empty() {
+        let to = recipients
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        let domain = from
+            .addr
+            .rsplit_once('@')
+            .map(|(_, d)| d)
+            .unwrap_or("localhost");
+
+        resent.extend(format!("Resent-From: {}\r\n", from.to_string()).as_bytes());
+        resent.extend(format!("Resent-Date: {}\r\n", date.to_rfc2822()).as_bytes());
+        resent.extend(format!("Resent-Message-ID: <{}@{domain}>\r\n", Uuid::new_v4()).as_bytes());
+        resent.extend(format!("Resent-To: {to}\r\n").as_bytes());
+    }
+
+    let mut skip = false;
+
+    for line in headers.split_inclusive(|b| *b == b'\n') {
+        let is_continuation = matches!(line.first(), Some(b' ' | b'\t'));
+
+        if !is_continuation {
+            skip = TRANSPORT_HEADERS
+                .iter()
+                .any(|name| has_header_name(line, name));
+        }
+
+        if !skip {
+            resent.extend(line);
+        }
+    }
+
+    resent.extend(body);
+    resent
+}
+
+/// Split the given raw message into its header section and its body,
+/// the body including the empty line separator.
+pub(super) fn split_headers(msg: &[u8]) -> (&[u8], &[u8]) {
+    let mut offset = 0;
+
+    for line in msg.split_inclusive(|b| *b == b'\n') {
+        if line == b"\n" || line == b"\r\n" {
+            break;
+        }
+        offset += line.len();
+    }
+
+    msg.split_at(offset)
+}
+
+pub(super) fn has_header_name(line: &[u8], name: &str) -> bool {
+    line.len() > name.len()
+        && line[name.len()] == b':'
+        && line[..name.len()].eq_ignore_ascii_case(name.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+    use concat_with::concat_line;
+
+    use crate::{
+        date::{Clock, FixedClock},
+        envelope::Address,
+    };
+
+    fn date() -> DateTime<FixedOffset> {
+        let now = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        FixedClock::new(now, offset).local_now()
+    }
+
+    #[test]
+    fn strip_transport_headers() {
+        let msg = concat_line!(
+            "Return-Path: <alice@localhost>",
+            "Received: from localhost",
+            "\tby localhost; Mon, 1 Jan 2024 00:00:00 +0000",
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "DKIM-Signature: v=1;",
+            " b=abc",
+            "Subject: subject",
+            "",
+            "Received: not a header",
+            "",
+        );
+
+        let from = Address::new_nameless("alice@localhost");
+        let resent = super::prepare_resent_message(msg.as_bytes(), &from, &[], date());
+
+        let expected = concat_line!(
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "Subject: subject",
+            "",
+            "Received: not a header",
+            "",
+        );
+
+        assert_eq!(String::from_utf8_lossy(&resent), expected);
+    }
+
+    #[test]
+    fn add_resent_headers() {
+        let msg = concat_line!("From: alice@localhost", "To: bob@localhost", "", "Hello!");
+
+        let from = Address::new_nameless("alice@localhost");
+        let to = [Address::new(Some("Carol"), "carol@localhost")];
+        let resent = super::prepare_resent_message(msg.as_bytes(), &from, &to, date());
+        let resent = String::from_utf8_lossy(&resent);
+
+        assert!(resent.starts_with("Resent-From: alice@localhost\r\n"));
+        assert!(resent.contains("Resent-Date: Mon, 1 Jan 2024 14:00:00 +0200\r\n"));
+        assert!(resent.contains("Resent-Message-ID: <"));
+        assert!(resent.contains("@localhost>\r\nResent-To: Carol <carol@localhost>\r\n"));
+        assert!(resent.ends_with(msg));
+    }
+}
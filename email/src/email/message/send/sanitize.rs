@@ -0,0 +1,307 @@
+//! Module dedicated to message sanitization.
+//!
+//! This module contains helpers to check and clean up a raw message
+//! just before handing it to a transport, see [`sanitize_message`].
+
+use std::borrow::Cow;
+
+use tracing::warn;
+
+use super::resend::{has_header_name, split_headers};
+use crate::email::error::Error;
+
+/// Headers added by the final delivery agent, which must not be
+/// present in a message being sent.
+pub const FORBIDDEN_HEADERS: [&str; 2] = ["Return-Path", "Delivered-To"];
+
+/// Headers listing blind carbon copy recipients, which must not be
+/// disclosed to other recipients.
+pub const BCC_HEADERS: [&str; 2] = ["Bcc", "Resent-Bcc"];
+
+/// The maximum length of a line, excluding the line ending (RFC 5322
+/// §2.1.1).
+pub const MAX_LINE_LEN: usize = 998;
+
+/// The recommended length of a line, excluding the line ending (RFC
+/// 5322 §2.1.1).
+pub const DEFAULT_HEADER_LINE_LEN: usize = 78;
+
+/// Check then sanitize the given raw message before sending it.
+///
+/// Messages containing one of the [`FORBIDDEN_HEADERS`], control
+/// characters in headers or NUL characters in the body are
+/// rejected. When `strip_bcc` is `true`, [`BCC_HEADERS`] are
+/// stripped from the transmitted message: envelope recipients must
+/// be computed before.
+///
+/// Header fields containing a line longer than `header_line_len`
+/// are folded at whitespaces (RFC 5322 §2.2.3), so that their lines
+/// do not exceed this soft limit when possible and never exceed
+/// [`MAX_LINE_LEN`].
+pub fn sanitize_message(
+    msg: &[u8],
+    strip_bcc: bool,
+    header_line_len: usize,
+) -> Result<Cow<'_, [u8]>, Error> {
+    let header_line_len = header_line_len.min(MAX_LINE_LEN);
+    let (headers, body) = split_headers(msg);
+
+    if let Some(b) = body.iter().find(|b| **b == 0) {
+        return Err(Error::SendMessageIllegalBodyCharError(*b));
+    }
+
+    let find_header = |line: &[u8], names: &[&'static str]| {
+        names
+            .iter()
+            .find(|name| has_header_name(line, name))
+            .copied()
+    };
+
+    let mut bcc_found = false;
+    let mut fold_needed = false;
+
+    for line in headers.split_inclusive(|b| *b == b'\n') {
+        if let Some(name) = find_header(line, &FORBIDDEN_HEADERS) {
+            return Err(Error::SendMessageForbiddenHeaderError(name));
+        }
+
+        let line = trim_line_ending(line);
+
+        if let Some(b) = line.iter().find(|b| is_illegal_header_char(**b)) {
+            return Err(Error::SendMessageIllegalHeaderCharError(*b));
+        }
+
+        bcc_found |= find_header(line, &BCC_HEADERS).is_some();
+        fold_needed |= line.len() > header_line_len;
+    }
+
+    let strip_bcc = strip_bcc && bcc_found;
+
+    if !strip_bcc && !fold_needed {
+        return Ok(Cow::Borrowed(msg));
+    }
+
+    if strip_bcc {
+        warn!("stripping bcc headers from message before sending it");
+    }
+
+    let mut sanitized = Vec::with_capacity(msg.len());
+
+    for field in split_fields(headers) {
+        if strip_bcc && find_header(field, &BCC_HEADERS).is_some() {
+            continue;
+        }
+
+        let too_long = field
+            .split_inclusive(|b| *b == b'\n')
+            .any(|line| trim_line_ending(line).len() > header_line_len);
+
+        if too_long {
+            fold_field(&mut sanitized, field, header_line_len)?;
+        } else {
+            sanitized.extend(field);
+        }
+    }
+
+    sanitized.extend(body);
+    Ok(Cow::Owned(sanitized))
+}
+
+/// Return `true` if the given byte is not allowed in headers.
+///
+/// Only horizontal tabs and line endings are allowed among control
+/// characters. Non-ASCII bytes are accepted, since headers can be
+/// UTF-8 encoded (RFC 6532).
+fn is_illegal_header_char(b: u8) -> bool {
+    (b.is_ascii_control() && b != b'\t' && b != b'\r') || b == 0x7f
+}
+
+fn trim_line_ending(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// Split the given headers into fields, each field being made of a
+/// line followed by its continuation lines.
+fn split_fields(headers: &[u8]) -> Vec<&[u8]> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut offset = 0;
+
+    for line in headers.split_inclusive(|b| *b == b'\n') {
+        let is_continuation = matches!(line.first(), Some(b' ' | b'\t'));
+
+        if !is_continuation && offset > start {
+            fields.push(&headers[start..offset]);
+            start = offset;
+        }
+
+        offset += line.len();
+    }
+
+    if offset > start {
+        fields.push(&headers[start..offset]);
+    }
+
+    fields
+}
+
+/// Unfold then fold again the given header field into the given
+/// buffer.
+///
+/// Lines are folded before the whitespace preceding the word that
+/// would make the line exceed the given length. Words longer than
+/// the given length are kept on their own line, as long as they do
+/// not exceed [`MAX_LINE_LEN`].
+fn fold_field(buf: &mut Vec<u8>, field: &[u8], line_len: usize) -> Result<(), Error> {
+    let eol: &[u8] = if field.ends_with(b"\r\n") {
+        b"\r\n"
+    } else {
+        b"\n"
+    };
+
+    let unfolded: Vec<u8> = field
+        .split_inclusive(|b| *b == b'\n')
+        .flat_map(trim_line_ending)
+        .copied()
+        .collect();
+
+    // never fold right after the colon, to keep the header name and
+    // the beginning of its value on the same line
+    let colon = unfolded
+        .iter()
+        .position(|b| *b == b':')
+        .map(|i| i + 2)
+        .unwrap_or_default();
+
+    let mut words = Vec::new();
+    let mut start = 0;
+
+    for i in colon.max(1)..unfolded.len() {
+        let is_wsp = matches!(unfolded[i], b' ' | b'\t');
+        let prev_is_wsp = matches!(unfolded[i - 1], b' ' | b'\t');
+
+        if is_wsp && !prev_is_wsp {
+            words.push(&unfolded[start..i]);
+            start = i;
+        }
+    }
+
+    words.push(&unfolded[start..]);
+
+    let mut len = 0;
+
+    for word in words {
+        if len > 0 && len + word.len() > line_len {
+            buf.extend(eol);
+            len = 0;
+        }
+
+        if len + word.len() > MAX_LINE_LEN {
+            let name = String::from_utf8_lossy(&unfolded[..colon.saturating_sub(2)]);
+            return Err(Error::SendMessageHeaderLineTooLongError(name.into_owned()));
+        }
+
+        buf.extend(word);
+        len += word.len();
+    }
+
+    buf.extend(eol);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::sanitize_message;
+    use crate::email::error::Error;
+
+    #[test]
+    fn strip_bcc_headers() {
+        let msg = concat_line!(
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "Bcc: carol@localhost,",
+            "\tdave@localhost",
+            "Subject: Hello",
+            "",
+            "Bcc: kept in body",
+        );
+
+        let expected = concat_line!(
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "Subject: Hello",
+            "",
+            "Bcc: kept in body",
+        );
+
+        let sanitized = sanitize_message(msg.as_bytes(), true, 78).unwrap();
+        assert_eq!(String::from_utf8_lossy(&sanitized), expected);
+
+        let sanitized = sanitize_message(msg.as_bytes(), false, 78).unwrap();
+        assert_eq!(String::from_utf8_lossy(&sanitized), msg);
+    }
+
+    #[test]
+    fn reject_forbidden_headers() {
+        let msg = concat_line!(
+            "Return-Path: <alice@localhost>",
+            "From: alice@localhost",
+            "",
+            "Hello",
+        );
+
+        let err = sanitize_message(msg.as_bytes(), true, 78).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SendMessageForbiddenHeaderError("Return-Path")
+        ));
+    }
+
+    #[test]
+    fn fold_long_headers() {
+        let msg = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost, carol@localhost, dave@localhost, eve@localhost\r\n",
+            "Subject: Hello\r\n",
+            "\r\n",
+            "Hello, world!\r\n",
+        );
+
+        let expected = concat!(
+            "From: alice@localhost\r\n",
+            "To: bob@localhost, carol@localhost,\r\n",
+            " dave@localhost, eve@localhost\r\n",
+            "Subject: Hello\r\n",
+            "\r\n",
+            "Hello, world!\r\n",
+        );
+
+        let sanitized = sanitize_message(msg.as_bytes(), true, 40).unwrap();
+        assert_eq!(String::from_utf8_lossy(&sanitized), expected);
+
+        // already folded fields are kept as they are
+        let sanitized = sanitize_message(expected.as_bytes(), true, 40).unwrap();
+        assert_eq!(String::from_utf8_lossy(&sanitized), expected);
+
+        let msg = format!("Subject: {}\n\nHello\n", "a".repeat(1000));
+        let err = sanitize_message(msg.as_bytes(), true, 78).unwrap_err();
+        assert!(matches!(err, Error::SendMessageHeaderLineTooLongError(name) if name == "Subject"));
+    }
+
+    #[test]
+    fn reject_illegal_chars() {
+        let msg = "From: alice@localhost\nSubject: Hel\x07lo\n\nHello\n";
+        let err = sanitize_message(msg.as_bytes(), true, 78).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::SendMessageIllegalHeaderCharError(0x07)
+        ));
+
+        let msg = "From: alice@localhost\n\nHel\0lo\n";
+        let err = sanitize_message(msg.as_bytes(), true, 78).unwrap_err();
+        assert!(matches!(err, Error::SendMessageIllegalBodyCharError(0)));
+    }
+}
@@ -2,7 +2,7 @@
 use mail_parser::MessageParser;
 use tracing::{debug, info};
 
-use super::SendMessage;
+use super::{sanitize::sanitize_message, SendMessage};
 use crate::{email::error::Error, sendmail::SendmailContextSync, AnyResult};
 
 #[derive(Clone)]
@@ -51,10 +51,18 @@ async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
             }
         };
 
+        // sendmail needs the Bcc headers to find the recipients, it
+        // strips them by itself
+        let line_len = self
+            .ctx
+            .account_config
+            .find_sent_message_header_line_length();
+        let msg = sanitize_message(msg.raw_message(), false, line_len)?;
+
         self.ctx
             .sendmail_config
             .cmd()
-            .run_with(msg.raw_message())
+            .run_with(msg.as_ref())
             .await
             .map_err(Error::RunSendmailCommandError)?;
 
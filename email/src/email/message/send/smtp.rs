@@ -29,7 +29,13 @@ async fn send_message(&self, msg: &[u8]) -> AnyResult<()> {
         info!("sending smtp message");
 
         let mut ctx = self.ctx.lock().await;
-        ctx.send(msg).await?;
+        ctx.account_config.ensure_online()?;
+        let report = ctx.send(msg).await?;
+        info!(
+            host = report.host,
+            port = report.port,
+            "smtp message accepted"
+        );
 
         Ok(())
     }
@@ -0,0 +1,44 @@
+//! # Message size
+//!
+//! Module dedicated to message size estimation. Sizes are estimated
+//! before composing messages, so that server limits (SMTP SIZE, IMAP
+//! APPENDLIMIT) can be checked before transmitting any byte.
+
+/// The estimated size of the MIME headers of a message part, in
+/// bytes (boundary, Content-Type, Content-Transfer-Encoding and
+/// Content-Disposition).
+pub const MIME_PART_OVERHEAD: usize = 256;
+
+/// The estimated size of the top-level headers of a message, in
+/// bytes, excluding the subject, the addresses and custom headers.
+pub const HEADERS_OVERHEAD: usize = 512;
+
+/// Return the size of the given amount of bytes once base64 encoded,
+/// including line endings (RFC 2045 §6.8).
+pub fn base64_encoded_len(len: usize) -> usize {
+    let encoded = len.div_ceil(3) * 4;
+    encoded + encoded.div_ceil(76) * 2
+}
+
+/// Return the estimated size of a message part holding the given
+/// amount of bytes, including its MIME headers.
+///
+/// Parts are considered base64 encoded, which is the worst case.
+pub fn estimate_part_size(len: usize) -> usize {
+    MIME_PART_OVERHEAD + base64_encoded_len(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::base64_encoded_len;
+
+    #[test]
+    fn base64_len() {
+        assert_eq!(base64_encoded_len(0), 0);
+        assert_eq!(base64_encoded_len(1), 6);
+        assert_eq!(base64_encoded_len(3), 6);
+        // 57 bytes make exactly one line of 76 characters
+        assert_eq!(base64_encoded_len(57), 78);
+        assert_eq!(base64_encoded_len(58), 84);
+    }
+}
@@ -0,0 +1,351 @@
+//! # Forward as attachment template
+//!
+//! The main structure of this module is the
+//! [`ForwardAsAttachmentTemplateBuilder`], which helps you to build
+//! template in order to forward a message as a `message/rfc822`
+//! attachment, instead of quoting it.
+
+use std::sync::Arc;
+
+use mail_builder::{
+    encoders::base64::base64_encode,
+    headers::{address::Address, raw::Raw},
+    MessageBuilder,
+};
+use mml::MimeInterpreterBuilder;
+
+use super::{config::ForwardTemplateSignatureStyle, trim_prefix};
+use crate::{
+    account::config::AccountConfig,
+    email::error::Error,
+    message::Message,
+    template::{Template, TemplateBody, TemplateCursor},
+};
+
+/// The forward as attachment template builder.
+///
+/// The original message is inlined in the template by a `<#part
+/// type=message/rfc822 data-encoding=base64>` MML part, so that
+/// compiling the template embeds it back as an attachment. Nothing is
+/// written to the disk, and the message is kept as it is whatever its
+/// charset.
+pub struct ForwardAsAttachmentTemplateBuilder<'a> {
+    /// Reference to the current account configuration.
+    config: Arc<AccountConfig>,
+
+    /// Reference to the original message.
+    msg: &'a Message<'a>,
+
+    /// Additional headers to add at the top of the template.
+    headers: Vec<(String, String)>,
+
+    /// Default body to put in the template.
+    body: String,
+
+    /// Override the placement of the signature.
+    ///
+    /// Uses the signature placement from the account configuration if
+    /// this one is `None`.
+    signature_style: Option<ForwardTemplateSignatureStyle>,
+
+    /// Template interpreter instance.
+    pub interpreter: MimeInterpreterBuilder,
+}
+
+impl<'a> ForwardAsAttachmentTemplateBuilder<'a> {
+    /// Creates a forward as attachment template builder from an
+    /// account configuration and a message references.
+    pub fn new(msg: &'a Message, config: Arc<AccountConfig>) -> Self {
+        let interpreter = config
+            .generate_tpl_interpreter()
+            .with_show_only_headers(config.get_message_write_headers());
+
+        Self {
+            config,
+            msg,
+            headers: Vec::new(),
+            body: String::new(),
+            signature_style: None,
+            interpreter,
+        }
+    }
+
+    /// Sets additional template headers following the builder
+    /// pattern.
+    pub fn with_headers(
+        mut self,
+        headers: impl IntoIterator<Item = (impl ToString, impl ToString)>,
+    ) -> Self {
+        self.headers.extend(
+            headers
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string())),
+        );
+        self
+    }
+
+    /// Sets some additional template headers following the builder
+    /// pattern.
+    pub fn with_some_headers(
+        mut self,
+        headers: Option<impl IntoIterator<Item = (impl ToString, impl ToString)>>,
+    ) -> Self {
+        if let Some(headers) = headers {
+            self = self.with_headers(headers);
+        }
+        self
+    }
+
+    /// Sets the template body following the builder pattern.
+    pub fn with_body(mut self, body: impl ToString) -> Self {
+        self.body = body.to_string();
+        self
+    }
+
+    /// Sets some template body following the builder pattern.
+    pub fn with_some_body(mut self, body: Option<impl ToString>) -> Self {
+        if let Some(body) = body {
+            self = self.with_body(body)
+        }
+        self
+    }
+
+    /// Set the signature style.
+    pub fn set_signature_style(&mut self, style: impl Into<ForwardTemplateSignatureStyle>) {
+        self.set_some_signature_style(Some(style));
+    }
+
+    /// Set some signature style.
+    pub fn set_some_signature_style(
+        &mut self,
+        style: Option<impl Into<ForwardTemplateSignatureStyle>>,
+    ) {
+        self.signature_style = style.map(Into::into);
+    }
+
+    /// Set some signature style, using the builder pattern.
+    pub fn with_some_signature_style(
+        mut self,
+        style: Option<impl Into<ForwardTemplateSignatureStyle>>,
+    ) -> Self {
+        self.set_some_signature_style(style);
+        self
+    }
+
+    /// Set the signature style, using the builder pattern.
+    pub fn with_signature_style(mut self, style: impl Into<ForwardTemplateSignatureStyle>) -> Self {
+        self.set_signature_style(style);
+        self
+    }
+
+    /// Sets the template interpreter following the builder pattern.
+    pub fn with_interpreter(mut self, interpreter: MimeInterpreterBuilder) -> Self {
+        self.interpreter = interpreter;
+        self
+    }
+
+    /// Builds the final forward as attachment message template.
+    pub async fn build(self) -> Result<Template, Error> {
+        let mut cursor = TemplateCursor::default();
+
+        let parsed = self.msg.parsed()?;
+        let mut builder = MessageBuilder::new();
+
+        // From
+
+        builder = builder.from(self.config.as_ref());
+        cursor.row += 1;
+
+        // To
+
+        builder = builder.to(Vec::<Address>::new());
+        cursor.row += 1;
+
+        // Subject
+
+        let prefix = String::from("Fwd: ");
+        let subject = trim_prefix(parsed.subject().unwrap_or_default());
+
+        builder = builder.subject(prefix + subject);
+        cursor.row += 1;
+
+        // Additional headers
+
+        for (key, val) in self.headers {
+            builder = builder.header(key, Raw::new(val));
+            cursor.row += 1;
+        }
+
+        // Body
+
+        let sig = self.config.find_full_signature();
+        let sig_style = self
+            .signature_style
+            .unwrap_or_else(|| self.config.get_forward_template_signature_style());
+
+        builder = builder.text_body({
+            let mut body = TemplateBody::new(cursor);
+
+            body.push_str(&self.body);
+            body.flush();
+            body.cursor.lock();
+
+            if sig_style.is_inlined() {
+                if let Some(ref sig) = sig {
+                    body.push_str(sig);
+                    body.flush();
+                }
+            }
+
+            // separates the body from the forwarded message part
+            body.flush();
+
+            cursor = body.cursor.clone();
+            body
+        });
+
+        if sig_style.is_attached() {
+            if let Some(sig) = sig {
+                builder = builder.attachment("text/plain", "signature.txt", sig)
+            }
+
+            if let Some(sig) = self.config.find_html_signature() {
+                builder = builder.attachment("text/html", "signature.html", sig);
+            }
+        }
+
+        let mut content = self
+            .interpreter
+            .build()
+            .from_msg_builder(builder)
+            .await
+            .map_err(Error::InterpretMessageAsTemplateError)?;
+
+        // Forwarded message
+
+        let file_name = parsed
+            .message_id()
+            .map(sanitize_file_name)
+            .unwrap_or_else(|| String::from("message"));
+
+        if !content.ends_with('\n') {
+            content.push('\n');
+        }
+
+        content.push_str(&format!(
+            "<#part type=message/rfc822 disposition=attachment recipient-filename=\"{file_name}.eml\" data-encoding=base64>\n"
+        ));
+        content.push_str(&encode_base64_lines(parsed.raw_message())?);
+        content.push_str("<#/part>\n");
+
+        Ok(Template::new_with_cursor(content, cursor))
+    }
+}
+
+/// Encodes the given data using base64, in lines of 76 characters.
+fn encode_base64_lines(data: &[u8]) -> Result<String, Error> {
+    let encoded = base64_encode(data).map_err(Error::EncodeForwardedMessageError)?;
+    let mut lines = String::with_capacity(encoded.len() + encoded.len() / 76 + 1);
+
+    for line in encoded.chunks(76) {
+        // base64 only produces ASCII characters
+        lines.push_str(&String::from_utf8_lossy(line));
+        lines.push('\n');
+    }
+
+    Ok(lines)
+}
+
+/// Turns the given message identifier into a safe file name.
+fn sanitize_file_name(id: &str) -> String {
+    id.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '@' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use concat_with::concat_line;
+    use mail_parser::{MessageParser, MimeHeaders, PartType};
+    use mml::MmlCompilerBuilder;
+
+    use super::ForwardAsAttachmentTemplateBuilder;
+    use crate::{account::config::AccountConfig, message::Message};
+
+    #[tokio::test]
+    async fn forward_as_attachment() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let config = Arc::new(AccountConfig {
+            display_name: Some("Me".into()),
+            email: "me@localhost".into(),
+            downloads_dir: Some(dir.path().to_owned()),
+            ..Default::default()
+        });
+
+        let raw = concat_line!(
+            "Message-ID: <id/1@localhost>\r",
+            "Content-Type: text/plain; charset=iso-8859-1\r",
+            "Content-Transfer-Encoding: 8bit\r",
+            "From: sender@localhost\r",
+            "To: me@localhost\r",
+            "Subject: subject\r",
+            "\r",
+            "Hello, world!\r",
+            "",
+        );
+
+        // the message is not valid UTF-8
+        let mut raw = raw.as_bytes().to_vec();
+        raw.extend(b"Caf\xe9\r\n");
+
+        let msg = &Message::from(raw.clone());
+
+        let tpl = ForwardAsAttachmentTemplateBuilder::new(msg, config)
+            .with_body("See attached.")
+            .build()
+            .await
+            .unwrap();
+
+        assert!(tpl.content.starts_with(concat_line!(
+            "From: Me <me@localhost>",
+            "To: ",
+            "Subject: Fwd: subject",
+            "",
+            "See attached.",
+        )));
+        assert!(tpl.content.contains(
+            "<#part type=message/rfc822 disposition=attachment recipient-filename=\"id_1@localhost.eml\" data-encoding=base64>\n"
+        ));
+        assert!(tpl.content.ends_with("<#/part>\n"));
+
+        // nothing is written to the downloads directory
+        assert_eq!(dir.path().read_dir().unwrap().count(), 0);
+
+        let compiled = MmlCompilerBuilder::new()
+            .build(&tpl)
+            .unwrap()
+            .compile()
+            .await
+            .unwrap()
+            .into_vec()
+            .unwrap();
+
+        let compiled = MessageParser::new().parse(&compiled).unwrap();
+        let forwarded = compiled
+            .attachments()
+            .find(|part| matches!(part.body, PartType::Message(_)))
+            .unwrap();
+
+        assert_eq!(forwarded.attachment_name(), Some("id_1@localhost.eml"));
+        match &forwarded.body {
+            PartType::Message(forwarded) => assert_eq!(forwarded.raw_message(), raw),
+            _ => unreachable!(),
+        }
+    }
+}
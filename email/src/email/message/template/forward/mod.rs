@@ -2,8 +2,10 @@
 //!
 //! The main structure of this module is the
 //! [`ForwardTemplateBuilder`], which helps you to build template in
-//! order to forward a message.
+//! order to forward a message. Messages can also be forwarded as
+//! attachment using the [`ForwardAsAttachmentTemplateBuilder`].
 
+mod attachment;
 pub mod config;
 
 use std::sync::Arc;
@@ -16,6 +18,8 @@
 use once_cell::sync::Lazy;
 use regex::Regex;
 
+#[doc(inline)]
+pub use self::attachment::ForwardAsAttachmentTemplateBuilder;
 use self::config::{ForwardTemplatePostingStyle, ForwardTemplateSignatureStyle};
 use super::{Template, TemplateBody, TemplateCursor};
 use crate::{account::config::AccountConfig, email::error::Error, message::Message};
@@ -284,6 +288,10 @@ pub async fn build(self) -> Result<Template, Error> {
             if let Some(sig) = sig {
                 builder = builder.attachment("text/plain", "signature.txt", sig)
             }
+
+            if let Some(sig) = self.config.find_html_signature() {
+                builder = builder.attachment("text/html", "signature.html", sig);
+            }
         }
 
         if posting_style.is_attached() {
@@ -177,6 +177,10 @@ pub async fn build(self) -> Result<Template, Error> {
             if let Some(sig) = sig {
                 msg = msg.attachment("text/plain", "signature.txt", sig)
             }
+
+            if let Some(sig) = self.config.find_html_signature() {
+                msg = msg.attachment("text/html", "signature.html", sig);
+            }
         }
 
         let content = self
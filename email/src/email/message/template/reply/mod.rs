@@ -11,7 +11,7 @@
     headers::{address::Address, raw::Raw},
     MessageBuilder,
 };
-use mail_parser::{Addr, HeaderValue};
+use mail_parser::HeaderValue;
 use mml::{message::FilterParts, MimeInterpreterBuilder};
 use once_cell::sync::Lazy;
 use regex::Regex;
@@ -21,6 +21,7 @@
 use crate::{
     account::config::AccountConfig,
     email::{address, error::Error},
+    envelope::address::normalize_addr,
     message::Message,
 };
 
@@ -229,8 +230,6 @@ pub async fn build(self) -> Result<Template, Error> {
         let parsed = self.msg.parsed()?;
         let mut builder = MessageBuilder::new();
 
-        let me = Addr::new(Some(&self.config.name), &self.config.email);
-
         let sender = parsed.header("Sender").unwrap_or(&HeaderValue::Empty);
         let from = parsed.header("From").unwrap_or(&HeaderValue::Empty);
         let to = parsed.header("To").unwrap_or(&HeaderValue::Empty);
@@ -268,18 +267,34 @@ pub async fn build(self) -> Result<Template, Error> {
 
         let mut curr_rcpts = Vec::<Address>::default();
         let mut all_rcpts_email = HashSet::<Cow<str>>::default();
-        all_rcpts_email.insert(me.address.clone().unwrap());
+        all_rcpts_email.insert(Cow::Owned(normalize_addr(&self.config.email)));
+        let is_own_address = |addr: &str| self.config.is_own_address(addr);
 
         if !address::is_empty(reply_to) {
-            address::push_builder_address(&mut all_rcpts_email, &mut curr_rcpts, &reply_to);
+            address::push_builder_address(
+                &mut all_rcpts_email,
+                &mut curr_rcpts,
+                reply_to,
+                is_own_address,
+            );
         } else {
             let from = if !address::is_empty(from) {
                 from
             } else {
                 sender
             };
-            address::push_builder_address(&mut all_rcpts_email, &mut curr_rcpts, &from);
-            address::push_builder_address(&mut all_rcpts_email, &mut curr_rcpts, &to);
+            address::push_builder_address(
+                &mut all_rcpts_email,
+                &mut curr_rcpts,
+                from,
+                is_own_address,
+            );
+            address::push_builder_address(
+                &mut all_rcpts_email,
+                &mut curr_rcpts,
+                to,
+                is_own_address,
+            );
         }
 
         builder = builder.to(Address::new_list(curr_rcpts.clone()));
@@ -291,7 +306,12 @@ pub async fn build(self) -> Result<Template, Error> {
             let cc = parsed.header("Cc").unwrap_or(&HeaderValue::Empty);
 
             curr_rcpts.clear();
-            address::push_builder_address(&mut all_rcpts_email, &mut curr_rcpts, &cc);
+            address::push_builder_address(
+                &mut all_rcpts_email,
+                &mut curr_rcpts,
+                cc,
+                is_own_address,
+            );
 
             if !curr_rcpts.is_empty() {
                 builder = builder.cc(curr_rcpts);
@@ -419,6 +439,10 @@ pub async fn build(self) -> Result<Template, Error> {
             if let Some(sig) = sig {
                 builder = builder.attachment("text/plain", "signature.txt", sig)
             }
+
+            if let Some(sig) = self.config.find_html_signature() {
+                builder = builder.attachment("text/html", "signature.html", sig);
+            }
         }
 
         let content = self
@@ -439,7 +463,7 @@ mod tests {
     use concat_with::concat_line;
 
     use crate::{
-        account::config::AccountConfig,
+        account::config::{alias::EmailAlias, AccountConfig},
         message::Message,
         template::{
             reply::{
@@ -1424,4 +1448,49 @@ async fn should_hide_part_markup_in_html_reply_thread() {
             ),
         );
     }
+    #[tokio::test]
+    async fn reply_all_skipping_email_aliases() {
+        let config = Arc::new(AccountConfig {
+            email: "me@localhost".into(),
+            email_aliases: Some(vec![
+                EmailAlias::PlusAddressing("me@localhost".into()),
+                EmailAlias::Exact("alias@remotehost".into()),
+                EmailAlias::Regex("^.*@me\\.localhost$".into()),
+            ]),
+            ..AccountConfig::default()
+        });
+
+        let msg = Message::from(concat_line!(
+            "Content-Type: text/plain",
+            "From: from@localhost",
+            "To: me+tag@localhost, to@localhost",
+            "Cc: alias@remotehost, cc@localhost, other@me.localhost",
+            "Subject: subject",
+            "",
+            "Hello!",
+        ));
+
+        let tpl = msg
+            .to_reply_tpl_builder(config)
+            .with_reply_all(true)
+            .build()
+            .await
+            .unwrap();
+
+        let expected_tpl = Template::new_with_cursor(
+            concat_line!(
+                "From: me@localhost",
+                "To: from@localhost, to@localhost",
+                "Cc: cc@localhost",
+                "Subject: Re: subject",
+                "",
+                "",
+                "",
+                "> Hello!",
+            ),
+            (6, 0),
+        );
+
+        assert_eq!(tpl, expected_tpl);
+    }
 }
@@ -9,8 +9,9 @@
 pub mod parser;
 
 use chrono::NaiveDate;
+use mail_parser::MessageParser;
 
-use crate::flag::Flag;
+use crate::{date::Clock, envelope::Envelope, flag::Flag};
 
 /// The search emails filter query.
 ///
@@ -73,3 +74,93 @@ pub enum SearchEmailsFilterQuery {
     /// envelope flags.
     Flag(Flag),
 }
+
+impl SearchEmailsFilterQuery {
+    /// Return `true` if the given envelope matches the filter.
+    ///
+    /// Dates are compared in the local timezone of the given clock,
+    /// see [`Clock::to_local`]. The raw message
+    /// is only read for body conditions, using the given
+    /// function. When it cannot be read, body conditions match.
+    pub fn matches_envelope(
+        &self,
+        envelope: &Envelope,
+        clock: &dyn Clock,
+        read_msg: &dyn Fn() -> Option<Vec<u8>>,
+    ) -> bool {
+        match self {
+            SearchEmailsFilterQuery::And(left, right) => {
+                let left = left.matches_envelope(envelope, clock, read_msg);
+                let right = right.matches_envelope(envelope, clock, read_msg);
+                left && right
+            }
+            SearchEmailsFilterQuery::Or(left, right) => {
+                let left = left.matches_envelope(envelope, clock, read_msg);
+                let right = right.matches_envelope(envelope, clock, read_msg);
+                left || right
+            }
+            SearchEmailsFilterQuery::Not(filter) => {
+                !filter.matches_envelope(envelope, clock, read_msg)
+            }
+            SearchEmailsFilterQuery::Date(date) => {
+                &clock.to_local(&envelope.date).date_naive() == date
+            }
+            SearchEmailsFilterQuery::BeforeDate(date) => {
+                &clock.to_local(&envelope.date).date_naive() < date
+            }
+            SearchEmailsFilterQuery::AfterDate(date) => {
+                &clock.to_local(&envelope.date).date_naive() > date
+            }
+            SearchEmailsFilterQuery::From(pattern) => {
+                let pattern = pattern.as_bytes();
+                if let Some(name) = &envelope.from.name {
+                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
+                        return true;
+                    }
+                }
+                contains_ignore_ascii_case(envelope.from.addr.as_bytes(), pattern)
+            }
+            SearchEmailsFilterQuery::To(pattern) => {
+                let pattern = pattern.as_bytes();
+                if let Some(name) = &envelope.to.name {
+                    if contains_ignore_ascii_case(name.as_bytes(), pattern) {
+                        return true;
+                    }
+                }
+                contains_ignore_ascii_case(envelope.to.addr.as_bytes(), pattern)
+            }
+            SearchEmailsFilterQuery::Subject(pattern) => {
+                contains_ignore_ascii_case(envelope.subject.as_bytes(), pattern.as_bytes())
+            }
+            SearchEmailsFilterQuery::Body(pattern) => match read_msg() {
+                Some(contents) => {
+                    if let Some(msg) = MessageParser::new().parse(&contents) {
+                        for plain in msg.text_bodies() {
+                            if contains_ignore_ascii_case(plain.contents(), pattern.as_bytes()) {
+                                return true;
+                            }
+                        }
+                        for html in msg.html_bodies() {
+                            if contains_ignore_ascii_case(html.contents(), pattern.as_bytes()) {
+                                return true;
+                            }
+                        }
+                    }
+                    false
+                }
+                None => true,
+            },
+            SearchEmailsFilterQuery::Flag(flag) => envelope.flags.contains(flag),
+        }
+    }
+}
+
+fn contains_ignore_ascii_case(haystack: &[u8], needle: &[u8]) -> bool {
+    for window in haystack.windows(needle.len()) {
+        if window.eq_ignore_ascii_case(needle) {
+            return true;
+        }
+    }
+
+    false
+}
@@ -32,6 +32,22 @@ pub enum EmailSyncHunk {
         RefreshSourceCache,
     ),
 
+    /// The email matching the given envelope has been moved to the
+    /// given folder on the given source side. The matching email of
+    /// the given target side needs to be moved from the given origin
+    /// folder to the given folder, then cached on both sides.
+    ///
+    /// The last envelope is the one of the target side, before the
+    /// move.
+    MoveThenCache(
+        FolderName,
+        Envelope,
+        SyncDestination,
+        SyncDestination,
+        FolderName,
+        Envelope,
+    ),
+
     /// The envelope matching the given envelope identifier from the
     /// given folder needs to refresh its flags cache for the given
     /// target.
@@ -64,6 +80,13 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
                     "Copying {source} envelope {id} to {target} folder {folder}"
                 )
             }
+            Self::MoveThenCache(folder, _, _, target, from_folder, envelope) => {
+                let id = &envelope.id;
+                write!(
+                    f,
+                    "Moving {target} envelope {id} from folder {from_folder} to folder {folder}"
+                )
+            }
             Self::UpdateCachedFlags(folder, envelope, target) => {
                 let id = &envelope.id;
                 let flags = envelope.flags.to_string();
@@ -95,6 +118,7 @@ pub fn folder(&self) -> &str {
         match self {
             Self::GetThenCache(folder, _, _) => folder.as_str(),
             Self::CopyThenCache(folder, _, _, _, _) => folder.as_str(),
+            Self::MoveThenCache(folder, _, _, _, _, _) => folder.as_str(),
             Self::UpdateCachedFlags(folder, _, _) => folder.as_str(),
             Self::UpdateFlags(folder, _, _) => folder.as_str(),
             Self::Uncache(folder, _, _) => folder.as_str(),
@@ -8,25 +8,30 @@
 
 use std::{
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    mem,
     string::String,
     sync::Arc,
 };
 
-use futures::{stream::FuturesUnordered, StreamExt};
-use tracing::{debug, trace};
+use futures::{
+    stream::{self, FuturesUnordered},
+    StreamExt,
+};
+use tokio::sync::SemaphorePermit;
+use tracing::{debug, trace, warn};
 
 use self::{hunk::EmailSyncHunk, report::EmailSyncReport};
 #[doc(inline)]
 pub use super::{Error, Result};
 use crate::{
-    backend::context::BackendContextBuilder,
+    backend::context::{BackendContext, BackendContextBuilder},
     envelope::{
         get::GetEnvelope,
         list::{ListEnvelopes, ListEnvelopesOptions},
         Envelope, Id, SingleId,
     },
-    flag::{add::AddFlags, set::SetFlags, Flag},
-    message::{add::AddMessage, peek::PeekMessages},
+    flag::{self, add::AddFlags, set::SetFlags, Flag, Flags},
+    message::{add::AddMessage, peek::PeekMessages, r#move::MoveMessages},
     search_query::SearchEmailsQuery,
     sync::{pool::SyncPoolContext, SyncDestination, SyncEvent},
     AnyBoxedError,
@@ -43,7 +48,12 @@ pub(crate) async fn sync<L, R>(
     R: BackendContextBuilder + 'static,
 {
     let mut report = EmailSyncReport::default();
-    let patch = FuturesUnordered::from_iter(folders.iter().map(|folder| {
+    let concurrency = ctx_ref.folder_concurrency(folders.len());
+
+    // envelopes listing tasks are only spawned when the folder is
+    // polled by the buffer, which limits the number of folders
+    // synchronized in parallel
+    let patch = stream::iter(folders.iter().map(|folder| {
         let ctx = ctx_ref.clone();
         let folder_ref = folder.clone();
 
@@ -59,6 +69,11 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            notmuch_query: None,
+                            with_previews: false,
+                            with_saved_dates: false,
+                            extra_headers: Vec::new(),
+                            filters: Default::default(),
                         },
                     )
                     .await
@@ -94,6 +109,11 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            notmuch_query: None,
+                            with_previews: false,
+                            with_saved_dates: false,
+                            extra_headers: Vec::new(),
+                            filters: Default::default(),
                         },
                     )
                     .await
@@ -129,6 +149,11 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            notmuch_query: None,
+                            with_previews: false,
+                            with_saved_dates: false,
+                            extra_headers: Vec::new(),
+                            filters: Default::default(),
                         },
                     )
                     .await
@@ -164,6 +189,11 @@ pub(crate) async fn sync<L, R>(
                                 filter: ctx.envelope_filters.clone().into(),
                                 sort: None,
                             }),
+                            notmuch_query: None,
+                            with_previews: false,
+                            with_saved_dates: false,
+                            extra_headers: Vec::new(),
+                            filters: Default::default(),
                         },
                     )
                     .await
@@ -196,12 +226,16 @@ pub(crate) async fn sync<L, R>(
             Result::Ok((folder.clone(), envelopes))
         }
     }))
+    .buffer_unordered(concurrency)
     .filter_map(|patch| async {
         let task = async {
             let (folder, envelopes) = patch?;
             let (lc, l, rc, r) = envelopes.map_err(|e| Error::FailedToGetEnvelopes(e))?;
-            let patch = patch::build(&folder, lc?, l?, rc?, r?);
-            Ok::<(String, HashSet<Vec<EmailSyncHunk>>), AnyBoxedError>((folder, patch))
+            let (l, r) = (l?, r?);
+            let lc = invalidate_cache(&ctx_ref, &folder, SyncDestination::Left, lc?).await?;
+            let rc = invalidate_cache(&ctx_ref, &folder, SyncDestination::Right, rc?).await?;
+            let patch = patch::build(&folder, lc, l.clone(), rc, r.clone());
+            Ok::<_, AnyBoxedError>((folder, patch, l, r))
         };
         match task.await {
             Ok(patch) => Some(patch),
@@ -212,89 +246,69 @@ pub(crate) async fn sync<L, R>(
             }
         }
     })
-    .fold(BTreeMap::new(), |mut patches, (folder, p)| async {
-        let mut patch = p.into_iter().flatten().collect::<BTreeSet<_>>();
-        ctx_ref.apply_flag_and_message_permissions(&mut patch);
-
-        patches.insert(folder, patch);
-        patches
-    })
+    .fold(
+        (BTreeMap::new(), HashMap::new(), HashMap::new()),
+        |(mut patches, mut left, mut right), (folder, p, l, r)| async {
+            let mut patch = p.into_iter().flatten().collect::<BTreeSet<_>>();
+            ctx_ref.apply_flag_and_message_permissions(&mut patch);
+
+            left.insert(folder.clone(), l);
+            right.insert(folder.clone(), r);
+            patches.insert(folder, patch);
+            (patches, left, right)
+        },
+    )
     .await;
 
+    // emails moved between folders on one side appear as a deletion
+    // plus a copy, which are turned into a single move
+    let (mut patch, left_envelopes, right_envelopes) = patch;
+    patch::detect_moves(&mut patch, &left_envelopes, &right_envelopes);
+
     SyncEvent::GeneratedEmailPatch(patch.clone())
         .emit(&ctx_ref.handler)
         .await;
 
-    report.patch = FuturesUnordered::from_iter(patch.into_values().flatten().map(|hunk| {
-        let ctx = ctx_ref.clone();
-        tokio::spawn(async move {
-            let hunk_clone = hunk.clone();
-            let handler = ctx.handler.clone();
-
-            let task = async move {
-                if ctx.dry_run {
-                    return Ok(());
+    let concurrency = ctx_ref.folder_concurrency(patch.len());
+
+    report.patch = stream::iter(patch.into_values())
+        .map(|hunks| {
+            // copies sharing the same source and target are processed
+            // in batches, so that backends able to add multiple
+            // messages at once send them together
+            let mut copies: BTreeMap<_, Vec<_>> = BTreeMap::new();
+            let mut others = Vec::new();
+
+            for hunk in hunks {
+                match &hunk {
+                    EmailSyncHunk::CopyThenCache(folder, _, source, target, _) => copies
+                        .entry((folder.clone(), source.clone(), target.clone()))
+                        .or_default()
+                        .push(hunk),
+                    _ => others.push(hunk),
                 }
+            }
 
-                match hunk_clone {
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Left) => {
-                        let envelope = ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
-                        let flags = envelope.flags.clone();
-                        let msg = envelope.to_sync_cache_msg();
-                        ctx.left_cache
-                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
-                        let envelope = ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
-                        let flags = envelope.flags.clone();
-                        let msg = envelope.to_sync_cache_msg();
-                        ctx.right_cache
-                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::CopyThenCache(
-                        folder,
-                        envelope,
-                        source,
-                        target,
-                        refresh_source_cache,
-                    ) => {
-                        let id = Id::single(&envelope.id);
-                        let msgs = match source {
-                            SyncDestination::Left => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.left_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.left.peek_messages(&folder, &id).await?
-                            }
-                            SyncDestination::Right => {
-                                if refresh_source_cache {
-                                    let flags = envelope.flags.clone();
-                                    let msg = envelope.to_sync_cache_msg();
-                                    ctx.right_cache
-                                        .add_message_with_flags(&folder, msg.as_bytes(), &flags)
-                                        .await?;
-                                };
-                                ctx.right.peek_messages(&folder, &id).await?
-                            }
-                        };
+            let copies = copies.into_iter().map(|((folder, source, target), hunks)| {
+                let ctx = ctx_ref.clone();
+                tokio::spawn(async move {
+                    copy_then_cache(&ctx, &folder, &source, &target, hunks).await
+                })
+            });
 
-                        let msgs = msgs.to_vec();
-                        let msg = msgs
-                            .first()
-                            .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
+            let others = others.into_iter().map(|hunk| {
+                let ctx = ctx_ref.clone();
+                tokio::spawn(async move {
+                    let hunk_clone = hunk.clone();
+                    let handler = ctx.handler.clone();
 
-                        match target {
-                            SyncDestination::Left => {
-                                let id = ctx
-                                    .left
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
+                    let task = async move {
+                        if ctx.dry_run {
+                            return Ok(());
+                        }
+
+                        match hunk_clone {
+                            EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Left) => {
                                 let envelope =
                                     ctx.left.get_envelope(&folder, &SingleId::from(id)).await?;
                                 let flags = envelope.flags.clone();
@@ -303,11 +317,7 @@ pub(crate) async fn sync<L, R>(
                                     .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                     .await?;
                             }
-                            SyncDestination::Right => {
-                                let id = ctx
-                                    .right
-                                    .add_message_with_flags(&folder, msg.raw()?, &envelope.flags)
-                                    .await?;
+                            EmailSyncHunk::GetThenCache(folder, id, SyncDestination::Right) => {
                                 let envelope =
                                     ctx.right.get_envelope(&folder, &SingleId::from(id)).await?;
                                 let flags = envelope.flags.clone();
@@ -316,81 +326,464 @@ pub(crate) async fn sync<L, R>(
                                     .add_message_with_flags(&folder, msg.as_bytes(), &flags)
                                     .await?;
                             }
+                            EmailSyncHunk::CopyThenCache(..) => {
+                                unreachable!("copies are processed in batches")
+                            }
+                            EmailSyncHunk::MoveThenCache(
+                                folder,
+                                envelope,
+                                source,
+                                target,
+                                from_folder,
+                                target_envelope,
+                            ) => {
+                                let flags = envelope.flags.clone();
+                                let msg = envelope.to_sync_cache_msg();
+                                match source {
+                                    SyncDestination::Left => {
+                                        ctx.left_cache
+                                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
+                                            .await?;
+                                    }
+                                    SyncDestination::Right => {
+                                        ctx.right_cache
+                                            .add_message_with_flags(&folder, msg.as_bytes(), &flags)
+                                            .await?;
+                                    }
+                                };
+
+                                // when the backend tells the identifier of
+                                // the moved email, flags changed on the
+                                // source side are applied right away
+                                // instead of during the next run
+                                let id = Id::single(&target_envelope.id);
+                                let synced_flags = flag::sync(
+                                    None,
+                                    Some(&envelope.flags),
+                                    None,
+                                    Some(&target_envelope.flags),
+                                );
+                                let msg = target_envelope.to_sync_cache_msg();
+                                match target {
+                                    SyncDestination::Left => {
+                                        let mapping = ctx
+                                            .left
+                                            .move_messages_with_mapping(&from_folder, &folder, &id)
+                                            .await?;
+                                        let flags = match mapping.get(&target_envelope.id) {
+                                            Some(id) if synced_flags != target_envelope.flags => {
+                                                ctx.left
+                                                    .set_flags(
+                                                        &folder,
+                                                        &Id::single(id),
+                                                        &synced_flags,
+                                                    )
+                                                    .await?;
+                                                &synced_flags
+                                            }
+                                            _ => &target_envelope.flags,
+                                        };
+                                        ctx.left_cache
+                                            .add_message_with_flags(&folder, msg.as_bytes(), flags)
+                                            .await?;
+                                    }
+                                    SyncDestination::Right => {
+                                        let mapping = ctx
+                                            .right
+                                            .move_messages_with_mapping(&from_folder, &folder, &id)
+                                            .await?;
+                                        let flags = match mapping.get(&target_envelope.id) {
+                                            Some(id) if synced_flags != target_envelope.flags => {
+                                                ctx.right
+                                                    .set_flags(
+                                                        &folder,
+                                                        &Id::single(id),
+                                                        &synced_flags,
+                                                    )
+                                                    .await?;
+                                                &synced_flags
+                                            }
+                                            _ => &target_envelope.flags,
+                                        };
+                                        ctx.right_cache
+                                            .add_message_with_flags(&folder, msg.as_bytes(), flags)
+                                            .await?;
+                                    }
+                                };
+                            }
+                            EmailSyncHunk::Uncache(folder, id, SyncDestination::Left) => {
+                                ctx.left_cache
+                                    .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                                    .await?;
+                            }
+                            EmailSyncHunk::Delete(folder, id, SyncDestination::Left) => {
+                                ctx.left
+                                    .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                                    .await?;
+                            }
+                            EmailSyncHunk::Uncache(folder, id, SyncDestination::Right) => {
+                                ctx.right_cache
+                                    .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                                    .await?;
+                            }
+                            EmailSyncHunk::Delete(folder, id, SyncDestination::Right) => {
+                                ctx.right
+                                    .add_flag(&folder, &Id::single(id), Flag::Deleted)
+                                    .await?;
+                            }
+                            EmailSyncHunk::UpdateCachedFlags(
+                                folder,
+                                envelope,
+                                SyncDestination::Left,
+                            ) => {
+                                ctx.left_cache
+                                    .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                                    .await?;
+                            }
+                            EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
+                                ctx.left
+                                    .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                                    .await?;
+                            }
+                            EmailSyncHunk::UpdateCachedFlags(
+                                folder,
+                                envelope,
+                                SyncDestination::Right,
+                            ) => {
+                                ctx.right_cache
+                                    .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                                    .await?;
+                            }
+                            EmailSyncHunk::UpdateFlags(
+                                folder,
+                                envelope,
+                                SyncDestination::Right,
+                            ) => {
+                                ctx.right
+                                    .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
+                                    .await?;
+                            }
                         };
+
+                        Ok(())
+                    };
+
+                    let output = task.await;
+
+                    SyncEvent::ProcessedEmailHunk(hunk.clone())
+                        .emit(&handler)
+                        .await;
+
+                    match output {
+                        Ok(()) => vec![(hunk, None)],
+                        Err(err) => vec![(hunk, Some(err))],
                     }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Left) => {
-                        ctx.left_cache
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Left) => {
-                        ctx.left
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Uncache(folder, id, SyncDestination::Right) => {
-                        ctx.right_cache
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::Delete(folder, id, SyncDestination::Right) => {
-                        ctx.right
-                            .add_flag(&folder, &Id::single(id), Flag::Deleted)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Left) => {
-                        ctx.left_cache
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Left) => {
-                        ctx.left
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateCachedFlags(folder, envelope, SyncDestination::Right) => {
-                        ctx.right_cache
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
-                    }
-                    EmailSyncHunk::UpdateFlags(folder, envelope, SyncDestination::Right) => {
-                        ctx.right
-                            .set_flags(&folder, &Id::single(&envelope.id), &envelope.flags)
-                            .await?;
+                })
+            });
+
+            FuturesUnordered::from_iter(copies.chain(others))
+                .filter_map(|res| async {
+                    match res {
+                        Ok(res) => Some(res),
+                        Err(err) => {
+                            debug!("cannot process email hunk: {err}");
+                            trace!("{err:?}");
+                            None
+                        }
                     }
-                };
+                })
+                .concat()
+        })
+        .buffer_unordered(concurrency)
+        .concat()
+        .await;
 
-                Ok(())
-            };
+    SyncEvent::ProcessedAllEmailHunks
+        .emit(&ctx_ref.handler)
+        .await;
 
-            let output = task.await;
+    Ok(report)
+}
 
-            SyncEvent::ProcessedEmailHunk(hunk.clone())
-                .emit(&handler)
-                .await;
+/// The maximum number of messages added to a folder at once.
+const COPY_BATCH_SIZE: usize = 100;
 
-            match output {
-                Ok(()) => (hunk, None),
-                Err(err) => (hunk, Some(err)),
-            }
-        })
-    }))
-    .filter_map(|res| async {
-        match res {
-            Ok(res) => Some(res),
+/// The messages waiting to be added to the target folder at once,
+/// with the bytes they hold in the budget.
+#[derive(Default)]
+struct CopyBatch<'a> {
+    hunks: Vec<EmailSyncHunk>,
+    msgs: Vec<(Flags, Vec<u8>)>,
+    permits: Vec<SemaphorePermit<'a>>,
+}
+
+/// Copy the messages of the given [`EmailSyncHunk::CopyThenCache`]
+/// hunks from the source side to the target side, then cache them.
+///
+/// Messages are downloaded one by one, then added to the target
+/// folder in batches using [`AddMessage::add_messages_with_flags`],
+/// so that backends able to do it (like IMAP servers supporting
+/// MULTIAPPEND) send a single command per batch. A batch is sent
+/// when it is full, or when the next message does not fit in the
+/// bytes budget: the budget is never waited for while the batch
+/// holds a part of it, so that concurrent batches cannot starve each
+/// other.
+async fn copy_then_cache<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    source: &SyncDestination,
+    target: &SyncDestination,
+    hunks: Vec<EmailSyncHunk>,
+) -> Vec<(EmailSyncHunk, Option<AnyBoxedError>)>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let mut report = Vec::with_capacity(hunks.len());
+    let mut batch = CopyBatch::default();
+
+    for hunk in hunks {
+        let EmailSyncHunk::CopyThenCache(_, envelope, _, _, refresh_source_cache) = &hunk else {
+            continue;
+        };
+
+        if batch.hunks.len() >= COPY_BATCH_SIZE {
+            report.extend(add_then_cache(ctx, folder, target, &mut batch).await);
+        }
+
+        // the budget is acquired before downloading the message,
+        // using the size announced by the source backend. Messages
+        // of unknown size are accounted once downloaded.
+        if let Some(size) = envelope.size {
+            report.extend(acquire_bytes(ctx, folder, target, &mut batch, size).await);
+        }
+
+        let msg = match peek_then_cache(ctx, folder, source, envelope, *refresh_source_cache).await
+        {
+            Ok(msg) => msg,
             Err(err) => {
-                debug!("cannot process email hunk: {err}");
-                trace!("{err:?}");
-                None
+                report.push(processed(ctx, hunk, Some(err)).await);
+                continue;
             }
+        };
+
+        if envelope.size.is_none() {
+            report.extend(acquire_bytes(ctx, folder, target, &mut batch, msg.len()).await);
         }
-    })
-    .collect::<Vec<_>>()
-    .await;
 
-    SyncEvent::ProcessedAllEmailHunks
-        .emit(&ctx_ref.handler)
+        batch.msgs.push((envelope.flags.clone(), msg));
+        batch.hunks.push(hunk);
+    }
+
+    report.extend(add_then_cache(ctx, folder, target, &mut batch).await);
+    report
+}
+
+/// Take the given amount of bytes from the budget for the given
+/// batch.
+///
+/// When the bytes do not fit right away, the batch is sent first so
+/// that its bytes are given back to the budget, then the budget is
+/// waited for.
+async fn acquire_bytes<'a, L, R>(
+    ctx: &'a SyncPoolContext<L, R>,
+    folder: &str,
+    target: &SyncDestination,
+    batch: &mut CopyBatch<'a>,
+    bytes: usize,
+) -> Vec<(EmailSyncHunk, Option<AnyBoxedError>)>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let Some(budget) = &ctx.bytes_budget else {
+        return Vec::new();
+    };
+
+    if let Some(permit) = budget.try_acquire(bytes) {
+        batch.permits.push(permit);
+        return Vec::new();
+    }
+
+    let report = add_then_cache(ctx, folder, target, batch).await;
+    batch.permits.push(budget.acquire(bytes).await);
+    report
+}
+
+/// Cache the given envelope on the source side if requested, then
+/// download its message.
+async fn peek_then_cache<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    source: &SyncDestination,
+    envelope: &Envelope,
+    refresh_source_cache: bool,
+) -> std::result::Result<Vec<u8>, AnyBoxedError>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let id = Id::single(&envelope.id);
+
+    if refresh_source_cache {
+        let flags = envelope.flags.clone();
+        let msg = envelope.to_sync_cache_msg();
+        match source {
+            SyncDestination::Left => &ctx.left_cache,
+            SyncDestination::Right => &ctx.right_cache,
+        }
+        .add_message_with_flags(folder, msg.as_bytes(), &flags)
+        .await?;
+    }
+
+    let msgs = match source {
+        SyncDestination::Left => ctx.left.peek_messages(folder, &id).await?,
+        SyncDestination::Right => ctx.right.peek_messages(folder, &id).await?,
+    };
+
+    let msgs = msgs.to_vec();
+    let msg = msgs
+        .first()
+        .ok_or_else(|| Error::FindMessageError(envelope.id.clone()))?;
+
+    Ok(msg.raw()?.to_vec())
+}
+
+/// Add the messages of the given batch to the target folder at
+/// once, then cache their envelopes.
+///
+/// The batch is emptied, and its bytes are given back to the budget.
+async fn add_then_cache<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    target: &SyncDestination,
+    batch: &mut CopyBatch<'_>,
+) -> Vec<(EmailSyncHunk, Option<AnyBoxedError>)>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let hunks = mem::take(&mut batch.hunks);
+    let msgs = mem::take(&mut batch.msgs);
+    let mut report = Vec::with_capacity(hunks.len());
+
+    if hunks.is_empty() {
+        batch.permits.clear();
+        return report;
+    }
+
+    let ids = match target {
+        SyncDestination::Left => ctx.left.add_messages_with_flags(folder, msgs).await,
+        SyncDestination::Right => ctx.right.add_messages_with_flags(folder, msgs).await,
+    };
+
+    batch.permits.clear();
+
+    let ids = match ids {
+        Ok(ids) => ids,
+        Err(err) => {
+            // the error is attached to the first hunk, the other
+            // ones refer to it
+            let mut err = Some(err);
+            for hunk in hunks {
+                let err = err.take().unwrap_or_else(|| {
+                    Error::AddMessagesBatchError(target.clone(), folder.to_owned()).into()
+                });
+                report.push(processed(ctx, hunk, Some(err)).await);
+            }
+            return report;
+        }
+    };
+
+    for (hunk, id) in hunks.into_iter().zip(ids) {
+        let res = async {
+            match target {
+                SyncDestination::Left => {
+                    let envelope = ctx.left.get_envelope(folder, &id).await?;
+                    let msg = envelope.to_sync_cache_msg();
+                    ctx.left_cache
+                        .add_message_with_flags(folder, msg.as_bytes(), &envelope.flags)
+                        .await?;
+                }
+                SyncDestination::Right => {
+                    let envelope = ctx.right.get_envelope(folder, &id).await?;
+                    let msg = envelope.to_sync_cache_msg();
+                    ctx.right_cache
+                        .add_message_with_flags(folder, msg.as_bytes(), &envelope.flags)
+                        .await?;
+                }
+            };
+
+            Ok::<_, AnyBoxedError>(())
+        }
         .await;
 
-    Ok(report)
+        report.push(processed(ctx, hunk, res.err()).await);
+    }
+
+    report
+}
+
+/// Emit the processed event of the given hunk, then return its
+/// report entry.
+async fn processed<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    hunk: EmailSyncHunk,
+    err: Option<AnyBoxedError>,
+) -> (EmailSyncHunk, Option<AnyBoxedError>)
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    SyncEvent::ProcessedEmailHunk(hunk.clone())
+        .emit(&ctx.handler)
+        .await;
+
+    (hunk, err)
+}
+
+/// Invalidate the given cached envelopes of the given side when the
+/// identifiers of the given folder changed, for example when the
+/// UIDVALIDITY of an IMAP mailbox changed.
+///
+/// Such change usually means that the mailbox has been rebuilt, for
+/// example restored from a backup. Comparing it with the cache would
+/// turn every message missing from the backup into a deletion to
+/// propagate, so the cache is dropped then rebuilt from scratch:
+/// messages missing on one side are copied again instead of being
+/// deleted.
+async fn invalidate_cache<L, R>(
+    ctx: &SyncPoolContext<L, R>,
+    folder: &str,
+    target: SyncDestination,
+    envelopes: HashMap<String, Envelope>,
+) -> Result<HashMap<String, Envelope>>
+where
+    L: BackendContext,
+    R: BackendContext,
+{
+    let (invalidated, cache) = match target {
+        SyncDestination::Left => (ctx.left.take_invalidated_folder(folder), &ctx.left_cache),
+        SyncDestination::Right => (ctx.right.take_invalidated_folder(folder), &ctx.right_cache),
+    };
+
+    if !invalidated {
+        return Ok(envelopes);
+    }
+
+    warn!("identifiers of {target} folder {folder} changed, rebuilding its sync cache");
+
+    if !ctx.dry_run && !envelopes.is_empty() {
+        let ids: Vec<_> = envelopes.values().map(|e| e.id.clone()).collect();
+        cache
+            .add_flag(folder, &Id::multiple(ids), Flag::Deleted)
+            .await
+            .map_err(|err| Error::InvalidateCacheError(err, target.clone(), folder.to_owned()))?;
+    }
+
+    SyncEvent::InvalidatedCache(folder.to_owned(), target)
+        .emit(&ctx.handler)
+        .await;
+
+    Ok(HashMap::new())
 }
@@ -4,10 +4,10 @@
 //! structure of the module is the [`EmailSyncPatch`], which
 //! represents a list of changes (hunks).
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
 use super::*;
-use crate::flag;
+use crate::{flag, folder::sync::hunk::FolderName};
 
 /// Alias for an envelope hash map where the key is its identifier.
 pub type Envelopes = HashMap<String, Envelope>;
@@ -501,8 +501,129 @@ pub fn build(
     patch
 }
 
+/// Detect emails moved from one folder to another.
+///
+/// A move on one side shows up in the patches as a deletion from the
+/// origin folder plus a copy to the destination folder on the other
+/// side, which means downloading the email again. Such pairs are
+/// matched by Message-ID and date, then replaced by a single
+/// [`EmailSyncHunk::MoveThenCache`] hunk.
+///
+/// The given envelopes are the left and right envelopes of each
+/// folder, indexed by Message-ID. Messages without Message-ID header
+/// are never matched, since their generated Message-ID is not
+/// unique (see [`Envelope::has_generated_message_id`]).
+pub fn detect_moves(
+    patches: &mut BTreeMap<FolderName, BTreeSet<EmailSyncHunk>>,
+    left: &HashMap<FolderName, Envelopes>,
+    right: &HashMap<FolderName, Envelopes>,
+) {
+    let left_by_id = index_by_id(left);
+    let right_by_id = index_by_id(right);
+
+    // index the target envelopes of deletions by Message-ID
+    let mut deleted: HashMap<(&str, SyncDestination), Vec<(EmailSyncHunk, Envelope)>> =
+        HashMap::new();
+
+    for hunks in patches.values() {
+        for hunk in hunks {
+            let EmailSyncHunk::Delete(folder, id, target) = hunk else {
+                continue;
+            };
+
+            let envelopes = match target {
+                SyncDestination::Left => left_by_id.get(folder.as_str()),
+                SyncDestination::Right => right_by_id.get(folder.as_str()),
+            };
+
+            let Some(&envelope) = envelopes.and_then(|e| e.get(id.as_str())) else {
+                continue;
+            };
+
+            if envelope.has_generated_message_id() {
+                continue;
+            }
+
+            deleted
+                .entry((envelope.message_id.as_str(), target.clone()))
+                .or_default()
+                .push((hunk.clone(), envelope.clone()));
+        }
+    }
+
+    if deleted.is_empty() {
+        return;
+    }
+
+    let mut moves = Vec::new();
+
+    for hunks in patches.values() {
+        for hunk in hunks {
+            let EmailSyncHunk::CopyThenCache(folder, envelope, source, target, true) = hunk else {
+                continue;
+            };
+
+            let key = (envelope.message_id.as_str(), target.clone());
+            let Some(candidates) = deleted.get_mut(&key) else {
+                continue;
+            };
+
+            let Some(pos) = candidates.iter().position(|(delete, deleted)| {
+                delete.folder() != folder && deleted.date == envelope.date
+            }) else {
+                continue;
+            };
+
+            let (delete, deleted) = candidates.swap_remove(pos);
+            let from_folder = delete.folder().to_owned();
+
+            let r#move = EmailSyncHunk::MoveThenCache(
+                folder.clone(),
+                envelope.clone(),
+                source.clone(),
+                target.clone(),
+                from_folder,
+                deleted,
+            );
+
+            moves.push((delete, hunk.clone(), r#move));
+        }
+    }
+
+    for (delete, copy, r#move) in moves {
+        if let Some(hunks) = patches.get_mut(delete.folder()) {
+            hunks.remove(&delete);
+        }
+
+        if let Some(hunks) = patches.get_mut(copy.folder()) {
+            hunks.remove(&copy);
+            hunks.insert(r#move);
+        }
+    }
+}
+
+/// Index the envelopes of each folder by id.
+fn index_by_id(
+    envelopes: &HashMap<FolderName, Envelopes>,
+) -> HashMap<&str, HashMap<&str, &Envelope>> {
+    envelopes
+        .iter()
+        .map(|(folder, envelopes)| {
+            let by_id = envelopes
+                .values()
+                .map(|envelope| (envelope.id.as_str(), envelope))
+                .collect();
+            (folder.as_str(), by_id)
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+    use chrono::{DateTime, FixedOffset};
+
     use super::{EmailSyncHunk, EmailSyncPatch, Envelopes};
     use crate::{
         envelope::Envelope,
@@ -1285,4 +1406,112 @@ fn build_patch_1110() {
             ])
         );
     }
+
+    #[test]
+    fn detect_moves() {
+        let moved = Envelope {
+            id: "left-id".into(),
+            message_id: "moved".into(),
+            ..Envelope::default()
+        };
+        let remote = Envelope {
+            id: "right-id".into(),
+            message_id: "moved".into(),
+            ..Envelope::default()
+        };
+
+        let left = HashMap::from_iter([(
+            "archives".to_owned(),
+            Envelopes::from_iter([("moved".into(), moved.clone())]),
+        )]);
+        let right = HashMap::from_iter([(
+            "inbox".to_owned(),
+            Envelopes::from_iter([("moved".into(), remote.clone())]),
+        )]);
+
+        let copy = EmailSyncHunk::CopyThenCache(
+            "archives".into(),
+            moved.clone(),
+            SyncDestination::Left,
+            SyncDestination::Right,
+            true,
+        );
+        let uncache = EmailSyncHunk::Uncache(
+            "inbox".into(),
+            "right-cache-id".into(),
+            SyncDestination::Right,
+        );
+        let delete =
+            EmailSyncHunk::Delete("inbox".into(), "right-id".into(), SyncDestination::Right);
+
+        let mut patches = BTreeMap::from_iter([
+            ("archives".to_owned(), BTreeSet::from_iter([copy])),
+            (
+                "inbox".to_owned(),
+                BTreeSet::from_iter([uncache.clone(), delete]),
+            ),
+        ]);
+
+        super::detect_moves(&mut patches, &left, &right);
+
+        assert_eq!(
+            patches,
+            BTreeMap::from_iter([
+                (
+                    "archives".to_owned(),
+                    BTreeSet::from_iter([EmailSyncHunk::MoveThenCache(
+                        "archives".into(),
+                        moved,
+                        SyncDestination::Left,
+                        SyncDestination::Right,
+                        "inbox".into(),
+                        remote,
+                    )]),
+                ),
+                ("inbox".to_owned(), BTreeSet::from_iter([uncache])),
+            ])
+        );
+    }
+
+    #[test]
+    fn detect_moves_skips_generated_message_ids() {
+        // messages without Message-ID sharing the same date share
+        // the same generated Message-ID
+        let date = DateTime::<FixedOffset>::default();
+        let archived = Envelope::from_raw_headers("left-id", Flags::default(), b"Subject: A\n");
+        let deleted = Envelope::from_raw_headers("right-id", Flags::default(), b"Subject: B\n");
+        assert!(archived.has_generated_message_id());
+        assert_eq!(archived.message_id, deleted.message_id);
+        assert_eq!(archived.date, date);
+        assert_eq!(deleted.date, date);
+
+        let left = HashMap::from_iter([(
+            "archives".to_owned(),
+            Envelopes::from_iter([(archived.message_id.clone(), archived.clone())]),
+        )]);
+        let right = HashMap::from_iter([(
+            "inbox".to_owned(),
+            Envelopes::from_iter([(deleted.message_id.clone(), deleted)]),
+        )]);
+
+        let copy = EmailSyncHunk::CopyThenCache(
+            "archives".into(),
+            archived,
+            SyncDestination::Left,
+            SyncDestination::Right,
+            true,
+        );
+        let delete =
+            EmailSyncHunk::Delete("inbox".into(), "right-id".into(), SyncDestination::Right);
+
+        let mut patches = BTreeMap::from_iter([
+            ("archives".to_owned(), BTreeSet::from_iter([copy])),
+            ("inbox".to_owned(), BTreeSet::from_iter([delete])),
+        ]);
+        let expected = patches.clone();
+
+        super::detect_moves(&mut patches, &left, &right);
+
+        assert_eq!(patches, expected);
+    }
 }
@@ -27,6 +27,8 @@ pub(crate) mod address {
     use once_cell::sync::Lazy;
     use regex::Regex;
 
+    use crate::envelope::address::normalize_addr;
+
     /// Regex used to detect if an email address is a noreply one.
     ///
     /// Matches usual names like `no_reply`, `noreply`, but also
@@ -42,22 +44,28 @@ pub(crate) fn is_empty(header: &parser::HeaderValue) -> bool {
         }
     }
 
+    /// Push addresses from the given header to the given builder
+    /// addresses.
+    ///
+    /// Addresses already pushed, noreply addresses and addresses for
+    /// which `is_own_address` returns `true` are skipped.
     pub(crate) fn push_builder_address<'a>(
         all_emails: &mut HashSet<Cow<'a, str>>,
         all_addrs: &mut Vec<builder::Address<'a>>,
         header: &'a parser::HeaderValue,
+        is_own_address: impl Fn(&str) -> bool,
     ) {
         match header {
             parser::HeaderValue::Address(parser::Address::List(addrs)) => {
                 for addr in addrs {
                     if let Some(email) = addr.address.as_ref() {
                         if let Some(addr) = &addr.address {
-                            if NO_REPLY.is_match(addr) {
+                            if NO_REPLY.is_match(addr) || is_own_address(addr) {
                                 continue;
                             }
                         }
 
-                        if all_emails.insert(email.clone()) {
+                        if all_emails.insert(Cow::Owned(normalize_addr(email))) {
                             all_addrs.push(builder::Address::new_address(
                                 addr.name.clone(),
                                 email.clone(),
@@ -75,11 +83,14 @@ pub(crate) fn push_builder_address<'a>(
                                 .addresses
                                 .iter()
                                 .filter_map(|addr| {
-                                    addr.address.as_ref().map(|email| {
-                                        let name = addr.name.clone();
-                                        let email = email.as_ref();
-                                        builder::Address::new_address(name, email)
-                                    })
+                                    let email = addr.address.as_ref()?;
+
+                                    if is_own_address(email) {
+                                        return None;
+                                    }
+
+                                    let name = addr.name.clone();
+                                    Some(builder::Address::new_address(name, email.as_ref()))
                                 })
                                 .collect();
 
@@ -0,0 +1,94 @@
+use async_trait::async_trait;
+use tracing::{debug, info};
+use utf7_imap::encode_utf7_imap as encode_utf7;
+
+use super::{AclEntry, AclRights, AclRightsModification, ManageFolderAcl};
+use crate::{
+    imap::{Error, ImapClient, ImapContext},
+    AnyResult,
+};
+
+#[derive(Debug)]
+pub struct ManageImapFolderAcl {
+    ctx: ImapContext,
+}
+
+impl ManageImapFolderAcl {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn ManageFolderAcl> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn ManageFolderAcl>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+/// Return the UTF-7 encoded name of the given folder, or fail when
+/// the server does not support the ACL extension.
+fn encode_folder(client: &ImapClient, folder: &str) -> AnyResult<String> {
+    if !client.ext_acl_supported() {
+        return Err(Error::AclNotSupportedError.into());
+    }
+
+    let folder = client.account_config.get_folder_alias(folder);
+    let folder_encoded = encode_utf7(folder);
+    debug!("utf7 encoded folder: {folder_encoded}");
+
+    Ok(folder_encoded)
+}
+
+#[async_trait]
+impl ManageFolderAcl for ManageImapFolderAcl {
+    async fn get_folder_acl(&self, folder: &str) -> AnyResult<Vec<AclEntry>> {
+        info!("getting acl of imap folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+        let folder_encoded = encode_folder(&client, folder)?;
+        let acl = client.get_acl(&folder_encoded).await?;
+        debug!(?acl, "got acl of folder {folder}");
+
+        Ok(acl)
+    }
+
+    async fn set_folder_acl(
+        &self,
+        folder: &str,
+        identifier: &str,
+        modification: &AclRightsModification,
+    ) -> AnyResult<()> {
+        info!("setting rights {modification} of {identifier} on imap folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+        let folder_encoded = encode_folder(&client, folder)?;
+        client
+            .set_acl(&folder_encoded, identifier, modification)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn delete_folder_acl(&self, folder: &str, identifier: &str) -> AnyResult<()> {
+        info!("deleting rights of {identifier} on imap folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+        let folder_encoded = encode_folder(&client, folder)?;
+        client.delete_acl(&folder_encoded, identifier).await?;
+
+        Ok(())
+    }
+
+    async fn get_folder_rights(&self, folder: &str) -> AnyResult<AclRights> {
+        info!("getting own rights on imap folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+        let folder_encoded = encode_folder(&client, folder)?;
+        let rights = client.my_rights(&folder_encoded).await?;
+        debug!(%rights, "got own rights on folder {folder}");
+
+        Ok(rights)
+    }
+}
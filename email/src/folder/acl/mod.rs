@@ -0,0 +1,295 @@
+//! # Folder ACL
+//!
+//! Module dedicated to the access control lists of folders, used by
+//! shared and delegated mailboxes, see the [`ManageFolderAcl`]
+//! backend feature. It contains the typed access rights
+//! [`AclRights`], modeled after the IMAP ACL extension (RFC 4314),
+//! as well as the modifications sent to change them
+//! [`AclRightsModification`].
+
+#[cfg(feature = "imap")]
+pub mod imap;
+
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use async_trait::async_trait;
+
+use crate::AnyResult;
+
+/// The ACL access right.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum AclRight {
+    /// `l`: the mailbox is visible to LIST and LSUB commands, and
+    /// can be subscribed to.
+    Lookup,
+
+    /// `r`: the mailbox can be selected, and its messages can be
+    /// fetched, searched and copied.
+    Read,
+
+    /// `s`: the `\Seen` flag is kept across sessions.
+    KeepSeen,
+
+    /// `w`: flags other than `\Seen` and `\Deleted` can be written.
+    Write,
+
+    /// `i`: messages can be appended and copied into the mailbox.
+    Insert,
+
+    /// `p`: messages can be submitted to the submission address of
+    /// the mailbox.
+    Post,
+
+    /// `k`: mailboxes can be created under the mailbox, or renamed
+    /// to it.
+    CreateMailbox,
+
+    /// `x`: the mailbox can be deleted or renamed.
+    DeleteMailbox,
+
+    /// `t`: the `\Deleted` flag can be written.
+    DeleteMessages,
+
+    /// `e`: the mailbox can be expunged.
+    Expunge,
+
+    /// `a`: the access rights of the mailbox can be administered.
+    Administer,
+
+    /// Any implementation-defined right, like digits.
+    Other(char),
+}
+
+impl AclRight {
+    /// Return the character representing the right.
+    pub fn as_char(&self) -> char {
+        match self {
+            Self::Lookup => 'l',
+            Self::Read => 'r',
+            Self::KeepSeen => 's',
+            Self::Write => 'w',
+            Self::Insert => 'i',
+            Self::Post => 'p',
+            Self::CreateMailbox => 'k',
+            Self::DeleteMailbox => 'x',
+            Self::DeleteMessages => 't',
+            Self::Expunge => 'e',
+            Self::Administer => 'a',
+            Self::Other(c) => *c,
+        }
+    }
+}
+
+impl From<char> for AclRight {
+    fn from(c: char) -> Self {
+        match c {
+            'l' => Self::Lookup,
+            'r' => Self::Read,
+            's' => Self::KeepSeen,
+            'w' => Self::Write,
+            'i' => Self::Insert,
+            'p' => Self::Post,
+            'k' => Self::CreateMailbox,
+            'x' => Self::DeleteMailbox,
+            't' => Self::DeleteMessages,
+            'e' => Self::Expunge,
+            'a' => Self::Administer,
+            c => Self::Other(c),
+        }
+    }
+}
+
+impl fmt::Display for AclRight {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_char())
+    }
+}
+
+/// The set of ACL access rights.
+///
+/// The obsolete RFC 2086 rights are converted on parsing, as defined
+/// in RFC 4314 section 2.1.1: `c` becomes `k`, and `d` becomes `t`
+/// and `e`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct AclRights(BTreeSet<AclRight>);
+
+impl AclRights {
+    /// Return `true` if the set contains the given right.
+    pub fn contains(&self, right: AclRight) -> bool {
+        self.0.contains(&right)
+    }
+
+    /// Return `true` if the set contains all the given rights.
+    pub fn contains_all(&self, rights: &AclRights) -> bool {
+        self.0.is_superset(&rights.0)
+    }
+
+    /// Return `true` if the set contains no right.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Add the given right.
+    pub fn insert(&mut self, right: AclRight) -> bool {
+        self.0.insert(right)
+    }
+
+    /// Remove the given right.
+    pub fn remove(&mut self, right: AclRight) -> bool {
+        self.0.remove(&right)
+    }
+
+    /// Iterate over the rights.
+    pub fn iter(&self) -> impl Iterator<Item = &AclRight> {
+        self.0.iter()
+    }
+
+    /// Apply the given modification, the way servers do when
+    /// receiving a `SETACL` command.
+    pub fn apply(&mut self, modification: &AclRightsModification) {
+        match modification {
+            AclRightsModification::Replace(rights) => {
+                self.0.clone_from(&rights.0);
+            }
+            AclRightsModification::Add(rights) => {
+                self.0.extend(rights.iter().copied());
+            }
+            AclRightsModification::Remove(rights) => {
+                self.0.retain(|right| !rights.contains(*right));
+            }
+        }
+    }
+}
+
+impl FromIterator<AclRight> for AclRights {
+    fn from_iter<T: IntoIterator<Item = AclRight>>(iter: T) -> Self {
+        Self(BTreeSet::from_iter(iter))
+    }
+}
+
+impl From<&str> for AclRights {
+    fn from(rights: &str) -> Self {
+        rights
+            .chars()
+            .flat_map(|c| match c {
+                'c' => vec![AclRight::CreateMailbox],
+                'd' => vec![AclRight::DeleteMessages, AclRight::Expunge],
+                c => vec![AclRight::from(c)],
+            })
+            .collect()
+    }
+}
+
+impl FromStr for AclRights {
+    type Err = std::convert::Infallible;
+
+    fn from_str(rights: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(rights))
+    }
+}
+
+impl fmt::Display for AclRights {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for right in &self.0 {
+            write!(f, "{right}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The ACL rights modification, as sent by IMAP `SETACL` commands.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum AclRightsModification {
+    /// The rights replace the existing ones.
+    Replace(AclRights),
+
+    /// The rights are added to the existing ones (`+` prefix).
+    Add(AclRights),
+
+    /// The rights are removed from the existing ones (`-` prefix).
+    Remove(AclRights),
+}
+
+impl From<&str> for AclRightsModification {
+    fn from(modification: &str) -> Self {
+        if let Some(rights) = modification.strip_prefix('+') {
+            Self::Add(rights.into())
+        } else if let Some(rights) = modification.strip_prefix('-') {
+            Self::Remove(rights.into())
+        } else {
+            Self::Replace(modification.into())
+        }
+    }
+}
+
+impl fmt::Display for AclRightsModification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Replace(rights) => write!(f, "{rights}"),
+            Self::Add(rights) => write!(f, "+{rights}"),
+            Self::Remove(rights) => write!(f, "-{rights}"),
+        }
+    }
+}
+
+/// The ACL entry, associating an identifier (a user or a group)
+/// with its access rights.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AclEntry {
+    /// The identifier the rights are granted to.
+    ///
+    /// The special identifier `anyone` refers to all users, and
+    /// identifiers starting with a `-` refer to negative rights.
+    pub identifier: String,
+
+    /// The access rights.
+    pub rights: AclRights,
+}
+
+#[async_trait]
+pub trait ManageFolderAcl: Send + Sync {
+    /// Get the access control list of the given folder.
+    async fn get_folder_acl(&self, folder: &str) -> AnyResult<Vec<AclEntry>>;
+
+    /// Modify the access rights granted to the given identifier on
+    /// the given folder.
+    async fn set_folder_acl(
+        &self,
+        folder: &str,
+        identifier: &str,
+        modification: &AclRightsModification,
+    ) -> AnyResult<()>;
+
+    /// Remove the given identifier from the access control list of
+    /// the given folder.
+    async fn delete_folder_acl(&self, folder: &str, identifier: &str) -> AnyResult<()>;
+
+    /// Get the access rights of the current user on the given
+    /// folder.
+    async fn get_folder_rights(&self, folder: &str) -> AnyResult<AclRights>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AclRight, AclRights, AclRightsModification};
+
+    #[test]
+    fn parse_and_apply() {
+        let mut rights = AclRights::from("lrswd9");
+        assert_eq!(rights.to_string(), "lrswte9");
+        assert!(rights.contains(AclRight::Expunge));
+        assert!(rights.contains(AclRight::Other('9')));
+        assert!(rights.contains_all(&"lr".into()));
+
+        let modification = AclRightsModification::from("-te");
+        assert_eq!(modification.to_string(), "-te");
+        rights.apply(&modification);
+        assert_eq!(rights.to_string(), "lrsw9");
+
+        rights.apply(&"+ic".into());
+        assert_eq!(rights.to_string(), "lrswik9");
+
+        rights.apply(&"lr".into());
+        assert_eq!(rights.to_string(), "lr");
+    }
+}
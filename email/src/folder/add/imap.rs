@@ -29,7 +29,7 @@ impl AddFolder for AddImapFolder {
     async fn add_folder(&self, folder: &str) -> AnyResult<()> {
         info!("creating imap folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
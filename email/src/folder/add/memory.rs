@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::AddFolder;
+use crate::{memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct AddMemoryFolder {
+    ctx: MemoryContextSync,
+}
+
+impl AddMemoryFolder {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn AddFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn AddFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl AddFolder for AddMemoryFolder {
+    async fn add_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("creating memory folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        self.ctx.lock().add_folder(&folder)?;
+
+        Ok(())
+    }
+}
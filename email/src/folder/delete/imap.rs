@@ -29,7 +29,7 @@ impl DeleteFolder for DeleteImapFolder {
     async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
         info!("deleting imap folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
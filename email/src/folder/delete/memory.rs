@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::DeleteFolder;
+use crate::{memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct DeleteMemoryFolder {
+    ctx: MemoryContextSync,
+}
+
+impl DeleteMemoryFolder {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn DeleteFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn DeleteFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl DeleteFolder for DeleteMemoryFolder {
+    async fn delete_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("deleting memory folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        self.ctx.lock().delete_folder(&folder)?;
+
+        Ok(())
+    }
+}
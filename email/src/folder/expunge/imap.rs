@@ -29,7 +29,7 @@ impl ExpungeFolder for ExpungeImapFolder {
     async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
         info!("expunging imap folder {folder}");
 
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
         let config = &client.account_config;
 
         let folder = config.get_folder_alias(folder);
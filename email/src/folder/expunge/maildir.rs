@@ -1,8 +1,12 @@
 use async_trait::async_trait;
-use tracing::info;
+use tracing::{debug, info, warn};
 
 use super::ExpungeFolder;
-use crate::{folder::error::Error, maildir::MaildirContextSync, AnyResult};
+use crate::{
+    folder::error::Error,
+    maildir::{quota::message_size, MaildirContextSync},
+    AnyResult,
+};
 
 pub struct ExpungeMaildirFolder {
     ctx: MaildirContextSync,
@@ -29,19 +33,34 @@ async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
 
         let ctx = self.ctx.lock().await;
         let mdir = ctx.get_maildir_from_folder_alias(folder)?;
+        let flags = ctx.maildir_config.find_expunge_flags()?;
+        debug!(?flags, "expunging maildir messages with flags");
 
         let entries = mdir
             .read()
             .map_err(|err| Error::ListCurrentFolderMaildirError(err, mdir.path().to_owned()))?;
 
+        let (mut size, mut count) = (0, 0);
+
         entries
-            .filter(|entry| entry.has_trash_flag())
+            .filter(|entry| match entry.flags() {
+                Ok(entry_flags) => !entry_flags.is_disjoint(&flags),
+                Err(_) => false,
+            })
             .try_for_each(|entry| {
+                size += message_size(entry.path()).unwrap_or_default() as i64;
+                count += 1;
                 entry
                     .remove()
                     .map_err(|err| Error::RemoveMaildirEntryError(err, entry.path().to_owned()))
             })?;
 
+        // messages are already removed, so the operation must not
+        // fail: the quota file is fixed by its next recalculation
+        if let Err(err) = ctx.update_quota(-size, -count) {
+            warn!(?err, "cannot update maildir quota, skipping it");
+        }
+
         Ok(())
     }
 }
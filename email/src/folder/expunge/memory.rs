@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::ExpungeFolder;
+use crate::{flag::Flag, memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct ExpungeMemoryFolder {
+    ctx: MemoryContextSync,
+}
+
+impl ExpungeMemoryFolder {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn ExpungeFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn ExpungeFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ExpungeFolder for ExpungeMemoryFolder {
+    async fn expunge_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("expunging memory folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        let mut store = self.ctx.lock();
+
+        store
+            .folder_mut(&folder)?
+            .retain(|msg| !msg.flags.contains(&Flag::Deleted));
+
+        Ok(())
+    }
+}
@@ -29,7 +29,7 @@ async fn list_folders(&self) -> AnyResult<Folders> {
         info!("listing imap folders");
 
         let config = &self.ctx.account_config;
-        let mut client = self.ctx.client().await;
+        let mut client = self.ctx.client().await?;
 
         let folders = client.list_all_mailboxes(config).await?;
 
@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::ListFolders;
+use crate::{
+    folder::{Folder, Folders},
+    memory::MemoryContextSync,
+    AnyResult,
+};
+
+#[derive(Clone)]
+pub struct ListMemoryFolders {
+    ctx: MemoryContextSync,
+}
+
+impl ListMemoryFolders {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn ListFolders> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn ListFolders>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl ListFolders for ListMemoryFolders {
+    async fn list_folders(&self) -> AnyResult<Folders> {
+        info!("listing memory folders");
+
+        let config = &self.ctx.account_config;
+        let store = self.ctx.lock();
+
+        let folders = Folders::from_iter(store.folders().map(|name| {
+            Folder {
+                kind: config
+                    .find_folder_kind_from_alias(name)
+                    .or_else(|| name.parse().ok()),
+                name: name.to_owned(),
+                desc: String::new(),
+            }
+        }));
+
+        Ok(folders)
+    }
+}
@@ -8,10 +8,11 @@
 //! the account configuration.
 //!
 //! Backend features reside in their own module as well: [`add`],
-//! [`list`], [`expunge`], [`purge`], [`delete`].
+//! [`list`], [`expunge`], [`purge`], [`delete`], [`quota`], [`acl`].
 //!
 //! Finally, the [`sync`] module contains everything needed to
 //! synchronize a remote folder with a local one.
+pub mod acl;
 pub mod add;
 pub mod config;
 pub mod delete;
@@ -23,6 +24,7 @@
 #[cfg(feature = "maildir")]
 pub mod maildir;
 pub mod purge;
+pub mod quota;
 #[cfg(feature = "sync")]
 pub mod sync;
 
@@ -0,0 +1,36 @@
+use async_trait::async_trait;
+use tracing::info;
+
+use super::PurgeFolder;
+use crate::{memory::MemoryContextSync, AnyResult};
+
+#[derive(Clone)]
+pub struct PurgeMemoryFolder {
+    ctx: MemoryContextSync,
+}
+
+impl PurgeMemoryFolder {
+    pub fn new(ctx: &MemoryContextSync) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &MemoryContextSync) -> Box<dyn PurgeFolder> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &MemoryContextSync) -> Option<Box<dyn PurgeFolder>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl PurgeFolder for PurgeMemoryFolder {
+    async fn purge_folder(&self, folder: &str) -> AnyResult<()> {
+        info!("purging memory folder {folder}");
+
+        let folder = self.ctx.account_config.get_folder_alias(folder);
+        self.ctx.lock().folder_mut(&folder)?.retain(|_| false);
+
+        Ok(())
+    }
+}
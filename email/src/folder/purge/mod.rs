@@ -1,5 +1,7 @@
 #[cfg(feature = "imap")]
 pub mod imap;
+#[cfg(feature = "memory")]
+pub mod memory;
 
 use async_trait::async_trait;
 
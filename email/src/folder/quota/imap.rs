@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use tracing::{debug, info};
+use utf7_imap::encode_utf7_imap as encode_utf7;
+
+use super::{GetQuota, Quota};
+use crate::{imap::ImapContext, AnyResult};
+
+#[derive(Debug)]
+pub struct GetImapQuota {
+    ctx: ImapContext,
+}
+
+impl GetImapQuota {
+    pub fn new(ctx: &ImapContext) -> Self {
+        Self { ctx: ctx.clone() }
+    }
+
+    pub fn new_boxed(ctx: &ImapContext) -> Box<dyn GetQuota> {
+        Box::new(Self::new(ctx))
+    }
+
+    pub fn some_new_boxed(ctx: &ImapContext) -> Option<Box<dyn GetQuota>> {
+        Some(Self::new_boxed(ctx))
+    }
+}
+
+#[async_trait]
+impl GetQuota for GetImapQuota {
+    async fn get_quota(&self, folder: &str) -> AnyResult<Quota> {
+        info!("getting quota of imap folder {folder}");
+
+        let mut client = self.ctx.client().await?;
+
+        if !client.ext_quota_supported() {
+            debug!("IMAP QUOTA extension not supported, no quota applies");
+            return Ok(Quota::default());
+        }
+
+        let config = &client.account_config;
+
+        let folder = config.get_folder_alias(folder);
+        let folder_encoded = encode_utf7(folder.clone());
+        debug!("utf7 encoded folder: {folder_encoded}");
+
+        let quota = client.get_quota_root(&folder_encoded).await?;
+        debug!(?quota, "got quota of folder {folder}");
+
+        Ok(quota)
+    }
+}
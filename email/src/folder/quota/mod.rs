@@ -0,0 +1,110 @@
+//! # Folder quota
+//!
+//! Module dedicated to the storage quota of folders, see the
+//! [`GetQuota`] backend feature. Frontends can use it to warn users
+//! before their mailbox becomes full.
+
+#[cfg(feature = "imap")]
+pub mod imap;
+
+use async_trait::async_trait;
+
+use crate::AnyResult;
+
+/// The usage of a quota resource.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct QuotaUsage {
+    /// The current usage of the resource.
+    pub used: u64,
+
+    /// The maximum usage of the resource.
+    pub limit: u64,
+}
+
+impl QuotaUsage {
+    pub fn new(used: u64, limit: u64) -> Self {
+        Self { used, limit }
+    }
+
+    /// Return the ratio of the limit currently used, between 0 and
+    /// 1 (or above when the limit is exceeded).
+    pub fn ratio(&self) -> f64 {
+        if self.limit == 0 {
+            return 1.0;
+        }
+
+        self.used as f64 / self.limit as f64
+    }
+
+    /// Return `true` if the usage reached the limit.
+    pub fn is_exceeded(&self) -> bool {
+        self.used >= self.limit
+    }
+}
+
+/// The quota applying to a folder.
+///
+/// Resources not limited by the server are `None`. When several
+/// quotas apply to the same folder, the most used one is kept for
+/// each resource.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct Quota {
+    /// The storage usage, in bytes.
+    pub storage: Option<QuotaUsage>,
+
+    /// The number of messages.
+    pub messages: Option<QuotaUsage>,
+}
+
+impl Quota {
+    /// Set the storage usage, unless a more used one is already
+    /// set.
+    pub fn merge_storage(&mut self, usage: QuotaUsage) {
+        merge(&mut self.storage, usage)
+    }
+
+    /// Set the messages usage, unless a more used one is already
+    /// set.
+    pub fn merge_messages(&mut self, usage: QuotaUsage) {
+        merge(&mut self.messages, usage)
+    }
+
+    /// Return `true` if no resource is limited.
+    pub fn is_empty(&self) -> bool {
+        self.storage.is_none() && self.messages.is_none()
+    }
+
+    /// Return `true` if the usage of any resource reached the given
+    /// ratio, for example `0.9` for 90%.
+    pub fn is_above(&self, ratio: f64) -> bool {
+        [self.storage, self.messages]
+            .into_iter()
+            .flatten()
+            .any(|usage| usage.ratio() >= ratio)
+    }
+}
+
+fn merge(current: &mut Option<QuotaUsage>, usage: QuotaUsage) {
+    match current {
+        Some(current) if current.ratio() >= usage.ratio() => (),
+        _ => *current = Some(usage),
+    }
+}
+
+#[async_trait]
+pub trait GetQuota: Send + Sync {
+    /// Get the quota applying to the given folder.
+    ///
+    /// Returns an empty [`Quota`] when no quota applies.
+    async fn get_quota(&self, folder: &str) -> AnyResult<Quota>;
+}
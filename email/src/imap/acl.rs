@@ -0,0 +1,99 @@
+//! # IMAP ACL
+//!
+//! Module dedicated to the IMAP ACL extension (RFC 4314), used by
+//! shared and delegated mailboxes. The access rights are typed by
+//! the [`crate::folder::acl`] module.
+//!
+//! The IMAP codec cannot encode `GETACL`, `SETACL`, `DELETEACL` and
+//! `MYRIGHTS` commands yet, nor decode their responses: commands are
+//! sent as raw commands, and their responses are parsed by this
+//! module. See [`super::ImapClient::get_acl`],
+//! [`super::ImapClient::set_acl`], [`super::ImapClient::delete_acl`]
+//! and [`super::ImapClient::my_rights`].
+
+use super::raw::tokenize;
+use crate::folder::acl::{AclEntry, AclRights};
+
+/// Parse the given untagged `ACL` response, like `ACL INBOX alice
+/// lrswipkxtea anyone lr`, into the mailbox name and its entries.
+pub(crate) fn parse_acl(response: &[u8]) -> Option<(String, Vec<AclEntry>)> {
+    let tokens = tokenize(response)?;
+    let (name, tokens) = tokens.split_first()?;
+
+    if !name.is_atom("ACL") {
+        return None;
+    }
+
+    let (mbox, tokens) = tokens.split_first()?;
+    let pairs = tokens.chunks_exact(2);
+
+    if !pairs.remainder().is_empty() {
+        return None;
+    }
+
+    let entries = pairs
+        .map(|pair| {
+            Some(AclEntry {
+                identifier: pair[0].as_astring()?,
+                rights: AclRights::from(pair[1].as_astring()?.as_str()),
+            })
+        })
+        .collect::<Option<_>>()?;
+
+    Some((mbox.as_astring()?, entries))
+}
+
+/// Parse the given untagged `MYRIGHTS` response, like `MYRIGHTS
+/// INBOX lrs`, into the mailbox name and the rights of the current
+/// user.
+pub(crate) fn parse_my_rights(response: &[u8]) -> Option<(String, AclRights)> {
+    match tokenize(response)?.as_slice() {
+        [name, mbox, rights] if name.is_atom("MYRIGHTS") => Some((
+            mbox.as_astring()?,
+            AclRights::from(rights.as_astring()?.as_str()),
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::folder::acl::{AclEntry, AclRights};
+
+    #[test]
+    fn parse_acl() {
+        let (mbox, entries) =
+            super::parse_acl(br#"ACL "Shared Box" alice lrswipkxtea "-bob" lr"#).unwrap();
+
+        assert_eq!(mbox, "Shared Box");
+        assert_eq!(
+            entries,
+            vec![
+                AclEntry {
+                    identifier: "alice".into(),
+                    rights: AclRights::from("lrswipkxtea"),
+                },
+                AclEntry {
+                    identifier: "-bob".into(),
+                    rights: AclRights::from("lr"),
+                },
+            ]
+        );
+
+        let (_, entries) = super::parse_acl(b"ACL INBOX").unwrap();
+        assert!(entries.is_empty());
+
+        assert_eq!(super::parse_acl(b"ACL INBOX alice"), None);
+        assert_eq!(super::parse_acl(b"MYRIGHTS INBOX lr"), None);
+    }
+
+    #[test]
+    fn parse_my_rights() {
+        let (mbox, rights) = super::parse_my_rights(b"MYRIGHTS INBOX lrsc").unwrap();
+        assert_eq!(mbox, "INBOX");
+        assert_eq!(rights.to_string(), "lrsk");
+
+        assert_eq!(super::parse_my_rights(b"MYRIGHTS INBOX"), None);
+        assert_eq!(super::parse_my_rights(b"ACL INBOX alice lr"), None);
+    }
+}
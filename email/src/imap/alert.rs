@@ -0,0 +1,242 @@
+//! # IMAP alerts
+//!
+//! Module dedicated to IMAP server notices. Servers may send
+//! human-readable messages that must be shown to the user, either
+//! using the `[ALERT]` response code (RFC 3501 section 7.1) or using
+//! untagged `NO` responses (warnings). The [`AlertTask`] wraps any
+//! IMAP task in order to collect them into [`ImapAlerts`] instead of
+//! swallowing them.
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use imap_client::{
+    imap_next::imap_types::{
+        auth::AuthenticateData,
+        command::CommandBody,
+        response::{Bye, Code, CommandContinuationRequest, Data, StatusBody, StatusKind},
+    },
+    tasks::Task,
+};
+use tracing::warn;
+
+/// The maximum number of alerts kept until they are taken.
+pub const MAX_IMAP_ALERTS: usize = 32;
+
+/// The kind of IMAP server notice.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImapAlertKind {
+    /// The notice comes with the `[ALERT]` response code.
+    Alert,
+
+    /// The notice comes from an untagged `NO` response.
+    Warning,
+}
+
+/// The IMAP server notice.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ImapAlert {
+    /// The kind of notice.
+    pub kind: ImapAlertKind,
+
+    /// The human-readable text sent by the server.
+    pub text: String,
+}
+
+impl ImapAlert {
+    /// Build an alert from the given status, if the status contains
+    /// a notice the user should be aware of.
+    pub fn from_status_body(status_body: &StatusBody<'_>) -> Option<Self> {
+        let kind = match (&status_body.kind, &status_body.code) {
+            (_, Some(Code::Alert)) => ImapAlertKind::Alert,
+            (StatusKind::No, _) => ImapAlertKind::Warning,
+            _ => return None,
+        };
+
+        Some(Self {
+            kind,
+            text: status_body.text.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for ImapAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ImapAlertKind::Alert => write!(f, "IMAP server alert: {}", self.text),
+            ImapAlertKind::Warning => write!(f, "IMAP server warning: {}", self.text),
+        }
+    }
+}
+
+/// The IMAP alerts collector.
+///
+/// The collector is bounded: when full, the oldest alerts are
+/// dropped first. Cloning the collector shares the same alerts.
+#[derive(Clone, Debug, Default)]
+pub struct ImapAlerts(Arc<Mutex<VecDeque<ImapAlert>>>);
+
+impl ImapAlerts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the given alert.
+    ///
+    /// An alert identical to the last collected one is ignored, since
+    /// servers tend to repeat them at every command.
+    pub fn push(&self, alert: ImapAlert) {
+        let mut alerts = self.0.lock().unwrap_or_else(|err| err.into_inner());
+
+        if alerts.back() == Some(&alert) {
+            return;
+        }
+
+        warn!(text = alert.text, kind = ?alert.kind, "received IMAP server notice");
+
+        if alerts.len() >= MAX_IMAP_ALERTS {
+            alerts.pop_front();
+        }
+
+        alerts.push_back(alert);
+    }
+
+    /// Take the alerts collected since the last call.
+    pub fn take(&self) -> Vec<ImapAlert> {
+        let mut alerts = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        alerts.drain(..).collect()
+    }
+}
+
+/// The task wrapper collecting IMAP alerts.
+///
+/// Responses are first given to the inner task. Untagged responses
+/// the inner task is not interested in are collected if they contain
+/// an alert, instead of being considered unsolicited.
+pub struct AlertTask<T: Task> {
+    alerts: ImapAlerts,
+    task: T,
+}
+
+impl<T: Task> AlertTask<T> {
+    pub fn new(alerts: &ImapAlerts, task: T) -> Self {
+        Self {
+            alerts: alerts.clone(),
+            task,
+        }
+    }
+}
+
+impl<T: Task> Task for AlertTask<T> {
+    type Output = T::Output;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        self.task.command_body()
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        self.task.process_data(data)
+    }
+
+    fn process_untagged(
+        &mut self,
+        status_body: StatusBody<'static>,
+    ) -> Option<StatusBody<'static>> {
+        let status_body = self.task.process_untagged(status_body)?;
+
+        match ImapAlert::from_status_body(&status_body) {
+            Some(alert) => {
+                self.alerts.push(alert);
+                None
+            }
+            None => Some(status_body),
+        }
+    }
+
+    fn process_continuation_request(
+        &mut self,
+        continuation: CommandContinuationRequest<'static>,
+    ) -> Option<CommandContinuationRequest<'static>> {
+        self.task.process_continuation_request(continuation)
+    }
+
+    fn process_continuation_request_authenticate(
+        &mut self,
+        continuation: CommandContinuationRequest<'static>,
+    ) -> Result<AuthenticateData<'static>, CommandContinuationRequest<'static>> {
+        self.task
+            .process_continuation_request_authenticate(continuation)
+    }
+
+    fn process_bye(&mut self, bye: Bye<'static>) -> Option<Bye<'static>> {
+        self.task.process_bye(bye)
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        // only the ALERT response code is collected here: tagged NO
+        // responses are errors, already surfaced by the inner task
+        if let Some(Code::Alert) = status_body.code {
+            if let Some(alert) = ImapAlert::from_status_body(&status_body) {
+                self.alerts.push(alert);
+            }
+        }
+
+        self.task.process_tagged(status_body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::imap_types::response::{Code, StatusBody, StatusKind};
+
+    use super::{ImapAlert, ImapAlertKind, ImapAlerts, MAX_IMAP_ALERTS};
+
+    fn status_body(
+        kind: StatusKind,
+        code: Option<Code<'static>>,
+        text: &str,
+    ) -> StatusBody<'static> {
+        StatusBody {
+            kind,
+            code,
+            text: text.to_owned().try_into().unwrap(),
+        }
+    }
+
+    #[test]
+    fn from_status_body() {
+        let body = status_body(StatusKind::Ok, Some(Code::Alert), "mailbox almost full");
+        let alert = ImapAlert::from_status_body(&body).unwrap();
+        assert_eq!(alert.kind, ImapAlertKind::Alert);
+        assert_eq!(alert.text, "mailbox almost full");
+
+        let body = status_body(StatusKind::No, None, "disk quota exceeded");
+        let alert = ImapAlert::from_status_body(&body).unwrap();
+        assert_eq!(alert.kind, ImapAlertKind::Warning);
+
+        let body = status_body(StatusKind::Ok, None, "done");
+        assert!(ImapAlert::from_status_body(&body).is_none());
+    }
+
+    #[test]
+    fn collect_alerts() {
+        let alerts = ImapAlerts::new();
+
+        for i in 0..MAX_IMAP_ALERTS + 2 {
+            let alert = ImapAlert {
+                kind: ImapAlertKind::Alert,
+                text: format!("alert {i}"),
+            };
+            alerts.push(alert.clone());
+            alerts.push(alert);
+        }
+
+        let taken = alerts.take();
+        assert_eq!(taken.len(), MAX_IMAP_ALERTS);
+        assert_eq!(taken[0].text, "alert 2");
+        assert!(alerts.take().is_empty());
+    }
+}
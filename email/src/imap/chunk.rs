@@ -0,0 +1,164 @@
+//! # IMAP sequence set chunking
+//!
+//! Some IMAP servers reject commands longer than a few kilobytes.
+//! Commands taking a large amount of UIDs (fetch, store, copy, move)
+//! are then split into several commands, each one taking a sequence
+//! set short enough to be accepted by the server. See
+//! [`chunk_sequence_set`].
+
+use std::num::NonZeroU32;
+
+use imap_client::imap_next::imap_types::sequence::{SeqOrUid, Sequence, SequenceSet};
+
+/// The default maximum length of a sequence set sent in a single
+/// command, in bytes.
+pub const DEFAULT_MAX_SEQUENCE_SET_LEN: usize = 1000;
+
+/// Coalesce the given sequence set.
+///
+/// Sequences are sorted, then overlapping or contiguous ones are
+/// merged into ranges. Sequence sets containing `*` are returned
+/// untouched, since `*` cannot be resolved without the mailbox state.
+pub fn coalesce_sequence_set(set: &SequenceSet) -> SequenceSet {
+    let mut ranges = Vec::with_capacity(set.0.as_ref().len());
+
+    for seq in set.0.as_ref() {
+        let range = match seq {
+            Sequence::Single(SeqOrUid::Value(n)) => (n.get(), n.get()),
+            Sequence::Range(SeqOrUid::Value(a), SeqOrUid::Value(b)) => {
+                (a.get().min(b.get()), a.get().max(b.get()))
+            }
+            _ => return set.clone(),
+        };
+
+        ranges.push(range);
+    }
+
+    ranges.sort_unstable();
+
+    let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+
+    for (lo, hi) in ranges {
+        match merged.last_mut() {
+            Some((_, last_hi)) if lo <= last_hi.saturating_add(1) => {
+                *last_hi = (*last_hi).max(hi);
+            }
+            _ => merged.push((lo, hi)),
+        }
+    }
+
+    let seqs: Vec<_> = merged.into_iter().map(to_sequence).collect();
+    SequenceSet::try_from(seqs).unwrap_or_else(|_| set.clone())
+}
+
+/// Coalesce then split the given sequence set into sequence sets
+/// whose encoded length does not exceed the given maximum length.
+///
+/// A single sequence longer than the maximum length still gets its
+/// own sequence set, since it cannot be split any further.
+pub fn chunk_sequence_set(set: &SequenceSet, max_len: usize) -> Vec<SequenceSet> {
+    let set = coalesce_sequence_set(set);
+
+    let mut chunks = Vec::new();
+    let mut chunk: Vec<Sequence> = Vec::new();
+    let mut chunk_len = 0;
+
+    for seq in set.0.into_iter() {
+        let seq_len = sequence_len(&seq);
+        // the comma separating sequences
+        let sep_len = if chunk.is_empty() { 0 } else { 1 };
+
+        if !chunk.is_empty() && chunk_len + sep_len + seq_len > max_len {
+            chunks.extend(SequenceSet::try_from(std::mem::take(&mut chunk)));
+            chunk_len = 0;
+        }
+
+        chunk_len += if chunk.is_empty() { 0 } else { 1 } + seq_len;
+        chunk.push(seq);
+    }
+
+    chunks.extend(SequenceSet::try_from(chunk));
+    chunks
+}
+
+/// Encode the given sequence set, like `1:3,5,7:*`.
+///
+/// Used by commands sent as raw commands, see [`super::raw`].
+pub fn encode_sequence_set(set: &SequenceSet) -> String {
+    let encode = |n: &SeqOrUid| match n {
+        SeqOrUid::Value(n) => n.to_string(),
+        SeqOrUid::Asterisk => String::from("*"),
+    };
+
+    set.0
+        .as_ref()
+        .iter()
+        .map(|seq| match seq {
+            Sequence::Single(n) => encode(n),
+            Sequence::Range(a, b) => format!("{}:{}", encode(a), encode(b)),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn to_sequence((lo, hi): (u32, u32)) -> Sequence {
+    // values come from non-zero values
+    let lo = SeqOrUid::Value(NonZeroU32::new(lo).unwrap());
+
+    if lo == SeqOrUid::Value(NonZeroU32::new(hi).unwrap()) {
+        Sequence::Single(lo)
+    } else {
+        Sequence::Range(lo, SeqOrUid::Value(NonZeroU32::new(hi).unwrap()))
+    }
+}
+
+fn seq_or_uid_len(n: &SeqOrUid) -> usize {
+    match n {
+        SeqOrUid::Value(n) => n.ilog10() as usize + 1,
+        SeqOrUid::Asterisk => 1,
+    }
+}
+
+fn sequence_len(seq: &Sequence) -> usize {
+    match seq {
+        Sequence::Single(n) => seq_or_uid_len(n),
+        Sequence::Range(a, b) => seq_or_uid_len(a) + 1 + seq_or_uid_len(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::imap_types::sequence::SequenceSet;
+
+    use super::{chunk_sequence_set, coalesce_sequence_set, encode_sequence_set};
+
+    fn set(s: &str) -> SequenceSet {
+        SequenceSet::try_from(s).unwrap()
+    }
+
+    #[test]
+    fn coalesce() {
+        assert_eq!(
+            coalesce_sequence_set(&set("5,1,2,3,10:8,7")),
+            set("1:3,5,7:10")
+        );
+        assert_eq!(coalesce_sequence_set(&set("3,1:4,2")), set("1:4"));
+        assert_eq!(coalesce_sequence_set(&set("3,1:*")), set("3,1:*"));
+    }
+
+    #[test]
+    fn chunk() {
+        assert_eq!(
+            chunk_sequence_set(&set("1,3,5,7,9,11,13"), 5),
+            vec![set("1,3,5"), set("7,9"), set("11,13")],
+        );
+
+        // contiguous ranges are coalesced first
+        assert_eq!(chunk_sequence_set(&set("1,2,3,4,5,6"), 3), vec![set("1:6")]);
+    }
+
+    #[test]
+    fn encode() {
+        assert_eq!(encode_sequence_set(&set("1:3,5,7:*")), "1:3,5,7:*");
+    }
+}
@@ -3,11 +3,21 @@
 //! This module contains the implementation of the IMAP backend and
 //! all associated structures related to it.
 
+use std::{fmt, time::Duration};
+
 #[doc(inline)]
-use super::{Error, Result};
+use super::{chunk::DEFAULT_MAX_SEQUENCE_SET_LEN, Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::OAuth2Config;
-use crate::{account::config::passwd::PasswordConfig, tls::Encryption};
+use crate::{
+    account::{budget::ConcurrencyBudget, config::passwd::PasswordConfig, runtime::AccountRuntime},
+    retry::DEFAULT_TIMEOUT,
+    tls::{Encryption, SecurityLevel, StartTlsPolicy, Tls},
+};
+
+/// The default timeout of waiting for the concurrency budgets to
+/// allow a new connection.
+pub const DEFAULT_BUDGET_TIMEOUT: Duration = Duration::from_secs(5 * 60);
 
 /// Errors related to the IMAP backend configuration.
 
@@ -30,6 +40,12 @@ pub struct ImapConfig {
     /// Supported encryption: SSL/TLS, STARTTLS or none.
     pub encryption: Option<Encryption>,
 
+    /// The IMAP STARTTLS policy.
+    ///
+    /// Only used when the encryption is STARTTLS. Defaults to
+    /// `require`, see [StartTlsPolicy].
+    pub starttls_policy: Option<StartTlsPolicy>,
+
     /// The IMAP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -56,6 +72,39 @@ pub struct ImapConfig {
     /// Defines the number of clients that are created and managed
     /// simultaneously by the IMAP context. Defaults to 1.
     pub clients_pool_size: Option<u8>,
+
+    /// The maximum number of connections opened to the IMAP server
+    /// host.
+    ///
+    /// Unlike the account concurrency budget, the limit is shared by
+    /// all accounts of the same
+    /// [`AccountRuntime`](crate::account::runtime::AccountRuntime)
+    /// connecting to the same host, which matters for
+    /// servers limiting connections per client address. Clients of
+    /// the pool that do not fit in the limit are not built, like
+    /// with the account budget. Defaults to no limit.
+    pub max_connections_per_host: Option<u32>,
+
+    /// The maximum length of a sequence set, in bytes.
+    ///
+    /// Commands taking a large amount of UIDs (fetch, store, copy and
+    /// move) are split into several commands so that their sequence
+    /// set does not exceed this length, which prevents servers from
+    /// rejecting too long command lines. Defaults to 1000.
+    pub max_sequence_set_len: Option<usize>,
+
+    /// Check that folders exist before selecting them.
+    ///
+    /// When enabled, the names returned by the LIST command are
+    /// cached, and selecting an unknown folder fails with a typed
+    /// error suggesting close folder names instead of a raw server
+    /// response. Defaults to `false`.
+    pub check_folders: Option<bool>,
+
+    /// The IMAP timeouts configuration, per operation class.
+    ///
+    /// See [ImapTimeoutsConfig].
+    pub timeouts: Option<ImapTimeoutsConfig>,
 }
 
 impl ImapConfig {
@@ -63,6 +112,25 @@ pub fn clients_pool_size(&self) -> u8 {
         self.clients_pool_size.unwrap_or(1)
     }
 
+    /// Get the concurrency budget of the IMAP server host from the
+    /// given runtime, if [`ImapConfig::max_connections_per_host`] is
+    /// defined.
+    pub fn host_concurrency_budget(&self, runtime: &AccountRuntime) -> Option<ConcurrencyBudget> {
+        let limit = self.max_connections_per_host?;
+        Some(runtime.host_concurrency_budget(&self.host, limit))
+    }
+
+    pub fn max_sequence_set_len(&self) -> usize {
+        self.max_sequence_set_len
+            .unwrap_or(DEFAULT_MAX_SEQUENCE_SET_LEN)
+    }
+
+    /// Return `true` if folders should be checked before being
+    /// selected, see [`ImapConfig::check_folders`].
+    pub fn is_folder_check_enabled(&self) -> bool {
+        self.check_folders.unwrap_or_default()
+    }
+
     pub fn send_id_after_auth(&self) -> bool {
         self.extensions
             .as_ref()
@@ -71,6 +139,12 @@ pub fn send_id_after_auth(&self) -> bool {
             .unwrap_or_default()
     }
 
+    /// Return the STARTTLS policy, defaults to
+    /// [`StartTlsPolicy::Require`].
+    pub fn starttls_policy(&self) -> StartTlsPolicy {
+        self.starttls_policy.unwrap_or_default()
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -89,6 +163,28 @@ pub fn is_encryption_disabled(&self) -> bool {
         matches!(self.encryption.as_ref(), Some(Encryption::None))
     }
 
+    /// Return the security level expected from the encryption
+    /// configuration.
+    pub fn expected_security_level(&self) -> SecurityLevel {
+        if self.is_encryption_disabled() {
+            SecurityLevel::Plaintext
+        } else if self.is_start_tls_encryption_enabled() {
+            SecurityLevel::StartTls
+        } else {
+            SecurityLevel::Tls
+        }
+    }
+
+    /// Return the TLS options of the encryption, or the default ones
+    /// when encryption is disabled.
+    pub fn find_tls(&self) -> Tls {
+        self.encryption
+            .as_ref()
+            .and_then(Encryption::tls)
+            .cloned()
+            .unwrap_or_default()
+    }
+
     /// Builds authentication credentials.
     ///
     /// Authentication credentials can be either a password or an
@@ -98,19 +194,61 @@ pub async fn build_credentials(&self) -> Result<String> {
     }
 
     /// Find the IMAP watch timeout.
-    pub fn find_watch_timeout(&self) -> Option<u64> {
+    pub fn find_watch_timeout(&self) -> Option<Duration> {
         self.watch.as_ref().and_then(|c| c.find_timeout())
     }
+
+    /// Return the timeout of the given operation class, defaults to
+    /// [`ImapOperation::default_timeout`].
+    pub fn timeout(&self, op: ImapOperation) -> Duration {
+        let timeouts = self.timeouts.as_ref();
+
+        let timeout = match op {
+            ImapOperation::Control => timeouts.and_then(|t| t.control),
+            ImapOperation::FetchSmall => timeouts.and_then(|t| t.fetch_small),
+            ImapOperation::FetchBody => timeouts.and_then(|t| t.fetch_body),
+            ImapOperation::Append => timeouts.and_then(|t| t.append),
+        };
+
+        timeout.unwrap_or_else(|| op.default_timeout())
+    }
+
+    /// Find the timeout of waiting for the concurrency budgets, see
+    /// [`ImapTimeoutsConfig::budget`].
+    pub fn find_budget_timeout(&self) -> Duration {
+        self.timeouts
+            .as_ref()
+            .and_then(|t| t.budget)
+            .unwrap_or(DEFAULT_BUDGET_TIMEOUT)
+    }
+
+    /// Find the IDLE timeout.
+    ///
+    /// Falls back to the IMAP watch timeout.
+    pub fn find_idle_timeout(&self) -> Option<Duration> {
+        self.timeouts
+            .as_ref()
+            .and_then(|t| t.idle)
+            .or_else(|| self.find_watch_timeout())
+    }
+
+    /// Get the capabilities to enable straight after authentication.
+    ///
+    /// Defaults to an empty list.
+    pub fn get_capabilities_to_enable(&self) -> &[ImapCapability] {
+        self.extensions
+            .as_ref()
+            .and_then(|ext| ext.enable.as_deref())
+            .unwrap_or_default()
+    }
 }
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for ImapConfig {
-    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
-        use std::hash::Hash;
-
-        Hash::hash(&self.host, state);
-        Hash::hash(&self.port, state);
-        Hash::hash(&self.login, state);
+    fn sync_hash(&self, state: &mut crate::sync::hash::SyncHasher) {
+        state.write_str(&self.host);
+        state.write_u16(self.port);
+        state.write_str(&self.login);
     }
 }
 
@@ -195,6 +333,10 @@ pub fn replace_empty_secrets(&mut self, name: impl AsRef<str>) -> Result<()> {
                     .refresh_token
                     .replace_with_keyring_if_empty(format!("{name}-imap-oauth2-refresh-token"))
                     .map_err(Error::ReplacingUnidentifiedFailed)?;
+                config
+                    .expires_at
+                    .replace_with_keyring_if_empty(format!("{name}-imap-oauth2-expires-at"))
+                    .map_err(Error::ReplacingUnidentifiedFailed)?;
             }
         }
 
@@ -256,16 +398,89 @@ pub struct ImapWatchConfig {
     ///
     /// Timeout used to refresh the IDLE command in
     /// background. Defaults to 29 min as defined in the RFC.
-    timeout: Option<u64>,
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    timeout: Option<Duration>,
 }
 
 impl ImapWatchConfig {
     /// Find the IMAP watch timeout.
-    pub fn find_timeout(&self) -> Option<u64> {
+    pub fn find_timeout(&self) -> Option<Duration> {
         self.timeout
     }
 }
 
+/// The IMAP operation classes.
+///
+/// Each class has its own timeout, since fetching a large message
+/// legitimately takes longer than a NOOP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ImapOperation {
+    /// Commands with small responses: NOOP, SELECT, LIST, SEARCH,
+    /// STORE, COPY, MOVE, EXPUNGE…
+    Control,
+
+    /// Fetch of envelopes, flags and previews.
+    FetchSmall,
+
+    /// Fetch of whole messages.
+    FetchBody,
+
+    /// Append of messages.
+    Append,
+}
+
+impl ImapOperation {
+    /// Return the default timeout of the operation class.
+    pub fn default_timeout(&self) -> Duration {
+        match self {
+            Self::Control | Self::FetchSmall => DEFAULT_TIMEOUT,
+            Self::FetchBody | Self::Append => Duration::from_secs(5 * 60),
+        }
+    }
+}
+
+/// The IMAP timeouts options.
+///
+/// Timeouts are either numbers of seconds or human-friendly
+/// durations like `2m30s`, see [humantime](https://docs.rs/humantime).
+///
+/// Requests timing out are retried, see [`crate::retry::Retry`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct ImapTimeoutsConfig {
+    /// The timeout of control commands. Defaults to 30 seconds.
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub control: Option<Duration>,
+
+    /// The timeout of envelopes, flags and previews fetches.
+    /// Defaults to 30 seconds.
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub fetch_small: Option<Duration>,
+
+    /// The timeout of whole messages fetches. Defaults to 5 minutes.
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub fetch_body: Option<Duration>,
+
+    /// The timeout of messages appends. Defaults to 5 minutes.
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub append: Option<Duration>,
+
+    /// The timeout after which the IDLE command is refreshed.
+    ///
+    /// Defaults to the watch timeout, see [ImapWatchConfig].
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub idle: Option<Duration>,
+
+    /// The timeout of waiting for the concurrency budgets to allow a
+    /// new connection. Defaults to 5 minutes.
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub budget: Option<Duration>,
+}
+
 /// The IMAP configuration dedicated to extensions.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
@@ -275,6 +490,60 @@ pub fn find_timeout(&self) -> Option<u64> {
 )]
 pub struct ImapExtensionsConfig {
     id: Option<ImapIdExtensionConfig>,
+
+    /// The capabilities to enable straight after authentication,
+    /// using the ENABLE extension.
+    ///
+    /// Capabilities rejected by the server, or all of them if the
+    /// server does not support the ENABLE extension, are skipped.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc5161.html
+    pub enable: Option<Vec<ImapCapability>>,
+}
+
+/// The IMAP capability that can be enabled using the ENABLE
+/// extension.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum ImapCapability {
+    /// Accept UTF-8 in mailbox names and message headers.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc6855.html
+    #[cfg_attr(feature = "derive", serde(rename = "utf8-accept"))]
+    Utf8Accept,
+
+    /// Conditional STORE operations and modification sequences.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7162.html
+    #[cfg_attr(feature = "derive", serde(rename = "condstore"))]
+    CondStore,
+
+    /// Quick mailbox resynchronization.
+    ///
+    /// https://www.rfc-editor.org/rfc/rfc7162.html
+    #[cfg_attr(feature = "derive", serde(rename = "qresync"))]
+    QResync,
+}
+
+impl ImapCapability {
+    /// Return the capability name, as sent to the server.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Utf8Accept => "UTF8=ACCEPT",
+            Self::CondStore => "CONDSTORE",
+            Self::QResync => "QRESYNC",
+        }
+    }
+}
+
+impl fmt::Display for ImapCapability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 /// The IMAP configuration dedicated to the ID extension.
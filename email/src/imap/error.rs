@@ -1,4 +1,6 @@
-use std::{any::Any, collections::HashSet, result};
+use std::{
+    any::Any, collections::HashSet, convert::Infallible, io, path::PathBuf, result, time::Duration,
+};
 
 use imap_client::{
     client::tokio::ClientError,
@@ -11,7 +13,7 @@
 use thiserror::Error;
 use tokio::task::JoinError;
 
-use crate::{account, AnyBoxedError, AnyError};
+use crate::{account, diagnostic::ConnectionDiagnostics, AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -25,12 +27,32 @@ pub enum Error {
     JoinClientError(#[source] JoinError),
     #[error("cannot build IMAP client")]
     BuildClientError(#[source] Box<Error>),
+    #[error("cannot build IMAP client: concurrency budget still exhausted after {0:?}")]
+    AcquireConcurrencyBudgetTimeoutError(Duration),
+    #[error("cannot connect to IMAP server {1}")]
+    ConnectImapServerError(#[source] Box<Error>, ConnectionDiagnostics),
     #[error("cannot connect to IMAP server {1}:{2} using TCP")]
     BuildInsecureClientError(#[source] ClientError, String, u16),
     #[error("cannot connect to IMAP server {1}:{2} using STARTTLS")]
     BuildStartTlsClientError(#[source] ClientError, String, u16),
     #[error("cannot connect to IMAP server {1}:{2} using SSL/TLS")]
     BuildTlsClientError(#[source] ClientError, String, u16),
+    #[error("cannot load IMAP UIDVALIDITY store at {1}")]
+    LoadUidValidityError(#[source] io::Error, PathBuf),
+    #[error("cannot update UIDVALIDITY of IMAP mailbox {1}")]
+    UpdateUidValidityError(#[source] io::Error, String),
+    #[error("cannot check UIDVALIDITY of IMAP mailbox {0}: not sent by the server")]
+    UnknownUidValidityError(String),
+    #[error("cannot upgrade connection to IMAP server {1}:{2} using STARTTLS, required by policy")]
+    StartTlsRequiredError(#[source] ClientError, String, u16),
+    #[error(
+        "cannot upgrade connection to IMAP server {1}:{2} using STARTTLS, refusing to downgrade"
+    )]
+    StartTlsDowngradeError(#[source] ClientError, String, u16),
+    #[error("cannot connect to IMAP server: TLS options {0} only supported by SMTP")]
+    TlsOptionsNotSupportedError(String),
+    #[error("cannot verify certificate of IMAP server {1}:{2}")]
+    VerifyCertificateError(#[source] crate::tls::Error, String, u16),
 
     #[error("cannot get imap password from global keyring")]
     GetPasswdImapError(#[source] secret::Error),
@@ -76,10 +98,8 @@ pub enum Error {
     LoginError(#[source] ClientError),
     #[error("cannot authenticate to IMAP server using SASL PLAIN mechanism")]
     AuthenticatePlainError(#[source] ClientError),
-    #[error("cannot authenticate to IMAP server using SASL XOAUTH2 mechanism")]
-    AuthenticateXOauth2Error(#[source] ClientError),
-    #[error("cannot authenticate to IMAP server using SASL OAUTHBEARER mechanism")]
-    AuthenticateOAuthBearerError(#[source] ClientError),
+    #[error("cannot authenticate to IMAP server using OAuth 2.0")]
+    AuthenticateOAuth2Error(#[source] crate::sasl::Error),
 
     #[error("cannot create IMAP mailbox")]
     CreateMailboxError(#[source] ClientError),
@@ -91,6 +111,14 @@ pub enum Error {
     #[error("cannot select IMAP mailbox: request timed out")]
     SelectMailboxTimedOutError,
 
+    #[error("cannot find IMAP folder {0}{}", format_suggestions(.1))]
+    FolderNotFoundError(String, Vec<String>),
+
+    #[error("cannot get quota of IMAP mailbox")]
+    GetQuotaRootError(#[source] ClientError),
+    #[error("cannot get quota of IMAP mailbox: request timed out")]
+    GetQuotaRootTimedOutError,
+
     #[error("cannot examine IMAP mailbox")]
     ExamineMailboxError(#[source] ClientError),
     #[error("cannot examine IMAP mailbox: request timed out")]
@@ -116,6 +144,15 @@ pub enum Error {
     #[error("cannot fetch IMAP messages: request timed out")]
     FetchMessagesTimedOutError,
 
+    #[error("cannot fetch IMAP message part")]
+    FetchMessagePartError(#[source] ClientError),
+    #[error("cannot fetch IMAP message part: request timed out")]
+    FetchMessagePartTimedOutError,
+    #[error("cannot fetch IMAP message part: part not found")]
+    FetchMessagePartEmptyError,
+    #[error("cannot parse IMAP message UID {0}")]
+    ParseUidError(String),
+
     #[error("cannot thread IMAP messages")]
     ThreadMessagesError(#[source] ClientError),
     #[error("cannot thread IMAP messages: request timed out")]
@@ -129,6 +166,8 @@ pub enum Error {
     AddMessageError(#[source] ClientError),
     #[error("cannot add IMAP message: request timed out")]
     AddMessageTimedOutError,
+    #[error("cannot add IMAP message of {0} bytes: server accepts up to {1} bytes")]
+    AddMessageTooLargeError(usize, usize),
     #[error("cannot copy IMAP message(s)")]
     CopyMessagesError(#[source] ClientError),
     #[error("cannot copy IMAP message(s): request timed out")]
@@ -162,6 +201,20 @@ pub enum Error {
     StopIdleError(#[source] StreamError<ClientFlowError>),
     #[error("IMAP IDLE mode interrupted")]
     IdleInterruptedError,
+    #[error("cannot send IMAP {1} command")]
+    SendRawCommandError(#[source] StreamError<Infallible>, &'static str),
+    #[error("cannot send IMAP {0} command: request timed out")]
+    SendRawCommandTimedOutError(&'static str),
+    #[error("cannot send IMAP {0} command: {1}")]
+    RejectRawCommandError(&'static str, String),
+    #[error("cannot parse IMAP {0} response: {1}")]
+    ParseRawResponseError(&'static str, String),
+    #[error("cannot wait for IMAP NOTIFY notifications")]
+    WaitForNotificationsError(#[source] StreamError<Infallible>),
+    #[error("IMAP NOTIFY mode interrupted")]
+    NotifyInterruptedError,
+    #[error("cannot manage IMAP ACL: extension not supported by the server")]
+    AclNotSupportedError,
     #[error("cannot append IMAP message")]
     AppendMessageError(#[source] ClientError),
     #[error("cannot execute IMAP no-op after append")]
@@ -178,10 +231,6 @@ pub enum Error {
     LoginNotSupportedError,
     #[error("plain authentication not supported (available: {0:?})")]
     AuthenticatePlainNotSupportedError(HashSet<AuthMechanism<'static>>),
-    #[error("XOAuth2 authentication not supported (available: {0:?})")]
-    AuthenticateXOAuth2NotSupportedError(HashSet<AuthMechanism<'static>>),
-    #[error("OAuthBearer authentication not supported (available: {0:?})")]
-    AuthenticateOAuthBearerNotSupportedError(HashSet<AuthMechanism<'static>>),
 
     // tasks
     #[error("cannot execute IMAP action")]
@@ -191,6 +240,15 @@ pub enum Error {
     BuildSessionRetryError(u8),
 }
 
+/// Format the folder names suggested when a folder cannot be found.
+fn format_suggestions(suggestions: &[String]) -> String {
+    if suggestions.is_empty() {
+        String::new()
+    } else {
+        format!(", did you mean {}?", suggestions.join(", "))
+    }
+}
+
 impl AnyError for Error {
     fn as_any(&self) -> &dyn Any {
         self
@@ -0,0 +1,157 @@
+//! # IMAP mailbox
+//!
+//! Module dedicated to IMAP mailbox names. It contains the
+//! [`ImapMailboxCache`], which caches the names returned by the LIST
+//! command so that the existence of a mailbox can be checked before
+//! selecting it, as well as the `INBOX` name normalization.
+
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+/// The maximum number of mailbox names suggested when a mailbox
+/// cannot be found.
+pub const MAX_SUGGESTIONS: usize = 3;
+
+/// Normalize the given mailbox name.
+///
+/// The `INBOX` name is case-insensitive (RFC 3501 section 5.1), so
+/// any case variant of it is replaced by `INBOX`. Other names are
+/// case-sensitive and are kept as is.
+pub fn normalize_mailbox(mbox: &str) -> &str {
+    if mbox.eq_ignore_ascii_case("INBOX") {
+        "INBOX"
+    } else {
+        mbox
+    }
+}
+
+/// The IMAP mailbox names cache.
+///
+/// The cache is filled with the names returned by the LIST command,
+/// and kept up to date when mailboxes are created or deleted.
+/// Cloning the cache shares the same names.
+#[derive(Clone, Debug, Default)]
+pub struct ImapMailboxCache(Arc<Mutex<Option<BTreeSet<String>>>>);
+
+impl ImapMailboxCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return `true` if the cache has been filled.
+    pub fn is_filled(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Replace the cached names by the given ones.
+    pub fn fill(&self, mboxes: impl IntoIterator<Item = String>) {
+        *self.lock() = Some(mboxes.into_iter().collect());
+    }
+
+    /// Empty the cache, so that it gets filled again.
+    pub fn invalidate(&self) {
+        *self.lock() = None;
+    }
+
+    /// Return `true` if the given mailbox is cached.
+    pub fn contains(&self, mbox: &str) -> bool {
+        let mbox = normalize_mailbox(mbox);
+        self.lock()
+            .as_ref()
+            .is_some_and(|mboxes| mboxes.contains(mbox))
+    }
+
+    /// Add the given mailbox to the cache, if filled.
+    pub fn insert(&self, mbox: &str) {
+        if let Some(mboxes) = self.lock().as_mut() {
+            mboxes.insert(normalize_mailbox(mbox).to_owned());
+        }
+    }
+
+    /// Remove the given mailbox from the cache, if filled.
+    pub fn remove(&self, mbox: &str) {
+        if let Some(mboxes) = self.lock().as_mut() {
+            mboxes.remove(normalize_mailbox(mbox));
+        }
+    }
+
+    /// Return the cached names close to the given one, closest
+    /// first.
+    ///
+    /// Names are compared case-insensitively using the Levenshtein
+    /// distance, which catches typos as well as case mistakes.
+    pub fn suggest(&self, mbox: &str) -> Vec<String> {
+        let Some(mboxes) = self.lock().clone() else {
+            return Vec::new();
+        };
+
+        let mbox = mbox.to_lowercase();
+        let max_distance = (mbox.chars().count() / 3).max(2);
+
+        let mut suggestions: Vec<_> = mboxes
+            .into_iter()
+            .map(|candidate| (levenshtein(&mbox, &candidate.to_lowercase()), candidate))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .collect();
+
+        suggestions.sort();
+        suggestions
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, candidate)| candidate)
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<BTreeSet<String>>> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Return the Levenshtein distance between the given strings.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(prev + 1).min(row[j] + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_mailbox, ImapMailboxCache};
+
+    #[test]
+    fn cache_and_suggestions() {
+        assert_eq!(normalize_mailbox("inbox"), "INBOX");
+        assert_eq!(normalize_mailbox("Inbox/Sub"), "Inbox/Sub");
+
+        let cache = ImapMailboxCache::new();
+        cache.insert("Ignored");
+        assert!(!cache.is_filled());
+        assert!(!cache.contains("Ignored"));
+
+        cache.fill(["INBOX", "Sent", "Archives", "Drafts"].map(String::from));
+        assert!(cache.contains("inbox"));
+        assert!(!cache.contains("sent"));
+
+        cache.insert("Archives/2024");
+        cache.remove("Drafts");
+
+        assert_eq!(cache.suggest("sent"), vec!["Sent"]);
+        assert_eq!(cache.suggest("Archivs"), vec!["Archives"]);
+        assert_eq!(cache.suggest("Draft"), Vec::<String>::new());
+        assert_eq!(cache.suggest("Junk"), Vec::<String>::new());
+    }
+}
@@ -1,74 +1,131 @@
+pub mod acl;
+pub mod alert;
+pub mod chunk;
 pub mod config;
 mod error;
+pub mod mailbox;
+pub mod notify;
+pub mod quota;
+mod raw;
+pub mod savedate;
+pub mod uidplus;
+pub mod uidvalidity;
 
 use std::{
-    collections::HashMap, env, fmt, io::ErrorKind::ConnectionReset, num::NonZeroU32, sync::Arc,
+    borrow::Cow,
+    collections::HashMap,
+    env, fmt,
+    io::ErrorKind::ConnectionReset,
+    num::NonZeroU32,
+    path::PathBuf,
+    sync::{Arc, Mutex as StdMutex, MutexGuard as StdMutexGuard},
     time::Duration,
 };
 
 use async_trait::async_trait;
-use futures::{stream::FuturesUnordered, StreamExt};
+use chrono::{DateTime, FixedOffset};
+use futures::{future::BoxFuture, stream::FuturesUnordered, StreamExt};
 use imap_client::{
-    client::tokio::{Client, ClientError},
+    client::tokio::{Client, ClientError, MaybeTlsStream},
     imap_next::imap_types::{
         auth::AuthMechanism,
-        core::{IString, NString, Vec1},
+        core::{IString, NString, NString8, Vec1},
         extensions::{
+            enable::CapabilityEnable,
             sort::SortCriterion,
             thread::{Thread, ThreadingAlgorithm},
         },
-        fetch::MessageDataItem,
+        fetch::{MacroOrMessageDataItemNames, MessageDataItem, MessageDataItemName, Part, Section},
         flag::{Flag, StoreType},
+        mailbox::Mailbox,
         search::SearchKey,
         sequence::SequenceSet,
     },
-    stream::Error as StreamError,
-    tasks::{tasks::select::SelectDataUnvalidated, SchedulerError},
+    stream::{Error as StreamError, Stream},
+    tasks::{
+        tasks::{
+            noop::NoOpTask,
+            select::{SelectDataUnvalidated, SelectTask},
+        },
+        SchedulerError,
+    },
 };
 use once_cell::sync::Lazy;
+use secret::Redacted;
 use tokio::{
     select,
     sync::{oneshot, Mutex, MutexGuard},
-    time::sleep,
+    time::{sleep, timeout},
 };
 use tracing::{debug, instrument, trace, warn};
+use utf7_imap::{decode_utf7_imap as decode_utf7, encode_utf7_imap as encode_utf7};
 
-use self::config::{ImapAuthConfig, ImapConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
-#[cfg(feature = "oauth2")]
-use crate::account::config::oauth2::OAuth2Method;
+use self::{
+    acl::{parse_acl, parse_my_rights},
+    alert::{AlertTask, ImapAlert, ImapAlerts},
+    chunk::{chunk_sequence_set, encode_sequence_set},
+    config::{ImapAuthConfig, ImapCapability, ImapConfig, ImapOperation},
+    mailbox::{normalize_mailbox, ImapMailboxCache},
+    notify::{ImapNotification, ImapNotifySet},
+    quota::GetQuotaRootTask,
+    raw::{is_named, RawCommand, RawResponse, RawState, RawStatus},
+    savedate::parse_save_date,
+    uidplus::{parse_appenduid, CopyUidTask, UidMapping},
+    uidvalidity::{UidValidityChange, UidValidityStore},
+};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{imap::ThreadImapEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
 use crate::envelope::watch::{imap::WatchImapEnvelopes, WatchEnvelopes};
+#[cfg(feature = "oauth2")]
+use crate::sasl;
 use crate::{
-    account::config::AccountConfig,
+    account::{
+        budget::{ConcurrencyBudget, ConcurrencyPermit, CONNECTION_WEIGHT},
+        config::AccountConfig,
+        runtime::AccountRuntime,
+    },
     backend::{
+        self,
         context::{BackendContext, BackendContextBuilder},
-        feature::{BackendFeature, CheckUp},
+        feature::{
+            BackendFeature, BackendFeatureKind, BackendFeatureSupport, BackendFeatures, CheckUp,
+        },
     },
+    diagnostic::ConnectionDiagnostics,
     envelope::{
         get::{imap::GetImapEnvelope, GetEnvelope},
-        imap::FETCH_ENVELOPES,
+        imap::{
+            fetch_envelopes_items, preview_from_imap_data_items, FETCH_ENVELOPES, FETCH_PREVIEWS,
+        },
         list::{imap::ListImapEnvelopes, ListEnvelopes},
-        Envelope, Envelopes,
+        Envelope, Envelopes, SingleId, StableId, StableIdKind,
     },
     flag::{
         add::{imap::AddImapFlags, AddFlags},
+        mark_read::{imap::MarkReadBeforeImap, MarkReadBefore},
         remove::{imap::RemoveImapFlags, RemoveFlags},
         set::{imap::SetImapFlags, SetFlags},
+        Flags,
     },
     folder::{
+        acl::{
+            imap::ManageImapFolderAcl, AclEntry, AclRights, AclRightsModification, ManageFolderAcl,
+        },
         add::{imap::AddImapFolder, AddFolder},
         delete::{imap::DeleteImapFolder, DeleteFolder},
         expunge::{imap::ExpungeImapFolder, ExpungeFolder},
+        imap::ImapMailboxes,
         list::{imap::ListImapFolders, ListFolders},
         purge::{imap::PurgeImapFolder, PurgeFolder},
+        quota::{imap::GetImapQuota, GetQuota, Quota},
         Folders,
     },
     message::{
         add::{imap::AddImapMessage, AddMessage},
+        attachment::{imap::GetImapAttachment, GetAttachment},
         copy::{imap::CopyImapMessages, CopyMessages},
         delete::{imap::DeleteImapMessages, DeleteMessages},
         get::{imap::GetImapMessages, GetMessages},
@@ -79,7 +136,8 @@
         Messages,
     },
     retry::{self, Retry, RetryState},
-    tls::{Encryption, Tls, TlsProvider},
+    sasl::SaslMechanism,
+    tls::{info::TlsConnectionInfo, Encryption, SecurityLevel, StartTlsPolicy, Tls, TlsProvider},
     AnyResult,
 };
 
@@ -148,7 +206,21 @@ pub struct ImapClient {
     /// The selected mailbox.
     mailbox: Option<String>,
 
+    /// The UIDVALIDITY store, shared by all clients of the context.
+    uid_validity: Arc<StdMutex<UidValidityStore>>,
+
+    /// The server alerts, shared by all clients of the context.
+    alerts: ImapAlerts,
+
+    /// The mailbox names cache, shared by all clients of the
+    /// context.
+    mailboxes: ImapMailboxCache,
+
     retry: Retry,
+
+    /// The parts of the account and host concurrency budgets held
+    /// by the client, given back when the client is dropped.
+    _permits: Vec<ConcurrencyPermit>,
 }
 
 impl ImapClient {
@@ -188,11 +260,13 @@ async fn retry<T>(
 
                 self.inner = self.client_builder.build().await?;
 
-                if let Some(mbox) = &self.mailbox {
-                    self.inner
+                if let Some(mbox) = self.mailbox.clone() {
+                    let data = self
+                        .inner
                         .select(mbox.clone())
                         .await
                         .map_err(Error::SelectMailboxError)?;
+                    self.track_uid_validity(&mbox, &data);
                 }
 
                 self.retry.attempts = 0;
@@ -204,117 +278,365 @@ async fn retry<T>(
         }
     }
 
-    pub fn ext_sort_supported(&self) -> bool {
-        self.inner.state.ext_sort_supported()
-    }
-
-    #[instrument(skip_all, fields(client = self.id))]
-    pub async fn noop(&mut self) -> Result<()> {
+    /// Run the IMAP command built by the given function, then wait
+    /// for its result.
+    ///
+    /// The command is rebuilt and sent again when it times out or
+    /// when the connection is lost, in which case the client
+    /// re-connects first. The command is built from the inner client
+    /// at every attempt, since re-connecting replaces it.
+    async fn run<T, F>(
+        &mut self,
+        operation: ImapOperation,
+        timed_out_err: Error,
+        map_err: fn(ClientError) -> Error,
+        mut command: F,
+    ) -> Result<T>
+    where
+        F: for<'a> FnMut(&'a mut Client) -> BoxFuture<'a, std::result::Result<T, ClientError>>,
+    {
         self.retry.reset();
 
         loop {
-            let res = self.retry.timeout(self.inner.noop()).await;
+            let timeout = self.imap_config.timeout(operation);
+            let res = self
+                .retry
+                .timeout_after(timeout, command(&mut self.inner))
+                .await;
 
             match self.retry(res).await? {
                 ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::NoOpTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::NoOpError),
+                ImapRetryState::TimedOut => break Err(timed_out_err),
+                ImapRetryState::Ok(res) => break res.map_err(map_err),
             }
         }
     }
 
-    #[instrument(skip_all, fields(client = self.id))]
-    pub async fn select_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDataUnvalidated> {
-        self.retry.reset();
+    pub fn ext_sort_supported(&self) -> bool {
+        self.inner.state.ext_sort_supported()
+    }
 
-        let data = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.select(mbox.to_string()))
-                .await;
+    /// Return the SASL mechanism used to authenticate the client.
+    pub fn auth_mechanism(&self) -> Option<SaslMechanism> {
+        self.client_builder.auth_mechanism
+    }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SelectMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::SelectMailboxError),
-            }
-        }?;
+    /// Return the security level negotiated with the server.
+    pub fn security_level(&self) -> Option<SecurityLevel> {
+        self.client_builder.security_level
+    }
+
+    /// Return the capabilities enabled by the server.
+    pub fn enabled_capabilities(&self) -> &[ImapCapability] {
+        &self.client_builder.enabled_capabilities
+    }
+
+    /// Return the information negotiated during the TLS handshake,
+    /// unless the connection is not encrypted.
+    pub fn tls_info(&self) -> Option<&TlsConnectionInfo> {
+        self.client_builder.tls_info.as_ref()
+    }
+
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn noop(&mut self) -> Result<()> {
+        let alerts = self.alerts.clone();
+
+        self.run(
+            ImapOperation::Control,
+            Error::NoOpTimedOutError,
+            Error::NoOpError,
+            |inner| {
+                let task = AlertTask::new(&alerts, NoOpTask::new());
+                Box::pin(async move { inner.resolve(task).await?.map_err(ClientError::from) })
+            },
+        )
+        .await
+    }
+
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn select_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDataUnvalidated> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        self.ensure_mailbox_exists(&mbox).await?;
+
+        let mailbox: Mailbox<'static> = mbox
+            .clone()
+            .try_into()
+            .map_err(|err| Error::SelectMailboxError(ClientError::from(err)))?;
+
+        let alerts = self.alerts.clone();
+        let data = self
+            .run(
+                ImapOperation::Control,
+                Error::SelectMailboxTimedOutError,
+                Error::SelectMailboxError,
+                |inner| {
+                    let task = AlertTask::new(&alerts, SelectTask::new(mailbox.clone()));
+                    Box::pin(async move { inner.resolve(task).await?.map_err(ClientError::from) })
+                },
+            )
+            .await?;
 
-        self.mailbox = Some(mbox.to_string());
+        self.track_uid_validity(&mbox, &data);
+        self.mailbox = Some(mbox);
 
         Ok(data)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn examine_mailbox(&mut self, mbox: impl ToString) -> Result<SelectDataUnvalidated> {
-        self.retry.reset();
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        self.ensure_mailbox_exists(&mbox).await?;
+
+        let mailbox: Mailbox<'static> = mbox
+            .clone()
+            .try_into()
+            .map_err(|err| Error::ExamineMailboxError(ClientError::from(err)))?;
+
+        let alerts = self.alerts.clone();
+        let data = self
+            .run(
+                ImapOperation::Control,
+                Error::ExamineMailboxTimedOutError,
+                Error::ExamineMailboxError,
+                |inner| {
+                    let task = AlertTask::new(&alerts, SelectTask::read_only(mailbox.clone()));
+                    Box::pin(async move { inner.resolve(task).await?.map_err(ClientError::from) })
+                },
+            )
+            .await?;
 
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.examine(mbox.to_string()))
-                .await;
+        self.track_uid_validity(&mbox, &data);
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExamineMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::ExamineMailboxError),
-            }
+        Ok(data)
+    }
+
+    /// Update the UIDVALIDITY store with the one announced by the
+    /// server when selecting the given mailbox.
+    ///
+    /// Failing to persist the store is not fatal: the change is still
+    /// detected in memory.
+    fn track_uid_validity(&self, mbox: &str, data: &SelectDataUnvalidated) {
+        let Some(uid_validity) = data.uid_validity else {
+            return;
+        };
+
+        let mut store = self
+            .uid_validity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        if let Err(err) = store.update(mbox, uid_validity) {
+            let err = Error::UpdateUidValidityError(err, mbox.to_owned());
+            warn!(?err, "cannot persist UIDVALIDITY");
         }
     }
 
+    /// Return the last known UIDVALIDITY of the given mailbox.
+    pub fn uid_validity(&self, mbox: &str) -> Option<NonZeroU32> {
+        self.uid_validity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(mbox)
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn create_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
-        self.retry.reset();
+        self.run(
+            ImapOperation::Control,
+            Error::CreateMailboxTimedOutError,
+            Error::CreateMailboxError,
+            |inner| Box::pin(inner.create(mbox.to_string())),
+        )
+        .await?;
 
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.create(mbox.to_string()))
-                .await;
+        self.mailboxes.insert(&mbox.to_string());
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::CreateMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::CreateMailboxError),
-            }
-        }
+        Ok(())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn list_all_mailboxes(&mut self, config: &AccountConfig) -> Result<Folders> {
-        self.retry.reset();
+        let mboxes = self.list_mailboxes().await?;
+        let folders = Folders::from_imap_mailboxes(config, mboxes);
+        Ok(folders)
+    }
 
-        let mboxes = loop {
-            let res = self.retry.timeout(self.inner.list("", "*")).await;
+    /// List all the mailboxes, and refresh the mailbox names cache
+    /// with them.
+    async fn list_mailboxes(&mut self) -> Result<ImapMailboxes> {
+        let mboxes = self
+            .run(
+                ImapOperation::Control,
+                Error::ListMailboxesTimedOutError,
+                Error::ListMailboxesError,
+                |inner| Box::pin(inner.list("", "*")),
+            )
+            .await?;
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ListMailboxesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::ListMailboxesError),
+        self.mailboxes
+            .fill(mboxes.iter().map(|(mbox, _, _)| match mbox {
+                Mailbox::Inbox => String::from("INBOX"),
+                Mailbox::Other(mbox) => String::from_utf8_lossy(mbox.as_ref()).into_owned(),
+            }));
+
+        Ok(mboxes)
+    }
+
+    /// Check that the given mailbox exists, when enabled by
+    /// [`ImapConfig::check_folders`].
+    ///
+    /// The mailbox names cache is refreshed when the mailbox is not
+    /// found, since it may have been created by another client.
+    async fn ensure_mailbox_exists(&mut self, mbox: &str) -> Result<()> {
+        if !self.imap_config.is_folder_check_enabled() || self.mailboxes.contains(mbox) {
+            return Ok(());
+        }
+
+        self.list_mailboxes().await?;
+
+        if self.mailboxes.contains(mbox) {
+            return Ok(());
+        }
+
+        let suggestions = self
+            .mailboxes
+            .suggest(mbox)
+            .into_iter()
+            .map(decode_utf7)
+            .collect();
+
+        Err(Error::FolderNotFoundError(
+            decode_utf7(mbox.to_owned()),
+            suggestions,
+        ))
+    }
+
+    /// Get the quota applying to the given mailbox, using the QUOTA
+    /// extension (RFC 9208).
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn get_quota_root(&mut self, mbox: impl ToString) -> Result<Quota> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        let mailbox: Mailbox<'static> = mbox
+            .try_into()
+            .map_err(|err| Error::GetQuotaRootError(ClientError::from(err)))?;
+
+        let alerts = self.alerts.clone();
+
+        self.run(
+            ImapOperation::Control,
+            Error::GetQuotaRootTimedOutError,
+            Error::GetQuotaRootError,
+            |inner| {
+                let task = AlertTask::new(&alerts, GetQuotaRootTask::new(mailbox.clone()));
+                Box::pin(async move { inner.resolve(task).await?.map_err(ClientError::from) })
+            },
+        )
+        .await
+    }
+
+    /// Get the access control list of the given mailbox, using the
+    /// ACL extension (RFC 4314).
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn get_acl(&mut self, mbox: impl ToString) -> Result<Vec<AclEntry>> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        let command = RawCommand::new("GETACL").string(&mbox);
+
+        let mut state = RawState::default();
+        let (responses, _) = self
+            .run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
+
+        let mut entries = Vec::new();
+
+        for response in responses.iter().filter(|r| is_named(r, "ACL")) {
+            let (name, acl) = parse_acl(response).ok_or_else(|| {
+                let response = String::from_utf8_lossy(response).into_owned();
+                Error::ParseRawResponseError("GETACL", response)
+            })?;
+
+            if normalize_mailbox(&name) == mbox {
+                entries.extend(acl);
             }
-        }?;
+        }
 
-        let folders = Folders::from_imap_mailboxes(config, mboxes);
+        Ok(entries)
+    }
 
-        Ok(folders)
+    /// Modify the access rights granted to the given identifier on
+    /// the given mailbox, using the ACL extension (RFC 4314).
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn set_acl(
+        &mut self,
+        mbox: impl ToString,
+        identifier: &str,
+        modification: &AclRightsModification,
+    ) -> Result<()> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        let command = RawCommand::new("SETACL")
+            .string(mbox)
+            .string(identifier)
+            .string(modification.to_string());
+
+        let mut state = RawState::default();
+        self.run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
+
+        Ok(())
     }
 
+    /// Remove the given identifier from the access control list of
+    /// the given mailbox, using the ACL extension (RFC 4314).
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
-        self.select_mailbox(mbox).await?;
+    pub async fn delete_acl(&mut self, mbox: impl ToString, identifier: &str) -> Result<()> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        let command = RawCommand::new("DELETEACL").string(mbox).string(identifier);
 
-        self.retry.reset();
+        let mut state = RawState::default();
+        self.run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
 
-        let expunged = loop {
-            let res = self.retry.timeout(self.inner.expunge()).await;
+        Ok(())
+    }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExpungeMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::ExpungeMailboxError),
+    /// Get the access rights of the current user on the given
+    /// mailbox, using the ACL extension (RFC 4314).
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn my_rights(&mut self, mbox: impl ToString) -> Result<AclRights> {
+        let mbox = normalize_mailbox(&mbox.to_string()).to_owned();
+        let command = RawCommand::new("MYRIGHTS").string(&mbox);
+
+        let mut state = RawState::default();
+        let (responses, _) = self
+            .run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
+
+        for response in responses.iter().filter(|r| is_named(r, "MYRIGHTS")) {
+            let (name, rights) = parse_my_rights(response).ok_or_else(|| {
+                let response = String::from_utf8_lossy(response).into_owned();
+                Error::ParseRawResponseError("MYRIGHTS", response)
+            })?;
+
+            if normalize_mailbox(&name) == mbox {
+                return Ok(rights);
             }
-        }?;
+        }
+
+        let reason = String::from("missing MYRIGHTS response");
+        Err(Error::ParseRawResponseError("MYRIGHTS", reason))
+    }
+
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn expunge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
+        self.select_mailbox(mbox).await?;
+
+        let expunged = self
+            .run(
+                ImapOperation::Control,
+                Error::ExpungeMailboxTimedOutError,
+                Error::ExpungeMailboxError,
+                |inner| Box::pin(inner.expunge()),
+            )
+            .await?;
 
         Ok(expunged.len())
     }
@@ -326,55 +648,146 @@ pub async fn purge_mailbox(&mut self, mbox: impl ToString) -> Result<usize> {
         self.add_deleted_flag_silently("1:*".try_into().unwrap())
             .await?;
 
-        let expunged = loop {
-            let res = self.retry.timeout(self.inner.expunge()).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ExpungeMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::ExpungeMailboxError),
-            }
-        }?;
+        let expunged = self
+            .run(
+                ImapOperation::Control,
+                Error::ExpungeMailboxTimedOutError,
+                Error::ExpungeMailboxError,
+                |inner| Box::pin(inner.expunge()),
+            )
+            .await?;
 
         Ok(expunged.len())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn delete_mailbox(&mut self, mbox: impl ToString) -> Result<()> {
-        self.retry.reset();
+        self.run(
+            ImapOperation::Control,
+            Error::DeleteMailboxTimedOutError,
+            Error::DeleteMailboxError,
+            |inner| Box::pin(inner.delete(mbox.to_string())),
+        )
+        .await?;
+
+        self.mailboxes.remove(&mbox.to_string());
+
+        // a mailbox created later with the same name is a different
+        // mailbox, its UIDVALIDITY must not be compared
+        let mbox = mbox.to_string();
+        let mut store = self
+            .uid_validity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+
+        if let Err(err) = store.invalidate(&mbox) {
+            let err = Error::UpdateUidValidityError(err, mbox);
+            warn!(?err, "cannot persist UIDVALIDITY");
+        }
 
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.delete(mbox.to_string()))
-                .await;
+        Ok(())
+    }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::DeleteMailboxTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::DeleteMailboxError),
-            }
-        }
+    /// Split the given UIDs into sequence sets short enough to be
+    /// sent in a single command, see [`chunk_sequence_set`].
+    fn chunk_uids(&self, uids: &SequenceSet) -> Vec<SequenceSet> {
+        chunk_sequence_set(uids, self.imap_config.max_sequence_set_len())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_envelopes(&mut self, uids: SequenceSet) -> Result<Envelopes> {
-        self.retry.reset();
+        self.fetch_envelopes_with_headers(uids, &[]).await
+    }
 
-        let fetches = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()))
-                .await;
+    /// Same as [`ImapClient::fetch_envelopes`], but also fetch the
+    /// given extra headers, see [`Envelope::headers`].
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_envelopes_with_headers(
+        &mut self,
+        uids: SequenceSet,
+        extra_headers: &[String],
+    ) -> Result<Envelopes> {
+        let items = fetch_envelopes_items(extra_headers);
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::FetchSmall,
+                    Error::FetchMessagesTimedOutError,
+                    Error::FetchMessagesError,
+                    |inner| Box::pin(inner.uid_fetch(uids.clone(), items.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+        Ok(Envelopes::from_imap_data_items(fetches))
+    }
+
+    /// Fetch previews of the given messages, see
+    /// [`Envelope::preview`].
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_previews(
+        &mut self,
+        uids: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, String>> {
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::FetchSmall,
+                    Error::FetchMessagesTimedOutError,
+                    Error::FetchMessagesError,
+                    |inner| Box::pin(inner.uid_fetch(uids.clone(), FETCH_PREVIEWS.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
+
+        let previews = fetches
+            .into_iter()
+            .filter_map(|(uid, items)| {
+                let preview = preview_from_imap_data_items(items.as_ref())?;
+                Some((uid, preview))
+            })
+            .collect();
+
+        Ok(previews)
+    }
+
+    /// Fetch the date the messages matching the given UIDs were
+    /// saved in the selected mailbox, using the SAVEDATE extension
+    /// (RFC 8514).
+    ///
+    /// Messages for which the server cannot tell the save date are
+    /// left out.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_save_dates(
+        &mut self,
+        uids: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, DateTime<FixedOffset>>> {
+        let mut save_dates = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let command = RawCommand::new("UID FETCH")
+                .arg(encode_sequence_set(&uids))
+                .arg("(UID SAVEDATE)");
+
+            let mut state = RawState::default();
+            let (responses, _) = self
+                .run_raw(&mut state, ImapOperation::FetchSmall, command)
+                .await?;
+
+            for response in responses {
+                if let Some((uid, Some(save_date))) = parse_save_date(&response) {
+                    save_dates.insert(uid, save_date);
+                }
             }
-        }?;
+        }
 
-        Ok(Envelopes::from_imap_data_items(fetches))
+        Ok(save_dates)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -382,18 +795,19 @@ pub async fn fetch_envelopes_map(
         &mut self,
         uids: SequenceSet,
     ) -> Result<HashMap<String, Envelope>> {
-        let fetches = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone()))
-                .await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
-            }
-        }?;
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::FetchSmall,
+                    Error::FetchMessagesTimedOutError,
+                    Error::FetchMessagesError,
+                    |inner| Box::pin(inner.uid_fetch(uids.clone(), FETCH_ENVELOPES.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
 
         let map = fetches
             .into_values()
@@ -408,37 +822,45 @@ pub async fn fetch_envelopes_map(
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_first_envelope(&mut self, uid: u32) -> Result<Envelope> {
-        let items = loop {
-            let task = self
-                .inner
-                .uid_fetch_first(uid.try_into().unwrap(), FETCH_ENVELOPES.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
-            }
-        }?;
+        let items = self
+            .run(
+                ImapOperation::FetchSmall,
+                Error::FetchMessagesTimedOutError,
+                Error::FetchMessagesError,
+                |inner| {
+                    Box::pin(
+                        inner.uid_fetch_first(uid.try_into().unwrap(), FETCH_ENVELOPES.clone()),
+                    )
+                },
+            )
+            .await?;
 
         Ok(Envelope::from_imap_data_items(items.as_ref()))
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn fetch_envelopes_by_sequence(&mut self, seq: SequenceSet) -> Result<Envelopes> {
-        let fetches = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.fetch(seq.clone(), FETCH_ENVELOPES.clone()))
-                .await;
+        self.fetch_envelopes_by_sequence_with_headers(seq, &[])
+            .await
+    }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
-            }
-        }?;
+    /// Same as [`ImapClient::fetch_envelopes_by_sequence`], but also
+    /// fetch the given extra headers, see [`Envelope::headers`].
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_envelopes_by_sequence_with_headers(
+        &mut self,
+        seq: SequenceSet,
+        extra_headers: &[String],
+    ) -> Result<Envelopes> {
+        let items = fetch_envelopes_items(extra_headers);
+        let fetches = self
+            .run(
+                ImapOperation::FetchSmall,
+                Error::FetchMessagesTimedOutError,
+                Error::FetchMessagesError,
+                |inner| Box::pin(inner.fetch(seq.clone(), items.clone())),
+            )
+            .await?;
 
         Ok(Envelopes::from_imap_data_items(fetches))
     }
@@ -449,25 +871,67 @@ pub async fn fetch_all_envelopes(&mut self) -> Result<Envelopes> {
             .await
     }
 
+    /// Fetch the flags of all messages of the selected mailbox,
+    /// indexed by UID.
+    ///
+    /// This is way lighter than [`ImapClient::fetch_all_envelopes`],
+    /// which makes it suitable for detecting changes.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_all_flags(&mut self) -> Result<HashMap<NonZeroU32, Flags>> {
+        let seq: SequenceSet = "1:*".try_into().unwrap();
+        let items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+            MessageDataItemName::Uid,
+            MessageDataItemName::Flags,
+        ]);
+
+        let fetches = self
+            .run(
+                ImapOperation::FetchSmall,
+                Error::FetchMessagesTimedOutError,
+                Error::FetchMessagesError,
+                |inner| Box::pin(inner.fetch(seq.clone(), items.clone())),
+            )
+            .await?;
+
+        let flags = fetches
+            .into_values()
+            .filter_map(|items| {
+                let mut uid = None;
+                let mut flags = Flags::default();
+
+                for item in items.as_ref() {
+                    match item {
+                        MessageDataItem::Uid(id) => uid = Some(*id),
+                        MessageDataItem::Flags(fetches) => {
+                            flags = Flags::from_imap_flag_fetches(fetches.as_ref());
+                        }
+                        _ => (),
+                    }
+                }
+
+                Some((uid?, flags))
+            })
+            .collect();
+
+        Ok(flags)
+    }
+
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn sort_uids(
         &mut self,
         sort_criteria: impl IntoIterator<Item = SortCriterion> + Clone,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<NonZeroU32>> {
-        loop {
-            let task = self
-                .inner
-                .uid_sort(sort_criteria.clone(), search_criteria.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SortUidsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::SortUidsError),
-            }
-        }
+        let sort_criteria: Vec<_> = sort_criteria.into_iter().collect();
+        let search_criteria: Vec<_> = search_criteria.into_iter().collect();
+
+        self.run(
+            ImapOperation::Control,
+            Error::SortUidsTimedOutError,
+            Error::SortUidsError,
+            |inner| Box::pin(inner.uid_sort(sort_criteria.clone(), search_criteria.clone())),
+        )
+        .await
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -475,18 +939,15 @@ pub async fn search_uids(
         &mut self,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<NonZeroU32>> {
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_search(search_criteria.clone()))
-                .await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::SearchUidsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::SearchUidsError),
-            }
-        }
+        let search_criteria: Vec<_> = search_criteria.into_iter().collect();
+
+        self.run(
+            ImapOperation::Control,
+            Error::SearchUidsTimedOutError,
+            Error::SearchUidsError,
+            |inner| Box::pin(inner.uid_search(search_criteria.clone())),
+        )
+        .await
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -495,21 +956,23 @@ pub async fn sort_envelopes(
         sort_criteria: impl IntoIterator<Item = SortCriterion> + Clone,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Envelopes> {
-        let fetches = loop {
-            let task = self.inner.uid_sort_or_fallback(
-                sort_criteria.clone(),
-                search_criteria.clone(),
-                FETCH_ENVELOPES.clone(),
-            );
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
-            }
-        }?;
+        let sort_criteria: Vec<_> = sort_criteria.into_iter().collect();
+        let search_criteria: Vec<_> = search_criteria.into_iter().collect();
+
+        let fetches = self
+            .run(
+                ImapOperation::FetchSmall,
+                Error::FetchMessagesTimedOutError,
+                Error::FetchMessagesError,
+                |inner| {
+                    Box::pin(inner.uid_sort_or_fallback(
+                        sort_criteria.clone(),
+                        search_criteria.clone(),
+                        FETCH_ENVELOPES.clone(),
+                    ))
+                },
+            )
+            .await?;
 
         Ok(Envelopes::from(fetches))
     }
@@ -519,19 +982,17 @@ pub async fn thread_envelopes(
         &mut self,
         search_criteria: impl IntoIterator<Item = SearchKey<'static>> + Clone,
     ) -> Result<Vec<Thread>> {
-        loop {
-            let task = self
-                .inner
-                .uid_thread(ThreadingAlgorithm::References, search_criteria.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::ThreadMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::ThreadMessagesError),
-            }
-        }
+        let search_criteria: Vec<_> = search_criteria.into_iter().collect();
+
+        self.run(
+            ImapOperation::FetchSmall,
+            Error::ThreadMessagesTimedOutError,
+            Error::ThreadMessagesError,
+            |inner| {
+                Box::pin(inner.uid_thread(ThreadingAlgorithm::References, search_criteria.clone()))
+            },
+        )
+        .await
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -554,62 +1015,203 @@ pub async fn idle(
         }
     }
 
+    /// Send the given raw command using the given state, then wait
+    /// for its tagged response, see [`raw`].
+    ///
+    /// Untagged responses received in the meantime are returned,
+    /// together with the text of the tagged OK response. Unlike
+    /// commands sent by the codec, raw commands are not retried.
+    async fn run_raw(
+        &mut self,
+        state: &mut RawState,
+        operation: ImapOperation,
+        command: RawCommand,
+    ) -> Result<(Vec<Vec<u8>>, String)> {
+        let name = command.name();
+        let duration = self.imap_config.timeout(operation);
+        let stream = &mut self.inner.stream;
+        let mut untagged = Vec::new();
+
+        state.enqueue(command);
+
+        let res = timeout(duration, async {
+            loop {
+                match stream.next(&mut *state).await {
+                    Ok(RawResponse::Untagged(response)) => untagged.push(response),
+                    Ok(RawResponse::Tagged(RawStatus::Ok(text))) => break Ok(text),
+                    Ok(RawResponse::Tagged(status)) => {
+                        break Err(Error::RejectRawCommandError(name, status.to_string()))
+                    }
+                    Err(err) => break Err(Error::SendRawCommandError(err, name)),
+                }
+            }
+        })
+        .await;
+
+        match res {
+            Ok(Ok(text)) => Ok((untagged, text)),
+            Ok(Err(err)) => Err(err),
+            Err(_) => Err(Error::SendRawCommandTimedOutError(name)),
+        }
+    }
+
+    /// Wait for the events of the given set to happen, using the
+    /// NOTIFY extension (RFC 5465), then return the notifications
+    /// received.
+    ///
+    /// Notifications are disabled again before returning, so that
+    /// they do not interfere with the next commands. Like IDLE, the
+    /// function also returns when the IDLE timeout elapses, without
+    /// notification.
+    ///
+    /// When the server does not advertise NOTIFY, the client falls
+    /// back to IDLE: only changes of the selected mailbox are
+    /// awaited, and no notification is returned since IDLE does not
+    /// expose them.
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn add_flags(
+    pub async fn notify(
         &mut self,
-        uids: SequenceSet,
-        flags: impl IntoIterator<Item = Flag<'static>> + Clone,
-    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
-        loop {
-            let task = self
-                .inner
-                .uid_store(uids.clone(), StoreType::Add, flags.clone());
+        set: &ImapNotifySet,
+        wait_for_shutdown_request: &mut oneshot::Receiver<()>,
+    ) -> Result<Vec<ImapNotification>> {
+        if !self.ext_notify_supported() {
+            if !set.is_selected_only() {
+                debug!(%set, "IMAP NOTIFY extension not supported, using IDLE");
+            }
 
-            let res = self.retry.timeout(task).await;
+            return match self.idle(wait_for_shutdown_request).await {
+                Err(Error::IdleInterruptedError) => Err(Error::NotifyInterruptedError),
+                res => res.map(|()| Vec::new()),
+            };
+        }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
+        let mut state = RawState::default();
+
+        let command = RawCommand::new("NOTIFY").arg(set.to_string());
+        let (responses, _) = self
+            .run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
+        let mut notifications: Vec<_> = responses
+            .iter()
+            .filter_map(|response| ImapNotification::parse(response))
+            .collect();
+
+        let idle_timeout = *self.inner.state.get_idle_timeout();
+        let mut interrupted = false;
+
+        while notifications.is_empty() {
+            let next = timeout(idle_timeout, self.inner.stream.next(&mut state));
+
+            select! {
+                res = next => match res {
+                    Err(_) => {
+                        debug!("timed out without notification");
+                        break;
+                    }
+                    Ok(Err(err)) => {
+                        return Err(Error::WaitForNotificationsError(err));
+                    }
+                    Ok(Ok(RawResponse::Untagged(response))) => {
+                        notifications.extend(ImapNotification::parse(&response));
+                    }
+                    Ok(Ok(RawResponse::Tagged(_))) => {
+                        // no command is pending
+                    }
+                },
+                _ = &mut *wait_for_shutdown_request => {
+                    debug!("shutdown requested, disabling notifications…");
+                    interrupted = true;
+                    break;
+                }
             }
         }
+
+        let command = RawCommand::new("NOTIFY").arg("NONE");
+        let (responses, _) = self
+            .run_raw(&mut state, ImapOperation::Control, command)
+            .await?;
+        notifications.extend(
+            responses
+                .iter()
+                .filter_map(|response| ImapNotification::parse(response)),
+        );
+
+        if interrupted {
+            return Err(Error::NotifyInterruptedError);
+        }
+
+        debug!(?notifications, "received notifications");
+        Ok(notifications)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn add_deleted_flag(
+    pub async fn add_flags(
         &mut self,
         uids: SequenceSet,
+        flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
-        loop {
-            let task = self
-                .inner
-                .uid_store(uids.clone(), StoreType::Add, Some(Flag::Deleted));
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::StoreFlagsTimedOutError,
+                    Error::StoreFlagsError,
+                    |inner| Box::pin(inner.uid_store(uids.clone(), StoreType::Add, flags.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
 
-            let res = self.retry.timeout(task).await;
+        Ok(fetches)
+    }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn add_deleted_flag(
+        &mut self,
+        uids: SequenceSet,
+    ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::StoreFlagsTimedOutError,
+                    Error::StoreFlagsError,
+                    |inner| {
+                        Box::pin(inner.uid_store(uids.clone(), StoreType::Add, Some(Flag::Deleted)))
+                    },
+                )
+                .await?;
+            fetches.extend(chunk);
         }
+
+        Ok(fetches)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn add_deleted_flag_silently(&mut self, uids: SequenceSet) -> Result<()> {
-        loop {
-            let task =
-                self.inner
-                    .uid_silent_store(uids.clone(), StoreType::Add, Some(Flag::Deleted));
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        for uids in self.chunk_uids(&uids) {
+            self.run(
+                ImapOperation::Control,
+                Error::StoreFlagsTimedOutError,
+                Error::StoreFlagsError,
+                |inner| {
+                    Box::pin(inner.uid_silent_store(
+                        uids.clone(),
+                        StoreType::Add,
+                        Some(Flag::Deleted),
+                    ))
+                },
+            )
+            .await?;
         }
+
+        Ok(())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -618,19 +1220,21 @@ pub async fn add_flags_silently(
         uids: SequenceSet,
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<()> {
-        loop {
-            let task = self
-                .inner
-                .uid_silent_store(uids.clone(), StoreType::Add, flags.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        for uids in self.chunk_uids(&uids) {
+            self.run(
+                ImapOperation::Control,
+                Error::StoreFlagsTimedOutError,
+                Error::StoreFlagsError,
+                |inner| {
+                    Box::pin(inner.uid_silent_store(uids.clone(), StoreType::Add, flags.clone()))
+                },
+            )
+            .await?;
         }
+
+        Ok(())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -639,19 +1243,25 @@ pub async fn set_flags(
         uids: SequenceSet,
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
-        loop {
-            let task = self
-                .inner
-                .uid_store(uids.clone(), StoreType::Replace, flags.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::StoreFlagsTimedOutError,
+                    Error::StoreFlagsError,
+                    |inner| {
+                        Box::pin(inner.uid_store(uids.clone(), StoreType::Replace, flags.clone()))
+                    },
+                )
+                .await?;
+            fetches.extend(chunk);
         }
+
+        Ok(fetches)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -660,19 +1270,25 @@ pub async fn set_flags_silently(
         uids: SequenceSet,
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<()> {
-        loop {
-            let task = self
-                .inner
-                .uid_silent_store(uids.clone(), StoreType::Replace, flags.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        for uids in self.chunk_uids(&uids) {
+            self.run(
+                ImapOperation::Control,
+                Error::StoreFlagsTimedOutError,
+                Error::StoreFlagsError,
+                |inner| {
+                    Box::pin(inner.uid_silent_store(
+                        uids.clone(),
+                        StoreType::Replace,
+                        flags.clone(),
+                    ))
+                },
+            )
+            .await?;
         }
+
+        Ok(())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -681,19 +1297,25 @@ pub async fn remove_flags(
         uids: SequenceSet,
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<HashMap<NonZeroU32, Vec1<MessageDataItem<'static>>>> {
-        loop {
-            let task = self
-                .inner
-                .uid_store(uids.clone(), StoreType::Remove, flags.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::StoreFlagsTimedOutError,
+                    Error::StoreFlagsError,
+                    |inner| {
+                        Box::pin(inner.uid_store(uids.clone(), StoreType::Remove, flags.clone()))
+                    },
+                )
+                .await?;
+            fetches.extend(chunk);
         }
+
+        Ok(fetches)
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -702,19 +1324,21 @@ pub async fn remove_flags_silently(
         uids: SequenceSet,
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
     ) -> Result<()> {
-        loop {
-            let task = self
-                .inner
-                .uid_silent_store(uids.clone(), StoreType::Remove, flags.clone());
-
-            let res = self.retry.timeout(task).await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::StoreFlagsTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::StoreFlagsError),
-            }
+        let flags: Vec<_> = flags.into_iter().collect();
+
+        for uids in self.chunk_uids(&uids) {
+            self.run(
+                ImapOperation::Control,
+                Error::StoreFlagsTimedOutError,
+                Error::StoreFlagsError,
+                |inner| {
+                    Box::pin(inner.uid_silent_store(uids.clone(), StoreType::Remove, flags.clone()))
+                },
+            )
+            .await?;
         }
+
+        Ok(())
     }
 
     #[instrument(skip_all, fields(client = self.id))]
@@ -724,37 +1348,252 @@ pub async fn add_message(
         flags: impl IntoIterator<Item = Flag<'static>> + Clone,
         msg: impl AsRef<[u8]> + Clone,
     ) -> Result<NonZeroU32> {
-        let id = loop {
-            let task =
-                self.inner
-                    .appenduid_or_fallback(mbox.to_string(), flags.clone(), msg.clone());
+        let flags: Vec<_> = flags.into_iter().collect();
+        // the message is shared by the attempts, without copying it
+        let msg: Arc<[u8]> = Arc::from(msg.as_ref());
+
+        if let Some(limit) = self.ext_appendlimit() {
+            let size = msg.len();
+            if size > limit {
+                return Err(Error::AddMessageTooLargeError(size, limit));
+            }
+        }
 
-            let res = self.retry.timeout(task).await;
+        let id = self
+            .run(
+                ImapOperation::Append,
+                Error::AddMessageTimedOutError,
+                Error::AddMessageError,
+                |inner| {
+                    Box::pin(inner.appenduid_or_fallback(
+                        mbox.to_string(),
+                        flags.clone(),
+                        msg.clone(),
+                    ))
+                },
+            )
+            .await?;
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::AddMessageTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::AddMessageError),
+        id.ok_or(Error::FindAppendedMessageUidError)
+    }
+
+    /// Return the maximum size of messages the server accepts to
+    /// append, as advertised by the APPENDLIMIT extension (RFC 7889).
+    ///
+    /// Limits advertised per mailbox are not taken into account.
+    pub fn ext_appendlimit(&self) -> Option<usize> {
+        self.inner.state.capabilities_iter().find_map(|cap| {
+            let cap = cap.to_string();
+            let (name, limit) = cap.split_once('=')?;
+            if name.eq_ignore_ascii_case("APPENDLIMIT") {
+                limit.parse().ok()
+            } else {
+                None
             }
-        }?;
+        })
+    }
 
-        id.ok_or(Error::FindAppendedMessageUidError)
+    /// Return the level of support of the given backend feature,
+    /// based on the capabilities advertised by the server.
+    pub fn feature_support(&self, feature: BackendFeatureKind) -> BackendFeatureSupport {
+        let state = &self.inner.state;
+
+        let (supported, fallback) = match feature {
+            // without SORT, envelopes are sorted client side
+            BackendFeatureKind::ListEnvelopes => {
+                (state.ext_sort_supported(), BackendFeatureSupport::Partial)
+            }
+            // without BINARY, parts are decoded client side
+            BackendFeatureKind::GetAttachment => {
+                (self.ext_binary_supported(), BackendFeatureSupport::Partial)
+            }
+            #[cfg(feature = "thread")]
+            BackendFeatureKind::ThreadEnvelopes => (
+                state.ext_thread_supported(),
+                BackendFeatureSupport::Unsupported,
+            ),
+            #[cfg(feature = "watch")]
+            BackendFeatureKind::WatchEnvelopes => (
+                state.ext_idle_supported(),
+                BackendFeatureSupport::Unsupported,
+            ),
+            BackendFeatureKind::GetQuota => (
+                self.ext_quota_supported(),
+                BackendFeatureSupport::Unsupported,
+            ),
+            BackendFeatureKind::ManageFolderAcl => {
+                (self.ext_acl_supported(), BackendFeatureSupport::Unsupported)
+            }
+            BackendFeatureKind::MoveMessages => (
+                state.ext_move_supported(),
+                BackendFeatureSupport::Unsupported,
+            ),
+            // deleting messages moves them to the trash folder
+            BackendFeatureKind::DeleteMessages => {
+                (state.ext_move_supported(), BackendFeatureSupport::Partial)
+            }
+            _ => (true, BackendFeatureSupport::Full),
+        };
+
+        if supported {
+            BackendFeatureSupport::Full
+        } else {
+            fallback
+        }
+    }
+
+    /// Return `true` if the server advertises the QUOTA extension
+    /// (RFC 2087, RFC 9208).
+    pub fn ext_quota_supported(&self) -> bool {
+        self.inner.state.capabilities_iter().any(|cap| {
+            let cap = cap.to_string().to_ascii_uppercase();
+            cap == "QUOTA" || cap.starts_with("QUOTA=")
+        })
+    }
+
+    /// Return `true` if the server advertises the MULTIAPPEND
+    /// extension (RFC 3502).
+    pub fn ext_multiappend_supported(&self) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("MULTIAPPEND"))
+    }
+
+    /// Return `true` if the server advertises the NOTIFY extension
+    /// (RFC 5465).
+    pub fn ext_notify_supported(&self) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("NOTIFY"))
+    }
+
+    /// Return `true` if the server advertises the SAVEDATE extension
+    /// (RFC 8514).
+    pub fn ext_savedate_supported(&self) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("SAVEDATE"))
+    }
+
+    /// Return `true` if the server advertises the ACL extension (RFC
+    /// 4314).
+    pub fn ext_acl_supported(&self) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("ACL"))
+    }
+
+    /// Return the rights advertised by the `RIGHTS=` capability of
+    /// the ACL extension (RFC 4314), which lists the rights supported
+    /// by the server on top of the RFC 2086 ones.
+    pub fn ext_acl_rights(&self) -> Option<AclRights> {
+        self.inner.state.capabilities_iter().find_map(|cap| {
+            let cap = cap.to_string();
+            let (name, rights) = cap.split_once('=')?;
+            if name.eq_ignore_ascii_case("RIGHTS") {
+                Some(AclRights::from(rights))
+            } else {
+                None
+            }
+        })
     }
 
+    /// Add the given messages with their flags to the given mailbox.
+    ///
+    /// When the server advertises both the MULTIAPPEND (RFC 3502) and
+    /// UIDPLUS (RFC 4315) extensions, messages are added using a
+    /// single APPEND command. Otherwise they are added one after the
+    /// other, using the same client for the whole batch.
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
-        let mut fetches = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), FETCH_MESSAGES.clone()))
-                .await;
+    pub async fn add_messages<F>(
+        &mut self,
+        mbox: impl ToString,
+        msgs: Vec<(F, Vec<u8>)>,
+    ) -> Result<Vec<NonZeroU32>>
+    where
+        F: IntoIterator<Item = Flag<'static>> + Clone,
+    {
+        let mbox = mbox.to_string();
+
+        if msgs.len() > 1
+            && self.ext_multiappend_supported()
+            && self.inner.state.ext_uidplus_supported()
+        {
+            return self.multiappend(&mbox, msgs).await;
+        }
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
+        let mut uids = Vec::with_capacity(msgs.len());
+
+        for (flags, msg) in msgs {
+            let uid = self.add_message(&mbox, flags, Cow::Owned(msg)).await?;
+            uids.push(uid);
+        }
+
+        Ok(uids)
+    }
+
+    /// Add the given messages with their flags to the given mailbox
+    /// using a single APPEND command, then return their UIDs as
+    /// announced by the APPENDUID response code.
+    ///
+    /// The server adds either all the messages or none of them (RFC
+    /// 3502 section 6.3.11).
+    async fn multiappend<F>(
+        &mut self,
+        mbox: &str,
+        msgs: Vec<(F, Vec<u8>)>,
+    ) -> Result<Vec<NonZeroU32>>
+    where
+        F: IntoIterator<Item = Flag<'static>>,
+    {
+        let count = msgs.len();
+        let limit = self.ext_appendlimit();
+        let mut command = RawCommand::new("APPEND").string(mbox);
+
+        for (flags, msg) in msgs {
+            if let Some(limit) = limit {
+                let size = msg.len();
+                if size > limit {
+                    return Err(Error::AddMessageTooLargeError(size, limit));
+                }
             }
-        }?;
+
+            let flags: Vec<_> = flags.into_iter().map(|flag| flag.to_string()).collect();
+            command = command.arg(format!("({})", flags.join(" "))).literal(msg);
+        }
+
+        debug!(count, "adding messages using MULTIAPPEND");
+
+        let mut state = RawState::default();
+        let (_, text) = self
+            .run_raw(&mut state, ImapOperation::Append, command)
+            .await?;
+
+        match parse_appenduid(&text) {
+            Some(uids) if uids.len() == count => Ok(uids),
+            _ => Err(Error::FindAppendedMessageUidError),
+        }
+    }
+
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::FetchBody,
+                    Error::FetchMessagesTimedOutError,
+                    Error::FetchMessagesError,
+                    |inner| Box::pin(inner.uid_fetch(uids.clone(), FETCH_MESSAGES.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
 
         let fetches: Vec<_> = uids
             .iter(NonZeroU32::MAX)
@@ -766,18 +1605,19 @@ pub async fn fetch_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
 
     #[instrument(skip_all, fields(client = self.id))]
     pub async fn peek_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
-        let mut fetches = loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_fetch(uids.clone(), PEEK_MESSAGES.clone()))
-                .await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::FetchMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::FetchMessagesError),
-            }
-        }?;
+        let mut fetches = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::FetchBody,
+                    Error::FetchMessagesTimedOutError,
+                    Error::FetchMessagesError,
+                    |inner| Box::pin(inner.uid_fetch(uids.clone(), PEEK_MESSAGES.clone())),
+                )
+                .await?;
+            fetches.extend(chunk);
+        }
 
         let fetches: Vec<_> = uids
             .iter(NonZeroU32::MAX)
@@ -787,36 +1627,188 @@ pub async fn peek_messages(&mut self, uids: SequenceSet) -> Result<Messages> {
         Ok(Messages::from(fetches))
     }
 
+    /// Return `true` if the server advertises the BINARY extension
+    /// (RFC 3516).
+    pub fn ext_binary_supported(&self) -> bool {
+        self.inner
+            .state
+            .capabilities_iter()
+            .any(|cap| cap.to_string().eq_ignore_ascii_case("BINARY"))
+    }
+
+    /// Fetch at most `len` bytes of the decoded content of the given
+    /// message part, starting at `offset`, without setting the
+    /// `\Seen` flag.
+    ///
+    /// Requires the BINARY extension. Retries only fetch the
+    /// requested byte range again.
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn copy_messages(&mut self, uids: SequenceSet, mbox: impl ToString) -> Result<()> {
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_copy(uids.clone(), mbox.to_string()))
-                .await;
+    pub async fn fetch_binary_part(
+        &mut self,
+        uid: NonZeroU32,
+        section: &[NonZeroU32],
+        offset: u32,
+        len: NonZeroU32,
+    ) -> Result<Vec<u8>> {
+        let items =
+            MacroOrMessageDataItemNames::MessageDataItemNames(vec![MessageDataItemName::Binary {
+                section: section.to_vec(),
+                partial: Some((offset, len)),
+                peek: true,
+            }]);
+
+        let items = self
+            .run(
+                ImapOperation::FetchBody,
+                Error::FetchMessagePartTimedOutError,
+                Error::FetchMessagePartError,
+                |inner| Box::pin(inner.uid_fetch_first(uid, items.clone())),
+            )
+            .await?;
 
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::CopyMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::CopyMessagesError),
+        items
+            .into_iter()
+            .find_map(|item| match item {
+                MessageDataItem::Binary { value, .. } => Some(match value {
+                    NString8::NString(value) => value
+                        .0
+                        .map(|value| value.as_ref().to_vec())
+                        .unwrap_or_default(),
+                    NString8::Literal8(value) => value.data.into_owned(),
+                }),
+                _ => None,
+            })
+            .ok_or(Error::FetchMessagePartEmptyError)
+    }
+
+    /// Fetch the MIME header and the raw content of the given
+    /// message part, without setting the `\Seen` flag.
+    ///
+    /// The result can be parsed as a standalone message holding the
+    /// part only.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn fetch_part(&mut self, uid: NonZeroU32, section: &[NonZeroU32]) -> Result<Vec<u8>> {
+        let part =
+            Part(Vec1::try_from(section.to_vec()).map_err(|_| Error::FetchMessagePartEmptyError)?);
+
+        let items = MacroOrMessageDataItemNames::MessageDataItemNames(vec![
+            MessageDataItemName::BodyExt {
+                section: Some(Section::Mime(part.clone())),
+                partial: None,
+                peek: true,
+            },
+            MessageDataItemName::BodyExt {
+                section: Some(Section::Part(part)),
+                partial: None,
+                peek: true,
+            },
+        ]);
+
+        let items = self
+            .run(
+                ImapOperation::FetchBody,
+                Error::FetchMessagePartTimedOutError,
+                Error::FetchMessagePartError,
+                |inner| Box::pin(inner.uid_fetch_first(uid, items.clone())),
+            )
+            .await?;
+
+        let mut header = None;
+        let mut body = None;
+
+        for item in items {
+            if let MessageDataItem::BodyExt { section, data, .. } = item {
+                let data = data.0.map(|data| data.as_ref().to_vec());
+                match section {
+                    Some(Section::Mime(_)) => header = data,
+                    Some(Section::Part(_)) => body = data,
+                    _ => (),
+                }
             }
         }
+
+        let (Some(mut header), Some(body)) = (header, body) else {
+            return Err(Error::FetchMessagePartEmptyError);
+        };
+
+        header.extend(body);
+        Ok(header)
+    }
+
+    /// Copy the given messages to the given mailbox.
+    ///
+    /// Returns the mapping between the given UIDs and the UIDs of the
+    /// copied messages. The mapping is empty if the server does not
+    /// support the UIDPLUS extension.
+    #[instrument(skip_all, fields(client = self.id))]
+    pub async fn copy_messages(
+        &mut self,
+        uids: SequenceSet,
+        mbox: impl ToString,
+    ) -> Result<UidMapping> {
+        let mbox: Mailbox<'static> = mbox
+            .to_string()
+            .try_into()
+            .map_err(|err| Error::CopyMessagesError(ClientError::from(err)))?;
+
+        let mut mapping = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::CopyMessagesTimedOutError,
+                    Error::CopyMessagesError,
+                    |inner| {
+                        let task = CopyUidTask::new(uids.clone(), mbox.clone());
+                        Box::pin(
+                            async move { inner.resolve(task).await?.map_err(ClientError::from) },
+                        )
+                    },
+                )
+                .await?;
+            mapping.extend(chunk);
+        }
+
+        Ok(mapping)
     }
 
+    /// Move the given messages to the given mailbox.
+    ///
+    /// Returns the mapping between the given UIDs and the UIDs of the
+    /// moved messages. The mapping is empty if the server does not
+    /// support the UIDPLUS extension.
     #[instrument(skip_all, fields(client = self.id))]
-    pub async fn move_messages(&mut self, uids: SequenceSet, mbox: impl ToString) -> Result<()> {
-        loop {
-            let res = self
-                .retry
-                .timeout(self.inner.uid_move(uids.clone(), mbox.to_string()))
-                .await;
-
-            match self.retry(res).await? {
-                ImapRetryState::Retry => continue,
-                ImapRetryState::TimedOut => break Err(Error::MoveMessagesTimedOutError),
-                ImapRetryState::Ok(res) => break res.map_err(Error::MoveMessagesError),
-            }
+    pub async fn move_messages(
+        &mut self,
+        uids: SequenceSet,
+        mbox: impl ToString,
+    ) -> Result<UidMapping> {
+        let mbox: Mailbox<'static> = mbox
+            .to_string()
+            .try_into()
+            .map_err(|err| Error::MoveMessagesError(ClientError::from(err)))?;
+
+        let mut mapping = HashMap::new();
+
+        for uids in self.chunk_uids(&uids) {
+            let chunk = self
+                .run(
+                    ImapOperation::Control,
+                    Error::MoveMessagesTimedOutError,
+                    Error::MoveMessagesError,
+                    |inner| {
+                        let task = CopyUidTask::new(uids.clone(), mbox.clone()).with_move(true);
+                        Box::pin(
+                            async move { inner.resolve(task).await?.map_err(ClientError::from) },
+                        )
+                    },
+                )
+                .await?;
+            mapping.extend(chunk);
         }
+
+        Ok(mapping)
     }
 }
 
@@ -830,6 +1822,31 @@ fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
     }
 }
 
+/// Collect the information negotiated during the TLS handshake of
+/// the given client, unless the connection is not encrypted.
+///
+/// The client stream does not expose the underlying connection by
+/// reference, so the stream is unwrapped then wrapped back, the same
+/// way the client does when upgrading to TLS. Nothing is lost: the
+/// stream only owns a scratch read buffer, data read ahead is kept by
+/// the client state.
+fn with_tls_info(mut client: Client) -> (Client, Option<TlsConnectionInfo>) {
+    let stream = client.stream.into_inner();
+
+    let info = match &stream {
+        MaybeTlsStream::Plain(_) => None,
+        #[cfg(feature = "tokio-rustls")]
+        MaybeTlsStream::Rustls(stream) => Some(TlsConnectionInfo::from_rustls(stream.get_ref().1)),
+        #[cfg(feature = "tokio-native-tls")]
+        MaybeTlsStream::NativeTls(stream) => {
+            Some(TlsConnectionInfo::from_native_tls(stream.get_ref()))
+        }
+    };
+
+    client.stream = Stream::new(stream);
+    (client, info)
+}
+
 /// The sync version of the IMAP backend context.
 ///
 /// This is just an IMAP session wrapped into a mutex, so the same
@@ -843,10 +1860,78 @@ pub struct ImapContext {
     pub imap_config: Arc<ImapConfig>,
 
     clients: Vec<Arc<Mutex<ImapClient>>>,
+
+    /// The UIDVALIDITY store, shared by all clients.
+    uid_validity: Arc<StdMutex<UidValidityStore>>,
+
+    /// The server alerts, shared by all clients.
+    alerts: ImapAlerts,
+
+    /// The level of support of backend features, computed from the
+    /// server capabilities when the context is built.
+    features: Arc<BackendFeatures>,
 }
 
 impl ImapContext {
-    pub async fn client(&self) -> MutexGuard<'_, ImapClient> {
+    /// Lock the first free client of the pool.
+    ///
+    /// Fails fast if the account is in offline mode.
+    pub async fn client(&self) -> backend::Result<MutexGuard<'_, ImapClient>> {
+        self.account_config.ensure_online()?;
+        Ok(self.lock_client().await)
+    }
+
+    /// Return the capabilities enabled by the server.
+    ///
+    /// All the clients of the pool share the same configuration, so
+    /// the first free client is used.
+    pub async fn enabled_capabilities(&self) -> Vec<ImapCapability> {
+        self.lock_client().await.enabled_capabilities().to_vec()
+    }
+
+    /// Take the UIDVALIDITY changes detected since the last call.
+    ///
+    /// When the UIDVALIDITY of a folder changes, every UID previously
+    /// known for this folder is invalid: UID-based caches need to be
+    /// invalidated, then rebuilt. The [`Backend`](crate::backend::Backend)
+    /// takes them after every operation through
+    /// [`BackendContext::take_invalidated_folders`].
+    pub fn take_uid_validity_changes(&self) -> Vec<UidValidityChange> {
+        self.lock_uid_validity().take_changes()
+    }
+
+    fn lock_uid_validity(&self) -> StdMutexGuard<'_, UidValidityStore> {
+        self.uid_validity
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Take the alerts and warnings sent by the server since the
+    /// last call.
+    ///
+    /// Servers use them to notify users about important events (the
+    /// mailbox is almost full, the password is about to expire etc),
+    /// they should be displayed as is.
+    pub fn take_alerts(&self) -> Vec<ImapAlert> {
+        self.alerts.take()
+    }
+
+    /// Build the stable identifier of the envelope matching the
+    /// given UID.
+    ///
+    /// Return `None` when the UIDVALIDITY of the folder is unknown,
+    /// which happens when the folder has not been selected yet.
+    pub fn stable_id(&self, folder: &str, uid: NonZeroU32) -> Option<StableId> {
+        let mbox = encode_utf7(self.account_config.get_folder_alias(folder));
+
+        let uid_validity = self.lock_uid_validity().get(normalize_mailbox(&mbox))?;
+
+        Some(StableId::imap(folder, uid_validity, uid))
+    }
+
+    /// Lock the first free client of the pool, without checking the
+    /// offline mode.
+    pub async fn lock_client(&self) -> MutexGuard<'_, ImapClient> {
         loop {
             let lock = self
                 .clients
@@ -866,7 +1951,54 @@ pub async fn client(&self) -> MutexGuard<'_, ImapClient> {
     }
 }
 
-impl BackendContext for ImapContext {}
+#[async_trait]
+impl BackendContext for ImapContext {
+    fn feature_support(&self, feature: BackendFeatureKind) -> BackendFeatureSupport {
+        self.features.get(&feature).copied().unwrap_or_default()
+    }
+
+    /// Take the folders whose UIDVALIDITY changed since the last
+    /// call.
+    fn take_invalidated_folders(&self) -> Vec<String> {
+        self.take_uid_validity_changes()
+            .into_iter()
+            .map(|change| decode_utf7(change.folder))
+            .collect()
+    }
+
+    /// Resolve the given stable identifier into a UID.
+    ///
+    /// Fails if the identifier does not belong to IMAP, or if the
+    /// UIDVALIDITY of its folder changed since it was built. When
+    /// the UIDVALIDITY of the folder is not known yet, the folder is
+    /// selected first.
+    async fn resolve_stable_id(&self, id: &StableId) -> AnyResult<SingleId> {
+        id.ensure_kind(StableIdKind::Imap)?;
+
+        let folder = self.account_config.get_folder_alias(id.folder());
+        let mbox = encode_utf7(folder.clone());
+        let mbox = normalize_mailbox(&mbox);
+
+        // the store guard must not be held across the select below
+        let known_uid_validity = self.lock_uid_validity().get(mbox);
+
+        let uid_validity = match known_uid_validity {
+            Some(uid_validity) => uid_validity,
+            None => {
+                debug!("unknown UIDVALIDITY for folder {folder}, selecting it");
+                let mut client = self.client().await?;
+                client.select_mailbox(mbox).await?;
+                client
+                    .uid_validity(mbox)
+                    .ok_or_else(|| Error::UnknownUidValidityError(folder.clone()))?
+            }
+        };
+
+        id.ensure_uid_validity(uid_validity)?;
+
+        Ok(SingleId::from(id.id()))
+    }
+}
 
 /// The IMAP backend context builder.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -878,9 +2010,12 @@ pub struct ImapContextBuilder {
     pub imap_config: Arc<ImapConfig>,
 
     /// The prebuilt IMAP credentials.
-    prebuilt_credentials: Option<String>,
+    prebuilt_credentials: Option<Redacted<String>>,
 
     pool_size: u8,
+
+    /// The file where UIDVALIDITY of folders is persisted.
+    uid_validity_path: Option<PathBuf>,
 }
 
 impl ImapContextBuilder {
@@ -892,11 +2027,13 @@ pub fn new(account_config: Arc<AccountConfig>, imap_config: Arc<ImapConfig>) ->
             imap_config,
             prebuilt_credentials: None,
             pool_size,
+            uid_validity_path: None,
         }
     }
 
     pub async fn prebuild_credentials(&mut self) -> Result<()> {
-        self.prebuilt_credentials = Some(self.imap_config.build_credentials().await?);
+        let credentials = self.imap_config.build_credentials().await?;
+        self.prebuilt_credentials = Some(Redacted::new(credentials));
         Ok(())
     }
 
@@ -909,11 +2046,26 @@ pub fn with_pool_size(mut self, pool_size: u8) -> Self {
         self.pool_size = pool_size;
         self
     }
+
+    /// Persist the UIDVALIDITY of folders in the given file.
+    ///
+    /// Without such file, UIDVALIDITY changes can only be detected
+    /// during the lifetime of the context.
+    pub fn set_uid_validity_path(&mut self, path: impl Into<PathBuf>) {
+        self.uid_validity_path = Some(path.into());
+    }
+
+    /// Persist the UIDVALIDITY of folders in the given file, using
+    /// the builder pattern.
+    pub fn with_uid_validity_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.set_uid_validity_path(path);
+        self
+    }
 }
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for ImapContextBuilder {
-    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
+    fn sync_hash(&self, state: &mut crate::sync::hash::SyncHasher) {
         self.imap_config.sync_hash(state);
     }
 }
@@ -946,6 +2098,14 @@ fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder
         Some(Arc::new(DeleteImapFolder::some_new_boxed))
     }
 
+    fn get_quota(&self) -> Option<BackendFeature<Self::Context, dyn GetQuota>> {
+        Some(Arc::new(GetImapQuota::some_new_boxed))
+    }
+
+    fn manage_folder_acl(&self) -> Option<BackendFeature<Self::Context, dyn ManageFolderAcl>> {
+        Some(Arc::new(ManageImapFolderAcl::some_new_boxed))
+    }
+
     fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
         Some(Arc::new(GetImapEnvelope::some_new_boxed))
     }
@@ -976,6 +2136,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveImapFlags::some_new_boxed))
     }
 
+    fn mark_read_before(&self) -> Option<BackendFeature<Self::Context, dyn MarkReadBefore>> {
+        Some(Arc::new(MarkReadBeforeImap::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddImapMessage::some_new_boxed))
     }
@@ -988,6 +2152,10 @@ fn get_messages(&self) -> Option<BackendFeature<Self::Context, dyn GetMessages>>
         Some(Arc::new(GetImapMessages::some_new_boxed))
     }
 
+    fn get_attachment(&self) -> Option<BackendFeature<Self::Context, dyn GetAttachment>> {
+        Some(Arc::new(GetImapAttachment::some_new_boxed))
+    }
+
     fn copy_messages(&self) -> Option<BackendFeature<Self::Context, dyn CopyMessages>> {
         Some(Arc::new(CopyImapMessages::some_new_boxed))
     }
@@ -1004,45 +2172,172 @@ fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMess
         Some(Arc::new(RemoveImapMessages::some_new_boxed))
     }
 
+    #[cfg(feature = "sync")]
+    fn set_sync_state_path(&mut self, path: PathBuf) {
+        self.set_uid_validity_path(path.with_extension("uidvalidity"));
+    }
+
     async fn build(self) -> AnyResult<Self::Context> {
-        let client_builder =
-            ImapClientBuilder::new(self.imap_config.clone(), self.prebuilt_credentials);
+        self.account_config.ensure_online()?;
 
-        debug!("building {} IMAP clients", self.pool_size);
+        let uid_validity = match &self.uid_validity_path {
+            Some(path) => UidValidityStore::load(path)
+                .map_err(|err| Error::LoadUidValidityError(err, path.clone()))?,
+            None => UidValidityStore::new(),
+        };
+        let uid_validity = Arc::new(StdMutex::new(uid_validity));
+
+        let client_builder = ImapClientBuilder::new(
+            self.imap_config.clone(),
+            self.prebuilt_credentials.map(Redacted::into_revealed),
+        )
+        .with_runtime(self.account_config.runtime.clone());
+
+        // every client holds a part of the account concurrency
+        // budget, and of the host one if any: the first one waits for
+        // them, the other ones are only built if the budgets allow it
+        // right away
+        let budget = self.account_config.concurrency_budget();
+        let runtime = &self.account_config.runtime;
+        let host_budget = self.imap_config.host_concurrency_budget(runtime);
+        let budget_timeout = self.imap_config.find_budget_timeout();
+        let permit = acquire_connection(&budget, host_budget.as_ref());
+        let permit = timeout(budget_timeout, permit)
+            .await
+            .map_err(|_| Error::AcquireConcurrencyBudgetTimeoutError(budget_timeout))?;
+        let mut permits = vec![permit];
+        permits.extend(
+            (1..self.pool_size)
+                .map_while(|_| try_acquire_connection(&budget, host_budget.as_ref())),
+        );
+
+        if permits.len() < self.pool_size as usize {
+            warn!(
+                limit = budget.limit(),
+                in_use = budget.in_use(),
+                host_limit = host_budget.as_ref().map(ConcurrencyBudget::limit),
+                host_in_use = host_budget.as_ref().map(ConcurrencyBudget::in_use),
+                "concurrency budget exhausted, building {} IMAP clients out of {}",
+                permits.len(),
+                self.pool_size,
+            );
+        }
 
-        let clients = FuturesUnordered::from_iter((0..self.pool_size).map(move |i| {
-            let mut client_builder = client_builder.clone();
-            tokio::spawn(async move {
-                let client = client_builder.build().await?;
-                Ok((i + 1, client_builder, client))
+        debug!("building {} IMAP clients", permits.len());
+
+        let alerts = ImapAlerts::new();
+        let clients_uid_validity = uid_validity.clone();
+        let clients_alerts = alerts.clone();
+        let clients_mailboxes = ImapMailboxCache::new();
+        let clients: Vec<Arc<Mutex<ImapClient>>> =
+            FuturesUnordered::from_iter(permits.into_iter().zip(1..).map(move |(permit, id)| {
+                let mut client_builder = client_builder.clone();
+                tokio::spawn(async move {
+                    let client = client_builder.build().await?;
+                    Ok((id, client_builder, client, permit))
+                })
+            }))
+            .map(|res| match res {
+                Err(err) => Err(Error::JoinClientError(err)),
+                Ok(Err(err)) => Err(Error::BuildClientError(Box::new(err))),
+                Ok(Ok((id, client_builder, inner, permit))) => {
+                    Ok(Arc::new(Mutex::new(ImapClient {
+                        id,
+                        account_config: self.account_config.clone(),
+                        imap_config: self.imap_config.clone(),
+                        client_builder,
+                        inner,
+                        mailbox: Default::default(),
+                        uid_validity: clients_uid_validity.clone(),
+                        alerts: clients_alerts.clone(),
+                        mailboxes: clients_mailboxes.clone(),
+                        retry: Default::default(),
+                        _permits: permit,
+                    })))
+                }
             })
-        }))
-        .map(|res| match res {
-            Err(err) => Err(Error::JoinClientError(err)),
-            Ok(Err(err)) => Err(Error::BuildClientError(Box::new(err))),
-            Ok(Ok((id, client_builder, inner))) => Ok(Arc::new(Mutex::new(ImapClient {
-                id,
-                account_config: self.account_config.clone(),
-                imap_config: self.imap_config.clone(),
-                client_builder,
-                inner,
-                mailbox: Default::default(),
-                retry: Default::default(),
-            }))),
-        })
-        .collect::<Vec<_>>()
-        .await
-        .into_iter()
-        .collect::<Result<_>>()?;
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<Result<_>>()?;
+
+        // all clients are connected to the same server, so the first
+        // one is enough to compute the level of support of features
+        let features = match clients.first() {
+            Some(client) => {
+                let client = client.lock().await;
+                BackendFeatureKind::all()
+                    .map(|feature| (feature, client.feature_support(feature)))
+                    .collect()
+            }
+            None => BackendFeatures::default(),
+        };
 
         Ok(ImapContext {
             account_config: self.account_config,
             imap_config: self.imap_config,
             clients,
+            uid_validity,
+            alerts,
+            features: Arc::new(features),
         })
     }
 }
 
+/// Return `true` if the given error comes from the server rejecting
+/// the credentials.
+///
+/// Servers reject credentials using a tagged `NO` response (RFC 3501
+/// section 6.2.2), usually with the `AUTHENTICATIONFAILED` response
+/// code (RFC 5530). The error is searched in the whole chain of
+/// sources, since the client wraps task errors.
+#[cfg(feature = "oauth2")]
+fn is_auth_failure(err: &ClientError) -> bool {
+    use imap_client::tasks::tasks::TaskError;
+
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(err);
+
+    while let Some(err) = source {
+        if let Some(TaskError::UnexpectedNoResponse(_)) = err.downcast_ref::<TaskError>() {
+            return true;
+        }
+
+        source = err.source();
+    }
+
+    false
+}
+
+/// Acquire a connection from the account budget, and from the host
+/// budget if any.
+async fn acquire_connection(
+    account: &ConcurrencyBudget,
+    host: Option<&ConcurrencyBudget>,
+) -> Vec<ConcurrencyPermit> {
+    let mut permits = vec![account.acquire(CONNECTION_WEIGHT).await];
+
+    if let Some(host) = host {
+        permits.push(host.acquire(CONNECTION_WEIGHT).await);
+    }
+
+    permits
+}
+
+/// Acquire a connection from the account budget, and from the host
+/// budget if any, only if both allow it right away.
+fn try_acquire_connection(
+    account: &ConcurrencyBudget,
+    host: Option<&ConcurrencyBudget>,
+) -> Option<Vec<ConcurrencyPermit>> {
+    let mut permits = vec![account.try_acquire(CONNECTION_WEIGHT)?];
+
+    if let Some(host) = host {
+        permits.push(host.try_acquire(CONNECTION_WEIGHT)?);
+    }
+
+    Some(permits)
+}
+
 #[derive(Clone, Debug)]
 pub struct CheckUpImap {
     ctx: ImapContext,
@@ -1067,14 +2362,47 @@ impl CheckUp for CheckUpImap {
     #[instrument(skip_all)]
     async fn check_up(&self) -> AnyResult<()> {
         debug!("executing check up backend feature");
-        Ok(self.ctx.client().await.noop().await?)
+        Ok(self.ctx.client().await?.noop().await?)
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct ImapClientBuilder {
     pub config: Arc<ImapConfig>,
     pub credentials: Option<String>,
+
+    /// The runtime state of the account, see [`AccountRuntime`].
+    pub runtime: AccountRuntime,
+
+    /// The SASL mechanism that succeeded during the last
+    /// authentication, for diagnostics purpose.
+    pub auth_mechanism: Option<SaslMechanism>,
+
+    /// The security level negotiated during the last connection, for
+    /// diagnostics purpose and downgrade protection.
+    pub security_level: Option<SecurityLevel>,
+
+    /// The capabilities enabled by the server during the last
+    /// connection, see [`ImapConfig::get_capabilities_to_enable`].
+    pub enabled_capabilities: Vec<ImapCapability>,
+
+    /// The information negotiated during the TLS handshake of the
+    /// last connection, for diagnostics purpose.
+    pub tls_info: Option<TlsConnectionInfo>,
+}
+
+impl fmt::Debug for ImapClientBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ImapClientBuilder")
+            .field("config", &self.config)
+            .field("credentials", &self.credentials.as_ref().map(Redacted::new))
+            .field("runtime", &self.runtime)
+            .field("auth_mechanism", &self.auth_mechanism)
+            .field("security_level", &self.security_level)
+            .field("enabled_capabilities", &self.enabled_capabilities)
+            .field("tls_info", &self.tls_info)
+            .finish()
+    }
 }
 
 impl ImapClientBuilder {
@@ -1082,87 +2410,255 @@ pub fn new(config: Arc<ImapConfig>, credentials: Option<String>) -> Self {
         Self {
             config,
             credentials,
+            runtime: Default::default(),
+            auth_mechanism: None,
+            security_level: None,
+            enabled_capabilities: Vec::new(),
+            tls_info: None,
         }
     }
 
-    /// Creates a new session from an IMAP configuration and optional
-    /// pre-built credentials.
+    /// Set the runtime state of the account, using the builder
+    /// pattern.
+    pub fn with_runtime(mut self, runtime: AccountRuntime) -> Self {
+        self.runtime = runtime;
+        self
+    }
+
+    /// Build a plaintext client.
+    async fn build_insecure_client(&self) -> Result<Client> {
+        Client::insecure(&self.config.host, self.config.port)
+            .await
+            .map_err(|err| {
+                let host = self.config.host.clone();
+                let port = self.config.port;
+                Error::BuildInsecureClientError(err, host, port)
+            })
+    }
+
+    /// Apply the STARTTLS policy to the result of a STARTTLS
+    /// connection.
     ///
-    /// Pre-built credentials are useful to prevent building them
-    /// every time a new session is created. The main use case is for
-    /// the synchronization, where multiple sessions can be created in
-    /// a row.
-    #[instrument(name = "client::build", skip(self))]
-    pub async fn build(&mut self) -> Result<Client> {
-        let mut client = match &self.config.encryption {
-            Some(Encryption::None) => Client::insecure(&self.config.host, self.config.port)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildInsecureClientError(err, host, port)
-                })?,
+    /// When the upgrade fails, the `require` policy aborts, while the
+    /// `opportunistic` policy falls back to plaintext unless a
+    /// previous connection succeeded to upgrade.
+    #[cfg_attr(not(any(feature = "rustls", feature = "native-tls")), allow(dead_code))]
+    async fn upgrade_starttls(
+        &self,
+        client: std::result::Result<Client, ClientError>,
+    ) -> Result<(Client, SecurityLevel)> {
+        let err = match client {
+            Ok(client) => return Ok((client, SecurityLevel::StartTls)),
+            Err(err) => err,
+        };
+
+        let host = self.config.host.clone();
+        let port = self.config.port;
+
+        match self.config.starttls_policy() {
+            StartTlsPolicy::Opportunistic
+                if matches!(self.security_level, None | Some(SecurityLevel::Plaintext)) =>
+            {
+                warn!(
+                    ?err,
+                    "cannot upgrade connection using STARTTLS, continuing in plaintext"
+                );
+                Ok((
+                    self.build_insecure_client().await?,
+                    SecurityLevel::Plaintext,
+                ))
+            }
+            StartTlsPolicy::Opportunistic => Err(Error::StartTlsDowngradeError(err, host, port)),
+            StartTlsPolicy::Require | StartTlsPolicy::Never => {
+                Err(Error::StartTlsRequiredError(err, host, port))
+            }
+        }
+    }
+
+    /// Connect to the IMAP server, using the configured encryption.
+    async fn connect(&self) -> Result<(Client, SecurityLevel)> {
+        // the IMAP client builds its own TLS configuration, only the
+        // pinned fingerprint can be checked once connected
+        if let Some(tls) = self.config.encryption.as_ref().and_then(Encryption::tls) {
+            let options = tls.find_custom_config_options();
+            if !options.is_empty() {
+                let options = options.join(", ");
+                return Err(Error::TlsOptionsNotSupportedError(options));
+            }
+        }
+
+        let conn = match &self.config.encryption {
+            Some(Encryption::None) => (
+                self.build_insecure_client().await?,
+                SecurityLevel::Plaintext,
+            ),
+            Some(Encryption::StartTls(_))
+                if self.config.starttls_policy() == StartTlsPolicy::Never =>
+            {
+                debug!("STARTTLS disabled by policy, staying in plaintext");
+                (
+                    self.build_insecure_client().await?,
+                    SecurityLevel::Plaintext,
+                )
+            }
             Some(Encryption::Tls(Tls {
                 provider: Some(TlsProvider::None),
+                ..
             }))
             | Some(Encryption::StartTls(Tls {
                 provider: Some(TlsProvider::None),
+                ..
             })) => {
                 return Err(Error::BuildTlsClientMissingProvider);
             }
             #[cfg(feature = "rustls")]
             Some(Encryption::Tls(Tls {
                 provider: Some(TlsProvider::Rustls(_)) | None,
+                ..
             }))
-            | None => Client::rustls(&self.config.host, self.config.port, false)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildStartTlsClientError(err, host, port)
-                })?,
+            | None => {
+                let client = Client::rustls(&self.config.host, self.config.port, false)
+                    .await
+                    .map_err(|err| {
+                        let host = self.config.host.clone();
+                        let port = self.config.port;
+                        Error::BuildStartTlsClientError(err, host, port)
+                    })?;
+                (client, SecurityLevel::Tls)
+            }
             #[cfg(feature = "native-tls")]
             Some(Encryption::Tls(Tls {
                 provider: Some(TlsProvider::NativeTls(_)),
-            })) => Client::native_tls(&self.config.host, self.config.port, false)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildStartTlsClientError(err, host, port)
-                })?,
+                ..
+            })) => {
+                let client = Client::native_tls(&self.config.host, self.config.port, false)
+                    .await
+                    .map_err(|err| {
+                        let host = self.config.host.clone();
+                        let port = self.config.port.clone();
+                        Error::BuildStartTlsClientError(err, host, port)
+                    })?;
+                (client, SecurityLevel::Tls)
+            }
             #[cfg(feature = "rustls")]
             Some(Encryption::StartTls(Tls {
                 provider: Some(TlsProvider::Rustls(_)) | None,
-            })) => Client::rustls(&self.config.host, self.config.port, true)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildStartTlsClientError(err, host, port)
-                })?,
+                ..
+            })) => {
+                let client = Client::rustls(&self.config.host, self.config.port, true).await;
+                self.upgrade_starttls(client).await?
+            }
             #[cfg(feature = "native-tls")]
             Some(Encryption::StartTls(Tls {
                 provider: Some(TlsProvider::NativeTls(_)),
-            })) => Client::native_tls(&self.config.host, self.config.port, true)
-                .await
-                .map_err(|err| {
-                    let host = self.config.host.clone();
-                    let port = self.config.port.clone();
-                    Error::BuildStartTlsClientError(err, host, port)
-                })?,
+                ..
+            })) => {
+                let client = Client::native_tls(&self.config.host, self.config.port, true).await;
+                self.upgrade_starttls(client).await?
+            }
+        };
+
+        Ok(conn)
+    }
+
+    /// Enable the configured capabilities.
+    ///
+    /// Failures are not fatal: the session simply continues without
+    /// the rejected capabilities. Returns the capabilities actually
+    /// enabled by the server.
+    async fn enable_capabilities(&self, client: &mut Client) -> Vec<ImapCapability> {
+        let capabilities = self.config.get_capabilities_to_enable();
+
+        if capabilities.is_empty() {
+            return Vec::new();
+        }
+
+        debug!(?capabilities, "enabling capabilities…");
+
+        let capabilities_enable = capabilities
+            .iter()
+            .filter_map(|cap| CapabilityEnable::try_from(cap.as_str()).ok());
+
+        match client.enable(capabilities_enable).await {
+            Ok(Some(enabled)) => {
+                let enabled: Vec<_> = capabilities
+                    .iter()
+                    .filter(|cap| {
+                        enabled
+                            .iter()
+                            .any(|enabled| enabled.to_string().eq_ignore_ascii_case(cap.as_str()))
+                    })
+                    .copied()
+                    .collect();
+                debug!(?enabled, "enabled capabilities");
+                enabled
+            }
+            Ok(None) => {
+                warn!("ENABLE extension not supported, skipping capabilities");
+                Vec::new()
+            }
+            Err(err) => {
+                let err = Error::EnableCapabilityError(err);
+                warn!(?err, "cannot enable capabilities, skipping them");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Creates a new session from an IMAP configuration and optional
+    /// pre-built credentials.
+    ///
+    /// Pre-built credentials are useful to prevent building them
+    /// every time a new session is created. The main use case is for
+    /// the synchronization, where multiple sessions can be created in
+    /// a row.
+    #[instrument(name = "client::build", skip(self))]
+    pub async fn build(&mut self) -> Result<Client> {
+        let (client, security_level) = match self.connect().await {
+            Ok(conn) => conn,
+            Err(
+                err
+                @ (Error::BuildTlsClientMissingProvider | Error::TlsOptionsNotSupportedError(_)),
+            ) => return Err(err),
+            Err(err) => {
+                let host = &self.config.host;
+                let port = self.config.port;
+                let level = self.config.expected_security_level();
+                let tls = self.config.find_tls();
+                let diagnostics =
+                    ConnectionDiagnostics::probe_with_tls(host, port, level, &tls).await;
+                return Err(Error::ConnectImapServerError(Box::new(err), diagnostics));
+            }
         };
 
+        debug!(%security_level, "connected to IMAP server");
+        self.security_level = Some(security_level);
+
+        let (mut client, tls_info) = with_tls_info(client);
+
+        if let (Some(tls), Some(info)) = (
+            self.config.encryption.as_ref().and_then(Encryption::tls),
+            &tls_info,
+        ) {
+            tls.verify_cert_fingerprint(info).map_err(|err| {
+                let host = self.config.host.clone();
+                let port = self.config.port;
+                Error::VerifyCertificateError(err, host, port)
+            })?;
+        }
+
+        self.tls_info = tls_info;
+
         client
             .state
-            .set_some_idle_timeout(self.config.find_watch_timeout().map(Duration::from_secs));
+            .set_some_idle_timeout(self.config.find_idle_timeout());
 
         match &self.config.auth {
             ImapAuthConfig::Password(passwd) => {
                 debug!("using password authentication");
 
                 let passwd = match self.credentials.as_ref() {
-                    Some(passwd) => passwd.to_string(),
+                    Some(passwd) => passwd.to_owned(),
                     None => passwd
                         .get()
                         .await
@@ -1205,6 +2701,7 @@ pub async fn build(&mut self) -> Result<Client> {
 
                     if auth.is_ok() {
                         debug!(?mechanism, "authentication succeeded!");
+                        self.auth_mechanism = Some(SaslMechanism::Plain);
                         authenticated = true;
                         break;
                     }
@@ -1223,126 +2720,509 @@ pub async fn build(&mut self) -> Result<Client> {
                         .map_err(Error::LoginError)?;
 
                     debug!("login succeeded!");
+                    self.auth_mechanism = Some(SaslMechanism::Login);
                 }
             }
             #[cfg(feature = "oauth2")]
             ImapAuthConfig::OAuth2(oauth2) => {
                 debug!("using OAuth 2.0 authentication");
 
-                match oauth2.method {
-                    OAuth2Method::XOAuth2 => {
-                        if !client.state.supports_auth_mechanism(AuthMechanism::XOAuth2) {
-                            let auth = client.state.supported_auth_mechanisms().cloned().collect();
-                            return Err(Error::AuthenticateXOAuth2NotSupportedError(auth));
+                let mechanisms: Vec<_> =
+                    client.state.supported_auth_mechanisms().cloned().collect();
+                let login = self.config.login.as_str();
+                let host = self.config.host.as_str();
+                let port = self.config.port;
+
+                let auth = sasl::authenticate_oauth2(
+                    &self.runtime,
+                    oauth2,
+                    login,
+                    self.credentials.clone(),
+                    |mechanism| match mechanism {
+                        SaslMechanism::XOAuth2 => mechanisms.contains(&AuthMechanism::XOAuth2),
+                        SaslMechanism::OAuthBearer => {
+                            mechanisms.contains(&AuthMechanism::OAuthBearer)
                         }
+                        _ => false,
+                    },
+                    is_auth_failure,
+                    client,
+                    |mut client, mechanism, access_token| async move {
+                        let auth = match mechanism {
+                            SaslMechanism::XOAuth2 => {
+                                client
+                                    .authenticate_xoauth2(login, access_token.as_str())
+                                    .await
+                            }
+                            _ => {
+                                client
+                                    .authenticate_oauthbearer(
+                                        login,
+                                        host,
+                                        port,
+                                        access_token.as_str(),
+                                    )
+                                    .await
+                            }
+                        };
 
-                        debug!("using XOAUTH2 auth mechanism");
+                        (client, auth)
+                    },
+                )
+                .await
+                .map_err(Error::AuthenticateOAuth2Error)?;
 
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
+                client = auth.context;
+                self.credentials = Some(auth.access_token);
+                self.auth_mechanism = Some(auth.mechanism);
+            }
+        };
 
-                        let auth = client
-                            .authenticate_xoauth2(self.config.login.as_str(), access_token.as_str())
-                            .await;
+        if self.config.send_id_after_auth() {
+            let params = ID_PARAMS.clone();
+            debug!(?params, "client identity");
 
-                        if auth.is_err() {
-                            warn!("authentication failed, refreshing access token and retrying…");
+            let params = client
+                .id(Some(ID_PARAMS.clone()))
+                .await
+                .map_err(Error::ExchangeIdsError)?;
 
-                            let access_token = oauth2
-                                .refresh_access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?;
+            debug!(?params, "server identity");
+        }
 
-                            client
-                                .authenticate_xoauth2(
-                                    self.config.login.as_str(),
-                                    access_token.as_str(),
-                                )
-                                .await
-                                .map_err(Error::AuthenticateXOauth2Error)?;
+        self.enabled_capabilities = self.enable_capabilities(&mut client).await;
 
-                            self.credentials = Some(access_token);
-                        }
-                    }
-                    OAuth2Method::OAuthBearer => {
-                        if !client
-                            .state
-                            .supports_auth_mechanism("OAUTHBEARER".try_into().unwrap())
-                        {
-                            let auth = client.state.supported_auth_mechanisms().cloned().collect();
-                            return Err(Error::AuthenticateOAuthBearerNotSupportedError(auth));
-                        }
+        Ok(client)
+    }
+}
 
-                        debug!("using OAUTHBEARER auth mechanism");
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashMap,
+        io,
+        num::NonZeroU32,
+        sync::{Arc, Mutex as StdMutex},
+    };
+
+    use chrono::{FixedOffset, TimeZone};
+    use imap_client::{
+        client::tokio::{Client, ClientError},
+        imap_next::imap_types::{flag::Flag, sequence::SequenceSet},
+    };
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+        sync::oneshot,
+    };
+
+    use super::{
+        config::ImapConfig,
+        notify::{ImapNotification, ImapNotifyEvent, ImapNotifyMailboxes, ImapNotifySet},
+        Error, ImapAlerts, ImapClient, ImapClientBuilder, ImapMailboxCache,
+    };
+    use crate::{
+        account::{budget::CONNECTION_WEIGHT, runtime::AccountRuntime},
+        folder::acl::{AclEntry, AclRights, AclRightsModification},
+        tls::{Encryption, SecurityLevel, StartTlsPolicy},
+    };
+
+    /// Spawn a plaintext IMAP server greeting every client, and
+    /// return its port.
+    async fn spawn_server() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                tokio::spawn(async move {
+                    let greeting = b"* OK [CAPABILITY IMAP4rev1] ready\r\n";
+                    stream.write_all(greeting).await.unwrap();
+                    // keep the connection open until the client
+                    // drops it
+                    let _ = stream.readable().await;
+                });
+            }
+        });
 
-                        let access_token = match self.credentials.as_ref() {
-                            Some(access_token) => access_token.to_string(),
-                            None => oauth2
-                                .access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?,
-                        };
+        port
+    }
 
-                        let auth = client
-                            .authenticate_oauthbearer(
-                                self.config.login.as_str(),
-                                self.config.host.as_str(),
-                                self.config.port,
-                                access_token.as_str(),
-                            )
-                            .await;
+    /// Spawn a plaintext IMAP server advertising the given
+    /// capabilities, and return its port together with the commands
+    /// it receives, without their tag.
+    ///
+    /// The server answers the nth command with the nth lines of the
+    /// given script, where `{tag}` is replaced by the tag of the
+    /// command. The `DONE` continuation of IDLE counts as a command
+    /// tagged like the IDLE command.
+    async fn spawn_scripted_server(
+        capabilities: &'static str,
+        script: Vec<Vec<&'static str>>,
+    ) -> (u16, Arc<StdMutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let commands = Arc::new(StdMutex::new(Vec::new()));
+        let received = commands.clone();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut stream = BufReader::new(stream);
+            let greeting = format!("* OK [CAPABILITY IMAP4rev1 {capabilities}] ready\r\n");
+            stream.write_all(greeting.as_bytes()).await.unwrap();
+
+            let mut tag = String::new();
+
+            for lines in script {
+                let mut command = Vec::new();
+
+                loop {
+                    if stream.read_until(b'\n', &mut command).await.unwrap() == 0 {
+                        return;
+                    }
 
-                        if auth.is_err() {
-                            warn!("authentication failed, refreshing access token and retrying");
+                    let line = String::from_utf8_lossy(&command).into_owned();
+                    let Some(len) = line
+                        .strip_suffix("}\r\n")
+                        .and_then(|line| line.rsplit_once('{'))
+                        .and_then(|(_, len)| len.parse::<usize>().ok())
+                    else {
+                        break;
+                    };
 
-                            let access_token = oauth2
-                                .refresh_access_token()
-                                .await
-                                .map_err(Error::RefreshAccessTokenError)?;
+                    stream.write_all(b"+ go ahead\r\n").await.unwrap();
+                    let mut literal = vec![0; len];
+                    stream.read_exact(&mut literal).await.unwrap();
+                    command.extend(literal);
+                }
 
-                            client
-                                .authenticate_oauthbearer(
-                                    self.config.login.as_str(),
-                                    self.config.host.as_str(),
-                                    self.config.port,
-                                    access_token.as_str(),
-                                )
-                                .await
-                                .map_err(Error::AuthenticateOAuthBearerError)?;
+                let command = String::from_utf8(command).unwrap();
+                let command = command.trim_end();
 
-                            self.credentials = Some(access_token);
-                        }
+                let command = match command.split_once(' ') {
+                    Some((t, command)) if command != "DONE" => {
+                        tag = t.to_owned();
+                        command
                     }
+                    _ => command,
+                };
+
+                received.lock().unwrap().push(command.to_owned());
+
+                for line in lines {
+                    let line = line.replace("{tag}", &tag) + "\r\n";
+                    stream.write_all(line.as_bytes()).await.unwrap();
                 }
             }
+
+            // keep the connection open until the client drops it
+            let _ = stream.read(&mut [0; 1]).await;
+        });
+
+        (port, commands)
+    }
+
+    async fn client(port: u16) -> ImapClient {
+        let config = Arc::new(ImapConfig {
+            host: "127.0.0.1".into(),
+            port,
+            ..Default::default()
+        });
+
+        ImapClient {
+            id: 1,
+            account_config: Default::default(),
+            imap_config: config.clone(),
+            client_builder: ImapClientBuilder::new(config, None),
+            inner: Client::insecure("127.0.0.1", port).await.unwrap(),
+            mailbox: None,
+            uid_validity: Default::default(),
+            alerts: ImapAlerts::new(),
+            mailboxes: ImapMailboxCache::new(),
+            retry: Default::default(),
+            _permits: Vec::new(),
+        }
+    }
+
+    fn uid(n: u32) -> NonZeroU32 {
+        NonZeroU32::new(n).unwrap()
+    }
+
+    fn builder(port: u16, policy: StartTlsPolicy) -> ImapClientBuilder {
+        let config = ImapConfig {
+            host: "127.0.0.1".into(),
+            port,
+            starttls_policy: Some(policy),
+            ..Default::default()
         };
 
-        if self.config.send_id_after_auth() {
-            let params = ID_PARAMS.clone();
-            debug!(?params, "client identity");
+        ImapClientBuilder::new(Arc::new(config), None)
+    }
 
-            let params = client
-                .id(Some(ID_PARAMS.clone()))
-                .await
-                .map_err(Error::ExchangeIdsError)?;
+    fn upgrade_error() -> Result<imap_client::client::tokio::Client, ClientError> {
+        let err = io::Error::new(io::ErrorKind::InvalidData, "no STARTTLS");
+        Err(ClientError::ConnectToTlsStreamError(err))
+    }
 
-            debug!(?params, "server identity");
-        }
+    #[tokio::test]
+    async fn starttls_required() {
+        let builder = builder(spawn_server().await, StartTlsPolicy::Require);
+        let res = builder.upgrade_starttls(upgrade_error()).await;
+        assert!(matches!(res, Err(Error::StartTlsRequiredError(..))));
+    }
 
-        // TODO: make it customizable
-        //
-        // debug!("enabling UTF8 capability…");
-        //
-        // client
-        //     .enable(Some(CapabilityEnable::Utf8(Utf8Kind::Accept)))
-        //     .await
-        //     .map_err(Error::EnableCapabilityError)?;
+    #[tokio::test]
+    async fn starttls_opportunistic() {
+        let mut builder = builder(spawn_server().await, StartTlsPolicy::Opportunistic);
 
-        Ok(client)
+        // without any previous upgrade, plaintext is accepted
+        let (_, level) = builder.upgrade_starttls(upgrade_error()).await.unwrap();
+        assert_eq!(level, SecurityLevel::Plaintext);
+
+        // once upgraded, falling back to plaintext is a downgrade
+        builder.security_level = Some(SecurityLevel::StartTls);
+        let res = builder.upgrade_starttls(upgrade_error()).await;
+        assert!(matches!(res, Err(Error::StartTlsDowngradeError(..))));
+    }
+
+    #[tokio::test]
+    async fn starttls_never() {
+        let mut builder = builder(spawn_server().await, StartTlsPolicy::Never);
+        Arc::make_mut(&mut builder.config).encryption =
+            Some(Encryption::StartTls(Default::default()));
+
+        let (_, level) = builder.connect().await.unwrap();
+        assert_eq!(level, SecurityLevel::Plaintext);
+    }
+
+    #[tokio::test]
+    async fn connections_capped_per_host() {
+        let runtime = AccountRuntime::new();
+        let config = ImapConfig {
+            host: "localhost".into(),
+            max_connections_per_host: Some(CONNECTION_WEIGHT),
+            ..Default::default()
+        };
+
+        let budget = runtime.concurrency_budget("account", CONNECTION_WEIGHT * 3);
+        let host_budget = config.host_concurrency_budget(&runtime);
+        let host_budget = host_budget.as_ref();
+
+        let permits = super::acquire_connection(&budget, host_budget).await;
+        assert_eq!(permits.len(), 2);
+
+        // the host budget is exhausted: the account budget is not
+        // held by the failed attempt
+        assert!(super::try_acquire_connection(&budget, host_budget).is_none());
+        assert_eq!(budget.in_use(), CONNECTION_WEIGHT);
+
+        // the host budget is shared with other accounts
+        let other = runtime.concurrency_budget("other", CONNECTION_WEIGHT);
+        assert!(super::try_acquire_connection(&other, host_budget).is_none());
+
+        drop(permits);
+        assert!(super::try_acquire_connection(&other, host_budget).is_some());
+    }
+
+    #[tokio::test]
+    async fn notify() {
+        let (port, commands) = spawn_scripted_server(
+            "NOTIFY",
+            vec![
+                vec![
+                    "{tag} OK notifying",
+                    "* 3 EXISTS",
+                    "* STATUS Drafts (MESSAGES 1)",
+                ],
+                vec!["{tag} OK done"],
+            ],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        let (_shutdown, mut wait_for_shutdown) = oneshot::channel();
+        let set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES)
+            .with_group(
+                ImapNotifyMailboxes::Mailboxes(vec!["Drafts".into()]),
+                ImapNotifyEvent::ENVELOPES,
+            );
+
+        let notifications = client.notify(&set, &mut wait_for_shutdown).await.unwrap();
+
+        assert_eq!(
+            notifications,
+            vec![
+                ImapNotification::Selected,
+                ImapNotification::Mailbox("Drafts".into()),
+            ]
+        );
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec![format!("NOTIFY {set}"), "NOTIFY NONE".to_owned()]
+        );
+    }
+
+    #[tokio::test]
+    async fn notify_interrupted() {
+        let (port, commands) = spawn_scripted_server(
+            "NOTIFY",
+            vec![vec!["{tag} OK notifying"], vec!["{tag} OK done"]],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        let (shutdown, mut wait_for_shutdown) = oneshot::channel();
+        let set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES);
+
+        shutdown.send(()).unwrap();
+        let res = client.notify(&set, &mut wait_for_shutdown).await;
+
+        assert!(matches!(res, Err(Error::NotifyInterruptedError)));
+        assert_eq!(commands.lock().unwrap().last().unwrap(), "NOTIFY NONE");
+    }
+
+    #[tokio::test]
+    async fn notify_falls_back_to_idle() {
+        let (port, commands) = spawn_scripted_server(
+            "IDLE",
+            vec![vec!["+ idling", "* 3 EXISTS"], vec!["{tag} OK done"]],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        let (_shutdown, mut wait_for_shutdown) = oneshot::channel();
+        let set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES);
+
+        let notifications = client.notify(&set, &mut wait_for_shutdown).await.unwrap();
+
+        assert!(notifications.is_empty());
+        assert_eq!(*commands.lock().unwrap(), vec!["IDLE", "DONE"]);
+    }
+
+    #[tokio::test]
+    async fn add_messages_using_multiappend() {
+        let (port, commands) = spawn_scripted_server(
+            "UIDPLUS MULTIAPPEND",
+            vec![vec!["{tag} OK [APPENDUID 1 4:5] done"]],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        let msgs = vec![
+            (vec![Flag::Seen], b"first".to_vec()),
+            (vec![], b"second".to_vec()),
+        ];
+
+        let uids = client.add_messages("Sent", msgs).await.unwrap();
+
+        assert_eq!(uids, vec![uid(4), uid(5)]);
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec!["APPEND \"Sent\" (\\Seen) {5}\r\nfirst () {6}\r\nsecond"]
+        );
+    }
+
+    #[tokio::test]
+    async fn add_messages_rejected_by_multiappend() {
+        let (port, _) = spawn_scripted_server(
+            "UIDPLUS MULTIAPPEND",
+            vec![vec!["{tag} NO [TRYCREATE] no such mailbox"]],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        let msgs = vec![(vec![], b"first".to_vec()), (vec![], b"second".to_vec())];
+
+        let res = client.add_messages("Sent", msgs).await;
+        assert!(matches!(
+            res,
+            Err(Error::RejectRawCommandError("APPEND", _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn manage_acl() {
+        let (port, commands) = spawn_scripted_server(
+            "ACL",
+            vec![
+                vec![
+                    "* ACL Shared alice lrswipkxtea \"-bob\" lr",
+                    "* ACL Other carol lr",
+                    "{tag} OK done",
+                ],
+                vec!["{tag} OK done"],
+                vec!["{tag} OK done"],
+                vec!["* MYRIGHTS Shared lrs", "{tag} OK done"],
+            ],
+        )
+        .await;
+
+        let mut client = client(port).await;
+
+        let acl = client.get_acl("Shared").await.unwrap();
+        assert_eq!(
+            acl,
+            vec![
+                AclEntry {
+                    identifier: "alice".into(),
+                    rights: AclRights::from("lrswipkxtea"),
+                },
+                AclEntry {
+                    identifier: "-bob".into(),
+                    rights: AclRights::from("lr"),
+                },
+            ]
+        );
+
+        client
+            .set_acl("Shared", "bob", &AclRightsModification::from("+w"))
+            .await
+            .unwrap();
+        client.delete_acl("Shared", "alice").await.unwrap();
+
+        let rights = client.my_rights("Shared").await.unwrap();
+        assert_eq!(rights, AclRights::from("lrs"));
+
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec![
+                "GETACL \"Shared\"",
+                "SETACL \"Shared\" \"bob\" \"+w\"",
+                "DELETEACL \"Shared\" \"alice\"",
+                "MYRIGHTS \"Shared\"",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_save_dates() {
+        let (port, commands) = spawn_scripted_server(
+            "SAVEDATE",
+            vec![vec![
+                "* 1 FETCH (UID 3 SAVEDATE \"17-Oct-2026 10:00:00 +0200\")",
+                "* 2 FETCH (UID 5 SAVEDATE NIL)",
+                "{tag} OK done",
+            ]],
+        )
+        .await;
+
+        let mut client = client(port).await;
+        assert!(client.ext_savedate_supported());
+
+        let uids = SequenceSet::try_from(vec![uid(3), uid(4), uid(5)]).unwrap();
+        let save_dates = client.fetch_save_dates(uids).await.unwrap();
+
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let date = offset.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap();
+        assert_eq!(save_dates, HashMap::from_iter([(uid(3), date)]));
+
+        assert_eq!(
+            *commands.lock().unwrap(),
+            vec!["UID FETCH 3:5 (UID SAVEDATE)"]
+        );
     }
 }
@@ -0,0 +1,259 @@
+//! # IMAP NOTIFY
+//!
+//! Module dedicated to the IMAP NOTIFY extension (RFC 5465), which
+//! allows a single connection to be notified about changes happening
+//! in multiple mailboxes, instead of opening one IDLE connection per
+//! mailbox. It contains the typed events [`ImapNotifyEvent`] and
+//! mailbox specifiers [`ImapNotifyMailboxes`], rendered as the
+//! arguments of a `NOTIFY SET` command by [`ImapNotifySet`], as well
+//! as the notifications sent back by the server
+//! [`ImapNotification`].
+//!
+//! The IMAP codec cannot encode `NOTIFY` commands yet, so they are
+//! sent as raw commands, see [`super::ImapClient::notify`].
+
+use std::fmt;
+
+use utf7_imap::{decode_utf7_imap as decode_utf7, encode_utf7_imap as encode_utf7};
+
+use super::raw::{quoted, tokenize, RawToken};
+
+/// The IMAP NOTIFY event.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ImapNotifyEvent {
+    /// A message has been added to a mailbox.
+    MessageNew,
+
+    /// A message has been expunged from a mailbox.
+    MessageExpunge,
+
+    /// The flags of a message changed.
+    FlagChange,
+
+    /// The annotations of a message changed.
+    AnnotationChange,
+
+    /// A mailbox has been created, deleted or renamed.
+    MailboxName,
+
+    /// The subscription of a mailbox changed.
+    SubscriptionChange,
+}
+
+impl ImapNotifyEvent {
+    /// The events needed to keep a list of envelopes up to date.
+    pub const ENVELOPES: [ImapNotifyEvent; 3] = [
+        ImapNotifyEvent::MessageNew,
+        ImapNotifyEvent::MessageExpunge,
+        ImapNotifyEvent::FlagChange,
+    ];
+}
+
+impl fmt::Display for ImapNotifyEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MessageNew => write!(f, "MessageNew"),
+            Self::MessageExpunge => write!(f, "MessageExpunge"),
+            Self::FlagChange => write!(f, "FlagChange"),
+            Self::AnnotationChange => write!(f, "AnnotationChange"),
+            Self::MailboxName => write!(f, "MailboxName"),
+            Self::SubscriptionChange => write!(f, "SubscriptionChange"),
+        }
+    }
+}
+
+/// The IMAP NOTIFY mailboxes specifier.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum ImapNotifyMailboxes {
+    /// The currently selected mailbox.
+    Selected,
+
+    /// All the mailboxes receiving messages (at least `INBOX`).
+    Inboxes,
+
+    /// All the mailboxes belonging to the user.
+    Personal,
+
+    /// All the mailboxes the user is subscribed to.
+    Subscribed,
+
+    /// The given mailboxes and all their children.
+    Subtree(Vec<String>),
+
+    /// The given mailboxes.
+    Mailboxes(Vec<String>),
+}
+
+impl fmt::Display for ImapNotifyMailboxes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_mboxes = |f: &mut fmt::Formatter<'_>, mboxes: &[String]| {
+            let mboxes: Vec<_> = mboxes
+                .iter()
+                .map(|mbox| quoted(&encode_utf7(mbox.clone())))
+                .collect();
+            write!(f, "({})", mboxes.join(" "))
+        };
+
+        match self {
+            Self::Selected => write!(f, "SELECTED"),
+            Self::Inboxes => write!(f, "INBOXES"),
+            Self::Personal => write!(f, "PERSONAL"),
+            Self::Subscribed => write!(f, "SUBSCRIBED"),
+            Self::Subtree(mboxes) => {
+                write!(f, "SUBTREE ")?;
+                write_mboxes(f, mboxes)
+            }
+            Self::Mailboxes(mboxes) => {
+                write!(f, "MAILBOXES ")?;
+                write_mboxes(f, mboxes)
+            }
+        }
+    }
+}
+
+/// The arguments of a `NOTIFY SET` command, made of event groups
+/// associating mailboxes with the events to be notified about.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ImapNotifySet(Vec<(ImapNotifyMailboxes, Vec<ImapNotifyEvent>)>);
+
+impl ImapNotifySet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an event group.
+    pub fn add_group(
+        &mut self,
+        mailboxes: ImapNotifyMailboxes,
+        events: impl IntoIterator<Item = ImapNotifyEvent>,
+    ) {
+        self.0.push((mailboxes, events.into_iter().collect()));
+    }
+
+    /// Add an event group, using the builder pattern.
+    pub fn with_group(
+        mut self,
+        mailboxes: ImapNotifyMailboxes,
+        events: impl IntoIterator<Item = ImapNotifyEvent>,
+    ) -> Self {
+        self.add_group(mailboxes, events);
+        self
+    }
+
+    /// Return `true` if the set only watches the selected mailbox,
+    /// which can be achieved using IDLE.
+    pub fn is_selected_only(&self) -> bool {
+        self.0
+            .iter()
+            .all(|(mboxes, _)| *mboxes == ImapNotifyMailboxes::Selected)
+    }
+}
+
+impl fmt::Display for ImapNotifySet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SET")?;
+
+        for (mboxes, events) in &self.0 {
+            if events.is_empty() {
+                write!(f, " ({mboxes} NONE)")?;
+            } else {
+                let events: Vec<_> = events.iter().map(ToString::to_string).collect();
+                write!(f, " ({mboxes} ({}))", events.join(" "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The notification sent by the server once NOTIFY is set.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ImapNotification {
+    /// Messages of the selected mailbox changed.
+    Selected,
+
+    /// Messages of the given mailbox, which is not the selected one,
+    /// changed.
+    Mailbox(String),
+
+    /// The server stopped sending notifications, because too many
+    /// changes happened: all mailboxes should be checked.
+    Overflow,
+}
+
+impl ImapNotification {
+    /// Parse the notification from the given raw untagged response.
+    ///
+    /// Returns `None` for responses not notifying a change, like
+    /// untagged `OK` responses.
+    pub(crate) fn parse(response: &[u8]) -> Option<Self> {
+        let tokens = tokenize(response)?;
+
+        match tokens.as_slice() {
+            [RawToken::Atom(n), kind, ..]
+                if n.parse::<u32>().is_ok()
+                    && (kind.is_atom("EXISTS")
+                        || kind.is_atom("EXPUNGE")
+                        || kind.is_atom("FETCH")) =>
+            {
+                Some(Self::Selected)
+            }
+            [kind, ..] if kind.is_atom("VANISHED") => Some(Self::Selected),
+            [kind, mbox, ..] if kind.is_atom("STATUS") => {
+                Some(Self::Mailbox(decode_utf7(mbox.as_astring()?)))
+            }
+            [kind, code, ..] if kind.is_atom("OK") && code.is_atom("[NOTIFICATIONOVERFLOW]") => {
+                Some(Self::Overflow)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ImapNotification, ImapNotifyEvent, ImapNotifyMailboxes, ImapNotifySet};
+
+    #[test]
+    fn notify_set() {
+        let set = ImapNotifySet::new()
+            .with_group(ImapNotifyMailboxes::Selected, ImapNotifyEvent::ENVELOPES)
+            .with_group(
+                ImapNotifyMailboxes::Mailboxes(vec!["INBOX".into(), "Été".into()]),
+                [ImapNotifyEvent::MessageNew],
+            )
+            .with_group(ImapNotifyMailboxes::Personal, []);
+
+        assert!(!set.is_selected_only());
+        assert_eq!(
+            set.to_string(),
+            concat!(
+                "SET (SELECTED (MessageNew MessageExpunge FlagChange))",
+                " (MAILBOXES (\"INBOX\" \"&AMk-t&AOk-\") (MessageNew))",
+                " (PERSONAL NONE)",
+            )
+        );
+    }
+
+    #[test]
+    fn parse_notifications() {
+        assert_eq!(
+            ImapNotification::parse(b"12 EXISTS"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            ImapNotification::parse(b"3 FETCH (UID 9 FLAGS (\\Seen))"),
+            Some(ImapNotification::Selected)
+        );
+        assert_eq!(
+            ImapNotification::parse(b"STATUS \"&AMk-t&AOk-\" (MESSAGES 2 UIDNEXT 5)"),
+            Some(ImapNotification::Mailbox("Été".into()))
+        );
+        assert_eq!(
+            ImapNotification::parse(b"OK [NOTIFICATIONOVERFLOW] too many changes"),
+            Some(ImapNotification::Overflow)
+        );
+        assert_eq!(ImapNotification::parse(b"OK still here"), None);
+        assert_eq!(ImapNotification::parse(b"2 RECENT"), None);
+    }
+}
@@ -0,0 +1,134 @@
+//! # IMAP QUOTA
+//!
+//! Module dedicated to the IMAP QUOTA extension (RFC 2087, RFC
+//! 9208). It contains the GETQUOTAROOT task, collecting the quotas
+//! applying to a mailbox.
+
+use imap_client::{
+    imap_next::imap_types::{
+        command::CommandBody,
+        extensions::quota::{QuotaGet, Resource},
+        mailbox::Mailbox,
+        response::{Data, StatusBody, StatusKind},
+    },
+    tasks::{tasks::TaskError, Task},
+};
+use tracing::debug;
+
+use crate::folder::quota::{Quota, QuotaUsage};
+
+/// The GETQUOTAROOT task, collecting the untagged QUOTA responses
+/// of the quota roots applying to the given mailbox.
+#[derive(Clone, Debug)]
+pub struct GetQuotaRootTask {
+    mailbox: Mailbox<'static>,
+    quota: Quota,
+}
+
+impl GetQuotaRootTask {
+    pub fn new(mailbox: Mailbox<'static>) -> Self {
+        Self {
+            mailbox,
+            quota: Quota::default(),
+        }
+    }
+
+    fn collect_quota(&mut self, quota: &QuotaGet<'_>) {
+        match quota.resource {
+            // storage is expressed in units of 1024 octets
+            Resource::Storage => self.quota.merge_storage(QuotaUsage::new(
+                quota.usage.saturating_mul(1024),
+                quota.limit.saturating_mul(1024),
+            )),
+            Resource::Message => self
+                .quota
+                .merge_messages(QuotaUsage::new(quota.usage, quota.limit)),
+            _ => debug!(resource = ?quota.resource, "skipping unsupported quota resource"),
+        }
+    }
+}
+
+impl Task for GetQuotaRootTask {
+    type Output = Result<Quota, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        CommandBody::GetQuotaRoot {
+            mailbox: self.mailbox.clone(),
+        }
+    }
+
+    fn process_data(&mut self, data: Data<'static>) -> Option<Data<'static>> {
+        match data {
+            Data::QuotaRoot { roots, .. } => {
+                debug!(count = roots.len(), "received QUOTAROOT response");
+                None
+            }
+            Data::Quota { quotas, .. } => {
+                for quota in quotas.as_ref() {
+                    self.collect_quota(quota);
+                }
+                None
+            }
+            data => Some(data),
+        }
+    }
+
+    fn process_tagged(self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => Ok(self.quota),
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::{
+        imap_next::imap_types::{
+            core::Vec1,
+            extensions::quota::{QuotaGet, Resource},
+            mailbox::Mailbox,
+            response::Data,
+        },
+        tasks::Task,
+    };
+
+    use super::GetQuotaRootTask;
+    use crate::folder::quota::QuotaUsage;
+
+    #[test]
+    fn collect_quotas() {
+        let mut task = GetQuotaRootTask::new(Mailbox::Inbox);
+
+        let quotas = Vec1::try_from(vec![
+            QuotaGet::new(Resource::Storage, 512, 1024),
+            QuotaGet::new(Resource::Mailbox, 1, 10),
+        ])
+        .unwrap();
+        let data = Data::Quota {
+            root: "user".try_into().unwrap(),
+            quotas,
+        };
+        assert!(task.process_data(data).is_none());
+
+        let quotas = Vec1::try_from(vec![
+            QuotaGet::new(Resource::Storage, 100, 1000),
+            QuotaGet::new(Resource::Message, 42, 1000),
+        ])
+        .unwrap();
+        let data = Data::Quota {
+            root: "partition".try_into().unwrap(),
+            quotas,
+        };
+        assert!(task.process_data(data).is_none());
+
+        assert_eq!(
+            task.quota.storage,
+            Some(QuotaUsage::new(512 * 1024, 1024 * 1024))
+        );
+        assert_eq!(task.quota.messages, Some(QuotaUsage::new(42, 1000)));
+        assert!(task.quota.is_above(0.5));
+        assert!(!task.quota.is_above(0.6));
+    }
+}
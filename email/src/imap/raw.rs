@@ -0,0 +1,548 @@
+//! # IMAP raw commands
+//!
+//! Module dedicated to the IMAP commands the IMAP codec cannot encode
+//! yet, like the ones of the NOTIFY, ACL and MULTIAPPEND extensions.
+//! Such commands are written by hand as [`RawCommand`]s, then sent
+//! over the stream of the client by the [`RawState`], which collects
+//! their responses as raw bytes instead of decoding them. Untagged
+//! responses are parsed by the modules of the extensions, see
+//! [`tokenize`].
+
+use std::{collections::VecDeque, convert::Infallible, fmt};
+
+use imap_client::imap_next::{imap_types::core::TagGenerator, Interrupt, Io, State};
+use tracing::debug;
+
+/// The IMAP command written by hand.
+///
+/// The command is made of fragments separated by synchronizing
+/// literals: a fragment is only sent once the server accepted the
+/// literal announced at the end of the previous one.
+#[derive(Clone, Debug)]
+pub(crate) struct RawCommand {
+    name: &'static str,
+    fragments: Vec<Vec<u8>>,
+}
+
+impl RawCommand {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            fragments: vec![name.as_bytes().to_vec()],
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn fragment(&mut self) -> &mut Vec<u8> {
+        // the command always has at least one fragment
+        self.fragments.last_mut().unwrap()
+    }
+
+    /// Append the given argument as it is, preceded by a space.
+    pub fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        let fragment = self.fragment();
+        fragment.push(b' ');
+        fragment.extend_from_slice(arg.as_ref());
+        self
+    }
+
+    /// Append the given string as a quoted string, or as a literal
+    /// when it cannot be quoted.
+    pub fn string(self, string: impl AsRef<[u8]>) -> Self {
+        let string = string.as_ref();
+
+        if is_quotable(string) {
+            self.arg(quote(string))
+        } else {
+            self.literal(string)
+        }
+    }
+
+    /// Append the given bytes as a synchronizing literal.
+    pub fn literal(mut self, bytes: impl AsRef<[u8]>) -> Self {
+        let bytes = bytes.as_ref();
+        let prefix = format!(" {{{}}}\r\n", bytes.len());
+        self.fragment().extend_from_slice(prefix.as_bytes());
+        self.fragments.push(bytes.to_vec());
+        self
+    }
+
+    fn into_fragments(self, tag: &str) -> VecDeque<Vec<u8>> {
+        let mut fragments = VecDeque::from(self.fragments);
+
+        if let Some(first) = fragments.front_mut() {
+            first.splice(0..0, format!("{tag} ").into_bytes());
+        }
+
+        if let Some(last) = fragments.back_mut() {
+            last.extend_from_slice(b"\r\n");
+        }
+
+        fragments
+    }
+}
+
+/// Return `true` if the given string can be sent as a quoted string.
+fn is_quotable(string: &[u8]) -> bool {
+    string
+        .iter()
+        .all(|b| b.is_ascii() && !matches!(b, b'\0' | b'\r' | b'\n'))
+}
+
+/// Quote the given string, escaping double quotes and backslashes.
+fn quote(string: &[u8]) -> Vec<u8> {
+    let mut quoted = Vec::with_capacity(string.len() + 2);
+    quoted.push(b'"');
+    for b in string {
+        if matches!(b, b'"' | b'\\') {
+            quoted.push(b'\\');
+        }
+        quoted.push(*b);
+    }
+    quoted.push(b'"');
+    quoted
+}
+
+/// Quote the given string, see [`RawCommand::string`].
+///
+/// Only meant for ASCII strings like UTF-7 encoded mailbox names.
+pub(crate) fn quoted(string: &str) -> String {
+    String::from_utf8_lossy(&quote(string.as_bytes())).into_owned()
+}
+
+/// The status of the tagged response completing a raw command.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum RawStatus {
+    Ok(String),
+    No(String),
+    Bad(String),
+}
+
+impl RawStatus {
+    fn parse(status: &[u8]) -> Self {
+        let status = String::from_utf8_lossy(status);
+        let (kind, text) = status.split_once(' ').unwrap_or((&status, ""));
+        let text = text.to_owned();
+
+        if kind.eq_ignore_ascii_case("OK") {
+            Self::Ok(text)
+        } else if kind.eq_ignore_ascii_case("NO") {
+            Self::No(text)
+        } else {
+            Self::Bad(text)
+        }
+    }
+}
+
+impl fmt::Display for RawStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok(text) => write!(f, "OK {text}"),
+            Self::No(text) => write!(f, "NO {text}"),
+            Self::Bad(text) => write!(f, "BAD {text}"),
+        }
+    }
+}
+
+/// The raw response received from the server.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum RawResponse {
+    /// The untagged response, without the leading `* ` nor the
+    /// trailing CRLF. Literals are kept inline.
+    Untagged(Vec<u8>),
+
+    /// The tagged response completing the command.
+    Tagged(RawStatus),
+}
+
+/// The state sending raw commands and collecting their responses.
+///
+/// The state can also be progressed without any command, in which
+/// case it only collects untagged responses: this is how NOTIFY
+/// notifications are received.
+///
+/// The state owns the bytes it reads, so bytes read after the last
+/// response taken out of the state are lost when it is dropped. This
+/// is harmless as long as the server does not send anything after
+/// the tagged response of the last command.
+#[derive(Debug, Default)]
+pub(crate) struct RawState {
+    tag: Option<String>,
+    output: VecDeque<Vec<u8>>,
+    flush: bool,
+    input: Vec<u8>,
+}
+
+impl RawState {
+    /// Enqueue the given command, which is sent the next time the
+    /// state is progressed.
+    pub fn enqueue(&mut self, command: RawCommand) {
+        // a new generator never generates tags of other generators,
+        // like the one of the client
+        let tag = TagGenerator::new().generate();
+        let tag = tag.as_ref().to_owned();
+
+        self.output = command.into_fragments(&tag);
+        self.flush = true;
+        self.tag = Some(tag);
+    }
+
+    /// Take the next complete response out of the input, including
+    /// its literals.
+    fn next_response(&mut self) -> Option<Vec<u8>> {
+        let mut start = 0;
+
+        loop {
+            let end = start + find_crlf(&self.input[start..])?;
+
+            match literal_len(&self.input[start..end]) {
+                Some(len) => {
+                    start = end + 2 + len;
+                    if start > self.input.len() {
+                        return None;
+                    }
+                }
+                None => {
+                    let response = self.input[..end].to_vec();
+                    self.input.drain(..end + 2);
+                    return Some(response);
+                }
+            }
+        }
+    }
+}
+
+impl State for RawState {
+    type Event = RawResponse;
+    type Error = Infallible;
+
+    fn enqueue_input(&mut self, bytes: &[u8]) {
+        self.input.extend_from_slice(bytes);
+    }
+
+    fn next(&mut self) -> Result<Self::Event, Interrupt<Self::Error>> {
+        if self.flush {
+            self.flush = false;
+            if let Some(fragment) = self.output.pop_front() {
+                return Err(Interrupt::Io(Io::Output(fragment)));
+            }
+        }
+
+        while let Some(response) = self.next_response() {
+            if let Some(data) = response.strip_prefix(b"* ") {
+                return Ok(RawResponse::Untagged(data.to_vec()));
+            }
+
+            if response.starts_with(b"+") {
+                match self.output.pop_front() {
+                    Some(fragment) => return Err(Interrupt::Io(Io::Output(fragment))),
+                    None => {
+                        debug!("ignoring unexpected continuation request");
+                        continue;
+                    }
+                }
+            }
+
+            let (tag, status) = match response.iter().position(|b| *b == b' ') {
+                Some(pos) => (&response[..pos], &response[pos + 1..]),
+                None => (response.as_slice(), &[][..]),
+            };
+
+            if self.tag.as_deref().map(str::as_bytes) == Some(tag) {
+                self.tag = None;
+                // remaining fragments are dropped when the server
+                // rejects a literal
+                self.output.clear();
+                return Ok(RawResponse::Tagged(RawStatus::parse(status)));
+            }
+
+            let response = String::from_utf8_lossy(&response);
+            debug!(%response, "ignoring unexpected response");
+        }
+
+        Err(Interrupt::Io(Io::NeedMoreInput))
+    }
+}
+
+fn find_crlf(bytes: &[u8]) -> Option<usize> {
+    bytes.windows(2).position(|w| w == b"\r\n")
+}
+
+/// Return the length of the literal announced at the end of the given
+/// line, if any.
+fn literal_len(line: &[u8]) -> Option<usize> {
+    let line = line.strip_suffix(b"}")?;
+    let open = line.iter().rposition(|b| *b == b'{')?;
+    let len = &line[open + 1..];
+    let len = len.strip_suffix(b"+").unwrap_or(len);
+    std::str::from_utf8(len).ok()?.parse().ok()
+}
+
+/// Return `true` if the given untagged response starts with the
+/// given name, like `ACL` or `MYRIGHTS`.
+pub(crate) fn is_named(response: &[u8], name: &str) -> bool {
+    match response.get(..name.len()) {
+        Some(prefix) => {
+            prefix.eq_ignore_ascii_case(name.as_bytes())
+                && matches!(response.get(name.len()), None | Some(b' '))
+        }
+        None => false,
+    }
+}
+
+/// The token of a raw response.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum RawToken {
+    /// An atom, like a number, a keyword, a flag or `NIL`.
+    Atom(String),
+
+    /// A quoted string or a literal.
+    String(Vec<u8>),
+
+    /// A parenthesized list.
+    List(Vec<RawToken>),
+}
+
+impl RawToken {
+    /// Return the token as an `astring`: either an atom or a string.
+    pub fn as_astring(&self) -> Option<String> {
+        match self {
+            Self::Atom(atom) => Some(atom.clone()),
+            Self::String(string) => Some(String::from_utf8_lossy(string).into_owned()),
+            Self::List(_) => None,
+        }
+    }
+
+    /// Return `true` if the token is the given atom, ignoring case.
+    pub fn is_atom(&self, atom: &str) -> bool {
+        matches!(self, Self::Atom(a) if a.eq_ignore_ascii_case(atom))
+    }
+}
+
+/// Split the given raw response into tokens.
+///
+/// Response codes are not parsed: brackets are kept in atoms.
+pub(crate) fn tokenize(response: &[u8]) -> Option<Vec<RawToken>> {
+    let mut pos = 0;
+    tokenize_from(response, &mut pos, false)
+}
+
+fn tokenize_from(input: &[u8], pos: &mut usize, nested: bool) -> Option<Vec<RawToken>> {
+    let mut tokens = Vec::new();
+
+    loop {
+        while input.get(*pos) == Some(&b' ') {
+            *pos += 1;
+        }
+
+        match input.get(*pos) {
+            None if nested => return None,
+            None => return Some(tokens),
+            Some(b')') if nested => {
+                *pos += 1;
+                return Some(tokens);
+            }
+            Some(b')') => return None,
+            Some(b'(') => {
+                *pos += 1;
+                tokens.push(RawToken::List(tokenize_from(input, pos, true)?));
+            }
+            Some(b'"') => {
+                *pos += 1;
+                let mut string = Vec::new();
+                loop {
+                    match input.get(*pos)? {
+                        b'\\' => {
+                            string.push(*input.get(*pos + 1)?);
+                            *pos += 2;
+                        }
+                        b'"' => {
+                            *pos += 1;
+                            break;
+                        }
+                        b => {
+                            string.push(*b);
+                            *pos += 1;
+                        }
+                    }
+                }
+                tokens.push(RawToken::String(string));
+            }
+            Some(b'{') => {
+                let close = *pos + input[*pos..].iter().position(|b| *b == b'}')?;
+                let len = literal_len(&input[*pos..=close])?;
+                let start = close + 3;
+                if input.get(close + 1..start)? != b"\r\n" {
+                    return None;
+                }
+                tokens.push(RawToken::String(input.get(start..start + len)?.to_vec()));
+                *pos = start + len;
+            }
+            Some(_) => {
+                let start = *pos;
+                while let Some(b) = input.get(*pos) {
+                    if matches!(b, b' ' | b'(' | b')' | b'"') {
+                        break;
+                    }
+                    *pos += 1;
+                }
+                let atom = String::from_utf8_lossy(&input[start..*pos]).into_owned();
+                tokens.push(RawToken::Atom(atom));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use imap_client::imap_next::{Interrupt, Io, State};
+
+    use super::{tokenize, RawCommand, RawResponse, RawState, RawStatus, RawToken};
+
+    fn output(state: &mut RawState) -> String {
+        match state.next() {
+            Err(Interrupt::Io(Io::Output(bytes))) => String::from_utf8(bytes).unwrap(),
+            res => panic!("expected output, got {res:?}"),
+        }
+    }
+
+    #[test]
+    fn command() {
+        let mut state = RawState::default();
+        state.enqueue(RawCommand::new("NOTIFY").arg("NONE"));
+        let tag = state.tag.clone().unwrap();
+
+        assert_eq!(output(&mut state), format!("{tag} NOTIFY NONE\r\n"));
+        assert!(matches!(
+            state.next(),
+            Err(Interrupt::Io(Io::NeedMoreInput))
+        ));
+
+        state.enqueue_input(b"* 3 EXISTS\r\n* OK still here\r\n");
+        state.enqueue_input(format!("{tag} OK done\r\n").as_bytes());
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Untagged(b"3 EXISTS".to_vec()))
+        );
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Untagged(b"OK still here".to_vec()))
+        );
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Tagged(RawStatus::Ok("done".into())))
+        );
+        assert!(state.tag.is_none());
+    }
+
+    #[test]
+    fn command_with_literals() {
+        let command = RawCommand::new("APPEND")
+            .string("Sent \"Items\"")
+            .arg("(\\Seen)")
+            .literal("abc")
+            .arg("()")
+            .string("é");
+
+        let mut state = RawState::default();
+        state.enqueue(command);
+        let tag = state.tag.clone().unwrap();
+
+        let first = output(&mut state);
+        assert_eq!(
+            first,
+            format!("{tag} APPEND \"Sent \\\"Items\\\"\" (\\Seen) {{3}}\r\n")
+        );
+        assert!(matches!(
+            state.next(),
+            Err(Interrupt::Io(Io::NeedMoreInput))
+        ));
+
+        state.enqueue_input(b"+ go ahead\r\n");
+        assert_eq!(output(&mut state), "abc () {2}\r\n");
+
+        state.enqueue_input(b"+ go ahead\r\n");
+        assert_eq!(output(&mut state), "é\r\n");
+
+        state.enqueue_input(b"* 3 EXISTS\r\n");
+        state.enqueue_input(format!("{tag} OK [APPENDUID 1 4:5] done\r\n").as_bytes());
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Untagged(b"3 EXISTS".to_vec()))
+        );
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Tagged(RawStatus::Ok(
+                "[APPENDUID 1 4:5] done".into()
+            )))
+        );
+    }
+
+    #[test]
+    fn rejected_literal() {
+        let mut state = RawState::default();
+        state.enqueue(RawCommand::new("APPEND").string("INBOX").literal("abc"));
+        let tag = state.tag.clone().unwrap();
+
+        output(&mut state);
+        state.enqueue_input(format!("{tag} NO [TOOBIG] too big\r\n").as_bytes());
+        assert_eq!(
+            state.next(),
+            Ok(RawResponse::Tagged(RawStatus::No(
+                "[TOOBIG] too big".into()
+            )))
+        );
+        assert!(state.output.is_empty());
+    }
+
+    #[test]
+    fn untagged_with_literal() {
+        let mut state = RawState::default();
+
+        state.enqueue_input(b"* ACL {5}\r\nIN");
+        assert!(matches!(
+            state.next(),
+            Err(Interrupt::Io(Io::NeedMoreInput))
+        ));
+
+        state.enqueue_input(b"BOX alice lr\r\n");
+        let RawResponse::Untagged(response) = state.next().unwrap() else {
+            panic!("expected untagged response");
+        };
+
+        assert_eq!(
+            tokenize(&response).unwrap(),
+            vec![
+                RawToken::Atom("ACL".into()),
+                RawToken::String(b"INBOX".to_vec()),
+                RawToken::Atom("alice".into()),
+                RawToken::Atom("lr".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenize_lists() {
+        let tokens = tokenize(br#"STATUS "a \"b\"" (MESSAGES 2 UIDNEXT 3)"#).unwrap();
+
+        assert_eq!(
+            tokens,
+            vec![
+                RawToken::Atom("STATUS".into()),
+                RawToken::String(br#"a "b""#.to_vec()),
+                RawToken::List(vec![
+                    RawToken::Atom("MESSAGES".into()),
+                    RawToken::Atom("2".into()),
+                    RawToken::Atom("UIDNEXT".into()),
+                    RawToken::Atom("3".into()),
+                ]),
+            ]
+        );
+
+        assert_eq!(tokenize(b"(unbalanced"), None);
+        assert_eq!(tokenize(b"unbalanced)"), None);
+    }
+}
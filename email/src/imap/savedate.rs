@@ -0,0 +1,92 @@
+//! # IMAP SAVEDATE
+//!
+//! Module dedicated to the IMAP SAVEDATE extension (RFC 8514), which
+//! exposes the date a message was saved in its current mailbox. It
+//! differs from the INTERNALDATE, which is kept when the message is
+//! copied or moved to another mailbox.
+//!
+//! The IMAP codec cannot encode the `SAVEDATE` fetch item yet, nor
+//! decode it: the fetch is sent as a raw command, and its responses
+//! are parsed by this module. See
+//! [`super::ImapClient::fetch_save_dates`].
+
+use std::num::NonZeroU32;
+
+use chrono::{DateTime, FixedOffset};
+
+use super::raw::{tokenize, RawToken};
+
+/// The format of IMAP dates, like `17-Oct-2026 10:00:00 +0200`.
+const DATE_TIME_FORMAT: &str = "%d-%b-%Y %H:%M:%S %z";
+
+/// Parse the given untagged `FETCH` response, like `12 FETCH (UID 42
+/// SAVEDATE "17-Oct-2026 10:00:00 +0200")`, into the UID of the
+/// message and its save date.
+///
+/// The save date is `None` when the server cannot tell it (`NIL`).
+pub(crate) fn parse_save_date(
+    response: &[u8],
+) -> Option<(NonZeroU32, Option<DateTime<FixedOffset>>)> {
+    let tokens = tokenize(response)?;
+
+    let items = match tokens.as_slice() {
+        [_, name, RawToken::List(items)] if name.is_atom("FETCH") => items,
+        _ => return None,
+    };
+
+    let mut uid = None;
+    let mut save_date = None;
+
+    for pair in items.chunks_exact(2) {
+        let (name, value) = (&pair[0], &pair[1]);
+
+        if name.is_atom("UID") {
+            uid = value.as_astring()?.parse().ok();
+        } else if name.is_atom("SAVEDATE") && !value.is_atom("NIL") {
+            let date = value.as_astring()?;
+            // days lower than 10 are padded with a space
+            save_date = Some(DateTime::parse_from_str(date.trim_start(), DATE_TIME_FORMAT).ok()?);
+        }
+    }
+
+    Some((uid?, save_date))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use chrono::{FixedOffset, TimeZone};
+
+    use super::parse_save_date;
+
+    #[test]
+    fn parse() {
+        let uid = NonZeroU32::new(42).unwrap();
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+
+        assert_eq!(
+            parse_save_date(br#"12 FETCH (UID 42 SAVEDATE "17-Oct-2026 10:00:00 +0200")"#),
+            Some((
+                uid,
+                Some(offset.with_ymd_and_hms(2026, 10, 17, 10, 0, 0).unwrap())
+            )),
+        );
+
+        assert_eq!(
+            parse_save_date(br#"12 FETCH (SAVEDATE " 7-Oct-2026 10:00:00 +0200" UID 42)"#),
+            Some((
+                uid,
+                Some(offset.with_ymd_and_hms(2026, 10, 7, 10, 0, 0).unwrap())
+            )),
+        );
+
+        assert_eq!(
+            parse_save_date(b"12 FETCH (UID 42 SAVEDATE NIL)"),
+            Some((uid, None)),
+        );
+
+        assert_eq!(parse_save_date(b"12 FETCH (FLAGS (\\Seen))"), None);
+        assert_eq!(parse_save_date(b"12 EXPUNGE"), None);
+    }
+}
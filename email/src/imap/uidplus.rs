@@ -0,0 +1,219 @@
+//! # IMAP UIDPLUS
+//!
+//! Module dedicated to the IMAP UIDPLUS extension (RFC 4315). It
+//! contains COPY and MOVE tasks able to collect the COPYUID response
+//! code, so that callers know the UIDs assigned to messages in the
+//! destination mailbox without having to fetch them again.
+
+use std::{collections::HashMap, num::NonZeroU32};
+
+use imap_client::{
+    imap_next::imap_types::{
+        command::CommandBody,
+        core::Vec1,
+        extensions::uidplus::{UidElement, UidSet},
+        mailbox::Mailbox,
+        response::{Code, StatusBody, StatusKind},
+        sequence::SequenceSet,
+    },
+    tasks::{tasks::TaskError, Task},
+};
+use tracing::debug;
+
+/// The mapping between source UIDs and destination UIDs, as
+/// announced by the COPYUID response code.
+pub type UidMapping = HashMap<NonZeroU32, NonZeroU32>;
+
+/// The COPY or MOVE task collecting the COPYUID response code.
+///
+/// The response code is sent in the tagged response of COPY commands,
+/// and in an untagged OK response of MOVE commands (RFC 6851).
+#[derive(Clone, Debug)]
+pub struct CopyUidTask {
+    sequence_set: SequenceSet,
+    mailbox: Mailbox<'static>,
+    r#move: bool,
+    mapping: UidMapping,
+}
+
+impl CopyUidTask {
+    pub fn new(sequence_set: SequenceSet, mailbox: Mailbox<'static>) -> Self {
+        Self {
+            sequence_set,
+            mailbox,
+            r#move: false,
+            mapping: Default::default(),
+        }
+    }
+
+    pub fn set_move(&mut self, r#move: bool) {
+        self.r#move = r#move;
+    }
+
+    pub fn with_move(mut self, r#move: bool) -> Self {
+        self.set_move(r#move);
+        self
+    }
+
+    fn collect_copyuid(&mut self, code: &Option<Code<'static>>) {
+        if let Some(Code::CopyUid {
+            uid_validity,
+            source,
+            destination,
+        }) = code
+        {
+            debug!(%uid_validity, "received COPYUID response code");
+            self.mapping.extend(map_uid_sets(source, destination));
+        }
+    }
+}
+
+impl Task for CopyUidTask {
+    type Output = Result<UidMapping, TaskError>;
+
+    fn command_body(&self) -> CommandBody<'static> {
+        if self.r#move {
+            CommandBody::Move {
+                sequence_set: self.sequence_set.clone(),
+                mailbox: self.mailbox.clone(),
+                uid: true,
+            }
+        } else {
+            CommandBody::Copy {
+                sequence_set: self.sequence_set.clone(),
+                mailbox: self.mailbox.clone(),
+                uid: true,
+            }
+        }
+    }
+
+    fn process_untagged(
+        &mut self,
+        status_body: StatusBody<'static>,
+    ) -> Option<StatusBody<'static>> {
+        if let (StatusKind::Ok, Some(Code::CopyUid { .. })) = (&status_body.kind, &status_body.code)
+        {
+            self.collect_copyuid(&status_body.code);
+            None
+        } else {
+            Some(status_body)
+        }
+    }
+
+    fn process_tagged(mut self, status_body: StatusBody<'static>) -> Self::Output {
+        match status_body.kind {
+            StatusKind::Ok => {
+                self.collect_copyuid(&status_body.code);
+                Ok(self.mapping)
+            }
+            StatusKind::No => Err(TaskError::UnexpectedNoResponse(status_body)),
+            StatusKind::Bad => Err(TaskError::UnexpectedBadResponse(status_body)),
+        }
+    }
+}
+
+/// Expand the given UID set into a list of UIDs, keeping the order
+/// announced by the server.
+fn expand_uid_set(set: &UidSet) -> Vec<NonZeroU32> {
+    set.0
+        .as_ref()
+        .iter()
+        .flat_map(|elem| match *elem {
+            UidElement::Single(uid) => vec![uid],
+            UidElement::Range(a, b) => {
+                let (from, to) = if a <= b { (a, b) } else { (b, a) };
+                (from.get()..=to.get())
+                    .filter_map(NonZeroU32::new)
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+/// Parse the UIDs announced by the APPENDUID response code found at
+/// the beginning of the given response text, like `[APPENDUID 38505
+/// 3955:3957] done`.
+///
+/// The response code is parsed by hand because it is collected from
+/// raw responses, see the MULTIAPPEND extension (RFC 3502).
+pub(crate) fn parse_appenduid(text: &str) -> Option<Vec<NonZeroU32>> {
+    let code = text.strip_prefix('[')?.split_once(']')?.0;
+    let mut args = code.split(' ');
+
+    if !args.next()?.eq_ignore_ascii_case("APPENDUID") {
+        return None;
+    }
+
+    let _uid_validity = args.next()?;
+    let elems = args
+        .next()?
+        .split(',')
+        .map(|elem| match elem.split_once(':') {
+            Some((a, b)) => Some(UidElement::Range(a.parse().ok()?, b.parse().ok()?)),
+            None => Some(UidElement::Single(elem.parse().ok()?)),
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    let set = UidSet(Vec1::try_from(elems).ok()?);
+    Some(expand_uid_set(&set))
+}
+
+/// Pair source UIDs with destination UIDs.
+///
+/// Both sets have the same number of elements, the n-th source UID
+/// corresponding to the n-th destination UID (RFC 4315 section 3).
+fn map_uid_sets(source: &UidSet, destination: &UidSet) -> UidMapping {
+    expand_uid_set(source)
+        .into_iter()
+        .zip(expand_uid_set(destination))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use imap_client::imap_next::imap_types::{
+        core::Vec1,
+        extensions::uidplus::{UidElement, UidSet},
+    };
+
+    fn uid(n: u32) -> NonZeroU32 {
+        NonZeroU32::new(n).unwrap()
+    }
+
+    #[test]
+    fn map_uid_sets() {
+        let source = UidSet(
+            Vec1::try_from(vec![
+                UidElement::Single(uid(4)),
+                UidElement::Range(uid(7), uid(9)),
+            ])
+            .unwrap(),
+        );
+
+        let destination =
+            UidSet(Vec1::try_from(vec![UidElement::Range(uid(103), uid(100))]).unwrap());
+
+        let mapping = super::map_uid_sets(&source, &destination);
+
+        assert_eq!(mapping.len(), 4);
+        assert_eq!(mapping[&uid(4)], uid(100));
+        assert_eq!(mapping[&uid(7)], uid(101));
+        assert_eq!(mapping[&uid(8)], uid(102));
+        assert_eq!(mapping[&uid(9)], uid(103));
+    }
+
+    #[test]
+    fn parse_appenduid() {
+        let uids = super::parse_appenduid("[APPENDUID 38505 3955:3957,3960] done");
+        assert_eq!(uids, Some(vec![uid(3955), uid(3956), uid(3957), uid(3960)]));
+
+        let uids = super::parse_appenduid("[appenduid 1 4] done");
+        assert_eq!(uids, Some(vec![uid(4)]));
+
+        assert_eq!(super::parse_appenduid("done"), None);
+        assert_eq!(super::parse_appenduid("[READ-WRITE] done"), None);
+        assert_eq!(super::parse_appenduid("[APPENDUID 1 0] done"), None);
+    }
+}
@@ -0,0 +1,196 @@
+//! # IMAP UIDVALIDITY
+//!
+//! Module dedicated to UIDVALIDITY tracking. When the UIDVALIDITY of
+//! a mailbox changes, every UID previously known for this mailbox is
+//! invalid. The [`UidValidityStore`] keeps track of the last known
+//! UIDVALIDITY of each mailbox, optionally persisted in a file, and
+//! reports changes so that UID-based caches can be invalidated.
+
+use std::{collections::HashMap, fmt, fs, io, num::NonZeroU32, path::PathBuf};
+
+use tracing::{debug, warn};
+
+/// The UIDVALIDITY change of a mailbox.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UidValidityChange {
+    /// The name of the mailbox.
+    pub folder: String,
+
+    /// The previously known UIDVALIDITY.
+    pub old: NonZeroU32,
+
+    /// The new UIDVALIDITY.
+    pub new: NonZeroU32,
+}
+
+impl fmt::Display for UidValidityChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let folder = &self.folder;
+        let (old, new) = (self.old, self.new);
+        write!(
+            f,
+            "UIDVALIDITY of folder {folder} changed from {old} to {new}"
+        )
+    }
+}
+
+/// The UIDVALIDITY store.
+///
+/// The store is persisted in the given file, one mailbox per line,
+/// using the format `<uidvalidity> <mailbox>`.
+#[derive(Clone, Debug, Default)]
+pub struct UidValidityStore {
+    path: Option<PathBuf>,
+    validities: HashMap<String, NonZeroU32>,
+    changes: Vec<UidValidityChange>,
+}
+
+impl UidValidityStore {
+    /// Create a store living in memory only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the store from the given file.
+    ///
+    /// A missing file leads to an empty store.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+
+        let validities = match fs::read_to_string(&path) {
+            Ok(contents) => parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err),
+        };
+
+        debug!(?path, count = validities.len(), "loaded UIDVALIDITY store");
+
+        Ok(Self {
+            path: Some(path),
+            validities,
+            changes: Vec::new(),
+        })
+    }
+
+    /// Return the last known UIDVALIDITY of the given mailbox.
+    pub fn get(&self, folder: &str) -> Option<NonZeroU32> {
+        self.validities.get(folder).copied()
+    }
+
+    /// Update the UIDVALIDITY of the given mailbox.
+    ///
+    /// Returns the change if the UIDVALIDITY differs from the last
+    /// known one. The change is also kept in the store, see
+    /// [`UidValidityStore::take_changes`].
+    pub fn update(
+        &mut self,
+        folder: impl ToString,
+        uid_validity: NonZeroU32,
+    ) -> io::Result<Option<UidValidityChange>> {
+        let folder = folder.to_string();
+
+        let change = match self.validities.insert(folder.clone(), uid_validity) {
+            Some(old) if old == uid_validity => return Ok(None),
+            Some(old) => Some(UidValidityChange {
+                folder,
+                old,
+                new: uid_validity,
+            }),
+            None => None,
+        };
+
+        if let Some(change) = &change {
+            warn!("{change}, cached UIDs are invalid");
+            self.changes.push(change.clone());
+        }
+
+        self.save()?;
+
+        Ok(change)
+    }
+
+    /// Forget the UIDVALIDITY of the given mailbox, for example when
+    /// it is deleted.
+    pub fn invalidate(&mut self, folder: &str) -> io::Result<()> {
+        if self.validities.remove(folder).is_some() {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Take the changes detected since the last call.
+    pub fn take_changes(&mut self) -> Vec<UidValidityChange> {
+        std::mem::take(&mut self.changes)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, format(&self.validities))
+    }
+}
+
+fn parse(contents: &str) -> HashMap<String, NonZeroU32> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let (uid_validity, folder) = line.split_once(' ')?;
+            Some((folder.to_owned(), uid_validity.parse().ok()?))
+        })
+        .collect()
+}
+
+fn format(validities: &HashMap<String, NonZeroU32>) -> String {
+    let mut lines: Vec<_> = validities
+        .iter()
+        .map(|(folder, uid_validity)| format!("{uid_validity} {folder}\n"))
+        .collect();
+    lines.sort();
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::UidValidityStore;
+
+    fn validity(n: u32) -> NonZeroU32 {
+        NonZeroU32::new(n).unwrap()
+    }
+
+    #[test]
+    fn detect_changes() {
+        let mut store = UidValidityStore::new();
+
+        assert_eq!(store.update("INBOX", validity(1)).unwrap(), None);
+        assert_eq!(store.update("INBOX", validity(1)).unwrap(), None);
+
+        let change = store.update("INBOX", validity(2)).unwrap().unwrap();
+        assert_eq!(change.folder, "INBOX");
+        assert_eq!(change.old, validity(1));
+        assert_eq!(change.new, validity(2));
+
+        assert_eq!(store.take_changes(), vec![change]);
+        assert!(store.take_changes().is_empty());
+    }
+
+    #[test]
+    fn parse_and_format() {
+        let contents = "42 INBOX\n7 Sent Items\ninvalid\n";
+        let validities = super::parse(contents);
+
+        assert_eq!(validities.get("INBOX"), Some(&validity(42)));
+        assert_eq!(validities.get("Sent Items"), Some(&validity(7)));
+        assert_eq!(validities.len(), 2);
+
+        assert_eq!(super::format(&validities), "42 INBOX\n7 Sent Items\n");
+    }
+}
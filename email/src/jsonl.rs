@@ -0,0 +1,177 @@
+//! # JSON lines
+//!
+//! Module dedicated to JSON lines files, one JSON value per line,
+//! see [`JsonLinesWriter`]. They back the backend journal and the
+//! account health log.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{sync::Mutex, task};
+use tracing::debug;
+
+/// The rotation policy of an append-only JSON lines file.
+///
+/// Once the file reaches `max_size` bytes, it becomes `<path>.1`,
+/// the previous `<path>.1` becomes `<path>.2` and so on, up to
+/// `max_files` rotated files.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) struct Rotation {
+    pub max_size: u64,
+    pub max_files: usize,
+}
+
+/// The writer of a JSON lines file.
+///
+/// Writes are serialized and run on the blocking thread pool, so
+/// that file system operations never block the async
+/// runtime. Cloning the writer shares the same lock.
+#[derive(Clone, Debug)]
+pub(crate) struct JsonLinesWriter {
+    path: PathBuf,
+    lock: Arc<Mutex<()>>,
+}
+
+impl JsonLinesWriter {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Default::default(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the given value as a new line, rotating the file
+    /// beforehand if needed.
+    pub async fn append(
+        &self,
+        value: &impl Serialize,
+        rotation: Option<Rotation>,
+    ) -> io::Result<()> {
+        let mut line = serde_json::to_vec(value)?;
+        line.push(b'\n');
+
+        // the guard is moved into the blocking task, so that the lock
+        // is held until the write ends even if this future is dropped
+        let guard = self.lock.clone().lock_owned().await;
+        let path = self.path.clone();
+
+        task::spawn_blocking(move || {
+            let _guard = guard;
+
+            create_parent_dir(&path)?;
+
+            if let Some(rotation) = rotation {
+                rotate_if_needed(&path, rotation)?;
+            }
+
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)?
+                .write_all(&line)
+        })
+        .await?
+    }
+
+    /// Replace the content of the file by the given values.
+    ///
+    /// Values are collected once the lock is acquired, so that
+    /// concurrent replacements always end up with the latest
+    /// values. The file is written to a temporary file first so that
+    /// a crash never leaves a truncated file behind.
+    pub async fn replace<T: Serialize>(&self, values: impl FnOnce() -> Vec<T>) -> io::Result<()> {
+        let guard = self.lock.clone().lock_owned().await;
+
+        let mut contents = Vec::new();
+        for value in values() {
+            serde_json::to_writer(&mut contents, &value)?;
+            contents.push(b'\n');
+        }
+
+        let path = self.path.clone();
+
+        task::spawn_blocking(move || {
+            let _guard = guard;
+
+            create_parent_dir(&path)?;
+
+            let mut tmp_path = path.clone().into_os_string();
+            tmp_path.push(".tmp");
+
+            fs::write(&tmp_path, contents)?;
+            fs::rename(&tmp_path, &path)
+        })
+        .await?
+    }
+}
+
+/// Read the values of the given JSON lines file.
+///
+/// Lines that cannot be deserialized are skipped.
+pub(crate) fn read<T: DeserializeOwned>(path: &Path) -> io::Result<Vec<T>> {
+    let mut values = Vec::new();
+
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+
+        match serde_json::from_str(&line) {
+            Ok(value) => values.push(value),
+            Err(err) => debug!(line, ?err, "skipping invalid json line"),
+        }
+    }
+
+    Ok(values)
+}
+
+fn create_parent_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+}
+
+fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    let mut path = path.to_owned().into_os_string();
+    path.push(format!(".{n}"));
+    PathBuf::from(path)
+}
+
+fn rotate_if_needed(path: &Path, rotation: Rotation) -> io::Result<()> {
+    match fs::metadata(path) {
+        Ok(metadata) if metadata.len() >= rotation.max_size => (),
+        Ok(_) => return Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    }
+
+    if rotation.max_files == 0 {
+        return remove_file_if_exists(path);
+    }
+
+    remove_file_if_exists(&rotated_path(path, rotation.max_files))?;
+
+    for n in (1..rotation.max_files).rev() {
+        let rotated = rotated_path(path, n);
+        if rotated.exists() {
+            fs::rename(rotated, rotated_path(path, n + 1))?;
+        }
+    }
+
+    fs::rename(path, rotated_path(path, 1))
+}
+
+fn remove_file_if_exists(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+        _ => Ok(()),
+    }
+}
@@ -53,16 +53,23 @@
 pub mod autoconfig;
 pub mod backend;
 pub mod config;
+#[cfg(any(feature = "imap", feature = "smtp"))]
+pub mod diagnostic;
 pub mod email;
 mod error;
 pub mod folder;
 #[cfg(feature = "imap")]
 pub mod imap;
+mod jsonl;
 #[cfg(feature = "maildir")]
 pub mod maildir;
+#[cfg(feature = "memory")]
+pub mod memory;
 #[cfg(feature = "notmuch")]
 pub mod notmuch;
 pub mod retry;
+#[cfg(any(feature = "imap", feature = "smtp"))]
+pub mod sasl;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
 #[cfg(feature = "derive")]
@@ -71,6 +78,8 @@
 pub mod smtp;
 #[cfg(feature = "sync")]
 pub mod sync;
+#[cfg(feature = "testing")]
+pub mod testing;
 #[cfg(any(feature = "imap", feature = "smtp"))]
 pub mod tls;
 pub mod watch;
@@ -0,0 +1,251 @@
+//! # Asynchronous Maildir
+//!
+//! Module dedicated to the asynchronous API of Maildir folders. The
+//! [`maildirs`] crate only exposes blocking [`std::fs`] operations,
+//! which stall the async runtime when called from a task. The
+//! [`AsyncMaildir`] wrapper moves them to the blocking thread pool of
+//! the runtime (cargo features `tokio` or `async-std`).
+
+use std::{
+    io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use maildirs::{Flag, Maildir, MaildirEntry, Result};
+
+use super::{
+    fsck::{MaildirAnomaly, MaildirFsckExt},
+    store_cur_with_flags_and_time,
+    tmp::MaildirTmpExt,
+};
+
+/// The asynchronous Maildir folder.
+///
+/// Each operation runs in its own blocking task. Directory reads are
+/// batched: entries are all collected within the same task, instead
+/// of being yielded one by one by an iterator.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AsyncMaildir(Maildir);
+
+impl AsyncMaildir {
+    /// Create a new asynchronous Maildir folder from the given
+    /// blocking one.
+    pub fn new(mdir: Maildir) -> Self {
+        Self(mdir)
+    }
+
+    /// Return the inner blocking Maildir folder.
+    pub fn as_blocking(&self) -> &Maildir {
+        &self.0
+    }
+
+    /// Consume the wrapper and return the inner blocking Maildir
+    /// folder.
+    pub fn into_blocking(self) -> Maildir {
+        self.0
+    }
+
+    /// Return the root path of the Maildir folder.
+    pub fn path(&self) -> &Path {
+        self.0.path()
+    }
+
+    /// Run the given blocking function against the Maildir folder.
+    ///
+    /// Useful to batch several blocking operations in the same task,
+    /// for example to find then to update multiple entries.
+    pub async fn run<T: Send + 'static>(
+        &self,
+        f: impl FnOnce(Maildir) -> T + Send + 'static,
+    ) -> Result<T> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || f(mdir)).await
+    }
+
+    /// Create the Maildir folder structure, including the root
+    /// folder.
+    pub async fn create_all(&self) -> Result<()> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || mdir.create_all()).await?
+    }
+
+    /// Remove the Maildir folder and all its entries.
+    pub async fn remove_all(&self) -> Result<()> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || mdir.remove_all()).await?
+    }
+
+    /// List entries from both `new` and `cur` directories.
+    pub async fn read(&self) -> Result<Vec<MaildirEntry>> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || Ok(mdir.read()?.collect())).await?
+    }
+
+    /// List entries from the `new` directory.
+    pub async fn read_new(&self) -> Result<Vec<MaildirEntry>> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || {
+            let new = mdir.new().to_owned();
+            Ok(mdir
+                .read()?
+                .filter(|e| e.path().starts_with(&new))
+                .collect())
+        })
+        .await?
+    }
+
+    /// List entries from the `cur` directory.
+    pub async fn read_cur(&self) -> Result<Vec<MaildirEntry>> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || {
+            let cur = mdir.cur().to_owned();
+            Ok(mdir
+                .read()?
+                .filter(|e| e.path().starts_with(&cur))
+                .collect())
+        })
+        .await?
+    }
+
+    /// Find the entry matching the given identifier.
+    pub async fn find(&self, id: impl ToString) -> Result<Option<MaildirEntry>> {
+        let mdir = self.0.clone();
+        let id = id.to_string();
+        spawn_blocking(move || mdir.find(id)).await?
+    }
+
+    /// Get the entry matching the given identifier, or fail if it
+    /// does not exist.
+    pub async fn get(&self, id: impl ToString) -> Result<MaildirEntry> {
+        let mdir = self.0.clone();
+        let id = id.to_string();
+        spawn_blocking(move || mdir.get(id)).await?
+    }
+
+    /// Store the given contents in the `new` directory.
+    pub async fn write_new(&self, contents: impl Into<Vec<u8>>) -> Result<MaildirEntry> {
+        let mdir = self.0.clone();
+        let contents = contents.into();
+        spawn_blocking(move || mdir.write_new(contents)).await?
+    }
+
+    /// Store the given contents in the `cur` directory, with the
+    /// given flags.
+    pub async fn write_cur(
+        &self,
+        contents: impl Into<Vec<u8>>,
+        flags: impl IntoIterator<Item = Flag>,
+    ) -> Result<MaildirEntry> {
+        let mdir = self.0.clone();
+        let contents = contents.into();
+        let flags: Vec<_> = flags.into_iter().collect();
+        spawn_blocking(move || mdir.write_cur(contents, flags)).await?
+    }
+
+    /// Store the given contents in the `cur` directory, with the
+    /// given flags and delivery time.
+    ///
+    /// See [`store_cur_with_flags_and_time`].
+    pub async fn write_cur_with_time(
+        &self,
+        contents: impl Into<Vec<u8>>,
+        flags: impl IntoIterator<Item = Flag>,
+        time: SystemTime,
+    ) -> Result<MaildirEntry> {
+        let mdir = self.0.clone();
+        let contents = contents.into();
+        let flags: Vec<_> = flags.into_iter().collect();
+        spawn_blocking(move || store_cur_with_flags_and_time(&mdir, &contents, flags, time)).await?
+    }
+
+    /// Remove stale temporary files.
+    ///
+    /// See [`MaildirTmpExt::clean_tmp`].
+    pub async fn clean_tmp(&self, older_than: Duration) -> io::Result<usize> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || mdir.clean_tmp(older_than))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Detect anomalies in the Maildir folder.
+    ///
+    /// See [`MaildirFsckExt::fsck`].
+    pub async fn fsck(&self, new_max_age: Duration) -> io::Result<Vec<MaildirAnomaly>> {
+        let mdir = self.0.clone();
+        spawn_blocking(move || mdir.fsck(new_max_age))
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+impl From<Maildir> for AsyncMaildir {
+    fn from(mdir: Maildir) -> Self {
+        Self::new(mdir)
+    }
+}
+
+impl From<AsyncMaildir> for Maildir {
+    fn from(mdir: AsyncMaildir) -> Self {
+        mdir.into_blocking()
+    }
+}
+
+/// Run the given blocking function on the blocking thread pool of
+/// the async runtime.
+#[cfg(feature = "tokio")]
+async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|err| io::Error::other(err).into())
+}
+
+/// Run the given blocking function on the blocking thread pool of
+/// the async runtime.
+#[cfg(all(feature = "async-std", not(feature = "tokio")))]
+async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    Ok(async_std::task::spawn_blocking(f).await)
+}
+
+/// Run the given blocking function in place, since no async runtime
+/// is available.
+#[cfg(not(any(feature = "tokio", feature = "async-std")))]
+async fn spawn_blocking<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Result<T> {
+    Ok(f())
+}
+
+#[cfg(test)]
+mod tests {
+    use maildirs::{Flag, Maildir};
+
+    use super::AsyncMaildir;
+
+    #[tokio::test]
+    async fn read_and_write() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = AsyncMaildir::from(Maildir::from(path));
+        mdir.create_all().await.unwrap();
+
+        let new = mdir.write_new(b"new".to_vec()).await.unwrap();
+        let cur = mdir.write_cur(b"cur".to_vec(), [Flag::Seen]).await.unwrap();
+
+        assert_eq!(mdir.read().await.unwrap().len(), 2);
+
+        let entries = mdir.read_new().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path(), new.path());
+
+        let entries = mdir.read_cur().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].path(), cur.path());
+
+        let entry = mdir.get(new.id().unwrap()).await.unwrap();
+        assert_eq!(entry.path(), new.path());
+        assert!(mdir.find("unknown").await.unwrap().is_none());
+
+        mdir.remove_all().await.unwrap();
+        assert!(!mdir.path().exists());
+    }
+}
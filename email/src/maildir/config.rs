@@ -3,7 +3,9 @@
 //! This module contains the configuration specific to the Maildir
 //! backend.
 
-use std::path::PathBuf;
+use std::{collections::HashSet, path::PathBuf};
+
+use crate::{email::Result, flag::Flag};
 
 /// The Maildir backend configuration.
 #[derive(Debug, Default, Clone, Eq, PartialEq)]
@@ -23,11 +25,45 @@ pub struct MaildirConfig {
 
     #[cfg_attr(feature = "derive", serde(default))]
     pub maildirpp: bool,
+
+    /// Remove stale temporary files when building the context.
+    ///
+    /// Temporary files left in `tmp` directories by crashed
+    /// processes are removed once they have not been accessed for 36
+    /// hours, as defined by the Maildir specification.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub clean_tmp: bool,
+
+    /// The flags marking messages for permanent removal.
+    ///
+    /// Messages having at least one of these flags are removed when
+    /// expunging a folder, the same way IMAP servers remove messages
+    /// having the Deleted flag. Flags are parsed like envelope flags,
+    /// and must have a Maildir equivalent. Defaults to `deleted`,
+    /// which maps to the Maildir flag `T` (trashed).
+    pub expunge_flags: Option<Vec<String>>,
+}
+
+impl MaildirConfig {
+    /// Find the Maildir flags marking messages for permanent
+    /// removal.
+    ///
+    /// Fails if one of the configured flags does not have a Maildir
+    /// equivalent.
+    pub fn find_expunge_flags(&self) -> Result<HashSet<maildirs::Flag>> {
+        match &self.expunge_flags {
+            None => Ok(HashSet::from_iter([maildirs::Flag::Trashed])),
+            Some(flags) => flags
+                .iter()
+                .map(|flag| maildirs::Flag::try_from(Flag::from(flag.as_str())))
+                .collect(),
+        }
+    }
 }
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for MaildirConfig {
-    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
-        std::hash::Hash::hash(&shellexpand_utils::shellexpand_path(&self.root_dir), state);
+    fn sync_hash(&self, state: &mut crate::sync::hash::SyncHasher) {
+        state.write_path(&shellexpand_utils::shellexpand_path(&self.root_dir));
     }
 }
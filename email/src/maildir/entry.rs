@@ -0,0 +1,182 @@
+//! # Maildir entry
+//!
+//! Module dedicated to Maildir entries, see [`MaildirEntryExt`].
+
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use maildirs::{Maildir, MaildirEntry};
+use tracing::debug;
+
+use super::{fsck::parse_file_name, Error, Result};
+use crate::envelope::{Envelope, Flags};
+
+/// Extension trait for [`MaildirEntry`] giving access to message
+/// metadata without reading the whole message file.
+pub trait MaildirEntryExt {
+    /// Parse the headers of the entry.
+    ///
+    /// Only the header section of the message file is read: the
+    /// body is never loaded. The date, the sender, the subject and
+    /// the Message-ID are parsed and decoded the same way backends
+    /// do, see [`Envelope::from_raw_headers`].
+    fn parsed_headers(&self) -> Result<Envelope>;
+
+    /// Return the size of the message, in bytes.
+    ///
+    /// The size is taken from the Maildir++ size field `,S=<size>`
+    /// of the file name. Returns `None` when the file name has no
+    /// size field.
+    fn size(&self) -> Option<u64>;
+
+    /// Move the entry to the `cur` directory of the given Maildir
+    /// folder, keeping its file name (and therefore its flags).
+    ///
+    /// When both folders live on different filesystems, the entry
+    /// cannot be renamed. It is then copied to the `tmp` directory
+    /// of the given folder, synced to disk, renamed to `cur`, and
+    /// finally removed from its original folder. The message is
+    /// never lost, at worst it is duplicated when the removal fails.
+    ///
+    /// Returns the new path of the entry, or
+    /// [`Error::MoveEntryToSameFolderError`] if the entry is already
+    /// in the `cur` directory of the given folder.
+    fn move_to(&self, mdir: &Maildir) -> Result<PathBuf>;
+}
+
+impl MaildirEntryExt for MaildirEntry {
+    fn parsed_headers(&self) -> Result<Envelope> {
+        let id = self.id()?;
+        let flags = Flags::try_from(self.clone()).unwrap_or_default();
+        let headers = self.read_headers()?;
+        Ok(Envelope::from_raw_headers(id, flags, &headers))
+    }
+
+    fn size(&self) -> Option<u64> {
+        parse_file_name(self.path())?.size
+    }
+
+    fn move_to(&self, mdir: &Maildir) -> Result<PathBuf> {
+        let src = self.path();
+
+        if Some(mdir.cur()) == src.parent() {
+            return Err(Error::MoveEntryToSameFolderError(src.to_owned()));
+        }
+
+        let file_name = self.file_name()?;
+        let dest = mdir.cur().join(file_name);
+
+        match fs::rename(src, &dest) {
+            Ok(()) => return Ok(dest),
+            Err(err) if is_cross_device(&err) => {
+                debug!(
+                    ?src,
+                    ?dest,
+                    "cannot rename maildir entry across filesystems"
+                );
+            }
+            Err(err) => return Err(Error::MoveEntryError(err, src.to_owned(), dest)),
+        }
+
+        let tmp = mdir.tmp().join(file_name);
+
+        copy_synced(src, &tmp)
+            .and_then(|()| fs::rename(&tmp, &dest))
+            .and_then(|()| sync_dir(mdir.cur()))
+            .map_err(|err| {
+                let _ = fs::remove_file(&tmp);
+                Error::MoveEntryError(err, src.to_owned(), dest.clone())
+            })?;
+
+        fs::remove_file(src)
+            .map_err(|err| Error::MoveEntryError(err, src.to_owned(), dest.clone()))?;
+
+        Ok(dest)
+    }
+}
+
+/// Return `true` if the given error is raised when renaming a file
+/// across filesystems (`EXDEV` on Unix, `ERROR_NOT_SAME_DEVICE` on
+/// Windows).
+fn is_cross_device(err: &io::Error) -> bool {
+    #[cfg(unix)]
+    const CROSS_DEVICE_ERROR: i32 = 18;
+    #[cfg(windows)]
+    const CROSS_DEVICE_ERROR: i32 = 17;
+    #[cfg(not(any(unix, windows)))]
+    const CROSS_DEVICE_ERROR: i32 = -1;
+
+    err.raw_os_error() == Some(CROSS_DEVICE_ERROR)
+}
+
+/// Copy the given file, then flush the copy to disk.
+fn copy_synced(src: &Path, dest: &Path) -> io::Result<()> {
+    fs::copy(src, dest)?;
+    File::open(dest)?.sync_all()
+}
+
+/// Flush the given directory entries to disk, so that a rename
+/// survives a crash.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    File::open(dir)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use maildirs::{Flag, Maildir};
+
+    use super::MaildirEntryExt;
+    use crate::flag::Flag as EmailFlag;
+
+    #[test]
+    fn parsed_headers_and_size() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = Maildir::from(path.clone());
+        mdir.create_all().unwrap();
+
+        let contents = concat!(
+            "Message-ID: <id@localhost>\r\n",
+            "From: =?utf-8?q?J=C3=B6rg?= <jorg@localhost>\r\n",
+            "Subject: =?utf-8?b?w6l0w6k=?=\r\n",
+            "Date: Thu, 01 Jan 2015 00:00:00 +0000\r\n",
+            "\r\n",
+            "body\r\n",
+        );
+
+        let entry = mdir.write_cur(contents, [Flag::Seen]).unwrap();
+        assert_eq!(entry.size(), None);
+
+        let headers = entry.parsed_headers().unwrap();
+        assert_eq!(headers.id, entry.id().unwrap());
+        assert_eq!(headers.message_id, "<id@localhost>");
+        assert_eq!(headers.subject, "été");
+        assert_eq!(headers.from.name.as_deref(), Some("Jörg"));
+        assert_eq!(headers.from.addr, "jorg@localhost");
+        assert_eq!(headers.date.to_rfc3339(), "2015-01-01T00:00:00+00:00");
+        assert!(headers.flags.contains(&EmailFlag::Seen));
+
+        let sized = entry.path().with_file_name("1.host,S=42:2,S");
+        fs::rename(entry.path(), &sized).unwrap();
+        let entry = mdir.get("1.host,S=42").unwrap();
+        assert_eq!(entry.size(), Some(42));
+
+        let archives = Maildir::from(path.join(".Archives"));
+        archives.create_all().unwrap();
+        assert!(entry.move_to(&mdir).is_err());
+        let moved = entry.move_to(&archives).unwrap();
+        assert_eq!(moved, archives.cur().join("1.host,S=42:2,S"));
+        assert!(!entry.path().exists());
+    }
+}
@@ -1,7 +1,8 @@
-use std::{any::Any, path::PathBuf, result};
+use std::{any::Any, io, path::PathBuf, result};
 
 use thiserror::Error;
 
+use super::quota::MaildirQuota;
 use crate::{AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
@@ -16,6 +17,29 @@ pub enum Error {
     CheckUpCurrentDirectoryError(#[source] maildirs::Error),
     #[error("cannot create maildir folder structure at {0}")]
     CreateFolderStructureError(#[source] maildirs::Error, PathBuf),
+    #[error("cannot check consistency of maildir folder at {1}")]
+    CheckFolderConsistencyError(#[source] io::Error, PathBuf),
+    #[error("cannot repair maildir folder at {1}")]
+    RepairFolderError(#[source] io::Error, PathBuf),
+
+    #[error("cannot read maildir quota at {1}")]
+    ReadQuotaError(#[source] io::Error, PathBuf),
+    #[error("cannot update maildir quota at {1}")]
+    UpdateQuotaError(#[source] io::Error, PathBuf),
+    #[error("cannot store message of {1} bytes: maildir quota exceeded ({0})")]
+    QuotaExceededError(MaildirQuota, u64),
+
+    #[error("cannot find maildir entry matching stable id {0} in folder {1}")]
+    StableIdNotFoundError(String, String),
+
+    #[error("cannot move maildir entry {0}: entry already in target folder")]
+    MoveEntryToSameFolderError(PathBuf),
+    #[error("cannot move maildir entry {1} to {2}")]
+    MoveEntryError(#[source] io::Error, PathBuf, PathBuf),
+
+    #[cfg(feature = "watch")]
+    #[error("cannot watch maildir folder at {1}")]
+    WatchFolderError(#[source] notify::Error, PathBuf),
 
     #[error(transparent)]
     ExpandPathError(#[from] shellexpand_utils::Error),
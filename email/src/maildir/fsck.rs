@@ -0,0 +1,383 @@
+//! # Maildir consistency check
+//!
+//! Module dedicated to the detection and the repair of anomalies in
+//! Maildir folders, see [`MaildirFsckExt`]. Such anomalies are
+//! usually left by other tools which do not strictly follow the
+//! Maildir and Maildir++ specifications.
+//!
+//! Repairs are safe: files are only renamed, and removed only when
+//! they are byte-for-byte copies of another file of the folder.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use mail_parser::MessageParser;
+use maildirs::Maildir;
+use tracing::{debug, warn};
+
+/// The default age after which a message in `new` is considered
+/// stale.
+///
+/// Mail user agents move messages from `new` to `cur` as soon as
+/// they see them, so messages staying longer in `new` were most
+/// likely delivered by a tool nobody reads the mailbox with.
+pub const NEW_FILE_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// The separator between the unique name and the info of a message
+/// file name.
+#[cfg(unix)]
+const INFO_SEPARATOR: char = ':';
+#[cfg(windows)]
+const INFO_SEPARATOR: char = ';';
+
+/// The anomaly kinds detected by [`MaildirFsckExt::fsck`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MaildirAnomalyKind {
+    /// The file name misses the Maildir++ size field `,S=<size>`.
+    MissingSize,
+
+    /// The file name in `cur` misses the info suffix `:2,<flags>`.
+    MissingInfo,
+
+    /// The file name cannot be interpreted: hidden file, invalid
+    /// UTF-8, unknown info version or invalid flags.
+    InvalidFilename,
+
+    /// Another file of the folder holds a message with the given
+    /// Message-ID.
+    DuplicateMessageId(String),
+
+    /// The file stayed in `new` for the given duration.
+    StaleNew(Duration),
+}
+
+/// The safe actions repairing an anomaly.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MaildirRepair {
+    /// Rename the file, possibly to another directory of the folder.
+    Rename(PathBuf, PathBuf),
+
+    /// Remove the file, which is an exact copy of the given file.
+    RemoveCopyOf(PathBuf, PathBuf),
+}
+
+/// An anomaly detected in a Maildir folder.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaildirAnomaly {
+    /// The path of the file the anomaly was detected on.
+    pub path: PathBuf,
+
+    /// The kind of anomaly.
+    pub kind: MaildirAnomalyKind,
+
+    /// The action repairing the anomaly, if it can be safely
+    /// repaired.
+    pub repair: Option<MaildirRepair>,
+}
+
+/// Extension trait for [`Maildir`] dedicated to consistency checks.
+pub trait MaildirFsckExt {
+    /// Detect anomalies in the `new` and `cur` directories.
+    ///
+    /// Messages staying in `new` longer than the given duration are
+    /// reported as stale, see [`NEW_FILE_MAX_AGE`]. Nothing is
+    /// modified on the file system.
+    fn fsck(&self, new_max_age: Duration) -> io::Result<Vec<MaildirAnomaly>>;
+
+    /// Apply the repair actions of the given anomalies, in order.
+    ///
+    /// Actions are skipped when their source file disappeared (for
+    /// example because a previous action already renamed it) or when
+    /// their target file already exists.
+    ///
+    /// Returns the number of applied actions.
+    fn repair(&self, anomalies: &[MaildirAnomaly]) -> io::Result<usize>;
+}
+
+impl MaildirFsckExt for Maildir {
+    fn fsck(&self, new_max_age: Duration) -> io::Result<Vec<MaildirAnomaly>> {
+        let now = SystemTime::now();
+        let mut anomalies = Vec::new();
+        let mut ids: HashMap<String, PathBuf> = HashMap::new();
+
+        for (dir, is_new) in [(self.new(), true), (self.cur(), false)] {
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            let mut paths = Vec::new();
+
+            for entry in entries {
+                let entry = entry?;
+                if entry.file_type()?.is_file() {
+                    paths.push(entry.path());
+                }
+            }
+
+            // keep reports stable across runs
+            paths.sort();
+
+            for path in paths {
+                let mut anomaly = |kind, repair| {
+                    anomalies.push(MaildirAnomaly {
+                        path: path.clone(),
+                        kind,
+                        repair,
+                    })
+                };
+
+                let Some(name) = parse_file_name(&path) else {
+                    anomaly(MaildirAnomalyKind::InvalidFilename, None);
+                    continue;
+                };
+
+                let metadata = fs::metadata(&path)?;
+
+                let fixed_name = name.to_fixed_string(metadata.len(), !is_new);
+
+                if is_new {
+                    let age = metadata
+                        .modified()
+                        .ok()
+                        .and_then(|modified| now.duration_since(modified).ok())
+                        .unwrap_or_default();
+
+                    if age > new_max_age {
+                        let name = name.to_fixed_string(metadata.len(), true);
+                        let repair = MaildirRepair::Rename(path.clone(), self.cur().join(name));
+                        anomaly(MaildirAnomalyKind::StaleNew(age), Some(repair));
+                    }
+                }
+
+                if name.size.is_none() {
+                    let repair = MaildirRepair::Rename(path.clone(), dir.join(&fixed_name));
+                    anomaly(MaildirAnomalyKind::MissingSize, Some(repair));
+                }
+
+                if !is_new && name.info.is_none() {
+                    let repair = MaildirRepair::Rename(path.clone(), dir.join(&fixed_name));
+                    anomaly(MaildirAnomalyKind::MissingInfo, Some(repair));
+                }
+
+                let Some(id) = read_message_id(&path)? else {
+                    continue;
+                };
+
+                match ids.get(&id) {
+                    None => {
+                        ids.insert(id, path);
+                    }
+                    Some(original) => {
+                        let repair = if fs::read(original)? == fs::read(&path)? {
+                            Some(MaildirRepair::RemoveCopyOf(path.clone(), original.clone()))
+                        } else {
+                            None
+                        };
+                        anomaly(MaildirAnomalyKind::DuplicateMessageId(id), repair);
+                    }
+                }
+            }
+        }
+
+        debug!(path = ?self.path(), "found {} maildir anomalies", anomalies.len());
+
+        Ok(anomalies)
+    }
+
+    fn repair(&self, anomalies: &[MaildirAnomaly]) -> io::Result<usize> {
+        let mut count = 0;
+        let mut renamed: HashMap<&Path, &Path> = HashMap::new();
+
+        for repair in anomalies.iter().filter_map(|a| a.repair.as_ref()) {
+            match repair {
+                MaildirRepair::Rename(from, to) => {
+                    if !from.exists() {
+                        continue;
+                    }
+
+                    if to.exists() {
+                        warn!(?from, ?to, "cannot rename maildir file: target exists");
+                        continue;
+                    }
+
+                    fs::rename(from, to)?;
+                    renamed.insert(from, to);
+                    debug!(?from, ?to, "renamed maildir file");
+                }
+                MaildirRepair::RemoveCopyOf(path, original) => {
+                    let original = match renamed.get(original.as_path()) {
+                        Some(original) => *original,
+                        None => original.as_path(),
+                    };
+
+                    // never remove the last copy of a message
+                    if !original.exists() {
+                        continue;
+                    }
+
+                    match fs::remove_file(path) {
+                        Ok(()) => debug!(?path, ?original, "removed maildir duplicate"),
+                        Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                        Err(err) => return Err(err),
+                    }
+                }
+            }
+
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+/// The parts of a message file name: `<id>[,S=<size>][:2,<flags>]`.
+pub(super) struct FileName<'a> {
+    pub(super) id: &'a str,
+    pub(super) size: Option<u64>,
+    pub(super) info: Option<&'a str>,
+}
+
+impl FileName<'_> {
+    /// Build the file name with the size field and, if requested,
+    /// the info suffix.
+    fn to_fixed_string(&self, size: u64, with_info: bool) -> String {
+        let size = self.size.unwrap_or(size);
+        let mut name = format!("{},S={size}", self.id);
+
+        if let Some(info) = self.info {
+            name.push(INFO_SEPARATOR);
+            name.push_str(info);
+        } else if with_info {
+            name.push(INFO_SEPARATOR);
+            name.push_str("2,");
+        }
+
+        name
+    }
+}
+
+/// Parse the name of the given message file.
+///
+/// Returns `None` if the name is invalid.
+pub(super) fn parse_file_name(path: &Path) -> Option<FileName<'_>> {
+    let name = path.file_name()?.to_str()?;
+
+    if name.is_empty() || name.starts_with('.') {
+        return None;
+    }
+
+    let (unique, info) = match name.split_once(INFO_SEPARATOR) {
+        None => (name, None),
+        Some((unique, info)) => {
+            let flags = info.strip_prefix("2,")?;
+            if !flags.bytes().all(|b| b.is_ascii_alphabetic()) {
+                return None;
+            }
+            (unique, Some(info))
+        }
+    };
+
+    let mut fields = unique.split(',');
+    let id = fields.next().filter(|id| !id.is_empty())?;
+    let mut size = None;
+
+    for field in fields {
+        if let Some(s) = field.strip_prefix("S=") {
+            size = Some(s.parse().ok()?);
+        }
+    }
+
+    Some(FileName { id, size, info })
+}
+
+/// Read the Message-ID of the given message file.
+fn read_message_id(path: &Path) -> io::Result<Option<String>> {
+    let contents = fs::read(path)?;
+    let headers = MessageParser::new().parse_headers(&contents);
+    Ok(headers.and_then(|msg| msg.message_id().map(ToOwned::to_owned)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        fs::{File, FileTimes},
+        time::{Duration, SystemTime},
+    };
+
+    use maildirs::Maildir;
+
+    use super::{MaildirAnomalyKind, MaildirFsckExt, NEW_FILE_MAX_AGE};
+
+    #[test]
+    fn fsck_and_repair() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = Maildir::from(path.clone());
+        mdir.create_all().unwrap();
+
+        let msg = |id: &str| format!("Message-ID: <{id}@localhost>\r\n\r\nHello\r\n");
+
+        // valid file, left untouched
+        let valid = msg("valid");
+        let name = format!("1.valid,S={}:2,S", valid.len());
+        fs::write(mdir.cur().join(&name), &valid).unwrap();
+
+        // missing size and info, plus an exact copy
+        fs::write(mdir.cur().join("2.broken"), msg("broken")).unwrap();
+        fs::write(mdir.cur().join("3.copy,S=41:2,"), msg("broken")).unwrap();
+
+        // stale message in new
+        fs::write(mdir.new().join("4.stale,S=44"), msg("stale")).unwrap();
+        let time = SystemTime::now() - NEW_FILE_MAX_AGE - Duration::from_secs(60);
+        File::options()
+            .write(true)
+            .open(mdir.new().join("4.stale,S=44"))
+            .unwrap()
+            .set_times(FileTimes::new().set_modified(time))
+            .unwrap();
+
+        // invalid info version
+        fs::write(mdir.cur().join("5.invalid:1,"), msg("invalid")).unwrap();
+
+        let anomalies = mdir.fsck(NEW_FILE_MAX_AGE).unwrap();
+        let kinds: Vec<_> = anomalies.iter().map(|a| a.kind.clone()).collect();
+
+        assert_eq!(kinds.len(), 5);
+        assert!(matches!(kinds[0], MaildirAnomalyKind::StaleNew(_)));
+        assert_eq!(kinds[1], MaildirAnomalyKind::MissingSize);
+        assert_eq!(kinds[2], MaildirAnomalyKind::MissingInfo);
+        assert_eq!(
+            kinds[3],
+            MaildirAnomalyKind::DuplicateMessageId("broken@localhost".into())
+        );
+        assert_eq!(kinds[4], MaildirAnomalyKind::InvalidFilename);
+
+        // the missing size and info share the same rename, so the
+        // second one is skipped
+        assert_eq!(mdir.repair(&anomalies).unwrap(), 3);
+
+        let mut names: Vec<_> = fs::read_dir(mdir.cur())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+
+        assert_eq!(
+            names,
+            vec![
+                name,
+                "2.broken,S=41:2,".to_owned(),
+                "4.stale,S=44:2,".to_owned(),
+                "5.invalid:1,".to_owned(),
+            ]
+        );
+        assert_eq!(fs::read_dir(mdir.new()).unwrap().count(), 0);
+    }
+}
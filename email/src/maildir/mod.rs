@@ -1,17 +1,39 @@
+pub mod asynchronous;
 pub mod config;
+pub mod entry;
 mod error;
-
-use std::{ops::Deref, path::PathBuf, sync::Arc};
+pub mod fsck;
+pub mod quota;
+pub mod tmp;
+#[cfg(feature = "watch")]
+pub mod watch;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{self, File, FileTimes},
+    ops::Deref,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
-use maildirs::{Maildir, Maildirs};
+use maildirs::{Flag, Maildir, MaildirEntry, Maildirs};
 use shellexpand_utils::{shellexpand_path, try_shellexpand_path};
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{debug, info, warn};
 
-use self::config::MaildirConfig;
 #[doc(inline)]
-pub use self::error::{Error, Result};
+pub use self::{
+    asynchronous::AsyncMaildir,
+    error::{Error, Result},
+};
+use self::{
+    config::MaildirConfig,
+    fsck::{MaildirAnomaly, MaildirFsckExt},
+    quota::{MaildirQuota, MaildirQuotaExt},
+    tmp::{MaildirTmpExt, TMP_FILE_MAX_AGE},
+};
 #[cfg(feature = "thread")]
 use crate::envelope::thread::{maildir::ThreadMaildirEnvelopes, ThreadEnvelopes};
 #[cfg(feature = "watch")]
@@ -25,9 +47,11 @@
     envelope::{
         get::{maildir::GetMaildirEnvelope, GetEnvelope},
         list::{maildir::ListMaildirEnvelopes, ListEnvelopes},
+        SingleId, StableId, StableIdKind,
     },
     flag::{
         add::{maildir::AddMaildirFlags, AddFlags},
+        mark_read::{maildir::MarkReadBeforeMaildir, MarkReadBefore},
         remove::{maildir::RemoveMaildirFlags, RemoveFlags},
         set::{maildir::SetMaildirFlags, SetFlags},
     },
@@ -79,6 +103,76 @@ pub fn get_maildir_from_folder_alias(&self, folder: &str) -> Result<Maildir> {
         let mdir = self.root.get(folder)?;
         Ok(mdir)
     }
+
+    /// Read the Maildir++ quota.
+    ///
+    /// Returns `None` when no quota is defined. See
+    /// [`MaildirQuotaExt::quota`].
+    pub fn quota(&self) -> Result<Option<MaildirQuota>> {
+        self.root
+            .quota()
+            .map_err(|err| Error::ReadQuotaError(err, self.root.quota_path()))
+    }
+
+    /// Recalculate the Maildir++ quota from the messages of all the
+    /// folders.
+    ///
+    /// See [`MaildirQuotaExt::recalculate_quota`].
+    pub fn recalculate_quota(&self) -> Result<Option<MaildirQuota>> {
+        self.root
+            .recalculate_quota()
+            .map_err(|err| Error::UpdateQuotaError(err, self.root.quota_path()))
+    }
+
+    /// Fail if storing a message of the given size would exceed the
+    /// Maildir++ quota.
+    pub fn check_quota(&self, size: u64) -> Result<()> {
+        match self.quota()? {
+            Some(quota) if quota.would_exceed(size, 1) => {
+                Err(Error::QuotaExceededError(quota, size))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Append the given size and messages count variations to the
+    /// Maildir++ quota.
+    ///
+    /// See [`MaildirQuotaExt::update_quota`].
+    pub fn update_quota(&self, size: i64, count: i64) -> Result<()> {
+        self.root
+            .update_quota(size, count)
+            .map_err(|err| Error::UpdateQuotaError(err, self.root.quota_path()))
+    }
+
+    /// Detect anomalies in all the Maildir folders.
+    ///
+    /// See [`MaildirFsckExt::fsck`].
+    pub fn fsck(&self, new_max_age: Duration) -> Result<Vec<MaildirAnomaly>> {
+        let mut anomalies = Vec::new();
+
+        // the iterator also yields the root folder when it is a
+        // Maildir itself
+        for mdir in self.root.iter().map(|entry| entry.maildir) {
+            let path = mdir.path().to_owned();
+            let found = mdir
+                .fsck(new_max_age)
+                .map_err(|err| Error::CheckFolderConsistencyError(err, path))?;
+            anomalies.extend(found);
+        }
+
+        Ok(anomalies)
+    }
+
+    /// Apply the repair actions of the given anomalies, in order.
+    ///
+    /// See [`MaildirFsckExt::repair`].
+    pub fn repair(&self, anomalies: &[MaildirAnomaly]) -> Result<usize> {
+        let path = self.root.path().to_owned();
+        Maildir::from(path.clone())
+            .repair(anomalies)
+            .map_err(|err| Error::RepairFolderError(err, path))
+    }
 }
 
 /// The sync version of the Maildir backend context.
@@ -104,7 +198,40 @@ fn deref(&self) -> &Self::Target {
     }
 }
 
-impl BackendContext for MaildirContextSync {}
+#[async_trait]
+impl BackendContext for MaildirContextSync {
+    /// Resolve the given stable identifier into a Maildir entry
+    /// identifier.
+    ///
+    /// Only the hash of the entry identifier is stored, so the entry
+    /// is found back by hashing the entries of the folder. Fails if
+    /// no entry matches, for example because the message has been
+    /// moved or deleted since the identifier was built.
+    async fn resolve_stable_id(&self, id: &StableId) -> AnyResult<SingleId> {
+        id.ensure_kind(StableIdKind::Maildir)?;
+
+        let mdir = self
+            .lock()
+            .await
+            .get_maildir_from_folder_alias(id.folder())?;
+
+        let entry_id = AsyncMaildir::from(mdir)
+            .read()
+            .await
+            .map_err(Error::from)?
+            .into_iter()
+            .filter_map(|entry| entry.id().ok().map(ToOwned::to_owned))
+            .find(|entry_id| StableId::maildir_hash(entry_id) == id.id());
+
+        match entry_id {
+            Some(entry_id) => Ok(SingleId::from(entry_id)),
+            None => {
+                let err = Error::StableIdNotFoundError(id.encode(), id.folder().to_owned());
+                Err(err.into())
+            }
+        }
+    }
+}
 
 /// The Maildir backend context builder.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -131,11 +258,33 @@ pub fn expanded_root_dir(&self) -> PathBuf {
     pub fn maildir(&self) -> Maildirs {
         Maildirs::new(self.expanded_root_dir()).with_maildirpp(self.mdir_config.maildirpp)
     }
+
+    /// Remove stale temporary files from all the Maildir folders.
+    ///
+    /// Errors are logged but not propagated, since housekeeping
+    /// should not prevent the context from being built.
+    fn clean_tmp(&self, root: &Maildirs) {
+        let mut count = 0;
+
+        // the iterator also yields the root folder when it is a
+        // Maildir itself
+        for mdir in root.iter().map(|entry| entry.maildir) {
+            match mdir.clean_tmp(TMP_FILE_MAX_AGE) {
+                Ok(n) => count += n,
+                Err(err) => {
+                    let path = mdir.tmp();
+                    warn!(?path, ?err, "cannot clean maildir temporary files");
+                }
+            }
+        }
+
+        debug!("removed {count} stale maildir temporary files");
+    }
 }
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for MaildirContextBuilder {
-    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
+    fn sync_hash(&self, state: &mut crate::sync::hash::SyncHasher) {
         self.mdir_config.sync_hash(state);
     }
 }
@@ -218,6 +367,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveMaildirFlags::some_new_boxed))
     }
 
+    fn mark_read_before(&self) -> Option<BackendFeature<Self::Context, dyn MarkReadBefore>> {
+        Some(Arc::new(MarkReadBeforeMaildir::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddMaildirMessage::some_new_boxed))
     }
@@ -249,10 +402,16 @@ fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMess
     async fn build(self) -> AnyResult<Self::Context> {
         info!("building new maildir context");
 
+        let root = self.maildir();
+
+        if self.mdir_config.clean_tmp {
+            self.clean_tmp(&root);
+        }
+
         let ctx = MaildirContext {
             account_config: self.account_config.clone(),
             maildir_config: self.mdir_config.clone(),
-            root: self.maildir(),
+            root,
         };
 
         Ok(MaildirContextSync {
@@ -309,3 +468,216 @@ pub fn decode_folder(folder: impl AsRef<str> + ToString) -> String {
         .map(|folder| folder.to_string())
         .unwrap_or_else(|_| folder.to_string())
 }
+
+/// Store the given message in the `cur` folder of the given Maildir
+/// with the given flags, as if it was delivered at the given time.
+///
+/// The time is used both as the leading part of the unique file name
+/// and as the modification time of the file. Clients sorting messages
+/// by file name or by file time then keep the chronology of imported
+/// messages, instead of sorting them by import time.
+pub fn store_cur_with_flags_and_time(
+    mdir: &Maildir,
+    data: &[u8],
+    flags: impl IntoIterator<Item = Flag>,
+    time: SystemTime,
+) -> maildirs::Result<MaildirEntry> {
+    let entry = mdir.write_cur(data, flags)?;
+    let entry = rename_with_time(entry, time)?;
+
+    let times = FileTimes::new().set_accessed(time).set_modified(time);
+    File::options()
+        .write(true)
+        .open(entry.path())?
+        .set_times(times)?;
+
+    Ok(entry)
+}
+
+/// Replace the leading delivery time of the unique file name of the
+/// given entry by the given time.
+///
+/// The rest of the unique name holds the device and the inode of the
+/// file, so the renamed entry stays unique. Times before the Unix
+/// epoch cannot be part of a file name, in which case the entry is
+/// returned untouched.
+fn rename_with_time(entry: MaildirEntry, time: SystemTime) -> maildirs::Result<MaildirEntry> {
+    let Ok(secs) = time.duration_since(UNIX_EPOCH) else {
+        return Ok(entry);
+    };
+
+    let Some((_, unique)) = entry.file_name()?.split_once('.') else {
+        return Ok(entry);
+    };
+
+    let path = entry
+        .path()
+        .with_file_name(format!("{}.{unique}", secs.as_secs()));
+    fs::rename(entry.path(), &path)?;
+
+    Ok(MaildirEntry::new(path))
+}
+
+/// Find the entry matching the given identifier in the given
+/// Maildir, either in `new` or in `cur`.
+///
+/// As stated by the Maildir specification, messages in `new` cannot
+/// hold flags. An entry found in `new` is therefore moved to `cur`
+/// and given the informational suffix, so that its flags can be
+/// updated.
+pub fn find_cur_entry(
+    mdir: &Maildir,
+    id: impl AsRef<str>,
+) -> maildirs::Result<Option<MaildirEntry>> {
+    match mdir.find(id)? {
+        Some(entry) => into_cur_entry(mdir, entry).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Find the entries matching the given identifiers in the given
+/// Maildir, indexed by identifier.
+///
+/// Unlike [`Maildir::find`], directories are read only once for all
+/// identifiers. Unknown identifiers are skipped.
+pub fn find_entries(
+    mdir: &Maildir,
+    ids: &[impl AsRef<str>],
+) -> maildirs::Result<HashMap<String, MaildirEntry>> {
+    let ids: HashSet<&str> = ids.iter().map(AsRef::as_ref).collect();
+
+    let entries = mdir
+        .read()?
+        .filter_map(|entry| {
+            let id = entry.id().ok()?.to_owned();
+            ids.contains(id.as_str()).then_some((id, entry))
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Find the entries matching the given identifiers in the given
+/// Maildir, either in `new` or in `cur`, in the order of the given
+/// identifiers.
+///
+/// Batched version of [`find_cur_entry`]: directories are read only
+/// once for all identifiers. Unknown identifiers and entries that
+/// cannot be moved to `cur` are skipped.
+pub fn find_cur_entries(
+    mdir: &Maildir,
+    ids: &[impl AsRef<str>],
+) -> maildirs::Result<Vec<MaildirEntry>> {
+    let mut entries = find_entries(mdir, ids)?;
+
+    let entries = ids
+        .iter()
+        .filter_map(|id| entries.remove(id.as_ref()))
+        .filter_map(|entry| match into_cur_entry(mdir, entry) {
+            Ok(entry) => Some(entry),
+            Err(err) => {
+                debug!(?err, "cannot move maildir entry to cur, skipping it");
+                None
+            }
+        })
+        .collect();
+
+    Ok(entries)
+}
+
+/// Move the given entry from `new` to `cur`, if needed.
+fn into_cur_entry(mdir: &Maildir, entry: MaildirEntry) -> maildirs::Result<MaildirEntry> {
+    let Some(path) = entry.r#move(mdir)? else {
+        return Ok(entry);
+    };
+
+    debug!(?path, "moved maildir entry from new to cur");
+
+    let flags = entry.flags()?;
+    let mut entry = MaildirEntry::new(path);
+    entry.update_flags(flags)?;
+
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        time::{Duration, UNIX_EPOCH},
+    };
+
+    use maildirs::{Flag, Maildir};
+
+    use super::{find_cur_entries, find_cur_entry, store_cur_with_flags_and_time};
+
+    #[test]
+    fn find_cur_entry_from_new() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = Maildir::from(path.clone());
+        mdir.create_all().unwrap();
+
+        let entry = mdir
+            .write_new("From: alice@localhost\r\n\r\nHello")
+            .unwrap();
+        let id = entry.id().unwrap().to_owned();
+        assert_eq!(entry.path().parent(), Some(mdir.new()));
+
+        let mut entry = find_cur_entry(&mdir, &id).unwrap().unwrap();
+        assert_eq!(entry.path().parent(), Some(mdir.cur()));
+        assert_eq!(entry.id().unwrap(), id);
+        assert!(entry.file_name().unwrap().ends_with(":2,"));
+
+        entry.insert_flag(Flag::Seen).unwrap();
+        assert!(entry.file_name().unwrap().ends_with(":2,S"));
+        assert_eq!(fs::read_dir(mdir.new()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn find_cur_entries_in_order() {
+        let root = tempfile::tempdir().unwrap();
+        let mdir = Maildir::from(root.path().to_owned());
+        mdir.create_all().unwrap();
+
+        let new = mdir.write_new("From: alice@localhost\r\n\r\nNew").unwrap();
+        let cur = mdir
+            .write_cur("From: alice@localhost\r\n\r\nCur", [Flag::Seen])
+            .unwrap();
+        let new_id = new.id().unwrap().to_owned();
+        let cur_id = cur.id().unwrap().to_owned();
+
+        let ids = [cur_id.as_str(), "unknown", new_id.as_str()];
+        let entries = find_cur_entries(&mdir, &ids).unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path(), cur.path());
+        assert_eq!(entries[1].id().unwrap(), new_id);
+        assert_eq!(entries[1].path().parent(), Some(mdir.cur()));
+    }
+
+    #[test]
+    fn store_cur_with_time() {
+        let root = tempfile::tempdir().unwrap();
+        let mdir = Maildir::from(root.path().to_owned());
+        mdir.create_all().unwrap();
+
+        let contents = b"From: alice@localhost\r\n\r\nOld";
+        let time = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let entry = store_cur_with_flags_and_time(&mdir, contents, [Flag::Seen], time).unwrap();
+
+        let file_name = entry.file_name().unwrap();
+        assert!(file_name.starts_with("1000000000."));
+        assert!(file_name.ends_with(":2,S"));
+        assert_eq!(entry.path().parent(), Some(mdir.cur()));
+        assert_eq!(entry.read().unwrap(), contents);
+        assert_eq!(
+            fs::metadata(entry.path()).unwrap().modified().unwrap(),
+            time
+        );
+
+        let id = entry.id().unwrap();
+        assert_eq!(mdir.get(id).unwrap().path(), entry.path());
+        assert_eq!(fs::read_dir(mdir.cur()).unwrap().count(), 1);
+    }
+}
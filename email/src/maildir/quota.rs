@@ -0,0 +1,333 @@
+//! # Maildir++ quota
+//!
+//! Module dedicated to the Maildir++ quota, stored in the
+//! `maildirsize` file at the root of the Maildir++ directory, see
+//! [`MaildirQuotaExt`].
+//!
+//! The first line of the file defines the quota, for example
+//! `1000000S,1000C` for one megabyte and one thousand messages. Each
+//! following line holds a size and a message count, separated by a
+//! space. Lines are appended whenever messages are added or removed,
+//! and the current usage is the sum of all lines. The quota is only
+//! enforced when the file exists.
+//!
+//! See <https://www.courier-mta.org/imap/README.maildirquota.html>.
+
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use maildirs::{Maildir, Maildirs};
+use tracing::debug;
+
+use super::fsck::parse_file_name;
+
+/// The name of the quota file.
+pub const MAILDIRSIZE: &str = "maildirsize";
+
+/// The size above which the quota file is recalculated, as defined
+/// by the Maildir++ specification.
+const MAILDIRSIZE_MAX_LEN: u64 = 5120;
+
+/// The age above which the quota file of an over quota Maildir is
+/// recalculated, as defined by the Maildir++ specification.
+const MAILDIRSIZE_MAX_AGE: Duration = Duration::from_secs(15 * 60);
+
+/// The Maildir++ quota.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MaildirQuota {
+    /// The maximum size of all messages, in bytes.
+    pub max_size: Option<u64>,
+
+    /// The maximum number of messages.
+    pub max_count: Option<u64>,
+
+    /// The current size of all messages, in bytes.
+    pub size: u64,
+
+    /// The current number of messages.
+    pub count: u64,
+}
+
+impl MaildirQuota {
+    /// Parse the given quota file contents.
+    pub fn parse(contents: &str) -> Self {
+        let mut lines = contents.lines();
+        let mut quota = Self::default();
+
+        for def in lines.next().unwrap_or_default().split(',') {
+            let def = def.trim();
+            if let Some(size) = def.strip_suffix('S') {
+                quota.max_size = size.parse().ok();
+            } else if let Some(count) = def.strip_suffix('C') {
+                quota.max_count = count.parse().ok();
+            }
+        }
+
+        let (mut size, mut count) = (0i64, 0i64);
+
+        for line in lines {
+            let mut fields = line.split_whitespace();
+            size += fields.next().and_then(|s| s.parse().ok()).unwrap_or(0i64);
+            count += fields.next().and_then(|c| c.parse().ok()).unwrap_or(0i64);
+        }
+
+        quota.size = size.max(0) as u64;
+        quota.count = count.max(0) as u64;
+        quota
+    }
+
+    /// Return the quota definition, as written in the first line of
+    /// the quota file.
+    pub fn definition(&self) -> String {
+        let size = self.max_size.map(|size| format!("{size}S"));
+        let count = self.max_count.map(|count| format!("{count}C"));
+        size.into_iter().chain(count).collect::<Vec<_>>().join(",")
+    }
+
+    /// Return `true` if adding the given size and number of messages
+    /// would exceed the quota.
+    pub fn would_exceed(&self, size: u64, count: u64) -> bool {
+        let size_exceeded = self
+            .max_size
+            .is_some_and(|max| self.size.saturating_add(size) > max);
+        let count_exceeded = self
+            .max_count
+            .is_some_and(|max| self.count.saturating_add(count) > max);
+        size_exceeded || count_exceeded
+    }
+}
+
+impl fmt::Display for MaildirQuota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}S,{}C", self.size, self.count)?;
+
+        let def = self.definition();
+        if !def.is_empty() {
+            write!(f, " out of {def}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Extension trait for [`Maildirs`] dedicated to the Maildir++
+/// quota.
+pub trait MaildirQuotaExt {
+    /// Return the path of the quota file.
+    fn quota_path(&self) -> PathBuf;
+
+    /// Read the quota.
+    ///
+    /// Returns `None` if the quota file does not exist. The quota
+    /// file is recalculated first when it grew too big, or when the
+    /// quota is exceeded and the file is older than 15 minutes.
+    fn quota(&self) -> io::Result<Option<MaildirQuota>>;
+
+    /// Recalculate the quota from the messages of all the folders,
+    /// then rewrite the quota file.
+    ///
+    /// Returns `None` if the quota file does not exist.
+    fn recalculate_quota(&self) -> io::Result<Option<MaildirQuota>>;
+
+    /// Append the given size and messages count variations to the
+    /// quota file.
+    ///
+    /// Does nothing if the quota file does not exist.
+    fn update_quota(&self, size: i64, count: i64) -> io::Result<()>;
+}
+
+impl MaildirQuotaExt for Maildirs {
+    fn quota_path(&self) -> PathBuf {
+        self.path().join(MAILDIRSIZE)
+    }
+
+    fn quota(&self) -> io::Result<Option<MaildirQuota>> {
+        let path = self.quota_path();
+
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        let age = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .unwrap_or_default();
+
+        if metadata.len() > MAILDIRSIZE_MAX_LEN {
+            debug!(?path, "maildir quota file too big, recalculating it");
+            return self.recalculate_quota();
+        }
+
+        let contents = fs::read_to_string(&path)?;
+        let quota = MaildirQuota::parse(&contents);
+
+        // the quota may be exceeded because of outdated variations,
+        // for example when messages have been removed by another
+        // client not aware of the quota file
+        if age > MAILDIRSIZE_MAX_AGE && quota.would_exceed(0, 0) {
+            debug!(?path, "maildir over quota, recalculating old quota file");
+            return self.recalculate_quota();
+        }
+
+        Ok(Some(quota))
+    }
+
+    fn recalculate_quota(&self) -> io::Result<Option<MaildirQuota>> {
+        let path = self.quota_path();
+
+        let mut quota = match fs::read_to_string(&path) {
+            Ok(contents) => MaildirQuota::parse(&contents),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        quota.size = 0;
+        quota.count = 0;
+
+        let mut mdirs: Vec<Maildir> = self.iter().map(|entry| entry.maildir).collect();
+
+        // the root folder is the inbox of Maildir++ directories
+        let root = Maildir::from(self.path().to_owned());
+        if root.exists() && !mdirs.iter().any(|mdir| mdir.path() == root.path()) {
+            mdirs.push(root);
+        }
+
+        for mdir in mdirs {
+            let (size, count) = maildir_usage(&mdir)?;
+            quota.size += size;
+            quota.count += count;
+        }
+
+        // write to a temporary file first, so that concurrent readers
+        // never see a partial quota file
+        let tmp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let contents = format!("{}\n{} {}\n", quota.definition(), quota.size, quota.count);
+        fs::write(&tmp_path, contents)?;
+        fs::rename(&tmp_path, &path)?;
+
+        debug!(?path, %quota, "recalculated maildir quota");
+
+        Ok(Some(quota))
+    }
+
+    fn update_quota(&self, size: i64, count: i64) -> io::Result<()> {
+        let path = self.quota_path();
+
+        let mut file = match fs::OpenOptions::new().append(true).open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        // a single write keeps the line atomic for concurrent writers
+        file.write_all(format!("{size} {count}\n").as_bytes())
+    }
+}
+
+/// Return the size of the given message file.
+///
+/// The size is taken from the Maildir++ size field `,S=<size>` of
+/// the file name if any, otherwise from the file metadata.
+pub fn message_size(path: &Path) -> io::Result<u64> {
+    match parse_file_name(path).and_then(|name| name.size) {
+        Some(size) => Ok(size),
+        None => Ok(fs::metadata(path)?.len()),
+    }
+}
+
+/// Compute the size and the number of messages of the given Maildir
+/// folder.
+fn maildir_usage(mdir: &Maildir) -> io::Result<(u64, u64)> {
+    let (mut size, mut count) = (0, 0);
+
+    for dir in [mdir.new(), mdir.cur()] {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+
+            let hidden = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map_or(true, |name| name.starts_with('.'));
+
+            if hidden || !path.is_file() {
+                continue;
+            }
+
+            size += message_size(&path)?;
+            count += 1;
+        }
+    }
+
+    Ok((size, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use maildirs::{Maildir, Maildirs};
+
+    use super::{MaildirQuota, MaildirQuotaExt};
+
+    #[test]
+    fn parse() {
+        let quota = MaildirQuota::parse("1000S,10C\n100 2\n50 1\n-30 -1\n");
+
+        assert_eq!(quota.max_size, Some(1000));
+        assert_eq!(quota.max_count, Some(10));
+        assert_eq!(quota.size, 120);
+        assert_eq!(quota.count, 2);
+        assert_eq!(quota.definition(), "1000S,10C");
+
+        assert!(!quota.would_exceed(880, 8));
+        assert!(quota.would_exceed(881, 1));
+        assert!(quota.would_exceed(1, 9));
+    }
+
+    #[test]
+    fn update_and_recalculate() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().to_owned();
+        let root = Maildirs::new(&path).with_maildirpp(true);
+        Maildir::from(path.clone()).create_all().unwrap();
+
+        // without quota file, the quota is disabled
+        root.update_quota(10, 1).unwrap();
+        assert_eq!(root.quota().unwrap(), None);
+
+        fs::write(root.quota_path(), "100S\n").unwrap();
+        Maildir::from(path.clone()).write_new("hello").unwrap();
+        root.create("Archives")
+            .unwrap()
+            .write_cur("world!", [])
+            .unwrap();
+
+        root.update_quota(42, 1).unwrap();
+        assert_eq!(root.quota().unwrap().unwrap().size, 42);
+
+        let quota = root.recalculate_quota().unwrap().unwrap();
+        assert_eq!(quota.max_size, Some(100));
+        assert_eq!(quota.size, 11);
+        assert_eq!(quota.count, 2);
+        assert_eq!(root.quota().unwrap(), Some(quota));
+
+        // an oversized quota file is recalculated, whatever its age
+        for _ in 0..2000 {
+            root.update_quota(1, 0).unwrap();
+        }
+        assert_eq!(root.quota().unwrap().unwrap().size, 11);
+    }
+}
@@ -0,0 +1,139 @@
+//! # Maildir temporary files
+//!
+//! Module dedicated to the garbage collection of the Maildir `tmp`
+//! directory. A message is first written in `tmp`, then moved to
+//! `new` or `cur`. When the writing process crashes in between, the
+//! temporary file stays in `tmp` forever.
+//!
+//! The Maildir specification states that a file in `tmp` which has
+//! not been accessed for 36 hours can be safely removed, see
+//! [`TMP_FILE_MAX_AGE`].
+
+use std::{
+    fs, io,
+    time::{Duration, SystemTime},
+};
+
+use maildirs::Maildir;
+use tracing::{debug, trace};
+
+/// The age after which a temporary file is considered stale, as
+/// defined by the Maildir specification.
+pub const TMP_FILE_MAX_AGE: Duration = Duration::from_secs(36 * 60 * 60);
+
+/// Extension trait for [`Maildir`] dedicated to the `tmp` directory.
+pub trait MaildirTmpExt {
+    /// Remove temporary files older than the given duration.
+    ///
+    /// The age of a file is computed from its most recent access or
+    /// modification time. Only regular files are removed: symlinks
+    /// and directories are left untouched. Durations shorter than
+    /// [`TMP_FILE_MAX_AGE`] are raised to it, so that files being
+    /// written by another process are never removed.
+    ///
+    /// Returns the number of removed files.
+    fn clean_tmp(&self, older_than: Duration) -> io::Result<usize>;
+}
+
+impl MaildirTmpExt for Maildir {
+    fn clean_tmp(&self, older_than: Duration) -> io::Result<usize> {
+        let older_than = older_than.max(TMP_FILE_MAX_AGE);
+        let now = SystemTime::now();
+        let mut count = 0;
+
+        let entries = match fs::read_dir(self.tmp()) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+
+            // symlink_metadata does not follow symlinks
+            let metadata = match fs::symlink_metadata(&path) {
+                Ok(metadata) => metadata,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let modified = metadata.modified()?;
+            let last_used = match metadata.accessed() {
+                Ok(accessed) => accessed.max(modified),
+                Err(_) => modified,
+            };
+
+            // a last use time in the future means the file is not
+            // stale
+            let Ok(age) = now.duration_since(last_used) else {
+                continue;
+            };
+
+            if age < older_than {
+                trace!(?path, ?age, "keeping recent temporary file");
+                continue;
+            }
+
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    debug!(?path, ?age, "removed stale temporary file");
+                    count += 1;
+                }
+                // another process moved or removed it in between
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File, FileTimes},
+        time::{Duration, SystemTime},
+    };
+
+    use maildirs::Maildir;
+
+    use super::{MaildirTmpExt, TMP_FILE_MAX_AGE};
+
+    #[test]
+    fn clean_stale_tmp_files() {
+        let root = tempfile::tempdir().unwrap();
+        let path = root.path().to_owned();
+        let mdir = Maildir::from(path.clone());
+        mdir.create_all().unwrap();
+
+        let stale = mdir.tmp().join("stale");
+        let recent = mdir.tmp().join("recent");
+        let dir = mdir.tmp().join("dir");
+
+        fs::write(&stale, "stale").unwrap();
+        fs::write(&recent, "recent").unwrap();
+        fs::create_dir(&dir).unwrap();
+
+        let time = SystemTime::now() - TMP_FILE_MAX_AGE - Duration::from_secs(60);
+        let times = FileTimes::new().set_accessed(time).set_modified(time);
+        File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_times(times)
+            .unwrap();
+
+        // shorter durations are raised to the maximum age
+        assert_eq!(mdir.clean_tmp(Duration::ZERO).unwrap(), 1);
+
+        assert!(!stale.exists());
+        assert!(recent.exists());
+        assert!(dir.exists());
+    }
+}
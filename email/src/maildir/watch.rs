@@ -0,0 +1,282 @@
+//! # Maildir watcher
+//!
+//! Module dedicated to the watching of Maildir folders. The
+//! [`MaildirWatcher`] monitors the `new` and `cur` directories of a
+//! folder, and turns filesystem changes into [`MaildirEvent`]s:
+//! messages added, messages removed, and flags changed (Maildir flags
+//! live in file names, so changing them renames files).
+//!
+//! Events are received asynchronously using the [`MaildirWatch`] as
+//! a [`Stream`], which does not depend on any async runtime.
+//!
+//! Native filesystem notifications are used when available. When
+//! they are not (network filesystems, inotify watches limit reached
+//! etc), directories are polled instead.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    pin::Pin,
+    sync::mpsc,
+    task::{Context, Poll},
+    thread,
+    time::Duration,
+};
+
+use futures::{
+    channel::mpsc::{unbounded, UnboundedReceiver},
+    executor, Stream, StreamExt,
+};
+use maildirs::{Flag, Maildir, MaildirEntry};
+use notify::{Config, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use tracing::{debug, trace, warn};
+
+use super::{Error, Result};
+
+/// The default interval between two polls, used when native
+/// filesystem notifications are not available.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The Maildir event, emitted by the [`MaildirWatch`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MaildirEvent {
+    /// The given entry has been added to the folder.
+    MessageAdded(MaildirEntry),
+
+    /// The flags of the given entry changed.
+    FlagsChanged(MaildirEntry),
+
+    /// The entry matching the given identifier has been removed from
+    /// the folder.
+    MessageRemoved(String),
+}
+
+/// The Maildir watcher builder.
+#[derive(Clone, Debug)]
+pub struct MaildirWatcher {
+    mdir: Maildir,
+    poll_interval: Duration,
+}
+
+impl MaildirWatcher {
+    /// Create a new watcher for the given Maildir folder.
+    pub fn new(mdir: Maildir) -> Self {
+        Self {
+            mdir,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Set the interval between two polls, used when native
+    /// filesystem notifications are not available.
+    pub fn set_poll_interval(&mut self, interval: Duration) {
+        self.poll_interval = interval;
+    }
+
+    /// Set the poll interval, using the builder pattern.
+    pub fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.set_poll_interval(interval);
+        self
+    }
+
+    /// Start watching the Maildir folder.
+    ///
+    /// Changes are detected from the moment this function is called.
+    /// The folder is watched until the returned [`MaildirWatch`] is
+    /// dropped.
+    pub fn watch(self) -> Result<MaildirWatch> {
+        let mut snapshot = Snapshot::read(&self.mdir)?;
+        let dirs = [self.mdir.new().to_owned(), self.mdir.cur().to_owned()];
+
+        let (notify_tx, notify_rx) = mpsc::channel();
+        let watcher = match watch_natively(notify_tx.clone(), &dirs) {
+            Ok(watcher) => watcher,
+            Err(err) => {
+                warn!(?err, "cannot watch maildir natively, polling it instead");
+                let config = Config::default().with_poll_interval(self.poll_interval);
+                let mut watcher = PollWatcher::new(notify_tx, config)
+                    .map_err(|err| Error::WatchFolderError(err, self.mdir.path().to_owned()))?;
+                for dir in &dirs {
+                    watcher
+                        .watch(dir, RecursiveMode::NonRecursive)
+                        .map_err(|err| Error::WatchFolderError(err, dir.clone()))?;
+                }
+                Box::new(watcher) as Box<dyn Watcher + Send>
+            }
+        };
+
+        let (tx, rx) = unbounded();
+        let mdir = self.mdir;
+
+        // the loop ends when the watcher is dropped, since it owns
+        // the sender of the notify channel
+        thread::spawn(move || {
+            for res in notify_rx {
+                match res {
+                    Ok(evt) => trace!(?evt, "received maildir change event"),
+                    Err(err) => {
+                        debug!(?err, "error while watching maildir, skipping it");
+                        continue;
+                    }
+                }
+
+                let next = match Snapshot::read(&mdir) {
+                    Ok(next) => next,
+                    Err(err) => {
+                        debug!(?err, "cannot read maildir entries, skipping change");
+                        continue;
+                    }
+                };
+
+                for evt in snapshot.diff(&next) {
+                    if tx.unbounded_send(evt).is_err() {
+                        return;
+                    }
+                }
+
+                snapshot = next;
+            }
+        });
+
+        Ok(MaildirWatch {
+            events: rx,
+            _watcher: watcher,
+        })
+    }
+}
+
+/// The running Maildir watch.
+///
+/// Events are received asynchronously by using the watch as a
+/// [`Stream`], or synchronously using [`MaildirWatch::recv`]. The
+/// folder stops being watched once the watch is dropped.
+pub struct MaildirWatch {
+    events: UnboundedReceiver<MaildirEvent>,
+    _watcher: Box<dyn Watcher + Send>,
+}
+
+impl MaildirWatch {
+    /// Block until the next event is received.
+    ///
+    /// Must not be called from an async task, use the watch as a
+    /// [`Stream`] instead.
+    pub fn recv(&mut self) -> Option<MaildirEvent> {
+        executor::block_on(self.events.next())
+    }
+
+    /// Return the next event if any, without blocking.
+    pub fn try_recv(&mut self) -> Option<MaildirEvent> {
+        self.events.try_next().ok().flatten()
+    }
+}
+
+impl Stream for MaildirWatch {
+    type Item = MaildirEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().events.poll_next_unpin(cx)
+    }
+}
+
+fn watch_natively(
+    tx: mpsc::Sender<notify::Result<notify::Event>>,
+    dirs: &[PathBuf],
+) -> notify::Result<Box<dyn Watcher + Send>> {
+    let mut watcher = RecommendedWatcher::new(tx, Config::default())?;
+    for dir in dirs {
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    }
+    Ok(Box::new(watcher))
+}
+
+/// The entries of a Maildir folder at a given time, indexed by
+/// identifier.
+struct Snapshot(HashMap<String, (MaildirEntry, HashSet<Flag>)>);
+
+impl Snapshot {
+    fn read(mdir: &Maildir) -> Result<Self> {
+        let entries = mdir
+            .read()?
+            .filter_map(|entry| {
+                let id = entry.id().ok()?.to_owned();
+                let flags = entry.flags().ok()?;
+                Some((id, (entry, flags)))
+            })
+            .collect();
+
+        Ok(Self(entries))
+    }
+
+    /// Compute the events leading from this snapshot to the given
+    /// one, sorted by identifier.
+    fn diff(&self, next: &Snapshot) -> Vec<MaildirEvent> {
+        let mut ids: Vec<&String> = self.0.keys().chain(next.0.keys()).collect();
+        ids.sort();
+        ids.dedup();
+
+        ids.into_iter()
+            .filter_map(|id| match (self.0.get(id), next.0.get(id)) {
+                (None, Some((entry, _))) => Some(MaildirEvent::MessageAdded(entry.clone())),
+                (Some(_), None) => Some(MaildirEvent::MessageRemoved(id.clone())),
+                (Some((_, prev_flags)), Some((entry, flags))) if prev_flags != flags => {
+                    Some(MaildirEvent::FlagsChanged(entry.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, time::Duration};
+
+    use futures::StreamExt;
+    use maildirs::{Flag, Maildir};
+
+    use super::{MaildirEvent, MaildirWatcher, Snapshot};
+
+    #[tokio::test]
+    async fn diff() {
+        let root = tempfile::tempdir().unwrap();
+        let mdir = Maildir::from(root.path().to_owned());
+        mdir.create_all().unwrap();
+
+        let removed = mdir.write_new("removed").unwrap();
+        let mut changed = mdir.write_cur("changed", []).unwrap();
+        let prev = Snapshot::read(&mdir).unwrap();
+
+        fs::remove_file(removed.path()).unwrap();
+        changed.insert_flag(Flag::Seen).unwrap();
+        let added = mdir.write_new("added").unwrap();
+        let next = Snapshot::read(&mdir).unwrap();
+
+        let mut events = prev.diff(&next);
+        events.sort_by_key(|evt| match evt {
+            MaildirEvent::MessageAdded(_) => 0,
+            MaildirEvent::FlagsChanged(_) => 1,
+            MaildirEvent::MessageRemoved(_) => 2,
+        });
+
+        assert_eq!(
+            events,
+            vec![
+                MaildirEvent::MessageAdded(added),
+                MaildirEvent::FlagsChanged(changed),
+                MaildirEvent::MessageRemoved(removed.id().unwrap().to_owned()),
+            ]
+        );
+
+        // polling fallback and native watchers both emit events
+        let mut watch = MaildirWatcher::new(mdir.clone())
+            .with_poll_interval(Duration::from_millis(50))
+            .watch()
+            .unwrap();
+        let entry = mdir.write_new("watched").unwrap();
+        let evt = tokio::time::timeout(Duration::from_secs(5), watch.next())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(evt, MaildirEvent::MessageAdded(entry));
+    }
+}
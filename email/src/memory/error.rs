@@ -0,0 +1,35 @@
+use std::{any::Any, result};
+
+use thiserror::Error;
+
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot find memory folder {0}")]
+    FolderNotFoundError(String),
+    #[error("cannot create memory folder {0}: folder already exists")]
+    FolderAlreadyExistsError(String),
+    #[error("cannot delete memory inbox folder")]
+    DeleteInboxForbiddenError,
+    #[error("cannot find memory message {1} from folder {0}")]
+    MessageNotFoundError(String, String),
+    #[error("cannot get memory envelopes from folder {0}: page {1} out of bounds")]
+    GetEnvelopesOutOfBoundsError(String, usize),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
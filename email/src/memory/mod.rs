@@ -0,0 +1,520 @@
+//! # Memory backend
+//!
+//! Module dedicated to the in-memory backend. Folders, envelopes,
+//! flags and messages live in a [`MemoryStore`] backed by maps, so
+//! this backend never touches the filesystem nor the network.
+//!
+//! It is useful for tests and for environments without filesystem,
+//! and it serves as a reference implementation of backend features
+//! semantics.
+//!
+//! Messages are identified by a numeric id, unique across the whole
+//! store. Like the Maildir backend, operations targeting ids that do
+//! not exist are ignored.
+
+mod error;
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, FixedOffset};
+use tracing::info;
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use crate::{
+    account::config::AccountConfig,
+    backend::{
+        context::{BackendContext, BackendContextBuilder},
+        feature::BackendFeature,
+    },
+    envelope::{
+        get::{memory::GetMemoryEnvelope, GetEnvelope},
+        list::{memory::ListMemoryEnvelopes, ListEnvelopes},
+        Envelope, Id, SingleId,
+    },
+    flag::{
+        add::{memory::AddMemoryFlags, AddFlags},
+        remove::{memory::RemoveMemoryFlags, RemoveFlags},
+        set::{memory::SetMemoryFlags, SetFlags},
+        Flags,
+    },
+    folder::{
+        add::{memory::AddMemoryFolder, AddFolder},
+        delete::{memory::DeleteMemoryFolder, DeleteFolder},
+        expunge::{memory::ExpungeMemoryFolder, ExpungeFolder},
+        list::{memory::ListMemoryFolders, ListFolders},
+        purge::{memory::PurgeMemoryFolder, PurgeFolder},
+        FolderKind, INBOX,
+    },
+    message::{
+        add::{memory::AddMemoryMessage, AddMessage},
+        copy::{memory::CopyMemoryMessages, CopyMessages},
+        delete::{memory::DeleteMemoryMessages, DeleteMessages},
+        get::{memory::GetMemoryMessages, GetMessages},
+        peek::{memory::PeekMemoryMessages, PeekMessages},
+        r#move::{memory::MoveMemoryMessages, MoveMessages},
+        remove::{memory::RemoveMemoryMessages, RemoveMessages},
+        Message,
+    },
+    AnyResult,
+};
+
+/// The message stored in memory.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryMessage {
+    /// The flags of the message.
+    pub flags: Flags,
+
+    /// The raw content of the message.
+    pub raw: Vec<u8>,
+
+    /// The date the message entered its folder, if known.
+    pub saved_at: Option<DateTime<FixedOffset>>,
+}
+
+impl MemoryMessage {
+    pub fn new(raw: impl Into<Vec<u8>>, flags: Flags) -> Self {
+        Self {
+            flags,
+            raw: raw.into(),
+            saved_at: None,
+        }
+    }
+
+    /// Set the date the message entered its folder.
+    pub fn with_saved_at(mut self, saved_at: DateTime<FixedOffset>) -> Self {
+        self.saved_at = Some(saved_at);
+        self
+    }
+
+    /// Build the envelope of the message.
+    pub fn to_envelope(&self, id: impl ToString) -> Envelope {
+        let msg = Message::from(self.raw.as_slice());
+        let mut envelope = Envelope::from_msg(id, self.flags.clone(), msg);
+        envelope.saved_at = self.saved_at;
+        envelope
+    }
+}
+
+/// The folder stored in memory.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemoryFolder {
+    messages: BTreeMap<u64, MemoryMessage>,
+}
+
+impl MemoryFolder {
+    /// Get the message matching the given id.
+    pub fn get(&self, id: &str) -> Option<&MemoryMessage> {
+        self.messages.get(&id.parse().ok()?)
+    }
+
+    /// Get the mutable message matching the given id.
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut MemoryMessage> {
+        self.messages.get_mut(&id.parse().ok()?)
+    }
+
+    /// Remove the message matching the given id.
+    pub fn remove(&mut self, id: &str) -> Option<MemoryMessage> {
+        self.messages.remove(&id.parse().ok()?)
+    }
+
+    /// Remove messages not matching the given predicate.
+    pub fn retain(&mut self, mut f: impl FnMut(&MemoryMessage) -> bool) {
+        self.messages.retain(|_, msg| f(msg))
+    }
+
+    /// Iterate over messages and their id, in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (String, &MemoryMessage)> {
+        self.messages.iter().map(|(id, msg)| (id.to_string(), msg))
+    }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+}
+
+/// The in-memory store.
+///
+/// The store always contains the inbox folder, which cannot be
+/// deleted. Folder names matching the inbox are case-insensitive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MemoryStore {
+    folders: BTreeMap<String, MemoryFolder>,
+    next_id: u64,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self {
+            folders: BTreeMap::from_iter([(INBOX.to_owned(), MemoryFolder::default())]),
+            next_id: 1,
+        }
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn key(folder: &str) -> String {
+        if FolderKind::matches_inbox(folder) {
+            INBOX.to_owned()
+        } else {
+            folder.to_owned()
+        }
+    }
+
+    /// Iterate over folder names.
+    pub fn folders(&self) -> impl Iterator<Item = &str> {
+        self.folders.keys().map(String::as_str)
+    }
+
+    /// Get the folder matching the given name.
+    pub fn folder(&self, folder: &str) -> Result<&MemoryFolder> {
+        self.folders
+            .get(&Self::key(folder))
+            .ok_or_else(|| Error::FolderNotFoundError(folder.to_owned()))
+    }
+
+    /// Get the mutable folder matching the given name.
+    pub fn folder_mut(&mut self, folder: &str) -> Result<&mut MemoryFolder> {
+        self.folders
+            .get_mut(&Self::key(folder))
+            .ok_or_else(|| Error::FolderNotFoundError(folder.to_owned()))
+    }
+
+    /// Create a new empty folder.
+    pub fn add_folder(&mut self, folder: &str) -> Result<()> {
+        let key = Self::key(folder);
+
+        if self.folders.contains_key(&key) {
+            return Err(Error::FolderAlreadyExistsError(folder.to_owned()));
+        }
+
+        self.folders.insert(key, MemoryFolder::default());
+        Ok(())
+    }
+
+    /// Delete the given folder and its messages.
+    pub fn delete_folder(&mut self, folder: &str) -> Result<()> {
+        let key = Self::key(folder);
+
+        if key == INBOX {
+            return Err(Error::DeleteInboxForbiddenError);
+        }
+
+        match self.folders.remove(&key) {
+            Some(_) => Ok(()),
+            None => Err(Error::FolderNotFoundError(folder.to_owned())),
+        }
+    }
+
+    /// Add the given message to the given folder, and return its
+    /// newly assigned id.
+    pub fn add_message(&mut self, folder: &str, msg: MemoryMessage) -> Result<SingleId> {
+        let id = self.next_id;
+        self.folder_mut(folder)?.messages.insert(id, msg);
+        self.next_id += 1;
+        Ok(SingleId::from(id))
+    }
+
+    /// Copy messages matching the given id from a folder to another
+    /// one. Copies get new ids, and are saved at the given date.
+    pub fn copy_messages(
+        &mut self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+        saved_at: DateTime<FixedOffset>,
+    ) -> Result<()> {
+        let from = self.folder(from_folder)?;
+        let msgs: Vec<_> = id.iter().filter_map(|id| from.get(id)).cloned().collect();
+
+        // fail before adding anything if the target does not exist
+        self.folder(to_folder)?;
+
+        for msg in msgs {
+            self.add_message(to_folder, msg.with_saved_at(saved_at))?;
+        }
+
+        Ok(())
+    }
+
+    /// Move messages matching the given id from a folder to another
+    /// one. Moved messages get new ids, and are saved at the given
+    /// date.
+    pub fn move_messages(
+        &mut self,
+        from_folder: &str,
+        to_folder: &str,
+        id: &Id,
+        saved_at: DateTime<FixedOffset>,
+    ) -> Result<()> {
+        self.copy_messages(from_folder, to_folder, id, saved_at)?;
+
+        let from = self.folder_mut(from_folder)?;
+        for id in id.iter() {
+            from.remove(id);
+        }
+
+        Ok(())
+    }
+}
+
+/// The memory backend context.
+///
+/// The store is wrapped into a mutex, so the same store can be shared
+/// and updated across multiple threads.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryContext {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryContext {
+    /// Lock the store.
+    ///
+    /// The store is only mutated through its own methods, which
+    /// leave it consistent, so a poisoned lock is recovered.
+    pub fn lock(&self) -> MutexGuard<'_, MemoryStore> {
+        self.store.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+pub type MemoryContextSync = MemoryContext;
+
+impl BackendContext for MemoryContextSync {}
+
+/// The memory backend context builder.
+///
+/// Clones of the builder share the same store, as well as the
+/// contexts they build.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryContextBuilder {
+    /// The account configuration.
+    pub account_config: Arc<AccountConfig>,
+
+    store: Arc<Mutex<MemoryStore>>,
+}
+
+impl MemoryContextBuilder {
+    pub fn new(account_config: Arc<AccountConfig>) -> Self {
+        Self {
+            account_config,
+            store: Default::default(),
+        }
+    }
+
+    pub fn set_store(&mut self, store: MemoryStore) {
+        self.store = Arc::new(Mutex::new(store));
+    }
+
+    pub fn with_store(mut self, store: MemoryStore) -> Self {
+        self.set_store(store);
+        self
+    }
+
+    /// Get a snapshot of the store.
+    pub fn store(&self) -> MemoryStore {
+        self.store
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+#[async_trait]
+impl BackendContextBuilder for MemoryContextBuilder {
+    type Context = MemoryContextSync;
+
+    fn add_folder(&self) -> Option<BackendFeature<Self::Context, dyn AddFolder>> {
+        Some(Arc::new(AddMemoryFolder::some_new_boxed))
+    }
+
+    fn list_folders(&self) -> Option<BackendFeature<Self::Context, dyn ListFolders>> {
+        Some(Arc::new(ListMemoryFolders::some_new_boxed))
+    }
+
+    fn expunge_folder(&self) -> Option<BackendFeature<Self::Context, dyn ExpungeFolder>> {
+        Some(Arc::new(ExpungeMemoryFolder::some_new_boxed))
+    }
+
+    fn purge_folder(&self) -> Option<BackendFeature<Self::Context, dyn PurgeFolder>> {
+        Some(Arc::new(PurgeMemoryFolder::some_new_boxed))
+    }
+
+    fn delete_folder(&self) -> Option<BackendFeature<Self::Context, dyn DeleteFolder>> {
+        Some(Arc::new(DeleteMemoryFolder::some_new_boxed))
+    }
+
+    fn get_envelope(&self) -> Option<BackendFeature<Self::Context, dyn GetEnvelope>> {
+        Some(Arc::new(GetMemoryEnvelope::some_new_boxed))
+    }
+
+    fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelopes>> {
+        Some(Arc::new(ListMemoryEnvelopes::some_new_boxed))
+    }
+
+    fn add_flags(&self) -> Option<BackendFeature<Self::Context, dyn AddFlags>> {
+        Some(Arc::new(AddMemoryFlags::some_new_boxed))
+    }
+
+    fn set_flags(&self) -> Option<BackendFeature<Self::Context, dyn SetFlags>> {
+        Some(Arc::new(SetMemoryFlags::some_new_boxed))
+    }
+
+    fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>> {
+        Some(Arc::new(RemoveMemoryFlags::some_new_boxed))
+    }
+
+    fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
+        Some(Arc::new(AddMemoryMessage::some_new_boxed))
+    }
+
+    fn peek_messages(&self) -> Option<BackendFeature<Self::Context, dyn PeekMessages>> {
+        Some(Arc::new(PeekMemoryMessages::some_new_boxed))
+    }
+
+    fn get_messages(&self) -> Option<BackendFeature<Self::Context, dyn GetMessages>> {
+        Some(Arc::new(GetMemoryMessages::some_new_boxed))
+    }
+
+    fn copy_messages(&self) -> Option<BackendFeature<Self::Context, dyn CopyMessages>> {
+        Some(Arc::new(CopyMemoryMessages::some_new_boxed))
+    }
+
+    fn move_messages(&self) -> Option<BackendFeature<Self::Context, dyn MoveMessages>> {
+        Some(Arc::new(MoveMemoryMessages::some_new_boxed))
+    }
+
+    fn delete_messages(&self) -> Option<BackendFeature<Self::Context, dyn DeleteMessages>> {
+        Some(Arc::new(DeleteMemoryMessages::some_new_boxed))
+    }
+
+    fn remove_messages(&self) -> Option<BackendFeature<Self::Context, dyn RemoveMessages>> {
+        Some(Arc::new(RemoveMemoryMessages::some_new_boxed))
+    }
+
+    async fn build(self) -> AnyResult<Self::Context> {
+        info!("building new memory context");
+
+        Ok(MemoryContextSync {
+            account_config: self.account_config,
+            store: self.store,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use mail_builder::MessageBuilder;
+
+    use super::MemoryContextBuilder;
+    use crate::{
+        account::config::AccountConfig,
+        backend::BackendBuilder,
+        envelope::{get::GetEnvelope, list::ListEnvelopes, Id},
+        flag::{add::AddFlags, remove::RemoveFlags, Flag},
+        folder::{
+            add::AddFolder, delete::DeleteFolder, expunge::ExpungeFolder, list::ListFolders,
+            FolderKind,
+        },
+        message::{
+            add::AddMessage, copy::CopyMessages, delete::DeleteMessages, peek::PeekMessages,
+            r#move::MoveMessages,
+        },
+    };
+
+    #[tokio::test]
+    async fn memory_features() {
+        let account_config = Arc::new(AccountConfig::default());
+        let ctx = MemoryContextBuilder::new(account_config.clone());
+        let backend = BackendBuilder::new(account_config, ctx.clone())
+            .build()
+            .await
+            .unwrap();
+
+        // folders
+
+        backend.add_folder("Archives").await.unwrap();
+        backend.add_folder("Trash").await.unwrap();
+        assert!(backend.add_folder("Archives").await.is_err());
+        assert!(backend.delete_folder("inbox").await.is_err());
+
+        let folders = backend.list_folders().await.unwrap();
+        let names: Vec<_> = folders.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["Archives", "INBOX", "Trash"]);
+        assert_eq!(folders[1].kind, Some(FolderKind::Inbox));
+
+        // messages and envelopes
+
+        let msg = MessageBuilder::new()
+            .from("alice@localhost")
+            .to("bob@localhost")
+            .subject("Plain message!")
+            .text_body("Plain message!")
+            .write_to_vec()
+            .unwrap();
+
+        let id = backend
+            .add_message_with_flag("inbox", &msg, Flag::Seen)
+            .await
+            .unwrap();
+
+        let envelope = backend.get_envelope("INBOX", &id).await.unwrap();
+        assert_eq!(envelope.from.addr, "alice@localhost");
+        assert_eq!(envelope.subject, "Plain message!");
+        assert!(envelope.flags.contains(&Flag::Seen));
+
+        let msgs = backend.peek_messages("INBOX", &id.clone().into()).await;
+        assert_eq!(msgs.unwrap().to_vec()[0].raw().unwrap(), msg);
+
+        // flags
+
+        let id = Id::from(id);
+        backend.add_flag("INBOX", &id, Flag::Flagged).await.unwrap();
+        backend.remove_flag("INBOX", &id, Flag::Seen).await.unwrap();
+
+        let envelopes = backend
+            .list_envelopes("INBOX", Default::default())
+            .await
+            .unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert!(envelopes[0].flags.contains(&Flag::Flagged));
+        assert!(!envelopes[0].flags.contains(&Flag::Seen));
+
+        // copy, move, delete and expunge
+
+        backend
+            .copy_messages("INBOX", "Archives", &id)
+            .await
+            .unwrap();
+        backend.move_messages("INBOX", "Trash", &id).await.unwrap();
+
+        let store = ctx.store();
+        assert!(store.folder("INBOX").unwrap().is_empty());
+        assert_eq!(store.folder("Archives").unwrap().len(), 1);
+        assert_eq!(store.folder("Trash").unwrap().len(), 1);
+
+        let (id, _) = store.folder("Trash").unwrap().iter().next().unwrap();
+        let id = Id::single(id);
+        backend.delete_messages("Trash", &id).await.unwrap();
+        assert_eq!(ctx.store().folder("Trash").unwrap().len(), 1);
+
+        backend.expunge_folder("Trash").await.unwrap();
+        assert!(ctx.store().folder("Trash").unwrap().is_empty());
+    }
+}
@@ -3,7 +3,10 @@
 //! This module contains the configuration specific to the Notmuch
 //! backend.
 
-use std::path::{Path, PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use notmuch::{Database, DatabaseMode};
 use shellexpand_utils::shellexpand_path;
@@ -46,6 +49,23 @@ pub struct NotmuchConfig {
 
     #[cfg_attr(feature = "derive", serde(default))]
     pub maildirpp: bool,
+
+    /// Refresh the index automatically before listing envelopes.
+    ///
+    /// The value represents the staleness threshold, in seconds: the
+    /// index is refreshed only if the last refresh is older than
+    /// this threshold. `0` means that the index is refreshed before
+    /// every listing. The index is never refreshed automatically if
+    /// omitted.
+    pub refresh_index_threshold: Option<u64>,
+
+    /// The interval, in seconds, at which the database revision is
+    /// polled when watching envelopes.
+    ///
+    /// Polling catches tag changes made outside of the Maildir, for
+    /// example using the `notmuch tag` command. Defaults to 60
+    /// seconds.
+    pub watch_poll_interval: Option<u64>,
 }
 
 impl NotmuchConfig {
@@ -86,6 +106,17 @@ pub fn find_config_path(&self) -> Option<&Path> {
         self.config_path.as_ref().map(AsRef::as_ref)
     }
 
+    /// Find the index refresh staleness threshold.
+    pub fn find_refresh_index_threshold(&self) -> Option<Duration> {
+        self.refresh_index_threshold.map(Duration::from_secs)
+    }
+
+    /// Find the database revision polling interval used by the
+    /// envelopes watcher.
+    pub fn find_watch_poll_interval(&self) -> Duration {
+        Duration::from_secs(self.watch_poll_interval.unwrap_or(60))
+    }
+
     /// Find the Notmuch profile.
     pub fn find_profile(&self) -> Option<&str> {
         self.profile.as_deref()
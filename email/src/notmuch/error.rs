@@ -1,4 +1,4 @@
-use std::{any::Any, result};
+use std::{any::Any, io, path::PathBuf, result};
 
 use thiserror::Error;
 
@@ -18,6 +18,16 @@ pub enum Error {
     ExecuteQueryError(#[source] notmuch::Error),
     #[error("cannot close notmuch database")]
     CloseDatabaseError(#[source] notmuch::Error),
+    #[error("cannot begin notmuch atomic operation")]
+    BeginAtomicError(#[source] notmuch::Error),
+    #[error("cannot end notmuch atomic operation")]
+    EndAtomicError(#[source] notmuch::Error),
+    #[error("cannot add file {1:?} to notmuch index")]
+    AddFileToIndexError(#[source] notmuch::Error, PathBuf),
+    #[error("cannot remove file {1:?} from notmuch index")]
+    RemoveFileFromIndexError(#[source] notmuch::Error, PathBuf),
+    #[error("cannot read maildir directory {1:?}")]
+    ReadDirError(#[source] io::Error, PathBuf),
 }
 
 impl AnyError for Error {
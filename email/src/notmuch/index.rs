@@ -0,0 +1,116 @@
+//! Module dedicated to the Notmuch index refresh.
+//!
+//! This module contains the equivalent of the `notmuch new` command,
+//! based on the library binding, see [`refresh_index`].
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use notmuch::Database;
+use tracing::{debug, trace};
+
+use super::{Error, Result};
+
+/// The report of an index refresh.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RefreshIndexReport {
+    /// The number of files added to the index.
+    pub added: usize,
+
+    /// The number of files removed from the index.
+    pub removed: usize,
+}
+
+/// Refresh the index of the given database against the given Maildir
+/// root directory.
+///
+/// Files that are not indexed yet are added to the database (with
+/// tags synchronized from their Maildir flags), and indexed files that
+/// do not exist anymore are removed from the database.
+pub fn refresh_index(db: &Database, root: &Path) -> Result<RefreshIndexReport> {
+    let mut report = RefreshIndexReport::default();
+
+    let msgs = db
+        .create_query("*")
+        .map_err(Error::CreateQueryError)?
+        .search_messages()
+        .map_err(Error::ExecuteQueryError)?;
+
+    let mut indexed = HashSet::new();
+
+    for msg in msgs {
+        indexed.extend(msg.filenames());
+    }
+
+    db.begin_atomic().map_err(Error::BeginAtomicError)?;
+
+    for path in &indexed {
+        if !path.exists() {
+            trace!("removing file {} from notmuch index", path.display());
+            db.remove_message(path)
+                .map_err(|err| Error::RemoveFileFromIndexError(err, path.clone()))?;
+            report.removed += 1;
+        }
+    }
+
+    let mut paths = Vec::new();
+    collect_maildir_files(root, &mut paths)?;
+
+    for path in paths {
+        if indexed.contains(&path) {
+            continue;
+        }
+
+        trace!("adding file {} to notmuch index", path.display());
+        let msg = db
+            .index_file(&path, None)
+            .map_err(|err| Error::AddFileToIndexError(err, path.clone()))?;
+        msg.maildir_flags_to_tags()
+            .map_err(|err| Error::AddFileToIndexError(err, path.clone()))?;
+        report.added += 1;
+    }
+
+    db.end_atomic().map_err(Error::EndAtomicError)?;
+
+    debug!("refreshed notmuch index: {report:?}");
+
+    Ok(report)
+}
+
+/// Recursively collect message files contained in `cur` and `new`
+/// Maildir directories.
+fn collect_maildir_files(dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+    let entries = fs::read_dir(dir).map_err(|err| Error::ReadDirError(err, dir.to_owned()))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::ReadDirError(err, dir.to_owned()))?;
+        let path = entry.path();
+
+        if !path.is_dir() {
+            continue;
+        }
+
+        match entry.file_name().to_str() {
+            Some(".notmuch" | "tmp") => continue,
+            Some("cur" | "new") => {
+                let files =
+                    fs::read_dir(&path).map_err(|err| Error::ReadDirError(err, path.clone()))?;
+
+                for file in files {
+                    let file = file.map_err(|err| Error::ReadDirError(err, path.clone()))?;
+                    let file = file.path();
+
+                    if file.is_file() {
+                        paths.push(file);
+                    }
+                }
+            }
+            _ => collect_maildir_files(&path, paths)?,
+        }
+    }
+
+    Ok(())
+}
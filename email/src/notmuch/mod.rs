@@ -1,18 +1,24 @@
 pub mod config;
 mod error;
+pub mod index;
 
-use std::{ops::Deref, sync::Arc};
+use std::{ops::Deref, sync::Arc, time::Instant};
 
 use async_trait::async_trait;
 use maildirs::Maildirs;
 use notmuch::{Database, DatabaseMode};
 use shellexpand_utils::shellexpand_path;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{debug, info};
 
-use self::config::NotmuchConfig;
 #[doc(inline)]
 pub use self::error::{Error, Result};
+use self::{
+    config::NotmuchConfig,
+    index::{refresh_index, RefreshIndexReport},
+};
+#[cfg(feature = "watch")]
+use crate::envelope::watch::{notmuch::WatchNotmuchEnvelopes, WatchEnvelopes};
 use crate::{
     account::config::AccountConfig,
     backend::{
@@ -25,6 +31,7 @@
     },
     flag::{
         add::{notmuch::AddNotmuchFlags, AddFlags},
+        mark_read::{notmuch::MarkReadBeforeNotmuch, MarkReadBefore},
         remove::{notmuch::RemoveNotmuchFlags, RemoveFlags},
         set::{notmuch::SetNotmuchFlags, SetFlags},
     },
@@ -61,6 +68,9 @@ pub struct NotmuchContext {
 
     /// The Maildir context associated to the Notmuch database.
     pub mdir_ctx: MaildirContext,
+
+    /// The instant of the last index refresh.
+    last_index_refresh: Option<Instant>,
 }
 
 impl NotmuchContext {
@@ -83,6 +93,40 @@ pub fn open_db(&self) -> Result<Database> {
     pub fn maildirpp(&self) -> bool {
         self.notmuch_config.maildirpp
     }
+
+    /// Refresh the index, like the `notmuch new` command does.
+    ///
+    /// See [`refresh_index`].
+    pub fn refresh_index(&mut self) -> Result<RefreshIndexReport> {
+        info!("refreshing notmuch index");
+
+        let db = self.open_db()?;
+        let report = refresh_index(&db, self.mdir_ctx.root.path())?;
+        db.close().map_err(Error::CloseDatabaseError)?;
+
+        self.last_index_refresh = Some(Instant::now());
+
+        Ok(report)
+    }
+
+    /// Refresh the index if the last refresh is older than the
+    /// staleness threshold from the Notmuch configuration.
+    ///
+    /// Returns `None` if the index did not need to be refreshed.
+    pub fn refresh_index_if_stale(&mut self) -> Result<Option<RefreshIndexReport>> {
+        let Some(threshold) = self.notmuch_config.find_refresh_index_threshold() else {
+            return Ok(None);
+        };
+
+        if let Some(last_refresh) = self.last_index_refresh {
+            if last_refresh.elapsed() < threshold {
+                debug!("notmuch index is fresh, skipping refresh");
+                return Ok(None);
+            }
+        }
+
+        self.refresh_index().map(Some)
+    }
 }
 
 /// The sync version of the Notmuch backend context.
@@ -101,6 +145,15 @@ pub struct NotmuchContextSync {
     inner: Arc<Mutex<NotmuchContext>>,
 }
 
+impl NotmuchContextSync {
+    /// Refresh the index, like the `notmuch new` command does.
+    ///
+    /// See [`NotmuchContext::refresh_index`].
+    pub async fn refresh_index(&self) -> Result<RefreshIndexReport> {
+        self.lock().await.refresh_index()
+    }
+}
+
 impl Deref for NotmuchContextSync {
     type Target = Arc<Mutex<NotmuchContext>>;
 
@@ -132,9 +185,9 @@ pub fn new(account_config: Arc<AccountConfig>, notmuch_config: Arc<NotmuchConfig
 
 #[cfg(feature = "sync")]
 impl crate::sync::hash::SyncHash for NotmuchContextBuilder {
-    fn sync_hash(&self, state: &mut std::hash::DefaultHasher) {
+    fn sync_hash(&self, state: &mut crate::sync::hash::SyncHasher) {
         if let Ok(path) = self.notmuch_config.try_get_maildir_path() {
-            std::hash::Hash::hash(&path, state);
+            state.write_path(&path);
         }
     }
 }
@@ -178,10 +231,10 @@ fn list_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn ListEnvelop
         Some(Arc::new(ListNotmuchEnvelopes::some_new_boxed))
     }
 
-    // TODO
-    // fn watch_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn WatchEnvelopes>> {
-    //     Some(Arc::new(WatchNotmuchEnvelopes::some_new_boxed))
-    // }
+    #[cfg(feature = "watch")]
+    fn watch_envelopes(&self) -> Option<BackendFeature<Self::Context, dyn WatchEnvelopes>> {
+        Some(Arc::new(WatchNotmuchEnvelopes::some_new_boxed))
+    }
 
     fn add_flags(&self) -> Option<BackendFeature<Self::Context, dyn AddFlags>> {
         Some(Arc::new(AddNotmuchFlags::some_new_boxed))
@@ -195,6 +248,10 @@ fn remove_flags(&self) -> Option<BackendFeature<Self::Context, dyn RemoveFlags>>
         Some(Arc::new(RemoveNotmuchFlags::some_new_boxed))
     }
 
+    fn mark_read_before(&self) -> Option<BackendFeature<Self::Context, dyn MarkReadBefore>> {
+        Some(Arc::new(MarkReadBeforeNotmuch::some_new_boxed))
+    }
+
     fn add_message(&self) -> Option<BackendFeature<Self::Context, dyn AddMessage>> {
         Some(Arc::new(AddNotmuchMessage::some_new_boxed))
     }
@@ -231,6 +288,8 @@ async fn build(self) -> AnyResult<Self::Context> {
         let maildir_config = Arc::new(MaildirConfig {
             root_dir: root.path().to_owned(),
             maildirpp: self.notmuch_config.maildirpp,
+            clean_tmp: false,
+            expunge_flags: None,
         });
 
         let mdir_ctx = MaildirContext {
@@ -243,6 +302,7 @@ async fn build(self) -> AnyResult<Self::Context> {
             account_config: self.account_config.clone(),
             notmuch_config: self.notmuch_config.clone(),
             mdir_ctx,
+            last_index_refresh: None,
         };
 
         Ok(NotmuchContextSync {
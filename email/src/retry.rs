@@ -4,6 +4,9 @@
 
 pub type Result<T> = std::result::Result<T, Elapsed>;
 
+/// The default timeout of a request.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
 #[derive(Debug)]
 pub enum RetryState<T> {
     Ok(T),
@@ -22,7 +25,12 @@ pub fn reset(&mut self) {
     }
 
     pub fn timeout<F: IntoFuture>(&self, f: F) -> Timeout<F::IntoFuture> {
-        timeout(Duration::from_secs(30), f)
+        self.timeout_after(DEFAULT_TIMEOUT, f)
+    }
+
+    /// Same as [`Retry::timeout`], with a custom timeout.
+    pub fn timeout_after<F: IntoFuture>(&self, duration: Duration, f: F) -> Timeout<F::IntoFuture> {
+        timeout(duration, f)
     }
 
     pub fn next<T>(&mut self, res: Result<T>) -> RetryState<T> {
@@ -0,0 +1,103 @@
+//! # SASL server challenges
+//!
+//! Module dedicated to the parsing of the error challenge sent by the
+//! server when an OAuth 2.0 SASL authentication (XOAUTH2 or
+//! OAUTHBEARER) fails.
+
+use mail_parser::decoders::base64::base64_decode;
+use serde_json::Value;
+
+use super::{Error, Result};
+
+/// The OAuth 2.0 error challenge.
+///
+/// See [RFC 7628](https://datatracker.ietf.org/doc/html/rfc7628#section-3.2.2)
+/// and [Google XOAUTH2 protocol](https://developers.google.com/gmail/imap/xoauth2-protocol#error_response).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct OAuth2ErrorChallenge {
+    /// The error status.
+    ///
+    /// RFC 7628 defines `invalid_token`, `invalid_request` and
+    /// `insufficient_scope`, while XOAUTH2 servers usually send HTTP
+    /// status codes like `400` or `401`.
+    pub status: String,
+
+    /// The authentication schemes supported by the server.
+    pub schemes: Option<String>,
+
+    /// The scope required by the server.
+    pub scope: Option<String>,
+
+    /// The URL of the OpenID Connect discovery document.
+    pub openid_configuration: Option<String>,
+}
+
+impl OAuth2ErrorChallenge {
+    /// Parse the given challenge, either raw or base64-encoded.
+    pub fn parse(challenge: impl AsRef<[u8]>) -> Result<Self> {
+        let challenge = challenge.as_ref().trim_ascii();
+
+        let json = if challenge.starts_with(b"{") {
+            challenge.to_vec()
+        } else {
+            base64_decode(challenge).ok_or(Error::DecodeOAuth2ChallengeError)?
+        };
+
+        let json: Value =
+            serde_json::from_slice(&json).map_err(Error::ParseOAuth2ChallengeError)?;
+
+        let field = |name: &str| match json.get(name) {
+            Some(Value::String(value)) => Some(value.clone()),
+            Some(Value::Number(value)) => Some(value.to_string()),
+            _ => None,
+        };
+
+        Ok(Self {
+            status: field("status").ok_or(Error::MissingOAuth2ChallengeStatusError)?,
+            schemes: field("schemes"),
+            scope: field("scope"),
+            openid_configuration: field("openid-configuration"),
+        })
+    }
+
+    /// Return `true` if the challenge means that the access token is
+    /// invalid or expired, in which case it should be refreshed.
+    pub fn is_invalid_token(&self) -> bool {
+        matches!(self.status.as_str(), "invalid_token" | "400" | "401")
+    }
+
+    /// Return `true` if the challenge means that the access token
+    /// lacks the required scope.
+    pub fn is_insufficient_scope(&self) -> bool {
+        matches!(self.status.as_str(), "insufficient_scope" | "403")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OAuth2ErrorChallenge;
+
+    #[test]
+    fn parse_xoauth2_challenge() {
+        // {"status":"400","schemes":"Bearer","scope":"https://mail.google.com/"}
+        let challenge = "eyJzdGF0dXMiOiI0MDAiLCJzY2hlbWVzIjoiQmVhcmVyIiwic2NvcGUiOiJodHRwczovL21haWwuZ29vZ2xlLmNvbS8ifQ==";
+        let challenge = OAuth2ErrorChallenge::parse(challenge).unwrap();
+
+        assert_eq!(challenge.status, "400");
+        assert_eq!(challenge.schemes.as_deref(), Some("Bearer"));
+        assert_eq!(challenge.scope.as_deref(), Some("https://mail.google.com/"));
+        assert!(challenge.is_invalid_token());
+    }
+
+    #[test]
+    fn parse_oauthbearer_challenge() {
+        let challenge = r#"{"status":"insufficient_scope","scope":"mail","openid-configuration":"https://example.com/.well-known/openid-configuration"}"#;
+        let challenge = OAuth2ErrorChallenge::parse(challenge).unwrap();
+
+        assert!(challenge.is_insufficient_scope());
+        assert!(challenge.openid_configuration.is_some());
+
+        assert!(OAuth2ErrorChallenge::parse("{}").is_err());
+        assert!(OAuth2ErrorChallenge::parse("not base64!").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use std::{any::Any, error::Error as StdError, io, result};
+
+use thiserror::Error;
+
+use super::SaslMechanism;
+#[cfg(feature = "oauth2")]
+use crate::account;
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot authenticate: no supported SASL mechanism among {0:?}")]
+    MechanismNotSupportedError(Vec<SaslMechanism>),
+    #[error("cannot authenticate using SASL {0} mechanism")]
+    AuthenticateError(SaslMechanism, #[source] Box<dyn StdError + Send + Sync>),
+    #[error("cannot encode SASL initial response using base64")]
+    EncodeInitialResponseError(#[source] io::Error),
+    #[error("cannot decode base64 OAuth 2.0 challenge")]
+    DecodeOAuth2ChallengeError,
+    #[error("cannot parse OAuth 2.0 challenge")]
+    ParseOAuth2ChallengeError(#[source] serde_json::Error),
+    #[error("cannot parse OAuth 2.0 challenge: missing status")]
+    MissingOAuth2ChallengeStatusError,
+    #[cfg(feature = "oauth2")]
+    #[error("cannot get OAuth 2.0 access token")]
+    GetAccessTokenError(#[source] account::Error),
+    #[cfg(feature = "oauth2")]
+    #[error("cannot refresh OAuth 2.0 access token")]
+    RefreshAccessTokenError(#[source] account::Error),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,307 @@
+//! # SASL
+//!
+//! Module dedicated to SASL authentication helpers shared by the IMAP
+//! and the SMTP backends, so that the authentication logic (fallback
+//! chain, retry after OAuth 2.0 access token refresh) stays the same
+//! for both.
+//!
+//! It also exposes typed SASL initial client responses and the OAuth
+//! 2.0 server challenge parser, so that external transports do not
+//! need to build those strings by hand.
+
+mod challenge;
+mod error;
+mod response;
+
+use std::fmt;
+#[cfg(feature = "oauth2")]
+use std::{error::Error as StdError, future::Future};
+
+#[cfg(feature = "oauth2")]
+use tracing::{debug, warn};
+
+#[doc(inline)]
+pub use self::{
+    challenge::OAuth2ErrorChallenge,
+    error::{Error, Result},
+    response::SaslInitialResponse,
+};
+#[cfg(feature = "oauth2")]
+use crate::account::{
+    self,
+    config::oauth2::{OAuth2Config, OAuth2Method},
+    runtime::AccountRuntime,
+};
+
+/// The SASL mechanisms supported by the IMAP and the SMTP backends.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SaslMechanism {
+    /// The PLAIN mechanism.
+    Plain,
+    /// The LOGIN mechanism (or the IMAP LOGIN command).
+    Login,
+    /// The XOAUTH2 mechanism.
+    XOAuth2,
+    /// The OAUTHBEARER mechanism.
+    OAuthBearer,
+}
+
+impl fmt::Display for SaslMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain => write!(f, "PLAIN"),
+            Self::Login => write!(f, "LOGIN"),
+            Self::XOAuth2 => write!(f, "XOAUTH2"),
+            Self::OAuthBearer => write!(f, "OAUTHBEARER"),
+        }
+    }
+}
+
+#[cfg(feature = "oauth2")]
+impl From<&OAuth2Method> for SaslMechanism {
+    fn from(method: &OAuth2Method) -> Self {
+        match method {
+            OAuth2Method::XOAuth2 => Self::XOAuth2,
+            OAuth2Method::OAuthBearer => Self::OAuthBearer,
+        }
+    }
+}
+
+/// The result of a successful OAuth 2.0 authentication.
+#[cfg(feature = "oauth2")]
+#[derive(Debug)]
+pub struct OAuth2Authentication<C, T> {
+    /// The context given back by the authentication function.
+    pub context: C,
+
+    /// The output of the authentication function.
+    pub output: T,
+
+    /// The SASL mechanism that succeeded.
+    pub mechanism: SaslMechanism,
+
+    /// The access token that succeeded.
+    pub access_token: String,
+}
+
+/// Build the OAUTHBEARER initial client response.
+///
+/// See [RFC 7628](https://datatracker.ietf.org/doc/html/rfc7628#section-3.1).
+pub fn oauthbearer_payload(login: &str, host: &str, port: u16, access_token: &str) -> String {
+    SaslInitialResponse::oauthbearer(login, host, port, access_token).encode()
+}
+
+/// Return the OAuth 2.0 mechanisms fallback chain.
+///
+/// The configured method is tried first, then the other OAuth 2.0
+/// method.
+#[cfg(feature = "oauth2")]
+pub fn oauth2_mechanisms(method: &OAuth2Method) -> [SaslMechanism; 2] {
+    match method {
+        OAuth2Method::XOAuth2 => [SaslMechanism::XOAuth2, SaslMechanism::OAuthBearer],
+        OAuth2Method::OAuthBearer => [SaslMechanism::OAuthBearer, SaslMechanism::XOAuth2],
+    }
+}
+
+/// Authenticate using OAuth 2.0.
+///
+/// Mechanisms from [`oauth2_mechanisms`] supported by the server are
+/// tried in order using the `authenticate` function. If they all
+/// fail and at least one of them has been rejected by the server (as
+/// told by the `is_auth_failure` function), the access token is
+/// refreshed using the shared token manager (see
+/// [`OAuth2Config::token_manager`]) and the whole chain is tried
+/// again. Other failures (network errors, timeouts etc) are returned
+/// as is, since a new access token would not help.
+///
+/// The context `C` is moved into the `authenticate` function then
+/// given back, which allows the function to mutate it (for example
+/// an IMAP client or an SMTP client builder).
+#[cfg(feature = "oauth2")]
+#[allow(clippy::too_many_arguments)]
+pub async fn authenticate_oauth2<C, T, E, F, Fut>(
+    runtime: &AccountRuntime,
+    oauth2: &OAuth2Config,
+    login: &str,
+    access_token: Option<String>,
+    is_supported: impl Fn(SaslMechanism) -> bool,
+    is_auth_failure: impl Fn(&E) -> bool,
+    mut context: C,
+    mut authenticate: F,
+) -> Result<OAuth2Authentication<C, T>>
+where
+    E: StdError + Send + Sync + 'static,
+    F: FnMut(C, SaslMechanism, String) -> Fut,
+    Fut: Future<Output = (C, std::result::Result<T, E>)>,
+{
+    let mechanisms: Vec<_> = oauth2_mechanisms(&oauth2.method)
+        .into_iter()
+        .filter(|mechanism| is_supported(*mechanism))
+        .collect();
+
+    if mechanisms.is_empty() {
+        let mechanisms = oauth2_mechanisms(&oauth2.method).to_vec();
+        return Err(Error::MechanismNotSupportedError(mechanisms));
+    }
+
+    let mut access_token = match access_token {
+        Some(access_token) => access_token,
+        None => oauth2
+            .token_manager(runtime, login)
+            .await
+            .map_err(Error::GetAccessTokenError)?
+            .access_token()
+            .await
+            .map_err(|err| {
+                Error::GetAccessTokenError(account::Error::GetAccessTokenFromManagerOauthError(err))
+            })?,
+    };
+
+    let mut refreshed = false;
+
+    loop {
+        let mut last_err = Error::MechanismNotSupportedError(mechanisms.clone());
+        let mut rejected = false;
+
+        for mechanism in mechanisms.iter().copied() {
+            debug!(%mechanism, "trying auth mechanism…");
+
+            let (ctx, res) = authenticate(context, mechanism, access_token.clone()).await;
+            context = ctx;
+
+            match res {
+                Ok(output) => {
+                    debug!(%mechanism, "authentication succeeded!");
+                    return Ok(OAuth2Authentication {
+                        context,
+                        output,
+                        mechanism,
+                        access_token,
+                    });
+                }
+                Err(err) => {
+                    warn!(%mechanism, ?err, "authentication failed");
+                    rejected |= is_auth_failure(&err);
+                    last_err = Error::AuthenticateError(mechanism, Box::new(err));
+                }
+            }
+        }
+
+        if refreshed || !rejected {
+            break Err(last_err);
+        }
+
+        warn!("authentication failed, refreshing access token and retrying…");
+
+        access_token = oauth2
+            .token_manager(runtime, login)
+            .await
+            .map_err(Error::RefreshAccessTokenError)?
+            .refresh_access_token(Some(&access_token))
+            .await
+            .map_err(|err| {
+                Error::RefreshAccessTokenError(account::Error::RefreshAccessTokenOauthError(err))
+            })?;
+
+        refreshed = true;
+    }
+}
+
+#[cfg(all(test, feature = "oauth2"))]
+mod tests {
+    use std::io;
+
+    use super::{authenticate_oauth2, oauthbearer_payload, Error, SaslMechanism};
+    use crate::account::{
+        config::oauth2::{OAuth2Config, OAuth2Method},
+        runtime::AccountRuntime,
+    };
+
+    #[test]
+    fn oauthbearer() {
+        assert_eq!(
+            oauthbearer_payload("user@localhost", "localhost", 587, "token"),
+            "n,a=user@localhost,\x01host=localhost\x01port=587\x01auth=Bearer token\x01\x01",
+        );
+    }
+
+    #[tokio::test]
+    async fn oauth2_fallback() {
+        let config = OAuth2Config {
+            method: OAuth2Method::XOAuth2,
+            ..Default::default()
+        };
+
+        let auth = authenticate_oauth2(
+            &AccountRuntime::new(),
+            &config,
+            "user@localhost",
+            Some("token".into()),
+            |_| true,
+            |_| true,
+            Vec::new(),
+            |mut tried: Vec<SaslMechanism>, mechanism, _| async move {
+                tried.push(mechanism);
+
+                let res = match mechanism {
+                    SaslMechanism::OAuthBearer => Ok(()),
+                    _ => Err(io::Error::other("unsupported")),
+                };
+
+                (tried, res)
+            },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(auth.mechanism, SaslMechanism::OAuthBearer);
+        assert_eq!(auth.access_token, "token");
+        assert_eq!(
+            auth.context,
+            vec![SaslMechanism::XOAuth2, SaslMechanism::OAuthBearer]
+        );
+    }
+
+    #[tokio::test]
+    async fn oauth2_not_supported() {
+        let config = OAuth2Config::default();
+
+        let auth = authenticate_oauth2(
+            &AccountRuntime::new(),
+            &config,
+            "user@localhost",
+            Some("token".into()),
+            |_| false,
+            |_| true,
+            (),
+            |ctx, _, _| async move { (ctx, Ok::<_, io::Error>(())) },
+        )
+        .await;
+
+        assert!(auth.is_err());
+    }
+
+    #[tokio::test]
+    async fn oauth2_no_refresh_without_auth_failure() {
+        let config = OAuth2Config::default();
+
+        let auth = authenticate_oauth2(
+            &AccountRuntime::new(),
+            &config,
+            "user@localhost",
+            Some("token".into()),
+            |_| true,
+            |err: &io::Error| err.kind() == io::ErrorKind::PermissionDenied,
+            0,
+            |count, _, _| async move {
+                let err = io::Error::from(io::ErrorKind::ConnectionReset);
+                (count + 1, Err::<(), _>(err))
+            },
+        )
+        .await;
+
+        // the access token is not refreshed, which would fail with
+        // the default configuration
+        assert!(matches!(auth, Err(Error::AuthenticateError(..))));
+    }
+}
@@ -0,0 +1,213 @@
+//! # SASL initial client responses
+//!
+//! Module dedicated to the initial client responses of the SASL
+//! mechanisms supported by the IMAP and the SMTP backends. External
+//! transports can use them to authenticate the same way.
+
+use std::fmt;
+
+use mail_builder::encoders::base64::base64_encode;
+
+use super::{Error, Result, SaslMechanism};
+
+/// The initial client response of a SASL mechanism.
+///
+/// The [`fmt::Debug`] implementation redacts passwords and access
+/// tokens.
+#[derive(Clone, Eq, PartialEq)]
+pub enum SaslInitialResponse {
+    /// The PLAIN initial response.
+    ///
+    /// See [RFC 4616](https://datatracker.ietf.org/doc/html/rfc4616#section-2).
+    Plain {
+        authzid: Option<String>,
+        login: String,
+        password: String,
+    },
+
+    /// The XOAUTH2 initial response.
+    ///
+    /// See [Google XOAUTH2 protocol](https://developers.google.com/gmail/imap/xoauth2-protocol#the_sasl_xoauth2_mechanism).
+    XOAuth2 { login: String, access_token: String },
+
+    /// The OAUTHBEARER initial response.
+    ///
+    /// See [RFC 7628](https://datatracker.ietf.org/doc/html/rfc7628#section-3.1).
+    OAuthBearer {
+        login: String,
+        host: String,
+        port: u16,
+        access_token: String,
+    },
+}
+
+impl SaslInitialResponse {
+    pub fn plain(login: impl ToString, password: impl ToString) -> Self {
+        Self::Plain {
+            authzid: None,
+            login: login.to_string(),
+            password: password.to_string(),
+        }
+    }
+
+    pub fn xoauth2(login: impl ToString, access_token: impl ToString) -> Self {
+        Self::XOAuth2 {
+            login: login.to_string(),
+            access_token: access_token.to_string(),
+        }
+    }
+
+    pub fn oauthbearer(
+        login: impl ToString,
+        host: impl ToString,
+        port: u16,
+        access_token: impl ToString,
+    ) -> Self {
+        Self::OAuthBearer {
+            login: login.to_string(),
+            host: host.to_string(),
+            port,
+            access_token: access_token.to_string(),
+        }
+    }
+
+    /// Set the authorization identity of the PLAIN response.
+    ///
+    /// Has no effect on other mechanisms.
+    pub fn set_authzid(&mut self, authzid: impl ToString) {
+        if let Self::Plain { authzid: a, .. } = self {
+            *a = Some(authzid.to_string());
+        }
+    }
+
+    /// Set the authorization identity of the PLAIN response, using
+    /// the builder pattern.
+    pub fn with_authzid(mut self, authzid: impl ToString) -> Self {
+        self.set_authzid(authzid);
+        self
+    }
+
+    /// Return the SASL mechanism of the response.
+    pub fn mechanism(&self) -> SaslMechanism {
+        match self {
+            Self::Plain { .. } => SaslMechanism::Plain,
+            Self::XOAuth2 { .. } => SaslMechanism::XOAuth2,
+            Self::OAuthBearer { .. } => SaslMechanism::OAuthBearer,
+        }
+    }
+
+    /// Encode the response, as expected by the mechanism before the
+    /// base64 transfer encoding.
+    pub fn encode(&self) -> String {
+        match self {
+            Self::Plain {
+                authzid,
+                login,
+                password,
+            } => {
+                let authzid = authzid.as_deref().unwrap_or_default();
+                format!("{authzid}\0{login}\0{password}")
+            }
+            Self::XOAuth2 {
+                login,
+                access_token,
+            } => {
+                format!("user={login}\x01auth=Bearer {access_token}\x01\x01")
+            }
+            Self::OAuthBearer {
+                login,
+                host,
+                port,
+                access_token,
+            } => {
+                let login = escape_saslname(login);
+                format!("n,a={login},\x01host={host}\x01port={port}\x01auth=Bearer {access_token}\x01\x01")
+            }
+        }
+    }
+
+    /// Encode the response using base64, as sent on the wire.
+    pub fn encode_base64(&self) -> Result<String> {
+        let encoded =
+            base64_encode(self.encode().as_bytes()).map_err(Error::EncodeInitialResponseError)?;
+        // base64 only produces ASCII characters
+        Ok(String::from_utf8_lossy(&encoded).into_owned())
+    }
+}
+
+/// Escape the given authorization identity of the GS2 header.
+///
+/// See [RFC 5801](https://datatracker.ietf.org/doc/html/rfc5801#section-4).
+fn escape_saslname(name: &str) -> String {
+    name.replace('=', "=3D").replace(',', "=2C")
+}
+
+impl fmt::Debug for SaslInitialResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain { authzid, login, .. } => f
+                .debug_struct("Plain")
+                .field("authzid", authzid)
+                .field("login", login)
+                .field("password", &"<redacted>")
+                .finish(),
+            Self::XOAuth2 { login, .. } => f
+                .debug_struct("XOAuth2")
+                .field("login", login)
+                .field("access_token", &"<redacted>")
+                .finish(),
+            Self::OAuthBearer {
+                login, host, port, ..
+            } => f
+                .debug_struct("OAuthBearer")
+                .field("login", login)
+                .field("host", host)
+                .field("port", port)
+                .field("access_token", &"<redacted>")
+                .finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SaslInitialResponse;
+    use crate::sasl::SaslMechanism;
+
+    #[test]
+    fn plain() {
+        let res = SaslInitialResponse::plain("user", "pass");
+        assert_eq!(res.mechanism(), SaslMechanism::Plain);
+        assert_eq!(res.encode(), "\0user\0pass");
+        assert_eq!(res.encode_base64().unwrap(), "AHVzZXIAcGFzcw==");
+
+        let res = res.with_authzid("admin");
+        assert_eq!(res.encode(), "admin\0user\0pass");
+    }
+
+    #[test]
+    fn xoauth2() {
+        let res = SaslInitialResponse::xoauth2("user@localhost", "token");
+        assert_eq!(res.mechanism(), SaslMechanism::XOAuth2);
+        assert_eq!(
+            res.encode(),
+            "user=user@localhost\x01auth=Bearer token\x01\x01"
+        );
+    }
+
+    #[test]
+    fn oauthbearer_escape_login() {
+        let res = SaslInitialResponse::oauthbearer("a,b=c", "localhost", 993, "token");
+        assert_eq!(res.mechanism(), SaslMechanism::OAuthBearer);
+        assert_eq!(
+            res.encode(),
+            "n,a=a=2Cb=3Dc,\x01host=localhost\x01port=993\x01auth=Bearer token\x01\x01"
+        );
+    }
+
+    #[test]
+    fn redact_secrets() {
+        let res = SaslInitialResponse::xoauth2("user@localhost", "s3cr3t");
+        assert!(!format!("{res:?}").contains("s3cr3t"));
+    }
+}
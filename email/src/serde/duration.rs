@@ -0,0 +1,89 @@
+//! # Duration
+//!
+//! Module dedicated to the (de)serialization of optional durations.
+//! Durations are either numbers of seconds or human-friendly
+//! durations like `2m30s`, as parsed by [`humantime`]. They are
+//! serialized as human-friendly durations.
+
+use std::time::Duration;
+
+use serde::{de, Deserialize, Deserializer, Serializer};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawDuration {
+    Secs(u64),
+    Human(String),
+}
+
+pub(crate) fn serialize<S: Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => {
+            let duration = humantime::format_duration(*duration).to_string();
+            serializer.serialize_some(&duration)
+        }
+        None => serializer.serialize_none(),
+    }
+}
+
+pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let duration = match Option::<RawDuration>::deserialize(deserializer)? {
+        None => None,
+        Some(RawDuration::Secs(secs)) => Some(Duration::from_secs(secs)),
+        Some(RawDuration::Human(duration)) => match duration.trim().parse() {
+            Ok(secs) => Some(Duration::from_secs(secs)),
+            Err(_) => Some(humantime::parse_duration(&duration).map_err(de::Error::custom)?),
+        },
+    };
+
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+    struct Config {
+        #[serde(default, with = "super")]
+        timeout: Option<Duration>,
+    }
+
+    #[test]
+    fn deserialize_durations() {
+        let parse = |json: &str| serde_json::from_str::<Config>(json).unwrap().timeout;
+
+        assert_eq!(parse(r#"{}"#), None);
+        assert_eq!(parse(r#"{"timeout":null}"#), None);
+        assert_eq!(parse(r#"{"timeout":90}"#), Some(Duration::from_secs(90)));
+        assert_eq!(parse(r#"{"timeout":"90"}"#), Some(Duration::from_secs(90)));
+        assert_eq!(
+            parse(r#"{"timeout":"2m30s"}"#),
+            Some(Duration::from_secs(150))
+        );
+        assert_eq!(
+            parse(r#"{"timeout":"1h 30m"}"#),
+            Some(Duration::from_secs(5400))
+        );
+        assert!(serde_json::from_str::<Config>(r#"{"timeout":"soon"}"#).is_err());
+    }
+
+    #[test]
+    fn serialize_durations() {
+        let config = Config {
+            timeout: Some(Duration::from_secs(150)),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&config).unwrap(),
+            r#"{"timeout":"2m 30s"}"#
+        );
+    }
+}
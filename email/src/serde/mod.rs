@@ -1,3 +1,6 @@
+#[cfg(any(feature = "imap", feature = "smtp"))]
+pub(crate) mod duration;
+
 #[allow(unused_macros)]
 macro_rules! serde_deprecated {
     ($name:ident, $from:literal, $to:literal) => {
@@ -3,16 +3,45 @@
 //! This module contains the configuration specific to the SMTP
 //! sender.
 
-use std::io;
+use std::{borrow::Cow, io, iter, time::Duration};
 
 use mail_send::Credentials;
-use tracing::debug;
+use tracing::{debug, info};
 
 #[doc(inline)]
 pub use super::{Error, Result};
 #[cfg(feature = "oauth2")]
 use crate::account::config::oauth2::{OAuth2Config, OAuth2Method};
-use crate::{account::config::passwd::PasswordConfig, tls::Encryption};
+use crate::{
+    account::{config::passwd::PasswordConfig, runtime::AccountRuntime},
+    diagnostic::{ConnectionDiagnostics, ConnectionStep},
+    retry::DEFAULT_TIMEOUT,
+    tls::{Encryption, SecurityLevel, Tls},
+};
+
+/// The port of SMTP submission over implicit TLS (RFC 8314).
+pub const SUBMISSIONS_PORT: u16 = 465;
+
+/// The port of SMTP submission, upgraded using STARTTLS (RFC 6409).
+pub const SUBMISSION_PORT: u16 = 587;
+
+/// The port of SMTP relay, upgraded using STARTTLS.
+pub const SMTP_PORT: u16 = 25;
+
+/// The SMTP encryption detected by
+/// [`SmtpConfig::detect_encryption`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmtpEncryptionDetection {
+    /// The detected encryption.
+    ///
+    /// TLS options of the configured encryption, if any, are kept.
+    pub encryption: Encryption,
+
+    /// `true` if the encryption has been detected by probing the
+    /// server, `false` if it has been deduced from a well-known
+    /// port.
+    pub probed: bool,
+}
 
 /// The SMTP sender configuration.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -33,6 +62,15 @@ pub struct SmtpConfig {
     /// Supported encryption: SSL/TLS or STARTTLS.
     pub encryption: Option<Encryption>,
 
+    /// Detect the encryption protocol from the port.
+    ///
+    /// When enabled, implicit SSL/TLS is used for port 465 and
+    /// STARTTLS for ports 587 and 25. Other ports are probed before
+    /// connecting. Only the TLS options of [`SmtpConfig::encryption`]
+    /// are taken into account. See
+    /// [`SmtpConfig::detect_encryption`].
+    pub auto_encryption: Option<bool>,
+
     /// The SMTP server login.
     ///
     /// Usually, the login is either the email address or its left
@@ -44,9 +82,137 @@ pub struct SmtpConfig {
     /// Authentication can be done using password or OAuth 2.0.
     /// See [SmtpAuthConfig].
     pub auth: SmtpAuthConfig,
+
+    /// The SMTP timeout, used when connecting to the server and
+    /// when sending messages.
+    ///
+    /// Messages sending times out after 30 seconds by default.
+    ///
+    /// The timeout is either a number of seconds or a human-friendly
+    /// duration like `2m30s`, see [humantime](https://docs.rs/humantime).
+    #[cfg_attr(feature = "derive", serde(default, with = "crate::serde::duration"))]
+    pub timeout: Option<Duration>,
+
+    /// The fallback SMTP servers.
+    ///
+    /// When this server cannot be reached or greets with an error,
+    /// fallbacks are tried in order. Each fallback has its own
+    /// encryption and authentication settings. Fallbacks of
+    /// fallbacks are ignored.
+    #[cfg_attr(
+        feature = "derive",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub fallbacks: Vec<SmtpConfig>,
 }
 
 impl SmtpConfig {
+    /// Return the SMTP servers in priority order: this server first,
+    /// then its fallbacks.
+    pub fn relays(&self) -> impl Iterator<Item = &SmtpConfig> {
+        iter::once(self).chain(self.fallbacks.iter())
+    }
+
+    /// Return the SMTP timeout, defaults to [`DEFAULT_TIMEOUT`].
+    pub fn timeout(&self) -> Duration {
+        self.timeout.unwrap_or(DEFAULT_TIMEOUT)
+    }
+
+    /// Return `true` if the encryption protocol should be detected.
+    pub fn is_auto_encryption_enabled(&self) -> bool {
+        self.auto_encryption.unwrap_or_default()
+    }
+
+    /// Detect the encryption protocol of the server.
+    ///
+    /// Well-known ports are mapped without any network round trip.
+    /// Other ports are probed: an implicit SSL/TLS handshake is
+    /// attempted first, then a plaintext SMTP greeting (meaning
+    /// STARTTLS). The result can be used by configuration wizards to
+    /// fill [`SmtpConfig::encryption`].
+    pub async fn detect_encryption(&self) -> Result<SmtpEncryptionDetection> {
+        let tls = self.find_tls();
+
+        let (encryption, probed) = match self.port {
+            SUBMISSIONS_PORT => (Encryption::Tls(tls), false),
+            SUBMISSION_PORT | SMTP_PORT => (Encryption::StartTls(tls), false),
+            port => {
+                let host = &self.host;
+                debug!(host, port, "probing smtp server encryption");
+
+                let diagnostics =
+                    ConnectionDiagnostics::probe_with_tls(host, port, SecurityLevel::Tls, &tls)
+                        .await;
+
+                if diagnostics.failure.is_none() {
+                    (Encryption::Tls(tls), true)
+                } else {
+                    let level = SecurityLevel::StartTls;
+                    let diagnostics =
+                        ConnectionDiagnostics::probe_with_tls(host, port, level, &tls).await;
+
+                    match diagnostics.steps.last() {
+                        Some(ConnectionStep::Greeting { banner }) if banner.starts_with("220") => {
+                            (Encryption::StartTls(tls), true)
+                        }
+                        _ => return Err(Error::DetectSmtpEncryptionError(diagnostics)),
+                    }
+                }
+            }
+        };
+
+        info!(
+            host = self.host,
+            port = self.port,
+            probed,
+            "detected smtp encryption: {encryption}"
+        );
+
+        Ok(SmtpEncryptionDetection { encryption, probed })
+    }
+
+    /// Return the configuration to connect with, where the encryption
+    /// has been detected if [`SmtpConfig::auto_encryption`] is
+    /// enabled.
+    ///
+    /// The detected encryption is cached by the given runtime, so
+    /// that the server is probed once per host and port.
+    pub async fn resolve_encryption(&self, runtime: &AccountRuntime) -> Result<Cow<'_, Self>> {
+        if !self.is_auto_encryption_enabled() {
+            return Ok(Cow::Borrowed(self));
+        }
+
+        let encryption = match runtime.smtp_security_level(&self.host, self.port) {
+            Some(level) => {
+                let tls = self.find_tls();
+                debug!(host = self.host, port = self.port, %level, "using cached smtp encryption");
+
+                match level {
+                    SecurityLevel::Tls => Encryption::Tls(tls),
+                    SecurityLevel::StartTls => Encryption::StartTls(tls),
+                    SecurityLevel::Plaintext => Encryption::None,
+                }
+            }
+            None => {
+                let encryption = self.detect_encryption().await?.encryption;
+                let level = match encryption {
+                    Encryption::Tls(_) => SecurityLevel::Tls,
+                    Encryption::StartTls(_) => SecurityLevel::StartTls,
+                    Encryption::None => SecurityLevel::Plaintext,
+                };
+
+                runtime.set_smtp_security_level(&self.host, self.port, level);
+                encryption
+            }
+        };
+
+        Ok(Cow::Owned(Self {
+            encryption: Some(encryption),
+            auto_encryption: Some(false),
+            ..self.clone()
+        }))
+    }
+
     /// Return `true` if TLS or StartTLS is enabled.
     pub fn is_encryption_enabled(&self) -> bool {
         matches!(
@@ -65,12 +231,44 @@ pub fn is_encryption_disabled(&self) -> bool {
         matches!(self.encryption.as_ref(), Some(Encryption::None))
     }
 
+    /// Return the security level expected from the encryption
+    /// configuration.
+    pub fn expected_security_level(&self) -> SecurityLevel {
+        if self.is_encryption_disabled() {
+            SecurityLevel::Plaintext
+        } else if self.is_start_tls_encryption_enabled() {
+            SecurityLevel::StartTls
+        } else {
+            SecurityLevel::Tls
+        }
+    }
+
+    /// Return the TLS options of the encryption, or the default ones
+    /// when encryption is disabled.
+    pub fn find_tls(&self) -> Tls {
+        self.encryption
+            .as_ref()
+            .and_then(Encryption::tls)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Builds the OAUTHBEARER initial client response for the given
+    /// access token.
+    #[cfg(feature = "oauth2")]
+    pub fn oauthbearer_payload(&self, access_token: &str) -> String {
+        crate::sasl::oauthbearer_payload(&self.login, &self.host, self.port, access_token)
+    }
+
     /// Builds the SMTP credentials string.
     ///
     /// The result depends on the [`SmtpAuthConfig`]: if password mode
     /// then creates credentials from login/password, if OAuth 2.0
     /// then creates credentials from access token.
-    pub async fn credentials(&self) -> Result<Credentials<String>> {
+    pub async fn credentials(
+        &self,
+        #[cfg_attr(not(feature = "oauth2"), allow(unused_variables))] runtime: &AccountRuntime,
+    ) -> Result<Credentials<String>> {
         Ok(match &self.auth {
             SmtpAuthConfig::Password(passwd) => {
                 let passwd = passwd.get().await.map_err(Error::GetPasswdSmtpError)?;
@@ -83,6 +281,9 @@ pub async fn credentials(&self) -> Result<Credentials<String>> {
             #[cfg(feature = "oauth2")]
             SmtpAuthConfig::OAuth2(oauth2) => {
                 let access_token = oauth2
+                    .token_manager(runtime, &self.login)
+                    .await
+                    .map_err(|_| Error::AccessTokenWasNotAvailable)?
                     .access_token()
                     .await
                     .map_err(|_| Error::AccessTokenWasNotAvailable)?;
@@ -91,7 +292,9 @@ pub async fn credentials(&self) -> Result<Credentials<String>> {
                     OAuth2Method::XOAuth2 => {
                         Credentials::new_xoauth2(self.login.clone(), access_token)
                     }
-                    OAuth2Method::OAuthBearer => Credentials::new_oauth(access_token),
+                    OAuth2Method::OAuthBearer => {
+                        Credentials::new_oauth(self.oauthbearer_payload(&access_token))
+                    }
                 }
             }
         })
@@ -177,6 +380,10 @@ pub fn replace_empty_secrets(&mut self, name: impl AsRef<str>) -> Result<()> {
                     .refresh_token
                     .replace_with_keyring_if_empty(format!("{name}-smtp-oauth2-refresh-token"))
                     .map_err(Error::ReplacingKeyringFailed)?;
+                config
+                    .expires_at
+                    .replace_with_keyring_if_empty(format!("{name}-smtp-oauth2-expires-at"))
+                    .map_err(Error::ReplacingKeyringFailed)?;
             }
         }
 
@@ -222,3 +429,67 @@ fn from(config: SmtpAuthConfigDerive) -> Self {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncWriteExt, net::TcpListener};
+
+    use super::SmtpConfig;
+    use crate::{
+        account::runtime::AccountRuntime,
+        tls::{Encryption, SecurityLevel},
+    };
+
+    fn config(port: u16) -> SmtpConfig {
+        SmtpConfig {
+            host: "127.0.0.1".into(),
+            port,
+            auto_encryption: Some(true),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn detect_encryption_from_well_known_ports() {
+        let detection = config(465).detect_encryption().await.unwrap();
+        assert!(matches!(detection.encryption, Encryption::Tls(_)));
+        assert!(!detection.probed);
+
+        let detection = config(587).detect_encryption().await.unwrap();
+        assert!(matches!(detection.encryption, Encryption::StartTls(_)));
+        assert!(!detection.probed);
+    }
+
+    #[tokio::test]
+    async fn detect_start_tls_encryption_by_probing() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let _ = stream.write_all(b"220 smtp.localhost ESMTP\r\n").await;
+            }
+        });
+
+        let config = config(port);
+        let detection = config.detect_encryption().await.unwrap();
+        assert!(matches!(detection.encryption, Encryption::StartTls(_)));
+        assert!(detection.probed);
+
+        let runtime = AccountRuntime::new();
+        let resolved = config.resolve_encryption(&runtime).await.unwrap();
+        assert!(resolved.is_start_tls_encryption_enabled());
+        assert!(!resolved.is_auto_encryption_enabled());
+
+        // the detection is cached by the runtime: the server is not
+        // probed anymore
+        let config = SmtpConfig {
+            host: "localhost.invalid".into(),
+            ..config
+        };
+        runtime.set_smtp_security_level(&config.host, port, SecurityLevel::StartTls);
+        let resolved = config.resolve_encryption(&runtime).await.unwrap();
+        assert!(resolved.is_start_tls_encryption_enabled());
+    }
+}
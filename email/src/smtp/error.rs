@@ -2,7 +2,7 @@
 
 use thiserror::Error;
 
-use crate::{AnyBoxedError, AnyError};
+use crate::{diagnostic::ConnectionDiagnostics, AnyBoxedError, AnyError};
 
 /// The global `Result` alias of the module.
 pub type Result<T> = result::Result<T, Error>;
@@ -14,6 +14,16 @@ pub enum Error {
     SendMessageMissingSenderError,
     #[error("cannot send message without a recipient")]
     SendMessageMissingRecipientError,
+    #[error("cannot find any SMTP relay to connect to")]
+    ConnectSmtpRelayNotFoundError,
+    #[error("cannot encode SMTP envelope address")]
+    EncodeEnvelopeAddressError(#[source] crate::email::Error),
+    #[error("cannot sanitize message before sending it")]
+    SanitizeMessageError(#[source] crate::email::Error),
+    #[error("cannot send message of {0} bytes: server accepts up to {1} bytes")]
+    MessageTooLargeError(usize, usize),
+    #[error("cannot query smtp server capabilities")]
+    QueryCapabilitiesError(#[source] mail_send::Error),
     #[error("cannot send message: request timed out")]
     SendMessageTimedOutError,
     #[error("cannot send message")]
@@ -22,14 +32,23 @@ pub enum Error {
     ConnectTcpSmtpError(#[source] mail_send::Error),
     #[error("cannot connect to smtp server using tls")]
     ConnectTlsSmtpError(#[source] mail_send::Error),
+    #[error("cannot build tls connector for smtp server")]
+    BuildTlsConnectorSmtpError(#[source] crate::tls::Error),
+    #[error("cannot connect to smtp server: tls options require the tokio-rustls feature")]
+    TlsOptionsNotSupportedError,
+    #[error("cannot detect encryption of smtp server {0}")]
+    DetectSmtpEncryptionError(ConnectionDiagnostics),
+    #[error("cannot connect to smtp server {1}")]
+    ConnectSmtpServerError(#[source] Box<Error>, ConnectionDiagnostics),
     #[error("cannot get smtp password")]
     GetPasswdSmtpError(#[source] secret::Error),
     #[error("cannot get smtp password: password is empty")]
     GetPasswdEmptySmtpError,
     #[error("cannot get access token")]
     AccessTokenWasNotAvailable,
-    #[error("cannot refresh access token")]
-    RefreshingAccessTokenFailed,
+    #[cfg(feature = "oauth2")]
+    #[error("cannot authenticate to smtp server using OAuth 2.0")]
+    AuthenticateOAuth2Error(#[source] crate::sasl::Error),
     #[error("resetting oauth failed")]
     ResettingOAuthFailed,
     #[error("configuring oauth failed")]
@@ -5,7 +5,9 @@
 
 use async_trait::async_trait;
 use futures::lock::Mutex;
-use mail_parser::{Addr, Address, HeaderName, HeaderValue, Message, MessageParser};
+use mail_parser::{Addr, Address, Header, HeaderName, HeaderValue, Message, MessageParser};
+#[cfg(feature = "oauth2")]
+use mail_send::Credentials;
 use mail_send::{
     smtp::message::{Address as SmtpAddress, IntoMessage, Message as SmtpMessage},
     SmtpClientBuilder,
@@ -21,14 +23,21 @@
 use self::config::{SmtpAuthConfig, SmtpConfig};
 #[doc(inline)]
 pub use self::error::{Error, Result};
+#[cfg(feature = "oauth2")]
+use crate::sasl;
+#[cfg(feature = "tokio-rustls")]
+use crate::tls::{info::TlsConnectionInfo, Encryption};
 use crate::{
-    account::config::AccountConfig,
+    account::{config::AccountConfig, runtime::AccountRuntime},
     backend::{
         context::{BackendContext, BackendContextBuilder},
         feature::{BackendFeature, CheckUp},
     },
-    message::send::{smtp::SendSmtpMessage, SendMessage},
+    diagnostic::ConnectionDiagnostics,
+    envelope::address,
+    message::send::{sanitize::sanitize_message, smtp::SendSmtpMessage, SendMessage},
     retry::{Retry, RetryState},
+    sasl::SaslMechanism,
     AnyResult,
 };
 
@@ -43,16 +52,42 @@ pub struct SmtpContext {
     /// The SMTP configuration.
     pub smtp_config: Arc<SmtpConfig>,
 
+    /// The index of the relay the client is connected to, see
+    /// [`SmtpConfig::relays`].
+    relay: usize,
+
     /// The SMTP client builder.
     client_builder: mail_send::SmtpClientBuilder<String>,
 
     /// The SMTP client.
     client: SmtpClientStream,
+
+    /// The SASL mechanism that succeeded during authentication, if
+    /// known.
+    auth_mechanism: Option<SaslMechanism>,
+
+    /// The maximum size of messages accepted by the relay, as
+    /// advertised by the SIZE extension (RFC 1870).
+    ///
+    /// The size is queried lazily, then cached until the client
+    /// reconnects. A size of 0 means that the relay does not
+    /// advertise any limit.
+    max_message_size: Option<usize>,
 }
 
 impl SmtpContext {
-    pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
+    /// Send the given raw message.
+    ///
+    /// When the connection breaks, the client reconnects to the
+    /// current relay, then falls back to the next relays. The
+    /// returned report tells which relay accepted the message.
+    ///
+    /// Messages exceeding the maximum size advertised by the relay
+    /// are given to the too-large hook, if any, then rejected before
+    /// transmitting any byte.
+    pub async fn send(&mut self, msg: &[u8]) -> Result<SmtpSendReport> {
         let buffer: Vec<u8>;
+        let lighter_buffer: Vec<u8>;
 
         let mut msg = MessageParser::new().parse(msg).unwrap_or_else(|| {
             debug!("cannot parse raw email message");
@@ -75,13 +110,55 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
             }
         };
 
+        // envelope recipients are computed from the original
+        // message, so that Bcc recipients are kept even if their
+        // headers are stripped from the transmitted message
+        let strip_bcc = self.account_config.should_strip_bcc_sent_message();
+        let line_len = self.account_config.find_sent_message_header_line_length();
+        let mut body = sanitize_message(msg.raw_message(), strip_bcc, line_len)
+            .map_err(Error::SanitizeMessageError)?;
+
+        let max_size = self.max_message_size().await;
+
+        if max_size > 0 && body.len() > max_size {
+            if let Some(cmd) = self.account_config.find_message_send_too_large_hook() {
+                debug!(size = body.len(), max_size, "running too-large hook");
+                match cmd.run_with(msg.raw_message()).await {
+                    Ok(res) => {
+                        lighter_buffer = res.into();
+                        msg = MessageParser::new()
+                            .parse(&lighter_buffer)
+                            .unwrap_or_else(|| {
+                                debug!("cannot parse email raw message");
+                                Default::default()
+                            });
+                        body = sanitize_message(msg.raw_message(), strip_bcc, line_len)
+                            .map_err(Error::SanitizeMessageError)?;
+                    }
+                    Err(_err) => {
+                        debug!("cannot execute too-large hook: {_err}");
+                        debug!("{_err:?}");
+                    }
+                }
+            }
+
+            if body.len() > max_size {
+                return Err(Error::MessageTooLargeError(body.len(), max_size));
+            }
+        }
+
         let mut retry = Retry::default();
 
         loop {
             // NOTE: cannot clone the final message
-            let msg = into_smtp_msg(msg.clone())?;
-
-            match retry.next(retry.timeout(self.client.send(msg)).await) {
+            let mut msg = into_smtp_msg(msg.clone())?;
+            msg.body = body.clone();
+
+            match retry.next(
+                retry
+                    .timeout_after(self.relay_config().timeout(), self.client.send(msg))
+                    .await,
+            ) {
                 RetryState::Retry => {
                     debug!(attempt = retry.attempts, "request timed out");
                     continue;
@@ -89,8 +166,12 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
                 RetryState::TimedOut => {
                     break Err(Error::SendMessageTimedOutError);
                 }
-                RetryState::Ok(Ok(res)) => {
-                    break Ok(res);
+                RetryState::Ok(Ok(())) => {
+                    let relay = self.relay_config();
+                    break Ok(SmtpSendReport {
+                        host: relay.host.clone(),
+                        port: relay.port,
+                    });
                 }
                 RetryState::Ok(Err(err)) => {
                     match err {
@@ -112,13 +193,7 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
                     };
 
                     debug!("re-connecting…");
-
-                    self.client = if self.smtp_config.is_encryption_enabled() {
-                        build_tls_client(&self.client_builder).await
-                    } else {
-                        build_tcp_client(&self.client_builder).await
-                    }?;
-
+                    self.reconnect().await?;
                     retry.reset();
                     continue;
                 }
@@ -126,9 +201,113 @@ pub async fn send(&mut self, msg: &[u8]) -> Result<()> {
         }
     }
 
+    /// Reconnect to the current relay, or to the next relays if the
+    /// current one cannot be reached anymore.
+    async fn reconnect(&mut self) -> Result<()> {
+        let relay = self.relay_config();
+
+        let client = if relay.is_encryption_enabled() {
+            build_tls_client(&self.client_builder).await
+        } else {
+            build_tcp_client(&self.client_builder).await
+        };
+
+        let err = match client {
+            Ok(client) => {
+                self.client = client;
+                self.max_message_size = None;
+                return Ok(());
+            }
+            Err(err) => err,
+        };
+
+        if self.relay + 1 >= self.smtp_config.relays().count() {
+            return Err(err);
+        }
+
+        warn!(
+            host = relay.host,
+            port = relay.port,
+            ?err,
+            "cannot reconnect to smtp relay"
+        );
+
+        let conn = connect_relays(
+            &self.account_config.runtime,
+            &self.smtp_config,
+            self.relay + 1,
+        )
+        .await?;
+        self.relay = conn.relay;
+        self.client_builder = conn.client_builder;
+        self.client = conn.client;
+        self.auth_mechanism = conn.auth_mechanism;
+        self.max_message_size = None;
+
+        Ok(())
+    }
+
+    /// Return the maximum size of messages accepted by the relay, in
+    /// bytes.
+    ///
+    /// Returns 0 if the relay does not advertise any limit, or if its
+    /// capabilities cannot be queried.
+    pub async fn max_message_size(&mut self) -> usize {
+        if let Some(size) = self.max_message_size {
+            return size;
+        }
+
+        let local_host = &self.client_builder.local_host;
+        let is_lmtp = self.client_builder.is_lmtp;
+
+        let size = match self.client.capabilities(local_host, is_lmtp).await {
+            Ok(size) => size,
+            Err(err) => {
+                warn!(?err, "cannot query smtp capabilities, skipping size check");
+                0
+            }
+        };
+
+        self.max_message_size = Some(size);
+        size
+    }
+
     pub async fn noop(&mut self) -> Result<()> {
         self.client.noop().await
     }
+
+    /// Return the information negotiated during the TLS handshake
+    /// with the current relay, unless the connection is not
+    /// encrypted.
+    #[cfg(feature = "tokio-rustls")]
+    pub fn tls_info(&self) -> Option<TlsConnectionInfo> {
+        self.client.tls_info()
+    }
+
+    /// Return the configuration of the relay the client is connected
+    /// to.
+    pub fn relay_config(&self) -> &SmtpConfig {
+        self.smtp_config
+            .relays()
+            .nth(self.relay)
+            .unwrap_or(&self.smtp_config)
+    }
+
+    /// Return the SASL mechanism used to authenticate the client, if
+    /// known.
+    pub fn auth_mechanism(&self) -> Option<SaslMechanism> {
+        self.auth_mechanism
+    }
+}
+
+/// The report of a message accepted by an SMTP relay.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SmtpSendReport {
+    /// The host name of the relay which accepted the message.
+    pub host: String,
+
+    /// The host port of the relay which accepted the message.
+    pub port: u16,
 }
 
 /// The sync version of the SMTP backend context.
@@ -175,25 +354,23 @@ fn send_message(&self) -> Option<BackendFeature<Self::Context, dyn SendMessage>>
     /// The SMTP client is created at this moment. If the client
     /// cannot be created using the OAuth 2.0 authentication, the
     /// access token is refreshed first then a new client is created.
+    /// If the SMTP server cannot be reached, fallback relays are
+    /// tried in order.
     async fn build(self) -> AnyResult<Self::Context> {
         info!("building new smtp context");
 
-        let mut client_builder =
-            SmtpClientBuilder::new(self.smtp_config.host.clone(), self.smtp_config.port)
-                .credentials(self.smtp_config.credentials().await?)
-                .implicit_tls(!self.smtp_config.is_start_tls_encryption_enabled());
-
-        if self.smtp_config.is_encryption_disabled() {
-            client_builder = client_builder.allow_invalid_certs();
-        }
+        self.account_config.ensure_online()?;
 
-        let (client_builder, client) = build_client(&self.smtp_config, client_builder).await?;
+        let conn = connect_relays(&self.account_config.runtime, &self.smtp_config, 0).await?;
 
         let ctx = SmtpContext {
             account_config: self.account_config,
             smtp_config: self.smtp_config,
-            client_builder,
-            client,
+            relay: conn.relay,
+            client_builder: conn.client_builder,
+            client: conn.client,
+            auth_mechanism: conn.auth_mechanism,
+            max_message_size: None,
         };
 
         Ok(Arc::new(Mutex::new(ctx)))
@@ -213,12 +390,34 @@ pub async fn send(&mut self, msg: impl IntoMessage<'_>) -> mail_send::Result<()>
         }
     }
 
+    /// Query the capabilities of the server, and return the maximum
+    /// size of messages it accepts (0 means no limit).
+    pub async fn capabilities(&mut self, local_host: &str, is_lmtp: bool) -> Result<usize> {
+        let res = match self {
+            Self::Tcp(client) => client.capabilities(local_host, is_lmtp).await,
+            Self::Tls(client) => client.capabilities(local_host, is_lmtp).await,
+        };
+
+        res.map(|ehlo| ehlo.size)
+            .map_err(Error::QueryCapabilitiesError)
+    }
+
     pub async fn noop(&mut self) -> Result<()> {
         match self {
             Self::Tcp(client) => client.noop().await.map_err(Error::MailSendNoOpFailed),
             Self::Tls(client) => client.noop().await.map_err(Error::MailSendNoOpFailed),
         }
     }
+
+    /// Return the information negotiated during the TLS handshake,
+    /// unless the connection is not encrypted.
+    #[cfg(feature = "tokio-rustls")]
+    pub fn tls_info(&self) -> Option<TlsConnectionInfo> {
+        match self {
+            Self::Tcp(_) => None,
+            Self::Tls(client) => Some(TlsConnectionInfo::from_rustls(client.tls_connection())),
+        }
+    }
 }
 
 #[derive(Clone)]
@@ -244,61 +443,210 @@ pub fn some_new_boxed(ctx: &SmtpContextSync) -> Option<Box<dyn CheckUp>> {
 impl CheckUp for CheckUpSmtp {
     async fn check_up(&self) -> AnyResult<()> {
         let mut ctx = self.ctx.lock().await;
+        ctx.account_config.ensure_online()?;
         Ok(ctx.noop().await?)
     }
 }
 
-pub async fn build_client(
+/// The connection to an SMTP relay.
+struct SmtpConnection {
+    relay: usize,
+    client_builder: mail_send::SmtpClientBuilder<String>,
+    client: SmtpClientStream,
+    auth_mechanism: Option<SaslMechanism>,
+}
+
+/// Connect to the first reachable SMTP relay, starting from the relay
+/// at the given index (see [`SmtpConfig::relays`]).
+///
+/// The error of the last relay is returned if none can be reached.
+async fn connect_relays(
+    runtime: &AccountRuntime,
     smtp_config: &SmtpConfig,
-    #[cfg_attr(not(feature = "oauth2"), allow(unused_mut))]
-    mut client_builder: mail_send::SmtpClientBuilder<String>,
-) -> Result<(mail_send::SmtpClientBuilder<String>, SmtpClientStream)> {
-    match (&smtp_config.auth, smtp_config.is_encryption_enabled()) {
-        (SmtpAuthConfig::Password(_), false) => {
-            let client = build_tcp_client(&client_builder).await?;
-            Ok((client_builder, client))
-        }
-        (SmtpAuthConfig::Password(_), true) => {
-            let client = build_tls_client(&client_builder).await?;
-            Ok((client_builder, client))
-        }
-        #[cfg(feature = "oauth2")]
-        (SmtpAuthConfig::OAuth2(oauth2_config), false) => {
-            match Ok(build_tcp_client(&client_builder).await?) {
-                Ok(client) => Ok((client_builder, client)),
-                Err(Error::ConnectTcpSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
-                    warn!("authentication failed, refreshing access token and retrying…");
-                    oauth2_config
-                        .refresh_access_token()
-                        .await
-                        .map_err(|_| Error::RefreshingAccessTokenFailed)?;
-                    client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tcp_client(&client_builder).await?;
-                    Ok((client_builder, client))
+    from: usize,
+) -> Result<SmtpConnection> {
+    let mut last_err = None;
+
+    for (relay, config) in smtp_config.relays().enumerate().skip(from) {
+        match connect(runtime, config).await {
+            Ok((client_builder, client, auth_mechanism)) => {
+                if relay > 0 {
+                    info!(
+                        host = config.host,
+                        port = config.port,
+                        "using fallback smtp relay"
+                    );
                 }
-                Err(err) => Err(err),
+
+                return Ok(SmtpConnection {
+                    relay,
+                    client_builder,
+                    client,
+                    auth_mechanism,
+                });
+            }
+            Err(err) => {
+                warn!(
+                    host = config.host,
+                    port = config.port,
+                    ?err,
+                    "cannot connect to smtp relay"
+                );
+                last_err = Some(err);
             }
         }
+    }
+
+    Err(last_err.unwrap_or(Error::ConnectSmtpRelayNotFoundError))
+}
+
+/// Connect to the given SMTP server, using its own encryption and
+/// authentication settings.
+///
+/// The encryption is detected first if
+/// [`SmtpConfig::auto_encryption`] is enabled.
+///
+/// Connection errors are diagnosed, see [`ConnectionDiagnostics`].
+///
+/// See [`build_client`].
+pub async fn connect(
+    runtime: &AccountRuntime,
+    smtp_config: &SmtpConfig,
+) -> Result<(
+    mail_send::SmtpClientBuilder<String>,
+    SmtpClientStream,
+    Option<SaslMechanism>,
+)> {
+    let smtp_config = &*smtp_config.resolve_encryption(runtime).await?;
+
+    let mut client_builder = SmtpClientBuilder::new(smtp_config.host.clone(), smtp_config.port)
+        .credentials(smtp_config.credentials(runtime).await?)
+        .implicit_tls(!smtp_config.is_start_tls_encryption_enabled());
+
+    if let Some(timeout) = smtp_config.timeout {
+        client_builder = client_builder.timeout(timeout);
+    }
+
+    // the TLS connector is picked once, so that the TLS options can
+    // neither be overridden nor override another connector
+    client_builder = match smtp_config.encryption.as_ref() {
+        Some(Encryption::None) => client_builder.allow_invalid_certs(),
+        #[cfg(feature = "tokio-rustls")]
+        Some(Encryption::Tls(tls) | Encryption::StartTls(tls)) if tls.is_customized() => {
+            client_builder.tls_connector = tls
+                .build_rustls_connector()
+                .map_err(Error::BuildTlsConnectorSmtpError)?;
+            client_builder
+        }
+        #[cfg(not(feature = "tokio-rustls"))]
+        Some(Encryption::Tls(tls) | Encryption::StartTls(tls)) if tls.is_customized() => {
+            return Err(Error::TlsOptionsNotSupportedError);
+        }
+        _ => client_builder,
+    };
+
+    match build_client(runtime, smtp_config, client_builder).await {
+        Err(err @ (Error::ConnectTcpSmtpError(_) | Error::ConnectTlsSmtpError(_))) => {
+            let host = &smtp_config.host;
+            let port = smtp_config.port;
+            let level = smtp_config.expected_security_level();
+            let tls = smtp_config.find_tls();
+            let diagnostics = ConnectionDiagnostics::probe_with_tls(host, port, level, &tls).await;
+            Err(Error::ConnectSmtpServerError(Box::new(err), diagnostics))
+        }
+        res => res,
+    }
+}
+
+/// Build an SMTP client.
+///
+/// Returns the client builder, the client and the SASL mechanism
+/// that succeeded (if known).
+///
+/// When using OAuth 2.0, the fallback chain and the retry after
+/// access token refresh are handled by
+/// [`sasl::authenticate_oauth2`].
+pub async fn build_client(
+    #[cfg_attr(not(feature = "oauth2"), allow(unused_variables))] runtime: &AccountRuntime,
+    smtp_config: &SmtpConfig,
+    client_builder: mail_send::SmtpClientBuilder<String>,
+) -> Result<(
+    mail_send::SmtpClientBuilder<String>,
+    SmtpClientStream,
+    Option<SaslMechanism>,
+)> {
+    let encrypted = smtp_config.is_encryption_enabled();
+
+    match &smtp_config.auth {
+        SmtpAuthConfig::Password(_) => {
+            let client = if encrypted {
+                build_tls_client(&client_builder).await?
+            } else {
+                build_tcp_client(&client_builder).await?
+            };
+
+            // NOTE: the SASL mechanism is negotiated by mail-send and
+            // cannot be retrieved
+            Ok((client_builder, client, None))
+        }
         #[cfg(feature = "oauth2")]
-        (SmtpAuthConfig::OAuth2(oauth2_config), true) => {
-            match Ok(build_tls_client(&client_builder).await?) {
-                Ok(client) => Ok((client_builder, client)),
-                Err(Error::ConnectTlsSmtpError(mail_send::Error::AuthenticationFailed(_))) => {
-                    warn!("authentication failed, refreshing access token and retrying…");
-                    oauth2_config
-                        .refresh_access_token()
-                        .await
-                        .map_err(|_| Error::RefreshingAccessTokenFailed)?;
-                    client_builder = client_builder.credentials(smtp_config.credentials().await?);
-                    let client = build_tls_client(&client_builder).await?;
-                    Ok((client_builder, client))
-                }
-                Err(err) => Err(err),
-            }
+        SmtpAuthConfig::OAuth2(oauth2_config) => {
+            let auth = sasl::authenticate_oauth2(
+                runtime,
+                oauth2_config,
+                &smtp_config.login,
+                None,
+                // mail-send negotiates the mechanism on its own, so
+                // both of them are tried
+                |_| true,
+                is_auth_failure,
+                client_builder,
+                |client_builder, mechanism, access_token| async move {
+                    let credentials = match mechanism {
+                        SaslMechanism::XOAuth2 => {
+                            Credentials::new_xoauth2(smtp_config.login.clone(), access_token)
+                        }
+                        _ => Credentials::new_oauth(smtp_config.oauthbearer_payload(&access_token)),
+                    };
+
+                    let client_builder = client_builder.credentials(credentials);
+
+                    let client = if encrypted {
+                        build_tls_client(&client_builder).await
+                    } else {
+                        build_tcp_client(&client_builder).await
+                    };
+
+                    (client_builder, client)
+                },
+            )
+            .await
+            .map_err(Error::AuthenticateOAuth2Error)?;
+
+            Ok((auth.context, auth.output, Some(auth.mechanism)))
         }
     }
 }
 
+/// Return `true` if the given error comes from the server rejecting
+/// the credentials.
+///
+/// Servers reply `535` when rejecting credentials, or `334` when
+/// sending an OAuth 2.0 error challenge the client does not answer
+/// (RFC 4954, RFC 7628).
+#[cfg(feature = "oauth2")]
+fn is_auth_failure(err: &Error) -> bool {
+    match err {
+        Error::ConnectTcpSmtpError(err) | Error::ConnectTlsSmtpError(err) => matches!(
+            err,
+            mail_send::Error::AuthenticationFailed(res)
+                | mail_send::Error::UnexpectedReply(res)
+                if res.code == 535 || res.code == 334
+        ),
+        _ => false,
+    }
+}
+
 pub async fn build_tcp_client(
     client_builder: &mail_send::SmtpClientBuilder<String>,
 ) -> Result<SmtpClientStream> {
@@ -326,12 +674,40 @@ fn into_smtp_msg(msg: Message<'_>) -> Result<SmtpMessage<'_>> {
     let mut mail_from = None;
     let mut rcpt_to = HashSet::new();
 
-    for header in msg.headers() {
+    // Resent messages must be delivered to the recipients of the
+    // topmost Resent-* block instead of the original ones (RFC 5322
+    // section 3.6.6). Other blocks belong to previous re-sendings.
+    let resent_block = find_topmost_resent_block(msg.headers());
+    let resent = resent_block.iter().any(|header| {
+        matches!(
+            header.name,
+            HeaderName::ResentTo | HeaderName::ResentCc | HeaderName::ResentBcc
+        )
+    });
+    let headers = if resent { resent_block } else { msg.headers() };
+
+    let (from, to, cc, bcc) = if resent {
+        (
+            HeaderName::ResentFrom,
+            HeaderName::ResentTo,
+            HeaderName::ResentCc,
+            HeaderName::ResentBcc,
+        )
+    } else {
+        (
+            HeaderName::From,
+            HeaderName::To,
+            HeaderName::Cc,
+            HeaderName::Bcc,
+        )
+    };
+
+    for header in headers {
         let key = &header.name;
         let val = header.value();
 
         match key {
-            HeaderName::From => match val {
+            key if *key == from => match val {
                 HeaderValue::Address(Address::List(addrs)) => {
                     if let Some(email) = addrs.first().and_then(find_valid_email) {
                         mail_from = email.to_string().into();
@@ -346,7 +722,7 @@ fn into_smtp_msg(msg: Message<'_>) -> Result<SmtpMessage<'_>> {
                 }
                 _ => (),
             },
-            HeaderName::To | HeaderName::Cc | HeaderName::Bcc => match val {
+            key if *key == to || *key == cc || *key == bcc => match val {
                 HeaderValue::Address(Address::List(addrs)) => {
                     rcpt_to.extend(addrs.iter().filter_map(find_valid_email));
                 }
@@ -368,23 +744,80 @@ fn into_smtp_msg(msg: Message<'_>) -> Result<SmtpMessage<'_>> {
         return Err(Error::SendMessageMissingRecipientError);
     }
 
+    let mail_from = mail_from.ok_or(Error::SendMessageMissingSenderError)?;
+
+    // Unicode local parts cannot be encoded, they require the
+    // SMTPUTF8 extension (RFC 6531). Unicode domains are encoded
+    // using IDNA so that servers without SMTPUTF8 accept them.
+    let smtputf8 = address::requires_smtputf8(&mail_from)
+        || rcpt_to
+            .iter()
+            .any(|email| address::requires_smtputf8(email));
+
+    let mut mail_from = SmtpAddress::new(
+        address::addr_to_ascii(&mail_from).map_err(Error::EncodeEnvelopeAddressError)?,
+        Default::default(),
+    );
+
+    if smtputf8 {
+        debug!("envelope contains unicode local parts, enabling SMTPUTF8");
+        mail_from.parameters.add("SMTPUTF8");
+    }
+
     let msg = SmtpMessage {
-        mail_from: mail_from
-            .ok_or(Error::SendMessageMissingSenderError)?
-            .into(),
+        mail_from,
         rcpt_to: rcpt_to
             .into_iter()
-            .map(|email| SmtpAddress {
-                email: email.into(),
-                ..Default::default()
+            .map(|email| {
+                let email = address::addr_to_ascii(&email)?;
+                Ok(SmtpAddress::new(email, Default::default()))
             })
-            .collect(),
+            .collect::<crate::email::Result<_>>()
+            .map_err(Error::EncodeEnvelopeAddressError)?,
         body: msg.raw_message,
     };
 
     Ok(msg)
 }
 
+/// Return the topmost block of Resent-* headers, prepended by the
+/// last re-sending.
+///
+/// A block is made of adjacent Resent-* headers, each of them
+/// appearing at most once: a repeated header starts the next block.
+fn find_topmost_resent_block<'a, 'x>(headers: &'a [Header<'x>]) -> &'a [Header<'x>] {
+    let Some(start) = headers.iter().position(|h| is_resent_header(&h.name)) else {
+        return &[];
+    };
+
+    let mut seen = Vec::new();
+    let mut end = start;
+
+    for header in &headers[start..] {
+        if !is_resent_header(&header.name) || seen.contains(&header.name) {
+            break;
+        }
+
+        seen.push(header.name.clone());
+        end += 1;
+    }
+
+    &headers[start..end]
+}
+
+fn is_resent_header(name: &HeaderName) -> bool {
+    matches!(
+        name,
+        HeaderName::ResentDate
+            | HeaderName::ResentFrom
+            | HeaderName::ResentSender
+            | HeaderName::ResentTo
+            | HeaderName::ResentCc
+            | HeaderName::ResentBcc
+            | HeaderName::ResentMessageId
+    )
+}
+
 fn find_valid_email(addr: &Addr) -> Option<String> {
     match &addr.address {
         None => None,
@@ -398,3 +831,140 @@ fn find_valid_email(addr: &Addr) -> Option<String> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+    use mail_parser::MessageParser;
+    use secret::Secret;
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::{config::SmtpConfig, Error};
+    use crate::{
+        account::{config::passwd::PasswordConfig, runtime::AccountRuntime},
+        smtp::config::SmtpAuthConfig,
+        tls::Encryption,
+    };
+
+    fn relay(port: u16) -> SmtpConfig {
+        SmtpConfig {
+            host: "127.0.0.1".into(),
+            port,
+            encryption: Some(Encryption::None),
+            login: "login".into(),
+            auth: SmtpAuthConfig::Password(PasswordConfig(Secret::new_raw("passwd"))),
+            ..Default::default()
+        }
+    }
+
+    /// Return a local port nothing listens on.
+    async fn closed_port() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().port()
+    }
+
+    /// Spawn a plaintext SMTP server accepting any PLAIN
+    /// authentication, and return its port.
+    async fn spawn_relay() -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+
+            writer.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+
+            while let Ok(Some(line)) = lines.next_line().await {
+                let res: &[u8] = if line.starts_with("EHLO") {
+                    b"250-localhost\r\n250 AUTH PLAIN\r\n"
+                } else if line.starts_with("AUTH") {
+                    b"235 2.7.0 Authentication successful\r\n"
+                } else {
+                    b"250 OK\r\n"
+                };
+
+                writer.write_all(res).await.unwrap();
+            }
+        });
+
+        port
+    }
+
+    #[tokio::test]
+    async fn connect_fallback_relay() {
+        let config = SmtpConfig {
+            fallbacks: vec![relay(spawn_relay().await)],
+            ..relay(closed_port().await)
+        };
+
+        let runtime = AccountRuntime::new();
+        let conn = super::connect_relays(&runtime, &config, 0).await.unwrap();
+        assert_eq!(conn.relay, 1);
+    }
+
+    #[tokio::test]
+    async fn connect_no_relay() {
+        let config = SmtpConfig {
+            fallbacks: vec![relay(closed_port().await)],
+            ..relay(closed_port().await)
+        };
+
+        let runtime = AccountRuntime::new();
+        let err = super::connect_relays(&runtime, &config, 0).await.err();
+        assert!(matches!(err, Some(Error::ConnectSmtpServerError(..))));
+
+        // relays before the given index are skipped
+        let err = super::connect_relays(&runtime, &config, 2).await.err();
+        assert!(matches!(err, Some(Error::ConnectSmtpRelayNotFoundError)));
+    }
+
+    #[test]
+    fn into_smtp_msg_uses_topmost_resent_block() {
+        let msg = concat_line!(
+            "Resent-From: carol@localhost",
+            "Resent-Date: Tue, 2 Jan 2024 12:00:00 +0000",
+            "Resent-To: dave@localhost",
+            "Resent-From: bob@localhost",
+            "Resent-Date: Mon, 1 Jan 2024 12:00:00 +0000",
+            "Resent-To: erin@localhost",
+            "Resent-Cc: frank@localhost",
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "",
+            "Hello, world!",
+        );
+
+        let msg = MessageParser::new().parse(msg.as_bytes()).unwrap();
+        let msg = super::into_smtp_msg(msg).unwrap();
+
+        assert_eq!(msg.mail_from.email, "carol@localhost");
+
+        let rcpt_to: Vec<_> = msg.rcpt_to.iter().map(|a| a.email.as_ref()).collect();
+        assert_eq!(rcpt_to, vec!["dave@localhost"]);
+    }
+
+    #[test]
+    fn into_smtp_msg_without_resent_block() {
+        let msg = concat_line!(
+            "From: alice@localhost",
+            "To: bob@localhost",
+            "Cc: carol@localhost",
+            "",
+            "Hello, world!",
+        );
+
+        let msg = MessageParser::new().parse(msg.as_bytes()).unwrap();
+        let msg = super::into_smtp_msg(msg).unwrap();
+
+        assert_eq!(msg.mail_from.email, "alice@localhost");
+
+        let mut rcpt_to: Vec<_> = msg.rcpt_to.iter().map(|a| a.email.as_ref()).collect();
+        rcpt_to.sort();
+        assert_eq!(rcpt_to, vec!["bob@localhost", "carol@localhost"]);
+    }
+}
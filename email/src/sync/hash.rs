@@ -1,9 +1,161 @@
 //! # Sync hash
 //!
 //! Module dedicated to synchronization hashing.
+//!
+//! Backend builders are hashed in order to identify their
+//! synchronization directories (cache, state and lock files). The
+//! hash must then be stable across releases, which is why a SHA-256
+//! digest of the relevant configuration fields is used, see
+//! [`SyncHasher`].
+
+use std::{
+    fmt::Write,
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+};
 
-use std::hash::DefaultHasher;
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+/// The version of the sync hash.
+///
+/// The version is part of the hashed data: bumping it changes every
+/// synchronization directory name.
+pub const SYNC_HASH_VERSION: u8 = 1;
 
 pub trait SyncHash {
-    fn sync_hash(&self, state: &mut DefaultHasher);
+    fn sync_hash(&self, state: &mut SyncHasher);
+}
+
+/// The synchronization hasher.
+///
+/// Fields are written with an explicit length prefix into a SHA-256
+/// digest, which makes the hash stable across Rust releases, unlike
+/// the [`DefaultHasher`].
+///
+/// The hasher also computes the legacy hash, based on the
+/// [`DefaultHasher`], so that directories named after it can be
+/// migrated, see [`migrate_legacy_dir`].
+#[derive(Clone, Debug)]
+pub struct SyncHasher {
+    digest: Sha256,
+    legacy: DefaultHasher,
+}
+
+impl SyncHasher {
+    /// Create a new synchronization hasher.
+    pub fn new() -> Self {
+        let mut digest = Sha256::new();
+        digest.update(b"pimalaya-sync-hash");
+        digest.update([SYNC_HASH_VERSION]);
+
+        Self {
+            digest,
+            legacy: DefaultHasher::new(),
+        }
+    }
+
+    fn write_field(&mut self, bytes: &[u8]) {
+        self.digest.update((bytes.len() as u64).to_be_bytes());
+        self.digest.update(bytes);
+    }
+
+    /// Write the given string.
+    pub fn write_str(&mut self, s: &str) {
+        self.write_field(s.as_bytes());
+        Hash::hash(s, &mut self.legacy);
+    }
+
+    /// Write the given port number.
+    pub fn write_u16(&mut self, n: u16) {
+        self.write_field(&n.to_be_bytes());
+        Hash::hash(&n, &mut self.legacy);
+    }
+
+    /// Write the given path.
+    pub fn write_path(&mut self, path: &Path) {
+        self.write_field(path.as_os_str().as_encoded_bytes());
+        Hash::hash(path, &mut self.legacy);
+    }
+
+    /// Return the stable hash, as a 16 characters long hexadecimal
+    /// string.
+    pub fn finish(&self) -> String {
+        self.digest.clone().finalize()[..8]
+            .iter()
+            .fold(String::with_capacity(16), |mut hash, b| {
+                let _ = write!(hash, "{b:02x}");
+                hash
+            })
+    }
+
+    /// Return the legacy hash, based on the [`DefaultHasher`].
+    pub fn finish_legacy(&self) -> String {
+        format!("{:x}", self.legacy.finish())
+    }
+}
+
+impl Default for SyncHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Migrate the directory named after the legacy hash of the given
+/// hasher to the one named after its stable hash, both located in
+/// the given parent directory.
+///
+/// Nothing is done if the stable directory already exists, or if the
+/// legacy one does not exist. Returns the stable directory path.
+pub fn migrate_legacy_dir(parent: impl AsRef<Path>, hasher: &SyncHasher) -> io::Result<PathBuf> {
+    let parent = parent.as_ref();
+    let dir = parent.join(hasher.finish());
+    let legacy_dir = parent.join(hasher.finish_legacy());
+
+    if dir.exists() || !legacy_dir.is_dir() {
+        debug!(?dir, "no legacy sync directory to migrate");
+        return Ok(dir);
+    }
+
+    info!(?legacy_dir, ?dir, "migrating legacy sync directory");
+    fs::rename(&legacy_dir, &dir)?;
+
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{migrate_legacy_dir, SyncHasher};
+
+    #[test]
+    fn stable_hash() {
+        let mut hasher = SyncHasher::new();
+        hasher.write_str("localhost");
+        hasher.write_u16(993);
+        hasher.write_path(Path::new("/tmp/mail"));
+
+        // must never change, otherwise sync directories are orphaned
+        assert_eq!(hasher.finish(), "9673a0a1197d05f9");
+    }
+
+    #[test]
+    fn migrate_legacy() {
+        let root = tempfile::tempdir().unwrap();
+        let parent = root.path().to_owned();
+
+        let mut hasher = SyncHasher::new();
+        hasher.write_str("localhost");
+
+        let legacy_dir = parent.join(hasher.finish_legacy());
+        fs::create_dir_all(&legacy_dir).unwrap();
+
+        let dir = migrate_legacy_dir(&parent, &hasher).unwrap();
+        assert_eq!(dir, parent.join(hasher.finish()));
+        assert!(dir.is_dir());
+        assert!(!legacy_dir.exists());
+    }
 }
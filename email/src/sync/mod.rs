@@ -14,7 +14,6 @@
     env, fmt,
     fs::{self, OpenOptions},
     future::Future,
-    hash::{DefaultHasher, Hash, Hasher},
     path::PathBuf,
     pin::Pin,
     sync::Arc,
@@ -23,12 +22,16 @@
 use advisory_lock::{AdvisoryFileLock, FileLockMode};
 use dirs::{cache_dir, runtime_dir};
 use once_cell::sync::Lazy;
-use tracing::debug;
+use tracing::{debug, warn};
 
 #[doc(inline)]
 pub use self::error::{Error, Result};
-use self::{hash::SyncHash, report::SyncReport};
+use self::{
+    hash::{SyncHash, SyncHasher},
+    report::SyncReport,
+};
 use crate::{
+    account::health::HealthOperation,
     backend::{context::BackendContextBuilder, BackendBuilder},
     email::{self, sync::hunk::EmailSyncHunk},
     envelope::sync::config::EnvelopeSyncFilters,
@@ -42,6 +45,7 @@
         },
     },
     maildir::{config::MaildirConfig, MaildirContextBuilder},
+    message::delete::retention::PurgeExpiredMessages,
     message::sync::config::MessageSyncPermissions,
     sync::pool::{SyncPoolConfig, SyncPoolContextBuilder},
 };
@@ -61,8 +65,10 @@
 pub struct SyncBuilder<L: BackendContextBuilder + SyncHash, R: BackendContextBuilder + SyncHash> {
     config: SyncPoolConfig,
     left_builder: BackendBuilder<L>,
+    left_hasher: SyncHasher,
     left_hash: String,
     right_builder: BackendBuilder<R>,
+    right_hasher: SyncHasher,
     right_hash: String,
     cache_dir: Option<PathBuf>,
 }
@@ -75,19 +81,21 @@ impl<L, R> SyncBuilder<L, R>
     /// Create a new synchronization builder using the two given
     /// backend builders.
     pub fn new(left_builder: BackendBuilder<L>, right_builder: BackendBuilder<R>) -> Self {
-        let mut left_hasher = DefaultHasher::new();
+        let mut left_hasher = SyncHasher::new();
         left_builder.sync_hash(&mut left_hasher);
-        let left_hash = format!("{:x}", left_hasher.finish());
+        let left_hash = left_hasher.finish();
 
-        let mut right_hasher = DefaultHasher::new();
+        let mut right_hasher = SyncHasher::new();
         right_builder.sync_hash(&mut right_hasher);
-        let right_hash = format!("{:x}", right_hasher.finish());
+        let right_hash = right_hasher.finish();
 
         Self {
             config: Default::default(),
             left_builder,
+            left_hasher,
             left_hash,
             right_builder,
+            right_hasher,
             right_hash,
             cache_dir: None,
         }
@@ -192,6 +200,56 @@ pub fn with_folder_filters(mut self, f: impl Into<FolderSyncStrategy>) -> Self {
         self
     }
 
+    // folder concurrency setters
+
+    /// Set the maximum number of folders synchronized in parallel.
+    ///
+    /// By default, all folders are synchronized in parallel. The
+    /// number of connections opened to a backend does not depend on
+    /// this option: IMAP connections are taken from the IMAP clients
+    /// pool, shared by all folders.
+    pub fn set_some_folder_concurrency(&mut self, n: Option<usize>) {
+        self.config.folder_concurrency = n;
+    }
+
+    pub fn set_folder_concurrency(&mut self, n: usize) {
+        self.set_some_folder_concurrency(Some(n));
+    }
+
+    pub fn with_some_folder_concurrency(mut self, n: Option<usize>) -> Self {
+        self.set_some_folder_concurrency(n);
+        self
+    }
+
+    pub fn with_folder_concurrency(mut self, n: usize) -> Self {
+        self.set_folder_concurrency(n);
+        self
+    }
+
+    // max in-flight bytes setters
+
+    /// Set the global budget of message bytes in flight, shared by
+    /// all folders.
+    ///
+    /// See [`pool::SyncBytesBudget`].
+    pub fn set_some_max_in_flight_bytes(&mut self, bytes: Option<usize>) {
+        self.config.max_in_flight_bytes = bytes;
+    }
+
+    pub fn set_max_in_flight_bytes(&mut self, bytes: usize) {
+        self.set_some_max_in_flight_bytes(Some(bytes));
+    }
+
+    pub fn with_some_max_in_flight_bytes(mut self, bytes: Option<usize>) -> Self {
+        self.set_some_max_in_flight_bytes(bytes);
+        self
+    }
+
+    pub fn with_max_in_flight_bytes(mut self, bytes: usize) -> Self {
+        self.set_max_in_flight_bytes(bytes);
+        self
+    }
+
     // left folder permissions setters
 
     pub fn set_some_left_folder_permissions(
@@ -384,6 +442,8 @@ pub fn get_left_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBuil
             Arc::new(MaildirConfig {
                 root_dir,
                 maildirpp: false,
+                clean_tmp: true,
+                expunge_flags: None,
             }),
         );
         let left_cache_builder = BackendBuilder::new(left_config, ctx);
@@ -398,6 +458,8 @@ pub fn get_right_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBui
             Arc::new(MaildirConfig {
                 root_dir,
                 maildirpp: false,
+                clean_tmp: true,
+                expunge_flags: None,
             }),
         );
         let right_cache_builder = BackendBuilder::new(right_config, ctx);
@@ -406,7 +468,22 @@ pub fn get_right_cache_builder(&self) -> Result<BackendBuilder<MaildirContextBui
 
     // build
 
+    /// Synchronize both backends.
+    ///
+    /// The outcome is recorded in the health log of the right
+    /// account, if any.
     pub async fn sync(self) -> Result<SyncReport> {
+        let health_log = self.right_builder.account_config.health_log();
+        let result = self.sync_unrecorded().await;
+
+        if let Some(log) = health_log {
+            log.record(HealthOperation::Sync, &result).await;
+        }
+
+        result
+    }
+
+    async fn sync_unrecorded(self) -> Result<SyncReport> {
         let left_lock_file_path = RUNTIME_DIR.join(format!("{}.lock", self.left_hash));
         debug!("locking left sync file {left_lock_file_path:?}");
         let left_lock_file = OpenOptions::new()
@@ -431,10 +508,21 @@ pub async fn sync(self) -> Result<SyncReport> {
             .try_lock(FileLockMode::Exclusive)
             .map_err(|err| Error::LockFileError(err, right_lock_file_path.clone()))?;
 
+        let cache_dir = self.get_cache_dir()?;
+        for hasher in [&self.left_hasher, &self.right_hasher] {
+            if let Err(err) = hash::migrate_legacy_dir(&cache_dir, hasher) {
+                warn!(?err, "cannot migrate legacy sync directory, skipping it");
+            }
+        }
+
         let mut left_cache_builder = self.get_left_cache_builder()?;
         let left_cache_check = left_cache_builder.ctx_builder.check_configuration();
 
         let mut left_builder = self.left_builder.clone();
+        let left_state_path = self.get_cache_dir()?.join(&self.left_hash);
+        left_builder
+            .ctx_builder
+            .set_sync_state_path(left_state_path);
         let left_check = left_builder.ctx_builder.check_configuration();
 
         match (left_cache_check, left_check) {
@@ -467,6 +555,10 @@ pub async fn sync(self) -> Result<SyncReport> {
         let right_cache_check = right_cache_builder.ctx_builder.check_configuration();
 
         let mut right_builder = self.right_builder.clone();
+        let right_state_path = self.get_cache_dir()?.join(&self.right_hash);
+        right_builder
+            .ctx_builder
+            .set_sync_state_path(right_state_path);
         let right_check = right_builder.ctx_builder.check_configuration();
 
         match (right_cache_check, right_check) {
@@ -517,6 +609,24 @@ pub async fn sync(self) -> Result<SyncReport> {
             .await
             .map_err(Error::SyncEmailsError)?;
 
+        if !ctx.dry_run {
+            if ctx
+                .left
+                .account_config
+                .should_auto_apply_message_retention()
+            {
+                ctx.left.apply_message_retention().await;
+            }
+
+            if ctx
+                .right
+                .account_config
+                .should_auto_apply_message_retention()
+            {
+                ctx.right.apply_message_retention().await;
+            }
+        }
+
         folder::sync::expunge::<L, R>(ctx.clone(), &report.folder.names).await;
 
         debug!("unlocking sync files");
@@ -556,6 +666,7 @@ pub enum SyncEvent {
     GeneratedEmailPatch(BTreeMap<FolderName, BTreeSet<EmailSyncHunk>>),
     ProcessedEmailHunk(EmailSyncHunk),
     ProcessedAllEmailHunks,
+    InvalidatedCache(FolderName, SyncDestination),
     ExpungedAllFolders,
 }
 
@@ -623,6 +734,9 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             SyncEvent::ProcessedAllEmailHunks => {
                 write!(f, "Processed all email hunks")
             }
+            SyncEvent::InvalidatedCache(folder, target) => {
+                write!(f, "Invalidated {target} cache of folder {folder}")
+            }
             SyncEvent::ExpungedAllFolders => {
                 write!(f, "Expunged all folders")
             }
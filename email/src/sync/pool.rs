@@ -1,5 +1,7 @@
 use std::{collections::BTreeSet, sync::Arc};
 
+use tokio::sync::{Semaphore, SemaphorePermit};
+
 #[doc(inline)]
 pub use super::{Error, Result};
 use super::{SyncDestination, SyncEventHandler};
@@ -30,6 +32,8 @@ pub struct SyncPoolConfig {
     pub right_flag_permissions: Option<FlagSyncPermissions>,
     pub right_message_permissions: Option<MessageSyncPermissions>,
     pub pool_size: Option<usize>,
+    pub folder_concurrency: Option<usize>,
+    pub max_in_flight_bytes: Option<usize>,
     pub folder_filters: Option<FolderSyncStrategy>,
     pub envelope_filters: Option<EnvelopeSyncFilters>,
     pub handler: Option<Arc<SyncEventHandler>>,
@@ -203,6 +207,8 @@ pub async fn build(self) -> AnyResult<SyncPoolContext<L::Context, R::Context>> {
             right_message_permissions,
             folder_filters,
             envelope_filters,
+            folder_concurrency: self.config.folder_concurrency.filter(|n| *n > 0),
+            bytes_budget: self.config.max_in_flight_bytes.map(SyncBytesBudget::new),
             handler: self.config.handler,
             dry_run: self.config.dry_run.unwrap_or_default(),
         })
@@ -222,11 +228,35 @@ pub struct SyncPoolContext<L: BackendContext, R: BackendContext> {
     pub right_message_permissions: MessageSyncPermissions,
     pub folder_filters: FolderSyncStrategy,
     pub envelope_filters: EnvelopeSyncFilters,
+
+    /// The maximum number of folders synchronized in parallel.
+    ///
+    /// [`None`] means that all folders are synchronized in parallel.
+    pub folder_concurrency: Option<usize>,
+
+    /// The global budget of message bytes in flight.
+    pub bytes_budget: Option<SyncBytesBudget>,
+
     pub handler: Option<Arc<SyncEventHandler>>,
     pub dry_run: bool,
 }
 
 impl<L: BackendContext, R: BackendContext> SyncPoolContext<L, R> {
+    /// Return the number of folders that can be synchronized in
+    /// parallel, out of the given total.
+    ///
+    /// Workers never exceed the concurrency budget of the accounts,
+    /// since they would only wait for a connection.
+    pub fn folder_concurrency(&self, total: usize) -> usize {
+        let budget = self
+            .left
+            .account_config
+            .find_concurrency_budget()
+            .min(self.right.account_config.find_concurrency_budget());
+
+        folder_concurrency(self.folder_concurrency, budget, total)
+    }
+
     pub fn apply_folder_permissions(&self, patch: &mut FolderSyncPatches) {
         use FolderSyncHunk::*;
         use SyncDestination::*;
@@ -250,6 +280,12 @@ pub fn apply_flag_and_message_permissions(&self, patch: &mut BTreeSet<EmailSyncH
             GetThenCache(_, _, Right) => self.right_message_permissions.create,
             CopyThenCache(_, _, _, Left, _) => self.left_message_permissions.create,
             CopyThenCache(_, _, _, Right, _) => self.right_message_permissions.create,
+            MoveThenCache(_, _, _, Left, _, _) => {
+                self.left_message_permissions.create && self.left_message_permissions.delete
+            }
+            MoveThenCache(_, _, _, Right, _, _) => {
+                self.right_message_permissions.create && self.right_message_permissions.delete
+            }
             UpdateCachedFlags(_, _, Left) => self.left_flag_permissions.update,
             UpdateCachedFlags(_, _, Right) => self.right_flag_permissions.update,
             UpdateFlags(_, _, Left) => self.left_flag_permissions.update,
@@ -259,3 +295,111 @@ pub fn apply_flag_and_message_permissions(&self, patch: &mut BTreeSet<EmailSyncH
         });
     }
 }
+
+/// Return the number of folders that can be synchronized in
+/// parallel, out of the given total, bounded by the given limit and
+/// concurrency budget.
+fn folder_concurrency(limit: Option<usize>, budget: u32, total: usize) -> usize {
+    limit
+        .unwrap_or(total)
+        .min(budget as usize)
+        .clamp(1, total.max(1))
+}
+
+/// The global budget of message bytes in flight.
+///
+/// Messages transferred from one backend to another are held in
+/// memory between their download and their upload. The budget limits
+/// the amount of bytes being transferred at the same time: it is
+/// acquired before the download, using the size announced by the
+/// source envelope (see [`Envelope::size`]), and released after the
+/// upload. A message bigger than the whole budget still gets
+/// transferred, alone.
+///
+/// [`Envelope::size`]: crate::envelope::Envelope::size
+#[derive(Debug)]
+pub struct SyncBytesBudget {
+    max: usize,
+    semaphore: Semaphore,
+}
+
+impl SyncBytesBudget {
+    pub fn new(max: usize) -> Self {
+        // permits are acquired using u32
+        let max = max.clamp(1, u32::MAX as usize);
+
+        Self {
+            max,
+            semaphore: Semaphore::new(max),
+        }
+    }
+
+    /// Wait until the given amount of bytes fits in the budget.
+    ///
+    /// The bytes are given back to the budget when the returned
+    /// permit is dropped.
+    pub async fn acquire(&self, bytes: usize) -> SemaphorePermit<'_> {
+        let permits = bytes.clamp(1, self.max) as u32;
+        self.semaphore
+            .acquire_many(permits)
+            .await
+            .expect("sync bytes budget semaphore should never be closed")
+    }
+
+    /// Take the given amount of bytes from the budget, only if it
+    /// fits right away.
+    pub fn try_acquire(&self, bytes: usize) -> Option<SemaphorePermit<'_>> {
+        let permits = bytes.clamp(1, self.max) as u32;
+        self.semaphore.try_acquire_many(permits).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use super::{folder_concurrency, SyncBytesBudget};
+
+    #[test]
+    fn folder_concurrency_bounds() {
+        // all folders in parallel by default
+        assert_eq!(folder_concurrency(None, 10, 4), 4);
+
+        // bounded by the limit, then by the concurrency budget
+        assert_eq!(folder_concurrency(Some(2), 10, 4), 2);
+        assert_eq!(folder_concurrency(Some(8), 3, 4), 3);
+
+        // at least one worker, even without folders
+        assert_eq!(folder_concurrency(Some(2), 0, 4), 1);
+        assert_eq!(folder_concurrency(None, 10, 0), 1);
+    }
+
+    #[tokio::test]
+    async fn bytes_budget() {
+        let budget = SyncBytesBudget::new(100);
+
+        let first = budget.acquire(60).await;
+        let second = budget.acquire(40).await;
+
+        // the budget is exhausted until one message is released
+        let third = timeout(Duration::from_millis(50), budget.acquire(10)).await;
+        assert!(third.is_err());
+
+        drop(first);
+        let third = timeout(Duration::from_millis(50), budget.acquire(10)).await;
+        assert!(third.is_ok());
+
+        drop(second);
+        drop(third);
+
+        // messages bigger than the budget are transferred alone
+        let big = budget.acquire(1000).await;
+        let other = timeout(Duration::from_millis(50), budget.acquire(1)).await;
+        assert!(other.is_err());
+        assert!(budget.try_acquire(1).is_none());
+        drop(big);
+        assert!(budget.try_acquire(1).is_some());
+    }
+}
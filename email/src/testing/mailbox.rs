@@ -0,0 +1,152 @@
+//! # Mailbox fixture
+//!
+//! Module dedicated to the mailbox fixture, see [`MailboxFixture`].
+
+#[cfg(feature = "maildir")]
+use std::path::Path;
+
+use chrono::Duration;
+
+use super::MessageFixture;
+use crate::{envelope::SingleId, message::add::AddMessage, AnyResult};
+
+/// The mailbox fixture builder.
+///
+/// Holds a named list of [`MessageFixture`]s, which can be added to
+/// any backend implementing [`AddMessage`] (including an IMAP
+/// backend connected to the testing server), or written straight
+/// to a Maildir directory.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MailboxFixture {
+    pub name: String,
+    pub messages: Vec<MessageFixture>,
+}
+
+impl MailboxFixture {
+    pub fn new(name: impl ToString) -> Self {
+        Self {
+            name: name.to_string(),
+            messages: Vec::new(),
+        }
+    }
+
+    pub fn with_message(mut self, msg: MessageFixture) -> Self {
+        self.messages.push(msg);
+        self
+    }
+
+    pub fn with_messages(mut self, msgs: impl IntoIterator<Item = MessageFixture>) -> Self {
+        self.messages.extend(msgs);
+        self
+    }
+
+    /// Add a thread made of the given root message followed by the
+    /// given number of replies, each reply answering the previous
+    /// message.
+    pub fn with_thread(mut self, root: MessageFixture, replies: usize) -> Self {
+        let mut msg = root;
+
+        for _ in 0..replies {
+            let reply = msg.reply();
+            self.messages.push(msg);
+            msg = reply;
+        }
+
+        self.messages.push(msg);
+        self
+    }
+
+    /// Add the given number of plain messages, one day apart.
+    pub fn with_plain_messages(mut self, count: usize) -> Self {
+        let msg = MessageFixture::new();
+
+        self.messages.extend((0..count).map(|i| {
+            msg.clone()
+                .with_message_id(format!("{}@localhost", uuid::Uuid::new_v4()))
+                .with_subject(format!("Message #{}", i + 1))
+                .with_date(msg.date + Duration::days(i as i64))
+        }));
+
+        self
+    }
+
+    /// Add the messages to the mailbox of the given backend.
+    ///
+    /// The mailbox is expected to exist. Returns the identifiers of
+    /// the added messages, in the same order.
+    pub async fn populate(&self, backend: &impl AddMessage) -> AnyResult<Vec<SingleId>> {
+        let msgs = self
+            .messages
+            .iter()
+            .map(|msg| (msg.flags.clone(), msg.build()))
+            .collect();
+
+        backend.add_messages_with_flags(&self.name, msgs).await
+    }
+
+    /// Write the messages to the Maildir located at the given path,
+    /// creating it if needed.
+    ///
+    /// Returns the identifiers of the written entries, in the same
+    /// order.
+    #[cfg(feature = "maildir")]
+    pub fn write_maildir(&self, path: impl AsRef<Path>) -> maildirs::Result<Vec<String>> {
+        let mdir = maildirs::Maildir::from(path.as_ref().to_owned());
+        mdir.create_all()?;
+
+        self.messages
+            .iter()
+            .map(|msg| {
+                let flags = msg
+                    .flags
+                    .iter()
+                    .filter_map(|flag| maildirs::Flag::try_from(flag).ok());
+                let entry = mdir.write_cur(msg.build(), flags)?;
+                Ok(entry.id()?.to_owned())
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::MessageParser;
+
+    use super::MailboxFixture;
+    use crate::testing::MessageFixture;
+
+    #[test]
+    fn thread() {
+        let root = MessageFixture::new()
+            .with_subject("Plans")
+            .with_html_body("<p>Hello, world!</p>")
+            .with_attachment("notes.txt", "text/plain", "notes");
+
+        let mbox = MailboxFixture::new("INBOX").with_thread(root, 2);
+        assert_eq!(mbox.messages.len(), 3);
+
+        let raw: Vec<_> = mbox.messages.iter().map(MessageFixture::build).collect();
+        let msgs: Vec<_> = raw
+            .iter()
+            .map(|raw| MessageParser::new().parse(raw).unwrap())
+            .collect();
+
+        assert_eq!(msgs[0].subject(), Some("Plans"));
+        assert_eq!(msgs[0].attachment_count(), 1);
+        assert_eq!(msgs[0].html_body_count(), 1);
+        assert_eq!(msgs[2].subject(), Some("Re: Plans"));
+        assert_eq!(msgs[2].in_reply_to().as_text(), msgs[1].message_id(),);
+        assert_eq!(msgs[2].references().as_text_list().unwrap().len(), 2);
+        assert_eq!(
+            msgs[1].from().unwrap().first().unwrap().address(),
+            Some("bob@localhost")
+        );
+    }
+
+    #[test]
+    fn broken_encoding() {
+        let raw = MessageFixture::new().with_broken_encoding().build();
+        let msg = MessageParser::new().parse(&raw).unwrap();
+        assert!(msg.parts[0].is_encoding_problem);
+    }
+}
@@ -0,0 +1,237 @@
+//! # Message fixture
+//!
+//! Module dedicated to the message fixture, see [`MessageFixture`].
+
+use chrono::{DateTime, Duration, FixedOffset, TimeZone, Utc};
+use mail_builder::{headers::address::Address, MessageBuilder};
+
+use crate::flag::{Flag, Flags};
+
+/// The message fixture builder.
+///
+/// Builds realistic raw messages: plain text and/or HTML bodies,
+/// attachments, threading headers, or even broken encodings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MessageFixture {
+    pub message_id: String,
+    pub from: String,
+    pub to: Vec<String>,
+    pub cc: Vec<String>,
+    pub subject: String,
+    pub date: DateTime<FixedOffset>,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub text_body: Option<String>,
+    pub html_body: Option<String>,
+    pub attachments: Vec<AttachmentFixture>,
+    pub broken_encoding: bool,
+    pub flags: Flags,
+}
+
+/// An attachment of a [`MessageFixture`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AttachmentFixture {
+    pub filename: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+}
+
+impl Default for MessageFixture {
+    fn default() -> Self {
+        Self {
+            message_id: format!("{}@localhost", uuid::Uuid::new_v4()),
+            from: String::from("alice@localhost"),
+            to: vec![String::from("bob@localhost")],
+            cc: Vec::new(),
+            subject: String::from("Hello"),
+            date: Utc
+                .with_ymd_and_hms(2024, 1, 1, 12, 0, 0)
+                .unwrap()
+                .fixed_offset(),
+            in_reply_to: None,
+            references: Vec::new(),
+            text_body: Some(String::from("Hello, world!")),
+            html_body: None,
+            attachments: Vec::new(),
+            broken_encoding: false,
+            flags: Flags::default(),
+        }
+    }
+}
+
+impl MessageFixture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_message_id(mut self, id: impl ToString) -> Self {
+        self.message_id = id.to_string();
+        self
+    }
+
+    pub fn with_from(mut self, addr: impl ToString) -> Self {
+        self.from = addr.to_string();
+        self
+    }
+
+    pub fn with_to(mut self, addr: impl ToString) -> Self {
+        self.to.push(addr.to_string());
+        self
+    }
+
+    pub fn with_cc(mut self, addr: impl ToString) -> Self {
+        self.cc.push(addr.to_string());
+        self
+    }
+
+    pub fn with_subject(mut self, subject: impl ToString) -> Self {
+        self.subject = subject.to_string();
+        self
+    }
+
+    pub fn with_date(mut self, date: impl Into<DateTime<FixedOffset>>) -> Self {
+        self.date = date.into();
+        self
+    }
+
+    pub fn with_text_body(mut self, body: impl ToString) -> Self {
+        self.text_body = Some(body.to_string());
+        self
+    }
+
+    pub fn without_text_body(mut self) -> Self {
+        self.text_body = None;
+        self
+    }
+
+    /// Add an HTML body. When the message also has a plain text
+    /// body, both are sent as `multipart/alternative`.
+    pub fn with_html_body(mut self, body: impl ToString) -> Self {
+        self.html_body = Some(body.to_string());
+        self
+    }
+
+    pub fn with_attachment(
+        mut self,
+        filename: impl ToString,
+        content_type: impl ToString,
+        content: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.attachments.push(AttachmentFixture {
+            filename: filename.to_string(),
+            content_type: content_type.to_string(),
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Produce a message with a broken encoding: an invalid encoded
+    /// word in the subject, and a quoted-printable body containing
+    /// invalid escape sequences as well as raw 8-bit characters not
+    /// matching the declared charset.
+    ///
+    /// Such messages do not have any attachment nor HTML body.
+    pub fn with_broken_encoding(mut self) -> Self {
+        self.broken_encoding = true;
+        self
+    }
+
+    pub fn with_flag(mut self, flag: Flag) -> Self {
+        self.flags.insert(flag);
+        self
+    }
+
+    /// Build a reply to the current message, from its first
+    /// recipient to its sender, one hour later.
+    pub fn reply(&self) -> Self {
+        let subject = if self.subject.starts_with("Re:") {
+            self.subject.clone()
+        } else {
+            format!("Re: {}", self.subject)
+        };
+
+        let mut references = self.references.clone();
+        references.push(self.message_id.clone());
+
+        Self {
+            from: self.to.first().cloned().unwrap_or_default(),
+            to: vec![self.from.clone()],
+            subject,
+            date: self.date + Duration::hours(1),
+            in_reply_to: Some(self.message_id.clone()),
+            references,
+            text_body: Some(format!("Reply to: {}", self.subject)),
+            ..Self::default()
+        }
+    }
+
+    /// Build the raw message.
+    pub fn build(&self) -> Vec<u8> {
+        if self.broken_encoding {
+            return self.build_broken();
+        }
+
+        let mut builder = MessageBuilder::new()
+            .message_id(self.message_id.as_str())
+            .from(self.from.as_str())
+            .to(Address::new_list(
+                self.to.iter().map(|a| Address::from(a.as_str())).collect(),
+            ))
+            .subject(self.subject.as_str())
+            .date(self.date.timestamp());
+
+        if !self.cc.is_empty() {
+            builder = builder.cc(Address::new_list(
+                self.cc.iter().map(|a| Address::from(a.as_str())).collect(),
+            ));
+        }
+
+        if let Some(id) = &self.in_reply_to {
+            builder = builder.in_reply_to(id.as_str());
+        }
+
+        if !self.references.is_empty() {
+            let refs: Vec<_> = self.references.iter().map(String::as_str).collect();
+            builder = builder.references(refs);
+        }
+
+        if let Some(body) = &self.text_body {
+            builder = builder.text_body(body.as_str());
+        }
+
+        if let Some(body) = &self.html_body {
+            builder = builder.html_body(body.as_str());
+        }
+
+        for attachment in &self.attachments {
+            builder = builder.attachment(
+                attachment.content_type.as_str(),
+                attachment.filename.as_str(),
+                attachment.content.as_slice(),
+            );
+        }
+
+        builder.write_to_vec().unwrap()
+    }
+
+    fn build_broken(&self) -> Vec<u8> {
+        let mut msg = Vec::new();
+
+        msg.extend(format!("Message-ID: <{}>\r\n", self.message_id).as_bytes());
+        msg.extend(format!("Date: {}\r\n", self.date.to_rfc2822()).as_bytes());
+        msg.extend(format!("From: {}\r\n", self.from).as_bytes());
+        msg.extend(format!("To: {}\r\n", self.to.join(", ")).as_bytes());
+        msg.extend(b"Subject: =?utf-8?B?not@base64?= ");
+        msg.extend(self.subject.as_bytes());
+        msg.extend(b"\r\n");
+        msg.extend(b"MIME-Version: 1.0\r\n");
+        msg.extend(b"Content-Type: text/plain; charset=utf-8\r\n");
+        msg.extend(b"Content-Transfer-Encoding: quoted-printable\r\n");
+        msg.extend(b"\r\n");
+        msg.extend(b"Caf=E9 =ZZ d\xe9j\xe0 vu=\r\n");
+        msg.extend(self.text_body.as_deref().unwrap_or_default().as_bytes());
+        msg.extend(b"\r\n");
+
+        msg
+    }
+}
@@ -0,0 +1,13 @@
+//! # Testing
+//!
+//! Module dedicated to test fixtures. It contains builders producing
+//! realistic raw messages, see [`MessageFixture`], and mailboxes
+//! filled with such messages, see [`MailboxFixture`]. Fixtures are
+//! used by the library own tests, and can be reused by downstream
+//! projects.
+
+pub mod mailbox;
+pub mod message;
+
+#[doc(inline)]
+pub use self::{mailbox::MailboxFixture, message::MessageFixture};
@@ -0,0 +1,53 @@
+use std::{any::Any, io, path::PathBuf, result};
+
+use thiserror::Error;
+#[cfg(feature = "tokio-rustls")]
+use tokio_rustls::rustls::{client::VerifierBuilderError, pki_types::pem};
+
+use super::fingerprint::CertFingerprint;
+use crate::{AnyBoxedError, AnyError};
+
+/// The global `Result` alias of the module.
+pub type Result<T> = result::Result<T, Error>;
+
+/// The global `Error` enum of the module.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("cannot parse certificate fingerprint {0}: expected 32 hexadecimal bytes")]
+    ParseCertFingerprintError(String),
+    #[error("cannot verify certificate: fingerprint {1} does not match pinned fingerprint {0}")]
+    CertFingerprintMismatchError(CertFingerprint, CertFingerprint),
+    #[error("cannot verify certificate: server did not present any certificate")]
+    MissingPeerCertificateError,
+    #[error("cannot read root certificates at {1}")]
+    ReadRootCertsError(#[source] io::Error, PathBuf),
+    #[error("cannot find any root certificate at {0}")]
+    MissingRootCertsError(PathBuf),
+    #[cfg(feature = "tokio-rustls")]
+    #[error("cannot parse root certificates at {1}")]
+    ParseRootCertsError(#[source] pem::Error, PathBuf),
+    #[cfg(feature = "tokio-rustls")]
+    #[error("cannot add root certificate from {1}")]
+    AddRootCertError(#[source] tokio_rustls::rustls::Error, PathBuf),
+    #[cfg(feature = "tokio-rustls")]
+    #[error("cannot build certificate verifier for additional root certificates")]
+    BuildRootCertsVerifierError(#[source] VerifierBuilderError),
+    #[cfg(feature = "tokio-native-tls")]
+    #[error("cannot parse root certificate at {1}")]
+    ParseNativeRootCertError(#[source] tokio_native_tls::native_tls::Error, PathBuf),
+    #[cfg(feature = "tokio-native-tls")]
+    #[error("cannot build native TLS connector")]
+    BuildNativeTlsConnectorError(#[source] tokio_native_tls::native_tls::Error),
+}
+
+impl AnyError for Error {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl From<Error> for AnyBoxedError {
+    fn from(err: Error) -> Self {
+        Box::new(err)
+    }
+}
@@ -0,0 +1,89 @@
+//! # Certificate fingerprint
+//!
+//! Module dedicated to certificate pinning, see [`CertFingerprint`].
+
+use std::{fmt, str::FromStr};
+
+use sha2::{Digest, Sha256};
+
+use super::{Error, Result};
+
+/// The SHA-256 fingerprint of a certificate.
+///
+/// A fingerprint is written as 32 hexadecimal bytes, optionally
+/// separated by colons, the way `openssl x509 -noout -fingerprint
+/// -sha256` prints it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CertFingerprint([u8; 32]);
+
+impl CertFingerprint {
+    /// Compute the fingerprint of the given DER-encoded certificate.
+    pub fn of(der: &[u8]) -> Self {
+        Self(Sha256::digest(der).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl FromStr for CertFingerprint {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let err = || Error::ParseCertFingerprintError(s.to_owned());
+
+        let hex: Vec<u8> = s
+            .bytes()
+            .filter(|b| *b != b':' && !b.is_ascii_whitespace())
+            .collect();
+
+        if hex.len() != 64 {
+            return Err(err());
+        }
+
+        let mut bytes = [0; 32];
+
+        for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| err())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| err())?;
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+impl fmt::Display for CertFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CertFingerprint;
+
+    #[test]
+    fn parse_and_display() {
+        let fingerprint = CertFingerprint::of(b"certificate");
+        let hex = fingerprint.to_string().replace(':', "").to_lowercase();
+
+        assert_eq!(hex.parse::<CertFingerprint>().unwrap(), fingerprint);
+        assert_eq!(
+            fingerprint.to_string().parse::<CertFingerprint>().unwrap(),
+            fingerprint
+        );
+        assert_eq!(fingerprint.to_string().len(), 32 * 3 - 1);
+
+        assert!("".parse::<CertFingerprint>().is_err());
+        assert!("AB:CD".parse::<CertFingerprint>().is_err());
+        assert!("zz".repeat(32).parse::<CertFingerprint>().is_err());
+    }
+}
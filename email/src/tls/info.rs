@@ -0,0 +1,133 @@
+//! # TLS connection information
+//!
+//! Module dedicated to the information negotiated during a TLS
+//! handshake, see [`TlsConnectionInfo`].
+
+use chrono::{DateTime, Utc};
+
+/// The information negotiated during the TLS handshake of an
+/// established connection.
+///
+/// Depending on the TLS provider, some information may not be
+/// available: native TLS only exposes the peer certificate, for
+/// example.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TlsConnectionInfo {
+    /// The negotiated TLS protocol version, for example `TLSv1_3`.
+    pub version: Option<String>,
+
+    /// The negotiated cipher suite, for example
+    /// `TLS13_AES_256_GCM_SHA384`.
+    pub cipher_suite: Option<String>,
+
+    /// The negotiated ALPN protocol, if any.
+    pub alpn_protocol: Option<String>,
+
+    /// The certificate chain presented by the server, starting with
+    /// the end-entity certificate.
+    pub peer_certificates: Vec<PeerCertificate>,
+}
+
+impl TlsConnectionInfo {
+    /// Return the end-entity certificate presented by the server.
+    pub fn peer_certificate(&self) -> Option<&PeerCertificate> {
+        self.peer_certificates.first()
+    }
+}
+
+/// A certificate presented by the server during the TLS handshake.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeerCertificate {
+    /// The DER encoding of the certificate.
+    pub der: Vec<u8>,
+
+    /// The date the certificate starts being valid.
+    pub not_before: Option<DateTime<Utc>>,
+
+    /// The date the certificate stops being valid.
+    pub not_after: Option<DateTime<Utc>>,
+}
+
+impl PeerCertificate {
+    /// Build a peer certificate from its DER encoding.
+    ///
+    /// Validity dates are left empty when they cannot be extracted
+    /// from the certificate.
+    pub fn from_der(der: impl Into<Vec<u8>>) -> Self {
+        let der = der.into();
+        let (not_before, not_after) = match parse_validity(&der) {
+            Some((not_before, not_after)) => (Some(not_before), Some(not_after)),
+            None => (None, None),
+        };
+
+        Self {
+            der,
+            not_before,
+            not_after,
+        }
+    }
+
+    /// Return `true` if the certificate is valid at the given date.
+    ///
+    /// Certificates without known validity dates are considered
+    /// valid.
+    pub fn is_valid_at(&self, date: &DateTime<Utc>) -> bool {
+        let started = self.not_before.map(|d| d <= *date).unwrap_or(true);
+        let not_expired = self.not_after.map(|d| *date <= d).unwrap_or(true);
+        started && not_expired
+    }
+}
+
+/// Extract the validity dates of the given DER-encoded X.509
+/// certificate.
+fn parse_validity(der: &[u8]) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let (_, cert) = x509_parser::parse_x509_certificate(der).ok()?;
+    let validity = cert.validity();
+
+    let not_before = DateTime::from_timestamp(validity.not_before.timestamp(), 0)?;
+    let not_after = DateTime::from_timestamp(validity.not_after.timestamp(), 0)?;
+
+    Some((not_before, not_after))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use x509_parser::pem::parse_x509_pem;
+
+    use super::PeerCertificate;
+
+    /// A self-signed certificate valid from 2024-01-01 00:00:00 to
+    /// 2034-12-31 23:59:59 UTC.
+    const CERT: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBfTCCASOgAwIBAgIUR0sBNLr7PjPWcukkQFtItE1XVJkwCgYIKoZIzj0EAwIw
+FDESMBAGA1UEAwwJbG9jYWxob3N0MB4XDTI0MDEwMTAwMDAwMFoXDTM0MTIzMTIz
+NTk1OVowFDESMBAGA1UEAwwJbG9jYWxob3N0MFkwEwYHKoZIzj0CAQYIKoZIzj0D
+AQcDQgAEnJGIxOiEpsKptAYkNrpPHNgabYdZ8SwVvDJgDhYX9m6ZN+VzuOs0hYSB
+sb5UmG8RRFSc3PY1N4jFljoWdPdMqaNTMFEwHQYDVR0OBBYEFJlf/VWaEI5w1nWX
+UMfOEnUIa25cMB8GA1UdIwQYMBaAFJlf/VWaEI5w1nWXUMfOEnUIa25cMA8GA1Ud
+EwEB/wQFMAMBAf8wCgYIKoZIzj0EAwIDSAAwRQIgD8uDFW6nnOF/L9tPjvXvxQVk
+qMgrHqdWPYPsHc1TbjgCIQCGquZCAkgQvGJFnO8mFMq5SquT6r4s/sVDB3ohLz6S
+HQ==
+-----END CERTIFICATE-----
+";
+
+    #[test]
+    fn peer_certificate_validity() {
+        let (_, pem) = parse_x509_pem(CERT.as_bytes()).unwrap();
+        let cert = PeerCertificate::from_der(pem.contents);
+
+        let not_before = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let not_after = Utc.with_ymd_and_hms(2034, 12, 31, 23, 59, 59).unwrap();
+
+        assert_eq!(cert.not_before, Some(not_before));
+        assert_eq!(cert.not_after, Some(not_after));
+        assert!(cert.is_valid_at(&Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap()));
+        assert!(!cert.is_valid_at(&Utc.with_ymd_and_hms(2035, 1, 1, 0, 0, 0).unwrap()));
+
+        let cert = PeerCertificate::from_der(b"invalid".to_vec());
+        assert_eq!(cert.not_before, None);
+        assert_eq!(cert.not_after, None);
+    }
+}
@@ -1,7 +1,20 @@
-use std::fmt;
-
 #[cfg(feature = "derive")]
 pub mod derive;
+mod error;
+pub mod fingerprint;
+pub mod info;
+#[cfg(feature = "tokio-native-tls")]
+pub mod native_tls;
+#[cfg(feature = "tokio-rustls")]
+pub mod rustls;
+
+use std::{fmt, path::PathBuf};
+
+use shellexpand_utils::shellexpand_path;
+
+#[doc(inline)]
+pub use self::error::{Error, Result};
+use self::{fingerprint::CertFingerprint, info::TlsConnectionInfo};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
@@ -32,6 +45,66 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     }
 }
 
+/// The STARTTLS policy.
+///
+/// Defines how to behave when the connection cannot be upgraded to
+/// TLS using STARTTLS.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum StartTlsPolicy {
+    /// Abort if the connection cannot be upgraded to TLS.
+    #[default]
+    Require,
+
+    /// Upgrade the connection to TLS when possible, otherwise
+    /// continue in plaintext.
+    ///
+    /// Once an upgrade succeeded, next connections must be upgraded
+    /// as well: falling back to plaintext is then considered as a
+    /// downgrade attack.
+    Opportunistic,
+
+    /// Never upgrade the connection to TLS.
+    Never,
+}
+
+/// The security level negotiated with a server.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SecurityLevel {
+    /// The connection is not encrypted.
+    Plaintext,
+
+    /// The connection was upgraded to TLS using STARTTLS.
+    StartTls,
+
+    /// The connection is encrypted using SSL/TLS from the start.
+    Tls,
+}
+
+impl fmt::Display for SecurityLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plaintext => write!(f, "plaintext"),
+            Self::StartTls => write!(f, "STARTTLS"),
+            Self::Tls => write!(f, "SSL/TLS"),
+        }
+    }
+}
+
+impl Encryption {
+    /// Return the TLS options, unless encryption is disabled.
+    pub fn tls(&self) -> Option<&Tls> {
+        match self {
+            Self::Tls(tls) | Self::StartTls(tls) => Some(tls),
+            Self::None => None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
@@ -40,6 +113,142 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 )]
 pub struct Tls {
     pub provider: Option<TlsProvider>,
+
+    /// Additional root certificates to trust, as paths to PEM files.
+    ///
+    /// Useful for self-hosted servers whose certificate is issued by
+    /// a private certificate authority. These certificates are
+    /// trusted in addition to the platform ones.
+    ///
+    /// Only supported by SMTP: the IMAP client does not accept a
+    /// custom TLS configuration yet, IMAP connections fail when this
+    /// option is set.
+    pub root_certs: Option<Vec<PathBuf>>,
+
+    /// The SHA-256 fingerprint of the certificate the server must
+    /// present, see [`CertFingerprint`].
+    ///
+    /// When set, certificates not matching the fingerprint are
+    /// rejected. The pinned certificate still needs to pass the
+    /// other verifications: to pin a self-signed certificate, trust
+    /// it with [`Tls::root_certs`]. Supported by both IMAP and SMTP.
+    pub cert_fingerprint: Option<String>,
+
+    /// Accept invalid certificates (self-signed, expired or issued
+    /// for another host) from the server.
+    ///
+    /// This disables the verification of the server identity for
+    /// the current host only, prefer pinning the certificate
+    /// fingerprint whenever possible. Defaults to `false`.
+    ///
+    /// Only supported by SMTP, see [`Tls::root_certs`].
+    pub accept_invalid_certs: Option<bool>,
+}
+
+impl Tls {
+    /// Return `true` if invalid certificates are accepted.
+    pub fn accepts_invalid_certs(&self) -> bool {
+        self.accept_invalid_certs.unwrap_or_default()
+    }
+
+    /// Return the additional root certificates paths, with shell
+    /// expansion applied.
+    pub fn find_root_certs(&self) -> Vec<PathBuf> {
+        self.root_certs
+            .iter()
+            .flatten()
+            .map(shellexpand_path)
+            .collect()
+    }
+
+    /// Return the pinned certificate fingerprint, if any.
+    pub fn find_cert_fingerprint(&self) -> Result<Option<CertFingerprint>> {
+        self.cert_fingerprint.as_deref().map(str::parse).transpose()
+    }
+
+    /// Return `true` if the verification of the server certificate
+    /// differs from the platform one.
+    pub fn is_verification_customized(&self) -> bool {
+        self.root_certs
+            .as_ref()
+            .is_some_and(|certs| !certs.is_empty())
+            || self.cert_fingerprint.is_some()
+            || self.accepts_invalid_certs()
+    }
+
+    /// Return the names of the options requiring a custom TLS
+    /// configuration, which clients not accepting one cannot honor.
+    ///
+    /// The pinned fingerprint is not part of them, since it can be
+    /// checked once the connection is established.
+    pub fn find_custom_config_options(&self) -> Vec<&'static str> {
+        let mut options = Vec::new();
+
+        if self.root_certs.as_ref().is_some_and(|c| !c.is_empty()) {
+            options.push("root-certs");
+        }
+
+        if self.accepts_invalid_certs() {
+            options.push("accept-invalid-certs");
+        }
+
+        #[cfg(feature = "rustls")]
+        if let Some(rustls) = self.rustls() {
+            if rustls.alpn_protocols.is_some() {
+                options.push("alpn-protocols");
+            }
+
+            if rustls.requires_ocsp_stapling() {
+                options.push("ocsp-stapling");
+            }
+        }
+
+        options
+    }
+
+    /// Return `true` if the options differ from the defaults of the
+    /// underlying client.
+    pub fn is_customized(&self) -> bool {
+        #[cfg(feature = "rustls")]
+        if self.rustls().is_some_and(Rustls::is_customized) {
+            return true;
+        }
+
+        self.is_verification_customized()
+    }
+
+    /// Check the certificate presented by the server against the
+    /// pinned fingerprint, if any.
+    ///
+    /// Used by clients whose certificate verifier cannot be
+    /// customized, where the check happens once the connection is
+    /// established. This is equivalent to the check done during the
+    /// handshake by the rustls verifier.
+    pub fn verify_cert_fingerprint(&self, info: &TlsConnectionInfo) -> Result<()> {
+        let Some(expected) = self.find_cert_fingerprint()? else {
+            return Ok(());
+        };
+
+        let cert = info
+            .peer_certificate()
+            .ok_or(Error::MissingPeerCertificateError)?;
+        let got = CertFingerprint::of(&cert.der);
+
+        if got != expected {
+            return Err(Error::CertFingerprintMismatchError(expected, got));
+        }
+
+        Ok(())
+    }
+
+    /// Return the rustls options, if rustls is the TLS provider.
+    #[cfg(feature = "rustls")]
+    pub fn rustls(&self) -> Option<&Rustls> {
+        match &self.provider {
+            Some(TlsProvider::Rustls(rustls)) => Some(rustls),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -92,7 +301,34 @@ fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     serde(rename_all = "kebab-case")
 )]
 pub struct Rustls {
-    // TODO: define rustls specific options?
+    /// The ALPN protocols to advertise during the handshake.
+    ///
+    /// Defaults to the protocols advertised by the underlying client.
+    /// Only supported by SMTP, see [`Tls::root_certs`].
+    pub alpn_protocols: Option<Vec<String>>,
+
+    /// Require the server to staple an OCSP response to its
+    /// certificate.
+    ///
+    /// The stapled response is verified by the platform verifier, on
+    /// platforms supporting it. Defaults to `false`. Only supported
+    /// by SMTP, see [`Tls::root_certs`].
+    pub ocsp_stapling: Option<bool>,
+}
+
+#[cfg(feature = "rustls")]
+impl Rustls {
+    /// Return `true` if the server is required to staple an OCSP
+    /// response.
+    pub fn requires_ocsp_stapling(&self) -> bool {
+        self.ocsp_stapling.unwrap_or_default()
+    }
+
+    /// Return `true` if the options differ from the defaults of the
+    /// underlying client.
+    pub fn is_customized(&self) -> bool {
+        self.alpn_protocols.is_some() || self.requires_ocsp_stapling()
+    }
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -105,3 +341,47 @@ pub struct Rustls {
 pub struct NativeTls {
     // TODO: define native-tls specific options?
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Tls;
+
+    #[test]
+    fn custom_config_options() {
+        let tls = Tls {
+            cert_fingerprint: Some(String::from("AA")),
+            ..Default::default()
+        };
+        assert!(tls.find_custom_config_options().is_empty());
+
+        let tls = Tls {
+            root_certs: Some(vec![PathBuf::from("ca.pem")]),
+            accept_invalid_certs: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(
+            tls.find_custom_config_options(),
+            vec!["root-certs", "accept-invalid-certs"],
+        );
+    }
+
+    #[cfg(feature = "rustls")]
+    #[test]
+    fn custom_rustls_config_options() {
+        use super::{Rustls, TlsProvider};
+
+        let tls = Tls {
+            provider: Some(TlsProvider::Rustls(Rustls {
+                alpn_protocols: Some(vec![String::from("imap")]),
+                ocsp_stapling: Some(true),
+            })),
+            ..Default::default()
+        };
+        assert_eq!(
+            tls.find_custom_config_options(),
+            vec!["alpn-protocols", "ocsp-stapling"],
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! # Native TLS
+//!
+//! Module dedicated to the [`native_tls`](tokio_native_tls::native_tls)
+//! TLS provider: connector built from [`Tls`] options and negotiated
+//! connection information.
+
+use std::{
+    fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use tokio_native_tls::{
+    native_tls::{self, Certificate, TlsStream},
+    TlsConnector,
+};
+
+use super::{
+    info::{PeerCertificate, TlsConnectionInfo},
+    Error, Result, Tls,
+};
+
+const PEM_CERT_BEGIN: &str = "-----BEGIN CERTIFICATE-----";
+const PEM_CERT_END: &str = "-----END CERTIFICATE-----";
+
+impl Tls {
+    /// Build a native TLS connector from the options.
+    ///
+    /// The additional root certificates are trusted in addition to
+    /// the platform ones. Native TLS cannot check the pinned
+    /// fingerprint during the handshake: it needs to be checked
+    /// once connected, using [`Tls::verify_cert_fingerprint`].
+    pub fn build_native_tls_connector(&self) -> Result<TlsConnector> {
+        let mut builder = native_tls::TlsConnector::builder();
+        builder.danger_accept_invalid_certs(self.accepts_invalid_certs());
+
+        for path in self.find_root_certs() {
+            for cert in load_root_certs(&path)? {
+                builder.add_root_certificate(cert);
+            }
+        }
+
+        let connector = builder
+            .build()
+            .map_err(Error::BuildNativeTlsConnectorError)?;
+
+        Ok(TlsConnector::from(connector))
+    }
+}
+
+/// Load the certificates of the given PEM file.
+///
+/// Native TLS only parses the first certificate of a PEM document,
+/// so the file is split into one document per certificate.
+fn load_root_certs(path: &Path) -> Result<Vec<Certificate>> {
+    let pem =
+        fs::read_to_string(path).map_err(|err| Error::ReadRootCertsError(err, path.to_owned()))?;
+
+    let mut certs = Vec::new();
+    let mut rest = pem.as_str();
+
+    while let Some(begin) = rest.find(PEM_CERT_BEGIN) {
+        let Some(end) = rest[begin..].find(PEM_CERT_END) else {
+            break;
+        };
+
+        let end = begin + end + PEM_CERT_END.len();
+        let cert = Certificate::from_pem(rest[begin..end].as_bytes())
+            .map_err(|err| Error::ParseNativeRootCertError(err, path.to_owned()))?;
+
+        certs.push(cert);
+        rest = &rest[end..];
+    }
+
+    if certs.is_empty() {
+        return Err(Error::MissingRootCertsError(path.to_owned()));
+    }
+
+    Ok(certs)
+}
+
+impl TlsConnectionInfo {
+    /// Collect the information negotiated by the given native TLS
+    /// stream.
+    ///
+    /// Native TLS only exposes the end-entity certificate: the
+    /// protocol version, the cipher suite and the ALPN protocol are
+    /// left empty.
+    pub fn from_native_tls<S: Read + Write>(stream: &TlsStream<S>) -> Self {
+        let peer_certificates = stream
+            .peer_certificate()
+            .ok()
+            .flatten()
+            .and_then(|cert| cert.to_der().ok())
+            .map(PeerCertificate::from_der)
+            .into_iter()
+            .collect();
+
+        Self {
+            peer_certificates,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! # Rustls
+//!
+//! Module dedicated to the [`rustls`](tokio_rustls::rustls) TLS
+//! provider: client configuration built from [`Rustls`] options and
+//! negotiated connection information.
+
+use std::{fs, path::Path, sync::Arc};
+
+use rustls_platform_verifier::{ConfigVerifierExt, Verifier};
+use tokio_rustls::{
+    rustls::{
+        client::{
+            danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+            WebPkiServerVerifier,
+        },
+        crypto::CryptoProvider,
+        pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+        ClientConfig, ClientConnection, DigitallySignedStruct, Error as RustlsError, RootCertStore,
+        SignatureScheme,
+    },
+    TlsConnector,
+};
+use tracing::warn;
+
+use super::{
+    fingerprint::CertFingerprint,
+    info::{PeerCertificate, TlsConnectionInfo},
+    Error, Result, Rustls, Tls,
+};
+
+type VerifyResult<T> = std::result::Result<T, RustlsError>;
+
+impl Tls {
+    /// Build the rustls client configuration matching the TLS and
+    /// rustls options.
+    ///
+    /// Certificates are verified by the platform verifier, then by
+    /// the additional root certificates if any. A pinned fingerprint
+    /// is checked on top of the other verification options, like
+    /// [`Tls::verify_cert_fingerprint`] does for other clients.
+    pub fn build_rustls_client_config(&self) -> Result<ClientConfig> {
+        let default = Rustls::default();
+        let rustls = self.rustls().unwrap_or(&default);
+
+        let mut config = ClientConfig::with_platform_verifier();
+
+        if rustls.requires_ocsp_stapling() || self.is_verification_customized() {
+            let provider = config.crypto_provider().clone();
+            let verifier = self.build_rustls_verifier(rustls, provider)?;
+            config.dangerous().set_certificate_verifier(verifier);
+        }
+
+        if let Some(protocols) = &rustls.alpn_protocols {
+            config.alpn_protocols = protocols.iter().map(|p| p.as_bytes().to_vec()).collect();
+        }
+
+        Ok(config)
+    }
+
+    /// Build a rustls TLS connector from the options.
+    pub fn build_rustls_connector(&self) -> Result<TlsConnector> {
+        let config = self.build_rustls_client_config()?;
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    fn build_rustls_verifier(
+        &self,
+        rustls: &Rustls,
+        provider: Arc<CryptoProvider>,
+    ) -> Result<Arc<dyn ServerCertVerifier>> {
+        let mut verifier: Arc<dyn ServerCertVerifier> =
+            Arc::new(Verifier::new().with_provider(provider.clone()));
+
+        let mut roots = RootCertStore::empty();
+
+        for path in self.find_root_certs() {
+            for cert in load_root_certs(&path)? {
+                roots
+                    .add(cert)
+                    .map_err(|err| Error::AddRootCertError(err, path.clone()))?;
+            }
+        }
+
+        if !roots.is_empty() {
+            let extra = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(Error::BuildRootCertsVerifierError)?;
+            verifier = Arc::new(ExtraRootsVerifier(verifier, extra));
+        }
+
+        if rustls.requires_ocsp_stapling() {
+            verifier = Arc::new(OcspStaplingVerifier(verifier));
+        }
+
+        if self.accepts_invalid_certs() {
+            verifier = Arc::new(InvalidCertVerifier(verifier));
+        }
+
+        if let Some(fingerprint) = self.find_cert_fingerprint()? {
+            verifier = Arc::new(PinnedCertVerifier(fingerprint, verifier));
+        }
+
+        Ok(verifier)
+    }
+}
+
+/// Load the certificates of the given PEM file.
+fn load_root_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = fs::read(path).map_err(|err| Error::ReadRootCertsError(err, path.to_owned()))?;
+
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|err| Error::ParseRootCertsError(err, path.to_owned()))?;
+
+    if certs.is_empty() {
+        return Err(Error::MissingRootCertsError(path.to_owned()));
+    }
+
+    Ok(certs)
+}
+
+impl TlsConnectionInfo {
+    /// Collect the information negotiated by the given rustls
+    /// connection.
+    pub fn from_rustls(conn: &ClientConnection) -> Self {
+        Self {
+            version: conn.protocol_version().map(|v| format!("{v:?}")),
+            cipher_suite: conn
+                .negotiated_cipher_suite()
+                .map(|s| format!("{:?}", s.suite())),
+            alpn_protocol: conn
+                .alpn_protocol()
+                .map(|p| String::from_utf8_lossy(p).into_owned()),
+            peer_certificates: conn
+                .peer_certificates()
+                .unwrap_or_default()
+                .iter()
+                .map(|cert| PeerCertificate::from_der(cert.as_ref()))
+                .collect(),
+        }
+    }
+}
+
+/// Certificate verifier requiring the server to staple an OCSP
+/// response.
+///
+/// The response itself is checked by the inner verifier.
+#[derive(Debug)]
+struct OcspStaplingVerifier(Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for OcspStaplingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> VerifyResult<ServerCertVerified> {
+        if ocsp_response.is_empty() {
+            let err = "server did not staple any OCSP response";
+            return Err(RustlsError::General(err.to_owned()));
+        }
+
+        self.0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Certificate verifier trusting additional root certificates.
+///
+/// Certificates rejected by the inner verifier are verified again
+/// against the additional root certificates only.
+#[derive(Debug)]
+struct ExtraRootsVerifier(Arc<dyn ServerCertVerifier>, Arc<WebPkiServerVerifier>);
+
+impl ServerCertVerifier for ExtraRootsVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> VerifyResult<ServerCertVerified> {
+        let err = match self.0.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            Ok(verified) => return Ok(verified),
+            Err(err) => err,
+        };
+
+        self.1
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+            .map_err(|_| err)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+/// Certificate verifier accepting only the certificate matching the
+/// pinned fingerprint.
+///
+/// The matching certificate is then verified by the inner verifier.
+#[derive(Debug)]
+struct PinnedCertVerifier(CertFingerprint, Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> VerifyResult<ServerCertVerified> {
+        let fingerprint = CertFingerprint::of(end_entity);
+
+        if fingerprint != self.0 {
+            let err = Error::CertFingerprintMismatchError(self.0, fingerprint);
+            return Err(RustlsError::General(err.to_string()));
+        }
+
+        self.1
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.1.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.1.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.1.supported_verify_schemes()
+    }
+}
+
+/// Certificate verifier accepting certificates rejected by the inner
+/// verifier.
+///
+/// Signatures are still checked by the inner verifier, so that the
+/// server must own the key of the certificate it presents.
+#[derive(Debug)]
+struct InvalidCertVerifier(Arc<dyn ServerCertVerifier>);
+
+impl ServerCertVerifier for InvalidCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> VerifyResult<ServerCertVerified> {
+        let verified =
+            self.0
+                .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now);
+
+        if let Err(err) = verified {
+            warn!(?err, "accepting invalid certificate");
+        }
+
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> VerifyResult<HandshakeSignatureValid> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
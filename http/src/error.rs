@@ -21,6 +21,43 @@ pub enum Error {
     #[error("error while sending request")]
     SendRequestError(#[source] ureq::Error),
 
+    #[cfg(feature = "webdav")]
+    #[error("cannot parse XML document")]
+    ParseXmlError(#[source] xml::reader::Error),
+    #[cfg(feature = "webdav")]
+    #[error("cannot find root element of XML document")]
+    ParseXmlRootNotFoundError,
+    #[cfg(feature = "webdav")]
+    #[error("cannot parse WebDAV multistatus: unexpected root element {0}")]
+    ParseMultistatusError(String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot parse WebDAV server URL {1}")]
+    ParseWebdavUrlError(#[source] ureq::http::uri::InvalidUri, String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot find host of WebDAV server URL {0}")]
+    GetWebdavUrlHostNotFoundError(String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot get WebDAV credentials")]
+    GetWebdavCredentialsError(#[source] secret::Error),
+    #[cfg(feature = "webdav")]
+    #[error("cannot send {1} request to {2}")]
+    SendWebdavRequestError(#[source] Box<Error>, String, String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot read response of {1} request to {2}")]
+    ReadWebdavResponseError(#[source] ureq::Error, String, String),
+    #[cfg(feature = "webdav")]
+    #[error("unexpected status {0} for {1} request to {2}")]
+    UnexpectedWebdavStatusError(u16, String, String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot parse response of {1} request to {2}")]
+    ParseWebdavResponseError(#[source] Box<Error>, String, String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot find current user principal at {0}")]
+    FindWebdavPrincipalError(String),
+    #[cfg(feature = "webdav")]
+    #[error("cannot find {0} of principal {1}")]
+    FindWebdavHomeSetError(String, String),
+
     #[error(transparent)]
     UreqError(#[from] ureq::Error),
     #[error(transparent)]
@@ -2,6 +2,8 @@
 #![doc = include_str!("../README.md")]
 
 mod error;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 
 pub use ureq;
 use ureq::{
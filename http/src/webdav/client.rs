@@ -0,0 +1,346 @@
+//! # WebDAV client
+//!
+//! Module dedicated to the WebDAV client, see [`WebdavClient`]. The
+//! client sends authenticated WebDAV requests relative to a server
+//! URL, and is the base of the CalDAV and CardDAV clients.
+
+use std::fmt::Write;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secret::Secret;
+use tracing::debug;
+use ureq::http::Uri;
+
+use super::{request, Multistatus, DAV_NS};
+use crate::{Client, Error, Result};
+
+/// The content type of XML request bodies.
+const XML_CONTENT_TYPE: &str = "application/xml; charset=utf-8";
+
+/// The WebDAV authentication.
+///
+/// Passwords and tokens are [`Secret`]s, so that they can be taken
+/// from a shell command or from the system keyring. They are
+/// retrieved before every request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum WebdavAuth {
+    /// The HTTP basic authentication.
+    Basic { username: String, password: Secret },
+
+    /// The bearer token authentication, for OAuth 2.0.
+    Bearer(Secret),
+}
+
+impl WebdavAuth {
+    /// Builds the value of the `Authorization` header.
+    pub async fn to_header(&self) -> Result<String> {
+        match self {
+            Self::Basic { username, password } => {
+                let password = password
+                    .get()
+                    .await
+                    .map_err(Error::GetWebdavCredentialsError)?;
+                let credentials = STANDARD.encode(format!("{username}:{password}"));
+                Ok(format!("Basic {credentials}"))
+            }
+            Self::Bearer(token) => {
+                let token = token
+                    .get()
+                    .await
+                    .map_err(Error::GetWebdavCredentialsError)?;
+                Ok(format!("Bearer {token}"))
+            }
+        }
+    }
+}
+
+/// The raw response of a WebDAV request.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct WebdavResponse {
+    /// The status code of the response.
+    pub status: u16,
+
+    /// The entity tag of the resource, if any.
+    pub etag: Option<String>,
+
+    /// The body of the response.
+    pub body: Vec<u8>,
+}
+
+/// The WebDAV client.
+#[derive(Clone, Debug)]
+pub struct WebdavClient {
+    http: Client,
+    url: String,
+    origin: String,
+    auth: Option<WebdavAuth>,
+}
+
+impl WebdavClient {
+    /// Creates a new client for the given server URL.
+    ///
+    /// The URL is the context path of the server, for example
+    /// `https://dav.example.com/dav/`.
+    pub fn new(url: impl ToString) -> Result<Self> {
+        let url = url.to_string();
+        let uri: Uri = url
+            .parse()
+            .map_err(|err| Error::ParseWebdavUrlError(err, url.clone()))?;
+
+        let scheme = uri.scheme_str().unwrap_or("https");
+        let authority = uri
+            .authority()
+            .ok_or_else(|| Error::GetWebdavUrlHostNotFoundError(url.clone()))?;
+        let origin = format!("{scheme}://{authority}");
+
+        Ok(Self {
+            http: Client::new(),
+            url,
+            origin,
+            auth: None,
+        })
+    }
+
+    pub fn set_auth(&mut self, auth: WebdavAuth) {
+        self.auth = Some(auth);
+    }
+
+    pub fn with_auth(mut self, auth: WebdavAuth) -> Self {
+        self.set_auth(auth);
+        self
+    }
+
+    /// Builds the absolute URL of the given URL path.
+    pub fn url(&self, href: &str) -> String {
+        if href.starts_with("http://") || href.starts_with("https://") {
+            href.to_owned()
+        } else {
+            format!("{}/{}", self.origin, href.trim_start_matches('/'))
+        }
+    }
+
+    /// Sends a request, then returns the raw response.
+    ///
+    /// Error statuses are returned as responses, so that callers can
+    /// turn them into meaningful errors. The content type of the
+    /// body, if any, is given as a header.
+    pub async fn send(
+        &self,
+        method: &'static str,
+        href: &str,
+        headers: Vec<(&'static str, String)>,
+        body: Option<String>,
+    ) -> Result<WebdavResponse> {
+        let url = self.url(href);
+        let auth = match &self.auth {
+            Some(auth) => Some(auth.to_header().await?),
+            None => None,
+        };
+        debug!(method, url, "sending WebDAV request");
+
+        let response = self
+            .http
+            .send({
+                let url = url.clone();
+                move |agent| {
+                    let mut request = request(agent, method, &url)?;
+
+                    if let Some(auth) = auth {
+                        request = request.header("Authorization", auth);
+                    }
+
+                    for (key, val) in headers {
+                        request = request.header(key, val);
+                    }
+
+                    let request = request.config().http_status_as_error(false).build();
+
+                    match body {
+                        Some(body) => request.send(body),
+                        None => request.send_empty(),
+                    }
+                }
+            })
+            .await
+            .map_err(|err| {
+                Error::SendWebdavRequestError(Box::new(err), method.to_owned(), url.clone())
+            })?;
+
+        let status = response.status().as_u16();
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|etag| etag.to_str().ok())
+            .map(ToOwned::to_owned);
+
+        let body = response
+            .into_body()
+            .read_to_vec()
+            .map_err(|err| Error::ReadWebdavResponseError(err, method.to_owned(), url.clone()))?;
+
+        Ok(WebdavResponse { status, etag, body })
+    }
+
+    /// Sends a request with the given XML body, expecting a
+    /// `multistatus` response.
+    pub async fn send_multistatus(
+        &self,
+        method: &'static str,
+        href: &str,
+        depth: &str,
+        body: String,
+    ) -> Result<Multistatus> {
+        let headers = vec![
+            ("Depth", depth.to_owned()),
+            ("Content-Type", XML_CONTENT_TYPE.to_owned()),
+        ];
+        let response = self.send(method, href, headers, Some(body)).await?;
+        let url = self.url(href);
+
+        if response.status != 207 {
+            let method = method.to_owned();
+            return Err(Error::UnexpectedWebdavStatusError(
+                response.status,
+                method,
+                url,
+            ));
+        }
+
+        Multistatus::parse(&response.body)
+            .map_err(|err| Error::ParseWebdavResponseError(Box::new(err), method.to_owned(), url))
+    }
+
+    /// Sends a request with the given XML body, expecting a
+    /// successful response.
+    pub async fn send_xml(&self, method: &'static str, href: &str, body: String) -> Result<()> {
+        let headers = vec![("Content-Type", XML_CONTENT_TYPE.to_owned())];
+        let response = self.send(method, href, headers, Some(body)).await?;
+        self.expect_success(method, href, response.status)
+    }
+
+    /// Finds the URL path of the given home set property of the
+    /// current user principal, for example `calendar-home-set` for
+    /// CalDAV.
+    pub async fn find_home_set(&self, namespace: &str, name: &str) -> Result<String> {
+        let body = propfind_body(&[("d", DAV_NS, "current-user-principal")]);
+        let url = self.url.clone();
+        let multistatus = self.send_multistatus("PROPFIND", &url, "0", body).await?;
+
+        let principal = multistatus
+            .responses
+            .iter()
+            .find_map(|res| res.prop(DAV_NS, "current-user-principal"))
+            .and_then(|prop| prop.child_text(DAV_NS, "href"))
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| Error::FindWebdavPrincipalError(url))?;
+
+        let body = propfind_body(&[("h", namespace, name)]);
+        let multistatus = self
+            .send_multistatus("PROPFIND", &principal, "0", body)
+            .await?;
+
+        multistatus
+            .responses
+            .iter()
+            .find_map(|res| res.prop(namespace, name))
+            .and_then(|prop| prop.child_text(DAV_NS, "href"))
+            .map(ToOwned::to_owned)
+            .ok_or_else(|| Error::FindWebdavHomeSetError(name.to_owned(), principal))
+    }
+
+    /// Returns an error if the given status is not successful.
+    pub fn expect_success(&self, method: &str, href: &str, status: u16) -> Result<()> {
+        if (200..300).contains(&status) {
+            Ok(())
+        } else {
+            let url = self.url(href);
+            Err(Error::UnexpectedWebdavStatusError(
+                status,
+                method.to_owned(),
+                url,
+            ))
+        }
+    }
+}
+
+/// Builds the body of a `PROPFIND` request for the given properties,
+/// described by their namespace prefix, namespace and local name.
+pub fn propfind_body(props: &[(&str, &str, &str)]) -> String {
+    let mut namespaces = String::new();
+    let mut prop = String::new();
+
+    for (prefix, ns, name) in props {
+        let xmlns = format!(r#" xmlns:{prefix}="{ns}""#);
+
+        if !namespaces.contains(&xmlns) {
+            namespaces.push_str(&xmlns);
+        }
+
+        let _ = write!(prop, "<{prefix}:{name}/>");
+    }
+
+    if !namespaces.contains(r#"xmlns:d="DAV:""#) {
+        namespaces.push_str(r#" xmlns:d="DAV:""#);
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?><d:propfind{namespaces}><d:prop>{prop}</d:prop></d:propfind>"#
+    )
+}
+
+/// Turns the given unique identifier into a safe resource name.
+pub fn sanitize_resource_name(uid: &str) -> String {
+    uid.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | '.' | '@' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use secret::Secret;
+
+    use super::{propfind_body, sanitize_resource_name, WebdavAuth};
+
+    #[tokio::test]
+    async fn build_auth_header_from_secret() {
+        let auth = WebdavAuth::Basic {
+            username: String::from("user"),
+            password: Secret::new_raw("pass"),
+        };
+
+        assert_eq!(auth.to_header().await.unwrap(), "Basic dXNlcjpwYXNz");
+
+        let auth = WebdavAuth::Bearer(Secret::new_raw("token"));
+        assert_eq!(auth.to_header().await.unwrap(), "Bearer token");
+    }
+
+    #[test]
+    fn build_propfind_body() {
+        let body = propfind_body(&[
+            ("d", "DAV:", "displayname"),
+            ("c", "urn:ietf:params:xml:ns:caldav", "calendar-home-set"),
+        ]);
+
+        assert_eq!(
+            body,
+            concat!(
+                r#"<?xml version="1.0" encoding="utf-8"?>"#,
+                r#"<d:propfind xmlns:d="DAV:" xmlns:c="urn:ietf:params:xml:ns:caldav">"#,
+                "<d:prop><d:displayname/><c:calendar-home-set/></d:prop>",
+                "</d:propfind>",
+            )
+        );
+    }
+
+    #[test]
+    fn sanitize_uid() {
+        assert_eq!(
+            sanitize_resource_name("abc-123@example.com"),
+            "abc-123@example.com"
+        );
+        assert_eq!(sanitize_resource_name("a/b c?"), "a_b_c_");
+    }
+}
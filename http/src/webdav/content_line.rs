@@ -0,0 +1,181 @@
+//! # Content line
+//!
+//! Module dedicated to the content lines of iCalendar (RFC 5545) and
+//! vCard (RFC 6350) data, the formats exchanged by CalDAV and
+//! CardDAV. It contains a [`ContentLine`] parser, folding and
+//! unfolding functions, and text value escaping.
+
+/// The maximum length of a content line, in octets.
+pub const MAX_LINE_LEN: usize = 75;
+
+/// The content line.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContentLine<'a> {
+    /// The group of the property, if any (vCard only).
+    pub group: Option<&'a str>,
+
+    /// The upper-cased name of the property.
+    pub name: String,
+
+    /// The parameters of the property, unquoted.
+    pub params: Vec<(String, String)>,
+
+    /// The raw value of the property.
+    pub value: &'a str,
+}
+
+impl<'a> ContentLine<'a> {
+    /// Splits an unfolded content line into its group, name,
+    /// parameters and value.
+    ///
+    /// The group is split from the name: `item1.EMAIL` gives the
+    /// group `item1` and the name `EMAIL`.
+    pub fn parse(line: &'a str) -> Option<Self> {
+        let mut quoted = false;
+        let colon = line.char_indices().find_map(|(i, c)| match c {
+            '"' => {
+                quoted = !quoted;
+                None
+            }
+            ':' if !quoted => Some(i),
+            _ => None,
+        })?;
+
+        let (head, value) = (&line[..colon], &line[colon + 1..]);
+        let mut parts = head.split(';');
+        let name = parts.next()?;
+        let (group, name) = match name.rsplit_once('.') {
+            Some((group, name)) => (Some(group), name),
+            None => (None, name),
+        };
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, val)| (key.to_owned(), val.trim_matches('"').to_owned()))
+            .collect();
+
+        Some(Self {
+            group,
+            name: name.to_ascii_uppercase(),
+            params,
+            value,
+        })
+    }
+
+    /// Returns the value of the given parameter, ignoring case.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, val)| val.as_str())
+    }
+}
+
+/// Unfolds the content lines of the given data.
+///
+/// Empty lines are dropped.
+pub fn unfold(data: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in data.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(cont), Some(last)) => last.push_str(cont),
+            _ if line.is_empty() => (),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    lines
+}
+
+/// Folds the given content line, then appends it to the given data.
+///
+/// Lines are folded at [`MAX_LINE_LEN`] octets, without splitting
+/// multi-byte characters.
+pub fn fold(data: &mut String, line: &str) {
+    let mut len = 0;
+
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            data.push_str("\r\n ");
+            len = 1;
+        }
+
+        data.push(c);
+        len += c.len_utf8();
+    }
+
+    data.push_str("\r\n");
+}
+
+/// Escapes the given text value.
+pub fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Unescapes the given text value.
+pub fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_text, fold, unescape_text, unfold, ContentLine, MAX_LINE_LEN};
+
+    #[test]
+    fn parse_content_line() {
+        let line =
+            ContentLine::parse(r#"item1.email;TYPE=INTERNET;X-LABEL="a:b":john@example.com"#)
+                .unwrap();
+
+        assert_eq!(line.group, Some("item1"));
+        assert_eq!(line.name, "EMAIL");
+        assert_eq!(line.param("type"), Some("INTERNET"));
+        assert_eq!(line.param("x-label"), Some("a:b"));
+        assert_eq!(line.value, "john@example.com");
+
+        let line = ContentLine::parse("DTSTART;TZID=Europe/Paris:20240102T100000").unwrap();
+        assert_eq!(line.group, None);
+        assert_eq!(line.name, "DTSTART");
+        assert_eq!(line.value, "20240102T100000");
+
+        assert!(ContentLine::parse("no colon").is_none());
+    }
+
+    #[test]
+    fn fold_unfold() {
+        let line = format!("DESCRIPTION:{}", "é".repeat(MAX_LINE_LEN));
+
+        let mut data = String::new();
+        fold(&mut data, &line);
+
+        assert!(data.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert_eq!(unfold(&data), vec![line]);
+    }
+
+    #[test]
+    fn escape_unescape() {
+        let text = "a, b; c\\d\nnext";
+        assert_eq!(escape_text(text), "a\\, b\\; c\\\\d\\nnext");
+        assert_eq!(unescape_text(&escape_text(text)), text);
+    }
+}
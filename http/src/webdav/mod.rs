@@ -0,0 +1,315 @@
+//! # WebDAV
+//!
+//! Module dedicated to WebDAV (RFC 4918) helpers, shared by
+//! WebDAV-based protocols like CalDAV and CardDAV. It contains a
+//! request builder supporting WebDAV methods, a minimal XML tree
+//! able to read `multistatus` responses, a [`WebdavClient`] and a
+//! codec for the content lines of iCalendar and vCard data (see
+//! [`content_line`]).
+
+mod client;
+pub mod content_line;
+
+use std::mem;
+
+use ureq::{http::Method, typestate::WithBody, Agent, RequestBuilder};
+use xml::{
+    name::OwnedName,
+    reader::{ParserConfig, XmlEvent},
+};
+
+#[doc(inline)]
+pub use self::client::{
+    propfind_body, sanitize_resource_name, WebdavAuth, WebdavClient, WebdavResponse,
+};
+use crate::{Error, Result};
+
+/// The WebDAV XML namespace.
+pub const DAV_NS: &str = "DAV:";
+
+/// Builds a request using the given method, which can be a WebDAV
+/// one (`PROPFIND`, `REPORT`, `MKCALENDAR` etc).
+///
+/// The request always sends its body, even for methods the HTTP
+/// agent does not know about. The error type matches the one
+/// expected by [`crate::Client::send`] callbacks.
+pub fn request(
+    agent: &Agent,
+    method: &str,
+    uri: &str,
+) -> std::result::Result<RequestBuilder<WithBody>, ureq::Error> {
+    let method = Method::from_bytes(method.as_bytes()).map_err(ureq::http::Error::from)?;
+
+    let mut request = agent.get(uri).force_send_body();
+    let builder = mem::take(&mut *request);
+    *request = builder.method(method);
+
+    Ok(request)
+}
+
+/// Escapes the given text so that it can be safely inserted into an
+/// XML document.
+pub fn escape(text: &str) -> String {
+    xml::escape::escape_str_pcdata(text).into_owned()
+}
+
+/// The XML element.
+///
+/// This is a minimal tree representation of an XML document: only
+/// namespaced names, attributes values and text contents are kept.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Element {
+    /// The namespace of the element.
+    pub namespace: Option<String>,
+
+    /// The local name of the element.
+    pub name: String,
+
+    /// The attributes of the element, by local name.
+    pub attributes: Vec<(String, String)>,
+
+    /// The child elements.
+    pub children: Vec<Element>,
+
+    /// The text content of the element.
+    pub text: String,
+}
+
+impl Element {
+    /// Parses an XML document into its root element.
+    pub fn parse(xml: &[u8]) -> Result<Self> {
+        let reader = ParserConfig::new()
+            .trim_whitespace(true)
+            .cdata_to_characters(true)
+            .create_reader(xml);
+
+        let mut stack: Vec<Element> = Vec::new();
+
+        for event in reader {
+            match event.map_err(Error::ParseXmlError)? {
+                XmlEvent::StartElement {
+                    name, attributes, ..
+                } => {
+                    let mut element = Element::from(name);
+                    element.attributes = attributes
+                        .into_iter()
+                        .map(|attr| (attr.name.local_name, attr.value))
+                        .collect();
+                    stack.push(element);
+                }
+                XmlEvent::EndElement { .. } => {
+                    let element = stack.pop().ok_or(Error::ParseXmlRootNotFoundError)?;
+
+                    match stack.last_mut() {
+                        Some(parent) => parent.children.push(element),
+                        None => return Ok(element),
+                    }
+                }
+                XmlEvent::Characters(text) => {
+                    if let Some(element) = stack.last_mut() {
+                        element.text.push_str(&text);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        Err(Error::ParseXmlRootNotFoundError)
+    }
+
+    /// Returns `true` if the element has the given namespace and
+    /// local name.
+    pub fn is(&self, namespace: &str, name: &str) -> bool {
+        self.namespace.as_deref() == Some(namespace) && self.name == name
+    }
+
+    /// Returns the first child matching the given namespace and
+    /// local name.
+    pub fn child(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.is(namespace, name))
+    }
+
+    /// Returns all children matching the given namespace and local
+    /// name.
+    pub fn children<'a>(
+        &'a self,
+        namespace: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> {
+        self.children
+            .iter()
+            .filter(move |child| child.is(namespace, name))
+    }
+
+    /// Returns the trimmed text of the first child matching the
+    /// given namespace and local name, if not empty.
+    pub fn child_text(&self, namespace: &str, name: &str) -> Option<&str> {
+        let text = self.child(namespace, name)?.text.trim();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+impl From<OwnedName> for Element {
+    fn from(name: OwnedName) -> Self {
+        Self {
+            namespace: name.namespace,
+            name: name.local_name,
+            ..Default::default()
+        }
+    }
+}
+
+/// The WebDAV `multistatus` response.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Multistatus {
+    /// The responses, one per resource.
+    pub responses: Vec<MultistatusResponse>,
+
+    /// The synchronization token, only sent back by
+    /// `sync-collection` reports (RFC 6578).
+    pub sync_token: Option<String>,
+}
+
+impl Multistatus {
+    /// Parses a `multistatus` XML document.
+    pub fn parse(xml: &[u8]) -> Result<Self> {
+        let root = Element::parse(xml)?;
+
+        if !root.is(DAV_NS, "multistatus") {
+            return Err(Error::ParseMultistatusError(root.name));
+        }
+
+        let responses = root
+            .children(DAV_NS, "response")
+            .filter_map(MultistatusResponse::from_element)
+            .collect();
+
+        let sync_token = root.child_text(DAV_NS, "sync-token").map(ToOwned::to_owned);
+
+        Ok(Self {
+            responses,
+            sync_token,
+        })
+    }
+}
+
+/// The response of a single resource, inside a `multistatus`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MultistatusResponse {
+    /// The URL path of the resource.
+    pub href: String,
+
+    /// The status code of the whole response, if any.
+    ///
+    /// This status is mostly used by `sync-collection` reports to
+    /// announce deleted resources (`404`).
+    pub status: Option<u16>,
+
+    /// The properties found for the resource.
+    ///
+    /// Only properties of successful `propstat` are kept.
+    pub props: Vec<Element>,
+}
+
+impl MultistatusResponse {
+    fn from_element(response: &Element) -> Option<Self> {
+        let href = response.child_text(DAV_NS, "href")?.to_owned();
+        let status = response
+            .child_text(DAV_NS, "status")
+            .and_then(parse_status_line);
+
+        let props = response
+            .children(DAV_NS, "propstat")
+            .filter(|propstat| {
+                let status = propstat.child_text(DAV_NS, "status");
+                matches!(status.and_then(parse_status_line), Some(200..=299))
+            })
+            .filter_map(|propstat| propstat.child(DAV_NS, "prop"))
+            .flat_map(|prop| prop.children.iter().cloned())
+            .collect();
+
+        Some(Self {
+            href,
+            status,
+            props,
+        })
+    }
+
+    /// Returns the property matching the given namespace and local
+    /// name.
+    pub fn prop(&self, namespace: &str, name: &str) -> Option<&Element> {
+        self.props.iter().find(|prop| prop.is(namespace, name))
+    }
+
+    /// Returns the trimmed text of the property matching the given
+    /// namespace and local name, if not empty.
+    pub fn prop_text(&self, namespace: &str, name: &str) -> Option<&str> {
+        let text = self.prop(namespace, name)?.text.trim();
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// Returns `true` if the resource is announced as not found.
+    pub fn is_not_found(&self) -> bool {
+        self.status == Some(404)
+    }
+}
+
+/// Parses the status code of a status line like `HTTP/1.1 200 OK`.
+fn parse_status_line(line: &str) -> Option<u16> {
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Multistatus, DAV_NS};
+
+    #[test]
+    fn parse_multistatus() {
+        let xml = br#"<?xml version="1.0" encoding="utf-8"?>
+            <d:multistatus xmlns:d="DAV:" xmlns:cs="http://calendarserver.org/ns/">
+              <d:response>
+                <d:href>/calendars/user/work/</d:href>
+                <d:propstat>
+                  <d:prop>
+                    <d:displayname>Work &amp; co</d:displayname>
+                    <d:resourcetype><d:collection/></d:resourcetype>
+                  </d:prop>
+                  <d:status>HTTP/1.1 200 OK</d:status>
+                </d:propstat>
+                <d:propstat>
+                  <d:prop><cs:getctag/></d:prop>
+                  <d:status>HTTP/1.1 404 Not Found</d:status>
+                </d:propstat>
+              </d:response>
+              <d:response>
+                <d:href>/calendars/user/work/deleted.ics</d:href>
+                <d:status>HTTP/1.1 404 Not Found</d:status>
+              </d:response>
+              <d:sync-token>http://example.com/sync/42</d:sync-token>
+            </d:multistatus>"#;
+
+        let multistatus = Multistatus::parse(xml).unwrap();
+        assert_eq!(
+            multistatus.sync_token.as_deref(),
+            Some("http://example.com/sync/42")
+        );
+
+        let [calendar, deleted] = multistatus.responses.as_slice() else {
+            panic!("expected 2 responses");
+        };
+
+        assert_eq!(calendar.href, "/calendars/user/work/");
+        assert_eq!(calendar.prop_text(DAV_NS, "displayname"), Some("Work & co"));
+        assert!(calendar
+            .prop("http://calendarserver.org/ns/", "getctag")
+            .is_none());
+        assert!(calendar
+            .prop(DAV_NS, "resourcetype")
+            .and_then(|rt| rt.child(DAV_NS, "collection"))
+            .is_some());
+        assert!(!calendar.is_not_found());
+
+        assert!(deleted.is_not_found());
+        assert!(deleted.props.is_empty());
+    }
+}
@@ -23,6 +23,12 @@ pub enum Error {
     SetSecretError(#[source] native::Error, String),
     #[error("cannot delete secret from keyring matching `{1}`")]
     DeleteSecretError(#[source] native::Error, String),
+    #[error("cannot read keyring entries index")]
+    ReadIndexError(#[source] native::Error),
+    #[error("cannot write keyring entries index")]
+    WriteIndexError(#[source] native::Error),
+    #[error("cannot serialize keyring entries index")]
+    SerializeIndexError(#[source] serde_json::Error),
 
     #[cfg(feature = "tokio")]
     #[error(transparent)]
@@ -2,16 +2,18 @@
 #![doc = include_str!("../README.md")]
 
 mod error;
+mod metadata;
 mod service;
 
 use std::sync::Arc;
 
 pub use keyring_native as native;
-use tracing::debug;
+use tracing::{debug, warn};
 
 #[doc(inline)]
 pub use crate::{
     error::{Error, Result},
+    metadata::{list_entries, KeyringAttributes, KeyringEntryMetadata, INDEX_KEY, USE_RESOLUTION},
     service::{get_global_service_name, set_global_service_name},
 };
 
@@ -41,6 +43,9 @@ pub struct KeyringEntry {
     /// The key used to identify the current keyring entry.
     pub key: String,
 
+    /// The attributes of the keyring entry.
+    attributes: Arc<KeyringAttributes>,
+
     /// The native keyring entry.
     entry: Arc<native::Entry>,
 }
@@ -61,15 +66,57 @@ pub fn try_new(key: impl ToString) -> Result<Self> {
         Self::try_from(key.to_string())
     }
 
+    /// Gets the attributes of the keyring entry.
+    ///
+    /// Attributes are saved along with the secret, see
+    /// [`KeyringEntry::set_secret`].
+    pub fn attributes(&self) -> &KeyringAttributes {
+        &self.attributes
+    }
+
+    /// Defines the attributes of the keyring entry.
+    ///
+    /// See [`KeyringEntry::with_attributes`] for the builder pattern
+    /// alternative.
+    pub fn set_attributes(&mut self, attributes: KeyringAttributes) {
+        self.attributes = Arc::new(attributes);
+    }
+
+    /// Defines the attributes of the keyring entry, using the builder
+    /// pattern.
+    ///
+    /// See [`KeyringEntry::set_attributes`] for the setter
+    /// alternative.
+    pub fn with_attributes(mut self, attributes: KeyringAttributes) -> Self {
+        self.set_attributes(attributes);
+        self
+    }
+
+    /// Gets the metadata of the keyring entry from the index.
+    ///
+    /// Returns `None` if the entry is not indexed, see
+    /// [`list_entries`].
+    pub async fn get_metadata(&self) -> Result<Option<KeyringEntryMetadata>> {
+        let entries = list_entries().await?;
+        Ok(entries
+            .into_iter()
+            .find(|metadata| metadata.key == self.key))
+    }
+
     /// Gets the secret of the keyring entry.
     pub async fn get_secret(&self) -> Result<String> {
         let key = &self.key;
         debug!(key, "get keyring secret");
 
         let entry = self.entry.clone();
-        let secret = spawn_blocking(move || entry.get_password())
-            .await?
-            .map_err(|err| Error::GetSecretError(err, key.clone()))?;
+        let index_key = key.clone();
+        let secret = spawn_blocking(move || {
+            let secret = entry.get_password()?;
+            metadata::record_use(&index_key);
+            Ok(secret)
+        })
+        .await?
+        .map_err(|err| Error::GetSecretError(err, key.clone()))?;
 
         Ok(secret)
     }
@@ -83,7 +130,13 @@ pub async fn find_secret(&self) -> Result<Option<String>> {
         debug!(key, "find keyring secret");
 
         let entry = self.entry.clone();
-        let secret = spawn_blocking(move || entry.get_password()).await?;
+        let index_key = key.clone();
+        let secret = spawn_blocking(move || {
+            let secret = entry.get_password()?;
+            metadata::record_use(&index_key);
+            Ok(secret)
+        })
+        .await?;
 
         match secret {
             Err(native::Error::NoEntry) => Ok(None),
@@ -93,15 +146,31 @@ pub async fn find_secret(&self) -> Result<Option<String>> {
     }
 
     /// (Re)sets the secret of the keyring entry.
+    ///
+    /// Attributes are saved as native attributes when the platform
+    /// supports them, and in the index of entries.
     pub async fn set_secret(&self, secret: impl ToString) -> Result<()> {
         let key = &self.key;
         debug!(key, "set keyring secret");
 
         let secret = secret.to_string();
         let entry = self.entry.clone();
-        spawn_blocking(move || entry.set_password(&secret))
-            .await?
-            .map_err(|err| Error::SetSecretError(err, key.clone()))?;
+        let index_key = key.clone();
+        let attributes = self.attributes.clone();
+        spawn_blocking(move || {
+            entry.set_password(&secret)?;
+
+            if !attributes.is_empty() {
+                if let Err(err) = entry.update_attributes(&attributes.to_native()) {
+                    warn!(?err, key = index_key, "cannot update keyring attributes");
+                }
+            }
+
+            metadata::record_set(&index_key, &attributes);
+            Ok(())
+        })
+        .await?
+        .map_err(|err| Error::SetSecretError(err, key.clone()))?;
 
         Ok(())
     }
@@ -122,9 +191,14 @@ pub async fn delete_secret(&self) -> Result<()> {
         debug!(key, "delete keyring secret");
 
         let entry = self.entry.clone();
-        spawn_blocking(move || entry.delete_credential())
-            .await?
-            .map_err(|err| Error::DeleteSecretError(err, key.clone()))?;
+        let index_key = key.clone();
+        spawn_blocking(move || {
+            entry.delete_credential()?;
+            metadata::record_delete(&index_key);
+            Ok(())
+        })
+        .await?
+        .map_err(|err| Error::DeleteSecretError(err, key.clone()))?;
 
         Ok(())
     }
@@ -146,7 +220,11 @@ fn try_from(key: String) -> Result<Self> {
             Err(err) => Err(Error::BuildEntryError(err, key.clone())),
         }?;
 
-        Ok(Self { key, entry })
+        Ok(Self {
+            key,
+            attributes: Default::default(),
+            entry,
+        })
     }
 }
 
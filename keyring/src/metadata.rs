@@ -0,0 +1,417 @@
+//! # Entry metadata
+//!
+//! Module dedicated to keyring entry metadata. Attributes are pushed
+//! to the native keyring when the platform supports them, but native
+//! keyrings cannot list entries. Metadata of every entry managed by
+//! this library is therefore also kept in an index, itself stored as
+//! a keyring entry of the global service (see [`INDEX_KEY`]).
+//!
+//! The index is stored as JSON. Updates are serialized across
+//! processes using a lock file in the temporary directory, and uses
+//! of an entry are recorded at most once per [`USE_RESOLUTION`].
+//!
+//! The index is best-effort: failing to update it never makes secret
+//! operations fail.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, OpenOptions},
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{Mutex, MutexGuard},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::{get_global_service_name, native, spawn_blocking, Error, Result};
+
+/// The key of the keyring entry holding the index.
+pub const INDEX_KEY: &str = ".keyring-lib-index";
+
+/// The minimum delay between two recorded uses of the same entry.
+///
+/// Reading a secret does not write the index if the entry has been
+/// used more recently than that.
+pub const USE_RESOLUTION: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum time to wait for the index lock file.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The age after which an index lock file is considered stale, for
+/// example because its process got killed.
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// Lock preventing concurrent read-modify-write of the index by
+/// threads of the current process.
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// Uses recorded by the current process, by key.
+static RECORDED_USES: Mutex<BTreeMap<String, SystemTime>> = Mutex::new(BTreeMap::new());
+
+/// The attributes of a keyring entry.
+///
+/// Attributes describe who created the entry and why, so that
+/// cleanup tools can tell entries apart.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KeyringAttributes {
+    /// The application that created the entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application: Option<String>,
+
+    /// The account the entry belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+
+    /// The purpose of the entry (password, OAuth 2.0 access token
+    /// etc).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+}
+
+impl KeyringAttributes {
+    /// Returns `true` if no attribute is defined.
+    pub fn is_empty(&self) -> bool {
+        self.application.is_none() && self.account.is_none() && self.purpose.is_none()
+    }
+
+    /// Returns the attributes understood by native keyrings.
+    pub(crate) fn to_native(&self) -> HashMap<&str, &str> {
+        let mut attrs = HashMap::new();
+
+        if let Some(application) = &self.application {
+            attrs.insert("application", application.as_str());
+        }
+
+        if let Some(account) = &self.account {
+            attrs.insert("account", account.as_str());
+        }
+
+        if let Some(purpose) = &self.purpose {
+            attrs.insert("purpose", purpose.as_str());
+        }
+
+        attrs
+    }
+}
+
+/// The metadata of a keyring entry, as stored in the index.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct KeyringEntryMetadata {
+    /// The key of the entry.
+    pub key: String,
+
+    /// The attributes of the entry.
+    #[serde(default, skip_serializing_if = "KeyringAttributes::is_empty")]
+    pub attributes: KeyringAttributes,
+
+    /// When the secret of the entry has been set for the first time.
+    #[serde(default, with = "timestamp")]
+    pub created_at: Option<SystemTime>,
+
+    /// When the secret of the entry has been set or read for the last
+    /// time, with a resolution of [`USE_RESOLUTION`].
+    #[serde(default, with = "timestamp")]
+    pub last_used_at: Option<SystemTime>,
+}
+
+impl KeyringEntryMetadata {
+    /// Returns `true` if the entry has been used within the last
+    /// [`USE_RESOLUTION`].
+    fn used_recently(&self, now: SystemTime) -> bool {
+        self.last_used_at
+            .and_then(|time| now.duration_since(time).ok())
+            .is_some_and(|elapsed| elapsed < USE_RESOLUTION)
+    }
+}
+
+/// The index of keyring entries, by key.
+///
+/// The index is (de)serialized as a list of entry metadata.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(from = "Vec<KeyringEntryMetadata>", into = "Vec<KeyringEntryMetadata>")]
+struct Index(BTreeMap<String, KeyringEntryMetadata>);
+
+impl From<Vec<KeyringEntryMetadata>> for Index {
+    fn from(entries: Vec<KeyringEntryMetadata>) -> Self {
+        let entries = entries
+            .into_iter()
+            .map(|metadata| (metadata.key.clone(), metadata));
+
+        Self(BTreeMap::from_iter(entries))
+    }
+}
+
+impl From<Index> for Vec<KeyringEntryMetadata> {
+    fn from(index: Index) -> Self {
+        index.0.into_values().collect()
+    }
+}
+
+/// The index lock file, removed when dropped.
+///
+/// Native keyrings do not provide any way to lock entries, so
+/// processes sharing the same service name coordinate through a file
+/// created atomically in the temporary directory.
+struct IndexLockFile {
+    path: PathBuf,
+}
+
+impl IndexLockFile {
+    fn acquire() -> std::io::Result<Self> {
+        let name = get_global_service_name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect::<String>();
+        let path = std::env::temp_dir().join(format!("{name}{INDEX_KEY}.lock"));
+        let started_at = SystemTime::now();
+
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(Self { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => (),
+                Err(err) => return Err(err),
+            }
+
+            let stale = fs::metadata(&path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|time| time.elapsed().ok())
+                .is_some_and(|age| age > LOCK_STALE_AFTER);
+
+            if stale {
+                warn!(?path, "removing stale keyring index lock file");
+                let _ = fs::remove_file(&path);
+                continue;
+            }
+
+            if started_at.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                return Err(ErrorKind::TimedOut.into());
+            }
+
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+impl Drop for IndexLockFile {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!(?err, path = ?self.path, "cannot remove keyring index lock file");
+        }
+    }
+}
+
+/// Lists the entries of the global service managed by this library,
+/// with their metadata.
+///
+/// Entries created before the index existed, or by other libraries,
+/// are not listed.
+pub async fn list_entries() -> Result<Vec<KeyringEntryMetadata>> {
+    let index = spawn_blocking(read_index).await??;
+    Ok(index.0.into_values().collect())
+}
+
+/// Records the given entry as set in the index.
+pub(crate) fn record_set(key: &str, attributes: &KeyringAttributes) {
+    let now = SystemTime::now();
+
+    update_index(|index| {
+        let metadata = index
+            .0
+            .entry(key.to_owned())
+            .or_insert_with(|| KeyringEntryMetadata {
+                key: key.to_owned(),
+                created_at: Some(now),
+                ..Default::default()
+            });
+
+        if !attributes.is_empty() {
+            metadata.attributes = attributes.clone();
+        }
+
+        metadata.last_used_at = Some(now);
+        true
+    });
+
+    recorded_uses().insert(key.to_owned(), now);
+}
+
+/// Records the given entry as used in the index.
+///
+/// The index is left untouched if the entry has already been used
+/// within the last [`USE_RESOLUTION`], either by the current process
+/// or by another one.
+pub(crate) fn record_use(key: &str) {
+    let now = SystemTime::now();
+
+    let used_recently = recorded_uses()
+        .get(key)
+        .and_then(|time| now.duration_since(*time).ok())
+        .is_some_and(|elapsed| elapsed < USE_RESOLUTION);
+
+    if used_recently {
+        return;
+    }
+
+    update_index(|index| match index.0.get_mut(key) {
+        Some(metadata) if !metadata.used_recently(now) => {
+            metadata.last_used_at = Some(now);
+            true
+        }
+        _ => false,
+    });
+
+    recorded_uses().insert(key.to_owned(), now);
+}
+
+/// Removes the given entry from the index.
+pub(crate) fn record_delete(key: &str) {
+    update_index(|index| index.0.remove(key).is_some());
+    recorded_uses().remove(key);
+}
+
+fn recorded_uses() -> MutexGuard<'static, BTreeMap<String, SystemTime>> {
+    RECORDED_USES.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn index_entry() -> Result<native::Entry> {
+    native::Entry::new(get_global_service_name(), INDEX_KEY)
+        .map_err(|err| Error::BuildEntryError(err, INDEX_KEY.to_owned()))
+}
+
+fn read_index() -> Result<Index> {
+    let _lock = INDEX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    read_index_unlocked(&index_entry()?)
+}
+
+fn read_index_unlocked(entry: &native::Entry) -> Result<Index> {
+    match entry.get_password() {
+        Ok(index) => match serde_json::from_str(&index) {
+            Ok(index) => Ok(index),
+            Err(err) => {
+                warn!(?err, "invalid keyring index, starting from an empty one");
+                Ok(Index::default())
+            }
+        },
+        Err(native::Error::NoEntry) => Ok(Index::default()),
+        Err(err) => Err(Error::ReadIndexError(err)),
+    }
+}
+
+/// Updates the index using the given function.
+///
+/// The function returns `true` if it changed the index, in which case
+/// the index is written back. Errors are logged, not returned.
+fn update_index(f: impl FnOnce(&mut Index) -> bool) {
+    let _lock = INDEX_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+
+    let _lock_file = match IndexLockFile::acquire() {
+        Ok(lock) => lock,
+        Err(err) => {
+            warn!(?err, "cannot lock keyring index");
+            return;
+        }
+    };
+
+    let res = index_entry().and_then(|entry| {
+        let mut index = read_index_unlocked(&entry)?;
+
+        if !f(&mut index) {
+            return Ok(false);
+        }
+
+        if index.0.is_empty() {
+            match entry.delete_credential() {
+                Ok(()) | Err(native::Error::NoEntry) => Ok(true),
+                Err(err) => Err(Error::WriteIndexError(err)),
+            }
+        } else {
+            let index = serde_json::to_string(&index).map_err(Error::SerializeIndexError)?;
+            entry
+                .set_password(&index)
+                .map(|()| true)
+                .map_err(Error::WriteIndexError)
+        }
+    });
+
+    match res {
+        Ok(true) => debug!("keyring index updated"),
+        Ok(false) => debug!("keyring index unchanged"),
+        Err(err) => warn!(?err, "cannot update keyring index"),
+    }
+}
+
+/// (De)serializes optional timestamps as UNIX timestamps in seconds.
+mod timestamp {
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let secs = time.map(|time| {
+            time.duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default()
+        });
+
+        serializer.serialize_some(&secs)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        let secs = Option::<u64>::deserialize(deserializer)?;
+        Ok(secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{Index, KeyringAttributes, KeyringEntryMetadata, USE_RESOLUTION};
+
+    #[test]
+    fn index_roundtrip() {
+        let metadata = KeyringEntryMetadata {
+            key: String::from("key\twith\ttabs"),
+            attributes: KeyringAttributes {
+                application: Some(String::from("app")),
+                purpose: Some(String::from("password")),
+                ..Default::default()
+            },
+            created_at: Some(UNIX_EPOCH + Duration::from_secs(42)),
+            last_used_at: None,
+        };
+
+        let index = Index::from(vec![metadata.clone()]);
+        let json = serde_json::to_string(&index).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"key":"key\twith\ttabs","attributes":{"application":"app","purpose":"password"},"created_at":42,"last_used_at":null}]"#
+        );
+
+        let index: Index = serde_json::from_str(&json).unwrap();
+        assert_eq!(Vec::from(index), vec![metadata]);
+    }
+
+    #[test]
+    fn used_recently() {
+        let now = UNIX_EPOCH + USE_RESOLUTION * 2;
+
+        let mut metadata = KeyringEntryMetadata::default();
+        assert!(!metadata.used_recently(now));
+
+        metadata.last_used_at = Some(now - USE_RESOLUTION / 2);
+        assert!(metadata.used_recently(now));
+
+        metadata.last_used_at = Some(now - USE_RESOLUTION);
+        assert!(!metadata.used_recently(now));
+    }
+}
@@ -1,6 +1,8 @@
 #[cfg(feature = "async-std")]
 use async_std::test;
-use keyring::{get_global_service_name, set_global_service_name, KeyringEntry};
+use keyring::{
+    get_global_service_name, list_entries, set_global_service_name, KeyringAttributes, KeyringEntry,
+};
 #[cfg(feature = "tokio")]
 use tokio::test;
 
@@ -13,7 +15,13 @@ async fn main() {
     assert_eq!(get_global_service_name(), "example");
 
     // test entry
-    let entry = KeyringEntry::try_new("key").unwrap();
+    let entry = KeyringEntry::try_new("key")
+        .unwrap()
+        .with_attributes(KeyringAttributes {
+            application: Some(String::from("example")),
+            purpose: Some(String::from("password")),
+            ..Default::default()
+        });
     assert_eq!(entry.key, "key");
 
     // test set/get secret
@@ -21,7 +29,15 @@ async fn main() {
     let secret = entry.get_secret().await.unwrap();
     assert_eq!(secret, "secret");
 
+    // test entry metadata
+    let metadata = entry.get_metadata().await.unwrap().unwrap();
+    assert_eq!(&metadata.attributes, entry.attributes());
+    assert!(metadata.created_at.is_some());
+    assert!(metadata.last_used_at >= metadata.created_at);
+
     // test delete/find entry
     entry.delete_secret().await.unwrap();
     assert_eq!(entry.find_secret().await.unwrap(), None);
+    let entries = list_entries().await.unwrap();
+    assert!(!entries.iter().any(|metadata| metadata.key == "key"));
 }
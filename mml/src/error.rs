@@ -20,10 +20,25 @@ pub enum Error {
     #[cfg(feature = "compiler")]
     #[error("cannot read attachment at {1:?}")]
     ReadAttachmentError(#[source] io::Error, PathBuf),
+    #[cfg(feature = "compiler")]
+    #[error("cannot decode base64 data of part")]
+    DecodeBase64DataError,
+    #[cfg(feature = "compiler")]
+    #[error("cannot decode quoted-printable data of part")]
+    DecodeQuotedPrintableDataError,
 
     #[cfg(feature = "pgp")]
     #[error("cannot sign part using pgp: missing sender")]
     PgpSignMissingSenderError,
+    #[cfg(feature = "pgp")]
+    #[error("cannot encrypt part using pgp: missing public key for {}", .0.join(", "))]
+    PgpEncryptMissingPublicKeysError(Vec<String>),
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot encrypt data using password: pgp backend not supported")]
+    PgpEncryptWithPasswordNotSupportedError,
+    #[cfg(feature = "pgp-native")]
+    #[error("cannot get pgp encryption password")]
+    GetPgpEncryptionPasswordError(#[source] secret::Error),
 
     #[cfg(all(feature = "pgp-native", feature = "keyring"))]
     #[error("cannot get pgp secret key from keyring")]
@@ -74,6 +89,14 @@ pub enum Error {
     #[error("cannot read native pgp secret key")]
     ReadNativePgpSecretKeyError(#[source] pgp::Error),
 
+    #[cfg(all(feature = "pgp-native", unix))]
+    #[error("cannot get native pgp secret key of {0}: key held by gpg-agent")]
+    GetNativePgpSecretKeyFromAgentError(String),
+
+    #[cfg(all(feature = "pgp-native", unix))]
+    #[error("cannot read native pgp public key of gpg-agent secret key")]
+    ReadNativePgpAgentPublicKeyError(#[source] pgp::Error),
+
     #[error("cannot parse MIME message")]
     ParseMimeMessageError,
     #[error("cannot save attachment at {1}")]
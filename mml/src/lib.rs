@@ -2,6 +2,8 @@
 #![doc = include_str!("../README.md")]
 
 mod error;
+#[cfg(feature = "markdown")]
+pub mod markdown;
 pub mod message;
 #[cfg(feature = "pgp")]
 pub mod pgp;
@@ -0,0 +1,307 @@
+//! # Markdown
+//!
+//! Module dedicated to markdown bodies. A markdown body is compiled
+//! to a `multipart/alternative` part: the markdown source, which is
+//! already readable as is, becomes the plain text part, and the
+//! [`MarkdownRenderer`] produces the HTML part.
+//!
+//! Markdown is parsed by [`pulldown_cmark`], following the
+//! CommonMark specification. Since the HTML part is read by email
+//! clients, raw HTML is escaped and only links using one of the
+//! [`ALLOWED_URL_SCHEMES`] are rendered.
+
+use std::collections::HashMap;
+
+use pulldown_cmark::{html, Event, LinkType, Options, Parser, Tag, TagEnd};
+use tracing::debug;
+
+/// The URL schemes allowed in links and images.
+///
+/// Links and images using another scheme (`javascript:`, `data:`,
+/// relative URLs etc) are rendered as plain text.
+pub const ALLOWED_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// The markdown to HTML renderer.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MarkdownRenderer {
+    /// Inlined styles, by element name.
+    styles: HashMap<String, String>,
+}
+
+impl MarkdownRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the stylesheet to inline into the rendered elements.
+    ///
+    /// Since most email clients ignore `<style>` elements, rules are
+    /// inlined into the `style` attribute of the matching
+    /// elements. Only type selectors are supported, for example `p`,
+    /// `h1, h2` or `code`.
+    pub fn set_css(&mut self, css: &str) {
+        let css = strip_css_comments(css);
+
+        for rule in css.split('}') {
+            let Some((selectors, decls)) = rule.split_once('{') else {
+                continue;
+            };
+
+            let decls: Vec<_> = decls
+                .split(';')
+                .map(str::trim)
+                .filter(|decl| !decl.is_empty())
+                .collect();
+
+            if decls.is_empty() {
+                continue;
+            }
+
+            let decls = decls.join("; ");
+
+            for selector in selectors.split(',') {
+                let selector = selector.trim().to_ascii_lowercase();
+
+                if selector.is_empty() || !selector.chars().all(|c| c.is_ascii_alphanumeric()) {
+                    debug!(selector, "skipping unsupported css selector");
+                    continue;
+                }
+
+                self.styles
+                    .entry(selector)
+                    .and_modify(|style| {
+                        style.push_str("; ");
+                        style.push_str(&decls);
+                    })
+                    .or_insert_with(|| decls.clone());
+            }
+        }
+    }
+
+    /// Set some stylesheet, using the builder pattern.
+    pub fn with_some_css(mut self, css: Option<impl AsRef<str>>) -> Self {
+        if let Some(css) = css {
+            self.set_css(css.as_ref());
+        }
+        self
+    }
+
+    /// Set the stylesheet, using the builder pattern.
+    pub fn with_css(self, css: impl AsRef<str>) -> Self {
+        self.with_some_css(Some(css))
+    }
+
+    /// Render the given markdown as HTML.
+    pub fn render(&self, markdown: &str) -> String {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_TABLES);
+
+        // whether the opened links and images are skipped, so that
+        // their end can be skipped as well
+        let mut links = Vec::new();
+        let mut images = Vec::new();
+
+        let events = Parser::new_ext(markdown, options).filter_map(|event| match event {
+            Event::Start(Tag::Link {
+                link_type,
+                ref dest_url,
+                ..
+            }) => {
+                let allowed = is_url_allowed(link_type, dest_url);
+                links.push(allowed);
+                allowed.then_some(event)
+            }
+            Event::End(TagEnd::Link) => links.pop().unwrap_or(true).then_some(event),
+            Event::Start(Tag::Image {
+                link_type,
+                ref dest_url,
+                ..
+            }) => {
+                let allowed = is_url_allowed(link_type, dest_url);
+                images.push(allowed);
+                allowed.then_some(event)
+            }
+            Event::End(TagEnd::Image) => images.pop().unwrap_or(true).then_some(event),
+            Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+            event => Some(event),
+        });
+
+        let mut html = String::new();
+        html::push_html(&mut html, events);
+        self.inline_styles(&html)
+    }
+
+    /// Render the given signature as preformatted HTML, so that it
+    /// keeps its line breaks.
+    pub fn render_signature(&self, signature: &str) -> String {
+        let html = format!("<pre>{}</pre>\n", escape(signature));
+        self.inline_styles(&html)
+    }
+
+    /// Insert the inlined styles into the matching opening tags of
+    /// the given HTML.
+    ///
+    /// The HTML is expected to come from the renderer, where text and
+    /// attributes are escaped: every `<` starts a tag.
+    fn inline_styles(&self, html: &str) -> String {
+        if self.styles.is_empty() {
+            return html.to_owned();
+        }
+
+        let mut styled = String::with_capacity(html.len());
+        let mut rest = html;
+
+        while let Some(start) = rest.find('<') {
+            let tag = &rest[start + 1..];
+            let len = tag
+                .find(|c: char| !c.is_ascii_alphanumeric())
+                .unwrap_or(tag.len());
+            let name = tag[..len].to_ascii_lowercase();
+
+            styled.push_str(&rest[..start + 1 + len]);
+
+            if let Some(style) = self.styles.get(&name) {
+                styled.push_str(" style=\"");
+                styled.push_str(&escape(style));
+                styled.push('"');
+            }
+
+            rest = &tag[len..];
+        }
+
+        styled.push_str(rest);
+        styled
+    }
+}
+
+/// Return `true` if the given link or image URL uses one of the
+/// [`ALLOWED_URL_SCHEMES`].
+///
+/// Email autolinks have no scheme, the renderer prefixes them with
+/// `mailto:`.
+fn is_url_allowed(link_type: LinkType, url: &str) -> bool {
+    if link_type == LinkType::Email {
+        return true;
+    }
+
+    let allowed = url.split_once(':').is_some_and(|(scheme, _)| {
+        ALLOWED_URL_SCHEMES
+            .iter()
+            .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+    });
+
+    if !allowed {
+        debug!(url, "skipping link with disallowed url scheme");
+    }
+
+    allowed
+}
+
+fn strip_css_comments(css: &str) -> String {
+    let mut stripped = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("/*") {
+        stripped.push_str(&rest[..start]);
+        rest = match rest[start + 2..].find("*/") {
+            Some(end) => &rest[start + 2 + end + 2..],
+            None => "",
+        };
+    }
+
+    stripped.push_str(rest);
+    stripped
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use concat_with::concat_line;
+
+    use super::MarkdownRenderer;
+
+    #[test]
+    fn render() {
+        let markdown = concat_line!(
+            "# Hello *world*",
+            "",
+            "Some **bold** text with `code` and a [link](https://example.com \"title\").",
+            "Next line, <user@localhost> & co.",
+            "",
+            "- one",
+            "- two",
+            "",
+            "```rust",
+            "let x = 1 < 2;",
+            "```",
+        );
+
+        let html = MarkdownRenderer::new().render(markdown);
+
+        let expected = concat_line!(
+            "<h1>Hello <em>world</em></h1>",
+            "<p>Some <strong>bold</strong> text with <code>code</code> and a <a href=\"https://example.com\" title=\"title\">link</a>.",
+            "Next line, <a href=\"mailto:user@localhost\">user@localhost</a> &amp; co.</p>",
+            "<ul>",
+            "<li>one</li>",
+            "<li>two</li>",
+            "</ul>",
+            "<pre><code class=\"language-rust\">let x = 1 &lt; 2;",
+            "</code></pre>",
+            "",
+        );
+
+        assert_eq!(html, expected);
+    }
+
+    #[test]
+    fn disallowed_urls() {
+        let markdown = concat_line!(
+            "[a](javascript:alert(1)) [b](JavaScript:alert(1)) [c](data:text/html,x)",
+            "[d](relative/path) ![e](file:///etc/passwd) [f](MAILTO:me@localhost)",
+            "Raw <b onclick=\"alert(1)\">html</b>",
+        );
+
+        let html = MarkdownRenderer::new().render(markdown);
+
+        let expected = concat_line!(
+            "<p>a b c",
+            "d e <a href=\"MAILTO:me@localhost\">f</a>",
+            "Raw &lt;b onclick=\"alert(1)\"&gt;html&lt;/b&gt;</p>",
+            "",
+        );
+
+        assert_eq!(html, expected);
+    }
+
+    #[test]
+    fn inline_css() {
+        let renderer = MarkdownRenderer::new()
+            .with_css("/* comment */ p, li { margin: 0; color: red } a:hover { color: blue } p { padding: 0 }");
+
+        assert_eq!(
+            renderer.render("text"),
+            "<p style=\"margin: 0; color: red; padding: 0\">text</p>\n"
+        );
+        assert_eq!(
+            renderer.render("a < b"),
+            "<p style=\"margin: 0; color: red; padding: 0\">a &lt; b</p>\n"
+        );
+    }
+}
@@ -5,31 +5,42 @@
 mod parsers;
 mod tokens;
 
+#[cfg(feature = "pgp")]
+use std::sync::Mutex;
 use std::{ffi::OsStr, fs, ops::Deref};
 
 use async_recursion::async_recursion;
 use mail_builder::{
+    headers::content_type::ContentType,
     mime::{BodyPart, MimePart},
     MessageBuilder,
 };
+use mail_parser::decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode};
 use shellexpand_utils::shellexpand_path;
 #[allow(unused_imports)]
 use tracing::{debug, warn};
 
+#[cfg(feature = "markdown")]
+use crate::markdown::MarkdownRenderer;
 #[cfg(feature = "pgp")]
-use crate::pgp::Pgp;
+use crate::pgp::{Pgp, PgpMissingKeyPolicy, PgpRecipient, PgpRecipientStatus};
 use crate::{Error, Result};
 
+#[cfg(feature = "markdown")]
+use super::TEXT_MARKDOWN;
 use super::{
-    ALTERNATIVE, ATTACHMENT, DISPOSITION, ENCODING, ENCODING_7BIT, ENCODING_8BIT, ENCODING_BASE64,
-    ENCODING_QUOTED_PRINTABLE, FILENAME, INLINE, MIXED, MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED,
-    MULTIPART_END, MULTIPART_END_ESCAPED, NAME, PART_BEGIN, PART_BEGIN_ESCAPED, PART_END,
-    PART_END_ESCAPED, RECIPIENT_FILENAME, RELATED, TYPE,
+    ALTERNATIVE, ATTACHMENT, DATA_ENCODING, DISPOSITION, ENCODING, ENCODING_7BIT, ENCODING_8BIT,
+    ENCODING_BASE64, ENCODING_QUOTED_PRINTABLE, FILENAME, INLINE, MESSAGE_RFC822, MIXED,
+    MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED, MULTIPART_END, MULTIPART_END_ESCAPED, NAME,
+    PART_BEGIN, PART_BEGIN_ESCAPED, PART_END, PART_END_ESCAPED, RECIPIENT_FILENAME, RELATED, TYPE,
 };
 #[cfg(feature = "pgp")]
 use super::{ENCRYPT, PGP_MIME, SIGN};
 
-use self::{parsers::prelude::*, tokens::Part};
+use self::{
+    parsers::prelude::*,
+    tokens::{Part, Props},
+};
 
 /// MML → MIME message body compiler.
 ///
@@ -37,12 +48,43 @@
 /// is named `compile`.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct MmlBodyCompiler {
+    #[cfg(feature = "markdown")]
+    markdown: MarkdownRenderer,
     #[cfg(feature = "pgp")]
     pgp: Option<Pgp>,
     #[cfg(feature = "pgp")]
     pgp_sender: Option<String>,
     #[cfg(feature = "pgp")]
     pgp_recipients: Vec<String>,
+    #[cfg(feature = "pgp")]
+    pgp_missing_key_policy: PgpMissingKeyPolicy,
+}
+
+/// The state shared by the parts of a MML body being compiled.
+#[derive(Debug, Default)]
+pub(crate) struct MmlBodyCompileState {
+    /// The encryption status of the PGP recipients, from the last
+    /// encrypted part.
+    #[cfg(feature = "pgp")]
+    pgp_recipients: Mutex<Vec<PgpRecipient>>,
+}
+
+impl MmlBodyCompileState {
+    #[cfg(feature = "pgp")]
+    fn set_pgp_recipients(&self, recipients: Vec<PgpRecipient>) {
+        *self
+            .pgp_recipients
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = recipients;
+    }
+
+    /// Take the encryption status of the PGP recipients.
+    #[cfg(feature = "pgp")]
+    pub(crate) fn into_pgp_recipients(self) -> Vec<PgpRecipient> {
+        self.pgp_recipients
+            .into_inner()
+            .unwrap_or_else(|err| err.into_inner())
+    }
 }
 
 impl<'a> MmlBodyCompiler {
@@ -51,6 +93,17 @@ pub fn new() -> Self {
         Self::default()
     }
 
+    #[cfg(feature = "markdown")]
+    pub fn set_markdown_css(&mut self, css: impl AsRef<str>) {
+        self.markdown.set_css(css.as_ref());
+    }
+
+    #[cfg(feature = "markdown")]
+    pub fn with_markdown_css(mut self, css: impl AsRef<str>) -> Self {
+        self.set_markdown_css(css);
+        self
+    }
+
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
         self.pgp = Some(pgp.into());
@@ -85,9 +138,27 @@ pub fn with_pgp_recipients(mut self, recipients: Vec<String>) -> Self {
         self
     }
 
+    #[cfg(feature = "pgp")]
+    pub fn set_pgp_missing_key_policy(&mut self, policy: PgpMissingKeyPolicy) {
+        self.pgp_missing_key_policy = policy;
+    }
+
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_missing_key_policy(mut self, policy: PgpMissingKeyPolicy) -> Self {
+        self.set_pgp_missing_key_policy(policy);
+        self
+    }
+
     /// Encrypt the given MIME part using PGP.
+    ///
+    /// Recipients without public key are handled according to the
+    /// missing key policy, their status is saved in the given state.
     #[cfg(feature = "pgp")]
-    async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>> {
+    async fn encrypt_part(
+        &self,
+        clear_part: &MimePart<'a>,
+        state: &MmlBodyCompileState,
+    ) -> Result<MimePart<'a>> {
         match &self.pgp {
             None => {
                 debug!("cannot encrypt part: pgp not configured");
@@ -95,6 +166,21 @@ async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>>
             }
             Some(pgp) => {
                 let recipients = self.pgp_recipients.clone();
+                let missing = pgp.find_missing_public_keys(recipients.clone()).await?;
+
+                let status = |status: PgpRecipientStatus| {
+                    recipients
+                        .iter()
+                        .map(|email| PgpRecipient {
+                            email: email.clone(),
+                            status: if missing.contains(email) {
+                                PgpRecipientStatus::MissingKey
+                            } else {
+                                status.clone()
+                            },
+                        })
+                        .collect::<Vec<_>>()
+                };
 
                 let mut clear_part_bytes = Vec::new();
                 clear_part
@@ -102,7 +188,57 @@ async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>>
                     .write_part(&mut clear_part_bytes)
                     .map_err(Error::WriteCompiledPartToVecError)?;
 
-                let encrypted_part_bytes = pgp.encrypt(recipients, clear_part_bytes).await?;
+                let encrypted_part_bytes = if missing.is_empty() {
+                    state.set_pgp_recipients(status(PgpRecipientStatus::Encrypted));
+                    pgp.encrypt(recipients, clear_part_bytes).await?
+                } else {
+                    match &self.pgp_missing_key_policy {
+                        PgpMissingKeyPolicy::Fail => {
+                            return Err(Error::PgpEncryptMissingPublicKeysError(missing));
+                        }
+                        PgpMissingKeyPolicy::SkipEncryption => {
+                            warn!(?missing, "missing pgp public keys, skipping encryption");
+                            let recipients = recipients
+                                .into_iter()
+                                .map(|email| PgpRecipient {
+                                    email,
+                                    status: PgpRecipientStatus::Unencrypted,
+                                })
+                                .collect();
+                            state.set_pgp_recipients(recipients);
+                            return Ok(clear_part.clone());
+                        }
+                        PgpMissingKeyPolicy::EncryptToAvailable => {
+                            if missing.len() == recipients.len() {
+                                return Err(Error::PgpEncryptMissingPublicKeysError(missing));
+                            }
+
+                            warn!(?missing, "missing pgp public keys, encrypting to others");
+                            state.set_pgp_recipients(status(PgpRecipientStatus::Encrypted));
+                            let available = recipients
+                                .into_iter()
+                                .filter(|email| !missing.contains(email));
+                            pgp.encrypt(available, clear_part_bytes).await?
+                        }
+                        #[cfg(feature = "pgp-native")]
+                        PgpMissingKeyPolicy::Password(password) => {
+                            warn!(
+                                ?missing,
+                                "missing pgp public keys, encrypting with password"
+                            );
+                            let recipients = recipients
+                                .into_iter()
+                                .map(|email| PgpRecipient {
+                                    email,
+                                    status: PgpRecipientStatus::PasswordProtected,
+                                })
+                                .collect();
+                            state.set_pgp_recipients(recipients);
+                            pgp.encrypt_with_password(password, clear_part_bytes)
+                                .await?
+                        }
+                    }
+                };
                 let encrypted_part_bytes =
                     encrypted_part_bytes
                         .into_iter()
@@ -132,15 +268,22 @@ async fn encrypt_part(&self, clear_part: &MimePart<'a>) -> Result<MimePart<'a>>
     /// Try to encrypt the given MIME part using PGP.
     ///
     /// If the operation fails, log a warning and return the original
-    /// MIME part.
+    /// MIME part. Missing public keys are not considered as failures
+    /// but handled by the missing key policy, which may fail the
+    /// compilation.
     #[cfg(feature = "pgp")]
-    async fn try_encrypt_part(&self, clear_part: MimePart<'a>) -> MimePart<'a> {
-        match self.encrypt_part(&clear_part).await {
-            Ok(encrypted_part) => encrypted_part,
+    async fn try_encrypt_part(
+        &self,
+        clear_part: MimePart<'a>,
+        state: &MmlBodyCompileState,
+    ) -> Result<MimePart<'a>> {
+        match self.encrypt_part(&clear_part, state).await {
+            Ok(encrypted_part) => Ok(encrypted_part),
+            Err(err @ Error::PgpEncryptMissingPublicKeysError(_)) => Err(err),
             Err(err) => {
                 debug!("cannot encrypt email part using pgp: {err}");
                 debug!("{err:?}");
-                clear_part
+                Ok(clear_part)
             }
         }
     }
@@ -217,19 +360,89 @@ fn unescape_mml_markup(text: impl AsRef<str>) -> String {
             .replace(MULTIPART_END_ESCAPED, MULTIPART_END)
     }
 
+    /// Compile the given raw message to a `message/rfc822` MIME part.
+    ///
+    /// The message is embedded as it is, only line endings are
+    /// normalized to CRLF. Its transfer encoding is 7bit, or 8bit if
+    /// it contains non-ASCII bytes.
+    fn compile_message_part(ctype: impl Into<ContentType<'a>>, contents: &[u8]) -> MimePart<'a> {
+        let mut message = Vec::with_capacity(contents.len());
+        let mut prev = 0;
+
+        for &b in contents {
+            if b == b'\n' && prev != b'\r' {
+                message.push(b'\r');
+            }
+            message.push(b);
+            prev = b;
+        }
+
+        let encoding = if message.is_ascii() {
+            ENCODING_7BIT
+        } else {
+            ENCODING_8BIT
+        };
+
+        MimePart::new(ctype, message).transfer_encoding(encoding)
+    }
+
+    /// Compile the given markdown to a `multipart/alternative` MIME
+    /// part, made of the markdown source as plain text and of its
+    /// HTML rendering.
+    #[cfg(feature = "markdown")]
+    fn compile_markdown_part(&self, markdown: &str) -> MimePart<'a> {
+        let html = self.markdown.render(markdown);
+
+        MimePart::new(
+            "multipart/alternative",
+            vec![
+                MimePart::new("text/plain", markdown.to_owned()),
+                MimePart::new("text/html", html),
+            ],
+        )
+    }
+
+    /// Decode the given inline part body, if its data has been
+    /// encoded.
+    ///
+    /// Returns `None` if the part has no `data-encoding` property, in
+    /// which case the body is used as it is.
+    fn decode_data(props: &Props, body: &str) -> Result<Option<Vec<u8>>> {
+        match props.get(DATA_ENCODING) {
+            Some(&ENCODING_BASE64) => {
+                let data: Vec<u8> = body.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+                let data = base64_decode(&data).ok_or(Error::DecodeBase64DataError)?;
+                Ok(Some(data))
+            }
+            Some(&ENCODING_QUOTED_PRINTABLE) => {
+                let data = quoted_printable_decode(body.as_bytes())
+                    .ok_or(Error::DecodeQuotedPrintableDataError)?;
+                Ok(Some(data))
+            }
+            _ => Ok(None),
+        }
+    }
+
     /// Compile given parts parsed from a MML body to a
     /// [MessageBuilder].
-    async fn compile_parts(&'a self, parts: Vec<Part<'a>>) -> Result<MessageBuilder> {
+    async fn compile_parts(
+        &'a self,
+        parts: Vec<Part<'a>>,
+        state: &MmlBodyCompileState,
+    ) -> Result<MessageBuilder> {
         let mut builder = MessageBuilder::new();
 
         builder = match parts.len() {
             0 => builder.text_body(String::new()),
-            1 => builder.body(self.compile_part(parts.into_iter().next().unwrap()).await?),
+            1 => builder.body(
+                self.compile_part(parts.into_iter().next().unwrap(), state)
+                    .await?,
+            ),
             _ => {
                 let mut compiled_parts = Vec::new();
 
                 for part in parts {
-                    let part = self.compile_part(part).await?;
+                    let part = self.compile_part(part, state).await?;
                     compiled_parts.push(part);
                 }
 
@@ -242,7 +455,11 @@ async fn compile_parts(&'a self, parts: Vec<Part<'a>>) -> Result<MessageBuilder>
 
     /// Compile the given part parsed from MML body to a [MimePart].
     #[async_recursion]
-    async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
+    async fn compile_part(
+        &'a self,
+        part: Part<'a>,
+        #[allow(unused_variables)] state: &MmlBodyCompileState,
+    ) -> Result<MimePart> {
         match part {
             Part::Multi(props, parts) => {
                 let no_parts = BodyPart::Multipart(Vec::new());
@@ -258,7 +475,7 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                 };
 
                 for part in parts {
-                    multi_part.add_part(self.compile_part(part).await?)
+                    multi_part.add_part(self.compile_part(part, state).await?)
                 }
 
                 #[cfg(feature = "pgp")]
@@ -269,7 +486,7 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                     };
 
                     multi_part = match props.get(ENCRYPT) {
-                        Some(&PGP_MIME) => self.try_encrypt_part(multi_part).await,
+                        Some(&PGP_MIME) => self.try_encrypt_part(multi_part, state).await?,
                         _ => multi_part,
                     };
                 }
@@ -278,6 +495,13 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
             }
             Part::Single(ref props, body) => {
                 let fpath = props.get(FILENAME).map(shellexpand_path);
+                let is_message = props.get(TYPE) == Some(&MESSAGE_RFC822);
+                // only inline markdown without data encoding is
+                // rendered, other markdown parts are kept as they are
+                #[cfg(feature = "markdown")]
+                let is_markdown = fpath.is_none()
+                    && props.get(TYPE) == Some(&TEXT_MARKDOWN)
+                    && props.get(DATA_ENCODING).is_none();
 
                 let mut part = match &fpath {
                     Some(fpath) => {
@@ -287,19 +511,45 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                         if let Some(name) = props.get(NAME) {
                             ctype = ctype.attribute("name", *name);
                         }
-                        MimePart::new(ctype, contents)
+                        if is_message {
+                            Self::compile_message_part(ctype, &contents)
+                        } else {
+                            MimePart::new(ctype, contents)
+                        }
                     }
                     None => {
-                        let mut ctype =
-                            Part::get_or_guess_content_type(props, body.as_bytes()).into();
+                        let data = Self::decode_data(props, body)?;
+                        let contents = data.as_deref().unwrap_or(body.as_bytes());
+                        let mut ctype = Part::get_or_guess_content_type(props, contents).into();
                         if let Some(name) = props.get(NAME) {
                             ctype = ctype.attribute("name", *name);
                         }
-                        MimePart::new(ctype, body)
+                        match data {
+                            Some(data) if is_message => Self::compile_message_part(ctype, &data),
+                            Some(data) => MimePart::new(ctype, data),
+                            None if is_message => {
+                                Self::compile_message_part(ctype, body.as_bytes())
+                            }
+                            #[cfg(feature = "markdown")]
+                            None if is_markdown => self.compile_markdown_part(body),
+                            None => MimePart::new(ctype, body),
+                        }
                     }
                 };
 
                 part = match props.get(ENCODING) {
+                    // RFC 2046 §5.2.1: message/rfc822 parts can only
+                    // be 7bit, 8bit or binary encoded, which is
+                    // already handled by the message part itself
+                    Some(encoding) if is_message => {
+                        debug!("ignoring encoding {encoding} of message/rfc822 part");
+                        part
+                    }
+                    #[cfg(feature = "markdown")]
+                    Some(encoding) if is_markdown => {
+                        debug!("ignoring encoding {encoding} of markdown part");
+                        part
+                    }
                     Some(&ENCODING_7BIT) => part.transfer_encoding(ENCODING_7BIT),
                     Some(&ENCODING_8BIT) => part.transfer_encoding(ENCODING_8BIT),
                     Some(&ENCODING_QUOTED_PRINTABLE) => {
@@ -346,7 +596,7 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
                     };
 
                     part = match props.get(ENCRYPT) {
-                        Some(&PGP_MIME) => self.try_encrypt_part(part).await,
+                        Some(&PGP_MIME) => self.try_encrypt_part(part, state).await?,
                         _ => part,
                     };
                 };
@@ -363,9 +613,20 @@ async fn compile_part(&'a self, part: Part<'a>) -> Result<MimePart> {
 
     /// Compile the given raw MML body to MIME body.
     pub async fn compile(&'a self, mml_body: &'a str) -> Result<MessageBuilder> {
+        let state = MmlBodyCompileState::default();
+        self.compile_with_state(mml_body, &state).await
+    }
+
+    /// Compile the given raw MML body to MIME body, saving
+    /// compilation details in the given state.
+    pub(crate) async fn compile_with_state(
+        &'a self,
+        mml_body: &'a str,
+        state: &MmlBodyCompileState,
+    ) -> Result<MessageBuilder> {
         let res = parsers::parts().parse(mml_body);
         if let Some(parts) = res.output() {
-            Ok(self.compile_parts(parts.to_owned()).await?)
+            Ok(self.compile_parts(parts.to_owned(), state).await?)
         } else {
             let errs = res.errors().map(|err| err.clone().into_owned()).collect();
             Err(Error::ParseMmlError(errs, mml_body.to_owned()))
@@ -476,4 +737,115 @@ async fn attachment() {
 
         assert_eq!(msg, expected_msg);
     }
+
+    #[tokio::test]
+    async fn message_attachment() {
+        let mut attachment = Builder::new()
+            .prefix("message")
+            .suffix(".eml")
+            .rand_bytes(0)
+            .tempfile()
+            .unwrap();
+        write!(attachment, "From: a@localhost\nSubject: hi\n\nHello!\n").unwrap();
+        let attachment_path = attachment.path().to_string_lossy();
+
+        let mml_body = format!(
+            "<#part type=message/rfc822 filename={attachment_path} recipient-filename=hi.eml encoding=base64><#/part>"
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(&mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: message/rfc822\r",
+            "Content-Transfer-Encoding: 7bit\r",
+            "Content-Disposition: attachment; filename=\"hi.eml\"\r",
+            "\r",
+            "From: a@localhost\r",
+            "Subject: hi\r",
+            "\r",
+            "Hello!\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+    }
+
+    #[tokio::test]
+    async fn inline_message_attachment() {
+        let mml_body = concat_line!(
+            "<#part type=message/rfc822 disposition=attachment recipient-filename=hi.eml data-encoding=base64>",
+            "RnJvbTogYUBsb2NhbGhvc3QKU3ViamVj",
+            "dDogaGkKCkhlbGxvIQo=",
+            "<#/part>",
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .message_id("id@localhost")
+            .date(0_u64)
+            .write_to_string()
+            .unwrap();
+
+        let expected_msg = concat_line!(
+            "Message-ID: <id@localhost>\r",
+            "Date: Thu, 1 Jan 1970 00:00:00 +0000\r",
+            "MIME-Version: 1.0\r",
+            "Content-Type: message/rfc822\r",
+            "Content-Transfer-Encoding: 7bit\r",
+            "Content-Disposition: attachment; filename=\"hi.eml\"\r",
+            "\r",
+            "From: a@localhost\r",
+            "Subject: hi\r",
+            "\r",
+            "Hello!\r",
+            "",
+        );
+
+        assert_eq!(msg, expected_msg);
+    }
+
+    #[cfg(feature = "markdown")]
+    #[tokio::test]
+    async fn markdown() {
+        use mail_parser::MimeHeaders;
+
+        let mml_body = concat_line!(
+            "<#part type=text/markdown>",
+            "Hello, **world**! [Click](javascript:alert(1))",
+            "<#/part>",
+        );
+
+        let msg = MmlBodyCompiler::new()
+            .with_markdown_css("strong { color: red }")
+            .compile(mml_body)
+            .await
+            .unwrap()
+            .write_to_vec()
+            .unwrap();
+
+        let msg = mail_parser::MessageParser::new().parse(&msg).unwrap();
+        let content_type = msg.root_part().content_type().unwrap();
+
+        assert_eq!(content_type.subtype(), Some("alternative"));
+        assert_eq!(
+            msg.body_text(0).unwrap().trim(),
+            "Hello, **world**! [Click](javascript:alert(1))"
+        );
+        assert_eq!(
+            msg.body_html(0).unwrap().trim(),
+            "<p>Hello, <strong style=\"color: red\">world</strong>! Click</p>"
+        );
+    }
 }
@@ -2,7 +2,7 @@
 //!
 //! Module dedicated to MIME → MML message body interpretation.
 
-use std::{env, fs, path::PathBuf};
+use std::{borrow::Cow, env, fs, path::PathBuf};
 
 use async_recursion::async_recursion;
 use mail_builder::MessageBuilder;
@@ -13,7 +13,7 @@
 
 #[cfg(feature = "pgp")]
 use crate::pgp::Pgp;
-use crate::{Error, Result};
+use crate::{message::CharsetDetector, Error, Result};
 
 use super::{
     MULTIPART_BEGIN, MULTIPART_BEGIN_ESCAPED, MULTIPART_END, MULTIPART_END_ESCAPED, PART_BEGIN,
@@ -149,6 +149,14 @@ pub struct MimeBodyInterpreter {
     /// [`std::env::temp_dir()`].
     save_attachments_dir: PathBuf,
 
+    /// Defines the charset detector of text parts.
+    ///
+    /// Legacy messages may lack charset information, or may lie
+    /// about it. Text parts whose bytes do not decode in their
+    /// declared charset are decoded again using the detected one.
+    /// See [`CharsetDetector`].
+    charset_detector: CharsetDetector,
+
     #[cfg(feature = "pgp")]
     pgp: Option<Pgp>,
     #[cfg(feature = "pgp")]
@@ -168,6 +176,7 @@ fn default() -> Self {
             show_plain_texts_signature: true,
             save_attachments: Default::default(),
             save_attachments_dir: Self::default_save_attachments_dir(),
+            charset_detector: Default::default(),
             #[cfg(feature = "pgp")]
             pgp: Default::default(),
             #[cfg(feature = "pgp")]
@@ -227,6 +236,18 @@ pub fn with_save_attachments_dir(mut self, dir: impl Into<PathBuf>) -> Self {
         self
     }
 
+    pub fn set_charset_fallbacks(&mut self, charsets: impl IntoIterator<Item = impl ToString>) {
+        self.charset_detector = CharsetDetector::new(charsets);
+    }
+
+    pub fn with_charset_fallbacks(
+        mut self,
+        charsets: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.set_charset_fallbacks(charsets);
+        self
+    }
+
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
         self.pgp = Some(pgp.into());
@@ -270,6 +291,15 @@ fn escape_mml_markup(text: String) -> String {
             .replace(MULTIPART_END, MULTIPART_END_ESCAPED)
     }
 
+    /// Decode the given text part again using the charset detector,
+    /// if needed.
+    fn decode_text<'a>(&self, msg: &Message, part: &MessagePart, text: &'a str) -> Cow<'a, str> {
+        match self.charset_detector.decode_part(msg, part) {
+            Some(text) => Cow::Owned(text),
+            None => Cow::Borrowed(text),
+        }
+    }
+
     /// Decrypt the given [MessagePart] using PGP.
     #[cfg(feature = "pgp")]
     async fn decrypt_part(&self, encrypted_part: &MessagePart<'_>) -> Result<String> {
@@ -443,13 +473,16 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
 
         match &part.body {
             PartType::Text(plain) if ctype == "text/plain" => {
-                tpl.push_str(&self.interpret_text_plain(plain));
+                let plain = self.decode_text(msg, part, plain);
+                tpl.push_str(&self.interpret_text_plain(&plain));
             }
             PartType::Text(text) => {
-                tpl.push_str(&self.interpret_text(&ctype, text));
+                let text = self.decode_text(msg, part, text);
+                tpl.push_str(&self.interpret_text(&ctype, &text));
             }
             PartType::Html(html) => {
-                tpl.push_str(&self.interpret_text_html(html));
+                let html = self.decode_text(msg, part, html);
+                tpl.push_str(&self.interpret_text_html(&html));
             }
             PartType::Binary(data) => {
                 tpl.push_str(&self.interpret_attachment(&ctype, part, data)?);
@@ -457,6 +490,9 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
             PartType::InlineBinary(data) => {
                 tpl.push_str(&self.interpret_inline_attachment(&ctype, part, data)?);
             }
+            PartType::Message(msg) if is_attachment(part) => {
+                tpl.push_str(&self.interpret_attachment(&ctype, part, msg.raw_message())?);
+            }
             PartType::Message(msg) => {
                 tpl.push_str(&self.interpret_msg(msg).await?);
             }
@@ -471,14 +507,16 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
                                 PartType::Text(plain)
                                     if is_plain(part) && !plain.trim().is_empty() =>
                                 {
-                                    Some(Ok(self.interpret_text_plain(plain)))
+                                    let plain = self.decode_text(msg, part, plain);
+                                    Some(Ok(self.interpret_text_plain(&plain)))
                                 }
                                 _ => None,
                             })
                             .or_else(|| {
                                 parts.clone().find_map(|part| match &part.body {
                                     PartType::Html(html) if !html.trim().is_empty() => {
-                                        Some(Ok(self.interpret_text_html(html)))
+                                        let html = self.decode_text(msg, part, html);
+                                        Some(Ok(self.interpret_text_html(&html)))
                                     }
                                     _ => None,
                                 })
@@ -488,7 +526,8 @@ async fn interpret_part(&self, msg: &Message<'_>, part: &MessagePart<'_>) -> Res
                                     let ctype = get_ctype(part);
                                     match &part.body {
                                         PartType::Text(text) if !text.trim().is_empty() => {
-                                            Some(Ok(self.interpret_text(&ctype, text)))
+                                            let text = self.decode_text(msg, part, text);
+                                            Some(Ok(self.interpret_text(&ctype, &text)))
                                         }
                                         _ => None,
                                     }
@@ -623,6 +662,12 @@ fn get_ctype(part: &MessagePart) -> String {
         .unwrap_or_else(|| String::from("application/octet-stream"))
 }
 
+fn is_attachment(part: &MessagePart) -> bool {
+    part.content_disposition()
+        .map(|disposition| disposition.is_attachment())
+        .unwrap_or_default()
+}
+
 fn is_plain(part: &MessagePart) -> bool {
     get_ctype(part) == "text/plain"
 }
@@ -964,6 +1009,34 @@ async fn attachment() {
         assert_eq!(tpl, expected_tpl);
     }
 
+    #[tokio::test]
+    async fn message_attachment() {
+        let raw = concat_line!("From: a@localhost\r", "Subject: hi\r", "\r", "Hello!\r", "");
+        let builder = MessageBuilder::new().body(MimePart::new(
+            "multipart/mixed",
+            vec![
+                MimePart::new("text/plain", "See attached.\n"),
+                MimePart::new("message/rfc822", raw)
+                    .transfer_encoding("7bit")
+                    .attachment("hi.eml"),
+            ],
+        ));
+
+        let tpl = MimeBodyInterpreter::new()
+            .with_save_attachments_dir("~/Downloads")
+            .interpret_msg_builder(builder)
+            .await
+            .unwrap();
+
+        let expected_tpl = concat_line!(
+            "See attached.",
+            "<#part type=message/rfc822 filename=\"~/Downloads/hi.eml\"><#/part>",
+            "",
+        );
+
+        assert_eq!(tpl, expected_tpl);
+    }
+
     #[tokio::test]
     async fn hide_parts_single_html() {
         let builder = MessageBuilder::new().body(MimePart::new(
@@ -44,6 +44,7 @@
 pub(crate) const ENCRYPT: &str = "encrypt";
 pub(crate) const FILENAME: &str = "filename";
 pub(crate) const INLINE: &str = "inline";
+pub(crate) const MESSAGE_RFC822: &str = "message/rfc822";
 pub(crate) const MIXED: &str = "mixed";
 pub(crate) const MODIFICATION_DATE: &str = "modification-date";
 pub(crate) const NAME: &str = "name";
@@ -59,6 +60,8 @@
 #[cfg(feature = "pgp")]
 pub(crate) const SIGN: &str = "sign";
 pub(crate) const SIZE: &str = "size";
+#[cfg(feature = "markdown")]
+pub(crate) const TEXT_MARKDOWN: &str = "text/markdown";
 pub(crate) const TYPE: &str = "type";
 
 pub(crate) const BACKSLASH: char = '\\';
@@ -0,0 +1,206 @@
+//! # Charset detection
+//!
+//! Module dedicated to legacy messages that lack charset information,
+//! or whose bytes do not match the declared charset. The main
+//! structure of this module is [`CharsetDetector`].
+
+use std::borrow::Cow;
+
+use chardetng::EncodingDetector;
+use encoding_rs::Encoding as Charset;
+use mail_parser::{
+    decoders::{base64::base64_decode, quoted_printable::quoted_printable_decode},
+    Encoding, Message, MessageParser, MessagePart, MimeHeaders,
+};
+use tracing::debug;
+
+/// The charset detector.
+///
+/// The declared charset always wins when the bytes decode in it
+/// without error. Otherwise, or when no charset is declared and the
+/// bytes are not valid UTF-8, the charset is detected: the fallback
+/// charsets are tried first, in order, then the charset guessed from
+/// the bytes by [`chardetng`] is used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CharsetDetector {
+    fallbacks: Vec<String>,
+}
+
+impl CharsetDetector {
+    /// Create a new charset detector using the given fallback
+    /// charsets.
+    pub fn new(fallbacks: impl IntoIterator<Item = impl ToString>) -> Self {
+        Self {
+            fallbacks: fallbacks.into_iter().map(|cs| cs.to_string()).collect(),
+        }
+    }
+
+    /// Return the fallback charsets.
+    pub fn fallbacks(&self) -> &[String] {
+        &self.fallbacks
+    }
+
+    /// Decode the given bytes, detecting their charset if they do
+    /// not match the declared one.
+    pub fn decode<'a>(&self, bytes: &'a [u8], declared: Option<&str>) -> Cow<'a, str> {
+        match declared {
+            Some(charset) => match decode_strict(bytes, charset) {
+                Some(Some(text)) => text,
+                // unknown charset, decode it like the parser does
+                None => Cow::Owned(String::from_utf8_lossy(bytes).into_owned()),
+                Some(None) => self.detect(bytes),
+            },
+            None => match std::str::from_utf8(bytes) {
+                Ok(text) => Cow::Borrowed(text),
+                Err(_) => self.detect(bytes),
+            },
+        }
+    }
+
+    /// Decode the body of the given text part again, if its bytes do
+    /// not match its declared charset.
+    ///
+    /// Returns `None` when the body parsed by [`mail_parser`] is
+    /// already correct, or when it cannot be extracted from the raw
+    /// message.
+    pub fn decode_part(&self, msg: &Message, part: &MessagePart) -> Option<String> {
+        if part.offset_body >= part.offset_end {
+            return None;
+        }
+
+        let raw = msg.raw_message().get(part.offset_body..part.offset_end)?;
+        let bytes = match part.encoding {
+            Encoding::None => Cow::Borrowed(raw),
+            Encoding::QuotedPrintable => Cow::Owned(quoted_printable_decode(raw)?),
+            Encoding::Base64 => Cow::Owned(base64_decode(raw)?),
+        };
+
+        let declared = part
+            .content_type()
+            .and_then(|ctype| ctype.attribute("charset"));
+
+        let valid = match declared {
+            // unknown charsets are left to the parser
+            Some(charset) => !matches!(decode_strict(&bytes, charset), Some(None)),
+            None => std::str::from_utf8(&bytes).is_ok(),
+        };
+
+        if valid {
+            None
+        } else {
+            Some(self.detect(&bytes).into_owned())
+        }
+    }
+
+    /// Decode the given raw header value again, if it contains 8-bit
+    /// bytes that are not valid UTF-8.
+    ///
+    /// The value is transcoded to UTF-8 using the detected charset,
+    /// then parsed again by [`mail_parser`], which takes care of
+    /// unfolding and encoded words. Returns `None` when the parsed
+    /// value is already correct.
+    pub fn decode_header(&self, raw: &[u8]) -> Option<String> {
+        if std::str::from_utf8(raw).is_ok() {
+            return None;
+        }
+
+        let value = self.detect(raw);
+        let header = format!("Subject:{}\r\n\r\n", value.trim_end());
+        let msg = MessageParser::new().parse(header.as_bytes())?;
+
+        msg.subject().map(ToOwned::to_owned)
+    }
+
+    /// Decode the given bytes using the first fallback charset
+    /// decoding them without error, otherwise using the guessed one.
+    fn detect<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        for charset in &self.fallbacks {
+            if let Some(Some(text)) = decode_strict(bytes, charset) {
+                return text;
+            }
+        }
+
+        let mut detector = EncodingDetector::new();
+        detector.feed(bytes, true);
+        let charset = detector.guess(None, true);
+        debug!(charset = charset.name(), "detected charset");
+
+        let (text, _) = charset.decode_without_bom_handling(bytes);
+        text
+    }
+}
+
+/// Decode the given bytes using the given charset.
+///
+/// Returns `None` if the charset is unknown, and `Some(None)` if the
+/// bytes are malformed for this charset.
+fn decode_strict<'a>(bytes: &'a [u8], charset: &str) -> Option<Option<Cow<'a, str>>> {
+    let charset = Charset::for_label(charset.trim().as_bytes())?;
+    Some(charset.decode_without_bom_handling_and_without_replacement(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use mail_parser::{MessageParser, PartType};
+
+    use super::CharsetDetector;
+
+    // "中文" encoded in GBK
+    const GBK: &[u8] = b"\xd6\xd0\xce\xc4";
+
+    #[test]
+    fn decode() {
+        let detector = CharsetDetector::new(["gbk"]);
+
+        assert_eq!(detector.decode("中文".as_bytes(), None), "中文");
+        assert_eq!(detector.decode(GBK, None), "中文");
+        assert_eq!(detector.decode(GBK, Some("utf-8")), "中文");
+
+        // the declared charset wins when bytes decode in it
+        assert_eq!(detector.decode(GBK, Some("iso-8859-1")), "ÖÐÎÄ");
+        assert_eq!(detector.decode(b"caf\xe9", Some("iso-8859-1")), "café");
+    }
+
+    #[test]
+    fn detect_without_fallbacks() {
+        let detector = CharsetDetector::default();
+        let bytes = b"Le caf\xe9 cr\xe8me et la cr\xe8me br\xfbl\xe9e sont d\xe9licieux.";
+
+        assert_eq!(
+            detector.decode(bytes, Some("utf-8")),
+            "Le café crème et la crème brûlée sont délicieux.",
+        );
+    }
+
+    #[test]
+    fn decode_part_and_header() {
+        let mut raw = Vec::new();
+        raw.extend(b"Subject: =?utf-8?B?5Lit5paH?= \xd6\xd0\xce\xc4\r\n");
+        raw.extend(b"Content-Type: text/plain; charset=utf-8\r\n\r\n");
+        raw.extend(GBK);
+
+        let msg = MessageParser::new().parse(&raw).unwrap();
+        let part = msg.part(0).unwrap();
+        assert!(matches!(part.body, PartType::Text(_)));
+
+        let detector = CharsetDetector::new(["gbk"]);
+        assert_eq!(detector.decode_part(&msg, part).unwrap(), "中文");
+
+        let header = msg.headers().first().unwrap();
+        let raw_header = &msg.raw_message()[header.offset_start..header.offset_end];
+        assert_eq!(detector.decode_header(raw_header).unwrap(), "中文 中文");
+    }
+
+    #[test]
+    fn keep_part_matching_declared_charset() {
+        let mut raw = Vec::new();
+        raw.extend(b"Content-Type: text/plain; charset=iso-8859-1\r\n\r\n");
+        raw.extend(GBK);
+
+        let msg = MessageParser::new().parse(&raw).unwrap();
+        let part = msg.part(0).unwrap();
+
+        let detector = CharsetDetector::new(["gbk"]);
+        assert_eq!(detector.decode_part(&msg, part), None);
+    }
+}
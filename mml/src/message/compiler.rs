@@ -6,8 +6,14 @@
 use mail_parser::{Message, MessageParser};
 
 #[cfg(feature = "pgp")]
-use crate::{message::header, pgp::Pgp};
-use crate::{message::MmlBodyCompiler, Error, Result};
+use crate::{
+    message::header,
+    pgp::{Pgp, PgpMissingKeyPolicy, PgpRecipient},
+};
+use crate::{
+    message::{body::compiler::MmlBodyCompileState, MmlBodyCompiler},
+    Error, Result,
+};
 
 /// MML → MIME message compiler builder.
 ///
@@ -25,6 +31,21 @@ pub fn new() -> Self {
         Self::default()
     }
 
+    /// Customize the stylesheet inlined into HTML parts rendered
+    /// from markdown parts.
+    #[cfg(feature = "markdown")]
+    pub fn set_markdown_css(&mut self, css: impl AsRef<str>) {
+        self.mml_body_compiler.set_markdown_css(css);
+    }
+
+    /// Customize the stylesheet inlined into HTML parts rendered
+    /// from markdown parts.
+    #[cfg(feature = "markdown")]
+    pub fn with_markdown_css(mut self, css: impl AsRef<str>) -> Self {
+        self.mml_body_compiler.set_markdown_css(css);
+        self
+    }
+
     /// Customize PGP.
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
@@ -51,6 +72,21 @@ pub fn with_some_pgp(mut self, pgp: Option<impl Into<Pgp>>) -> Self {
         self
     }
 
+    /// Customize the strategy applied when recipients of encrypted
+    /// parts have no PGP public key.
+    #[cfg(feature = "pgp")]
+    pub fn set_pgp_missing_key_policy(&mut self, policy: PgpMissingKeyPolicy) {
+        self.mml_body_compiler.set_pgp_missing_key_policy(policy);
+    }
+
+    /// Customize the strategy applied when recipients of encrypted
+    /// parts have no PGP public key.
+    #[cfg(feature = "pgp")]
+    pub fn with_pgp_missing_key_policy(mut self, policy: PgpMissingKeyPolicy) -> Self {
+        self.mml_body_compiler.set_pgp_missing_key_policy(policy);
+        self
+    }
+
     /// Build the final [MmlCompiler] based on the defined options.
     pub fn build(self, mml_msg: &str) -> Result<MmlCompiler<'_>> {
         let mml_msg = MessageParser::new()
@@ -95,8 +131,11 @@ pub async fn compile(&self) -> Result<MmlCompileResult<'_>> {
             .ok_or(Error::ParseMmlEmptyBodyContentError)?;
 
         let mml_body_compiler = &self.mml_body_compiler;
+        let state = MmlBodyCompileState::default();
 
-        let mut mime_msg_builder = mml_body_compiler.compile(mml_body).await?;
+        let mut mime_msg_builder = mml_body_compiler
+            .compile_with_state(mml_body, &state)
+            .await?;
 
         mime_msg_builder = mime_msg_builder.header("MIME-Version", Text::new("1.0"));
 
@@ -106,7 +145,11 @@ pub async fn compile(&self) -> Result<MmlCompileResult<'_>> {
             mime_msg_builder = mime_msg_builder.header(key, val);
         }
 
-        Ok(MmlCompileResult { mime_msg_builder })
+        Ok(MmlCompileResult {
+            mime_msg_builder,
+            #[cfg(feature = "pgp")]
+            pgp_recipients: state.into_pgp_recipients(),
+        })
     }
 }
 
@@ -117,9 +160,19 @@ pub async fn compile(&self) -> Result<MmlCompileResult<'_>> {
 #[derive(Clone, Debug, Default)]
 pub struct MmlCompileResult<'a> {
     mime_msg_builder: MessageBuilder<'a>,
+    #[cfg(feature = "pgp")]
+    pgp_recipients: Vec<PgpRecipient>,
 }
 
 impl<'a> MmlCompileResult<'a> {
+    /// Return the PGP encryption status of each recipient.
+    ///
+    /// The list is empty when the message has no encrypted part.
+    #[cfg(feature = "pgp")]
+    pub fn pgp_recipients(&self) -> &[PgpRecipient] {
+        &self.pgp_recipients
+    }
+
     /// Return a reference to the final MIME message builder.
     pub fn as_msg_builder(&self) -> &MessageBuilder {
         &self.mime_msg_builder
@@ -9,16 +9,25 @@
 use mail_parser::{Addr, Address, ContentType, Group, Header, HeaderName, HeaderValue};
 use std::borrow::Cow;
 
+/// Return `true` if the given header key holds identifiers, which
+/// are displayed between angle brackets.
+pub(super) fn is_id(key: &str) -> bool {
+    matches!(
+        key,
+        "Message-ID"
+            | "References"
+            | "In-Reply-To"
+            | "Return-Path"
+            | "Content-ID"
+            | "Resent-Message-ID"
+    )
+}
+
 pub(super) fn display_value(key: &str, val: &HeaderValue) -> String {
     match val {
         HeaderValue::Address(Address::List(addrs)) => display_addrs(addrs),
         HeaderValue::Address(Address::Group(groups)) => display_groups(groups),
-        HeaderValue::Text(id) if key == "Message-ID" => format!("<{id}>"),
-        HeaderValue::Text(id) if key == "References" => format!("<{id}>"),
-        HeaderValue::Text(id) if key == "In-Reply-To" => format!("<{id}>"),
-        HeaderValue::Text(id) if key == "Return-Path" => format!("<{id}>"),
-        HeaderValue::Text(id) if key == "Content-ID" => format!("<{id}>"),
-        HeaderValue::Text(id) if key == "Resent-Message-ID" => format!("<{id}>"),
+        HeaderValue::Text(id) if is_id(key) => format!("<{id}>"),
         HeaderValue::Text(text) => text.to_string(),
         HeaderValue::TextList(texts) => display_texts(texts),
         HeaderValue::DateTime(datetime) => datetime.to_rfc822(),
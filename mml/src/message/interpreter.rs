@@ -3,13 +3,13 @@
 //! Module dedicated to MIME → MML message interpretation.
 
 use mail_builder::MessageBuilder;
-use mail_parser::{Message, MessageParser};
+use mail_parser::{GetHeader, Header, HeaderValue, Message, MessageParser};
 use std::path::PathBuf;
 
 #[cfg(feature = "pgp")]
 use crate::pgp::Pgp;
 use crate::{
-    message::{FilterParts, MimeBodyInterpreter},
+    message::{CharsetDetector, FilterParts, MimeBodyInterpreter},
     Error, Result,
 };
 
@@ -45,6 +45,9 @@ pub struct MimeInterpreterBuilder {
     /// The strategy to display headers.
     show_headers: FilterHeaders,
 
+    /// The charset detector of text headers.
+    charset_detector: CharsetDetector,
+
     /// The internal MIME to MML message body interpreter.
     mime_body_interpreter: MimeBodyInterpreter,
 }
@@ -188,6 +191,28 @@ pub fn with_save_some_attachments_dir(self, dir: Option<impl Into<PathBuf>>) ->
         }
     }
 
+    /// Customize the fallback charsets used to decode legacy text
+    /// headers and text parts.
+    ///
+    /// See [`CharsetDetector`].
+    pub fn set_charset_fallbacks(&mut self, charsets: impl IntoIterator<Item = impl ToString>) {
+        self.charset_detector = CharsetDetector::new(charsets);
+        self.mime_body_interpreter
+            .set_charset_fallbacks(self.charset_detector.fallbacks());
+    }
+
+    /// Customize the fallback charsets used to decode legacy text
+    /// headers and text parts.
+    ///
+    /// See [`CharsetDetector`].
+    pub fn with_charset_fallbacks(
+        mut self,
+        charsets: impl IntoIterator<Item = impl ToString>,
+    ) -> Self {
+        self.set_charset_fallbacks(charsets);
+        self
+    }
+
     /// Customize PGP.
     #[cfg(feature = "pgp")]
     pub fn set_pgp(&mut self, pgp: impl Into<Pgp>) {
@@ -221,6 +246,7 @@ pub fn with_some_pgp(mut self, pgp: Option<impl Into<Pgp>>) -> Self {
     pub fn build(self) -> MimeInterpreter {
         MimeInterpreter {
             show_headers: self.show_headers,
+            charset_detector: self.charset_detector,
             mime_body_interpreter: self.mime_body_interpreter,
         }
     }
@@ -230,6 +256,7 @@ pub fn build(self) -> MimeInterpreter {
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct MimeInterpreter {
     show_headers: FilterHeaders,
+    charset_detector: CharsetDetector,
     mime_body_interpreter: MimeBodyInterpreter,
 }
 
@@ -238,17 +265,22 @@ impl MimeInterpreter {
     pub async fn from_msg(self, msg: &Message<'_>) -> Result<String> {
         let mut mml = String::new();
 
-        match self.show_headers {
+        match &self.show_headers {
             FilterHeaders::All => msg.headers().iter().for_each(|header| {
                 let key = header.name.as_str();
-                let val = header::display_value(key, &header.value);
+                let val = self.display_header_value(msg, key, header);
                 mml.push_str(&format!("{key}: {val}\n"));
             }),
             FilterHeaders::Include(keys) => keys
                 .iter()
-                .filter_map(|key| msg.header(key.as_str()).map(|val| (key, val)))
-                .for_each(|(key, val)| {
-                    let val = header::display_value(key, val);
+                .filter_map(|key| {
+                    msg.root_part()
+                        .headers
+                        .header(key.as_str())
+                        .map(|h| (key, h))
+                })
+                .for_each(|(key, header)| {
+                    let val = self.display_header_value(msg, key, header);
                     mml.push_str(&format!("{key}: {val}\n"));
                 }),
             FilterHeaders::Exclude(keys) => msg
@@ -257,7 +289,7 @@ pub async fn from_msg(self, msg: &Message<'_>) -> Result<String> {
                 .filter(|header| !keys.contains(&header.name.as_str().to_owned()))
                 .for_each(|header| {
                     let key = header.name.as_str();
-                    let val = header::display_value(key, &header.value);
+                    let val = self.display_header_value(msg, key, header);
                     mml.push_str(&format!("{key}: {val}\n"));
                 }),
         };
@@ -280,6 +312,25 @@ pub async fn from_msg(self, msg: &Message<'_>) -> Result<String> {
         Ok(mml)
     }
 
+    /// Display the value of the given header, decoding text values
+    /// again using the charset detector if needed.
+    fn display_header_value(&self, msg: &Message, key: &str, header: &Header) -> String {
+        if let HeaderValue::Text(_) = header.value {
+            if !header::is_id(key) {
+                let raw = msg
+                    .raw_message()
+                    .get(header.offset_start..header.offset_end)
+                    .unwrap_or_default();
+
+                if let Some(val) = self.charset_detector.decode_header(raw) {
+                    return val;
+                }
+            }
+        }
+
+        header::display_value(key, &header.value)
+    }
+
     /// Interpret the given MIME message bytes as a MML [String].
     pub async fn from_bytes(self, bytes: impl AsRef<[u8]>) -> Result<String> {
         let msg = MessageParser::new()
@@ -13,6 +13,8 @@
 //! the [MimeInterpreterBuilder]/[MimeBodyInterpreter] builder.
 
 pub mod body;
+#[cfg(feature = "interpreter")]
+pub mod charset;
 #[cfg(feature = "compiler")]
 pub mod compiler;
 pub(crate) mod header;
@@ -29,5 +31,6 @@
 #[doc(inline)]
 pub use self::{
     body::{FilterParts, MimeBodyInterpreter},
+    charset::CharsetDetector,
     interpreter::{FilterHeaders, MimeInterpreter, MimeInterpreterBuilder},
 };
@@ -42,6 +42,27 @@ pub fn get_context(&self) -> Result<Context> {
         Ok(ctx)
     }
 
+    /// Finds the recipients having no public key.
+    pub async fn find_missing_public_keys(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<String>> {
+        let mut ctx = self.get_context()?;
+
+        let missing = emails
+            .into_iter()
+            .filter(|email| match ctx.locate_key(email) {
+                Ok(_) => false,
+                Err(err) => {
+                    debug!("cannot locate gpg key for {email}: {err}");
+                    true
+                }
+            })
+            .collect();
+
+        Ok(missing)
+    }
+
     /// Encrypts the given plain bytes using the given recipients.
     pub async fn encrypt(
         &self,
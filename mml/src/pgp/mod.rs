@@ -10,6 +10,8 @@
 #[cfg(feature = "pgp-native")]
 pub mod native;
 
+#[cfg(feature = "pgp-native")]
+use secret::Secret;
 use tracing::{debug, trace};
 
 use crate::{Error, Result};
@@ -20,12 +22,70 @@
 #[cfg(feature = "pgp-gpg")]
 #[doc(inline)]
 pub use self::gpg::PgpGpg;
+#[cfg(all(feature = "pgp-native", unix))]
+#[doc(inline)]
+pub use self::native::NativePgpAgentKey;
 #[cfg(feature = "pgp-native")]
 #[doc(inline)]
 pub use self::native::{
     NativePgpPublicKeysResolver, NativePgpSecretKey, PgpNative, SignedPublicKey, SignedSecretKey,
 };
 
+/// The strategy applied when some recipients of an encrypted part
+/// have no PGP public key.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum PgpMissingKeyPolicy {
+    /// Fail the compilation, listing the recipients without public
+    /// key.
+    #[default]
+    Fail,
+
+    /// Leave the part unencrypted.
+    SkipEncryption,
+
+    /// Encrypt the part for the recipients having a public key only.
+    ///
+    /// The other recipients will not be able to read the part.
+    EncryptToAvailable,
+
+    /// Encrypt the part using the given password instead of public
+    /// keys, so that every recipient can decrypt it once they got the
+    /// password through another channel.
+    ///
+    /// Only supported by the native backend.
+    #[cfg(feature = "pgp-native")]
+    Password(Secret),
+}
+
+/// The PGP encryption status of a recipient, see [`PgpRecipient`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PgpRecipientStatus {
+    /// The part is encrypted using the public key of the recipient.
+    Encrypted,
+
+    /// The recipient has no public key, the part is encrypted for the
+    /// other recipients only.
+    MissingKey,
+
+    /// The part is not encrypted.
+    Unencrypted,
+
+    /// The part is encrypted using the fallback password.
+    PasswordProtected,
+}
+
+/// A recipient of a PGP encrypted part, with its encryption status.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PgpRecipient {
+    pub email: String,
+    pub status: PgpRecipientStatus,
+}
+
 /// The PGP backends.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub enum Pgp {
@@ -70,6 +130,50 @@ pub async fn encrypt(
         }
     }
 
+    /// Finds the recipients having no public key.
+    ///
+    /// The commands backend cannot look for public keys: recipients
+    /// are all considered having one.
+    pub async fn find_missing_public_keys(
+        &self,
+        recipients: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<String>> {
+        let recipients: Vec<String> = recipients.into_iter().collect();
+        debug!(?recipients, "finding missing pgp public keys");
+
+        match self {
+            Self::None => Err(Error::PgpMissingConfigurationError),
+            #[cfg(feature = "pgp-commands")]
+            Self::Commands(_) => Ok(Vec::new()),
+            #[cfg(feature = "pgp-native")]
+            Self::Native(native) => {
+                let pkeys = native.find_public_keys(recipients.clone()).await;
+                let missing = recipients
+                    .into_iter()
+                    .filter(|recipient| !pkeys.contains_key(recipient))
+                    .collect();
+                Ok(missing)
+            }
+            #[cfg(feature = "pgp-gpg")]
+            Self::Gpg(gpg) => gpg.find_missing_public_keys(recipients).await,
+        }
+    }
+
+    /// Encrypts the given plain bytes using the given password.
+    #[cfg(feature = "pgp-native")]
+    pub async fn encrypt_with_password(
+        &self,
+        password: &Secret,
+        plain_bytes: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        debug!("encrypting bytes using pgp password");
+
+        match self {
+            Self::Native(native) => native.encrypt_with_password(password, plain_bytes).await,
+            _ => Err(Error::PgpEncryptWithPasswordNotSupportedError),
+        }
+    }
+
     /// Decrypts the given encrypted bytes using the given recipient.
     pub async fn decrypt(
         &self,
@@ -2,7 +2,10 @@
 //!
 //! This module contains the native PGP backend.
 
-use std::{collections::HashSet, path::PathBuf};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
 
 pub use pgp::native::{SignedPublicKey, SignedSecretKey};
 use secret::Secret;
@@ -33,6 +36,12 @@ pub enum NativePgpSecretKey {
     /// The native PGP secret key is located in the user's global
     /// keyring at the given entry.
     Keyring(secret::keyring::KeyringEntry),
+
+    #[cfg(unix)]
+    /// The native PGP secret key is held by gpg-agent, possibly on an
+    /// OpenPGP card. Private key operations are delegated to the
+    /// agent, so the key never needs to be exported.
+    Agent(NativePgpAgentKey),
 }
 
 impl NativePgpSecretKey {
@@ -64,10 +73,49 @@ pub async fn get(&self, recipient: impl ToString) -> Result<SignedSecretKey> {
                     .map_err(Error::ReadNativePgpSecretKeyError)?;
                 Ok(skey)
             }
+            #[cfg(unix)]
+            Self::Agent(_) => Ok(Err(Error::GetNativePgpSecretKeyFromAgentError(
+                recipient.clone(),
+            ))?),
         }
     }
 }
 
+/// The native PGP secret key held by gpg-agent.
+#[cfg(unix)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct NativePgpAgentKey {
+    /// The path to the armored public key of the secret key held by
+    /// the agent. It is used to find the matching agent key.
+    pub public_key: PathBuf,
+
+    /// The path to the gpg-agent socket.
+    ///
+    /// Defaults to the socket given by `gpgconf --list-dirs
+    /// agent-socket`.
+    #[cfg_attr(feature = "derive", serde(default))]
+    pub socket: Option<PathBuf>,
+}
+
+#[cfg(unix)]
+impl NativePgpAgentKey {
+    async fn get_public_key(&self) -> Result<SignedPublicKey> {
+        let path = shellexpand_path(&self.public_key);
+        pgp::read_pkey_from_path(path)
+            .await
+            .map_err(Error::ReadNativePgpAgentPublicKeyError)
+    }
+
+    fn socket(&self) -> Option<PathBuf> {
+        self.socket.as_ref().map(shellexpand_path)
+    }
+}
+
 /// The native PGP public key resolver.
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(
@@ -110,72 +158,66 @@ pub struct PgpNative {
 }
 
 impl PgpNative {
-    /// Encrypts the given plain bytes using the given recipients.
-    pub async fn encrypt(
+    /// Finds the public keys of the given recipients.
+    ///
+    /// Recipients without public key are not part of the returned
+    /// map.
+    pub async fn find_public_keys(
         &self,
         emails: impl IntoIterator<Item = String>,
-        data: Vec<u8>,
-    ) -> Result<Vec<u8>> {
-        let mut pkeys = Vec::new();
-        let mut recipients: HashSet<String> = HashSet::from_iter(emails.into_iter());
+    ) -> HashMap<String, SignedPublicKey> {
+        let mut pkeys = HashMap::new();
+        let mut recipients: HashSet<String> = HashSet::from_iter(emails);
 
         for resolver in &self.public_keys_resolvers {
             match resolver {
                 NativePgpPublicKeysResolver::Raw(recipient, pkey) => {
                     if recipients.remove(recipient) {
                         debug!("found pgp public key for {recipient} using raw pair");
-                        pkeys.push(pkey.clone())
+                        pkeys.insert(recipient.clone(), pkey.clone());
                     }
                 }
                 NativePgpPublicKeysResolver::Wkd => {
                     let recipients_clone = recipients.clone().into_iter().collect();
                     let wkd_pkeys = pgp::http::wkd::get_all(recipients_clone).await;
 
-                    pkeys.extend(wkd_pkeys.into_iter().fold(
-                        Vec::new(),
-                        |mut pkeys, (ref recipient, res)| {
-                            match res {
-                                Ok(pkey) => {
-                                    if recipients.remove(recipient) {
-                                        debug!("found pgp public key for {recipient} using wkd");
-                                        pkeys.push(pkey);
-                                    }
-                                }
-                                Err(err) => {
-                                    let msg = format!("cannot find pgp public key for {recipient}");
-                                    debug!("{msg} using wkd: {err}");
-                                    debug!("{err:?}");
+                    for (recipient, res) in wkd_pkeys {
+                        match res {
+                            Ok(pkey) => {
+                                if recipients.remove(&recipient) {
+                                    debug!("found pgp public key for {recipient} using wkd");
+                                    pkeys.insert(recipient, pkey);
                                 }
                             }
-                            pkeys
-                        },
-                    ));
+                            Err(err) => {
+                                let msg = format!("cannot find pgp public key for {recipient}");
+                                debug!("{msg} using wkd: {err}");
+                                debug!("{err:?}");
+                            }
+                        }
+                    }
                 }
                 NativePgpPublicKeysResolver::KeyServers(key_servers) => {
                     let recipients_clone = recipients.clone().into_iter().collect();
                     let http_pkeys =
                         pgp::http::get_all(recipients_clone, key_servers.to_owned()).await;
 
-                    pkeys.extend(http_pkeys.into_iter().fold(
-                        Vec::default(),
-                        |mut pkeys, (ref recipient, res)| {
-                            match res {
-                                Ok(pkey) => {
-                                    if recipients.remove(recipient) {
-                                        let msg = format!("found pgp public key for {recipient}");
-                                        debug!("{msg} using key servers");
-                                        pkeys.push(pkey);
-                                    }
-                                }
-                                Err(err) => {
-                                    let msg = format!("cannot find pgp public key for {recipient}");
-                                    debug!("{msg} using key servers: {err}");
-                                    debug!("{err:?}");
+                    for (recipient, res) in http_pkeys {
+                        match res {
+                            Ok(pkey) => {
+                                if recipients.remove(&recipient) {
+                                    let msg = format!("found pgp public key for {recipient}");
+                                    debug!("{msg} using key servers");
+                                    pkeys.insert(recipient, pkey);
                                 }
                             }
-                            pkeys
-                        },
-                    ));
+                            Err(err) => {
+                                let msg = format!("cannot find pgp public key for {recipient}");
+                                debug!("{msg} using key servers: {err}");
+                                debug!("{err:?}");
+                            }
+                        }
+                    }
                 }
             }
 
@@ -184,7 +226,32 @@ pub async fn encrypt(
             }
         }
 
-        let data = pgp::encrypt(pkeys, data)
+        pkeys
+    }
+
+    /// Encrypts the given plain bytes using the given recipients.
+    pub async fn encrypt(
+        &self,
+        emails: impl IntoIterator<Item = String>,
+        data: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        let pkeys = self.find_public_keys(emails).await;
+
+        let data = pgp::encrypt(pkeys.into_values().collect(), data)
+            .await
+            .map_err(Error::EncryptNativePgpError)?;
+
+        Ok(data)
+    }
+
+    /// Encrypts the given plain bytes using the given password.
+    pub async fn encrypt_with_password(&self, password: &Secret, data: Vec<u8>) -> Result<Vec<u8>> {
+        let password = password
+            .get()
+            .await
+            .map_err(Error::GetPgpEncryptionPasswordError)?;
+
+        let data = pgp::encrypt_with_password(password, data)
             .await
             .map_err(Error::EncryptNativePgpError)?;
 
@@ -193,6 +260,15 @@ pub async fn encrypt(
 
     /// Decrypts the given encrypted bytes using the given recipient.
     pub async fn decrypt(&self, email: impl ToString, data: Vec<u8>) -> Result<Vec<u8>> {
+        #[cfg(unix)]
+        if let NativePgpSecretKey::Agent(key) = &self.secret_key {
+            let pkey = key.get_public_key().await?;
+            let data = pgp::agent::decrypt(pkey, key.socket(), data)
+                .await
+                .map_err(Error::DecryptNativePgpError)?;
+            return Ok(data);
+        }
+
         let skey = self.secret_key.get(email).await?;
         let passphrase = self
             .secret_key_passphrase
@@ -207,6 +283,15 @@ pub async fn decrypt(&self, email: impl ToString, data: Vec<u8>) -> Result<Vec<u
 
     /// Signs the given plain bytes using the given recipient.
     pub async fn sign(&self, email: impl ToString, data: Vec<u8>) -> Result<Vec<u8>> {
+        #[cfg(unix)]
+        if let NativePgpSecretKey::Agent(key) = &self.secret_key {
+            let pkey = key.get_public_key().await?;
+            let data = pgp::agent::sign(pkey, key.socket(), data)
+                .await
+                .map_err(Error::SignNativePgpError)?;
+            return Ok(data);
+        }
+
         let skey = self.secret_key.get(email).await?;
         let passphrase = self
             .secret_key_passphrase
@@ -6,7 +6,10 @@
 use async_std::test;
 use concat_with::concat_line;
 use mml::{
-    pgp::{NativePgpPublicKeysResolver, NativePgpSecretKey, Pgp, PgpNative},
+    pgp::{
+        NativePgpPublicKeysResolver, NativePgpSecretKey, Pgp, PgpMissingKeyPolicy, PgpNative,
+        PgpRecipient, PgpRecipientStatus,
+    },
     MimeInterpreterBuilder, MmlCompilerBuilder,
 };
 use pgp::gen_key_pair;
@@ -132,3 +135,101 @@ async fn spawn_fake_key_server(pkeys: HashMap<String, String>) -> String {
 
     assert_eq!(mml, expected_mml);
 }
+
+#[test_log::test(test)]
+async fn pgp_native_missing_key_policy() {
+    let (_, bob_pkey) = gen_key_pair("bob@localhost", "").await.unwrap();
+
+    let mml = concat_line!(
+        "From: alice@localhost",
+        "To: bob@localhost, carl@localhost",
+        "Subject: subject",
+        "",
+        "<#part type=text/plain encrypt=pgpmime>",
+        "Encrypted message!",
+        "<#/part>",
+    );
+
+    let pgp = Pgp::Native(PgpNative {
+        secret_key: NativePgpSecretKey::None,
+        secret_key_passphrase: Secret::new_raw(""),
+        public_keys_resolvers: vec![NativePgpPublicKeysResolver::Raw(
+            "bob@localhost".into(),
+            bob_pkey,
+        )],
+    });
+
+    let recipients = |bob, carl| {
+        vec![
+            PgpRecipient {
+                email: "bob@localhost".into(),
+                status: bob,
+            },
+            PgpRecipient {
+                email: "carl@localhost".into(),
+                status: carl,
+            },
+        ]
+    };
+
+    let err = MmlCompilerBuilder::new()
+        .with_pgp(pgp.clone())
+        .build(mml)
+        .unwrap()
+        .compile()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        err,
+        mml::Error::PgpEncryptMissingPublicKeysError(missing) if missing == ["carl@localhost"],
+    ));
+
+    let compiler = MmlCompilerBuilder::new()
+        .with_pgp(pgp.clone())
+        .with_pgp_missing_key_policy(PgpMissingKeyPolicy::SkipEncryption)
+        .build(mml)
+        .unwrap();
+    let res = compiler.compile().await.unwrap();
+
+    assert_eq!(
+        res.pgp_recipients(),
+        recipients(
+            PgpRecipientStatus::Unencrypted,
+            PgpRecipientStatus::Unencrypted
+        ),
+    );
+    assert!(res.into_string().unwrap().contains("Encrypted message!"));
+
+    let compiler = MmlCompilerBuilder::new()
+        .with_pgp(pgp.clone())
+        .with_pgp_missing_key_policy(PgpMissingKeyPolicy::EncryptToAvailable)
+        .build(mml)
+        .unwrap();
+    let res = compiler.compile().await.unwrap();
+
+    assert_eq!(
+        res.pgp_recipients(),
+        recipients(
+            PgpRecipientStatus::Encrypted,
+            PgpRecipientStatus::MissingKey
+        ),
+    );
+    assert!(res.into_string().unwrap().contains("multipart/encrypted"));
+
+    let compiler = MmlCompilerBuilder::new()
+        .with_pgp(pgp)
+        .with_pgp_missing_key_policy(PgpMissingKeyPolicy::Password(Secret::new_raw("pass")))
+        .build(mml)
+        .unwrap();
+    let res = compiler.compile().await.unwrap();
+
+    assert_eq!(
+        res.pgp_recipients(),
+        recipients(
+            PgpRecipientStatus::PasswordProtected,
+            PgpRecipientStatus::PasswordProtected
+        ),
+    );
+    assert!(res.into_string().unwrap().contains("multipart/encrypted"));
+}
@@ -1,6 +1,12 @@
 //! Authorization Grant Code flow helper, as defined in the
 //! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-1.3.1)
 
+use std::{
+    future::{poll_fn, Future},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    task::Poll,
+};
+
 #[cfg(feature = "async-std")]
 use async_std::{
     io::{BufReadExt, BufReader, WriteExt},
@@ -15,8 +21,9 @@
     io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
     net::TcpListener,
 };
+use tracing::debug;
 
-use super::{Client, Error, Result};
+use super::{Client, Error, Provider, Result, Scopes};
 
 /// OAuth 2.0 Authorization Code Grant flow builder.
 ///
@@ -34,6 +41,31 @@
 pub struct AuthorizationCodeGrant {
     pub scopes: Vec<Scope>,
     pub pkce: Option<(PkceCodeChallenge, PkceCodeVerifier)>,
+
+    /// Extra parameters added to the authorization request, for
+    /// example `prompt=consent` or `access_type=offline`.
+    pub extra_params: Vec<(String, String)>,
+}
+
+/// The PKCE code challenge method, as defined in the
+/// [RFC7636](https://datatracker.ietf.org/doc/html/rfc7636#section-4.2).
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PkceMethod {
+    /// The code challenge is the SHA-256 hash of the code verifier.
+    #[default]
+    #[cfg_attr(feature = "derive", serde(alias = "S256"))]
+    S256,
+
+    /// The code challenge is the code verifier itself.
+    ///
+    /// Should only be used when the provider does not support
+    /// [`PkceMethod::S256`].
+    Plain,
 }
 
 impl AuthorizationCodeGrant {
@@ -49,8 +81,75 @@ pub fn with_scope<T>(mut self, scope: T) -> Self
         self
     }
 
-    pub fn with_pkce(mut self) -> Self {
-        self.pkce = Some(PkceCodeChallenge::new_random_sha256());
+    /// Request the required scopes that have not been granted yet,
+    /// keeping the granted ones.
+    ///
+    /// When the provider supports incremental consent, only missing
+    /// scopes are requested and previously granted ones are included
+    /// by the authorization server. Otherwise, a new authorization
+    /// replaces the previous one, so all scopes are requested again.
+    pub fn with_incremental_scopes(
+        mut self,
+        provider: Option<Provider>,
+        granted: &Scopes,
+        required: &Scopes,
+    ) -> Self {
+        let incremental = provider.is_some_and(|p| p.supports_incremental_consent());
+
+        let scopes = if incremental {
+            self = self.with_extra_param("include_granted_scopes", "true");
+            granted.missing(required)
+        } else {
+            granted.union(required)
+        };
+
+        for scope in scopes.iter() {
+            self = self.with_scope(scope);
+        }
+
+        self
+    }
+
+    /// Enable PKCE using the [`PkceMethod::S256`] method.
+    pub fn with_pkce(self) -> Self {
+        self.with_pkce_method(PkceMethod::S256)
+    }
+
+    /// Enable PKCE using the given method, or disable it if `None`.
+    pub fn set_pkce_method(&mut self, method: Option<PkceMethod>) {
+        self.pkce = method.map(|method| match method {
+            PkceMethod::S256 => PkceCodeChallenge::new_random_sha256(),
+            PkceMethod::Plain => PkceCodeChallenge::new_random_plain(),
+        });
+    }
+
+    /// Enable PKCE using the given method, using the builder pattern.
+    pub fn with_pkce_method(mut self, method: PkceMethod) -> Self {
+        self.set_pkce_method(Some(method));
+        self
+    }
+
+    /// Disable PKCE, using the builder pattern.
+    pub fn without_pkce(mut self) -> Self {
+        self.set_pkce_method(None);
+        self
+    }
+
+    /// Add an extra parameter to the authorization request.
+    pub fn with_extra_param(mut self, name: impl ToString, value: impl ToString) -> Self {
+        self.extra_params
+            .push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Add extra parameters to the authorization request.
+    pub fn with_extra_params(
+        mut self,
+        params: impl IntoIterator<Item = (impl ToString, impl ToString)>,
+    ) -> Self {
+        for (name, value) in params {
+            self = self.with_extra_param(name, value);
+        }
         self
     }
 
@@ -65,6 +164,10 @@ pub fn get_redirect_url(&self, client: &Client) -> (Url, CsrfToken) {
             redirect = redirect.set_pkce_challenge(pkce_challenge.clone());
         }
 
+        for (name, value) in &self.extra_params {
+            redirect = redirect.add_extra_param(name, value);
+        }
+
         redirect.url()
     }
 
@@ -72,25 +175,36 @@ pub fn get_redirect_url(&self, client: &Client) -> (Url, CsrfToken) {
     /// [`AuthorizationCodeGrant::get_redirect_url`], then exchange
     /// the received code with an access token and maybe a refresh
     /// token.
+    ///
+    /// The redirect server listens on the client's redirect host and
+    /// port. IPv6 hosts like `::1` are supported. When the redirect
+    /// host is `localhost`, the server listens on both IPv4 and IPv6
+    /// loopback addresses.
     pub async fn wait_for_redirection(
         self,
         client: &Client,
         csrf_state: CsrfToken,
     ) -> Result<(String, Option<String>)> {
-        // listen for one single connection
-        let (mut stream, _) =
-            TcpListener::bind((client.redirect_host.as_str(), client.redirect_port))
-                .await
-                .map_err(|err| {
-                    Error::BindRedirectServerError(
-                        client.redirect_host.clone(),
-                        client.redirect_port,
-                        err,
-                    )
-                })?
-                .accept()
-                .await
-                .map_err(Error::AcceptRedirectServerError)?;
+        let listeners = bind_redirect_server(client).await?;
+
+        // listen for one single connection, on any stack
+        let (mut stream, _) = {
+            let mut accepts: Vec<_> = listeners
+                .iter()
+                .map(|listener| Box::pin(listener.accept()))
+                .collect();
+
+            poll_fn(|cx| {
+                for accept in accepts.iter_mut() {
+                    if let Poll::Ready(res) = accept.as_mut().poll(cx) {
+                        return Poll::Ready(res);
+                    }
+                }
+                Poll::Pending
+            })
+            .await
+            .map_err(Error::AcceptRedirectServerError)?
+        };
 
         // extract the code from the url
         let code = {
@@ -104,28 +218,8 @@ pub async fn wait_for_redirection(
                 .nth(1)
                 .ok_or_else(|| Error::MissingRedirectUrlError(request_line.clone()))?;
             let redirect_url = format!("http://localhost{redirect_url}");
-            let redirect_url = Url::parse(&redirect_url)
-                .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.clone()))?;
-
-            let (_, state) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "state")
-                .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
-            let state = CsrfToken::new(state.into_owned());
-
-            if state.secret() != csrf_state.secret() {
-                return Err(Error::InvalidStateError(
-                    state.secret().to_owned(),
-                    csrf_state.secret().to_owned(),
-                ));
-            }
-
-            let (_, code) = redirect_url
-                .query_pairs()
-                .find(|(key, _)| key == "code")
-                .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
 
-            AuthorizationCode::new(code.into_owned())
+            parse_redirect_url(&redirect_url, &csrf_state)?
         };
 
         // write a basic http response in plain text
@@ -137,7 +231,46 @@ pub async fn wait_for_redirection(
         );
         stream.write_all(res.as_bytes()).await?;
 
-        // exchange the code for an access token and a refresh token
+        self.exchange_authorization_code(client, code).await
+    }
+
+    /// Extract the code from the given redirect URL received by the
+    /// caller, then exchange it with an access token and maybe a
+    /// refresh token.
+    ///
+    /// This is useful when the redirection cannot be handled by
+    /// [`AuthorizationCodeGrant::wait_for_redirection`], for example
+    /// when using a custom URI scheme like `myapp://callback` (see
+    /// [`Client::set_redirect_url`]).
+    pub async fn exchange_redirect_url(
+        self,
+        client: &Client,
+        redirect_url: impl AsRef<str>,
+        csrf_state: CsrfToken,
+    ) -> Result<(String, Option<String>)> {
+        let code = parse_redirect_url(redirect_url.as_ref(), &csrf_state)?;
+        self.exchange_authorization_code(client, code).await
+    }
+
+    /// Exchange the given code, received by the caller, with an
+    /// access token and maybe a refresh token.
+    ///
+    /// The caller is responsible for checking the state received
+    /// along with the code.
+    pub async fn exchange_code(
+        self,
+        client: &Client,
+        code: impl ToString,
+    ) -> Result<(String, Option<String>)> {
+        let code = AuthorizationCode::new(code.to_string());
+        self.exchange_authorization_code(client, code).await
+    }
+
+    async fn exchange_authorization_code(
+        self,
+        client: &Client,
+        code: AuthorizationCode,
+    ) -> Result<(String, Option<String>)> {
         let mut res = client.exchange_code(code);
 
         if let Some((_, pkce_verifier)) = self.pkce {
@@ -160,3 +293,116 @@ pub async fn wait_for_redirection(
         Ok((access_token, refresh_token))
     }
 }
+
+/// Bind the redirect server on the client's redirect host and port.
+///
+/// The `localhost` host is bound on both IPv4 and IPv6 loopback
+/// addresses, as long as at least one of them succeeds.
+async fn bind_redirect_server(client: &Client) -> Result<Vec<TcpListener>> {
+    let host = client.redirect_host.trim_matches(|c| c == '[' || c == ']');
+    let port = client.redirect_port;
+
+    if !host.eq_ignore_ascii_case("localhost") {
+        let listener = TcpListener::bind((host, port))
+            .await
+            .map_err(|err| Error::BindRedirectServerError(host.to_owned(), port, err))?;
+        return Ok(vec![listener]);
+    }
+
+    let mut listeners = Vec::new();
+    let mut last_err = None;
+
+    for ip in [
+        IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(Ipv6Addr::LOCALHOST),
+    ] {
+        match TcpListener::bind((ip, port)).await {
+            Ok(listener) => listeners.push(listener),
+            Err(err) => {
+                debug!(?ip, ?err, "cannot bind redirect server, skipping it");
+                last_err = Some(err);
+            }
+        }
+    }
+
+    match last_err {
+        Some(err) if listeners.is_empty() => {
+            Err(Error::BindRedirectServerError(host.to_owned(), port, err))
+        }
+        _ => Ok(listeners),
+    }
+}
+
+/// Extract the authorization code from the given redirect URL, after
+/// checking its state against the expected one.
+fn parse_redirect_url(redirect_url: &str, csrf_state: &CsrfToken) -> Result<AuthorizationCode> {
+    let redirect_url = Url::parse(redirect_url)
+        .map_err(|err| Error::ParseRedirectUrlError(err, redirect_url.to_owned()))?;
+
+    let (_, state) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .ok_or_else(|| Error::FindStateInRedirectUrlError(redirect_url.clone()))?;
+    let state = CsrfToken::new(state.into_owned());
+
+    if state.secret() != csrf_state.secret() {
+        return Err(Error::InvalidStateError(
+            state.secret().to_owned(),
+            csrf_state.secret().to_owned(),
+        ));
+    }
+
+    let (_, code) = redirect_url
+        .query_pairs()
+        .find(|(key, _)| key == "code")
+        .ok_or_else(|| Error::FindCodeInRedirectUrlError(redirect_url.clone()))?;
+
+    Ok(AuthorizationCode::new(code.into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use oauth2::CsrfToken;
+
+    use super::{parse_redirect_url, AuthorizationCodeGrant, PkceMethod};
+    use crate::v2_0::{Client, Provider};
+
+    #[test]
+    fn parse_custom_scheme_redirect_url() {
+        let state = CsrfToken::new("state".into());
+
+        let code = parse_redirect_url("myapp://callback?code=abc&state=state", &state).unwrap();
+        assert_eq!(code.secret(), "abc");
+
+        let err = parse_redirect_url("myapp://callback?code=abc&state=other", &state);
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn redirect_url_pkce_and_extra_params() {
+        let client = Client::from_provider(
+            Provider::Google,
+            "client-id",
+            None::<String>,
+            "http",
+            "localhost",
+            9999u16,
+        )
+        .unwrap();
+
+        let grant = AuthorizationCodeGrant::new()
+            .with_pkce_method(PkceMethod::Plain)
+            .with_extra_params(Provider::Google.extra_params().iter().copied());
+        let (url, _) = grant.get_redirect_url(&client);
+        let query: Vec<_> = url.query_pairs().collect();
+
+        assert!(query.contains(&("code_challenge_method".into(), "plain".into())));
+        assert!(query.contains(&("access_type".into(), "offline".into())));
+        assert!(query.contains(&("prompt".into(), "consent".into())));
+
+        let grant = AuthorizationCodeGrant::new().with_pkce().without_pkce();
+        let (url, _) = grant.get_redirect_url(&client);
+
+        assert!(!url.query_pairs().any(|(key, _)| key == "code_challenge"));
+    }
+}
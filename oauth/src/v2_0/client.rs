@@ -1,24 +1,57 @@
 //! Client builder, used by other flows to send requests and build
 //! URLs.
 
-use std::ops::Deref;
+use std::{net::IpAddr, ops::Deref};
 
 use oauth2::{
     http::{Method, Response},
-    AuthUrl, ClientId, ClientSecret, EndpointNotSet, EndpointSet, HttpRequest, HttpResponse,
-    RedirectUrl, TokenUrl,
+    AccessToken, AsyncHttpClient, AuthUrl, ClientId, ClientSecret, EndpointMaybeSet,
+    EndpointNotSet, EndpointSet, HttpRequest, HttpResponse, IntrospectionUrl, RedirectUrl,
+    RefreshToken, RevocationUrl, StandardRevocableToken, TokenIntrospectionResponse, TokenUrl,
 };
 
-use super::{Error, Result};
+use super::{Error, Provider, ProviderMetadata, Result};
 
 type BasicClient = oauth2::basic::BasicClient<
     EndpointSet,
     EndpointNotSet,
-    EndpointNotSet,
-    EndpointNotSet,
+    EndpointMaybeSet,
+    EndpointMaybeSet,
     EndpointSet,
 >;
 
+/// Hint about the type of the token submitted for revocation, as
+/// defined in the [RFC7009](https://datatracker.ietf.org/doc/html/rfc7009#section-2.1).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TokenTypeHint {
+    AccessToken,
+    RefreshToken,
+}
+
+/// Meta information about a token, as returned by the introspection
+/// endpoint defined in the [RFC7662](https://datatracker.ietf.org/doc/html/rfc7662#section-2.2).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TokenIntrospection {
+    /// Whether the token is currently active.
+    pub active: bool,
+
+    /// Scopes associated with the token.
+    pub scopes: Vec<String>,
+
+    /// Client identifier for which the token was issued.
+    pub client_id: Option<String>,
+
+    /// Human-readable identifier of the resource owner.
+    pub username: Option<String>,
+
+    /// Subject of the token, usually a machine-readable identifier
+    /// of the resource owner.
+    pub subject: Option<String>,
+
+    /// Expiration time of the token, as a Unix timestamp.
+    pub expires_at: Option<i64>,
+}
+
 /// Client builder, used by other flows to send requests and build
 /// URLs.
 #[derive(Clone, Debug)]
@@ -50,7 +83,11 @@ pub fn new(
             .set_token_uri(TokenUrl::new(token_url.to_string()).map_err(Error::BuildTokenUrlError)?)
             .set_redirect_uri({
                 let scheme = redirect_scheme.to_string();
-                RedirectUrl::new(format!("{scheme}://{redirect_host}:{redirect_port}"))
+                let host = match redirect_host.parse() {
+                    Ok(IpAddr::V6(ip)) => format!("[{ip}]"),
+                    _ => redirect_host.clone(),
+                };
+                RedirectUrl::new(format!("{scheme}://{host}:{redirect_port}"))
                     .map_err(Error::BuildRedirectUrlError)
             }?);
 
@@ -58,6 +95,10 @@ pub fn new(
             client = client.set_client_secret(ClientSecret::new(secret.to_string()));
         }
 
+        let client = client
+            .set_introspection_url_option(None)
+            .set_revocation_url_option(None);
+
         Ok(Self {
             inner: client,
             redirect_host,
@@ -65,6 +106,190 @@ pub fn new(
         })
     }
 
+    /// Build a client using the endpoints of the given provider
+    /// preset.
+    pub fn from_provider(
+        provider: Provider,
+        client_id: impl ToString,
+        client_secret: Option<impl ToString>,
+        redirect_scheme: impl ToString,
+        redirect_host: impl ToString,
+        redirect_port: impl Into<u16>,
+    ) -> Result<Self> {
+        let mut client = Self::new(
+            client_id,
+            client_secret,
+            provider.auth_url(),
+            provider.token_url(),
+            redirect_scheme,
+            redirect_host,
+            redirect_port,
+        )?;
+
+        if let Some(url) = provider.revocation_url() {
+            client.set_revocation_url(url)?;
+        }
+
+        Ok(client)
+    }
+
+    /// Build a client using the endpoints of the given discovered
+    /// provider metadata.
+    ///
+    /// See [`ProviderMetadata::discover`].
+    pub fn from_metadata(
+        metadata: &ProviderMetadata,
+        client_id: impl ToString,
+        client_secret: Option<impl ToString>,
+        redirect_scheme: impl ToString,
+        redirect_host: impl ToString,
+        redirect_port: impl Into<u16>,
+    ) -> Result<Self> {
+        let mut client = Self::new(
+            client_id,
+            client_secret,
+            &metadata.authorization_endpoint,
+            &metadata.token_endpoint,
+            redirect_scheme,
+            redirect_host,
+            redirect_port,
+        )?;
+
+        if let Some(url) = &metadata.revocation_endpoint {
+            client.set_revocation_url(url)?;
+        }
+
+        if let Some(url) = &metadata.introspection_endpoint {
+            client.set_introspection_url(url)?;
+        }
+
+        Ok(client)
+    }
+
+    /// Override the redirect URL built from the redirect scheme, host
+    /// and port.
+    ///
+    /// This is useful for custom URI schemes like `myapp://callback`,
+    /// which cannot be handled by the redirect server: the received
+    /// redirect URL needs to be fed back using
+    /// [`crate::v2_0::AuthorizationCodeGrant::exchange_redirect_url`].
+    pub fn set_redirect_url(&mut self, url: impl ToString) -> Result<()> {
+        let url = RedirectUrl::new(url.to_string()).map_err(Error::BuildRedirectUrlError)?;
+        self.inner = self.inner.clone().set_redirect_uri(url);
+        Ok(())
+    }
+
+    /// Override the redirect URL built from the redirect scheme, host
+    /// and port, using the builder pattern.
+    pub fn with_redirect_url(mut self, url: impl ToString) -> Result<Self> {
+        self.set_redirect_url(url)?;
+        Ok(self)
+    }
+
+    /// Set the URL of the authorization server's revocation endpoint.
+    pub fn set_revocation_url(&mut self, url: impl ToString) -> Result<()> {
+        let url = RevocationUrl::new(url.to_string()).map_err(Error::BuildRevocationUrlError)?;
+        self.inner = self.inner.clone().set_revocation_url_option(Some(url));
+        Ok(())
+    }
+
+    /// Set the URL of the authorization server's revocation
+    /// endpoint, using the builder pattern.
+    pub fn with_revocation_url(mut self, url: impl ToString) -> Result<Self> {
+        self.set_revocation_url(url)?;
+        Ok(self)
+    }
+
+    /// Set the URL of the authorization server's introspection
+    /// endpoint.
+    pub fn set_introspection_url(&mut self, url: impl ToString) -> Result<()> {
+        let url =
+            IntrospectionUrl::new(url.to_string()).map_err(Error::BuildIntrospectionUrlError)?;
+        self.inner = self.inner.clone().set_introspection_url_option(Some(url));
+        Ok(())
+    }
+
+    /// Set the URL of the authorization server's introspection
+    /// endpoint, using the builder pattern.
+    pub fn with_introspection_url(mut self, url: impl ToString) -> Result<Self> {
+        self.set_introspection_url(url)?;
+        Ok(self)
+    }
+
+    /// Revoke the given token, as defined in the
+    /// [RFC7009](https://datatracker.ietf.org/doc/html/rfc7009).
+    ///
+    /// Revoking a refresh token usually revokes the access tokens
+    /// issued from it as well, which makes it the way to go for
+    /// logging an account out.
+    pub async fn revoke_token(&self, token: impl ToString, hint: TokenTypeHint) -> Result<()> {
+        self.revoke_token_with(token, hint, &Self::send_oauth2_request)
+            .await
+    }
+
+    async fn revoke_token_with<'c, C>(
+        &'c self,
+        token: impl ToString,
+        hint: TokenTypeHint,
+        http_client: &'c C,
+    ) -> Result<()>
+    where
+        C: AsyncHttpClient<'c, Error = Error>,
+    {
+        let token = match hint {
+            TokenTypeHint::AccessToken => {
+                StandardRevocableToken::AccessToken(AccessToken::new(token.to_string()))
+            }
+            TokenTypeHint::RefreshToken => {
+                StandardRevocableToken::RefreshToken(RefreshToken::new(token.to_string()))
+            }
+        };
+
+        self.inner
+            .revoke_token(token)
+            .map_err(Error::ConfigureRevocationError)?
+            .request_async(http_client)
+            .await
+            .map_err(|err| Error::RevokeTokenError(Box::new(err)))
+    }
+
+    /// Get meta information about the given access token, as
+    /// defined in the [RFC7662](https://datatracker.ietf.org/doc/html/rfc7662).
+    pub async fn introspect_token(&self, token: impl ToString) -> Result<TokenIntrospection> {
+        let token = AccessToken::new(token.to_string());
+        self.introspect_token_with(&token, &Self::send_oauth2_request)
+            .await
+    }
+
+    async fn introspect_token_with<'c, C>(
+        &'c self,
+        token: &'c AccessToken,
+        http_client: &'c C,
+    ) -> Result<TokenIntrospection>
+    where
+        C: AsyncHttpClient<'c, Error = Error>,
+    {
+        let res = self
+            .inner
+            .introspect(token)
+            .map_err(Error::ConfigureIntrospectionError)?
+            .request_async(http_client)
+            .await
+            .map_err(|err| Error::IntrospectTokenError(Box::new(err)))?;
+
+        Ok(TokenIntrospection {
+            active: res.active(),
+            scopes: res
+                .scopes()
+                .map(|scopes| scopes.iter().map(|scope| scope.to_string()).collect())
+                .unwrap_or_default(),
+            client_id: res.client_id().map(|id| id.to_string()),
+            username: res.username().map(ToOwned::to_owned),
+            subject: res.sub().map(ToOwned::to_owned),
+            expires_at: res.exp().map(|exp| exp.timestamp()),
+        })
+    }
+
     pub(crate) async fn send_oauth2_request(oauth2_request: HttpRequest) -> Result<HttpResponse> {
         let client = http::Client::new();
 
@@ -127,3 +352,159 @@ fn deref(&self) -> &Self::Target {
         &self.inner
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Ready,
+        sync::{Arc, Mutex},
+    };
+
+    use oauth2::{http::Response, AccessToken, HttpRequest, HttpResponse};
+
+    use super::{Client, TokenIntrospection, TokenTypeHint};
+    use crate::v2_0::{Error, Result};
+
+    fn client() -> Client {
+        Client::new(
+            "client-id",
+            None::<String>,
+            "https://localhost/auth",
+            "https://localhost/token",
+            "http",
+            "localhost",
+            9999u16,
+        )
+        .unwrap()
+    }
+
+    /// HTTP client answering the given response, and keeping the
+    /// body of the last request.
+    fn http_client(
+        status: u16,
+        body: &'static str,
+    ) -> (
+        impl Fn(HttpRequest) -> Ready<Result<HttpResponse>>,
+        Arc<Mutex<String>>,
+    ) {
+        let request = Arc::new(Mutex::new(String::new()));
+        let last_request = request.clone();
+
+        let http_client = move |req: HttpRequest| {
+            *last_request.lock().unwrap() = String::from_utf8_lossy(req.body()).into_owned();
+
+            let res = Response::builder()
+                .status(status)
+                .header("content-type", "application/json")
+                .body(body.as_bytes().to_vec())
+                .unwrap();
+
+            std::future::ready(Ok(res))
+        };
+
+        (http_client, request)
+    }
+
+    #[tokio::test]
+    async fn revoke_token() {
+        let client = client()
+            .with_revocation_url("https://localhost/revoke")
+            .unwrap();
+        let (http_client, request) = http_client(200, "");
+
+        client
+            .revoke_token_with("refresh", TokenTypeHint::RefreshToken, &http_client)
+            .await
+            .unwrap();
+
+        let request = request.lock().unwrap();
+        assert!(request.contains("token=refresh"));
+        assert!(request.contains("token_type_hint=refresh_token"));
+    }
+
+    #[tokio::test]
+    async fn revoke_token_error() {
+        let client = client()
+            .with_revocation_url("https://localhost/revoke")
+            .unwrap();
+        let (http_client, _) = http_client(400, r#"{"error":"unsupported_token_type"}"#);
+
+        let err = client
+            .revoke_token_with("access", TokenTypeHint::AccessToken, &http_client)
+            .await
+            .unwrap_err();
+
+        let Error::RevokeTokenError(err) = err else {
+            panic!("expected revoke token error, got {err:?}");
+        };
+
+        assert!(err.to_string().contains("unsupported_token_type"));
+    }
+
+    #[tokio::test]
+    async fn revoke_token_without_url() {
+        let err = client()
+            .revoke_token("access", TokenTypeHint::AccessToken)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, Error::ConfigureRevocationError(_)));
+    }
+
+    #[tokio::test]
+    async fn introspect_token() {
+        let client = client()
+            .with_introspection_url("https://localhost/introspect")
+            .unwrap();
+        let (http_client, request) = http_client(
+            200,
+            r#"{
+                "active": true,
+                "scope": "read write",
+                "client_id": "client-id",
+                "username": "alice",
+                "sub": "42",
+                "exp": 1700000000
+            }"#,
+        );
+
+        let introspection = client
+            .introspect_token_with(&AccessToken::new(String::from("access")), &http_client)
+            .await
+            .unwrap();
+
+        assert!(request.lock().unwrap().contains("token=access"));
+        assert_eq!(
+            introspection,
+            TokenIntrospection {
+                active: true,
+                scopes: vec![String::from("read"), String::from("write")],
+                client_id: Some(String::from("client-id")),
+                username: Some(String::from("alice")),
+                subject: Some(String::from("42")),
+                expires_at: Some(1_700_000_000),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn introspect_inactive_token() {
+        let client = client()
+            .with_introspection_url("https://localhost/introspect")
+            .unwrap();
+        let (http_client, _) = http_client(200, r#"{"active":false}"#);
+
+        let introspection = client
+            .introspect_token_with(&AccessToken::new(String::from("access")), &http_client)
+            .await
+            .unwrap();
+
+        assert_eq!(introspection, TokenIntrospection::default());
+    }
+
+    #[tokio::test]
+    async fn introspect_token_without_url() {
+        let err = client().introspect_token("access").await.unwrap_err();
+        assert!(matches!(err, Error::ConfigureIntrospectionError(_)));
+    }
+}
@@ -0,0 +1,255 @@
+//! OpenID Connect discovery, based on the [OpenID Connect Discovery
+//! 1.0](https://openid.net/specs/openid-connect-discovery-1_0.html)
+//! specification.
+//!
+//! The discovery document of an issuer exposes its endpoints, so
+//! that users only need to supply the issuer URL and their client
+//! credentials.
+//!
+//! Discovery documents rarely change, so they are cached by issuer
+//! for the lifetime of the process.
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, OnceLock},
+};
+
+use serde_json::{Map, Value};
+use tracing::debug;
+
+use super::{Error, Result};
+
+/// Path of the discovery document, relative to the issuer URL.
+pub const WELL_KNOWN_PATH: &str = "/.well-known/openid-configuration";
+
+/// Discovered provider metadata, indexed by issuer.
+static CACHE: OnceLock<Mutex<HashMap<String, ProviderMetadata>>> = OnceLock::new();
+
+/// Provider metadata, as exposed by the discovery document.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ProviderMetadata {
+    /// URL of the issuer.
+    pub issuer: String,
+
+    /// URL of the authorization endpoint.
+    pub authorization_endpoint: String,
+
+    /// URL of the token endpoint.
+    pub token_endpoint: String,
+
+    /// URL of the revocation endpoint, if any.
+    pub revocation_endpoint: Option<String>,
+
+    /// URL of the introspection endpoint, if any.
+    pub introspection_endpoint: Option<String>,
+
+    /// Scopes supported by the provider.
+    pub scopes_supported: Vec<String>,
+}
+
+impl ProviderMetadata {
+    /// Build the URL of the discovery document of the given issuer.
+    pub fn discovery_url(issuer: &str) -> String {
+        format!("{}{WELL_KNOWN_PATH}", issuer.trim_end_matches('/'))
+    }
+
+    /// Get the provider metadata of the given issuer.
+    ///
+    /// The discovery document is fetched only once per issuer, see
+    /// [`ProviderMetadata::fetch`] to bypass the cache.
+    pub async fn discover(issuer: impl AsRef<str>) -> Result<Self> {
+        let issuer = issuer.as_ref();
+
+        if let Some(metadata) = Self::cached(issuer) {
+            debug!(issuer, "using cached oidc discovery document");
+            return Ok(metadata);
+        }
+
+        let metadata = Self::fetch(issuer).await?;
+        Self::cache(issuer, metadata.clone());
+        Ok(metadata)
+    }
+
+    /// Get the cached provider metadata of the given issuer, if any.
+    pub fn cached(issuer: &str) -> Option<Self> {
+        let cache = CACHE.get()?.lock().unwrap_or_else(|err| err.into_inner());
+        cache.get(issuer.trim_end_matches('/')).cloned()
+    }
+
+    /// Cache the given provider metadata for the given issuer.
+    pub fn cache(issuer: &str, metadata: Self) {
+        let mut cache = CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        cache.insert(issuer.trim_end_matches('/').to_owned(), metadata);
+    }
+
+    /// Fetch and parse the discovery document of the given issuer,
+    /// without using the cache.
+    pub async fn fetch(issuer: impl AsRef<str>) -> Result<Self> {
+        let issuer = issuer.as_ref();
+        let url = Self::discovery_url(issuer);
+        debug!(url, "fetching oidc discovery document");
+
+        let client = http::Client::new();
+
+        let response = client
+            .send({
+                let url = url.clone();
+                move |agent| agent.get(&url).call()
+            })
+            .await
+            .map_err(|err| Error::FetchDiscoveryDocumentError(err, url.clone()))?;
+
+        let body = response
+            .into_body()
+            .read_to_vec()
+            .map_err(http::Error::from)
+            .map_err(|err| Error::FetchDiscoveryDocumentError(err, url))?;
+
+        Self::parse(issuer, &body)
+    }
+
+    /// Parse the given discovery document of the given issuer.
+    ///
+    /// As required by the specification, the issuer exposed by the
+    /// document must match the one used to fetch it.
+    pub fn parse(issuer: &str, document: &[u8]) -> Result<Self> {
+        let mut document: Map<String, Value> =
+            serde_json::from_slice(document).map_err(Error::ParseDiscoveryDocumentError)?;
+
+        let metadata = Self {
+            issuer: required_field(&mut document, "issuer")?,
+            authorization_endpoint: required_field(&mut document, "authorization_endpoint")?,
+            token_endpoint: required_field(&mut document, "token_endpoint")?,
+            revocation_endpoint: optional_field(&mut document, "revocation_endpoint")?,
+            introspection_endpoint: optional_field(&mut document, "introspection_endpoint")?,
+            scopes_supported: optional_field(&mut document, "scopes_supported")?
+                .unwrap_or_default(),
+        };
+
+        let expected = issuer.trim_end_matches('/');
+        let got = metadata.issuer.trim_end_matches('/');
+
+        if expected != got {
+            let got = metadata.issuer;
+            return Err(Error::InvalidDiscoveryIssuerError(got, expected.to_owned()));
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Take the given field out of the discovery document.
+///
+/// Parsing the document by hand rather than deriving
+/// [`serde::Deserialize`] keeps serde derives behind the `derive`
+/// cargo feature.
+fn required_field<T: serde::de::DeserializeOwned>(
+    document: &mut Map<String, Value>,
+    key: &'static str,
+) -> Result<T> {
+    optional_field(document, key)?.ok_or(Error::MissingDiscoveryFieldError(key))
+}
+
+/// Take the given field out of the discovery document, if present
+/// and not null.
+fn optional_field<T: serde::de::DeserializeOwned>(
+    document: &mut Map<String, Value>,
+    key: &'static str,
+) -> Result<Option<T>> {
+    match document.remove(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value)
+            .map(Some)
+            .map_err(Error::ParseDiscoveryDocumentError),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProviderMetadata;
+
+    #[test]
+    fn parse_discovery_document() {
+        let document = br#"{
+            "issuer": "https://accounts.google.com",
+            "authorization_endpoint": "https://accounts.google.com/o/oauth2/v2/auth",
+            "token_endpoint": "https://oauth2.googleapis.com/token",
+            "revocation_endpoint": "https://oauth2.googleapis.com/revoke",
+            "scopes_supported": ["openid", "email", "profile"],
+            "response_types_supported": ["code"]
+        }"#;
+
+        let metadata = ProviderMetadata::parse("https://accounts.google.com/", document).unwrap();
+
+        assert_eq!(
+            metadata.authorization_endpoint,
+            "https://accounts.google.com/o/oauth2/v2/auth"
+        );
+        assert_eq!(
+            metadata.token_endpoint,
+            "https://oauth2.googleapis.com/token"
+        );
+        assert_eq!(
+            metadata.revocation_endpoint.as_deref(),
+            Some("https://oauth2.googleapis.com/revoke")
+        );
+        assert_eq!(metadata.introspection_endpoint, None);
+        assert_eq!(metadata.scopes_supported.len(), 3);
+
+        let err = ProviderMetadata::parse("https://evil.example.com", document).unwrap_err();
+        assert!(err.to_string().contains("https://evil.example.com"));
+
+        let document = br#"{ "issuer": "https://accounts.google.com" }"#;
+        let err = ProviderMetadata::parse("https://accounts.google.com", document).unwrap_err();
+        assert!(err.to_string().contains("authorization_endpoint"));
+    }
+
+    #[test]
+    fn cache_discovery_document() {
+        let issuer = "https://cache.example.com";
+        let document = br#"{
+            "issuer": "https://cache.example.com",
+            "authorization_endpoint": "https://cache.example.com/auth",
+            "token_endpoint": "https://cache.example.com/token"
+        }"#;
+
+        assert_eq!(ProviderMetadata::cached(issuer), None);
+
+        let metadata = ProviderMetadata::parse(issuer, document).unwrap();
+        ProviderMetadata::cache(issuer, metadata.clone());
+
+        assert_eq!(ProviderMetadata::cached(issuer), Some(metadata.clone()));
+        assert_eq!(
+            ProviderMetadata::cached("https://cache.example.com/"),
+            Some(metadata)
+        );
+    }
+
+    #[tokio::test]
+    async fn discover_from_cache() {
+        let issuer = "https://discover.example.com";
+        let document = br#"{
+            "issuer": "https://discover.example.com",
+            "authorization_endpoint": "https://discover.example.com/auth",
+            "token_endpoint": "https://discover.example.com/token"
+        }"#;
+
+        let metadata = ProviderMetadata::parse(issuer, document).unwrap();
+        ProviderMetadata::cache(issuer, metadata.clone());
+
+        // the issuer cannot be resolved, the metadata must come from
+        // the cache
+        assert_eq!(ProviderMetadata::discover(issuer).await.unwrap(), metadata);
+    }
+
+    #[test]
+    fn build_discovery_url() {
+        assert_eq!(
+            ProviderMetadata::discovery_url("https://login.example.com/tenant/"),
+            "https://login.example.com/tenant/.well-known/openid-configuration"
+        );
+    }
+}
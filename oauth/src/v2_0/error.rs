@@ -1,5 +1,5 @@
 use oauth2::{
-    basic::BasicErrorResponseType,
+    basic::{BasicErrorResponse, BasicErrorResponseType, BasicRevocationErrorResponse},
     url::{ParseError, Url},
     RequestTokenError, StandardErrorResponse,
 };
@@ -39,6 +39,32 @@ pub enum Error {
     FindStateInRedirectUrlError(Url),
     #[error("cannot exchange code for access and refresh tokens: {0}")]
     ExchangeCodeError(String),
+    #[error("cannot revoke token: missing or invalid revocation url")]
+    ConfigureRevocationError(#[source] oauth2::ConfigurationError),
+    #[error("cannot revoke token")]
+    RevokeTokenError(#[source] Box<RequestTokenError<Error, BasicRevocationErrorResponse>>),
+    #[error("cannot introspect token: missing or invalid introspection url")]
+    ConfigureIntrospectionError(#[source] oauth2::ConfigurationError),
+    #[error("cannot introspect token")]
+    IntrospectTokenError(#[source] Box<RequestTokenError<Error, BasicErrorResponse>>),
+    #[error("cannot parse unknown oauth2 provider {0}")]
+    ParseProviderError(String),
+    #[error("cannot fetch oidc discovery document at {1}")]
+    FetchDiscoveryDocumentError(#[source] http::Error, String),
+    #[error("cannot parse oidc discovery document")]
+    ParseDiscoveryDocumentError(#[source] serde_json::Error),
+    #[error("cannot parse oidc discovery document: missing field {0}")]
+    MissingDiscoveryFieldError(&'static str),
+    #[error("invalid oidc discovery issuer {0}: expected {1}")]
+    InvalidDiscoveryIssuerError(String, String),
+    #[error("cannot find oauth2 tokens")]
+    MissingTokensError,
+    #[error("cannot refresh access token: missing refresh token")]
+    MissingRefreshTokenError,
+    #[error("cannot load oauth2 tokens")]
+    LoadTokensError(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("cannot save oauth2 tokens")]
+    SaveTokensError(#[source] Box<dyn std::error::Error + Send + Sync>),
 
     #[error(transparent)]
     IoError(#[from] std::io::Error),
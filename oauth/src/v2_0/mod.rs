@@ -7,13 +7,26 @@
 
 mod authorization_code_grant;
 mod client;
+mod discovery;
 mod error;
+mod provider;
 mod refresh_access_token;
+mod scope;
+mod token_manager;
 
+#[cfg(feature = "secret")]
+#[doc(inline)]
+pub use self::token_manager::SecretTokenStorage;
 #[doc(inline)]
 pub use self::{
-    authorization_code_grant::AuthorizationCodeGrant,
-    client::Client,
+    authorization_code_grant::{AuthorizationCodeGrant, PkceMethod},
+    client::{Client, TokenIntrospection, TokenTypeHint},
+    discovery::{ProviderMetadata, WELL_KNOWN_PATH},
     error::{Error, Result},
+    provider::Provider,
     refresh_access_token::RefreshAccessToken,
+    scope::{Capability, Scopes},
+    token_manager::{
+        MemoryTokenStorage, TokenManager, TokenStorage, Tokens, DEFAULT_REFRESH_MARGIN,
+    },
 };
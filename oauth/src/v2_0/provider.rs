@@ -0,0 +1,167 @@
+//! Well-known OAuth 2.0 providers, exposing the endpoints and the
+//! scopes required to access emails.
+
+use std::{fmt, str::FromStr};
+
+use super::{Capability, Error, Scopes};
+
+/// Well-known OAuth 2.0 provider preset.
+///
+/// Presets only require the client id (and the client secret if
+/// any) issued by the provider during the registration process.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Provider {
+    Google,
+    #[cfg_attr(feature = "derive", serde(alias = "outlook"))]
+    Microsoft,
+    Yahoo,
+    Fastmail,
+}
+
+impl Provider {
+    /// URL of the provider's authorization endpoint.
+    pub fn auth_url(&self) -> &'static str {
+        match self {
+            Self::Google => "https://accounts.google.com/o/oauth2/v2/auth",
+            Self::Microsoft => "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            Self::Yahoo => "https://api.login.yahoo.com/oauth2/request_auth",
+            Self::Fastmail => "https://api.fastmail.com/oauth/authorize",
+        }
+    }
+
+    /// URL of the provider's token endpoint.
+    pub fn token_url(&self) -> &'static str {
+        match self {
+            Self::Google => "https://oauth2.googleapis.com/token",
+            Self::Microsoft => "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            Self::Yahoo => "https://api.login.yahoo.com/oauth2/get_token",
+            Self::Fastmail => "https://api.fastmail.com/oauth/refresh",
+        }
+    }
+
+    /// URL of the provider's revocation endpoint, if any.
+    pub fn revocation_url(&self) -> Option<&'static str> {
+        match self {
+            Self::Google => Some("https://oauth2.googleapis.com/revoke"),
+            Self::Microsoft | Self::Yahoo | Self::Fastmail => None,
+        }
+    }
+
+    /// Scopes required to read and send emails.
+    pub fn scopes(&self) -> &'static [&'static str] {
+        match self {
+            Self::Google => &["https://mail.google.com/"],
+            Self::Microsoft => &[
+                "https://outlook.office.com/IMAP.AccessAsUser.All",
+                "https://outlook.office.com/SMTP.Send",
+                // required to get a refresh token
+                "offline_access",
+            ],
+            Self::Yahoo => &["mail-w"],
+            Self::Fastmail => &[
+                "https://www.fastmail.com/dev/protocol-imap",
+                "https://www.fastmail.com/dev/protocol-smtp",
+            ],
+        }
+    }
+
+    /// Scopes required by the given capability, or `None` if the
+    /// provider does not support it.
+    pub fn capability_scopes(&self, capability: Capability) -> Option<&'static [&'static str]> {
+        match (self, capability) {
+            (Self::Google, Capability::Imap | Capability::Smtp) => {
+                Some(&["https://mail.google.com/"])
+            }
+            (Self::Google, Capability::Contacts) => {
+                Some(&["https://www.googleapis.com/auth/carddav"])
+            }
+            (Self::Google, Capability::Calendars) => {
+                Some(&["https://www.googleapis.com/auth/calendar"])
+            }
+            (Self::Microsoft, Capability::Imap) => Some(&[
+                "https://outlook.office.com/IMAP.AccessAsUser.All",
+                "offline_access",
+            ]),
+            (Self::Microsoft, Capability::Smtp) => {
+                Some(&["https://outlook.office.com/SMTP.Send", "offline_access"])
+            }
+            (Self::Yahoo, Capability::Imap | Capability::Smtp) => Some(&["mail-w"]),
+            (Self::Fastmail, Capability::Imap) => {
+                Some(&["https://www.fastmail.com/dev/protocol-imap"])
+            }
+            (Self::Fastmail, Capability::Smtp) => {
+                Some(&["https://www.fastmail.com/dev/protocol-smtp"])
+            }
+            (Self::Fastmail, Capability::Contacts) => {
+                Some(&["https://www.fastmail.com/dev/protocol-carddav"])
+            }
+            (Self::Fastmail, Capability::Calendars) => {
+                Some(&["https://www.fastmail.com/dev/protocol-caldav"])
+            }
+            (Self::Microsoft | Self::Yahoo, Capability::Contacts | Capability::Calendars) => None,
+        }
+    }
+
+    /// Scopes required by all the given capabilities, or `None` if
+    /// the provider does not support one of them.
+    pub fn required_scopes(
+        &self,
+        capabilities: impl IntoIterator<Item = Capability>,
+    ) -> Option<Scopes> {
+        let mut scopes: Vec<&str> = Vec::new();
+
+        for capability in capabilities {
+            scopes.extend(self.capability_scopes(capability)?);
+        }
+
+        Some(scopes.into_iter().collect())
+    }
+
+    /// Return `true` if the provider supports incremental consent,
+    /// which means that scopes granted by previous authorizations are
+    /// kept when requesting new ones.
+    pub fn supports_incremental_consent(&self) -> bool {
+        matches!(self, Self::Google)
+    }
+
+    /// Extra parameters to add to the authorization request.
+    ///
+    /// Google only issues a refresh token when the user is prompted
+    /// for consent with an offline access type.
+    pub fn extra_params(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Google => &[("access_type", "offline"), ("prompt", "consent")],
+            Self::Microsoft | Self::Yahoo | Self::Fastmail => &[],
+        }
+    }
+}
+
+impl fmt::Display for Provider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Google => write!(f, "google"),
+            Self::Microsoft => write!(f, "microsoft"),
+            Self::Yahoo => write!(f, "yahoo"),
+            Self::Fastmail => write!(f, "fastmail"),
+        }
+    }
+}
+
+impl FromStr for Provider {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "google" | "gmail" => Ok(Self::Google),
+            "microsoft" | "outlook" => Ok(Self::Microsoft),
+            "yahoo" => Ok(Self::Yahoo),
+            "fastmail" => Ok(Self::Fastmail),
+            _ => Err(Error::ParseProviderError(s.to_owned())),
+        }
+    }
+}
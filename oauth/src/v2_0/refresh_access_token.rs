@@ -3,7 +3,7 @@
 
 use oauth2::{RefreshToken, TokenResponse};
 
-use super::{Client, Error, Result};
+use super::{Client, Error, Result, Tokens};
 
 /// OAuth 2.0 Refresh Access Token flow builder. The builder is empty
 /// for now but scopes will be added in the future. This flow exchange
@@ -22,6 +22,17 @@ pub async fn refresh_access_token(
         client: &Client,
         refresh_token: impl ToString,
     ) -> Result<(String, Option<String>)> {
+        let tokens = self.refresh_tokens(client, refresh_token).await?;
+        Ok((tokens.access_token, tokens.refresh_token))
+    }
+
+    /// Exchange the given refresh token for a new set of tokens,
+    /// including the expiration time of the access token.
+    pub async fn refresh_tokens(
+        &self,
+        client: &Client,
+        refresh_token: impl ToString,
+    ) -> Result<Tokens> {
         let res = client
             .exchange_refresh_token(&RefreshToken::new(refresh_token.to_string()))
             .request_async(&Client::send_oauth2_request)
@@ -29,9 +40,14 @@ pub async fn refresh_access_token(
             .map_err(Box::new)
             .map_err(Error::RefreshAccessTokenError)?;
 
-        let access_token = res.access_token().secret().to_owned();
-        let refresh_token = res.refresh_token().map(|t| t.secret().clone());
+        let tokens = Tokens::new(res.access_token().secret())
+            .with_refresh_token(res.refresh_token().map(|t| t.secret()))
+            .with_expires_in(res.expires_in())
+            .with_scopes(
+                res.scopes()
+                    .map(|scopes| scopes.iter().map(|s| s.as_str()).collect()),
+            );
 
-        Ok((access_token, refresh_token))
+        Ok(tokens)
     }
 }
@@ -0,0 +1,159 @@
+//! Scope management helpers, used to compare the scopes granted by
+//! the authorization server with the ones required by features, as
+//! defined in the
+//! [RFC6749](https://datatracker.ietf.org/doc/html/rfc6749#section-3.3).
+//!
+//! A single account can progressively unlock features (contacts,
+//! calendars etc) by requesting missing scopes only when needed, see
+//! [`super::AuthorizationCodeGrant::with_incremental_scopes`].
+
+use std::{collections::BTreeSet, fmt};
+
+use super::TokenIntrospection;
+
+/// Feature requiring its own scopes.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Capability {
+    /// Read emails using IMAP.
+    Imap,
+
+    /// Send emails using SMTP.
+    Smtp,
+
+    /// Read and write contacts using CardDAV.
+    Contacts,
+
+    /// Read and write calendars using CalDAV.
+    Calendars,
+}
+
+/// Set of scopes.
+///
+/// Scopes are case-sensitive strings, serialized as a list of
+/// space-delimited strings.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Scopes(BTreeSet<String>);
+
+impl Scopes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse the given space-delimited list of scopes, as returned
+    /// by the token endpoint.
+    pub fn parse(scopes: impl AsRef<str>) -> Self {
+        scopes.as_ref().split_whitespace().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn contains(&self, scope: impl AsRef<str>) -> bool {
+        self.0.contains(scope.as_ref())
+    }
+
+    /// Return `true` if all the given scopes are part of the set.
+    pub fn contains_all(&self, scopes: &Scopes) -> bool {
+        self.0.is_superset(&scopes.0)
+    }
+
+    /// Return the given scopes that are not part of the set.
+    pub fn missing(&self, required: &Scopes) -> Scopes {
+        Self(required.0.difference(&self.0).cloned().collect())
+    }
+
+    /// Return the union of the set and the given scopes.
+    pub fn union(&self, scopes: &Scopes) -> Scopes {
+        Self(self.0.union(&scopes.0).cloned().collect())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl<T: ToString> FromIterator<T> for Scopes {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|scope| scope.to_string()).collect())
+    }
+}
+
+impl From<&TokenIntrospection> for Scopes {
+    fn from(introspection: &TokenIntrospection) -> Self {
+        introspection.scopes.iter().collect()
+    }
+}
+
+impl fmt::Display for Scopes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scopes: Vec<_> = self.iter().collect();
+        write!(f, "{}", scopes.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capability, Scopes};
+    use crate::v2_0::{AuthorizationCodeGrant, Client, Provider};
+
+    #[test]
+    fn missing_scopes() {
+        let granted = Scopes::parse("https://mail.google.com/  openid");
+        let required = Provider::Google
+            .required_scopes([Capability::Imap, Capability::Contacts])
+            .unwrap();
+
+        assert!(!granted.contains_all(&required));
+        assert_eq!(
+            granted.missing(&required).to_string(),
+            "https://www.googleapis.com/auth/carddav"
+        );
+        assert!(granted.union(&required).contains_all(&required));
+
+        assert_eq!(
+            Provider::Yahoo.required_scopes([Capability::Contacts]),
+            None
+        );
+    }
+
+    #[test]
+    fn incremental_scopes() {
+        let client = Client::from_provider(
+            Provider::Google,
+            "client-id",
+            None::<String>,
+            "http",
+            "localhost",
+            9999u16,
+        )
+        .unwrap();
+
+        let granted = Scopes::parse("https://mail.google.com/");
+        let required = Scopes::parse("https://www.googleapis.com/auth/carddav");
+
+        let grant = AuthorizationCodeGrant::new().with_incremental_scopes(
+            Some(Provider::Google),
+            &granted,
+            &required,
+        );
+        let (url, _) = grant.get_redirect_url(&client);
+        let query: Vec<_> = url.query_pairs().collect();
+
+        assert!(query.contains(&("scope".into(), required.to_string().into())));
+        assert!(query.contains(&("include_granted_scopes".into(), "true".into())));
+
+        let grant =
+            AuthorizationCodeGrant::new().with_incremental_scopes(None, &granted, &required);
+        let (url, _) = grant.get_redirect_url(&client);
+        let query: Vec<_> = url.query_pairs().collect();
+
+        let all = granted.union(&required).to_string();
+        assert!(query.contains(&("scope".into(), all.into())));
+    }
+}
@@ -0,0 +1,472 @@
+//! Token manager, which persists access and refresh tokens and
+//! transparently refreshes the access token before it expires.
+
+use std::{
+    fmt,
+    sync::Mutex as StdMutex,
+    time::{Duration, SystemTime},
+};
+
+#[cfg(feature = "async-std")]
+use async_std::sync::Mutex;
+use async_trait::async_trait;
+#[cfg(feature = "tokio")]
+use tokio::sync::Mutex;
+use tracing::debug;
+
+use super::{Client, Error, RefreshAccessToken, Result, Scopes};
+
+/// The default delay before expiry from which the access token is
+/// considered expired and gets refreshed.
+pub const DEFAULT_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// The set of tokens issued by the authorization server.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Tokens {
+    /// The access token.
+    pub access_token: String,
+
+    /// The optional refresh token.
+    pub refresh_token: Option<String>,
+
+    /// The optional expiration time of the access token, computed
+    /// from the `expires_in` field of the token response.
+    ///
+    /// An access token without expiration time is considered valid
+    /// until it gets explicitly refreshed.
+    pub expires_at: Option<SystemTime>,
+
+    /// The optional scopes granted to the access token, as returned
+    /// by the token endpoint.
+    ///
+    /// The token endpoint may omit them when they are identical to
+    /// the requested ones.
+    pub scopes: Option<Scopes>,
+}
+
+impl Tokens {
+    /// Create a new set of tokens from the given access token.
+    pub fn new(access_token: impl ToString) -> Self {
+        Self {
+            access_token: access_token.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the refresh token, using the builder pattern.
+    pub fn with_refresh_token(mut self, refresh_token: Option<impl ToString>) -> Self {
+        self.refresh_token = refresh_token.map(|token| token.to_string());
+        self
+    }
+
+    /// Set the expiration time from the given `expires_in` duration,
+    /// using the builder pattern.
+    pub fn with_expires_in(mut self, expires_in: Option<Duration>) -> Self {
+        self.expires_at = expires_in.map(|duration| SystemTime::now() + duration);
+        self
+    }
+
+    /// Set the granted scopes, using the builder pattern.
+    pub fn with_scopes(mut self, scopes: Option<Scopes>) -> Self {
+        self.scopes = scopes;
+        self
+    }
+
+    /// Return `true` if the access token expires within the given
+    /// margin.
+    pub fn expires_within(&self, margin: Duration) -> bool {
+        match self.expires_at {
+            Some(expires_at) => expires_at <= SystemTime::now() + margin,
+            None => false,
+        }
+    }
+}
+
+impl fmt::Debug for Tokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tokens")
+            .field("access_token", &"<redacted>")
+            .field(
+                "refresh_token",
+                &self.refresh_token.as_ref().map(|_| "<redacted>"),
+            )
+            .field("expires_at", &self.expires_at)
+            .field("scopes", &self.scopes)
+            .finish()
+    }
+}
+
+/// The token storage trait.
+///
+/// Describes where the [`TokenManager`] loads tokens from and saves
+/// them to.
+#[async_trait]
+pub trait TokenStorage: Send + Sync {
+    /// Load the tokens, if any.
+    async fn load(&self) -> Result<Option<Tokens>>;
+
+    /// Save the given tokens.
+    async fn save(&self, tokens: &Tokens) -> Result<()>;
+}
+
+/// The in-memory token storage.
+///
+/// Tokens are lost when the storage is dropped.
+#[derive(Debug, Default)]
+pub struct MemoryTokenStorage(StdMutex<Option<Tokens>>);
+
+impl MemoryTokenStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TokenStorage for MemoryTokenStorage {
+    async fn load(&self) -> Result<Option<Tokens>> {
+        let tokens = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        Ok(tokens.clone())
+    }
+
+    async fn save(&self, tokens: &Tokens) -> Result<()> {
+        let mut prev = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        *prev = Some(tokens.clone());
+        Ok(())
+    }
+}
+
+/// The secret token storage.
+///
+/// Tokens are loaded from secrets, and saved back to them when they
+/// are keyring entries. The expiration time of the access token is
+/// stored as a UNIX timestamp in seconds.
+///
+/// See [secret-lib](https://crates.io/crates/secret-lib).
+#[cfg(feature = "secret")]
+#[derive(Clone, Debug, Default)]
+pub struct SecretTokenStorage {
+    /// The access token secret.
+    pub access_token: secret::Secret,
+
+    /// The refresh token secret.
+    pub refresh_token: secret::Secret,
+
+    /// The expiration time secret.
+    ///
+    /// When empty, the expiration time is unknown until the first
+    /// refresh.
+    pub expires_at: secret::Secret,
+}
+
+#[cfg(feature = "secret")]
+impl SecretTokenStorage {
+    pub fn new(access_token: secret::Secret, refresh_token: secret::Secret) -> Self {
+        Self {
+            access_token,
+            refresh_token,
+            expires_at: Default::default(),
+        }
+    }
+
+    /// Set the expiration time secret, using the builder pattern.
+    pub fn with_expires_at(mut self, expires_at: secret::Secret) -> Self {
+        self.expires_at = expires_at;
+        self
+    }
+}
+
+#[cfg(feature = "secret")]
+#[async_trait]
+impl TokenStorage for SecretTokenStorage {
+    async fn load(&self) -> Result<Option<Tokens>> {
+        let Some(access_token) = self
+            .access_token
+            .find()
+            .await
+            .map_err(|err| Error::LoadTokensError(Box::new(err)))?
+        else {
+            return Ok(None);
+        };
+
+        let refresh_token = self
+            .refresh_token
+            .find()
+            .await
+            .map_err(|err| Error::LoadTokensError(Box::new(err)))?;
+
+        let expires_at = self
+            .expires_at
+            .find()
+            .await
+            .map_err(|err| Error::LoadTokensError(Box::new(err)))?
+            .and_then(|secs| match secs.trim() {
+                "" => None,
+                secs => match secs.parse() {
+                    Ok(secs) => Some(SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
+                    Err(err) => {
+                        debug!(secs, ?err, "ignoring invalid oauth2 expiration time");
+                        None
+                    }
+                },
+            });
+
+        let mut tokens = Tokens::new(access_token).with_refresh_token(refresh_token);
+        tokens.expires_at = expires_at;
+
+        Ok(Some(tokens))
+    }
+
+    async fn save(&self, tokens: &Tokens) -> Result<()> {
+        self.access_token
+            .set_if_keyring(&tokens.access_token)
+            .await
+            .map_err(|err| Error::SaveTokensError(Box::new(err)))?;
+
+        if let Some(refresh_token) = &tokens.refresh_token {
+            self.refresh_token
+                .set_if_keyring(refresh_token)
+                .await
+                .map_err(|err| Error::SaveTokensError(Box::new(err)))?;
+        }
+
+        // an unknown expiration time is saved as an empty value, so
+        // that the one of the previous access token does not apply
+        let expires_at = tokens
+            .expires_at
+            .and_then(|expires_at| expires_at.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|expires_at| expires_at.as_secs().to_string())
+            .unwrap_or_default();
+
+        self.expires_at
+            .set_if_keyring(expires_at)
+            .await
+            .map_err(|err| Error::SaveTokensError(Box::new(err)))?;
+
+        Ok(())
+    }
+}
+
+/// The token manager.
+///
+/// The manager keeps track of the last known tokens, persists them
+/// using its [`TokenStorage`], and refreshes the access token
+/// before it expires. Refreshes are serialized, so that concurrent
+/// consumers never refresh the same token twice.
+pub struct TokenManager {
+    client: Client,
+    storage: Box<dyn TokenStorage>,
+    refresh_margin: Duration,
+    tokens: Mutex<Option<Tokens>>,
+}
+
+impl TokenManager {
+    /// Create a new token manager from the given client and storage.
+    pub fn new(client: Client, storage: impl TokenStorage + 'static) -> Self {
+        Self {
+            client,
+            storage: Box::new(storage),
+            refresh_margin: DEFAULT_REFRESH_MARGIN,
+            tokens: Mutex::new(None),
+        }
+    }
+
+    /// Set the delay before expiry from which the access token gets
+    /// refreshed.
+    pub fn set_refresh_margin(&mut self, margin: Duration) {
+        self.refresh_margin = margin;
+    }
+
+    /// Set the delay before expiry from which the access token gets
+    /// refreshed, using the builder pattern.
+    pub fn with_refresh_margin(mut self, margin: Duration) -> Self {
+        self.set_refresh_margin(margin);
+        self
+    }
+
+    /// Save the given tokens, usually obtained from the
+    /// [`AuthorizationCodeGrant`](super::AuthorizationCodeGrant)
+    /// flow.
+    pub async fn set_tokens(&self, tokens: Tokens) -> Result<()> {
+        let mut current = self.tokens.lock().await;
+        self.storage.save(&tokens).await?;
+        *current = Some(tokens);
+        Ok(())
+    }
+
+    /// Return the last known tokens, loading them from the storage
+    /// if needed.
+    pub async fn tokens(&self) -> Result<Tokens> {
+        let mut current = self.tokens.lock().await;
+        Ok(self.load(&mut current).await?.clone())
+    }
+
+    /// Return a valid access token.
+    ///
+    /// The access token is refreshed first if it expires within the
+    /// refresh margin.
+    pub async fn access_token(&self) -> Result<String> {
+        let mut current = self.tokens.lock().await;
+        let tokens = self.load(&mut current).await?;
+
+        if tokens.expires_within(self.refresh_margin) {
+            debug!("oauth2 access token about to expire, refreshing it");
+            return self.refresh(&mut current).await;
+        }
+
+        Ok(tokens.access_token.clone())
+    }
+
+    /// Refresh the access token, typically after the server rejected
+    /// it.
+    ///
+    /// When the given stale access token does not match the last
+    /// known access token anymore, another consumer already
+    /// refreshed it: the last known access token is returned instead
+    /// of requesting a new one. This prevents consumers from
+    /// invalidating each other's refresh token when the provider
+    /// rotates them.
+    pub async fn refresh_access_token(&self, stale_access_token: Option<&str>) -> Result<String> {
+        let mut current = self.tokens.lock().await;
+        let tokens = self.load(&mut current).await?;
+
+        if let Some(stale) = stale_access_token {
+            if stale != tokens.access_token {
+                debug!("oauth2 access token already refreshed, skipping refresh");
+                return Ok(tokens.access_token.clone());
+            }
+        }
+
+        self.refresh(&mut current).await
+    }
+
+    async fn load<'a>(&self, current: &'a mut Option<Tokens>) -> Result<&'a Tokens> {
+        if current.is_none() {
+            *current = self.storage.load().await?;
+        }
+
+        current.as_ref().ok_or(Error::MissingTokensError)
+    }
+
+    async fn refresh(&self, current: &mut Option<Tokens>) -> Result<String> {
+        let prev = current.as_ref().ok_or(Error::MissingTokensError)?;
+        let refresh_token = prev
+            .refresh_token
+            .clone()
+            .ok_or(Error::MissingRefreshTokenError)?;
+
+        let mut tokens = RefreshAccessToken::new()
+            .refresh_tokens(&self.client, &refresh_token)
+            .await?;
+
+        // providers may not rotate refresh tokens
+        if tokens.refresh_token.is_none() {
+            tokens.refresh_token = Some(refresh_token);
+        }
+
+        self.storage.save(&tokens).await?;
+
+        let access_token = tokens.access_token.clone();
+        *current = Some(tokens);
+        Ok(access_token)
+    }
+}
+
+impl fmt::Debug for TokenManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenManager")
+            .field("client", &self.client)
+            .field("refresh_margin", &self.refresh_margin)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{MemoryTokenStorage, TokenManager, TokenStorage, Tokens};
+    use crate::v2_0::{Client, Error};
+
+    fn client() -> Client {
+        Client::new(
+            "client-id",
+            None::<String>,
+            "https://localhost/auth",
+            "https://localhost/token",
+            "http",
+            "localhost",
+            9999u16,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn access_token() {
+        let storage = MemoryTokenStorage::new();
+        let tokens = Tokens::new("access")
+            .with_refresh_token(Some("refresh"))
+            .with_expires_in(Some(Duration::from_secs(3600)));
+        storage.save(&tokens).await.unwrap();
+
+        let manager = TokenManager::new(client(), storage);
+
+        assert_eq!(manager.access_token().await.unwrap(), "access");
+        assert_eq!(
+            manager.refresh_access_token(Some("stale")).await.unwrap(),
+            "access"
+        );
+    }
+
+    #[tokio::test]
+    async fn missing_tokens() {
+        let manager = TokenManager::new(client(), MemoryTokenStorage::new());
+
+        assert!(matches!(
+            manager.access_token().await,
+            Err(Error::MissingTokensError)
+        ));
+
+        manager.set_tokens(Tokens::new("access")).await.unwrap();
+
+        assert!(matches!(
+            manager.refresh_access_token(None).await,
+            Err(Error::MissingRefreshTokenError)
+        ));
+    }
+
+    #[cfg(feature = "secret")]
+    #[tokio::test]
+    async fn secret_storage_expires_at() {
+        use std::time::{Duration, UNIX_EPOCH};
+
+        use secret::Secret;
+
+        use super::SecretTokenStorage;
+
+        let storage =
+            SecretTokenStorage::new(Secret::new_raw("access"), Secret::new_raw("refresh"))
+                .with_expires_at(Secret::new_raw("1700000000"));
+
+        let tokens = storage.load().await.unwrap().unwrap();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.refresh_token.as_deref(), Some("refresh"));
+        assert_eq!(
+            tokens.expires_at,
+            Some(UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+
+        let storage = storage.with_expires_at(Secret::new_raw("invalid"));
+        let tokens = storage.load().await.unwrap().unwrap();
+        assert_eq!(tokens.expires_at, None);
+    }
+
+    #[test]
+    fn expires_within() {
+        let tokens = Tokens::new("access");
+        assert!(!tokens.expires_within(Duration::from_secs(60)));
+
+        let tokens = tokens.with_expires_in(Some(Duration::from_secs(30)));
+        assert!(tokens.expires_within(Duration::from_secs(60)));
+        assert!(!tokens.expires_within(Duration::from_secs(0)));
+    }
+}
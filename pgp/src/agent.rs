@@ -0,0 +1,797 @@
+//! # Agent
+//!
+//! Module dedicated to gpg-agent. Private key operations are
+//! delegated to the agent using the Assuan protocol, so that secret
+//! keys never leave the agent or the OpenPGP card (YubiKey,
+//! Nitrokey…) it manages through scdaemon. This module exposes two
+//! functions [`sign`] and [`decrypt`], working like their secret key
+//! counterparts except that they take the public key of the secret
+//! key held by the agent.
+//!
+//! Keys stored on a card are known by the agent once `gpg
+//! --card-status` has been run at least once.
+
+use std::{
+    cell::RefCell,
+    env,
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Cursor, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    process::Command,
+};
+
+use rand::{CryptoRng, Rng};
+use tracing::debug;
+
+use crate::{
+    native::{
+        self,
+        crypto::{
+            aes_kw, checksum, ecdh::build_ecdh_param, hash::HashAlgorithm,
+            public_key::PublicKeyAlgorithm, sym::SymmetricKeyAlgorithm,
+        },
+        packet::{PublicKey, PublicSubkey},
+        types::{
+            EcdsaPublicParams, KeyId, KeyTrait, Mpi, PublicKeyTrait, PublicParams, SecretKeyRepr,
+            SecretKeyTrait, Tag,
+        },
+        Deserializable, Esk, Message, SignedPublicKey,
+    },
+    utils::spawn_blocking,
+    Error, Result,
+};
+
+/// The maximum length of an Assuan line, including the line feed.
+const MAX_LINE_LEN: usize = 1000;
+
+/// Signs given bytes using the secret key held by gpg-agent.
+///
+/// The agent socket is discovered using `gpgconf` when not given.
+pub async fn sign(
+    pkey: SignedPublicKey,
+    socket: Option<PathBuf>,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    spawn_blocking(move || {
+        let key = find_key(&pkey, |key| key.is_signing_key())
+            .ok_or(Error::FindSignedSecretKeyForSigningError)?;
+
+        let mut agent = Agent::connect(socket)?;
+        let keygrip = agent.find_keygrip(key.public_params())?;
+        let skey = AgentSecretKey {
+            key,
+            keygrip,
+            agent: RefCell::new(agent),
+        };
+
+        let msg = Message::new_literal_bytes("", &plain_bytes)
+            .sign(&skey, String::new, HashAlgorithm::SHA2_256)
+            .map_err(Error::SignMessageError)?;
+
+        let signature_bytes = msg
+            .into_signature()
+            .to_armored_bytes(None)
+            .map_err(Error::ExportSignedMessageToArmoredBytesError)?;
+
+        Ok(signature_bytes)
+    })
+    .await?
+}
+
+/// Decrypts bytes using the secret key held by gpg-agent.
+///
+/// The agent socket is discovered using `gpgconf` when not given.
+pub async fn decrypt(
+    pkey: SignedPublicKey,
+    socket: Option<PathBuf>,
+    encrypted_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    spawn_blocking(move || {
+        let (msg, _) = Message::from_armor_single(Cursor::new(&encrypted_bytes))
+            .map_err(Error::ImportMessageFromArmorError)?;
+
+        let Message::Encrypted { esk, edata } = msg else {
+            return Err(Error::DecryptMessageError(native::errors::Error::Message(
+                "message is not encrypted".into(),
+            )));
+        };
+
+        let mut agent = Agent::connect(socket)?;
+
+        let mut session_key = None;
+
+        for esk in &esk {
+            let Esk::PublicKeyEncryptedSessionKey(esk) = esk else {
+                continue;
+            };
+
+            let key = find_key(&pkey, |key| {
+                key.is_encryption_key()
+                    && (esk.id().as_ref().iter().all(|b| *b == 0) || key.key_id() == *esk.id())
+            });
+
+            let Some(key) = key else {
+                continue;
+            };
+
+            let keygrip = agent.find_keygrip(key.public_params())?;
+            session_key = Some(agent.decrypt_session_key(&key, &keygrip, esk.mpis())?);
+            break;
+        }
+
+        let (alg, session_key) = session_key.ok_or(Error::DecryptMessageError(
+            native::errors::Error::MissingKey,
+        ))?;
+
+        let mut msgs = Vec::new();
+
+        for edata in &edata {
+            let mut data = edata.data().to_vec();
+
+            let decrypted = if edata.tag() == Tag::SymEncryptedProtectedData {
+                alg.decrypt_protected(&session_key, &mut data)
+            } else {
+                alg.decrypt(&session_key, &mut data)
+            };
+
+            let decrypted = decrypted.map_err(Error::DecryptMessageError)?.to_vec();
+
+            for msg in Message::from_bytes_many(Cursor::new(decrypted)) {
+                msgs.push(msg.map_err(Error::DecryptMessageError)?);
+            }
+        }
+
+        let msg = msgs.into_iter().next().ok_or(Error::GetMessageEmptyError)?;
+        let msg = msg.decompress().map_err(Error::DecompressMessageError)?;
+
+        let plain_bytes = msg
+            .get_content()
+            .map_err(Error::GetMessageContentError)?
+            .ok_or(Error::GetMessageContentEmptyError)?;
+
+        Ok(plain_bytes)
+    })
+    .await?
+}
+
+/// Find the path of the gpg-agent socket.
+///
+/// Asks `gpgconf` first, then falls back to the default socket of the
+/// GnuPG home directory.
+pub fn find_socket_path() -> Result<PathBuf> {
+    let output = Command::new("gpgconf")
+        .args(["--list-dirs", "agent-socket"])
+        .output();
+
+    match output {
+        Ok(output) if output.status.success() => {
+            let path = unescape(String::from_utf8_lossy(&output.stdout).trim().as_bytes());
+            if !path.is_empty() {
+                return Ok(PathBuf::from(String::from_utf8_lossy(&path).into_owned()));
+            }
+        }
+        Ok(output) => {
+            debug!(status = ?output.status, "cannot get gpg-agent socket from gpgconf");
+        }
+        Err(err) => {
+            debug!(?err, "cannot get gpg-agent socket from gpgconf");
+        }
+    }
+
+    let home = env::var_os("GNUPGHOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".gnupg")))
+        .ok_or(Error::FindAgentSocketError)?;
+
+    Ok(home.join("S.gpg-agent"))
+}
+
+/// A primary key or a subkey of a public key.
+#[derive(Clone, Copy, Debug)]
+enum AgentKey<'a> {
+    Key(&'a PublicKey),
+    Subkey(&'a PublicSubkey),
+}
+
+impl AgentKey<'_> {
+    fn public_params(&self) -> &PublicParams {
+        match self {
+            Self::Key(k) => k.public_params(),
+            Self::Subkey(k) => k.public_params(),
+        }
+    }
+}
+
+impl KeyTrait for AgentKey<'_> {
+    fn fingerprint(&self) -> Vec<u8> {
+        match self {
+            Self::Key(k) => k.fingerprint(),
+            Self::Subkey(k) => k.fingerprint(),
+        }
+    }
+
+    fn key_id(&self) -> KeyId {
+        match self {
+            Self::Key(k) => k.key_id(),
+            Self::Subkey(k) => k.key_id(),
+        }
+    }
+
+    fn algorithm(&self) -> PublicKeyAlgorithm {
+        match self {
+            Self::Key(k) => k.algorithm(),
+            Self::Subkey(k) => k.algorithm(),
+        }
+    }
+}
+
+/// Find the subkey or the primary key matching the given predicate.
+///
+/// The primary key is tried first, like for secret keys.
+fn find_key(pkey: &SignedPublicKey, pred: impl Fn(&AgentKey) -> bool) -> Option<AgentKey<'_>> {
+    [AgentKey::Key(&pkey.primary_key)]
+        .into_iter()
+        .chain(
+            pkey.public_subkeys
+                .iter()
+                .map(|subkey| AgentKey::Subkey(&subkey.key)),
+        )
+        .find(pred)
+}
+
+/// A secret key held by gpg-agent.
+///
+/// The key can only be used for signing, the passphrase being asked
+/// by the agent itself using pinentry.
+#[derive(Debug)]
+struct AgentSecretKey<'a> {
+    key: AgentKey<'a>,
+    keygrip: String,
+    agent: RefCell<Agent>,
+}
+
+impl KeyTrait for AgentSecretKey<'_> {
+    fn fingerprint(&self) -> Vec<u8> {
+        self.key.fingerprint()
+    }
+
+    fn key_id(&self) -> KeyId {
+        self.key.key_id()
+    }
+
+    fn algorithm(&self) -> PublicKeyAlgorithm {
+        self.key.algorithm()
+    }
+}
+
+impl PublicKeyTrait for AgentSecretKey<'_> {
+    fn verify_signature(
+        &self,
+        hash: HashAlgorithm,
+        data: &[u8],
+        sig: &[Mpi],
+    ) -> native::errors::Result<()> {
+        match self.key {
+            AgentKey::Key(k) => k.verify_signature(hash, data, sig),
+            AgentKey::Subkey(k) => k.verify_signature(hash, data, sig),
+        }
+    }
+
+    fn encrypt<R: CryptoRng + Rng>(
+        &self,
+        rng: &mut R,
+        plain: &[u8],
+    ) -> native::errors::Result<Vec<Mpi>> {
+        match self.key {
+            AgentKey::Key(k) => k.encrypt(rng, plain),
+            AgentKey::Subkey(k) => k.encrypt(rng, plain),
+        }
+    }
+
+    fn to_writer_old(&self, writer: &mut impl io::Write) -> native::errors::Result<()> {
+        match self.key {
+            AgentKey::Key(k) => k.to_writer_old(writer),
+            AgentKey::Subkey(k) => k.to_writer_old(writer),
+        }
+    }
+}
+
+impl<'a> SecretKeyTrait for AgentSecretKey<'a> {
+    type PublicKey = AgentKey<'a>;
+
+    fn unlock<F, G>(&self, _pw: F, _work: G) -> native::errors::Result<()>
+    where
+        F: FnOnce() -> String,
+        G: FnOnce(&SecretKeyRepr) -> native::errors::Result<()>,
+    {
+        Err(native::errors::Error::Unimplemented(
+            "secret keys held by gpg-agent cannot be unlocked".into(),
+        ))
+    }
+
+    fn create_signature<F>(
+        &self,
+        _key_pw: F,
+        hash: HashAlgorithm,
+        digest: &[u8],
+    ) -> native::errors::Result<Vec<Mpi>>
+    where
+        F: FnOnce() -> String,
+    {
+        let algo = match hash {
+            HashAlgorithm::MD5 => 1,
+            HashAlgorithm::SHA1 => 2,
+            HashAlgorithm::RIPEMD160 => 3,
+            HashAlgorithm::SHA2_256 => 8,
+            HashAlgorithm::SHA2_384 => 9,
+            HashAlgorithm::SHA2_512 => 10,
+            HashAlgorithm::SHA2_224 => 11,
+            HashAlgorithm::SHA3_256 => 313,
+            HashAlgorithm::SHA3_512 => 315,
+            hash => {
+                let err = format!("hash algorithm {hash:?} for gpg-agent");
+                return Err(native::errors::Error::Unimplemented(err));
+            }
+        };
+
+        let mut agent = self.agent.borrow_mut();
+        let sig = agent
+            .sign_digest(&self.keygrip, algo, digest)
+            .map_err(|err| native::errors::Error::Message(err.to_string()))?;
+
+        let mpis = match self.algorithm() {
+            PublicKeyAlgorithm::RSA | PublicKeyAlgorithm::RSASign => vec![sig.value(b"s")],
+            _ => vec![sig.value(b"r"), sig.value(b"s")],
+        };
+
+        mpis.into_iter()
+            .map(|mpi| mpi.map(Mpi::from_raw_slice))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| native::errors::Error::Message("invalid gpg-agent signature".into()))
+    }
+
+    fn public_key(&self) -> Self::PublicKey {
+        self.key
+    }
+}
+
+/// The response of an Assuan command.
+#[derive(Debug, Default)]
+struct Response {
+    data: Vec<u8>,
+    status: Vec<Vec<u8>>,
+}
+
+/// A connection to gpg-agent.
+#[derive(Debug)]
+struct Agent {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Agent {
+    fn connect(socket: Option<PathBuf>) -> Result<Self> {
+        let path = match socket {
+            Some(path) => path,
+            None => find_socket_path()?,
+        };
+
+        debug!(?path, "connecting to gpg-agent");
+
+        let stream =
+            UnixStream::connect(&path).map_err(|err| Error::ConnectAgentError(err, path))?;
+        let writer = stream.try_clone().map_err(Error::WriteAgentError)?;
+
+        let mut agent = Self {
+            reader: BufReader::new(stream),
+            writer,
+        };
+
+        // greeting
+        agent.read_response("", None)?;
+
+        // forward the session so that pinentry shows up at the right
+        // place
+        for (var, option) in [
+            ("GPG_TTY", "ttyname"),
+            ("TERM", "ttytype"),
+            ("DISPLAY", "display"),
+        ] {
+            if let Ok(value) = env::var(var) {
+                agent.transact(&format!("OPTION {option}={value}"), None)?;
+            }
+        }
+
+        Ok(agent)
+    }
+
+    /// Send the given command and read its response.
+    ///
+    /// Inquiries matching the given keyword are answered with the
+    /// given data, the other ones are answered with no data.
+    fn transact(&mut self, cmd: &str, inquire: Option<(&str, &[u8])>) -> Result<Response> {
+        writeln!(self.writer, "{cmd}").map_err(Error::WriteAgentError)?;
+        self.read_response(cmd, inquire)
+    }
+
+    fn read_response(&mut self, cmd: &str, inquire: Option<(&str, &[u8])>) -> Result<Response> {
+        let mut res = Response::default();
+
+        loop {
+            let mut line = Vec::new();
+            let n = self
+                .reader
+                .read_until(b'\n', &mut line)
+                .map_err(Error::ReadAgentError)?;
+
+            if n == 0 {
+                let err = io::Error::from(io::ErrorKind::UnexpectedEof);
+                return Err(Error::ReadAgentError(err));
+            }
+
+            if line.ends_with(b"\n") {
+                line.pop();
+            }
+
+            if line == b"OK" || line.starts_with(b"OK ") {
+                return Ok(res);
+            }
+
+            if let Some(err) = line.strip_prefix(b"ERR ") {
+                let err = String::from_utf8_lossy(err).into_owned();
+                return Err(Error::AgentCommandError(err, cmd.to_owned()));
+            }
+
+            if let Some(data) = line.strip_prefix(b"D ") {
+                res.data.extend(unescape(data));
+            } else if let Some(status) = line.strip_prefix(b"S ") {
+                res.status.push(unescape(status));
+            } else if let Some(keyword) = line.strip_prefix(b"INQUIRE ") {
+                let keyword = keyword.split(|b| *b == b' ').next().unwrap_or_default();
+
+                match inquire {
+                    Some((expected, data)) if expected.as_bytes() == keyword => {
+                        self.send_data(data)?;
+                    }
+                    _ => {
+                        writeln!(self.writer, "END").map_err(Error::WriteAgentError)?;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send the given data as an inquiry answer.
+    fn send_data(&mut self, data: &[u8]) -> Result<()> {
+        let mut line = b"D ".to_vec();
+
+        for byte in data {
+            match byte {
+                b'%' | b'\r' | b'\n' => line.extend(format!("%{byte:02X}").as_bytes()),
+                byte => line.push(*byte),
+            }
+
+            if line.len() >= MAX_LINE_LEN - 4 {
+                line.push(b'\n');
+                self.writer
+                    .write_all(&line)
+                    .map_err(Error::WriteAgentError)?;
+                line = b"D ".to_vec();
+            }
+        }
+
+        if line.len() > 2 {
+            line.push(b'\n');
+            self.writer
+                .write_all(&line)
+                .map_err(Error::WriteAgentError)?;
+        }
+
+        writeln!(self.writer, "END").map_err(Error::WriteAgentError)
+    }
+
+    /// Find the keygrip of the agent key matching the given public
+    /// params.
+    fn find_keygrip(&mut self, params: &PublicParams) -> Result<String> {
+        let (algo, expected) = match params {
+            PublicParams::RSA { n, .. } => (b"n", n),
+            PublicParams::ECDH { p, .. } => (b"q", p),
+            PublicParams::EdDSA { q, .. } => (b"q", q),
+            PublicParams::ECDSA(EcdsaPublicParams::P256 { p, .. })
+            | PublicParams::ECDSA(EcdsaPublicParams::P384 { p, .. })
+            | PublicParams::ECDSA(EcdsaPublicParams::Unsupported { p, .. }) => (b"q", p),
+            params => {
+                let err = format!("{params:?}");
+                return Err(Error::UnsupportedAgentKeyAlgorithmError(err));
+            }
+        };
+
+        let keys = self.transact("KEYINFO --list", None)?;
+
+        for status in keys.status {
+            // S KEYINFO <keygrip> <type> …
+            let mut fields = status.split(|b| *b == b' ');
+            let (Some(b"KEYINFO"), Some(keygrip)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+
+            let keygrip = String::from_utf8_lossy(keygrip).into_owned();
+
+            let key = match self.transact(&format!("READKEY {keygrip}"), None) {
+                Ok(key) => key,
+                Err(err) => {
+                    debug!(keygrip, ?err, "cannot read gpg-agent key, skipping it");
+                    continue;
+                }
+            };
+
+            let matches = Sexp::parse(&key.data)
+                .and_then(|key| key.value(algo).map(Mpi::from_raw_slice))
+                .is_some_and(|mpi| mpi.as_bytes() == Mpi::from_raw_slice(expected).as_bytes());
+
+            if matches {
+                debug!(keygrip, "found gpg-agent key");
+                return Ok(keygrip);
+            }
+        }
+
+        Err(Error::FindAgentKeyError)
+    }
+
+    /// Sign the given digest using the key of the given keygrip.
+    fn sign_digest(&mut self, keygrip: &str, algo: u16, digest: &[u8]) -> Result<Sexp> {
+        self.transact(&format!("SIGKEY {keygrip}"), None)?;
+        self.transact(&format!("SETHASH {algo} {}", to_hex(digest)), None)?;
+        let sig = self.transact("PKSIGN", None)?;
+        Sexp::parse(&sig.data).ok_or(Error::ParseAgentResponseError)
+    }
+
+    /// Decrypt the session key encrypted for the given key.
+    ///
+    /// Returns the symmetric algorithm and the session key.
+    fn decrypt_session_key(
+        &mut self,
+        key: &AgentKey,
+        keygrip: &str,
+        mpis: &[Mpi],
+    ) -> Result<(SymmetricKeyAlgorithm, Vec<u8>)> {
+        let invalid = || Error::DecryptMessageError(native::errors::Error::InvalidInput);
+
+        let mut ciphertext = b"(7:enc-val".to_vec();
+
+        match key.public_params() {
+            PublicParams::RSA { .. } => {
+                ciphertext.extend(b"(3:rsa(1:a");
+                push_atom(&mut ciphertext, mpis.first().ok_or_else(invalid)?);
+                ciphertext.extend(b"))");
+            }
+            PublicParams::ECDH { .. } => {
+                let [point, _, wrapped] = mpis else {
+                    return Err(invalid());
+                };
+
+                ciphertext.extend(b"(4:ecdh(1:s");
+                push_atom(&mut ciphertext, wrapped);
+                ciphertext.extend(b")(1:e");
+                push_atom(&mut ciphertext, point);
+                ciphertext.extend(b"))");
+            }
+            params => {
+                let err = format!("{params:?}");
+                return Err(Error::UnsupportedAgentKeyAlgorithmError(err));
+            }
+        }
+
+        ciphertext.push(b')');
+
+        self.transact(&format!("SETKEY {keygrip}"), None)?;
+        let res = self.transact("PKDECRYPT", Some(("CIPHERTEXT", &ciphertext)))?;
+
+        let res = Sexp::parse(&res.data).ok_or(Error::ParseAgentResponseError)?;
+        let value = res.value(b"value").ok_or(Error::ParseAgentResponseError)?;
+
+        let frame = match key.public_params() {
+            PublicParams::ECDH {
+                curve,
+                hash,
+                alg_sym,
+                ..
+            } => {
+                // the agent returns the shared point, the session key
+                // still needs to be unwrapped as defined in RFC 6637
+                let x = match value.split_first() {
+                    Some((0x04, xy)) => &xy[..xy.len() / 2],
+                    Some((_, x)) => x,
+                    None => return Err(invalid()),
+                };
+
+                let param = build_ecdh_param(&curve.oid(), *alg_sym, *hash, &key.fingerprint());
+                let mut z = hash
+                    .digest(&[&[0, 0, 0, 1], x, &param].concat())
+                    .map_err(Error::DecryptMessageError)?;
+                z.truncate(alg_sym.key_size());
+
+                let len = mpis[1].first().copied().unwrap_or_default() as usize;
+                let wrapped = mpis[2].as_bytes();
+                let mut padded = vec![0; len.checked_sub(wrapped.len()).ok_or_else(invalid)?];
+                padded.extend(wrapped);
+
+                let mut frame = aes_kw::unwrap(&z, &padded).map_err(Error::DecryptMessageError)?;
+
+                // PKCS5 unpadding
+                let pad = frame.last().copied().unwrap_or_default() as usize;
+                if !(1..=8).contains(&pad) || pad > frame.len() {
+                    return Err(invalid());
+                }
+                frame.truncate(frame.len() - pad);
+                frame
+            }
+            _ if res.value(b"padding") == Some(b"0") => value.to_vec(),
+            _ => unpad_pkcs1(value).ok_or_else(invalid)?.to_vec(),
+        };
+
+        // algorithm (1 byte) + session key + checksum (2 bytes)
+        let (alg, key) = frame.split_first().ok_or_else(invalid)?;
+        let alg = sym_algorithm(*alg).ok_or_else(invalid)?;
+
+        if key.len() != alg.key_size() + 2 {
+            return Err(invalid());
+        }
+
+        let (key, sum) = key.split_at(alg.key_size());
+        checksum::simple(sum, key).map_err(Error::DecryptMessageError)?;
+
+        Ok((alg, key.to_vec()))
+    }
+}
+
+/// A canonical S-expression, as used by libgcrypt.
+#[derive(Debug, Eq, PartialEq)]
+enum Sexp {
+    Atom(Vec<u8>),
+    List(Vec<Sexp>),
+}
+
+impl Sexp {
+    fn parse(input: &[u8]) -> Option<Self> {
+        Self::parse_partial(input).map(|(sexp, _)| sexp)
+    }
+
+    fn parse_partial(input: &[u8]) -> Option<(Self, &[u8])> {
+        match input.first()? {
+            b'(' => {
+                let mut items = Vec::new();
+                let mut input = &input[1..];
+
+                loop {
+                    if let Some(input) = input.strip_prefix(b")") {
+                        return Some((Self::List(items), input));
+                    }
+
+                    let (item, rest) = Self::parse_partial(input)?;
+                    items.push(item);
+                    input = rest;
+                }
+            }
+            b'0'..=b'9' => {
+                let colon = input.iter().position(|b| *b == b':')?;
+                let len: usize = std::str::from_utf8(&input[..colon]).ok()?.parse().ok()?;
+                let end = colon.checked_add(1 + len)?;
+                let atom = input.get(colon + 1..end)?.to_vec();
+                Some((Self::Atom(atom), &input[end..]))
+            }
+            _ => None,
+        }
+    }
+
+    /// Find the value of the first list named after the given token,
+    /// like `(token value)`.
+    fn value(&self, token: &[u8]) -> Option<&[u8]> {
+        let Self::List(items) = self else {
+            return None;
+        };
+
+        match items.as_slice() {
+            [Self::Atom(name), Self::Atom(value), ..] if name == token => Some(value),
+            items => items.iter().find_map(|item| item.value(token)),
+        }
+    }
+}
+
+/// Append the given integer as a canonical S-expression atom.
+///
+/// A leading zero is added when the high bit is set, so that the
+/// integer is not read as a negative one.
+fn push_atom(sexp: &mut Vec<u8>, mpi: &Mpi) {
+    let bytes = mpi.as_bytes();
+    let zero = bytes.first().is_some_and(|b| b & 0x80 != 0);
+    let len = bytes.len() + zero as usize;
+
+    sexp.extend(format!("{len}:").as_bytes());
+    if zero {
+        sexp.push(0);
+    }
+    sexp.extend(bytes);
+}
+
+/// Strip the PKCS#1 v1.5 encryption padding of the given frame.
+fn unpad_pkcs1(frame: &[u8]) -> Option<&[u8]> {
+    let frame = frame.strip_prefix(&[0]).unwrap_or(frame);
+    let frame = frame.strip_prefix(&[2])?;
+    let sep = frame.iter().position(|b| *b == 0)?;
+    Some(&frame[sep + 1..])
+}
+
+fn sym_algorithm(alg: u8) -> Option<SymmetricKeyAlgorithm> {
+    use SymmetricKeyAlgorithm::*;
+
+    [
+        IDEA,
+        TripleDES,
+        CAST5,
+        Blowfish,
+        AES128,
+        AES192,
+        AES256,
+        Twofish,
+        Camellia128,
+        Camellia192,
+        Camellia256,
+    ]
+    .into_iter()
+    .find(|sym| *sym as u8 == alg)
+}
+
+fn to_hex(data: &[u8]) -> String {
+    data.iter().fold(String::new(), |mut hex, b| {
+        let _ = write!(hex, "{b:02X}");
+        hex
+    })
+}
+
+/// Decode the percent-escaped bytes of an Assuan line.
+fn unescape(line: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(line.len());
+    let mut i = 0;
+
+    while i < line.len() {
+        let hex = line.get(i + 1..i + 3).and_then(|hex| {
+            let hex = std::str::from_utf8(hex).ok()?;
+            u8::from_str_radix(hex, 16).ok()
+        });
+
+        match (line[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{unescape, unpad_pkcs1, Sexp};
+
+    #[test]
+    fn parse_agent_responses() {
+        assert_eq!(unescape(b"a%25b%0Ac%2"), b"a%b\nc%2");
+
+        let sig = Sexp::parse(b"(7:sig-val(5:eddsa(1:r2:\x01\x02)(1:s1:\x03)))").unwrap();
+        assert_eq!(sig.value(b"r"), Some(&[1, 2][..]));
+        assert_eq!(sig.value(b"s"), Some(&[3][..]));
+        assert_eq!(sig.value(b"n"), None);
+
+        let res = Sexp::parse(b"(5:value4:\x02\xff\x00\x09)").unwrap();
+        assert_eq!(unpad_pkcs1(res.value(b"value").unwrap()), Some(&[9][..]));
+
+        assert_eq!(Sexp::parse(b"(5:value9:short)"), None);
+    }
+}
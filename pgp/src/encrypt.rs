@@ -1,7 +1,8 @@
 //! # Encrypt
 //!
 //! Module dedicated to PGP encryption. This module exposes a simple
-//! function [`encrypt`] and its associated [`Error`]s.
+//! function [`encrypt`], its password-based variant
+//! [`encrypt_with_password`] and their associated [`Error`]s.
 
 use std::io;
 
@@ -11,7 +12,7 @@
     native::{
         self,
         crypto::{hash::HashAlgorithm, public_key::PublicKeyAlgorithm},
-        types::{CompressionAlgorithm, KeyId, KeyTrait, Mpi, PublicKeyTrait},
+        types::{CompressionAlgorithm, KeyId, KeyTrait, Mpi, PublicKeyTrait, StringToKey},
         Message, SignedPublicKey, SignedPublicSubKey,
     },
     utils::spawn_blocking,
@@ -122,3 +123,32 @@ pub async fn encrypt(pkeys: Vec<SignedPublicKey>, plain_bytes: Vec<u8>) -> Resul
     })
     .await?
 }
+
+/// Encrypts given bytes using the given password.
+///
+/// The message can be decrypted by anyone knowing the password,
+/// without public key.
+pub async fn encrypt_with_password(
+    password: impl ToString,
+    plain_bytes: Vec<u8>,
+) -> Result<Vec<u8>> {
+    let password = password.to_string();
+
+    spawn_blocking(move || {
+        let mut rng = thread_rng();
+
+        let msg = Message::new_literal_bytes("", &plain_bytes);
+        let s2k = StringToKey::new_default(&mut rng);
+
+        let encrypted_bytes = msg
+            .compress(CompressionAlgorithm::ZLIB)
+            .map_err(Error::CompressMessageError)?
+            .encrypt_with_password(&mut rng, s2k, Default::default(), || password)
+            .map_err(Error::EncryptMessageError)?
+            .to_armored_bytes(None)
+            .map_err(Error::ExportEncryptedMessageToArmorError)?;
+
+        Ok(encrypted_bytes)
+    })
+    .await?
+}
@@ -111,6 +111,31 @@ pub enum Error {
     #[error("cannot parse certificate")]
     ParseCertError(#[source] native::errors::Error),
 
+    #[cfg(unix)]
+    #[error("cannot find gpg-agent socket")]
+    FindAgentSocketError,
+    #[cfg(unix)]
+    #[error("cannot connect to gpg-agent at {1}")]
+    ConnectAgentError(#[source] std::io::Error, PathBuf),
+    #[cfg(unix)]
+    #[error("cannot read gpg-agent response")]
+    ReadAgentError(#[source] std::io::Error),
+    #[cfg(unix)]
+    #[error("cannot send gpg-agent command")]
+    WriteAgentError(#[source] std::io::Error),
+    #[cfg(unix)]
+    #[error("gpg-agent command {1} failed: {0}")]
+    AgentCommandError(String, String),
+    #[cfg(unix)]
+    #[error("cannot parse gpg-agent response")]
+    ParseAgentResponseError,
+    #[cfg(unix)]
+    #[error("cannot find gpg-agent key matching pgp public key")]
+    FindAgentKeyError,
+    #[cfg(unix)]
+    #[error("cannot use gpg-agent key with unsupported params {0}")]
+    UnsupportedAgentKeyAlgorithmError(String),
+
     #[cfg(feature = "tokio")]
     #[error(transparent)]
     JoinError(#[from] tokio::task::JoinError),
@@ -1,6 +1,8 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![doc = include_str!("../README.md")]
 
+#[cfg(unix)]
+pub mod agent;
 pub mod decrypt;
 pub mod encrypt;
 mod error;
@@ -15,7 +17,7 @@
 #[doc(inline)]
 pub use crate::{
     decrypt::decrypt,
-    encrypt::encrypt,
+    encrypt::{encrypt, encrypt_with_password},
     error::{Error, Result},
     sign::sign,
     utils::{
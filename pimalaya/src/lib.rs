@@ -0,0 +1,29 @@
+#![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
+#![doc = include_str!("../README.md")]
+
+pub mod prelude;
+
+#[cfg(feature = "email")]
+pub use email;
+#[cfg(feature = "keyring")]
+pub use keyring;
+#[cfg(feature = "mml")]
+pub use mml;
+#[cfg(feature = "oauth")]
+pub use oauth;
+#[cfg(feature = "secret")]
+pub use secret;
+#[cfg(feature = "time")]
+pub use time;
+
+#[cfg(any(
+    all(feature = "tokio", feature = "async-std"),
+    not(any(feature = "tokio", feature = "async-std"))
+))]
+compile_error!("Either feature `tokio` or `async-std` must be enabled for this crate.");
+
+#[cfg(any(
+    all(feature = "rustls", feature = "native-tls"),
+    not(any(feature = "rustls", feature = "native-tls"))
+))]
+compile_error!("Either feature `rustls` or `native-tls` must be enabled for this crate.");
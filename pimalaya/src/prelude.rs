@@ -0,0 +1,34 @@
+//! # Prelude
+//!
+//! Module re-exporting the most commonly used types of the enabled
+//! libraries, so that they can all be imported at once:
+//!
+//! ```rust,ignore
+//! use pimalaya::prelude::*;
+//! ```
+
+#[cfg(feature = "email")]
+#[doc(no_inline)]
+pub use email::{
+    account::config::AccountConfig,
+    backend::{Backend, BackendBuilder},
+    envelope::{Envelope, Envelopes},
+    flag::{Flag, Flags},
+    message::Message,
+    AnyBoxedError, AnyError, AnyResult,
+};
+#[cfg(feature = "keyring")]
+#[doc(no_inline)]
+pub use keyring::KeyringEntry;
+#[cfg(feature = "mml")]
+#[doc(no_inline)]
+pub use mml::{MimeInterpreterBuilder, MmlCompilerBuilder};
+#[cfg(feature = "oauth")]
+#[doc(no_inline)]
+pub use oauth::v2_0::{AuthorizationCodeGrant, Client as OAuthClient, RefreshAccessToken};
+#[cfg(feature = "secret")]
+#[doc(no_inline)]
+pub use secret::Secret;
+#[cfg(feature = "time")]
+#[doc(no_inline)]
+pub use time::timer::{Timer, TimerConfig, TimerEvent, TimerState};
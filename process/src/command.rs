@@ -14,7 +14,7 @@
 use tokio::{io::AsyncWriteExt, process::Command as AsyncCommand};
 use tracing::{debug, info};
 
-use crate::{Error, Output, Result};
+use crate::{CommandReport, Error, Output, Result};
 
 /// The command structure.
 ///
@@ -88,6 +88,15 @@ pub async fn run(&self) -> Result<Output> {
     /// standard input channel then waits for the output on the
     /// standard output channel.
     pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
+        self.run_report_with(input).await?.into_result()
+    }
+
+    /// Run the command with the given input, capturing its exit
+    /// status and its standard error instead of failing.
+    ///
+    /// Only spawning and I/O errors are returned as errors. See
+    /// [`Command::run_with`] for the input and output handling.
+    pub async fn run_report_with(&self, input: impl AsRef<[u8]>) -> Result<CommandReport> {
         info!(cmd = self.inner, "run shell command");
 
         let input = input.as_ref();
@@ -132,21 +141,22 @@ pub async fn run_with(&self, input: impl AsRef<[u8]>) -> Result<Output> {
         #[cfg(feature = "tokio")]
         let output = cmd.wait_with_output().await?;
 
-        let code = output
-            .status
-            .code()
-            .ok_or_else(|| Error::GetExitStatusCodeNotAvailableError(self.to_string()))?;
+        let code = output.status.code();
 
-        if code == 0 {
-            debug!(code, "shell command gracefully exited");
-        } else {
-            let cmd = self.to_string();
-            let err = String::from_utf8_lossy(&output.stderr).to_string();
-            debug!(code, err, "shell command ungracefully exited");
-            return Err(Error::GetExitStatusCodeNonZeroError(cmd, code, err));
+        match code {
+            Some(0) => debug!(code, "shell command gracefully exited"),
+            _ => {
+                let err = String::from_utf8_lossy(&output.stderr);
+                debug!(code, ?err, "shell command ungracefully exited");
+            }
         }
 
-        Ok(Output::from(output.stdout))
+        Ok(CommandReport::new(
+            self.to_string(),
+            code,
+            output.stdout,
+            output.stderr,
+        ))
     }
 }
 
@@ -19,6 +19,8 @@ pub enum Error {
     GetExitStatusCodeNotAvailableError(String),
     #[error("command {0} returned non-zero exit status code {1}: {2}")]
     GetExitStatusCodeNonZeroError(String, i32, String),
+    #[error("cannot run stage {1} of pipeline")]
+    RunPipelineStageError(#[source] Box<Error>, usize),
     #[error("cannot parse command output as string")]
     ParseOutputAsUtf8StringError(#[source] FromUtf8Error),
 
@@ -5,13 +5,15 @@
 mod error;
 mod output;
 mod pipeline;
+mod report;
 
 #[doc(inline)]
 pub use crate::{
     command::Command,
     error::{Error, Result},
     output::Output,
-    pipeline::Pipeline,
+    pipeline::{Pipeline, PipelineFailure},
+    report::{CommandReport, PipelineReport, StageReport},
 };
 
 #[cfg(any(
@@ -10,7 +10,20 @@
 
 use tracing::{debug, info};
 
-use crate::{Command, Output, Result};
+use crate::{Command, Output, PipelineReport, Result, StageReport};
+
+/// The behaviour of a pipeline when one of its stages fails.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PipelineFailure {
+    /// Stop the pipeline at the first failing stage.
+    #[default]
+    Stop,
+
+    /// Skip the failing stage and continue with the next one.
+    ///
+    /// The next stage receives the input the failing stage received.
+    Continue,
+}
 
 /// The command pipeline structure.
 ///
@@ -22,12 +35,38 @@
     derive(serde::Serialize, serde::Deserialize),
     serde(from = "Vec<String>", into = "Vec<String>")
 )]
-pub struct Pipeline(Vec<Command>);
+pub struct Pipeline {
+    /// The inner commands.
+    cmds: Vec<Command>,
+
+    /// The behaviour of the pipeline when a stage fails.
+    ///
+    /// Only used by [`Pipeline::run_report_with`]. Defaults to
+    /// [`PipelineFailure::Stop`].
+    on_failure: PipelineFailure,
+}
 
 impl Pipeline {
     /// Creates a new pipeline from the given iterator.
     pub fn new(cmds: impl IntoIterator<Item = impl ToString>) -> Self {
-        Self(cmds.into_iter().map(Command::new).collect())
+        Self::from(cmds.into_iter().map(Command::new).collect::<Vec<_>>())
+    }
+
+    /// Defines the behaviour of the pipeline when a stage fails.
+    ///
+    /// See [`Pipeline::with_on_failure`] for the builder pattern
+    /// alternative.
+    pub fn set_on_failure(&mut self, on_failure: PipelineFailure) {
+        self.on_failure = on_failure;
+    }
+
+    /// Defines the behaviour of the pipeline when a stage fails,
+    /// using the builder pattern.
+    ///
+    /// See [`Pipeline::set_on_failure`] for the setter alternative.
+    pub fn with_on_failure(mut self, on_failure: PipelineFailure) -> Self {
+        self.set_on_failure(on_failure);
+        self
     }
 
     /// Wrapper around [`alloc::str::replace`].
@@ -65,31 +104,84 @@ pub async fn run_with(&self, input: impl IntoIterator<Item = u8>) -> Result<Outp
 
         Ok(Output::from(output))
     }
+
+    /// Runs the current pipeline without initial input, capturing
+    /// the exit status and the standard error of every stage.
+    ///
+    /// See [`Pipeline::run_report_with`] to run command with output.
+    pub async fn run_report(&self) -> Result<PipelineReport> {
+        self.run_report_with([]).await
+    }
+
+    /// Run the command pipeline with the given initial input,
+    /// capturing the exit status and the standard error of every
+    /// stage.
+    ///
+    /// Failing stages do not make this function fail: they are
+    /// recorded in the returned [`PipelineReport`], and the pipeline
+    /// stops or continues depending on its [`PipelineFailure`]
+    /// behaviour. Only spawning and I/O errors are returned as
+    /// errors.
+    pub async fn run_report_with(
+        &self,
+        input: impl IntoIterator<Item = u8>,
+    ) -> Result<PipelineReport> {
+        info!("run pipeline of {} commands", self.len());
+
+        let mut output: Vec<u8> = input.into_iter().collect();
+        let mut stages = Vec::with_capacity(self.len());
+
+        for (index, cmd) in self.iter().enumerate() {
+            debug!("run command {} from pipeline", index + 1);
+            let report = cmd.run_report_with(&output).await?;
+
+            if report.is_success() {
+                output = report.output().to_vec();
+                stages.push(StageReport { index, report });
+                continue;
+            }
+
+            stages.push(StageReport { index, report });
+
+            if self.on_failure == PipelineFailure::Stop {
+                debug!("stop pipeline at failing command {}", index + 1);
+                let skipped = self.len() - index - 1;
+                return Ok(PipelineReport::new(stages, skipped, output));
+            }
+
+            debug!("skip failing command {} from pipeline", index + 1);
+        }
+
+        Ok(PipelineReport::new(stages, 0, output))
+    }
 }
 
 impl Deref for Pipeline {
     type Target = Vec<Command>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.cmds
     }
 }
 
 impl DerefMut for Pipeline {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.cmds
     }
 }
 
 impl From<Vec<Command>> for Pipeline {
     fn from(cmds: Vec<Command>) -> Self {
-        Self(cmds)
+        Self {
+            cmds,
+            on_failure: PipelineFailure::default(),
+        }
     }
 }
 
 impl From<Vec<String>> for Pipeline {
     fn from(cmds: Vec<String>) -> Self {
-        Self(cmds.into_iter().map(Command::from).collect())
+        Self::from(cmds.into_iter().map(Command::from).collect::<Vec<_>>())
     }
 }
 
@@ -0,0 +1,170 @@
+//! # Report
+//!
+//! Module dedicated to command and pipeline reports. Reports capture
+//! the exit status and the standard error of every command, so
+//! callers can tell precisely which stage of a pipeline failed and
+//! why.
+
+use crate::{Error, Output, Result};
+
+/// The report of a single command execution.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CommandReport {
+    cmd: String,
+    code: Option<i32>,
+    stdout: Output,
+    stderr: Vec<u8>,
+}
+
+impl CommandReport {
+    pub fn new(
+        cmd: impl ToString,
+        code: Option<i32>,
+        stdout: impl Into<Output>,
+        stderr: impl Into<Vec<u8>>,
+    ) -> Self {
+        Self {
+            cmd: cmd.to_string(),
+            code,
+            stdout: stdout.into(),
+            stderr: stderr.into(),
+        }
+    }
+
+    /// Returns the command that has been executed.
+    pub fn cmd(&self) -> &str {
+        &self.cmd
+    }
+
+    /// Returns the exit status code of the command.
+    ///
+    /// The code is not available when the command has been
+    /// terminated by a signal.
+    pub fn code(&self) -> Option<i32> {
+        self.code
+    }
+
+    /// Returns `true` if the command exited with a zero status code.
+    pub fn is_success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Returns the standard output of the command.
+    pub fn output(&self) -> &Output {
+        &self.stdout
+    }
+
+    /// Consumes the report and returns the standard output of the
+    /// command.
+    pub fn into_output(self) -> Output {
+        self.stdout
+    }
+
+    /// Returns the raw standard error of the command.
+    ///
+    /// The standard error is empty when the output of the command is
+    /// not piped.
+    pub fn stderr(&self) -> &[u8] {
+        &self.stderr
+    }
+
+    /// Reads the standard error of the command as string lossy.
+    pub fn stderr_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).to_string()
+    }
+
+    /// Turns the report into an error if the command failed.
+    pub fn into_result(self) -> Result<Output> {
+        match self.code {
+            Some(0) => Ok(self.stdout),
+            Some(code) => {
+                let err = self.stderr_lossy();
+                Err(Error::GetExitStatusCodeNonZeroError(self.cmd, code, err))
+            }
+            None => Err(Error::GetExitStatusCodeNotAvailableError(self.cmd)),
+        }
+    }
+}
+
+/// The report of a pipeline stage.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StageReport {
+    /// The position of the stage in the pipeline, starting from 0.
+    pub index: usize,
+
+    /// The report of the stage command.
+    pub report: CommandReport,
+}
+
+/// The report of a pipeline execution.
+///
+/// The report contains one [`StageReport`] per executed stage, in
+/// order. When the pipeline stops at the first failure, stages
+/// following the failing one are not executed and therefore not
+/// reported.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PipelineReport {
+    stages: Vec<StageReport>,
+    skipped: usize,
+    output: Output,
+}
+
+impl PipelineReport {
+    pub fn new(stages: Vec<StageReport>, skipped: usize, output: impl Into<Output>) -> Self {
+        Self {
+            stages,
+            skipped,
+            output: output.into(),
+        }
+    }
+
+    /// Returns the reports of the executed stages.
+    pub fn stages(&self) -> &[StageReport] {
+        &self.stages
+    }
+
+    /// Returns the number of stages that have not been executed.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// Returns `true` if all stages have been executed and exited
+    /// with a zero status code.
+    pub fn is_success(&self) -> bool {
+        self.skipped == 0 && self.stages.iter().all(|s| s.report.is_success())
+    }
+
+    /// Returns the reports of the failing stages.
+    pub fn failures(&self) -> impl Iterator<Item = &StageReport> {
+        self.stages.iter().filter(|s| !s.report.is_success())
+    }
+
+    /// Returns the report of the first failing stage, if any.
+    pub fn first_failure(&self) -> Option<&StageReport> {
+        self.failures().next()
+    }
+
+    /// Returns the final output of the pipeline.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// Consumes the report and returns the final output of the
+    /// pipeline.
+    pub fn into_output(self) -> Output {
+        self.output
+    }
+
+    /// Turns the report into an error if one of the stages failed.
+    ///
+    /// The error targets the first failing stage.
+    pub fn into_result(self) -> Result<Output> {
+        for StageReport { index, report } in self.stages {
+            if let Err(err) = report.into_result() {
+                return Err(Error::RunPipelineStageError(Box::new(err), index + 1));
+            }
+        }
+
+        Ok(self.output)
+    }
+}
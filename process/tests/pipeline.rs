@@ -1,6 +1,6 @@
 #[cfg(feature = "async-std")]
 use async_std::test;
-use process::{Error, Pipeline};
+use process::{Error, Pipeline, PipelineFailure};
 #[cfg(feature = "tokio")]
 use tokio::test;
 
@@ -20,3 +20,33 @@ async fn test_pipeline() {
         err => panic!("unexpected error: {err:?}"),
     }
 }
+
+#[test_log::test(test)]
+async fn test_pipeline_report() {
+    let cmd = Pipeline::new(vec!["echo hello", "bad", "cat"]);
+    let report = cmd.run_report().await.unwrap();
+    assert!(!report.is_success());
+    assert_eq!(report.stages().len(), 2);
+    assert_eq!(report.skipped(), 1);
+
+    let failure = report.first_failure().unwrap();
+    assert_eq!(failure.index, 1);
+    assert_eq!(failure.report.cmd(), "bad");
+    assert_eq!(failure.report.code(), Some(127));
+    assert!(failure.report.stderr_lossy().contains("bad"));
+
+    match report.into_result().unwrap_err() {
+        Error::RunPipelineStageError(err, stage) => {
+            assert_eq!(stage, 2);
+            assert!(matches!(*err, Error::GetExitStatusCodeNonZeroError(..)));
+        }
+        err => panic!("unexpected error: {err:?}"),
+    }
+
+    let cmd = cmd.with_on_failure(PipelineFailure::Continue);
+    let report = cmd.run_report().await.unwrap();
+    assert_eq!(report.stages().len(), 3);
+    assert_eq!(report.skipped(), 0);
+    assert_eq!(report.failures().count(), 1);
+    assert_eq!(report.output().to_string_lossy(), "hello\n");
+}
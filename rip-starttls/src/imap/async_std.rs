@@ -5,12 +5,14 @@
 
 use std::io::Result;
 
-use async_std::{
-    io::{BufReadExt, BufReader, WriteExt},
-    net::TcpStream,
-};
+use async_std::net::TcpStream;
+use buf_stream::BufDuplex;
 
 use super::{Event, State};
+use crate::io::{
+    async_std::{read_line, write_all},
+    ensure_drained,
+};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct RipStarttls {
@@ -24,32 +26,28 @@ pub fn new(handshake_discarded: bool) -> Self {
     }
 
     pub async fn do_starttls_prefix(mut self, mut stream: TcpStream) -> Result<TcpStream> {
+        let mut duplex = BufDuplex::new();
         let mut event = None;
 
         while let Some(output) = self.state.resume(event.take()) {
             match output {
                 State::DiscardHandshake => {
-                    let mut line = String::new();
-                    let mut reader = BufReader::new(stream);
-                    reader.read_line(&mut line).await?;
+                    let line = read_line(&mut stream, &mut duplex).await?;
                     event = Some(Event::HandshakeDiscarded(line));
-                    stream = reader.into_inner();
                 }
                 State::WriteStarttlsCommand => {
                     let cmd = super::RipStarttls::COMMAND;
-                    let count = stream.write(cmd.as_bytes()).await?;
+                    let count = write_all(&mut stream, &mut duplex, cmd.as_bytes()).await?;
                     event = Some(Event::StarttlsCommandWrote(count));
                 }
                 State::DiscardResponse => {
-                    let mut line = String::new();
-                    let mut reader = BufReader::new(stream);
-                    reader.read_line(&mut line).await?;
+                    let line = read_line(&mut stream, &mut duplex).await?;
                     event = Some(Event::ResponseDiscarded(line));
-                    stream = reader.into_inner();
                 }
             }
         }
 
+        ensure_drained(&duplex)?;
         Ok(stream)
     }
 }
@@ -1,14 +1,16 @@
 //! # Std
 //!
-//! This module contains the blocking, standard I/O connector for
-//! [`RipStarttls`](super::RipStarttls).
+//! This module contains the blocking, standard I/O connector for [`RipStarttls`](super::RipStarttls).
 
-use std::{
-    io::{BufRead, BufReader, Result, Write},
-    net::TcpStream,
-};
+use std::{io::Result, net::TcpStream};
+
+use buf_stream::BufDuplex;
 
 use super::{Event, State};
+use crate::io::{
+    ensure_drained,
+    std::{read_line, write_all},
+};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct RipStarttls {
@@ -22,32 +24,28 @@ pub fn new(handshake_discarded: bool) -> Self {
     }
 
     pub fn do_starttls_prefix(mut self, mut stream: TcpStream) -> Result<TcpStream> {
+        let mut duplex = BufDuplex::new();
         let mut event = None;
 
         while let Some(output) = self.state.resume(event.take()) {
             match output {
                 State::DiscardHandshake => {
-                    let mut line = String::new();
-                    let mut reader = BufReader::new(stream);
-                    reader.read_line(&mut line)?;
+                    let line = read_line(&mut stream, &mut duplex)?;
                     event = Some(Event::HandshakeDiscarded(line));
-                    stream = reader.into_inner();
                 }
                 State::WriteStarttlsCommand => {
                     let cmd = super::RipStarttls::COMMAND;
-                    let count = stream.write(cmd.as_bytes())?;
+                    let count = write_all(&mut stream, &mut duplex, cmd.as_bytes())?;
                     event = Some(Event::StarttlsCommandWrote(count));
                 }
                 State::DiscardResponse => {
-                    let mut line = String::new();
-                    let mut reader = BufReader::new(stream);
-                    reader.read_line(&mut line)?;
+                    let line = read_line(&mut stream, &mut duplex)?;
                     event = Some(Event::ResponseDiscarded(line));
-                    stream = reader.into_inner();
                 }
             }
         }
 
+        ensure_drained(&duplex)?;
         Ok(stream)
     }
 }
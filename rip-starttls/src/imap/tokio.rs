@@ -1,16 +1,17 @@
 //! # Tokio
 //!
-//! This module contains the async I/O connector based on [`tokio`]
-//! for [`RipStarttls`](super::RipStarttls).
+//! This module contains the async I/O connector based on [`tokio`] for [`RipStarttls`](super::RipStarttls).
 
 use std::io::Result;
 
-use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufStream},
-    net::TcpStream,
-};
+use buf_stream::BufDuplex;
+use tokio::net::TcpStream;
 
 use super::{Event, State};
+use crate::io::{
+    ensure_drained,
+    tokio::{read_line, write_all},
+};
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct RipStarttls {
@@ -23,31 +24,29 @@ pub fn new(handshake_discarded: bool) -> Self {
         Self { state }
     }
 
-    pub async fn do_starttls_prefix(mut self, stream: TcpStream) -> Result<TcpStream> {
-        let mut stream = BufStream::new(stream);
+    pub async fn do_starttls_prefix(mut self, mut stream: TcpStream) -> Result<TcpStream> {
+        let mut duplex = BufDuplex::new();
         let mut event = None;
 
         while let Some(output) = self.state.resume(event.take()) {
             match output {
                 State::DiscardHandshake => {
-                    let mut line = String::new();
-                    stream.read_line(&mut line).await?;
+                    let line = read_line(&mut stream, &mut duplex).await?;
                     event = Some(Event::HandshakeDiscarded(line));
                 }
                 State::WriteStarttlsCommand => {
                     let cmd = super::RipStarttls::COMMAND;
-                    let count = stream.write(cmd.as_bytes()).await?;
-                    stream.flush().await?;
+                    let count = write_all(&mut stream, &mut duplex, cmd.as_bytes()).await?;
                     event = Some(Event::StarttlsCommandWrote(count));
                 }
                 State::DiscardResponse => {
-                    let mut line = String::new();
-                    stream.read_line(&mut line).await?;
+                    let line = read_line(&mut stream, &mut duplex).await?;
                     event = Some(Event::ResponseDiscarded(line));
                 }
             }
         }
 
-        Ok(stream.into_inner())
+        ensure_drained(&duplex)?;
+        Ok(stream)
     }
 }
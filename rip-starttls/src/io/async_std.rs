@@ -0,0 +1,43 @@
+//! # Async-std
+//!
+//! This module contains the async I/O helpers based on [`async_std`].
+
+use std::io::Result;
+
+use async_std::io::{Read, ReadExt, Write, WriteExt};
+use buf_stream::BufDuplex;
+
+use super::take_line;
+
+/// Read the next line from the given stream.
+pub(crate) async fn read_line(
+    stream: &mut (impl Read + Unpin),
+    duplex: &mut BufDuplex,
+) -> Result<String> {
+    loop {
+        if let Some(line) = take_line(duplex)? {
+            return Ok(line);
+        }
+
+        let [mut slice] = duplex.read_slices();
+        let count = stream.read(&mut slice).await?;
+        duplex.progress_read(count)?;
+    }
+}
+
+/// Write the given bytes to the given stream, then flush it.
+pub(crate) async fn write_all(
+    stream: &mut (impl Write + Unpin),
+    duplex: &mut BufDuplex,
+    bytes: &[u8],
+) -> Result<usize> {
+    duplex.write(bytes);
+
+    while duplex.wants_write() {
+        let count = stream.write_vectored(&duplex.write_slices()).await?;
+        duplex.progress_write(count)?;
+    }
+
+    stream.flush().await?;
+    Ok(bytes.len())
+}
@@ -0,0 +1,102 @@
+//! # I/O
+//!
+//! This module contains the helpers shared by the I/O connectors.
+//! Lines are read and commands are written through the sans I/O
+//! [`BufDuplex`] core of [`buf_stream`], so that connectors never
+//! read ahead of what they actually consume.
+
+#[cfg(feature = "async-std")]
+pub(crate) mod async_std;
+#[cfg(feature = "std")]
+pub(crate) mod std;
+#[cfg(feature = "tokio")]
+pub(crate) mod tokio;
+
+use ::std::io::{Error, ErrorKind, Result};
+
+use buf_stream::BufDuplex;
+
+/// Take the next complete line out of the given core, if any.
+///
+/// Fails if the read buffer is full without containing any line.
+pub(crate) fn take_line(duplex: &mut BufDuplex) -> Result<Option<String>> {
+    let Some(pos) = duplex.buffered().iter().position(|b| *b == b'\n') else {
+        if duplex.needs_read() {
+            return Ok(None);
+        }
+
+        let err = "line exceeds the read buffer capacity";
+        return Err(Error::new(ErrorKind::InvalidData, err));
+    };
+
+    let mut line = vec![0; pos + 1];
+    duplex.read(&mut line);
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/// Make sure that the server did not send anything after the last
+/// expected line.
+///
+/// Bytes received in plain text after the last response cannot be
+/// trusted: they could be injected by an attacker in order to be
+/// interpreted later as part of the encrypted session.
+pub(crate) fn ensure_drained(duplex: &BufDuplex) -> Result<()> {
+    if duplex.wants_read() {
+        let count = duplex.buffered().len();
+        let err = format!("received {count} unexpected bytes after the last response");
+        return Err(Error::new(ErrorKind::InvalidData, err));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::ErrorKind;
+
+    use buf_stream::BufDuplex;
+
+    use super::{ensure_drained, take_line};
+
+    fn fill(duplex: &mut BufDuplex, bytes: &[u8]) {
+        let [mut slice] = duplex.read_slices();
+        slice[..bytes.len()].copy_from_slice(bytes);
+        duplex.progress_read(bytes.len()).unwrap();
+    }
+
+    #[test]
+    fn take_lines() {
+        let mut duplex = BufDuplex::new();
+
+        fill(&mut duplex, b"* OK ready\r\nA OK");
+        assert_eq!(take_line(&mut duplex).unwrap().unwrap(), "* OK ready\r\n");
+        assert_eq!(take_line(&mut duplex).unwrap(), None);
+
+        fill(&mut duplex, b" begin TLS\r\n");
+        assert_eq!(
+            take_line(&mut duplex).unwrap().unwrap(),
+            "A OK begin TLS\r\n"
+        );
+        assert!(ensure_drained(&duplex).is_ok());
+    }
+
+    #[test]
+    fn reject_injected_bytes() {
+        let mut duplex = BufDuplex::new();
+
+        fill(&mut duplex, b"A OK begin TLS\r\n* OK injected\r\n");
+        take_line(&mut duplex).unwrap().unwrap();
+
+        let err = ensure_drained(&duplex).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reject_too_long_line() {
+        let mut duplex = BufDuplex::new().with_read_capacity(4);
+
+        fill(&mut duplex, b"* OK");
+        let err = take_line(&mut duplex).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
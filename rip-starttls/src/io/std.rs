@@ -0,0 +1,39 @@
+//! # Std
+//!
+//! This module contains the blocking, standard I/O helpers.
+
+use std::io::{Read, Result, Write};
+
+use buf_stream::BufDuplex;
+
+use super::take_line;
+
+/// Read the next line from the given stream.
+pub(crate) fn read_line(stream: &mut impl Read, duplex: &mut BufDuplex) -> Result<String> {
+    loop {
+        if let Some(line) = take_line(duplex)? {
+            return Ok(line);
+        }
+
+        let [mut slice] = duplex.read_slices();
+        let count = stream.read(&mut slice)?;
+        duplex.progress_read(count)?;
+    }
+}
+
+/// Write the given bytes to the given stream, then flush it.
+pub(crate) fn write_all(
+    stream: &mut impl Write,
+    duplex: &mut BufDuplex,
+    bytes: &[u8],
+) -> Result<usize> {
+    duplex.write(bytes);
+
+    while duplex.wants_write() {
+        let count = stream.write_vectored(&duplex.write_slices())?;
+        duplex.progress_write(count)?;
+    }
+
+    stream.flush()?;
+    Ok(bytes.len())
+}
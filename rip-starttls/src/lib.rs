@@ -2,3 +2,6 @@
 #![doc = include_str!("../README.md")]
 
 pub mod imap;
+#[cfg(any(feature = "std", feature = "tokio", feature = "async-std"))]
+mod io;
+pub mod smtp;
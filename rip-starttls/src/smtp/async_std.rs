@@ -0,0 +1,109 @@
+//! # Async-std
+//!
+//! This module contains the async I/O connectors based on
+//! [`async_std`] for
+//! [`RipStarttls`](super::RipStarttls) and [`Ehlo`](super::Ehlo).
+
+use std::io::Result;
+
+use async_std::{
+    io::{Read, Write},
+    net::TcpStream,
+};
+use buf_stream::BufDuplex;
+
+use super::{EhloEvent, EhloState, Event, State};
+use crate::io::{
+    async_std::{read_line, write_all},
+    ensure_drained,
+};
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RipStarttls {
+    state: super::RipStarttls,
+}
+
+impl RipStarttls {
+    pub fn new(handshake_discarded: bool) -> Self {
+        let state = super::RipStarttls::new(handshake_discarded);
+        Self { state }
+    }
+
+    pub fn with_domain(mut self, domain: impl ToString) -> Self {
+        self.state = self.state.with_domain(domain);
+        self
+    }
+
+    pub async fn do_starttls_prefix(mut self, mut stream: TcpStream) -> Result<TcpStream> {
+        // the core is kept for the whole exchange, so that lines of
+        // multiline replies are not lost
+        let mut duplex = BufDuplex::new();
+        let mut event = None;
+
+        while let Some(output) = self.state.resume(event.take()) {
+            match output {
+                State::DiscardHandshake => {
+                    let line = read_line(&mut stream, &mut duplex).await?;
+                    event = Some(Event::HandshakeDiscarded(line));
+                }
+                State::WriteEhloCommand => {
+                    let cmd = self.state.ehlo_command();
+                    let count = write_all(&mut stream, &mut duplex, cmd.as_bytes()).await?;
+                    event = Some(Event::EhloCommandWrote(count));
+                }
+                State::DiscardEhloResponse => {
+                    let line = read_line(&mut stream, &mut duplex).await?;
+                    event = Some(Event::EhloResponseDiscarded(line));
+                }
+                State::WriteStarttlsCommand => {
+                    let cmd = super::RipStarttls::COMMAND;
+                    let count = write_all(&mut stream, &mut duplex, cmd.as_bytes()).await?;
+                    event = Some(Event::StarttlsCommandWrote(count));
+                }
+                State::DiscardResponse => {
+                    let line = read_line(&mut stream, &mut duplex).await?;
+                    event = Some(Event::ResponseDiscarded(line));
+                }
+            }
+        }
+
+        ensure_drained(&duplex)?;
+        Ok(stream)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Ehlo {
+    state: super::Ehlo,
+}
+
+impl Ehlo {
+    pub fn new(domain: impl ToString) -> Self {
+        let state = super::Ehlo::new(domain);
+        Self { state }
+    }
+
+    /// Re-establish the SMTP session state of the given stream, and
+    /// return the capabilities announced by the server.
+    pub async fn do_ehlo<S: Read + Write + Unpin>(mut self, stream: &mut S) -> Result<Vec<String>> {
+        let mut duplex = BufDuplex::new();
+        let mut event = None;
+
+        while let Some(output) = self.state.resume(event.take()) {
+            match output {
+                EhloState::WriteEhloCommand => {
+                    let cmd = self.state.command();
+                    let count = write_all(stream, &mut duplex, cmd.as_bytes()).await?;
+                    event = Some(EhloEvent::EhloCommandWrote(count));
+                }
+                EhloState::ReadEhloResponse(_) => {
+                    let line = read_line(stream, &mut duplex).await?;
+                    event = Some(EhloEvent::EhloResponseRead(line));
+                }
+            }
+        }
+
+        ensure_drained(&duplex)?;
+        Ok(self.state.into_capabilities())
+    }
+}
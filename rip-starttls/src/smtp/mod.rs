@@ -0,0 +1,344 @@
+//! # SMTP
+//!
+//! This module contains the sans I/O implementation for the SMTP
+//! protocol, as well as feature-gated I/O connectors.
+//!
+//! Next to [`RipStarttls`], the [`Ehlo`] coroutine re-establishes
+//! the SMTP session state by sending a new `EHLO` command: clients
+//! must discard everything they know about the server after the TLS
+//! negociation (and after a TLS shutdown), as defined in the
+//! [RFC3207](https://datatracker.ietf.org/doc/html/rfc3207#section-4.2).
+
+#[cfg(feature = "async-std")]
+pub mod async_std;
+#[cfg(feature = "std")]
+pub mod std;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+use tracing::debug;
+
+/// The default domain sent along with the `EHLO` command.
+pub const DEFAULT_DOMAIN: &str = "localhost";
+
+/// The main structure of the SMTP module.
+///
+/// This structure allows you to move a TCP stream to a TLS-ready
+/// state.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RipStarttls {
+    state: Option<State>,
+    event: Option<Event>,
+    handshake_discarded: bool,
+    domain: String,
+}
+
+impl RipStarttls {
+    pub const COMMAND: &str = "STARTTLS\r\n";
+
+    pub fn new(handshake_discarded: bool) -> Self {
+        Self {
+            state: None,
+            event: None,
+            handshake_discarded,
+            domain: DEFAULT_DOMAIN.to_owned(),
+        }
+    }
+
+    /// Set the domain sent along with the `EHLO` command, using the
+    /// builder pattern.
+    pub fn with_domain(mut self, domain: impl ToString) -> Self {
+        self.domain = domain.to_string();
+        self
+    }
+
+    /// Return the `EHLO` command to write.
+    pub fn ehlo_command(&self) -> String {
+        ehlo_command(&self.domain)
+    }
+
+    /// Acts like a coroutine's resume function, where the argument is
+    /// replaced by an event.
+    pub fn resume(&mut self, event: Option<Event>) -> Option<State> {
+        self.event = event;
+        self.next()
+    }
+}
+
+impl Default for RipStarttls {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+impl Iterator for RipStarttls {
+    type Item = State;
+
+    fn next(&mut self) -> Option<State> {
+        let event = self.event.take();
+
+        match self.state {
+            None => {
+                self.state = Some(if self.handshake_discarded {
+                    State::WriteEhloCommand
+                } else {
+                    State::DiscardHandshake
+                })
+            }
+            Some(State::DiscardHandshake) => {
+                if let Some(Event::HandshakeDiscarded(line)) = event {
+                    debug!("discarded SMTP greeting: {line:?}");
+
+                    if is_last_line(&line) {
+                        self.state = Some(State::WriteEhloCommand);
+                    }
+                }
+            }
+            Some(State::WriteEhloCommand) => {
+                if let Some(Event::EhloCommandWrote(_)) = event {
+                    debug!("wrote SMTP EHLO command: {:?}", self.ehlo_command());
+                    self.state = Some(State::DiscardEhloResponse);
+                }
+            }
+            Some(State::DiscardEhloResponse) => {
+                if let Some(Event::EhloResponseDiscarded(line)) = event {
+                    debug!("discarded SMTP EHLO response: {line:?}");
+
+                    if is_last_line(&line) {
+                        self.state = Some(State::WriteStarttlsCommand);
+                    }
+                }
+            }
+            Some(State::WriteStarttlsCommand) => {
+                if let Some(Event::StarttlsCommandWrote(_)) = event {
+                    let cmd = Self::COMMAND;
+                    debug!("wrote SMTP STARTTLS command: {cmd:?}");
+                    self.state = Some(State::DiscardResponse);
+                }
+            }
+            Some(State::DiscardResponse) => {
+                if let Some(Event::ResponseDiscarded(line)) = event {
+                    debug!("discarded SMTP response: {line:?}");
+
+                    if is_last_line(&line) {
+                        debug!("stream ready for TLS negociation");
+                        self.state = None;
+                    }
+                }
+            }
+        }
+
+        self.state.clone()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum State {
+    DiscardHandshake,
+    WriteEhloCommand,
+    DiscardEhloResponse,
+    WriteStarttlsCommand,
+    DiscardResponse,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Event {
+    HandshakeDiscarded(String),
+    EhloCommandWrote(usize),
+    EhloResponseDiscarded(String),
+    StarttlsCommandWrote(usize),
+    ResponseDiscarded(String),
+}
+
+/// The SMTP session re-handshake coroutine.
+///
+/// This coroutine sends a new `EHLO` command and collects the
+/// capabilities announced by the server. It should be used right
+/// after a TLS negociation or a TLS shutdown, since the session state
+/// needs to be re-established.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ehlo {
+    state: Option<EhloState>,
+    event: Option<EhloEvent>,
+    domain: String,
+    capabilities: Vec<String>,
+}
+
+impl Ehlo {
+    pub fn new(domain: impl ToString) -> Self {
+        Self {
+            state: None,
+            event: None,
+            domain: domain.to_string(),
+            capabilities: Vec::new(),
+        }
+    }
+
+    /// Return the `EHLO` command to write.
+    pub fn command(&self) -> String {
+        ehlo_command(&self.domain)
+    }
+
+    /// Return the capabilities collected so far.
+    ///
+    /// The first line of the response (the server greeting) is not
+    /// part of the capabilities.
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    /// Consume the coroutine and return the collected capabilities.
+    pub fn into_capabilities(self) -> Vec<String> {
+        self.capabilities
+    }
+
+    /// Acts like a coroutine's resume function, where the argument is
+    /// replaced by an event.
+    pub fn resume(&mut self, event: Option<EhloEvent>) -> Option<EhloState> {
+        self.event = event;
+        self.next()
+    }
+}
+
+impl Default for Ehlo {
+    fn default() -> Self {
+        Self::new(DEFAULT_DOMAIN)
+    }
+}
+
+impl Iterator for Ehlo {
+    type Item = EhloState;
+
+    fn next(&mut self) -> Option<EhloState> {
+        let event = self.event.take();
+
+        match self.state {
+            None => {
+                self.capabilities.clear();
+                self.state = Some(EhloState::WriteEhloCommand);
+            }
+            Some(EhloState::WriteEhloCommand) => {
+                if let Some(EhloEvent::EhloCommandWrote(_)) = event {
+                    debug!("wrote SMTP EHLO command: {:?}", self.command());
+                    self.state = Some(EhloState::ReadEhloResponse(true));
+                }
+            }
+            Some(EhloState::ReadEhloResponse(first)) => {
+                if let Some(EhloEvent::EhloResponseRead(line)) = event {
+                    debug!("read SMTP EHLO response: {line:?}");
+
+                    if !first {
+                        let capability = line.get(4..).unwrap_or_default().trim_end();
+                        self.capabilities.push(capability.to_owned());
+                    }
+
+                    self.state = if is_last_line(&line) {
+                        debug!("SMTP session re-established");
+                        None
+                    } else {
+                        Some(EhloState::ReadEhloResponse(false))
+                    };
+                }
+            }
+        }
+
+        self.state.clone()
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EhloState {
+    WriteEhloCommand,
+    /// Read the next response line, the boolean tells if it is the
+    /// first one.
+    ReadEhloResponse(bool),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EhloEvent {
+    EhloCommandWrote(usize),
+    EhloResponseRead(String),
+}
+
+fn ehlo_command(domain: &str) -> String {
+    format!("EHLO {domain}\r\n")
+}
+
+/// Return `true` if the given line is the last line of a reply.
+///
+/// Lines of a multiline reply are made of the code followed by a
+/// hyphen, except the last one. An empty line means that the stream
+/// reached its end, which also stops the reply.
+fn is_last_line(line: &str) -> bool {
+    line.as_bytes().get(3) != Some(&b'-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Ehlo, EhloEvent, EhloState, Event, RipStarttls, State};
+
+    #[test]
+    fn starttls_prefix() {
+        let mut starttls = RipStarttls::default().with_domain("client.localhost");
+        let line = |line: &str| line.to_owned();
+
+        assert_eq!(starttls.resume(None), Some(State::DiscardHandshake));
+        assert_eq!(
+            starttls.resume(Some(Event::HandshakeDiscarded(line("220-localhost\r\n")))),
+            Some(State::DiscardHandshake)
+        );
+        assert_eq!(
+            starttls.resume(Some(Event::HandshakeDiscarded(line("220 ready\r\n")))),
+            Some(State::WriteEhloCommand)
+        );
+        assert_eq!(starttls.ehlo_command(), "EHLO client.localhost\r\n");
+        assert_eq!(
+            starttls.resume(Some(Event::EhloCommandWrote(23))),
+            Some(State::DiscardEhloResponse)
+        );
+        assert_eq!(
+            starttls.resume(Some(Event::EhloResponseDiscarded(line(
+                "250-localhost\r\n"
+            )))),
+            Some(State::DiscardEhloResponse)
+        );
+        assert_eq!(
+            starttls.resume(Some(Event::EhloResponseDiscarded(line("250 STARTTLS\r\n")))),
+            Some(State::WriteStarttlsCommand)
+        );
+        assert_eq!(
+            starttls.resume(Some(Event::StarttlsCommandWrote(10))),
+            Some(State::DiscardResponse)
+        );
+        assert_eq!(
+            starttls.resume(Some(Event::ResponseDiscarded(line("220 go ahead\r\n")))),
+            None
+        );
+    }
+
+    #[test]
+    fn ehlo() {
+        let mut ehlo = Ehlo::default();
+
+        assert_eq!(ehlo.resume(None), Some(EhloState::WriteEhloCommand));
+        assert_eq!(ehlo.command(), "EHLO localhost\r\n");
+        assert_eq!(
+            ehlo.resume(Some(EhloEvent::EhloCommandWrote(16))),
+            Some(EhloState::ReadEhloResponse(true))
+        );
+
+        for line in ["250-localhost\r\n", "250-SIZE 1000\r\n"] {
+            assert!(ehlo
+                .resume(Some(EhloEvent::EhloResponseRead(line.to_owned())))
+                .is_some());
+        }
+
+        assert_eq!(
+            ehlo.resume(Some(EhloEvent::EhloResponseRead(
+                "250 AUTH PLAIN\r\n".into()
+            ))),
+            None
+        );
+        assert_eq!(ehlo.capabilities(), ["SIZE 1000", "AUTH PLAIN"]);
+    }
+}
@@ -4,6 +4,7 @@
 #[cfg(feature = "derive")]
 pub(crate) mod derive;
 mod error;
+pub mod redacted;
 
 #[cfg(feature = "keyring")]
 pub use keyring;
@@ -13,10 +14,15 @@
 pub use process;
 #[cfg(feature = "command")]
 use process::Command;
+use std::fmt;
+
 use tracing::debug;
 
 #[doc(inline)]
-pub use crate::error::{Error, Result};
+pub use crate::{
+    error::{Error, Result},
+    redacted::Redacted,
+};
 
 #[cfg(any(
     all(feature = "tokio", feature = "async-std"),
@@ -34,7 +40,7 @@
 ///
 /// A secret can be retrieved either from a raw string, from a shell
 /// command or from a keyring entry.
-#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[derive(Clone, Default, Eq, PartialEq)]
 #[cfg_attr(
     feature = "derive",
     derive(serde::Serialize, serde::Deserialize),
@@ -259,3 +265,17 @@ pub fn replace_with_keyring_if_empty(&mut self, entry: impl ToString) -> Result<
         Ok(())
     }
 }
+
+impl fmt::Debug for Secret {
+    /// Format the secret without revealing raw values.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => f.write_str("Empty"),
+            Self::Raw(_) => f.debug_tuple("Raw").field(&Redacted::new(())).finish(),
+            #[cfg(feature = "command")]
+            Self::Command(cmd) => f.debug_tuple("Command").field(cmd).finish(),
+            #[cfg(feature = "keyring")]
+            Self::Keyring(entry) => f.debug_tuple("Keyring").field(entry).finish(),
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! # Redacted
+//!
+//! Module dedicated to the [`Redacted`] wrapper, which prevents
+//! secrets from leaking into logs.
+
+use std::fmt;
+
+/// The placeholder printed instead of a redacted value.
+pub const REDACTED: &str = "***";
+
+/// Wrapper around a secret value.
+///
+/// The [`fmt::Debug`] and [`fmt::Display`] implementations print
+/// [`REDACTED`] instead of the inner value, so that structures
+/// holding secrets can safely derive [`fmt::Debug`]. The inner value
+/// needs to be explicitly revealed using [`Redacted::reveal`].
+#[derive(Clone, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct Redacted<T>(T);
+
+impl<T> Redacted<T> {
+    /// Wrap the given secret value.
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// Reveal the reference to the inner secret value.
+    pub fn reveal(&self) -> &T {
+        &self.0
+    }
+
+    /// Reveal the inner secret value, consuming the wrapper.
+    pub fn into_revealed(self) -> T {
+        self.0
+    }
+}
+
+impl<T> From<T> for Redacted<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<T> fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
@@ -0,0 +1,17 @@
+use secret::{Redacted, Secret};
+
+#[test]
+fn redacted() {
+    let secret = Redacted::new(String::from("password"));
+
+    assert_eq!(format!("{secret:?}"), "***");
+    assert_eq!(secret.to_string(), "***");
+    assert_eq!(secret.reveal(), "password");
+    assert_eq!(secret.into_revealed(), "password");
+}
+
+#[test]
+fn raw_secret_debug() {
+    let secret = Secret::new_raw("password");
+    assert_eq!(format!("{secret:?}"), "Raw(***)");
+}
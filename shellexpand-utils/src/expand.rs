@@ -1,7 +1,11 @@
 use log::{debug, warn};
-use std::path::{Path, PathBuf};
+use std::{
+    borrow::Cow,
+    env,
+    path::{Path, PathBuf},
+};
 
-use crate::{Error, Result};
+use crate::{Error, Result, VarResolver};
 
 pub fn try_path(path: impl AsRef<Path>) -> Result<PathBuf> {
     let path = path.as_ref();
@@ -47,3 +51,110 @@ pub fn str(str: impl AsRef<str>) -> String {
         str.to_owned()
     })
 }
+
+/// Expand the given string like [`shellexpand::full`], resolving
+/// variables with the given resolver first.
+fn full_with(str: &str, resolver: &impl VarResolver) -> Result<String> {
+    let expanded = shellexpand::env_with_context(str, |name| match resolver.resolve(name) {
+        Some(val) => Ok(Some(Cow::Owned(val))),
+        None => env::var(name).map(|val| Some(Cow::Owned(val))),
+    })
+    .map_err(|err| Error::ExpandStrError(err, str.to_owned()))?;
+
+    // a tilde coming from a variable value is not expanded
+    if !str.starts_with('~') && expanded.starts_with('~') {
+        return Ok(expanded.into_owned());
+    }
+
+    Ok(shellexpand::tilde(expanded.as_ref()).into_owned())
+}
+
+pub fn try_path_with(path: impl AsRef<Path>, resolver: &impl VarResolver) -> Result<PathBuf> {
+    let path = path.as_ref();
+    let path_str = path
+        .to_str()
+        .ok_or_else(|| Error::ConvertPathToStrError(path.to_owned()))?;
+    let expanded_path = PathBuf::from(full_with(path_str, resolver)?);
+    Ok(expanded_path)
+}
+
+pub fn path_with(path: impl AsRef<Path>, resolver: &impl VarResolver) -> PathBuf {
+    let path = path.as_ref();
+    match try_path_with(path, resolver) {
+        Ok(path) => path,
+        Err(Error::ConvertPathToStrError(path)) => {
+            warn!("cannot expand path {path:?}: cannot convert path to string");
+            path
+        }
+        Err(Error::ExpandStrError(err, path)) => {
+            warn!("{err}");
+            debug!("{err:?}");
+            PathBuf::from(path)
+        }
+        _ => panic!("this should be impossible!"),
+    }
+}
+
+pub fn try_str_with(str: impl AsRef<str>, resolver: &impl VarResolver) -> Result<String> {
+    full_with(str.as_ref(), resolver)
+}
+
+pub fn str_with(str: impl AsRef<str>, resolver: &impl VarResolver) -> String {
+    let str = str.as_ref();
+    try_str_with(str, resolver).unwrap_or_else(|err| {
+        warn!("{err}");
+        debug!("{err:?}");
+        str.to_owned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, env};
+
+    use super::full_with;
+    use crate::Error;
+
+    fn resolver(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(name, val)| (name.to_string(), val.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn resolver_takes_precedence_over_env() {
+        env::set_var("SHELLEXPAND_UTILS_PRECEDENCE", "env");
+        let resolver = resolver(&[("SHELLEXPAND_UTILS_PRECEDENCE", "resolver")]);
+
+        let expanded = full_with("${SHELLEXPAND_UTILS_PRECEDENCE}/mail", &resolver).unwrap();
+        assert_eq!(expanded, "resolver/mail");
+    }
+
+    #[test]
+    fn env_fallback() {
+        env::set_var("SHELLEXPAND_UTILS_FALLBACK", "env");
+        let resolver = resolver(&[("account", "work")]);
+
+        let expanded = full_with("$SHELLEXPAND_UTILS_FALLBACK/$account", &resolver).unwrap();
+        assert_eq!(expanded, "env/work");
+    }
+
+    #[test]
+    fn missing_var() {
+        env::remove_var("SHELLEXPAND_UTILS_MISSING");
+        let resolver = resolver(&[]);
+
+        let err = full_with("$SHELLEXPAND_UTILS_MISSING/mail", &resolver).unwrap_err();
+        assert!(
+            matches!(err, Error::ExpandStrError(_, str) if str == "$SHELLEXPAND_UTILS_MISSING/mail")
+        );
+    }
+
+    #[test]
+    fn tilde_from_var_is_kept() {
+        let resolver = resolver(&[("dir", "~/mail")]);
+
+        let expanded = full_with("$dir/work", &resolver).unwrap();
+        assert_eq!(expanded, "~/mail/work");
+    }
+}
@@ -1,11 +1,15 @@
 pub mod canonicalize;
 mod error;
 pub mod expand;
+pub mod resolver;
 
 use std::path::{Path, PathBuf};
 
 #[doc(inline)]
-pub use crate::error::{Error, Result};
+pub use crate::{
+    error::{Error, Result},
+    resolver::VarResolver,
+};
 
 pub fn try_shellexpand_path(path: impl AsRef<Path>) -> Result<PathBuf> {
     let path = expand::try_path(path)?;
@@ -25,3 +29,35 @@ pub fn try_shellexpand_str(str: impl AsRef<str>) -> Result<String> {
 pub fn shellexpand_str(str: impl AsRef<str>) -> String {
     expand::str(str)
 }
+
+/// Same as [`try_shellexpand_path`], but resolve variables with the
+/// given resolver first, see [`VarResolver`].
+pub fn try_shellexpand_path_with(
+    path: impl AsRef<Path>,
+    resolver: &impl VarResolver,
+) -> Result<PathBuf> {
+    let path = expand::try_path_with(path, resolver)?;
+    let path = canonicalize::try_path(path)?;
+    Ok(path)
+}
+
+/// Same as [`shellexpand_path`], but resolve variables with the given
+/// resolver first, see [`VarResolver`].
+pub fn shellexpand_path_with(path: impl AsRef<Path>, resolver: &impl VarResolver) -> PathBuf {
+    canonicalize::path(expand::path_with(path, resolver))
+}
+
+/// Same as [`try_shellexpand_str`], but resolve variables with the
+/// given resolver first, see [`VarResolver`].
+pub fn try_shellexpand_str_with(
+    str: impl AsRef<str>,
+    resolver: &impl VarResolver,
+) -> Result<String> {
+    expand::try_str_with(str, resolver)
+}
+
+/// Same as [`shellexpand_str`], but resolve variables with the given
+/// resolver first, see [`VarResolver`].
+pub fn shellexpand_str_with(str: impl AsRef<str>, resolver: &impl VarResolver) -> String {
+    expand::str_with(str, resolver)
+}
@@ -0,0 +1,59 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// Custom variable resolver.
+///
+/// Resolvers let applications expand their own variables (like
+/// `${account.name}` or `${config_dir}`). Variables not resolved
+/// fall back to the process environment.
+pub trait VarResolver {
+    /// Resolve the value of the given variable name.
+    ///
+    /// Returning `None` falls back to the process environment.
+    fn resolve(&self, name: &str) -> Option<String>;
+}
+
+impl<F: Fn(&str) -> Option<String>> VarResolver for F {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self(name)
+    }
+}
+
+impl VarResolver for HashMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+impl VarResolver for BTreeMap<String, String> {
+    fn resolve(&self, name: &str) -> Option<String> {
+        self.get(name).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use super::VarResolver;
+
+    #[test]
+    fn hash_map() {
+        let vars = HashMap::from_iter([("account".to_owned(), "work".to_owned())]);
+        assert_eq!(vars.resolve("account"), Some("work".to_owned()));
+        assert_eq!(vars.resolve("unknown"), None);
+    }
+
+    #[test]
+    fn btree_map() {
+        let vars = BTreeMap::from_iter([("account".to_owned(), "work".to_owned())]);
+        assert_eq!(vars.resolve("account"), Some("work".to_owned()));
+        assert_eq!(vars.resolve("unknown"), None);
+    }
+
+    #[test]
+    fn closure() {
+        let resolver = |name: &str| (name == "account").then(|| "work".to_owned());
+        assert_eq!(resolver.resolve("account"), Some("work".to_owned()));
+        assert_eq!(resolver.resolve("unknown"), None);
+    }
+}
@@ -7,6 +7,8 @@
 
 #[cfg(feature = "tcp-client")]
 pub mod tcp;
+#[cfg(all(unix, feature = "unix-client"))]
+pub mod unix;
 
 use std::io::{Error, ErrorKind, Result};
 
@@ -3,17 +3,17 @@
 //! This module contains the implementation of the TCP client, based
 //! on [`tokio::net::TcpStream`].
 
-use std::io::{Error, ErrorKind, Result};
+use std::io::Result;
 
 use async_trait::async_trait;
 use futures::{AsyncBufReadExt, AsyncWriteExt};
 use tracing::debug;
 
 use crate::{
+    line,
     request::{Request, RequestWriter},
     response::{Response, ResponseReader},
     tcp::{TcpHandler, TcpStream},
-    timer::Timer,
 };
 
 use super::{Client, ClientStream};
@@ -54,17 +54,8 @@ async fn send(&self, req: Request) -> Result<Response> {
 #[async_trait]
 impl RequestWriter for TcpHandler {
     async fn write(&mut self, req: Request) -> Result<()> {
-        let req = match req {
-            Request::Start => "start\n".to_owned(),
-            Request::Get => "get\n".to_owned(),
-            Request::Set(duration) => format!("set {duration}\n"),
-            Request::Pause => "pause\n".to_owned(),
-            Request::Resume => "resume\n".to_owned(),
-            Request::Stop => "stop\n".to_owned(),
-        };
-
+        let req = line::format_request(req);
         self.writer.write_all(req.as_bytes()).await?;
-
         Ok(())
     }
 }
@@ -74,29 +65,6 @@ impl ResponseReader for TcpHandler {
     async fn read(&mut self) -> Result<Response> {
         let mut res = String::new();
         self.reader.read_line(&mut res).await?;
-
-        let mut tokens = res.split_whitespace();
-        match tokens.next() {
-            Some("ok") => Ok(Response::Ok),
-            Some("timer") => match tokens.next().map(serde_json::from_str::<Timer>) {
-                Some(Ok(timer)) => Ok(Response::Timer(timer)),
-                Some(Err(err)) => Err(Error::new(
-                    ErrorKind::InvalidInput,
-                    format!("invalid timer: {err}"),
-                )),
-                None => Err(Error::new(
-                    ErrorKind::InvalidInput,
-                    "missing timer".to_owned(),
-                )),
-            },
-            Some(res) => Err(Error::new(
-                ErrorKind::InvalidInput,
-                format!("invalid response: {res}"),
-            )),
-            None => Err(Error::new(
-                ErrorKind::InvalidInput,
-                "missing response".to_owned(),
-            )),
-        }
+        line::parse_response(&res)
     }
 }
@@ -0,0 +1,64 @@
+//! # Unix socket client
+//!
+//! This module contains the implementation of the Unix socket client,
+//! based on [`tokio::net::UnixStream`].
+
+use std::{io::Result, path::PathBuf};
+
+use async_trait::async_trait;
+use futures::{AsyncBufReadExt, AsyncWriteExt};
+use tracing::debug;
+
+use crate::{
+    line,
+    request::{Request, RequestWriter},
+    response::{Response, ResponseReader},
+    unix::{UnixHandler, UnixStream},
+};
+
+use super::{Client, ClientStream};
+
+/// The Unix socket client.
+///
+/// This [`Client`] uses a Unix socket to connect to a listener, to
+/// read responses and write requests.
+pub struct UnixClient {
+    /// The path of the Unix socket the client should connect to.
+    pub path: PathBuf,
+}
+
+impl UnixClient {
+    /// Create a new Unix socket client using the given socket path.
+    pub fn new_boxed(path: impl Into<PathBuf>) -> Box<dyn Client> {
+        Box::new(Self { path: path.into() })
+    }
+}
+
+#[async_trait]
+impl Client for UnixClient {
+    /// Send the given request to the Unix socket server.
+    async fn send(&self, req: Request) -> Result<Response> {
+        debug!("connecting to Unix socket {}", self.path.display());
+        let stream = UnixStream::connect(&self.path).await?;
+        let mut handler = UnixHandler::new(stream);
+        handler.handle(req).await
+    }
+}
+
+#[async_trait]
+impl RequestWriter for UnixHandler {
+    async fn write(&mut self, req: Request) -> Result<()> {
+        let req = line::format_request(req);
+        self.writer.write_all(req.as_bytes()).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ResponseReader for UnixHandler {
+    async fn read(&mut self) -> Result<Response> {
+        let mut res = String::new();
+        self.reader.read_line(&mut res).await?;
+        line::parse_response(&res)
+    }
+}
@@ -0,0 +1,220 @@
+//! # Duration
+//!
+//! This module contains the [`HumanDuration`], a duration that can
+//! be written the way humans do (`25m`, `1h30m`, `90s`) in
+//! configuration files, instead of a raw number of seconds.
+
+use std::{error, fmt, str::FromStr, time::Duration};
+
+/// The duration units, from the largest to the smallest.
+const UNITS: [(&str, u64); 4] = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+
+/// The human-friendly duration.
+///
+/// A duration is made of one or more numbers followed by their unit:
+/// `d` (days), `h` (hours), `m` (minutes), `s` (seconds) or `ms`
+/// (milliseconds), for example `1h30m` or `2m 30s`. A number without
+/// unit is a number of seconds, so that durations previously
+/// configured as raw seconds remain valid.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct HumanDuration(Duration);
+
+impl HumanDuration {
+    pub fn new(duration: Duration) -> Self {
+        Self(duration)
+    }
+
+    pub fn from_secs(secs: u64) -> Self {
+        Self(Duration::from_secs(secs))
+    }
+
+    pub fn as_duration(&self) -> Duration {
+        self.0
+    }
+
+    pub fn as_secs(&self) -> u64 {
+        self.0.as_secs()
+    }
+}
+
+impl From<Duration> for HumanDuration {
+    fn from(duration: Duration) -> Self {
+        Self::new(duration)
+    }
+}
+
+impl From<u64> for HumanDuration {
+    fn from(secs: u64) -> Self {
+        Self::from_secs(secs)
+    }
+}
+
+impl From<HumanDuration> for Duration {
+    fn from(duration: HumanDuration) -> Self {
+        duration.0
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = ParseHumanDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseHumanDurationError(s.to_owned());
+        let s = s.trim();
+
+        if s.is_empty() {
+            return Err(err());
+        }
+
+        if let Ok(secs) = s.parse() {
+            return Ok(Self::from_secs(secs));
+        }
+
+        let mut duration = Duration::ZERO;
+        let mut rest = s;
+
+        while !rest.is_empty() {
+            let digits = rest.find(|c: char| !c.is_ascii_digit()).ok_or_else(err)?;
+            let (number, tail) = rest.split_at(digits);
+            let number: u64 = number.parse().map_err(|_| err())?;
+
+            let unit = tail
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(tail.len());
+            let (unit, tail) = tail.split_at(unit);
+
+            let part = match unit.to_ascii_lowercase().as_str() {
+                "ms" => Duration::from_millis(number),
+                unit => {
+                    let (_, secs) = UNITS
+                        .iter()
+                        .find(|(name, _)| *name == unit)
+                        .ok_or_else(err)?;
+                    Duration::from_secs(number.checked_mul(*secs).ok_or_else(err)?)
+                }
+            };
+
+            duration = duration.checked_add(part).ok_or_else(err)?;
+            rest = tail.trim_start();
+        }
+
+        Ok(Self(duration))
+    }
+}
+
+impl fmt::Display for HumanDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut secs = self.0.as_secs();
+        let millis = self.0.subsec_millis();
+
+        if secs == 0 && millis == 0 {
+            return write!(f, "0s");
+        }
+
+        for (unit, unit_secs) in UNITS {
+            if secs >= unit_secs {
+                write!(f, "{}{unit}", secs / unit_secs)?;
+                secs %= unit_secs;
+            }
+        }
+
+        if millis > 0 {
+            write!(f, "{millis}ms")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "derive")]
+impl serde::Serialize for HumanDuration {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "derive")]
+impl<'de> serde::Deserialize<'de> for HumanDuration {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl serde::de::Visitor<'_> for Visitor {
+            type Value = HumanDuration;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number of seconds or a duration like 1h30m")
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, secs: u64) -> Result<Self::Value, E> {
+                Ok(HumanDuration::from_secs(secs))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, secs: i64) -> Result<Self::Value, E> {
+                u64::try_from(secs)
+                    .map(HumanDuration::from_secs)
+                    .map_err(|_| E::invalid_value(serde::de::Unexpected::Signed(secs), &self))
+            }
+
+            fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                s.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(Visitor)
+    }
+}
+
+/// Deserialize a number of seconds from either a number or a
+/// [`HumanDuration`].
+///
+/// Useful for fields kept as raw seconds, like
+/// [`TimerCycle::duration`](crate::timer::TimerCycle::duration).
+#[cfg(feature = "derive")]
+pub fn deserialize_secs<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<usize, D::Error> {
+    let duration = <HumanDuration as serde::Deserialize>::deserialize(deserializer)?;
+    usize::try_from(duration.as_secs()).map_err(serde::de::Error::custom)
+}
+
+/// The error returned when a [`HumanDuration`] cannot be parsed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseHumanDurationError(String);
+
+impl fmt::Display for ParseHumanDurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot parse duration {:?}", self.0)
+    }
+}
+
+impl error::Error for ParseHumanDurationError {}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::HumanDuration;
+
+    #[test]
+    fn parse_and_display() {
+        let parse = |s: &str| s.parse::<HumanDuration>().map(|d| d.as_duration());
+
+        assert_eq!(parse("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse("25m"), Ok(Duration::from_secs(25 * 60)));
+        assert_eq!(parse("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse(" 2m 30S "), Ok(Duration::from_secs(150)));
+        assert_eq!(parse("1d500ms"), Ok(Duration::from_millis(86_400_500)));
+
+        assert!(parse("").is_err());
+        assert!(parse("1y").is_err());
+        assert!(parse("m").is_err());
+        assert!(parse("1h30").is_err());
+
+        assert_eq!(HumanDuration::from_secs(0).to_string(), "0s");
+        assert_eq!(HumanDuration::from_secs(5400).to_string(), "1h30m");
+        assert_eq!(
+            HumanDuration::new(Duration::from_millis(90_061_500)).to_string(),
+            "1d1h1m1s500ms"
+        );
+    }
+}
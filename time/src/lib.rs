@@ -3,7 +3,15 @@
 
 #[cfg(feature = "client")]
 pub mod client;
+pub mod duration;
 pub(crate) mod handler;
+#[cfg(any(
+    feature = "tcp-binder",
+    feature = "tcp-client",
+    feature = "unix-binder",
+    feature = "unix-client",
+))]
+pub(crate) mod line;
 pub mod request;
 pub mod response;
 #[cfg(feature = "server")]
@@ -11,3 +19,5 @@
 #[cfg(any(feature = "tcp-binder", feature = "tcp-client"))]
 pub mod tcp;
 pub mod timer;
+#[cfg(all(unix, any(feature = "unix-binder", feature = "unix-client")))]
+pub mod unix;
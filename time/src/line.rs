@@ -0,0 +1,89 @@
+//! # Line protocol
+//!
+//! This module contains the line-based protocol shared by stream
+//! binders and clients (TCP, Unix socket…). Each request and each
+//! response fits in a single line.
+
+use std::io::{Error, ErrorKind, Result};
+
+use crate::{request::Request, response::Response, timer::Timer};
+
+/// Parse the given line into a client request.
+pub(crate) fn parse_request(line: &str) -> Result<Request> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some("start") => Ok(Request::Start),
+        Some("get") => Ok(Request::Get),
+        Some("set") => match tokens.next().map(|duration| duration.parse::<usize>()) {
+            Some(Ok(duration)) => Ok(Request::Set(duration)),
+            Some(Err(err)) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid duration: {err}"),
+            )),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "missing duration".to_owned(),
+            )),
+        },
+        Some("pause") => Ok(Request::Pause),
+        Some("resume") => Ok(Request::Resume),
+        Some("stop") => Ok(Request::Stop),
+        Some(req) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid request: {req}"),
+        )),
+        None => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "missing request".to_owned(),
+        )),
+    }
+}
+
+/// Format the given client request into a line.
+pub(crate) fn format_request(req: Request) -> String {
+    match req {
+        Request::Start => "start\n".to_owned(),
+        Request::Get => "get\n".to_owned(),
+        Request::Set(duration) => format!("set {duration}\n"),
+        Request::Pause => "pause\n".to_owned(),
+        Request::Resume => "resume\n".to_owned(),
+        Request::Stop => "stop\n".to_owned(),
+    }
+}
+
+/// Parse the given line into a server response.
+pub(crate) fn parse_response(line: &str) -> Result<Response> {
+    let mut tokens = line.split_whitespace();
+    match tokens.next() {
+        Some("ok") => Ok(Response::Ok),
+        Some("timer") => match tokens.next().map(serde_json::from_str::<Timer>) {
+            Some(Ok(timer)) => Ok(Response::Timer(timer)),
+            Some(Err(err)) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid timer: {err}"),
+            )),
+            None => Err(Error::new(
+                ErrorKind::InvalidInput,
+                "missing timer".to_owned(),
+            )),
+        },
+        Some(res) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid response: {res}"),
+        )),
+        None => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "missing response".to_owned(),
+        )),
+    }
+}
+
+/// Format the given server response into a line.
+pub(crate) fn format_response(res: Response) -> String {
+    match res {
+        Response::Ok => "ok\n".to_string(),
+        Response::Timer(timer) => {
+            format!("timer {}\n", serde_json::to_string(&timer).unwrap())
+        }
+    }
+}
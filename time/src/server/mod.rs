@@ -5,10 +5,16 @@
 //! responses. It accepts connections using server binders. A server
 //! should have at least one binder, otherwise it stops by itself.
 //!
+//! Multiple binders can run simultaneously (for example TCP and Unix
+//! socket): they all control the same timer, and they all receive
+//! timer events.
+//!
 //!
 
 #[cfg(feature = "tcp-binder")]
 pub mod tcp;
+#[cfg(all(unix, feature = "unix-binder"))]
+pub mod unix;
 
 use std::{
     fmt::Debug,
@@ -56,7 +62,7 @@ pub struct ServerConfig {
     handler: Arc<Handler<ServerEvent>>,
 
     /// The binders list the server should use when starting up.
-    binders: Vec<Box<dyn ServerBind>>,
+    binders: Vec<Arc<dyn ServerBind>>,
 }
 
 impl Default for ServerConfig {
@@ -135,6 +141,15 @@ pub trait ServerBind: Debug + Send + Sync {
     /// Describe how the server should bind to accept connections from
     /// clients.
     async fn bind(&self, timer: ThreadSafeTimer) -> Result<()>;
+
+    /// Describe how the binder should react to timer events.
+    ///
+    /// Timer events are multicast to all the binders of the server,
+    /// which allows binders to forward them to their clients. Does
+    /// nothing by default.
+    async fn on_timer_event(&self, _event: TimerEvent) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// The server stream trait.
@@ -378,7 +393,17 @@ pub fn with_server_handler<F: Future<Output = Result<()>> + Send + 'static>(
 
     /// Push the given server binder.
     pub fn with_binder(mut self, binder: Box<dyn ServerBind>) -> Self {
-        self.server_config.binders.push(binder);
+        self.server_config.binders.push(Arc::from(binder));
+        self
+    }
+
+    /// Push the given server binders.
+    ///
+    /// All binders run simultaneously when the server starts.
+    pub fn with_binders(mut self, binders: impl IntoIterator<Item = Box<dyn ServerBind>>) -> Self {
+        for binder in binders {
+            self = self.with_binder(binder);
+        }
         self
     }
 
@@ -419,7 +444,34 @@ pub fn with_cycles_count(mut self, count: impl Into<TimerLoop>) -> Self {
     }
 
     /// Build the final server.
-    pub fn build(self) -> Result<Server> {
+    ///
+    /// The timer handler is wrapped so that timer events are also
+    /// multicast to all the server binders.
+    pub fn build(mut self) -> Result<Server> {
+        let binders = self.server_config.binders.clone();
+
+        if !binders.is_empty() {
+            let handler = self.timer_config.handler.clone();
+
+            self.timer_config.handler = Arc::new(move |event: TimerEvent| {
+                let handler = handler.clone();
+                let binders = binders.clone();
+
+                Box::pin(async move {
+                    let res = handler(event.clone()).await;
+
+                    for binder in binders {
+                        if let Err(err) = binder.on_timer_event(event.clone()).await {
+                            debug!("error while sending timer event to {binder:?}, skipping it");
+                            debug!("{err:?}");
+                        }
+                    }
+
+                    res
+                })
+            });
+        }
+
         Ok(Server {
             config: self.server_config,
             state: ThreadSafeState::new(),
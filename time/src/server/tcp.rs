@@ -14,6 +14,7 @@
 use tracing::debug;
 
 use crate::{
+    line,
     request::{Request, RequestReader},
     response::{Response, ResponseWriter},
     tcp::TcpHandler,
@@ -75,49 +76,15 @@ impl RequestReader for TcpHandler {
     async fn read(&mut self) -> io::Result<Request> {
         let mut req = String::new();
         self.reader.read_line(&mut req).await?;
-
-        let mut tokens = req.split_whitespace();
-        match tokens.next() {
-            Some("start") => Ok(Request::Start),
-            Some("get") => Ok(Request::Get),
-            Some("set") => match tokens.next().map(|duration| duration.parse::<usize>()) {
-                Some(Ok(duration)) => Ok(Request::Set(duration)),
-                Some(Err(err)) => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    format!("invalid duration: {err}"),
-                )),
-                None => Err(io::Error::new(
-                    io::ErrorKind::InvalidInput,
-                    "missing duration".to_owned(),
-                )),
-            },
-            Some("pause") => Ok(Request::Pause),
-            Some("resume") => Ok(Request::Resume),
-            Some("stop") => Ok(Request::Stop),
-            Some(req) => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!("invalid request: {req}"),
-            )),
-            None => Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                "missing request".to_owned(),
-            )),
-        }
+        line::parse_request(&req)
     }
 }
 
 #[async_trait]
 impl ResponseWriter for TcpHandler {
     async fn write(&mut self, res: Response) -> io::Result<()> {
-        let res = match res {
-            Response::Ok => "ok\n".to_string(),
-            Response::Timer(timer) => {
-                format!("timer {}\n", serde_json::to_string(&timer).unwrap())
-            }
-        };
-
+        let res = line::format_response(res);
         self.writer.write_all(res.as_bytes()).await?;
-
         Ok(())
     }
 }
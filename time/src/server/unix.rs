@@ -0,0 +1,97 @@
+//! # Unix socket binder
+//!
+//! This module contains the implementation of the Unix socket server
+//! binder, based on [`tokio::net::UnixStream`].
+
+use std::{io, path::PathBuf};
+
+#[cfg(feature = "async-std")]
+use async_std::os::unix::net::UnixListener;
+use async_trait::async_trait;
+use futures::{AsyncBufReadExt, AsyncWriteExt};
+#[cfg(feature = "tokio")]
+use tokio::net::UnixListener;
+use tracing::debug;
+
+use crate::{
+    line,
+    request::{Request, RequestReader},
+    response::{Response, ResponseWriter},
+    timer::ThreadSafeTimer,
+    unix::UnixHandler,
+};
+
+use super::{ServerBind, ServerStream};
+
+/// The Unix socket server binder.
+///
+/// This [`ServerBind`]er uses a Unix socket to bind a listener, to
+/// read requests and write responses. It is mostly useful for local
+/// control, next to a [`TcpBind`](super::tcp::TcpBind) for remote
+/// control.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnixBind {
+    /// The path of the Unix socket.
+    ///
+    /// An existing file at this path is removed before binding.
+    pub path: PathBuf,
+}
+
+impl UnixBind {
+    /// Create a new Unix socket binder using the given socket path.
+    pub fn new(path: impl Into<PathBuf>) -> Box<dyn ServerBind> {
+        Box::new(Self { path: path.into() })
+    }
+}
+
+#[async_trait]
+impl ServerBind for UnixBind {
+    async fn bind(&self, timer: ThreadSafeTimer) -> io::Result<()> {
+        // a previous server may have left its socket behind
+        match std::fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+            _ => (),
+        }
+
+        #[cfg(feature = "async-std")]
+        let listener = UnixListener::bind(&self.path).await?;
+        #[cfg(feature = "tokio")]
+        let listener = UnixListener::bind(&self.path)?;
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    debug!("Unix socket connection accepted");
+
+                    let mut handler = UnixHandler::new(stream);
+                    if let Err(err) = handler.handle(timer.clone()).await {
+                        debug!("cannot handle request");
+                        debug!("{err:?}");
+                    }
+                }
+                Err(err) => {
+                    debug!("cannot get stream from client");
+                    debug!("{err:?}");
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RequestReader for UnixHandler {
+    async fn read(&mut self) -> io::Result<Request> {
+        let mut req = String::new();
+        self.reader.read_line(&mut req).await?;
+        line::parse_request(&req)
+    }
+}
+
+#[async_trait]
+impl ResponseWriter for UnixHandler {
+    async fn write(&mut self, res: Response) -> io::Result<()> {
+        let res = line::format_response(res);
+        self.writer.write_all(res.as_bytes()).await?;
+        Ok(())
+    }
+}
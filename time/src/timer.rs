@@ -78,6 +78,14 @@ pub struct TimerCycle {
     /// the total duration of the cycle. *From the timer point of
     /// view*, the duration represents the amount of time remaining
     /// before the cycle ends.
+    ///
+    /// The duration is expressed in seconds, but can be deserialized
+    /// from a [`HumanDuration`](crate::duration::HumanDuration) like
+    /// `25m`.
+    #[cfg_attr(
+        feature = "derive",
+        serde(deserialize_with = "crate::duration::deserialize_secs")
+    )]
     pub duration: usize,
 }
 
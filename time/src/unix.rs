@@ -0,0 +1,95 @@
+//! # Unix socket
+//!
+//! This module contains shared Unix socket code for both server and
+//! client.
+
+use std::path::PathBuf;
+#[cfg(feature = "tokio")]
+use std::{
+    io::Result,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "async-std")]
+pub use async_std::os::unix::net::UnixStream;
+use futures::{
+    io::{BufReader, ReadHalf, WriteHalf},
+    AsyncReadExt,
+};
+#[cfg(feature = "tokio")]
+use futures::{ready, AsyncRead, AsyncWrite};
+
+/// The Unix socket shared configuration between clients and servers.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "derive",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub struct UnixConfig {
+    /// The path of the Unix socket.
+    pub path: PathBuf,
+}
+
+pub struct UnixHandler {
+    pub reader: BufReader<ReadHalf<UnixStream>>,
+    pub writer: WriteHalf<UnixStream>,
+}
+
+impl UnixHandler {
+    pub fn new(stream: impl Into<UnixStream>) -> Self {
+        let (reader, writer) = AsyncReadExt::split(stream.into());
+        let reader = BufReader::new(reader);
+        Self { reader, writer }
+    }
+}
+
+#[cfg(feature = "tokio")]
+pub struct UnixStream(tokio::net::UnixStream);
+
+#[cfg(feature = "tokio")]
+impl UnixStream {
+    pub async fn connect(path: impl AsRef<std::path::Path>) -> Result<tokio::net::UnixStream> {
+        tokio::net::UnixStream::connect(path).await
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl From<tokio::net::UnixStream> for UnixStream {
+    fn from(stream: tokio::net::UnixStream) -> Self {
+        Self(stream)
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncRead for UnixStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize>> {
+        let mut buf = tokio::io::ReadBuf::new(buf);
+        ready!(tokio::io::AsyncRead::poll_read(
+            Pin::new(&mut self.get_mut().0),
+            cx,
+            &mut buf
+        ))?;
+        Poll::Ready(Ok(buf.filled().len()))
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncWrite for UnixStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<Result<usize>> {
+        tokio::io::AsyncWrite::poll_write(Pin::new(&mut self.get_mut().0), cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        tokio::io::AsyncWrite::poll_flush(Pin::new(&mut self.get_mut().0), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        tokio::io::AsyncWrite::poll_shutdown(Pin::new(&mut self.get_mut().0), cx)
+    }
+}
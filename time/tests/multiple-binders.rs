@@ -0,0 +1,103 @@
+#![cfg(all(unix, feature = "tcp", feature = "unix"))]
+
+use std::{
+    io::Result,
+    net::TcpListener,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(feature = "async-std")]
+use async_std::{task::sleep, test};
+use async_trait::async_trait;
+use time::{
+    client::{tcp::TcpClient, unix::UnixClient},
+    server::{tcp::TcpBind, unix::UnixBind, ServerBind, ServerBuilder},
+    timer::{ThreadSafeTimer, Timer, TimerCycle, TimerEvent, TimerState},
+};
+#[cfg(feature = "tokio")]
+use tokio::{test, time::sleep};
+
+static HOST: &str = "127.0.0.1";
+
+/// Find a free TCP port by binding to port 0, then releasing it.
+fn free_port() -> u16 {
+    let listener = TcpListener::bind((HOST, 0)).unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// Binder that does not accept connections, it only records the
+/// timer events it receives.
+#[derive(Clone, Debug, Default)]
+struct EventsBind(Arc<Mutex<Vec<TimerEvent>>>);
+
+#[async_trait]
+impl ServerBind for EventsBind {
+    async fn bind(&self, _timer: ThreadSafeTimer) -> Result<()> {
+        loop {
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn on_timer_event(&self, event: TimerEvent) -> Result<()> {
+        self.0.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+#[test_log::test(test)]
+async fn multiple_binders() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("time-lib.sock");
+    let port = free_port();
+    let events = EventsBind::default();
+
+    let server = ServerBuilder::new()
+        .with_binders([
+            TcpBind::new(HOST, port),
+            UnixBind::new(&path),
+            Box::new(events.clone()),
+        ])
+        .with_cycle(("Work", 3))
+        .build()
+        .unwrap();
+
+    let unix_path = path.clone();
+    server
+        .bind_with(move || async move {
+            sleep(Duration::from_secs(1)).await;
+
+            let tcp_client = TcpClient::new_boxed(HOST, port);
+            let unix_client = UnixClient::new_boxed(unix_path);
+
+            tcp_client.start().await.unwrap();
+            unix_client.pause().await.unwrap();
+
+            assert_eq!(
+                tcp_client.get().await.unwrap(),
+                unix_client.get().await.unwrap(),
+            );
+
+            assert_eq!(
+                unix_client.get().await.unwrap(),
+                Timer {
+                    state: TimerState::Paused,
+                    cycle: TimerCycle::new("Work", 3),
+                    ..Timer::default()
+                }
+            );
+
+            Ok(())
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(
+        *events.0.lock().unwrap(),
+        vec![
+            TimerEvent::Started,
+            TimerEvent::Began(TimerCycle::new("Work", 3)),
+            TimerEvent::Paused(TimerCycle::new("Work", 3)),
+        ]
+    );
+}